@@ -0,0 +1,81 @@
+//! Exposes the resolved embeddenator version as `EMBEDDENATOR_VERSION`, and its git
+//! revision as `EMBEDDENATOR_REV` when it is a git dependency.
+//!
+//! embeddenator is a path dependency without a version constant of its own, so the
+//! version is read from the lockfile (`unknown` if it cannot be found). A git dependency
+//! is locked as `source = "git+<url>#<commit>"`; a path dependency has no source, and
+//! then `EMBEDDENATOR_REV` is not set.
+//!
+//! Also sets `BUILD_GIT_DIRTY` to `1` or `0` by whether `git status --porcelain` lists
+//! changes in the source tree, when it is a repository and git runs; the bench reads it
+//! instead of running git itself.
+
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+fn main() {
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+    let lock = Path::new(&manifest_dir).join("Cargo.lock");
+    println!("cargo:rerun-if-changed={}", lock.display());
+
+    let lock = fs::read_to_string(&lock).unwrap_or_default();
+    let version =
+        lock_field(&lock, "embeddenator", "version").unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=EMBEDDENATOR_VERSION={version}");
+    let rev = lock_field(&lock, "embeddenator", "source")
+        .filter(|source| source.starts_with("git+"))
+        .and_then(|source| {
+            source
+                .rsplit_once('#')
+                .map(|(_, commit)| commit.to_string())
+        });
+    if let Some(rev) = rev {
+        println!("cargo:rustc-env=EMBEDDENATOR_REV={rev}");
+    }
+
+    git_dirty(Path::new(&manifest_dir));
+}
+
+/// Commits, checkouts and staging touch `HEAD` or `index`; editing a source file does
+/// neither, so `src` is watched too.
+fn git_dirty(source: &Path) {
+    let Some(git_dir) = source
+        .ancestors()
+        .map(|d| d.join(".git"))
+        .find(|g| g.exists())
+    else {
+        return;
+    };
+    if git_dir.is_dir() {
+        for file in ["HEAD", "index"] {
+            println!("cargo:rerun-if-changed={}", git_dir.join(file).display());
+        }
+    }
+    println!("cargo:rerun-if-changed={}", source.join("src").display());
+
+    let status = Command::new("git")
+        .arg("-C")
+        .arg(source)
+        .args(["status", "--porcelain"])
+        .stderr(Stdio::null())
+        .output()
+        .ok();
+    if let Some(out) = status.filter(|out| out.status.success()) {
+        let dirty = u8::from(!out.stdout.is_empty());
+        println!("cargo:rustc-env=BUILD_GIT_DIRTY={dirty}");
+    }
+}
+
+/// `field` of `package` in a Cargo.lock: its `[[package]]` table has `name` first, then
+/// `version`, `source`, ... one per line.
+fn lock_field(lock: &str, package: &str, field: &str) -> Option<String> {
+    let name_line = format!("name = \"{package}\"");
+    let prefix = format!("{field} = \"");
+    lock.lines()
+        .skip_while(|line| line.trim() != name_line)
+        .skip(1)
+        .take_while(|line| !line.trim().is_empty() && !line.starts_with('['))
+        .find_map(|line| line.trim().strip_prefix(prefix.as_str())?.strip_suffix('"'))
+        .map(str::to_string)
+}
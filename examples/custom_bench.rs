@@ -0,0 +1,59 @@
+//! Adding a bench of your own to the suite without patching this crate.
+//!
+//! ```text
+//! cargo run --release --example custom_bench
+//! ```
+//!
+//! Registers a `checksum` section after the built-in ones, runs the suite with only
+//! `vsa` and `checksum` selected and prints the report.
+
+use embeddenator_contract_bench::harness::{measure_fn, Cost};
+use embeddenator_contract_bench::registry::{Bench, MeasurementSink, Registry};
+use embeddenator_contract_bench::schema::{tags, Measurement};
+use embeddenator_contract_bench::{run_suite_with, BenchConfig, SuiteSpec, VsaVariant};
+use serde_json::json;
+use std::io;
+
+/// Sums a buffer: stands in for an op only your fork has.
+struct Checksum {
+    len: usize,
+}
+
+impl Bench for Checksum {
+    fn name(&self) -> &str {
+        "checksum"
+    }
+
+    fn run(&self, cfg: &BenchConfig, sink: &mut dyn MeasurementSink) -> io::Result<()> {
+        let data: Vec<u64> = (0..self.len as u64).collect();
+        let (iters, warmup) = cfg.counts(Cost::Micro);
+        let m = measure_fn(iters, warmup, || data.iter().sum::<u64>());
+        sink.on_measurement(Measurement {
+            name: "checksum.sum".to_string(),
+            unit: "ns/iter".to_string(),
+            iters: m.iters,
+            warmup_iters: m.warmup_iters,
+            total_ns: m.total_ns,
+            ns_per_iter: m.ns_per_iter,
+            bytes_processed: None,
+            throughput_bytes_per_s: None,
+            extra: json!({ "len": self.len }),
+            tags: tags(&[("impl", "iter_sum")]),
+        })
+    }
+}
+
+fn main() -> io::Result<()> {
+    let spec = SuiteSpec {
+        variant: VsaVariant::Packed,
+        sections: vec!["vsa".to_string(), "checksum".to_string()],
+        ..Default::default()
+    };
+    let mut registry = Registry::suite(&spec)?;
+    registry.register(Checksum { len: 4096 });
+
+    let report = run_suite_with(&spec, &registry)?;
+    let json = serde_json::to_string_pretty(&report).map_err(io::Error::other)?;
+    println!("{json}");
+    Ok(())
+}
@@ -0,0 +1,326 @@
+//! `--assert`: absolute contracts checked against a run's measurements.
+//!
+//! Relative gates (`compare`, `trend`) catch regressions. These catch a number crossing
+//! a fixed line, e.g. "p99 query latency under 5 ms" or "recall@10 above 0.95":
+//!
+//! ```text
+//! [MEASUREMENT:]FIELD OP VALUE
+//! retrieval.query_pass_with_index:p99_ms<5
+//! recall_at_k>=0.95
+//! ```
+//!
+//! `OP` is one of `<`, `<=`, `>`, `>=`. With a measurement name, every measurement of that
+//! name must have the field and satisfy it. Without one, every measurement that has the
+//! field must, and at least one must have it.
+//!
+//! `FIELD` is a top-level measurement field (`ns_per_iter`, `iters`, `total_ns`,
+//! `bytes_processed`, `throughput_bytes_per_s`), a latency shorthand (`p50_ms`, `p95_ms`,
+//! `p99_ms`, `mean_ms` for `latency_ms.<p>`), or otherwise a dotted path into `extra`
+//! (`recall_at_k`, `recall_estimate.ci_low`).
+
+use crate::schema::Measurement;
+use serde_json::Value;
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Op {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Op {
+    fn as_str(self) -> &'static str {
+        match self {
+            Op::Lt => "<",
+            Op::Le => "<=",
+            Op::Gt => ">",
+            Op::Ge => ">=",
+        }
+    }
+
+    fn holds(self, value: f64, bound: f64) -> bool {
+        match self {
+            Op::Lt => value < bound,
+            Op::Le => value <= bound,
+            Op::Gt => value > bound,
+            Op::Ge => value >= bound,
+        }
+    }
+}
+
+/// One parsed `--assert`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Assertion {
+    pub measurement: Option<String>,
+    pub field: String,
+    pub op: Op,
+    pub bound: f64,
+}
+
+impl fmt::Display for Assertion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(name) = &self.measurement {
+            write!(f, "{name}:")?;
+        }
+        write!(f, "{}{}{}", self.field, self.op.as_str(), self.bound)
+    }
+}
+
+impl Assertion {
+    /// Parse `[MEASUREMENT:]FIELD OP VALUE` (whitespace around parts is ignored).
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let err = |msg: String| format!("invalid assertion `{s}`: {msg}");
+        let Some(at) = s.find(['<', '>']) else {
+            return Err(err(
+                "expected a comparison (<, <=, > or >=), e.g. `p99_ms<5`".to_string(),
+            ));
+        };
+        let (lhs, rest) = s.split_at(at);
+        let (op, rhs) = match rest.as_bytes() {
+            [b'<', b'=', ..] => (Op::Le, &rest[2..]),
+            [b'>', b'=', ..] => (Op::Ge, &rest[2..]),
+            [b'<', ..] => (Op::Lt, &rest[1..]),
+            _ => (Op::Gt, &rest[1..]),
+        };
+        let rhs = rhs.trim();
+        if rhs.is_empty() {
+            return Err(err(format!("missing a value after `{}`", op.as_str())));
+        }
+        let bound: f64 = rhs
+            .parse()
+            .ok()
+            .filter(|b: &f64| b.is_finite())
+            .ok_or_else(|| {
+                err(format!(
+                    "`{rhs}` is not a number (units are part of the field, e.g. p99_ms)"
+                ))
+            })?;
+
+        let (measurement, field) = match lhs.split_once(':') {
+            Some((name, field)) => {
+                let name = name.trim();
+                if name.is_empty() {
+                    return Err(err("empty measurement name before `:`".to_string()));
+                }
+                (Some(name.to_string()), field.trim())
+            }
+            None => (None, lhs.trim()),
+        };
+        if field.is_empty() {
+            return Err(err(format!("missing a field before `{}`", op.as_str())));
+        }
+        if field.split('.').any(str::is_empty) || field.contains(char::is_whitespace) {
+            return Err(err(format!("`{field}` is not a field name or dotted path")));
+        }
+        Ok(Self {
+            measurement,
+            field: field.to_string(),
+            op,
+            bound,
+        })
+    }
+}
+
+/// The value of `field` in `m`, if it has one (see the module docs for resolution).
+pub fn field_value(m: &Measurement, field: &str) -> Option<f64> {
+    match field {
+        "ns_per_iter" => return Some(m.ns_per_iter),
+        "iters" => return Some(m.iters as f64),
+        "total_ns" => return Some(m.total_ns as f64),
+        "bytes_processed" => return m.bytes_processed.map(|b| b as f64),
+        "throughput_bytes_per_s" => return m.throughput_bytes_per_s,
+        _ => {}
+    }
+    let path = match field.strip_suffix("_ms") {
+        Some(p @ ("p50" | "p95" | "p99" | "mean")) => format!("latency_ms.{p}"),
+        _ => field.to_string(),
+    };
+    path.split('.')
+        .try_fold(&m.extra, |v, key| v.get(key))
+        .and_then(Value::as_f64)
+}
+
+/// One assertion's result.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Outcome {
+    pub assertion: Assertion,
+    pub pass: bool,
+    /// What was checked, or why nothing could be.
+    pub detail: String,
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let verdict = if self.pass { "PASS" } else { "FAIL" };
+        write!(f, "{verdict} {}: {}", self.assertion, self.detail)
+    }
+}
+
+/// Check `assertion` against every measurement it applies to.
+pub fn evaluate(assertion: &Assertion, ms: &[Measurement]) -> Outcome {
+    let outcome = |pass: bool, detail: String| Outcome {
+        assertion: assertion.clone(),
+        pass,
+        detail,
+    };
+    let named: Vec<&Measurement> = match &assertion.measurement {
+        Some(name) => ms.iter().filter(|m| &m.name == name).collect(),
+        None => ms.iter().collect(),
+    };
+    if named.is_empty() {
+        return outcome(false, "no such measurement in this run".to_string());
+    }
+
+    let mut checked = Vec::new();
+    let mut violations = Vec::new();
+    for m in named {
+        match field_value(m, &assertion.field) {
+            Some(v) => {
+                let what = format!("{} = {v}", m.name);
+                if !assertion.op.holds(v, assertion.bound) {
+                    violations.push(what.clone());
+                }
+                checked.push(what);
+            }
+            None if assertion.measurement.is_some() => {
+                violations.push(format!("{} has no field {}", m.name, assertion.field));
+            }
+            None => {}
+        }
+    }
+    if checked.is_empty() && violations.is_empty() {
+        return outcome(
+            false,
+            format!("no measurement has field {}", assertion.field),
+        );
+    }
+    if violations.is_empty() {
+        outcome(true, checked.join(", "))
+    } else {
+        outcome(false, violations.join(", "))
+    }
+}
+
+/// [`evaluate`] every assertion, in order.
+pub fn evaluate_all(assertions: &[Assertion], ms: &[Measurement]) -> Vec<Outcome> {
+    assertions.iter().map(|a| evaluate(a, ms)).collect()
+}
+
+/// `--strict-contract`: names of the measurements whose own check did not pass
+/// (`extra.pass` is `false`), in order.
+pub fn failed_contracts(ms: &[Measurement]) -> Vec<&str> {
+    ms.iter()
+        .filter(|m| m.extra.get("pass").and_then(Value::as_bool) == Some(false))
+        .map(|m| m.name.as_str())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::collections::BTreeMap;
+
+    fn m(name: &str, extra: Value) -> Measurement {
+        Measurement {
+            name: name.to_string(),
+            unit: "ns/iter".to_string(),
+            iters: 100,
+            warmup_iters: 0,
+            total_ns: 1_000,
+            ns_per_iter: 10.0,
+            bytes_processed: None,
+            throughput_bytes_per_s: None,
+            extra,
+            tags: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        let a = Assertion::parse("retrieval.query_pass_with_index:p99_ms<5").unwrap();
+        assert_eq!(
+            a,
+            Assertion {
+                measurement: Some("retrieval.query_pass_with_index".to_string()),
+                field: "p99_ms".to_string(),
+                op: Op::Lt,
+                bound: 5.0,
+            }
+        );
+        assert_eq!(a.to_string(), "retrieval.query_pass_with_index:p99_ms<5");
+
+        let b = Assertion::parse(" recall_at_k >= 0.95 ").unwrap();
+        assert_eq!((b.measurement, b.op, b.bound), (None, Op::Ge, 0.95));
+        assert_eq!(Assertion::parse("x.y>1e3").unwrap().field, "x.y");
+        assert_eq!(Assertion::parse("n<=2").unwrap().op, Op::Le);
+
+        for (bad, why) in [
+            ("p99_ms=5", "expected a comparison"),
+            ("p99_ms<", "missing a value"),
+            ("p99_ms<5ms", "not a number"),
+            ("p99_ms<inf", "not a number"),
+            ("<5", "missing a field"),
+            (":p99_ms<5", "empty measurement name"),
+            ("latency..p99<5", "not a field name"),
+        ] {
+            let err = Assertion::parse(bad).unwrap_err();
+            assert!(err.contains(why) && err.contains(bad), "{bad}: {err}");
+        }
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let ms = [
+            m(
+                "retrieval.query_pass_with_index",
+                json!({"latency_ms": {"p99": 3.5}, "recall_at_k": 0.97}),
+            ),
+            m("retrieval.frontier.cf5", json!({"recall_at_k": 0.90})),
+            m("vsa.packed.bind", json!({})),
+        ];
+        let check = |s: &str| evaluate(&Assertion::parse(s).unwrap(), &ms);
+
+        assert!(check("retrieval.query_pass_with_index:p99_ms<5").pass);
+        assert!(!check("retrieval.query_pass_with_index:p99_ms<3").pass);
+        assert!(check("retrieval.query_pass_with_index:latency_ms.p99<=3.5").pass);
+        assert!(check("vsa.packed.bind:ns_per_iter<11").pass);
+
+        // Unnamed: every measurement with the field, so the frontier point fails it.
+        let recall = check("recall_at_k>0.95");
+        assert!(!recall.pass);
+        assert!(
+            recall.detail.contains("retrieval.frontier.cf5 = 0.9"),
+            "{recall}"
+        );
+        assert!(check("recall_at_k>0.85").pass);
+
+        let missing = check("vsa.packed.bind:p99_ms<5");
+        assert!(!missing.pass && missing.detail.contains("has no field p99_ms"));
+        assert!(!check("nope:ns_per_iter<1").pass);
+        assert!(!check("qps>1").pass);
+        assert!(check("recall_at_k>0.5")
+            .to_string()
+            .starts_with("PASS recall_at_k>0.5: "));
+    }
+
+    #[test]
+    fn test_failed_contracts() {
+        // A roundtrip mismatch is what fails --strict-contract; checks that passed, and
+        // measurements with no check, do not.
+        let roundtrip = json!({
+            "pass": false,
+            "substrates": {"packed": {"mismatches": 1, "first_mismatch": "record 3"}},
+        });
+        let ms = [
+            m("vsa.packed.bind", json!({})),
+            m("vsa.contract.bundle_capacity", json!({"pass": true})),
+            m("vsa.contract.roundtrip", roundtrip),
+            m("vsa.contract.bundle_semantics", json!({"pass": "false"})),
+        ];
+        assert_eq!(failed_contracts(&ms), ["vsa.contract.roundtrip"]);
+        assert!(failed_contracts(&ms[..2]).is_empty());
+    }
+}
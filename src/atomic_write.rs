@@ -0,0 +1,197 @@
+//! Crash-safe file replacement.
+//!
+//! Output is written to a temporary file next to the destination, synced, and renamed
+//! over it, then the directory is synced. A crash, a full disk or a failing writer
+//! leaves either the previous file or the complete new one in place, never a truncated
+//! mix; the temporary file is removed on every error path.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use tempfile::NamedTempFile;
+
+/// Atomically replace `path` with `contents`.
+pub fn write_atomic<P: AsRef<Path>>(path: P, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    write_atomic_with(path, |w| w.write_all(contents.as_ref()))
+}
+
+/// Atomically replace `path` with whatever `write` produces.
+///
+/// Nothing is renamed into place if `write` fails. A failure of the final rename is
+/// reported as such (with both paths), as it is the one error that is not about the
+/// data itself.
+pub fn write_atomic_with<P, F>(path: P, write: F) -> io::Result<()>
+where
+    P: AsRef<Path>,
+    F: FnOnce(&mut BufWriter<NamedTempFile>) -> io::Result<()>,
+{
+    let path = path.as_ref();
+    let dir = match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy())
+        .unwrap_or_default();
+    let tmp = tempfile::Builder::new()
+        .prefix(&format!(".{name}."))
+        .suffix(".tmp")
+        .tempfile_in(dir)?;
+    set_default_permissions(tmp.as_file(), path)?;
+
+    let mut writer = BufWriter::with_capacity(64 * 1024, tmp);
+    write(&mut writer)?;
+    let tmp = writer.into_inner().map_err(|e| e.into_error())?;
+    tmp.as_file().sync_all()?;
+
+    let tmp_path = tmp.path().to_path_buf();
+    tmp.persist(path).map_err(|e| {
+        io::Error::new(
+            e.error.kind(),
+            format!(
+                "rename {} -> {} failed: {}",
+                tmp_path.display(),
+                path.display(),
+                e.error
+            ),
+        )
+    })?;
+    sync_dir(dir)
+}
+
+/// Whether [`write_atomic`] could write `path`: its directory exists and takes a new
+/// file, and `path` is not a directory. Probes with a temporary file, which is removed.
+pub fn check_writable(path: &Path) -> io::Result<()> {
+    if path.is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is a directory", path.display()),
+        ));
+    }
+    let dir = match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    if !dir.is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("directory {} does not exist", dir.display()),
+        ));
+    }
+    tempfile::Builder::new()
+        .prefix(".probe.")
+        .suffix(".tmp")
+        .tempfile_in(dir)
+        .map(drop)
+        .map_err(|e| io::Error::new(e.kind(), format!("cannot write to {}: {e}", dir.display())))
+}
+
+/// Temporary files are created owner-only; give the result the permissions of the
+/// file it replaces, or the usual 0644 for a new one.
+#[cfg(unix)]
+fn set_default_permissions(file: &File, dest: &Path) -> io::Result<()> {
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    let perms = fs::metadata(dest)
+        .map(|m| m.permissions())
+        .unwrap_or_else(|_| fs::Permissions::from_mode(0o644));
+    file.set_permissions(perms)
+}
+
+#[cfg(not(unix))]
+fn set_default_permissions(_file: &File, _dest: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+/// Persist the rename itself; directories can only be opened for syncing on unix.
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> io::Result<()> {
+    File::open(dir)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn leftovers(dir: &Path) -> Vec<String> {
+        fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .filter(|n| n.ends_with(".tmp"))
+            .collect()
+    }
+
+    #[test]
+    fn test_replaces_and_cleans_up_on_writer_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("report.json");
+        write_atomic(&path, "old").unwrap();
+        write_atomic(&path, "new").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+
+        // A writer failing half-way (e.g. disk full) leaves the previous file intact.
+        let err = write_atomic_with(&path, |w| {
+            w.write_all(b"partial")?;
+            Err(io::Error::other("disk full"))
+        })
+        .unwrap_err();
+        assert_eq!(err.to_string(), "disk full");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        assert!(leftovers(dir.path()).is_empty());
+    }
+
+    #[test]
+    fn test_rename_failure_is_distinct() {
+        let dir = tempfile::tempdir().unwrap();
+        // Renaming a file over a non-empty directory fails even for root.
+        let path = dir.path().join("out");
+        fs::create_dir(&path).unwrap();
+        fs::write(path.join("keep"), "x").unwrap();
+
+        let err = write_atomic(&path, "data").unwrap_err();
+        assert!(err.to_string().starts_with("rename "), "{err}");
+        assert_eq!(fs::read_to_string(path.join("keep")).unwrap(), "x");
+        assert!(leftovers(dir.path()).is_empty());
+    }
+
+    #[test]
+    fn test_check_writable() {
+        let dir = tempfile::tempdir().unwrap();
+        check_writable(&dir.path().join("report.json")).unwrap();
+        assert!(leftovers(dir.path()).is_empty());
+
+        let err = check_writable(&dir.path().join("missing/report.json")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        assert!(err.to_string().contains("does not exist"), "{err}");
+        assert!(check_writable(dir.path()).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_read_only_dir_leaves_original() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("report.json");
+        fs::write(&path, "original").unwrap();
+        fs::set_permissions(dir.path(), fs::Permissions::from_mode(0o555)).unwrap();
+
+        // Root ignores directory permissions; nothing to check there.
+        if File::create(dir.path().join("probe")).is_err() {
+            assert!(write_atomic(&path, "new").is_err());
+            assert_eq!(
+                check_writable(&path).unwrap_err().kind(),
+                io::ErrorKind::PermissionDenied
+            );
+            assert_eq!(fs::read_to_string(&path).unwrap(), "original");
+        }
+        fs::set_permissions(dir.path(), fs::Permissions::from_mode(0o755)).unwrap();
+    }
+}
@@ -0,0 +1,174 @@
+//! Bundle associativity / order-sensitivity contract check.
+//!
+//! Consumers assume that bundling many vectors at once is roughly the same as folding
+//! pairwise bundles, but majority-style bundling is neither associative nor order
+//! independent. This check makes the actual contract explicit: for seeded inputs of a
+//! few sizes it runs every bundling entry point, records the pairwise cosine matrix
+//! between their results, and fails when any pair drops below a threshold.
+
+use crate::dataset::generate_indexed;
+use crate::harness::BenchConfig;
+use crate::measurements;
+use crate::schema::{tags, Measurement};
+use embeddenator::{BitslicedTritVec, BlockSparseTritVec, CarrySaveBundle, SparseVec, DIM};
+use serde_json::json;
+use std::time::Instant;
+
+/// Input sizes checked.
+pub const SIZES: [usize; 3] = [3, 8, 32];
+
+/// Bundling methods compared, in matrix order.
+pub const METHODS: [&str; 6] = [
+    "sparsevec.bundle_sum_many",
+    "sparsevec.left_fold",
+    "sparsevec.right_fold",
+    "sparsevec.bundle_hybrid_many",
+    "hybrid.carry_save_finalize",
+    "blocksparse.bundle_many",
+];
+
+/// Result of the bundle semantics check.
+#[derive(Clone, Debug)]
+pub struct BundleSemantics {
+    pub measurement: Measurement,
+    pub pass: bool,
+    /// Lowest pairwise cosine across all sizes.
+    pub min_cosine: f64,
+}
+
+fn bundle_all(inputs: &[SparseVec]) -> Vec<SparseVec> {
+    let left = inputs[1..]
+        .iter()
+        .fold(inputs[0].clone(), |acc, v| acc.bundle(v));
+    let last = inputs.len() - 1;
+    let right = inputs[..last]
+        .iter()
+        .rev()
+        .fold(inputs[last].clone(), |acc, v| v.bundle(&acc));
+
+    let mut acc = CarrySaveBundle::new(DIM);
+    for v in inputs {
+        acc.accumulate(&BitslicedTritVec::from_sparse(v, DIM));
+    }
+    let carry_save = acc.finalize().to_sparse();
+
+    let blocks: Vec<BlockSparseTritVec> = inputs
+        .iter()
+        .map(|v| BlockSparseTritVec::from_sparse(v, DIM))
+        .collect();
+    let block_sparse = BlockSparseTritVec::bundle_many(&blocks).to_sparse();
+
+    vec![
+        SparseVec::bundle_sum_many(inputs),
+        left,
+        right,
+        SparseVec::bundle_hybrid_many(inputs),
+        carry_save,
+        block_sparse,
+    ]
+}
+
+/// Run the check; `pass` is false if any pairwise cosine is below `threshold`.
+pub fn check(cfg: &BenchConfig, threshold: f64) -> BundleSemantics {
+    let sparsity = DIM / 100;
+    let start = Instant::now();
+
+    let mut min_cosine = f64::INFINITY;
+    let mut per_size = Vec::new();
+    for (size_idx, &n) in SIZES.iter().enumerate() {
+        // Disjoint index ranges per size so each size sees fresh inputs.
+        let base = size_idx * 1_000;
+        let inputs: Vec<SparseVec> = (0..n)
+            .map(|i| generate_indexed(cfg.seed, base + i, DIM, sparsity))
+            .collect();
+        let results = bundle_all(&inputs);
+
+        let mut matrix = vec![vec![0.0f64; results.len()]; results.len()];
+        let mut size_min = (f64::INFINITY, 0, 0);
+        for i in 0..results.len() {
+            matrix[i][i] = results[i].cosine(&results[i]);
+            for j in (i + 1)..results.len() {
+                let c = results[i].cosine(&results[j]);
+                matrix[i][j] = c;
+                matrix[j][i] = c;
+                if c < size_min.0 {
+                    size_min = (c, i, j);
+                }
+            }
+        }
+        min_cosine = min_cosine.min(size_min.0);
+
+        per_size.push(json!({
+            "n": n,
+            "matrix": matrix,
+            "min_cosine": size_min.0,
+            "min_pair": [METHODS[size_min.1], METHODS[size_min.2]],
+            "pass": size_min.0 >= threshold,
+        }));
+    }
+
+    let total_ns = start.elapsed().as_nanos();
+    let pass = min_cosine >= threshold;
+
+    let measurement = Measurement {
+        name: measurements::vsa::CONTRACT_BUNDLE_SEMANTICS.to_string(),
+        unit: "ns/iter".to_string(),
+        iters: 1,
+        warmup_iters: 0,
+        total_ns,
+        ns_per_iter: total_ns as f64,
+        bytes_processed: None,
+        throughput_bytes_per_s: None,
+        extra: json!({
+            "dim": DIM,
+            "sparsity": sparsity,
+            "seed": cfg.seed,
+            "threshold": threshold,
+            "methods": METHODS,
+            "sizes": per_size,
+            "min_cosine": min_cosine,
+            "pass": pass,
+        }),
+        tags: tags(&[("substrate", "contract")]),
+    };
+
+    BundleSemantics {
+        measurement,
+        pass,
+        min_cosine,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::harness::Profile;
+
+    #[test]
+    fn test_matrix_shape_and_threshold() {
+        let cfg = BenchConfig {
+            profile: Profile::Quick,
+            seed: 7,
+        };
+        let lenient = check(&cfg, -1.0);
+        assert!(lenient.pass);
+
+        let sizes = lenient.measurement.extra["sizes"].as_array().unwrap();
+        assert_eq!(sizes.len(), SIZES.len());
+        for s in sizes {
+            let matrix = s["matrix"].as_array().unwrap();
+            assert_eq!(matrix.len(), METHODS.len());
+            for (i, row) in matrix.iter().enumerate() {
+                // Each method's bundle against itself, computed like the other cells.
+                let self_cosine = row[i].as_f64().unwrap();
+                assert!((self_cosine - 1.0).abs() < 1e-9, "{self_cosine}");
+                for (j, other) in matrix.iter().enumerate() {
+                    assert_eq!(row[j], other[i]);
+                }
+            }
+        }
+
+        // Nothing beats a perfect match, so a threshold above 1 must fail.
+        assert!(!check(&cfg, 1.01).pass);
+    }
+}
@@ -0,0 +1,161 @@
+//! `--chunk-size`: ingest granularity for the encode and retrieval benches.
+//!
+//! EmbrFS cuts every ingested file into [`LIBRARY_CHUNK_SIZE`]-byte chunks and exposes no
+//! setting for it. To measure the contract at a smaller deployed chunk size, the benches
+//! split each larger input file into `chunk_size`-byte pieces before ingest, each ingested
+//! as its own logical file (`<rel>.part<N>`), so each piece is at most one library chunk.
+//! Above the library's size, the library still cuts each piece at its own size, so the
+//! effective chunk size is the smaller of the two. The split happens before the timed
+//! ingest, into a temporary directory.
+//!
+//! Every ingest-side measurement records the values in effect under `chunking`, including
+//! the `ReversibleVSAConfig` the chunks are encoded with, whether or not a size was given.
+
+use crate::benches::input_walk::{InputFile, InputWalk};
+use crate::disk_space::{self, ScratchDir};
+use crate::error::BenchError;
+use embeddenator::ReversibleVSAConfig;
+use serde_json::json;
+use std::fs::{self, File};
+use std::io::{self, Read};
+
+/// Bytes per chunk in `EmbrFS::ingest_file`.
+pub const LIBRARY_CHUNK_SIZE: usize = 4096;
+
+/// Input walks with every file over the chunk size replaced by its pieces.
+pub struct Split {
+    /// Holds the pieces for as long as the walks are used.
+    _dir: Option<ScratchDir>,
+    pub walks: Vec<InputWalk>,
+}
+
+/// Split the files of `walks` into `chunk_size`-byte pieces (files no larger stay as
+/// they are). `None` leaves the walks unchanged.
+pub fn split(walks: &[InputWalk], chunk_size: Option<usize>) -> io::Result<Split> {
+    let Some(chunk_size) = chunk_size else {
+        return Ok(Split {
+            _dir: None,
+            walks: walks.to_vec(),
+        });
+    };
+    if chunk_size == 0 {
+        return Err(BenchError::invalid_args("--chunk-size must be at least 1").into());
+    }
+
+    let dir = disk_space::scratch_dir()?;
+    let mut buf = vec![0u8; chunk_size];
+    let mut out = Vec::with_capacity(walks.len());
+    for (w, walk) in walks.iter().enumerate() {
+        let mut files = Vec::new();
+        for (i, f) in walk.files.iter().enumerate() {
+            if f.len <= chunk_size as u64 {
+                files.push(f.clone());
+                continue;
+            }
+            let mut src = File::open(&f.path)?;
+            let mut part = 0;
+            loop {
+                let n = read_full(&mut src, &mut buf)?;
+                if n == 0 {
+                    break;
+                }
+                let path = dir.path().join(format!("{w}_{i}_{part}"));
+                fs::write(&path, &buf[..n])?;
+                files.push(InputFile {
+                    path,
+                    rel: format!("{}.part{part}", f.rel),
+                    len: n as u64,
+                });
+                part += 1;
+            }
+        }
+        out.push(InputWalk {
+            files,
+            ..walk.clone()
+        });
+    }
+    Ok(Split {
+        _dir: Some(dir),
+        walks: out,
+    })
+}
+
+/// Fill `buf` as far as the reader allows; short only at end of input.
+fn read_full(r: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut n = 0;
+    while n < buf.len() {
+        match r.read(&mut buf[n..])? {
+            0 => break,
+            k => n += k,
+        }
+    }
+    Ok(n)
+}
+
+/// The `chunking` extra: requested, library and effective chunk sizes, and the encode
+/// config.
+pub fn extra(chunk_size: Option<usize>, config: &ReversibleVSAConfig) -> serde_json::Value {
+    json!({
+        "chunk_size": chunk_size.map_or(LIBRARY_CHUNK_SIZE, |c| c.min(LIBRARY_CHUNK_SIZE)),
+        "requested_chunk_size": chunk_size,
+        "library_chunk_size": LIBRARY_CHUNK_SIZE,
+        "vsa_config": {
+            "block_size": config.block_size,
+            "max_path_depth": config.max_path_depth,
+            "base_shift": config.base_shift,
+            "target_sparsity": config.target_sparsity,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benches::input_walk::{walk, WalkOptions};
+
+    #[test]
+    fn test_split_pieces() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("big.bin"),
+            (0..2500u32).map(|i| i as u8).collect::<Vec<_>>(),
+        )
+        .unwrap();
+        fs::write(dir.path().join("small.bin"), [1u8; 100]).unwrap();
+        let walks = vec![walk(dir.path(), &WalkOptions::default()).unwrap()];
+
+        let same = split(&walks, None).unwrap();
+        assert_eq!(same.walks[0].files, walks[0].files);
+
+        let s = split(&walks, Some(1000)).unwrap();
+        let files = &s.walks[0].files;
+        let rels: Vec<(&str, u64)> = files.iter().map(|f| (f.rel.as_str(), f.len)).collect();
+        assert_eq!(
+            rels,
+            [
+                ("big.bin.part0", 1000),
+                ("big.bin.part1", 1000),
+                ("big.bin.part2", 500),
+                ("small.bin", 100)
+            ]
+        );
+        let rejoined: Vec<u8> = files[..3]
+            .iter()
+            .flat_map(|f| fs::read(&f.path).unwrap())
+            .collect();
+        assert_eq!(rejoined, fs::read(dir.path().join("big.bin")).unwrap());
+        assert_eq!(s.walks[0].total_bytes, walks[0].total_bytes);
+
+        assert!(split(&walks, Some(0)).is_err());
+        let config = ReversibleVSAConfig::default();
+        assert_eq!(extra(Some(1000), &config)["chunk_size"], 1000);
+        assert_eq!(
+            extra(Some(1 << 20), &config)["chunk_size"],
+            LIBRARY_CHUNK_SIZE
+        );
+        assert_eq!(
+            extra(None, &config)["requested_chunk_size"],
+            serde_json::Value::Null
+        );
+    }
+}
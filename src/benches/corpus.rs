@@ -0,0 +1,235 @@
+//! Synthetic corpora for the encode bench (`encode --synthetic-files`).
+//!
+//! How small an engram gets depends on how much of the corpus repeats itself, and a
+//! directory taken off disk has whatever redundancy it happens to have. A generated
+//! corpus sets it instead: `files` files of `file_bytes` seeded random bytes each, of
+//! which a `redundancy` fraction are copies of an earlier file. Each byte of a copy is
+//! replaced by a random one with probability `mutation_rate`, so 0 gives exact copies
+//! and anything above near-copies. The first file is always an original: at redundancy
+//! 1.0 every other file descends from it.
+//!
+//! The ingest measurement records the corpus as `corpus` next to its size breakdown and
+//! correction stats, for charting the effective ratio against redundancy. Its
+//! `distinct_files` counts the byte-distinct files, which is what a deduplicating
+//! engram could at best be left holding; whether the backend gets there is up to it.
+
+use crate::error::BenchError;
+use rand::seq::SliceRandom;
+use rand::{Rng, RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The directory [`generate`] writes the files to, and so their logical prefix.
+pub const ROOT: &str = "corpus";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CorpusConfig {
+    pub files: usize,
+    pub file_bytes: usize,
+    /// Fraction of the files that copy an earlier one, in `0.0..=1.0`.
+    pub redundancy: f64,
+    /// Chance of each byte of a copy being replaced, in `0.0..=1.0`.
+    pub mutation_rate: f64,
+}
+
+impl Default for CorpusConfig {
+    fn default() -> Self {
+        Self {
+            files: 16,
+            file_bytes: 8192,
+            redundancy: 0.0,
+            mutation_rate: 0.0,
+        }
+    }
+}
+
+impl CorpusConfig {
+    pub fn validate(&self) -> Result<(), BenchError> {
+        if self.files == 0 || self.file_bytes == 0 {
+            return Err(BenchError::invalid_args(
+                "synthetic corpus: files and file bytes must be at least 1",
+            ));
+        }
+        for (name, v) in [
+            ("redundancy", self.redundancy),
+            ("mutation rate", self.mutation_rate),
+        ] {
+            if !(0.0..=1.0).contains(&v) {
+                return Err(BenchError::invalid_args(format!(
+                    "synthetic corpus: {name} must be in 0.0..=1.0, got {v}"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn total_bytes(&self) -> u64 {
+        (self.files as u64) * (self.file_bytes as u64)
+    }
+
+    /// How many of the files are copies: `redundancy` of them, rounded, short of the
+    /// first.
+    pub fn copies(&self) -> usize {
+        ((self.redundancy * self.files as f64).round() as usize).min(self.files - 1)
+    }
+}
+
+/// A generated corpus.
+#[derive(Clone, Debug)]
+pub struct Corpus {
+    /// The [`ROOT`] directory holding the files.
+    pub dir: PathBuf,
+    pub config: CorpusConfig,
+    pub seed: u64,
+    pub copies: usize,
+    /// Bytes of the copies that were replaced.
+    pub mutated_bytes: u64,
+    /// Files whose bytes differ from every other file's: `files - copies` at mutation
+    /// rate 0, and usually all of them above it.
+    pub distinct_files: usize,
+}
+
+impl Corpus {
+    pub fn extra(&self) -> serde_json::Value {
+        json!({
+            "files": self.config.files,
+            "file_bytes": self.config.file_bytes,
+            "redundancy": self.config.redundancy,
+            "mutation_rate": self.config.mutation_rate,
+            "copies": self.copies,
+            "mutated_bytes": self.mutated_bytes,
+            "distinct_files": self.distinct_files,
+            "seed": self.seed,
+        })
+    }
+}
+
+fn file_name(i: usize) -> String {
+    format!("f{i:05}.bin")
+}
+
+/// Write the corpus `config` describes under `parent`/[`ROOT`], the same for the same
+/// seed. Which files are copies, and of which earlier file, is seeded too.
+pub fn generate(parent: &Path, config: &CorpusConfig, seed: u64) -> io::Result<Corpus> {
+    config.validate()?;
+    let dir = parent.join(ROOT);
+    fs::create_dir_all(&dir)?;
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let mut later: Vec<usize> = (1..config.files).collect();
+    later.shuffle(&mut rng);
+    let copies: BTreeSet<usize> = later[..config.copies()].iter().copied().collect();
+
+    let mut mutated_bytes = 0;
+    let mut digests = BTreeSet::new();
+    for i in 0..config.files {
+        let body = if copies.contains(&i) {
+            let mut body = fs::read(dir.join(file_name(rng.gen_range(0..i))))?;
+            if config.mutation_rate > 0.0 {
+                for b in &mut body {
+                    if rng.gen_bool(config.mutation_rate) {
+                        *b = rng.gen();
+                        mutated_bytes += 1;
+                    }
+                }
+            }
+            body
+        } else {
+            let mut body = vec![0u8; config.file_bytes];
+            rng.fill_bytes(&mut body);
+            body
+        };
+        digests.insert(Sha256::digest(&body));
+        fs::write(dir.join(file_name(i)), body)?;
+    }
+    Ok(Corpus {
+        dir,
+        config: config.clone(),
+        seed,
+        copies: copies.len(),
+        mutated_bytes,
+        distinct_files: digests.len(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bodies(corpus: &Corpus) -> Vec<Vec<u8>> {
+        (0..corpus.config.files)
+            .map(|i| fs::read(corpus.dir.join(file_name(i))).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_generate_redundancy_and_mutation() {
+        let dir = tempfile::tempdir().unwrap();
+        let gen = |name: &str, redundancy, mutation_rate, seed| {
+            let config = CorpusConfig {
+                files: 10,
+                file_bytes: 1000,
+                redundancy,
+                mutation_rate,
+            };
+            generate(&dir.path().join(name), &config, seed).unwrap()
+        };
+        let distinct = |bodies: &[Vec<u8>]| bodies.iter().collect::<BTreeSet<_>>().len();
+
+        let none = gen("none", 0.0, 0.0, 7);
+        assert_eq!((none.copies, none.mutated_bytes), (0, 0));
+        let originals = bodies(&none);
+        assert!(originals.iter().all(|b| b.len() == 1000));
+        assert_eq!(distinct(&originals), 10);
+        assert_eq!(none.distinct_files, 10);
+        assert_eq!(bodies(&gen("again", 0.0, 0.0, 7)), originals);
+
+        // Every file past the first is an exact copy; a fraction rounds.
+        let exact = gen("exact", 1.0, 0.0, 7);
+        assert_eq!(exact.copies, 9);
+        assert_eq!(distinct(&bodies(&exact)), 1);
+        assert_eq!(exact.distinct_files, 1);
+        let some = gen("some", 0.34, 0.0, 7);
+        assert_eq!(some.copies, 3);
+        assert_eq!(distinct(&bodies(&some)), 7);
+        assert_eq!(some.distinct_files, 7);
+
+        // Near-copies are all distinct, each about the mutation rate away from an earlier file.
+        let near = gen("near", 1.0, 0.1, 7);
+        assert_eq!(near.copies, 9);
+        assert!(
+            (500..1300).contains(&near.mutated_bytes),
+            "{}",
+            near.mutated_bytes
+        );
+        assert_eq!(near.distinct_files, 10);
+        let near = bodies(&near);
+        assert_eq!(distinct(&near), 10);
+        for (i, body) in near.iter().enumerate().skip(1) {
+            let closest = near[..i]
+                .iter()
+                .map(|earlier| body.iter().zip(earlier).filter(|(a, b)| a == b).count())
+                .max()
+                .unwrap();
+            assert!(closest > 800, "file {i}: {closest}");
+        }
+        assert_eq!(near[0], originals[0]);
+
+        for (redundancy, mutation_rate) in [(1.5, 0.0), (0.5, -0.1), (f64::NAN, 0.0)] {
+            let config = CorpusConfig {
+                redundancy,
+                mutation_rate,
+                ..Default::default()
+            };
+            let err = generate(dir.path(), &config, 0).unwrap_err();
+            assert!(
+                matches!(BenchError::of(&err), Some(BenchError::InvalidArgs(_))),
+                "{err:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,268 @@
+//! Differential dataset benchmarking across on-disk formats (`dataset-bench-formats`).
+//!
+//! One logical dataset is generated once, in memory, and written in every
+//! [`DatasetFormat`]. Each format then gets the same three measurements, tagged
+//! `format=<label>`:
+//!
+//! - `dataset_formats.<format>.write`: writing the file from the in-memory vectors
+//!   (generation is not timed), with the resulting file size;
+//! - `dataset_formats.<format>.scan`: full decoding passes, as `vsa_dataset.reader.scan`;
+//! - `dataset_formats.<format>.packed_bind`: the packed bind loop of `vsa --dataset`, as a
+//!   representative op over records read from the file.
+//!
+//! Only the formats the reader and writer understand can be compared; a new format
+//! becomes a [`DatasetFormat`] variant and shows up here.
+
+use crate::benches::dataset_io;
+use crate::benches::vsa::{self, DatasetRunOptions, VsaOp};
+use crate::dataset::{
+    expected_file_size, format_count, generate_dataset, write_dataset_as, DatasetFormat,
+    DatasetSource, GenerateConfig, DEFAULT_READ_BUFFER,
+};
+use crate::disk_space::{self, Requirement, ScratchDir};
+use crate::harness::{measure_fn, BenchConfig, Cost};
+use crate::measurements;
+use crate::schema::{tags, Measurement};
+use crate::VsaVariant;
+use serde_json::json;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Debug)]
+pub struct FormatsArgs {
+    /// The logical dataset.
+    pub config: GenerateConfig,
+    /// Formats to compare, in order (empty = every format `config` can be written in).
+    pub formats: Vec<DatasetFormat>,
+    /// Write the files into this directory and keep them, instead of a temporary
+    /// directory that is removed afterwards.
+    pub keep: Option<PathBuf>,
+}
+
+/// Where the format files go: a kept directory or a temporary one.
+enum OutDir {
+    Kept(PathBuf),
+    Temp(ScratchDir),
+}
+
+impl OutDir {
+    fn path(&self) -> &Path {
+        match self {
+            OutDir::Kept(p) => p,
+            OutDir::Temp(t) => t.path(),
+        }
+    }
+}
+
+/// The format files [`run`] writes: all at once, in `keep` or the temp directory.
+pub fn disk_requirements(args: &FormatsArgs) -> Vec<Requirement> {
+    let formats: Vec<DatasetFormat> = if args.formats.is_empty() {
+        DatasetFormat::ALL
+            .into_iter()
+            .filter(|f| f.supports(&args.config))
+            .collect()
+    } else {
+        args.formats.clone()
+    };
+    let plain = expected_file_size(args.config.count, args.config.sparsity);
+    formats
+        .into_iter()
+        .map(|format| {
+            let labels = match format {
+                DatasetFormat::V2Labeled => args.config.count.saturating_mul(4),
+                _ => 0,
+            };
+            let bytes = plain.saturating_add(labels);
+            let path = args
+                .keep
+                .clone()
+                .unwrap_or_else(std::env::temp_dir)
+                .join(format!("{}.embr", format.label()));
+            let mut req = disk_space::generate_requirement(&path, bytes);
+            if args.keep.is_none() {
+                req.section = Some("dataset_formats".to_string());
+            }
+            req
+        })
+        .collect()
+}
+
+pub fn run(cfg: &BenchConfig, args: &FormatsArgs) -> io::Result<Vec<Measurement>> {
+    args.config.validate()?;
+    let formats: Vec<DatasetFormat> = if args.formats.is_empty() {
+        DatasetFormat::ALL
+            .into_iter()
+            .filter(|f| {
+                let ok = f.supports(&args.config);
+                if !ok {
+                    eprintln!(
+                        "note: skipping format {}: it cannot record the v2 generator",
+                        f.label()
+                    );
+                }
+                ok
+            })
+            .collect()
+    } else {
+        args.formats.clone()
+    };
+
+    let dir = match &args.keep {
+        Some(dir) => {
+            std::fs::create_dir_all(dir)?;
+            OutDir::Kept(dir.clone())
+        }
+        None => OutDir::Temp(disk_space::scratch_dir()?),
+    };
+    let vectors = generate_dataset(&args.config)?;
+
+    let mut out = Vec::new();
+    for format in formats {
+        let path = dir.path().join(format!("{}.embr", format.label()));
+        let write = measure_write(cfg, &args.config, &vectors, format, &path)?;
+        let file_bytes = std::fs::metadata(&path)?.len();
+
+        let mut scan = dataset_io::scan(cfg, &path, DEFAULT_READ_BUFFER)?;
+        scan.name = measurements::dataset_formats::stage(format.label(), "scan");
+
+        let bind_opts = DatasetRunOptions {
+            ops: Some(vec![VsaOp::Bind]),
+            ..Default::default()
+        };
+        let mut bind = vsa::run_dataset(
+            cfg,
+            VsaVariant::Packed,
+            &DatasetSource::File(path.clone()),
+            &bind_opts,
+        )?
+        .into_iter()
+        .find(|m| m.name == measurements::vsa_dataset::PACKED_BIND)
+        .ok_or_else(|| io::Error::other("packed bind did not run on this dataset"))?;
+        bind.name = measurements::dataset_formats::stage(format.label(), "packed_bind");
+
+        for mut m in [write, scan, bind] {
+            m.extra["format"] = json!(format.label());
+            m.extra["format_version"] = json!(format.version());
+            m.extra["file_bytes"] = json!(file_bytes);
+            m.extra["bytes_per_vector"] =
+                json!(file_bytes as f64 / args.config.count.max(1) as f64);
+            if args.keep.is_some() {
+                m.extra["file"] = json!(path.display().to_string());
+            }
+            m.tags
+                .insert("format".to_string(), format.label().to_string());
+            out.push(m);
+        }
+    }
+    Ok(out)
+}
+
+/// Time writing `vectors` to `path` in `format`; one iteration writes the whole file.
+fn measure_write(
+    cfg: &BenchConfig,
+    config: &GenerateConfig,
+    vectors: &[embeddenator::SparseVec],
+    format: DatasetFormat,
+    path: &Path,
+) -> io::Result<Measurement> {
+    let (iters, warmup) = cfg.counts(Cost::Macro);
+    let mut result = Ok(());
+    let m = measure_fn(iters, warmup, || {
+        if result.is_ok() {
+            result = write_dataset_as(path, vectors, config, format);
+        }
+    });
+    result?;
+
+    let count = vectors.len() as u64;
+    let file_bytes = std::fs::metadata(path)?.len();
+    let secs = (m.total_ns as f64 / 1e9).max(1e-12);
+    let (vectors_written, bytes) = (count * m.iters, file_bytes * m.iters);
+    let scale = format_count(count);
+    Ok(Measurement {
+        name: measurements::dataset_formats::stage(format.label(), "write"),
+        unit: "ns/vector".to_string(),
+        iters: vectors_written,
+        warmup_iters: count * m.warmup_iters,
+        total_ns: m.total_ns,
+        ns_per_iter: m.total_ns as f64 / vectors_written.max(1) as f64,
+        bytes_processed: Some(bytes),
+        throughput_bytes_per_s: Some(bytes as f64 / secs),
+        extra: json!({
+            "vectors": count,
+            "dim": config.dimension,
+            "passes": m.iters,
+            "warmup_passes": m.warmup_iters,
+            "vectors_per_s": vectors_written as f64 / secs,
+            "mb_per_s": bytes as f64 / secs / 1_048_576.0,
+        }),
+        tags: tags(&[("scale", scale.as_str())]),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dataset::{read_dataset_meta, GeneratorVersion};
+    use crate::harness::Profile;
+
+    #[test]
+    fn test_one_measurement_set_per_format() {
+        let cfg = BenchConfig {
+            profile: Profile::Quick,
+            seed: 0,
+        };
+        let keep = tempfile::tempdir().unwrap();
+        let args = FormatsArgs {
+            config: GenerateConfig {
+                count: 24,
+                dimension: 1000,
+                sparsity: 10,
+                ..Default::default()
+            },
+            formats: Vec::new(),
+            keep: Some(keep.path().join("formats")),
+        };
+        let ms = run(&cfg, &args).unwrap();
+
+        let names: Vec<&str> = ms.iter().map(|m| m.name.as_str()).collect();
+        let mut expected = Vec::new();
+        for format in ["v1", "v2", "v2-labeled"] {
+            for stage in ["write", "scan", "packed_bind"] {
+                expected.push(format!("dataset_formats.{format}.{stage}"));
+            }
+        }
+        assert_eq!(names, expected);
+        assert!(ms.iter().all(|m| m.tags["format"] == m.extra["format"]));
+
+        // Labels cost four bytes a record; the v2 header costs nothing.
+        let size = |i: usize| ms[i * 3].extra["file_bytes"].as_u64().unwrap();
+        assert_eq!(size(1), size(0));
+        assert_eq!(size(2), size(0) + 4 * 24);
+        let meta = read_dataset_meta(keep.path().join("formats/v2-labeled.embr")).unwrap();
+        assert_eq!((meta.version, meta.labeled, meta.count), (2, true, 24));
+        assert_eq!(ms[1].iters % 24, 0);
+
+        // v2-generated data has no version 1 form: skipped by default, an error on request.
+        let v2 = GenerateConfig {
+            generator: GeneratorVersion::V2,
+            ..args.config.clone()
+        };
+        let default = run(
+            &cfg,
+            &FormatsArgs {
+                config: v2.clone(),
+                formats: Vec::new(),
+                keep: None,
+            },
+        )
+        .unwrap();
+        assert!(default.iter().all(|m| m.tags["format"] != "v1"));
+        let explicit = FormatsArgs {
+            config: v2,
+            formats: vec![DatasetFormat::V1],
+            keep: None,
+        };
+        assert!(run(&cfg, &explicit).is_err());
+    }
+}
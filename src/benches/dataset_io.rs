@@ -0,0 +1,372 @@
+//! Raw dataset parse throughput.
+//!
+//! Streams a whole dataset file through `DatasetReader`, decoding every vector but doing
+//! no VSA work. This is the I/O + decode cost hidden inside every `vsa_dataset.*` number,
+//! and the measurement to watch when the on-disk format or the reader changes.
+//!
+//! Also times converting a batch of decoded vectors to each substrate, serially and on
+//! the rayon pool (`vsa_dataset.<substrate>.convert_batch[_serial]`).
+//!
+//! The main scan uses the `--read-buffer-kib` capacity (default
+//! [`DEFAULT_READ_BUFFER`]). A small study then repeats it at each of
+//! [`STUDY_BUFFERS`] (`vsa_dataset.reader.scan_buffer_<kib>k`), so the effect of the read
+//! size shows up next to the number it would change.
+//!
+//! [`generation_measurements`] turns a `generate-dataset` run into a report of its own
+//! (`--report`), so generation throughput can be tracked like any other number.
+
+use crate::dataset::{
+    convert_batch, convert_batch_serial, format_count, sequential_hint_extra, DatasetReader,
+    FromSparse, GenerateConfig, DEFAULT_READ_BUFFER,
+};
+use crate::error::BenchError;
+use crate::harness::{cool_down, BenchConfig, Cost};
+use crate::measurements;
+use crate::plan;
+use crate::registry::{Bench, MeasurementSink};
+use crate::schema::{tags, Measurement};
+use embeddenator::{BitslicedTritVec, BlockSparseTritVec, PackedTritVec, SparseVec};
+use serde_json::json;
+use std::hint::black_box;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// `(warmup, measured)` full-file passes. The warmup pass also primes the page cache,
+/// so the measured passes are decode-bound rather than disk-bound.
+fn passes(cfg: &BenchConfig) -> (u64, u64) {
+    let (iters, warmup) = cfg.counts(Cost::Macro);
+    (warmup, iters)
+}
+
+/// Vectors converted per pass by the conversion measurements.
+const CONVERT_BATCH: usize = 10_000;
+
+/// Read buffer capacities compared by the scan study: 64 KiB, 1 MiB, 8 MiB.
+pub const STUDY_BUFFERS: [usize; 3] = [64 * 1024, 1024 * 1024, 8 * 1024 * 1024];
+
+/// Total ns over `iters` conversions of `batch` (after `warmup` untimed ones).
+fn time_convert<T>(
+    batch: &[SparseVec],
+    dim: usize,
+    (warmup, iters): (u64, u64),
+    convert: fn(&[SparseVec], usize) -> Vec<T>,
+) -> u128 {
+    cool_down();
+    let mut total_ns = 0u128;
+    for pass in 0..warmup + iters {
+        let start = Instant::now();
+        black_box(convert(batch, dim));
+        if pass >= warmup {
+            total_ns += start.elapsed().as_nanos();
+        }
+    }
+    total_ns
+}
+
+/// A decoded batch to time substrate conversion on.
+struct ConvertBench<'a> {
+    batch: &'a [SparseVec],
+    dim: usize,
+    passes: (u64, u64),
+    scale: &'a str,
+}
+
+impl ConvertBench<'_> {
+    /// Serial and parallel conversion of the batch to `T`, as two measurements.
+    fn measure<T: FromSparse>(&self, substrate: &str) -> [Measurement; 2] {
+        let (batch, dim, passes) = (self.batch, self.dim, self.passes);
+        let serial_ns = time_convert(batch, dim, passes, convert_batch_serial::<T>);
+        let parallel_ns = time_convert(batch, dim, passes, convert_batch::<T>);
+        let vectors = batch.len() as u64 * passes.1;
+        let m = |suffix: &str, total_ns: u128, threads: usize| Measurement {
+            name: measurements::vsa_dataset::convert_batch(substrate, suffix),
+            unit: "ns/vector".to_string(),
+            iters: vectors,
+            warmup_iters: batch.len() as u64 * passes.0,
+            total_ns,
+            ns_per_iter: total_ns as f64 / vectors.max(1) as f64,
+            bytes_processed: None,
+            throughput_bytes_per_s: None,
+            extra: json!({
+                "dim": dim,
+                "batch": batch.len(),
+                "threads": threads,
+                "vectors_per_s": vectors as f64 / (total_ns as f64 / 1e9).max(1e-12),
+                "speedup_vs_serial": serial_ns as f64 / total_ns.max(1) as f64,
+            }),
+            tags: tags(&[("substrate", substrate), ("scale", self.scale)]),
+        };
+        [
+            m("", parallel_ns, rayon::current_num_threads()),
+            m("_serial", serial_ns, 1),
+        ]
+    }
+}
+
+/// `dataset.generate.{vectors_per_s,bytes_per_s,wall_s}` for `vectors` vectors of
+/// `config` written as `file_bytes` bytes in `elapsed`, tagged with the generation
+/// parameters so runs of different shapes do not align. The value is in `ns_per_iter`,
+/// as for other non-time units.
+pub fn generation_measurements(
+    config: &GenerateConfig,
+    vectors: u64,
+    file_bytes: u64,
+    elapsed: Duration,
+) -> Vec<Measurement> {
+    let secs = elapsed.as_secs_f64().max(1e-9);
+    let tags = tags(&[
+        ("count", vectors.to_string()),
+        ("dimension", config.dimension.to_string()),
+        ("sparsity", config.sparsity.to_string()),
+        ("generator", config.generator.label().to_string()),
+    ]);
+    let m = |name: &str, unit: &str, value: f64| Measurement {
+        name: name.to_string(),
+        unit: unit.to_string(),
+        iters: vectors,
+        warmup_iters: 0,
+        total_ns: elapsed.as_nanos(),
+        ns_per_iter: value,
+        bytes_processed: Some(file_bytes),
+        throughput_bytes_per_s: Some(file_bytes as f64 / secs),
+        extra: json!({
+            "seed": config.seed,
+            "index_distribution": config.index_distribution.label(),
+            "file_bytes": file_bytes,
+        }),
+        tags: tags.clone(),
+    };
+    vec![
+        m(
+            measurements::dataset::GENERATE_VECTORS_PER_S,
+            "vectors/s",
+            vectors as f64 / secs,
+        ),
+        m(
+            measurements::dataset::GENERATE_BYTES_PER_S,
+            "bytes/s",
+            file_bytes as f64 / secs,
+        ),
+        m(measurements::dataset::GENERATE_WALL_S, "s", secs),
+    ]
+}
+
+/// [`run`] as the suite's `dataset_io` section.
+pub struct ScanBench {
+    pub path: PathBuf,
+    pub read_buffer: Option<usize>,
+}
+
+impl Bench for ScanBench {
+    fn name(&self) -> &str {
+        "dataset_io"
+    }
+
+    fn run(&self, cfg: &BenchConfig, sink: &mut dyn MeasurementSink) -> io::Result<()> {
+        sink.push_all(run(cfg, &self.path, self.read_buffer)?)
+    }
+
+    fn unplanned(&self) -> Option<&'static str> {
+        Some(plan::STREAMED)
+    }
+}
+
+/// Emit `vsa_dataset.reader.scan` (read through a `read_buffer`-byte buffer, default
+/// [`DEFAULT_READ_BUFFER`]), the buffer study and the batch conversion measurements for
+/// the dataset at `path`.
+pub fn run(
+    cfg: &BenchConfig,
+    path: &Path,
+    read_buffer: Option<usize>,
+) -> io::Result<Vec<Measurement>> {
+    let mut out = vec![scan(cfg, path, read_buffer.unwrap_or(DEFAULT_READ_BUFFER))?];
+    for capacity in STUDY_BUFFERS {
+        let mut m = scan(cfg, path, capacity)?;
+        m.name = measurements::vsa_dataset::reader_scan_buffer(capacity / 1024);
+        out.push(m);
+    }
+
+    let (warmup, iters) = passes(cfg);
+    let mut reader = DatasetReader::open(path)?;
+    let dim = reader.meta().dimension as usize;
+    let scale = format_count(reader.meta().count);
+    let batch = reader.read_batch(CONVERT_BATCH)?;
+    let convert = ConvertBench {
+        batch: &batch,
+        dim,
+        passes: (warmup, iters),
+        scale: &scale,
+    };
+    out.extend(convert.measure::<PackedTritVec>("packed"));
+    out.extend(convert.measure::<BitslicedTritVec>("bitsliced"));
+    out.extend(convert.measure::<BlockSparseTritVec>("blocksparse"));
+    Ok(out)
+}
+
+/// `vsa_dataset.reader.scan`: full decoding passes over the dataset at `path`, read
+/// through a `capacity`-byte buffer.
+pub fn scan(cfg: &BenchConfig, path: &Path, capacity: usize) -> io::Result<Measurement> {
+    let mut reader = DatasetReader::open_with_capacity(path, capacity)?;
+    let sequential_hint = reader.sequential_hint();
+    let meta = reader.meta().clone();
+    let file_bytes = std::fs::metadata(path)?.len();
+    let (warmup, iters) = passes(cfg);
+
+    cool_down();
+    let mut total_ns = 0u128;
+    for pass in 0..warmup + iters {
+        reader.reset()?;
+        let start = Instant::now();
+        let mut decoded = 0u64;
+        for v in reader.by_ref() {
+            black_box(v?);
+            decoded += 1;
+        }
+        let elapsed = start.elapsed();
+        if decoded != meta.count {
+            return Err(BenchError::truncated(
+                None,
+                format!(
+                    "header claims {} vectors but {} were read",
+                    meta.count, decoded
+                ),
+            )
+            .into());
+        }
+        if pass >= warmup {
+            total_ns += elapsed.as_nanos();
+        }
+    }
+
+    let secs = (total_ns as f64 / 1e9).max(1e-12);
+    let vectors = meta.count * iters;
+    let bytes = file_bytes * iters;
+    let bytes_per_s = bytes as f64 / secs;
+    let scale = format_count(meta.count);
+
+    Ok(Measurement {
+        name: measurements::vsa_dataset::READER_SCAN.to_string(),
+        unit: "ns/vector".to_string(),
+        iters: vectors,
+        warmup_iters: meta.count * warmup,
+        total_ns,
+        ns_per_iter: total_ns as f64 / vectors.max(1) as f64,
+        bytes_processed: Some(bytes),
+        throughput_bytes_per_s: Some(bytes_per_s),
+        extra: json!({
+            "dataset": path.display().to_string(),
+            "vectors": meta.count,
+            "dim": meta.dimension,
+            "passes": iters,
+            "warmup_passes": warmup,
+            "format_version": meta.version,
+            "file_bytes": file_bytes,
+            "read_buffer_bytes": reader.buffer_capacity(),
+            "sequential_hint": sequential_hint_extra(sequential_hint),
+            "vectors_per_s": vectors as f64 / secs,
+            "mb_per_s": bytes_per_s / 1_048_576.0,
+        }),
+        tags: tags(&[("substrate", "reader"), ("scale", scale.as_str())]),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dataset::{write_dataset_streaming, GenerateConfig};
+    use crate::harness::Profile;
+
+    #[test]
+    fn test_generation_measurements() {
+        let config = GenerateConfig {
+            count: 1_000,
+            ..Default::default()
+        };
+        let ms = generation_measurements(&config, 1_000, 4_000_000, Duration::from_millis(500));
+        let values: Vec<(&str, &str, f64)> = ms
+            .iter()
+            .map(|m| (m.name.as_str(), m.unit.as_str(), m.ns_per_iter))
+            .collect();
+        assert_eq!(
+            values,
+            [
+                ("dataset.generate.vectors_per_s", "vectors/s", 2_000.0),
+                ("dataset.generate.bytes_per_s", "bytes/s", 8_000_000.0),
+                ("dataset.generate.wall_s", "s", 0.5),
+            ]
+        );
+        // Rates are higher-is-better for compare, the wall time is not.
+        assert!(ms[0].higher_is_better() && ms[1].higher_is_better() && !ms[2].higher_is_better());
+        assert!(ms
+            .iter()
+            .all(|m| m.tags["count"] == "1000" && m.tags["generator"] == "v1"));
+    }
+
+    #[test]
+    fn test_scan_reads_every_vector() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("scan.embr");
+        let config = GenerateConfig {
+            count: 40,
+            ..Default::default()
+        };
+        write_dataset_streaming(&path, &config, 16).unwrap();
+
+        let cfg = BenchConfig {
+            profile: Profile::Quick,
+            seed: 0,
+        };
+        let m = &run(&cfg, &path, None).unwrap()[0];
+        assert_eq!(m.iters, 40 * 2);
+        assert_eq!(m.warmup_iters, 40);
+        assert_eq!(
+            m.bytes_processed,
+            Some(2 * std::fs::metadata(&path).unwrap().len())
+        );
+        assert_eq!(m.extra["format_version"], crate::dataset::FORMAT_VERSION);
+        assert_eq!(m.tags["scale"], "40");
+        assert_eq!(m.extra["read_buffer_bytes"], DEFAULT_READ_BUFFER);
+
+        let ms = run(&cfg, &path, Some(4096)).unwrap();
+        assert_eq!(ms[0].extra["read_buffer_bytes"], 4096);
+        let study: Vec<(&str, u64)> = ms[1..4]
+            .iter()
+            .map(|m| {
+                (
+                    m.name.as_str(),
+                    m.extra["read_buffer_bytes"].as_u64().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            study,
+            [
+                ("vsa_dataset.reader.scan_buffer_64k", 64 << 10),
+                ("vsa_dataset.reader.scan_buffer_1024k", 1 << 20),
+                ("vsa_dataset.reader.scan_buffer_8192k", 8 << 20),
+            ]
+        );
+        // Same data whatever the buffer: every scan decodes the same vectors and bytes.
+        assert!(ms[..4]
+            .iter()
+            .all(|s| (s.iters, s.bytes_processed) == (m.iters, m.bytes_processed)));
+        let convert = ms
+            .iter()
+            .find(|m| m.name == "vsa_dataset.bitsliced.convert_batch")
+            .unwrap();
+        assert_eq!(
+            (convert.iters, convert.extra["batch"].as_u64()),
+            (80, Some(40))
+        );
+        assert!(ms.iter().any(
+            |m| m.name == "vsa_dataset.packed.convert_batch_serial" && m.extra["threads"] == 1
+        ));
+
+        // A header that overstates the count is caught instead of timing a short read.
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes[12..20].copy_from_slice(&41u64.to_le_bytes());
+        std::fs::write(&path, bytes).unwrap();
+        assert!(run(&cfg, &path, None).is_err());
+    }
+}
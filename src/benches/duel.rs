@@ -0,0 +1,395 @@
+//! Interleaved A/B runs (`duel`).
+//!
+//! Running a baseline and a candidate back to back leaves drift (thermal state,
+//! background load) between them that no amount of statistics removes. A duel times
+//! both sides in alternating op-blocks within one process instead, and reports paired
+//! per-block deltas with a sign-test p-value (see [`measure_paired`]).
+//!
+//! The sides are either two substrates over the fixed microbench inputs
+//! (`--a packed --b bitsliced`) or two dataset files under one substrate. Each op
+//! becomes a `duel.<op>` measurement whose `ns_per_iter` is side B's, with both sides
+//! and the paired statistics in extra.
+
+use crate::benches::vsa::rotation_inputs;
+use crate::budget::profile_ops;
+use crate::dataset::{convert_batch, DatasetReader};
+use crate::error::BenchError;
+use crate::harness::{measure_paired, BenchConfig, Profile};
+use crate::measurements;
+use crate::schema::{tags, Measurement};
+use clap::ValueEnum;
+use embeddenator::{
+    BitslicedTritVec, BlockSparseTritVec, PackedTritVec, ReversibleVSAConfig, SparseVec, DIM,
+};
+use serde_json::json;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Substrates a duel side can run on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum DuelSubstrate {
+    /// The high-level SparseVec API.
+    Sparsevec,
+    Packed,
+    Bitsliced,
+    #[value(alias = "block-sparse")]
+    Blocksparse,
+}
+
+impl DuelSubstrate {
+    pub fn name(self) -> &'static str {
+        match self {
+            DuelSubstrate::Sparsevec => "sparsevec",
+            DuelSubstrate::Packed => "packed",
+            DuelSubstrate::Bitsliced => "bitsliced",
+            DuelSubstrate::Blocksparse => "blocksparse",
+        }
+    }
+}
+
+/// One side of a duel: a substrate name, or otherwise a dataset path.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DuelSide {
+    Substrate(DuelSubstrate),
+    Dataset(PathBuf),
+}
+
+impl DuelSide {
+    /// Substrate names win; use `./packed` for a dataset file called `packed`.
+    pub fn parse(s: &str) -> Self {
+        match DuelSubstrate::from_str(s, true) {
+            Ok(substrate) => DuelSide::Substrate(substrate),
+            Err(_) => DuelSide::Dataset(PathBuf::from(s)),
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            DuelSide::Substrate(s) => s.name().to_string(),
+            DuelSide::Dataset(path) => path.display().to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct DuelArgs {
+    pub a: DuelSide,
+    pub b: DuelSide,
+    /// Substrate both sides use when they are datasets.
+    pub substrate: DuelSubstrate,
+    /// Number of alternating blocks (default: profile-dependent).
+    pub blocks: Option<u64>,
+    /// Cap on the (a, b) pairs loaded from each dataset.
+    pub max_ops: Option<u64>,
+}
+
+/// Ops every substrate supports. `similarity` is cosine where the substrate has it and
+/// dot otherwise (recorded per side as `kernel`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum DuelOp {
+    Bind,
+    Bundle,
+    Similarity,
+}
+
+const OPS: [DuelOp; 3] = [DuelOp::Bind, DuelOp::Bundle, DuelOp::Similarity];
+
+impl DuelOp {
+    fn name(self) -> &'static str {
+        match self {
+            DuelOp::Bind => "bind",
+            DuelOp::Bundle => "bundle",
+            DuelOp::Similarity => "similarity",
+        }
+    }
+}
+
+/// Input vectors converted to one substrate up front, so conversion is never timed.
+/// Pair `i` is vectors `2i` and `2i + 1`.
+enum Prepared {
+    Sparsevec(Vec<SparseVec>),
+    Packed(Vec<PackedTritVec>),
+    Bitsliced(Vec<BitslicedTritVec>),
+    Blocksparse(Vec<BlockSparseTritVec>),
+}
+
+impl Prepared {
+    fn new(substrate: DuelSubstrate, vectors: Vec<SparseVec>, dim: usize) -> Self {
+        match substrate {
+            DuelSubstrate::Sparsevec => Prepared::Sparsevec(vectors),
+            DuelSubstrate::Packed => Prepared::Packed(convert_batch(&vectors, dim)),
+            DuelSubstrate::Bitsliced => Prepared::Bitsliced(convert_batch(&vectors, dim)),
+            DuelSubstrate::Blocksparse => Prepared::Blocksparse(convert_batch(&vectors, dim)),
+        }
+    }
+
+    /// Number of (a, b) pairs.
+    fn len(&self) -> usize {
+        let vectors = match self {
+            Prepared::Sparsevec(p) => p.len(),
+            Prepared::Packed(p) => p.len(),
+            Prepared::Bitsliced(p) => p.len(),
+            Prepared::Blocksparse(p) => p.len(),
+        };
+        vectors / 2
+    }
+
+    fn kernel(&self, op: DuelOp) -> &'static str {
+        match (self, op) {
+            (Prepared::Packed(_), DuelOp::Similarity) => "dot",
+            (_, DuelOp::Similarity) => "cosine",
+            (_, op) => op.name(),
+        }
+    }
+
+    /// Run `op` on pair `i` (cycling), returning a value for the harness to black-box.
+    fn run(&self, op: DuelOp, i: u64) -> f64 {
+        let a = 2 * (i % self.len().max(1) as u64) as usize;
+        let b = a + 1;
+        match (self, op) {
+            (Prepared::Sparsevec(p), DuelOp::Bind) => p[a].bind(&p[b]).pos.len() as f64,
+            (Prepared::Sparsevec(p), DuelOp::Bundle) => p[a].bundle(&p[b]).pos.len() as f64,
+            (Prepared::Sparsevec(p), DuelOp::Similarity) => p[a].cosine(&p[b]),
+            (Prepared::Packed(p), DuelOp::Bind) => p[a].bind(&p[b]).len() as f64,
+            (Prepared::Packed(p), DuelOp::Bundle) => p[a].bundle(&p[b]).len() as f64,
+            (Prepared::Packed(p), DuelOp::Similarity) => p[a].dot(&p[b]) as f64,
+            (Prepared::Bitsliced(p), DuelOp::Bind) => p[a].bind_dispatch(&p[b]).len() as f64,
+            (Prepared::Bitsliced(p), DuelOp::Bundle) => p[a].bundle_dispatch(&p[b]).len() as f64,
+            (Prepared::Bitsliced(p), DuelOp::Similarity) => p[a].cosine(&p[b]),
+            (Prepared::Blocksparse(p), DuelOp::Bind) => {
+                p[a].bind_dispatch(&p[b]).block_count() as f64
+            }
+            (Prepared::Blocksparse(p), DuelOp::Bundle) => {
+                p[a].bundle_dispatch(&p[b]).block_count() as f64
+            }
+            (Prepared::Blocksparse(p), DuelOp::Similarity) => p[a].cosine_dispatch(&p[b]),
+        }
+    }
+}
+
+/// A side ready to time: its inputs and what to record about it.
+struct Contender {
+    label: String,
+    substrate: DuelSubstrate,
+    dim: usize,
+    prepared: Prepared,
+}
+
+fn blocks_for_profile(cfg: &BenchConfig) -> u64 {
+    match cfg.profile {
+        Profile::Quick => 30,
+        Profile::Full => 60,
+    }
+}
+
+/// The first `pairs` consecutive (a, b) record pairs of a dataset, and its dimension.
+fn load_pairs(path: &Path, pairs: u64) -> io::Result<(Vec<SparseVec>, usize)> {
+    let mut reader = DatasetReader::open(path)?;
+    let dim = reader.meta().dimension as usize;
+    Ok((reader.read_batch((pairs * 2) as usize)?, dim))
+}
+
+fn contenders(cfg: &BenchConfig, args: &DuelArgs) -> io::Result<(Contender, Contender)> {
+    match (&args.a, &args.b) {
+        (DuelSide::Substrate(a), DuelSide::Substrate(b)) => {
+            let vectors: Vec<SparseVec> = rotation_inputs(cfg, &ReversibleVSAConfig::default(), 1)
+                .into_iter()
+                .flat_map(|[a, b, _]| [a, b])
+                .collect();
+            let side = |s: DuelSubstrate| Contender {
+                label: s.name().to_string(),
+                substrate: s,
+                dim: DIM,
+                prepared: Prepared::new(s, vectors.clone(), DIM),
+            };
+            Ok((side(*a), side(*b)))
+        }
+        (DuelSide::Dataset(a), DuelSide::Dataset(b)) => {
+            let available = |path: &Path| -> io::Result<u64> {
+                let count = crate::dataset::read_dataset_meta(path)?.count;
+                Ok(count / 2)
+            };
+            // Both sides run the same op, so its cost does not matter here.
+            let pairs = profile_ops(cfg.profile, 1.0, available(a)?.min(available(b)?));
+            let pairs = args.max_ops.map_or(pairs, |max| pairs.min(max));
+            if pairs == 0 {
+                return Err(
+                    BenchError::too_small("duel: each dataset needs at least two vectors").into(),
+                );
+            }
+            let side = |path: &PathBuf| -> io::Result<Contender> {
+                let (vectors, dim) = load_pairs(path, pairs)?;
+                Ok(Contender {
+                    label: path.display().to_string(),
+                    substrate: args.substrate,
+                    dim,
+                    prepared: Prepared::new(args.substrate, vectors, dim),
+                })
+            };
+            Ok((side(a)?, side(b)?))
+        }
+        _ => Err(BenchError::invalid_args(format!(
+            "duel: --a {} and --b {} must both be substrates or both be dataset files",
+            args.a.label(),
+            args.b.label()
+        ))
+        .into()),
+    }
+}
+
+pub fn run(cfg: &BenchConfig, args: &DuelArgs) -> io::Result<Vec<Measurement>> {
+    let (a, b) = contenders(cfg, args)?;
+    let blocks = args
+        .blocks
+        .unwrap_or_else(|| blocks_for_profile(cfg))
+        .max(1);
+    let block_iters = (cfg.iters() / blocks).max(1);
+    let warmup = cfg.warmup_iters();
+
+    let mut out = Vec::new();
+    for op in OPS {
+        let m = measure_paired(
+            blocks,
+            block_iters,
+            warmup,
+            |i| a.prepared.run(op, i),
+            |i| b.prepared.run(op, i),
+        );
+        let s = m.stats();
+        let side = |c: &Contender, m: &crate::harness::Measured| {
+            json!({
+                "label": c.label,
+                "substrate": c.substrate.name(),
+                "kernel": c.prepared.kernel(op),
+                "dim": c.dim,
+                "pairs": c.prepared.len(),
+                "total_ns": m.total_ns,
+                "ns_per_iter": m.ns_per_iter,
+            })
+        };
+        out.push(Measurement {
+            name: measurements::duel::op(op.name()),
+            unit: "ns/iter".to_string(),
+            iters: m.b.iters,
+            warmup_iters: m.b.warmup_iters,
+            total_ns: m.b.total_ns,
+            ns_per_iter: m.b.ns_per_iter,
+            bytes_processed: None,
+            throughput_bytes_per_s: None,
+            extra: json!({
+                "a": side(&a, &m.a),
+                "b": side(&b, &m.b),
+                "blocks": s.blocks,
+                "block_iters": block_iters,
+                "b_faster_blocks": s.b_faster,
+                "b_slower_blocks": s.b_slower,
+                "tied_blocks": s.ties,
+                "median_delta_ratio": s.median_delta_ratio,
+                "mean_delta_ratio": s.mean_delta_ratio,
+                "sign_test_p": s.sign_test_p,
+            }),
+            tags: tags(&[("duel_a", a.label.as_str()), ("duel_b", b.label.as_str())]),
+        });
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dataset::{write_dataset_streaming, GenerateConfig};
+
+    fn cfg() -> BenchConfig {
+        BenchConfig {
+            profile: Profile::Quick,
+            seed: 5,
+        }
+    }
+
+    #[test]
+    fn test_side_parse() {
+        assert_eq!(
+            DuelSide::parse("packed"),
+            DuelSide::Substrate(DuelSubstrate::Packed)
+        );
+        assert_eq!(
+            DuelSide::parse("block-sparse"),
+            DuelSide::Substrate(DuelSubstrate::Blocksparse)
+        );
+        assert_eq!(
+            DuelSide::parse("./packed"),
+            DuelSide::Dataset(PathBuf::from("./packed"))
+        );
+    }
+
+    #[test]
+    fn test_substrate_duel_reports_both_sides() {
+        let args = DuelArgs {
+            a: DuelSide::Substrate(DuelSubstrate::Packed),
+            b: DuelSide::Substrate(DuelSubstrate::Bitsliced),
+            substrate: DuelSubstrate::Sparsevec,
+            blocks: Some(6),
+            max_ops: None,
+        };
+        let ms = run(&cfg(), &args).unwrap();
+        let names: Vec<&str> = ms.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, ["duel.bind", "duel.bundle", "duel.similarity"]);
+
+        let sim = &ms[2];
+        assert_eq!(sim.extra["a"]["kernel"], "dot");
+        assert_eq!(sim.extra["b"]["kernel"], "cosine");
+        assert_eq!(sim.extra["blocks"], 6);
+        assert_eq!(
+            sim.ns_per_iter,
+            sim.extra["b"]["ns_per_iter"].as_f64().unwrap()
+        );
+        let counted = ["b_faster_blocks", "b_slower_blocks", "tied_blocks"]
+            .iter()
+            .map(|k| sim.extra[k].as_u64().unwrap())
+            .sum::<u64>();
+        assert_eq!(counted, 6);
+        assert_eq!(sim.tags["duel_a"], "packed");
+    }
+
+    #[test]
+    fn test_dataset_duel_and_mixed_sides() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut paths = Vec::new();
+        for seed in [1, 2] {
+            let config = GenerateConfig {
+                count: 9,
+                dimension: 1_000,
+                seed,
+                sparsity: 10,
+                ..Default::default()
+            };
+            let path = dir.path().join(format!("d{seed}.embr"));
+            write_dataset_streaming(&path, &config, 4).unwrap();
+            paths.push(path);
+        }
+
+        let args = DuelArgs {
+            a: DuelSide::Dataset(paths[0].clone()),
+            b: DuelSide::Dataset(paths[1].clone()),
+            substrate: DuelSubstrate::Blocksparse,
+            blocks: Some(4),
+            max_ops: Some(3),
+        };
+        let ms = run(&cfg(), &args).unwrap();
+        assert_eq!(ms.len(), 3);
+        assert_eq!(ms[0].extra["a"]["pairs"], 3);
+        assert_eq!(ms[0].extra["b"]["substrate"], "blocksparse");
+        assert_eq!(ms[0].extra["b"]["dim"], 1_000);
+
+        let mixed = DuelArgs {
+            b: DuelSide::Substrate(DuelSubstrate::Packed),
+            ..args
+        };
+        let err = run(&cfg(), &mixed).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(matches!(
+            BenchError::of(&err),
+            Some(BenchError::InvalidArgs(_))
+        ));
+    }
+}
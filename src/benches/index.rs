@@ -0,0 +1,235 @@
+//! Raw `TernaryInvertedIndex` contract bench.
+//!
+//! The retrieval bench measures the index only through EmbrFS ingestion. This bench
+//! builds synthetic corpora directly from seeded `SparseVec::from_data` documents so the
+//! data structure itself has a tracked contract, independent of filesystem ingestion.
+//!
+//! Ingestion cost is split three ways per corpus size: `index.add` is the amortized cost
+//! of one incremental `add` (what streaming ingestion pays per document),
+//! `index.finalize` the one-shot finalize of an already-built index, and
+//! `index.rebuild` a batch build from empty through finalize. `index.build` is the
+//! add pass as a whole, kept for continuity with older reports.
+
+use crate::dataset::format_count;
+use crate::harness::{measure_fn_indexed, measure_fn_with_setup, BenchConfig, Profile};
+use crate::measurements;
+use crate::registry::{Bench, MeasurementSink};
+use crate::schema::{tags, Measurement};
+use embeddenator::retrieval::TernaryInvertedIndex;
+use embeddenator::SparseVec;
+use rand::Rng;
+use serde_json::json;
+use std::io;
+
+/// Corpus sizes for the full profile; quick uses only the first.
+pub const CORPUS_SIZES: [usize; 3] = [1_000, 5_000, 10_000];
+
+/// `k` values measured for `index.query_top_k`.
+pub const QUERY_KS: [usize; 2] = [10, 50];
+
+/// Bytes of seeded payload per synthetic document.
+const DOC_BYTES: usize = 128;
+
+/// Distinct query vectors cycled through by `index.query_top_k`.
+const QUERY_POOL: usize = 64;
+
+fn corpus_sizes(cfg: &BenchConfig) -> &'static [usize] {
+    match cfg.profile {
+        Profile::Quick => &CORPUS_SIZES[..1],
+        Profile::Full => &CORPUS_SIZES,
+    }
+}
+
+/// `n` seeded documents; the same `(seed, n)` always yields the same corpus.
+fn synthetic_docs(rng: &mut impl Rng, n: usize) -> Vec<SparseVec> {
+    (0..n)
+        .map(|_| {
+            let mut bytes = [0u8; DOC_BYTES];
+            rng.fill(&mut bytes[..]);
+            SparseVec::from_data(&bytes)
+        })
+        .collect()
+}
+
+fn build_index(docs: &[SparseVec]) -> TernaryInvertedIndex {
+    let mut index = TernaryInvertedIndex::new();
+    for (id, v) in docs.iter().enumerate() {
+        index.add(id, v);
+    }
+    index
+}
+
+/// [`run`] as the suite's `index` section.
+pub struct IndexBench;
+
+impl Bench for IndexBench {
+    fn name(&self) -> &str {
+        "index"
+    }
+
+    fn run(&self, cfg: &BenchConfig, sink: &mut dyn MeasurementSink) -> io::Result<()> {
+        sink.push_all(run(cfg))
+    }
+}
+
+pub fn run(cfg: &BenchConfig) -> Vec<Measurement> {
+    run_sizes(cfg, corpus_sizes(cfg))
+}
+
+fn run_sizes(cfg: &BenchConfig, sizes: &[usize]) -> Vec<Measurement> {
+    // Whole-corpus passes are expensive; keep their iteration counts small.
+    let (build_iters, build_warmup) = match cfg.profile {
+        Profile::Quick => (3, 1),
+        Profile::Full => (10, 2),
+    };
+
+    let mut out = Vec::new();
+    for &n in sizes {
+        let mut rng = cfg.rng();
+        let docs = synthetic_docs(&mut rng, n);
+        let queries = synthetic_docs(&mut rng, QUERY_POOL);
+        let corpus = format_count(n as u64);
+
+        {
+            // No setup, but the built index is dropped off the clock.
+            let m =
+                measure_fn_with_setup(build_iters, build_warmup, || (), |()| build_index(&docs));
+            out.push(Measurement {
+                name: measurements::index::BUILD.to_string(),
+                unit: "ns/iter".to_string(),
+                iters: m.iters,
+                warmup_iters: m.warmup_iters,
+                total_ns: m.total_ns,
+                ns_per_iter: m.ns_per_iter,
+                bytes_processed: None,
+                throughput_bytes_per_s: None,
+                extra: json!({"corpus_size": n, "doc_bytes": DOC_BYTES, "docs_per_s": n as f64 / (m.ns_per_iter / 1e9).max(1e-12)}),
+                tags: tags(&[("corpus", corpus.as_str())]),
+            });
+            // The same pass, per document: each iteration is `n` adds.
+            let adds = m.iters * n as u64;
+            out.push(Measurement {
+                name: measurements::index::ADD.to_string(),
+                unit: "ns/add".to_string(),
+                iters: adds,
+                warmup_iters: m.warmup_iters * n as u64,
+                total_ns: m.total_ns,
+                ns_per_iter: m.total_ns as f64 / adds.max(1) as f64,
+                bytes_processed: None,
+                throughput_bytes_per_s: None,
+                extra: json!({"corpus_size": n, "doc_bytes": DOC_BYTES, "adds_per_build": n}),
+                tags: tags(&[("corpus", corpus.as_str())]),
+            });
+        }
+        {
+            // Finalize works in place, so every iteration gets a freshly built,
+            // unfinalized index from setup.
+            let m = measure_fn_with_setup(
+                build_iters,
+                build_warmup,
+                || build_index(&docs),
+                |mut index| {
+                    index.finalize();
+                    index
+                },
+            );
+            out.push(Measurement {
+                name: measurements::index::FINALIZE.to_string(),
+                unit: "ns/iter".to_string(),
+                iters: m.iters,
+                warmup_iters: m.warmup_iters,
+                total_ns: m.total_ns,
+                ns_per_iter: m.ns_per_iter,
+                bytes_processed: None,
+                throughput_bytes_per_s: None,
+                extra: json!({"corpus_size": n, "setup_excluded": true}),
+                tags: tags(&[("corpus", corpus.as_str())]),
+            });
+        }
+        {
+            let m = measure_fn_with_setup(
+                build_iters,
+                build_warmup,
+                || (),
+                |()| {
+                    let mut index = build_index(&docs);
+                    index.finalize();
+                    index
+                },
+            );
+            out.push(Measurement {
+                name: measurements::index::REBUILD.to_string(),
+                unit: "ns/iter".to_string(),
+                iters: m.iters,
+                warmup_iters: m.warmup_iters,
+                total_ns: m.total_ns,
+                ns_per_iter: m.ns_per_iter,
+                bytes_processed: None,
+                throughput_bytes_per_s: None,
+                extra: json!({"corpus_size": n, "doc_bytes": DOC_BYTES}),
+                tags: tags(&[("corpus", corpus.as_str())]),
+            });
+        }
+
+        let mut index = build_index(&docs);
+        index.finalize();
+        for k in QUERY_KS {
+            let m = measure_fn_indexed(cfg.iters(), cfg.warmup_iters(), |i| {
+                index.query_top_k(&queries[i as usize % QUERY_POOL], k)
+            });
+            out.push(Measurement {
+                name: measurements::index::QUERY_TOP_K.to_string(),
+                unit: "ns/iter".to_string(),
+                iters: m.iters,
+                warmup_iters: m.warmup_iters,
+                total_ns: m.total_ns,
+                ns_per_iter: m.ns_per_iter,
+                bytes_processed: None,
+                throughput_bytes_per_s: None,
+                extra: json!({"corpus_size": n, "k": k, "query_pool": QUERY_POOL}),
+                tags: tags(&[("corpus", corpus.clone()), ("k", k.to_string())]),
+            });
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_measurements_per_size() {
+        let cfg = BenchConfig {
+            profile: Profile::Quick,
+            seed: 5,
+        };
+        let ms = run_sizes(&cfg, &[10, 20]);
+        let names: Vec<&str> = ms.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "index.build",
+                "index.add",
+                "index.finalize",
+                "index.rebuild",
+                "index.query_top_k",
+                "index.query_top_k",
+            ]
+            .repeat(2)
+        );
+        assert_eq!(ms[0].tags["corpus"], "10");
+        assert_eq!(ms[6].extra["corpus_size"], 20);
+        assert_eq!(ms[5].tags["k"], "50");
+        for (size, chunk) in [10u64, 20].iter().zip(ms.chunks(6)) {
+            for m in &chunk[..4] {
+                assert_eq!(m.tags["corpus"], size.to_string(), "{}", m.name);
+            }
+            let (build, add) = (&chunk[0], &chunk[1]);
+            assert_eq!(add.unit, "ns/add");
+            assert_eq!(add.iters, build.iters * size);
+            assert_eq!(add.total_ns, build.total_ns);
+        }
+        assert_eq!(corpus_sizes(&cfg), &[1_000]);
+    }
+}
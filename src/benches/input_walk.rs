@@ -0,0 +1,460 @@
+//! Input file collection shared by the encode and retrieval benches.
+//!
+//! Both benches take "an input directory", and both must mean the same file set by it:
+//! every regular file under the root, in relative-path order, after the same filters.
+//! Hidden entries (a name starting with `.`) are collected unless asked to be left out,
+//! symlinks are only followed on request, and `ignore` globs prune by path. Entries that cannot be
+//! read (permission denied, symlink loops) are skipped and counted with a warning
+//! rather than failing the run; any other walk error still fails.
+//!
+//! Ignore globs match the `/`-separated path relative to the root. `*` matches within
+//! one path component, `**` across components and `?` a single character. A glob
+//! without a `/` matches any single component, so `*.tmp` or `target` prune at any
+//! depth.
+//!
+//! Relative paths are lossless: a name that is not valid UTF-8 keeps its bytes
+//! percent-encoded rather than collapsing to `U+FFFD` (see [`logical_component`]), so
+//! two files never share one. They are `/`-separated on every platform, and
+//! [`logical_path`] puts a prefix in front of one, so a corpus walked on Windows and on
+//! unix lands on the same logical paths.
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Which files under an input root are collected.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WalkOptions {
+    pub follow_symlinks: bool,
+    /// Leave out entries whose name starts with `.`.
+    pub exclude_hidden: bool,
+    /// Files larger than this many bytes are skipped.
+    pub max_file_size: Option<u64>,
+    pub ignore: Vec<String>,
+}
+
+/// One collected file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InputFile {
+    pub path: PathBuf,
+    /// `/`-separated path relative to the root (the file name for a file root), each
+    /// component in [`logical_component`] form.
+    pub rel: String,
+    pub len: u64,
+}
+
+/// Entries left out of a walk, by reason.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct Skipped {
+    pub hidden: u64,
+    pub ignored: u64,
+    pub too_large: u64,
+    /// Symlinks not followed.
+    pub symlinks: u64,
+    /// Permission denied or a symlink loop; each also printed as a warning.
+    pub unreadable: u64,
+}
+
+/// The files under one input root, sorted by relative path.
+#[derive(Clone, Debug, Default)]
+pub struct InputWalk {
+    pub files: Vec<InputFile>,
+    pub total_bytes: u64,
+    pub skipped: Skipped,
+}
+
+impl InputWalk {
+    /// Combine the walks of several roots, in order.
+    pub fn merge(walks: &[InputWalk]) -> InputWalk {
+        let mut out = InputWalk::default();
+        for w in walks {
+            out.files.extend(w.files.iter().cloned());
+            out.total_bytes += w.total_bytes;
+            let (s, o) = (&w.skipped, &mut out.skipped);
+            o.hidden += s.hidden;
+            o.ignored += s.ignored;
+            o.too_large += s.too_large;
+            o.symlinks += s.symlinks;
+            o.unreadable += s.unreadable;
+        }
+        out
+    }
+
+    /// `input_files`, `input_bytes` and `input_skipped`, the same keys in every bench.
+    pub fn extra(&self, extra: &mut serde_json::Value) {
+        extra["input_files"] = json!(self.files.len());
+        extra["input_bytes"] = json!(self.total_bytes);
+        extra["input_skipped"] = json!(self.skipped);
+    }
+}
+
+/// Whether `glob` matches `text` (see the module docs for the syntax).
+fn glob_match(glob: &[u8], text: &[u8]) -> bool {
+    match glob {
+        [] => text.is_empty(),
+        [b'*', b'*', rest @ ..] => {
+            let rest = rest.strip_prefix(b"/").unwrap_or(rest);
+            (0..=text.len()).any(|i| glob_match(rest, &text[i..]))
+        }
+        [b'*', rest @ ..] => {
+            let segment = text.iter().position(|&c| c == b'/').unwrap_or(text.len());
+            (0..=segment).any(|i| glob_match(rest, &text[i..]))
+        }
+        [b'?', rest @ ..] => matches!(text, [c, tail @ ..] if *c != b'/' && glob_match(rest, tail)),
+        [g, rest @ ..] => matches!(text, [c, tail @ ..] if c == g && glob_match(rest, tail)),
+    }
+}
+
+/// Whether any of `globs` matches the relative path `rel`.
+fn ignored(globs: &[String], rel: &str) -> bool {
+    globs.iter().any(|g| {
+        if g.contains('/') {
+            glob_match(g.as_bytes(), rel.as_bytes())
+        } else {
+            rel.split('/')
+                .any(|c| glob_match(g.as_bytes(), c.as_bytes()))
+        }
+    })
+}
+
+/// `name` as one component of a logical path. Valid UTF-8 is kept as is, except that
+/// `%` and `\` are percent-encoded, as is every byte that is not valid UTF-8: distinct
+/// names stay distinct, and a component never reads as a separator on any platform.
+pub fn logical_component(name: &OsStr) -> String {
+    let mut out = String::new();
+    for chunk in name.as_encoded_bytes().utf8_chunks() {
+        for c in chunk.valid().chars() {
+            match c {
+                '%' | '\\' => out.push_str(&format!("%{:02X}", c as u32)),
+                c => out.push(c),
+            }
+        }
+        for b in chunk.invalid() {
+            out.push_str(&format!("%{b:02X}"));
+        }
+    }
+    out
+}
+
+/// `rel`, a relative path from [`walk`], under `prefix`: `/`-separated, with no empty or
+/// `.` components, and absolute if the prefix is. On Windows the prefix is split at `\`
+/// as well, so `--prefix docs\2024` names the same paths there as `docs/2024` does on
+/// unix. Elsewhere `\` is an ordinary character in a name, and each prefix component
+/// is encoded like a walked one ([`logical_component`]), so it reads `%5C` there too.
+pub fn logical_path(prefix: &str, rel: &str) -> String {
+    let separators: &[char] = if cfg!(windows) { &['/', '\\'] } else { &['/'] };
+    let root = if prefix.starts_with(separators) {
+        "/"
+    } else {
+        ""
+    };
+    let components: Vec<String> = prefix
+        .split(separators)
+        .map(|c| logical_component(OsStr::new(c)))
+        .chain(rel.split('/').map(str::to_string))
+        .filter(|c| !c.is_empty() && c != ".")
+        .collect();
+    format!("{root}{}", components.join("/"))
+}
+
+fn is_hidden(name: &OsStr) -> bool {
+    name.to_string_lossy().starts_with('.')
+}
+
+fn warn_unreadable(skipped: &mut Skipped, what: &dyn std::fmt::Display) {
+    eprintln!("warning: skipping input {what}");
+    skipped.unreadable += 1;
+}
+
+/// Collect the files under `root` (or `root` itself, if it is a file).
+pub fn walk(root: &Path, opts: &WalkOptions) -> io::Result<InputWalk> {
+    let mut out = InputWalk::default();
+    let rel_of = |path: &Path| -> String {
+        match path.strip_prefix(root) {
+            Ok(rel) if !rel.as_os_str().is_empty() => rel
+                .components()
+                .map(|c| logical_component(c.as_os_str()))
+                .collect::<Vec<_>>()
+                .join("/"),
+            _ => path
+                .file_name()
+                .map_or_else(|| "input.bin".to_string(), logical_component),
+        }
+    };
+
+    let walker = walkdir::WalkDir::new(root).follow_links(opts.follow_symlinks);
+    let mut it = walker.into_iter();
+    while let Some(entry) = it.next() {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) if e.loop_ancestor().is_some() => {
+                warn_unreadable(&mut out.skipped, &e);
+                continue;
+            }
+            Err(e)
+                if e.io_error()
+                    .is_some_and(|io| io.kind() == io::ErrorKind::PermissionDenied) =>
+            {
+                warn_unreadable(&mut out.skipped, &e);
+                continue;
+            }
+            Err(e) => return Err(e.into()),
+        };
+        // The root is walked whatever its name.
+        if entry.depth() > 0 {
+            let rel = rel_of(entry.path());
+            let prune = |it: &mut walkdir::IntoIter| {
+                if entry.file_type().is_dir() {
+                    it.skip_current_dir();
+                }
+            };
+            if opts.exclude_hidden && is_hidden(entry.file_name()) {
+                out.skipped.hidden += 1;
+                prune(&mut it);
+                continue;
+            }
+            if ignored(&opts.ignore, &rel) {
+                out.skipped.ignored += 1;
+                prune(&mut it);
+                continue;
+            }
+        }
+        if entry.file_type().is_symlink() {
+            out.skipped.symlinks += 1;
+            continue;
+        }
+        if !entry.file_type().is_file() {
+            continue;
+        }
+
+        let len = entry.metadata().map_err(io::Error::from)?.len();
+        if opts.max_file_size.is_some_and(|max| len > max) {
+            out.skipped.too_large += 1;
+            continue;
+        }
+        // Fail here rather than halfway through a timed ingest.
+        if let Err(e) = File::open(entry.path()) {
+            if e.kind() != io::ErrorKind::PermissionDenied {
+                return Err(e);
+            }
+            warn_unreadable(
+                &mut out.skipped,
+                &format_args!("{}: {e}", entry.path().display()),
+            );
+            continue;
+        }
+        out.total_bytes += len;
+        out.files.push(InputFile {
+            path: entry.path().to_path_buf(),
+            rel: rel_of(entry.path()),
+            len,
+        });
+    }
+    out.files.sort_by(|a, b| a.rel.cmp(&b.rel));
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn rels(walk: &InputWalk) -> Vec<&str> {
+        walk.files.iter().map(|f| f.rel.as_str()).collect()
+    }
+
+    fn tree() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for (rel, len) in [
+            ("b.txt", 3),
+            ("a/one.bin", 10),
+            ("a/big.bin", 5000),
+            ("a/scratch.tmp", 1),
+            (".hidden/x.bin", 4),
+            (".dotfile", 2),
+            ("target/out.bin", 7),
+        ] {
+            let p = dir.path().join(rel);
+            fs::create_dir_all(p.parent().unwrap()).unwrap();
+            fs::write(p, vec![1u8; len]).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn test_walk_filters() {
+        let dir = tree();
+        let all = walk(dir.path(), &WalkOptions::default()).unwrap();
+        assert_eq!(
+            rels(&all),
+            [
+                ".dotfile",
+                ".hidden/x.bin",
+                "a/big.bin",
+                "a/one.bin",
+                "a/scratch.tmp",
+                "b.txt",
+                "target/out.bin"
+            ]
+        );
+        assert_eq!(all.total_bytes, 2 + 4 + 5000 + 10 + 1 + 3 + 7);
+        assert_eq!(all.skipped, Skipped::default());
+
+        let visible = WalkOptions {
+            exclude_hidden: true,
+            ..Default::default()
+        };
+        let visible = walk(dir.path(), &visible).unwrap();
+        assert_eq!(
+            rels(&visible),
+            [
+                "a/big.bin",
+                "a/one.bin",
+                "a/scratch.tmp",
+                "b.txt",
+                "target/out.bin"
+            ]
+        );
+        assert_eq!(visible.skipped.hidden, 2);
+
+        let opts = WalkOptions {
+            max_file_size: Some(100),
+            ignore: vec!["*.tmp".to_string(), "target".to_string()],
+            ..Default::default()
+        };
+        let filtered = walk(dir.path(), &opts).unwrap();
+        assert_eq!(
+            rels(&filtered),
+            [".dotfile", ".hidden/x.bin", "a/one.bin", "b.txt"]
+        );
+        assert_eq!(
+            filtered.skipped,
+            Skipped {
+                ignored: 2,
+                too_large: 1,
+                ..Default::default()
+            }
+        );
+
+        let file = walk(&dir.path().join("b.txt"), &WalkOptions::default()).unwrap();
+        assert_eq!(rels(&file), ["b.txt"]);
+
+        let mut extra = json!({});
+        filtered.extra(&mut extra);
+        assert_eq!(
+            (
+                extra["input_files"].clone(),
+                extra["input_skipped"]["ignored"].clone()
+            ),
+            (json!(4), json!(2))
+        );
+    }
+
+    #[test]
+    fn test_logical_component() {
+        let c = |s: &str| logical_component(OsStr::new(s));
+        assert_eq!(c("with space ünï.bin"), "with space ünï.bin");
+        assert_eq!(c("100%"), "100%25");
+        assert_eq!(c("a\\b"), "a%5Cb");
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+            let raw = |b: &[u8]| logical_component(OsStr::from_bytes(b));
+            assert_eq!(raw(b"caf\xe9"), "caf%E9");
+            // Both used to read as `caf\u{FFFD}`.
+            assert_ne!(raw(b"caf\xe9"), raw(b"caf\xe8"));
+        }
+    }
+
+    #[test]
+    fn test_logical_path() {
+        assert_eq!(logical_path("docs", "a/b.txt"), "docs/a/b.txt");
+        assert_eq!(logical_path("./docs//x/", "f"), "docs/x/f");
+        assert_eq!(logical_path("", "f"), "f");
+        assert_eq!(logical_path("/data", "a/b.txt"), "/data/a/b.txt");
+        assert_eq!(logical_path("//data/", "f"), "/data/f");
+        // `\` separates only where it is the native separator; elsewhere the prefix
+        // encodes it (and `%`) the way a walked name does.
+        let native = if cfg!(windows) {
+            "docs/2024/a%5Cb"
+        } else {
+            "docs%5C2024/a%5Cb"
+        };
+        assert_eq!(logical_path("docs\\2024", "a%5Cb"), native);
+        assert_eq!(logical_path("100%", "f"), "100%25/f");
+
+        // Walked paths join with `/` whatever the platform's separator.
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("a").join("b")).unwrap();
+        std::fs::write(dir.path().join("a").join("b").join("f.bin"), b"x").unwrap();
+        let walked = walk(dir.path(), &WalkOptions::default()).unwrap();
+        assert_eq!(logical_path("in", &walked.files[0].rel), "in/a/b/f.bin");
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match(b"*.bin", b"x.bin"));
+        assert!(!glob_match(b"*.bin", b"a/x.bin"));
+        assert!(glob_match(b"a/*.bin", b"a/x.bin"));
+        assert!(glob_match(b"**/x.bin", b"a/b/x.bin"));
+        assert!(glob_match(b"**/x.bin", b"x.bin"));
+        assert!(glob_match(b"a/**", b"a/b/c"));
+        assert!(glob_match(b"?.bin", b"x.bin") && !glob_match(b"?.bin", b"xy.bin"));
+        assert!(ignored(&["*.tmp".to_string()], "deep/er/f.tmp"));
+        assert!(!ignored(&["a/*.tmp".to_string()], "b/a/f.tmp"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinks_followed_only_on_request() {
+        let dir = tree();
+        let outside = tempfile::tempdir().unwrap();
+        fs::write(outside.path().join("linked.bin"), [9u8; 6]).unwrap();
+        std::os::unix::fs::symlink(
+            outside.path().join("linked.bin"),
+            dir.path().join("link.bin"),
+        )
+        .unwrap();
+        // A loop back to the root.
+        std::os::unix::fs::symlink(dir.path(), dir.path().join("a/loop")).unwrap();
+
+        let plain = walk(dir.path(), &WalkOptions::default()).unwrap();
+        assert!(!rels(&plain).contains(&"link.bin"));
+        assert_eq!(plain.skipped.symlinks, 2);
+
+        let opts = WalkOptions {
+            follow_symlinks: true,
+            ..Default::default()
+        };
+        let followed = walk(dir.path(), &opts).unwrap();
+        assert!(rels(&followed).contains(&"link.bin"));
+        assert_eq!(followed.skipped.unreadable, 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_permission_denied_skipped() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tree();
+        let locked = dir.path().join("a");
+        let secret = dir.path().join("b.txt");
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).unwrap();
+        fs::set_permissions(&secret, fs::Permissions::from_mode(0o000)).unwrap();
+        // Permissions don't bind a privileged user; there is nothing to skip then.
+        let enforced = fs::read_dir(&locked).is_err();
+
+        let w = walk(dir.path(), &WalkOptions::default()).unwrap();
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+        fs::set_permissions(&secret, fs::Permissions::from_mode(0o644)).unwrap();
+        if enforced {
+            assert_eq!(rels(&w), ["target/out.bin"]);
+            assert_eq!(w.skipped.unreadable, 2);
+        } else {
+            assert_eq!(w.skipped.unreadable, 0);
+        }
+    }
+}
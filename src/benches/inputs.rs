@@ -0,0 +1,356 @@
+//! Input classes for the fixed-input VSA microbenches (`vsa --input-class`).
+//!
+//! The default inputs are what `SparseVec::encode_data` produces for short payloads:
+//! average-case vectors whose indices land all over the dimension. Each substrate has
+//! layouts it handles much better or much worse than that, and these classes construct
+//! them directly from a seed:
+//!
+//! - `clustered`: every index in one contiguous run, so block- and word-based
+//!   substrates touch as few blocks as possible.
+//! - `spread`: one index per 64-trit block, the worst case for block-sparse (every
+//!   stored block holds a single trit).
+//! - `dense`: every trit non-zero, the best case for packed and the worst for index
+//!   lists.
+//! - `single-block`: a single 64-trit block completely filled, i.e. one bitsliced word
+//!   per sign plane.
+//!
+//! [`density_vectors`] builds uniformly random vectors at a chosen density instead, for
+//! the `--density-sweep` axis, and [`block_regime_vectors`] sets of vectors that share
+//! their blocks or keep to blocks of their own, for the n-way bundles.
+
+use crate::harness::BenchConfig;
+use clap::ValueEnum;
+use embeddenator::SparseVec;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde_json::json;
+
+/// Trits per block-sparse block (and per bitsliced word).
+pub(crate) const BLOCK_TRITS: usize = 64;
+
+#[derive(Clone, Copy, Debug, Default, ValueEnum, PartialEq, Eq)]
+pub enum InputClass {
+    /// Encoded random payloads (the historical inputs).
+    #[default]
+    Random,
+    /// All indices in one contiguous run at a seeded offset.
+    Clustered,
+    /// One index per block, blocks evenly spaced.
+    Spread,
+    /// Every trit non-zero.
+    Dense,
+    /// One block, every trit in it non-zero.
+    SingleBlock,
+}
+
+impl InputClass {
+    pub fn label(self) -> &'static str {
+        match self {
+            InputClass::Random => "random",
+            InputClass::Clustered => "clustered",
+            InputClass::Spread => "spread",
+            InputClass::Dense => "dense",
+            InputClass::SingleBlock => "single-block",
+        }
+    }
+
+    /// Indices per sign for this class at `dim`: the usual 1% of the dimension, capped
+    /// by what the layout can hold (one per block for `spread`, one block for
+    /// `single-block`), or half the dimension for `dense`.
+    pub fn sparsity(self, dim: usize) -> usize {
+        let usual = dim / 100;
+        match self {
+            InputClass::Random | InputClass::Clustered => usual,
+            InputClass::Spread => usual.min(dim.div_ceil(BLOCK_TRITS) / 2),
+            InputClass::Dense => dim / 2,
+            InputClass::SingleBlock => BLOCK_TRITS.min(dim) / 2,
+        }
+    }
+
+    /// One vector of this class (`Random` draws its indices uniformly).
+    fn vector(self, rng: &mut ChaCha8Rng, dim: usize) -> SparseVec {
+        let sparsity = self.sparsity(dim);
+        let n = sparsity * 2;
+        let indices: Vec<usize> = match self {
+            InputClass::Random => rand::seq::index::sample(rng, dim, n).into_vec(),
+            // A run of `n` at a seeded start.
+            InputClass::Clustered => {
+                let start = rng.gen_range(0..=dim - n);
+                (start..start + n).collect()
+            }
+            // Block `i * stride`, at a seeded offset within the block (clamped to the
+            // last, possibly partial, block).
+            InputClass::Spread => {
+                let blocks = dim.div_ceil(BLOCK_TRITS);
+                let stride = blocks / n.max(1);
+                (0..n)
+                    .map(|i| {
+                        let block = i * stride * BLOCK_TRITS;
+                        let width = BLOCK_TRITS.min(dim - block);
+                        block + rng.gen_range(0..width)
+                    })
+                    .collect()
+            }
+            InputClass::Dense => (0..n).collect(),
+            // A seeded whole block.
+            InputClass::SingleBlock => {
+                let block = rng.gen_range(0..dim / BLOCK_TRITS.min(dim)) * BLOCK_TRITS;
+                (block..block + n).collect()
+            }
+        };
+        signed(rng, indices)
+    }
+
+    /// `n` seeded vectors of this class. `salt` separates independent input sets drawn
+    /// from the same seed.
+    pub fn vectors(self, cfg: &BenchConfig, dim: usize, n: usize, salt: u64) -> Vec<SparseVec> {
+        let mut rng = ChaCha8Rng::seed_from_u64(cfg.seed ^ salt);
+        (0..n).map(|_| self.vector(&mut rng, dim)).collect()
+    }
+}
+
+/// A vector non-zero at `indices`: which of them are +1 says nothing about layout, so
+/// they are split in half at random.
+fn signed(rng: &mut ChaCha8Rng, mut indices: Vec<usize>) -> SparseVec {
+    indices.shuffle(rng);
+    let half = indices.len() / 2;
+    let mut pos = indices[..half].to_vec();
+    let mut neg = indices[half..].to_vec();
+    pos.sort_unstable();
+    neg.sort_unstable();
+    SparseVec { pos, neg }
+}
+
+/// `n` seeded vectors with `density * dim` non-zero trits (rounded to an even count, at
+/// least two) at uniformly random indices. `salt` is as for [`InputClass::vectors`].
+pub fn density_vectors(
+    cfg: &BenchConfig,
+    dim: usize,
+    density: f64,
+    n: usize,
+    salt: u64,
+) -> Vec<SparseVec> {
+    let nnz = ((density * dim as f64 / 2.0).round() as usize).max(1) * 2;
+    let nnz = nnz.min(dim - dim % 2);
+    let mut rng = ChaCha8Rng::seed_from_u64(cfg.seed ^ salt);
+    (0..n)
+        .map(|_| {
+            let indices = rand::seq::index::sample(&mut rng, dim, nnz).into_vec();
+            signed(&mut rng, indices)
+        })
+        .collect()
+}
+
+/// How the inputs of an n-way bundle share blocks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlockRegime {
+    /// Every vector in the same blocks: the bundle has no more blocks than one input.
+    Shared,
+    /// Every vector in blocks of its own: the bundle has all of them.
+    Disjoint,
+}
+
+impl BlockRegime {
+    pub const ALL: [BlockRegime; 2] = [BlockRegime::Shared, BlockRegime::Disjoint];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            BlockRegime::Shared => "shared_blocks",
+            BlockRegime::Disjoint => "disjoint_blocks",
+        }
+    }
+}
+
+/// `n` seeded vectors laid out per `regime`. The whole blocks of `dim` are split `n`
+/// ways, and every vector touches exactly one share's worth of blocks: the same share
+/// (at a seeded offset) for [`BlockRegime::Shared`], share `i` for vector `i` for
+/// [`BlockRegime::Disjoint`]. Both regimes give the same number of non-zeros per vector:
+/// the usual 1% of the dimension per sign, capped at half the trits of a share.
+pub fn block_regime_vectors(
+    cfg: &BenchConfig,
+    dim: usize,
+    n: usize,
+    regime: BlockRegime,
+    salt: u64,
+) -> Vec<SparseVec> {
+    let blocks = dim / BLOCK_TRITS;
+    assert!(
+        (1..=blocks).contains(&n),
+        "{n} vectors cannot have blocks of their own in {blocks} blocks"
+    );
+    let share = blocks / n;
+    let trits = share * BLOCK_TRITS;
+    let nnz = (dim / 100 * 2).min(trits / 2).max(share);
+    let nnz = nnz + nnz % 2;
+    let mut rng = ChaCha8Rng::seed_from_u64(cfg.seed ^ salt);
+    let shared_start = rng.gen_range(0..=blocks - share);
+    (0..n)
+        .map(|i| {
+            let base = match regime {
+                BlockRegime::Shared => shared_start,
+                BlockRegime::Disjoint => i * share,
+            } * BLOCK_TRITS;
+            // One index in every block of the share, the rest anywhere in it.
+            let mut offsets: Vec<usize> = (0..share)
+                .map(|b| b * BLOCK_TRITS + rng.gen_range(0..BLOCK_TRITS))
+                .collect();
+            let mut rest: Vec<usize> = (0..trits).filter(|o| !offsets.contains(o)).collect();
+            rest.shuffle(&mut rng);
+            offsets.extend(rest.into_iter().take(nnz - share));
+            signed(&mut rng, offsets.into_iter().map(|o| base + o).collect())
+        })
+        .collect()
+}
+
+/// Number of distinct blocks `v` has a non-zero trit in.
+pub(crate) fn blocks_touched(v: &SparseVec) -> usize {
+    let mut blocks: Vec<usize> = v
+        .pos
+        .iter()
+        .chain(&v.neg)
+        .map(|i| i / BLOCK_TRITS)
+        .collect();
+    blocks.sort_unstable();
+    blocks.dedup();
+    blocks.len()
+}
+
+/// Layout of `vectors`: mean non-zeros, mean blocks touched, and mean non-zeros per
+/// touched block.
+pub fn layout_stats<'a>(vectors: impl IntoIterator<Item = &'a SparseVec>) -> serde_json::Value {
+    let (mut count, mut nnz, mut blocks) = (0usize, 0usize, 0usize);
+    for v in vectors {
+        count += 1;
+        nnz += v.pos.len() + v.neg.len();
+        blocks += blocks_touched(v);
+    }
+    let mean = |total: usize| total as f64 / count.max(1) as f64;
+    json!({
+        "vectors": count,
+        "nnz_mean": mean(nnz),
+        "blocks_mean": mean(blocks),
+        "block_occupancy_mean": if blocks > 0 { nnz as f64 / blocks as f64 } else { 0.0 },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::harness::Profile;
+    use embeddenator::DIM;
+
+    #[test]
+    fn test_class_layouts() {
+        let cfg = BenchConfig {
+            profile: Profile::Quick,
+            seed: 5,
+        };
+        let blocks = DIM.div_ceil(BLOCK_TRITS);
+        for class in [
+            InputClass::Random,
+            InputClass::Clustered,
+            InputClass::Spread,
+            InputClass::Dense,
+            InputClass::SingleBlock,
+        ] {
+            let vs = class.vectors(&cfg, DIM, 4, 0);
+            let sparsity = class.sparsity(DIM);
+            for v in &vs {
+                assert_eq!(
+                    (v.pos.len(), v.neg.len()),
+                    (sparsity, sparsity),
+                    "{class:?}"
+                );
+                let mut all: Vec<usize> = v.pos.iter().chain(&v.neg).copied().collect();
+                all.sort_unstable();
+                all.dedup();
+                assert_eq!(all.len(), sparsity * 2, "{class:?}: overlapping signs");
+                assert!(all.iter().all(|&i| i < DIM), "{class:?}");
+
+                let touched = blocks_touched(v);
+                match class {
+                    InputClass::Spread => assert_eq!(touched, sparsity * 2),
+                    InputClass::Clustered => {
+                        assert!(touched <= (sparsity * 2).div_ceil(BLOCK_TRITS) + 1)
+                    }
+                    InputClass::Dense => assert_eq!(touched, blocks),
+                    InputClass::SingleBlock => assert_eq!(touched, 1),
+                    InputClass::Random => {}
+                }
+            }
+            assert_eq!(class.vectors(&cfg, DIM, 4, 0)[3].pos, vs[3].pos);
+            let other = &class.vectors(&cfg, DIM, 4, 1)[0];
+            assert_ne!(
+                (&other.pos, &other.neg),
+                (&vs[0].pos, &vs[0].neg),
+                "{class:?}"
+            );
+        }
+
+        let stats = layout_stats(&InputClass::SingleBlock.vectors(&cfg, DIM, 3, 0));
+        assert_eq!(stats["blocks_mean"], 1.0);
+        assert_eq!(stats["block_occupancy_mean"], BLOCK_TRITS as f64);
+    }
+
+    #[test]
+    fn test_density_vectors() {
+        let cfg = BenchConfig {
+            profile: Profile::Quick,
+            seed: 5,
+        };
+        for (density, nnz) in [(0.001, 10), (0.2, 2000), (0.0, 2), (1.0, 10_000)] {
+            let vs = density_vectors(&cfg, 10_000, density, 3, 0);
+            for v in &vs {
+                assert_eq!((v.pos.len(), v.neg.len()), (nnz / 2, nnz / 2), "{density}");
+                assert!(v.pos.iter().all(|i| v.neg.binary_search(i).is_err()));
+            }
+            assert_eq!(
+                density_vectors(&cfg, 10_000, density, 3, 0)[2].neg,
+                vs[2].neg
+            );
+        }
+    }
+
+    #[test]
+    fn test_block_regimes_overlap_as_intended() {
+        let cfg = BenchConfig {
+            profile: Profile::Quick,
+            seed: 5,
+        };
+        let block_set = |v: &SparseVec| {
+            let mut b: Vec<usize> = v
+                .pos
+                .iter()
+                .chain(&v.neg)
+                .map(|i| i / BLOCK_TRITS)
+                .collect();
+            b.sort_unstable();
+            b.dedup();
+            b
+        };
+        for n in [8, 64] {
+            let share = DIM / BLOCK_TRITS / n;
+            let shared = block_regime_vectors(&cfg, DIM, n, BlockRegime::Shared, 0);
+            let disjoint = block_regime_vectors(&cfg, DIM, n, BlockRegime::Disjoint, 0);
+            assert_eq!((shared.len(), disjoint.len()), (n, n));
+            let nnz = shared[0].pos.len() + shared[0].neg.len();
+            for v in shared.iter().chain(&disjoint) {
+                assert_eq!(v.pos.len() + v.neg.len(), nnz, "n={n}");
+                assert_eq!(blocks_touched(v), share, "n={n}");
+                assert!(v.pos.iter().all(|i| v.neg.binary_search(i).is_err()));
+            }
+            // Shared: one block set for all; disjoint: no block in two vectors.
+            assert!(shared.iter().all(|v| block_set(v) == block_set(&shared[0])));
+            let mut union: Vec<usize> = disjoint.iter().flat_map(block_set).collect();
+            union.sort_unstable();
+            union.dedup();
+            assert_eq!(union.len(), n * share, "n={n}");
+
+            assert_eq!(
+                block_regime_vectors(&cfg, DIM, n, BlockRegime::Shared, 0)[1].pos,
+                shared[1].pos
+            );
+        }
+    }
+}
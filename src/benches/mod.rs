@@ -1,3 +1,15 @@
+pub mod bundle_semantics;
+pub mod chunking;
+pub mod corpus;
+pub mod dataset_formats;
+pub mod dataset_io;
+pub mod duel;
 pub mod encode;
+pub mod index;
+pub mod input_walk;
+pub mod inputs;
+pub mod query_log;
 pub mod retrieval;
+pub mod serialization;
+pub mod soak;
 pub mod vsa;
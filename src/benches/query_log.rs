@@ -0,0 +1,187 @@
+//! Recorded query logs for `retrieval --query-file`.
+//!
+//! A query log is JSONL with one query per line, replayed in file order:
+//!
+//! - `"aGVsbG8="`: a base64 payload, encoded with `SparseVec::encode_data`.
+//! - `{"data": "aGVsbG8=", "path": "docs/a.txt"}`: the same, encoded under a logical
+//!   path, as a corpus file with that path would be.
+//! - `{"pos": [3, 17], "neg": [42]}`: an explicit vector, by its trit indices.
+//!
+//! Blank lines are skipped. Anything else (bad JSON, bad base64, an index out of range
+//! or both positive and negative) is a [`BenchError::DatasetFormat`] naming the line.
+
+use crate::error::BenchError;
+use base64::Engine;
+use embeddenator::{ReversibleVSAConfig, SparseVec, DIM};
+use serde::Deserialize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Line {
+    Payload(String),
+    Encoded { data: String, path: Option<String> },
+    Explicit { pos: Vec<usize>, neg: Vec<usize> },
+}
+
+/// The queries of a log, in file order.
+#[derive(Clone, Debug)]
+pub struct QueryLog {
+    pub path: PathBuf,
+    /// SHA-256 of the file's bytes.
+    pub sha256: String,
+    pub queries: Vec<SparseVec>,
+    /// How many lines were payloads to encode (the rest were explicit vectors).
+    pub encoded: usize,
+}
+
+impl QueryLog {
+    pub fn load(path: &Path, config: &ReversibleVSAConfig) -> io::Result<Self> {
+        let bytes = std::fs::read(path)?;
+        let sha256 = Sha256::digest(&bytes)
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect();
+        let text = std::str::from_utf8(&bytes)
+            .map_err(|e| BenchError::format(None, format!("{}: not UTF-8: {e}", path.display())))?;
+
+        let mut queries = Vec::new();
+        let mut encoded = 0;
+        for (i, line) in text.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let bad = |what: String| {
+                io::Error::from(BenchError::format(
+                    None,
+                    format!("{}:{}: {what}", path.display(), i + 1),
+                ))
+            };
+            let parsed: Line = serde_json::from_str(line).map_err(|_| {
+                bad(
+                    "expected a base64 string, {\"data\", \"path\"} or {\"pos\", \"neg\"}"
+                        .to_string(),
+                )
+            })?;
+            let v = match parsed {
+                Line::Payload(data) => {
+                    encoded += 1;
+                    encode(&data, None, config).map_err(bad)?
+                }
+                Line::Encoded { data, path } => {
+                    encoded += 1;
+                    encode(&data, path.as_deref(), config).map_err(bad)?
+                }
+                Line::Explicit { pos, neg } => explicit(pos, neg).map_err(bad)?,
+            };
+            queries.push(v);
+        }
+        if queries.is_empty() {
+            return Err(BenchError::too_small(format!("{}: no queries", path.display())).into());
+        }
+        Ok(Self {
+            path: path.to_path_buf(),
+            sha256,
+            queries,
+            encoded,
+        })
+    }
+
+    /// `extra["query_file"]`: where the queries came from, for reproducing the run.
+    pub fn extra(&self, extra: &mut serde_json::Value) {
+        extra["query_file"] = json!({
+            "path": self.path.to_string_lossy(),
+            "sha256": self.sha256,
+            "queries": self.queries.len(),
+            "encoded": self.encoded,
+            "explicit": self.queries.len() - self.encoded,
+        });
+    }
+}
+
+fn encode(
+    data: &str,
+    path: Option<&str>,
+    config: &ReversibleVSAConfig,
+) -> Result<SparseVec, String> {
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(data)
+        .map_err(|e| format!("invalid base64: {e}"))?;
+    Ok(SparseVec::encode_data(&bytes, config, path))
+}
+
+fn explicit(mut pos: Vec<usize>, mut neg: Vec<usize>) -> Result<SparseVec, String> {
+    if let Some(i) = pos.iter().chain(&neg).find(|&&i| i >= DIM) {
+        return Err(format!("index {i} out of range (dimension {DIM})"));
+    }
+    pos.sort_unstable();
+    pos.dedup();
+    neg.sort_unstable();
+    neg.dedup();
+    if let Some(i) = pos.iter().find(|i| neg.binary_search(i).is_ok()) {
+        return Err(format!("index {i} is both positive and negative"));
+    }
+    Ok(SparseVec { pos, neg })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_and_line_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("queries.jsonl");
+        let config = ReversibleVSAConfig::default();
+        std::fs::write(
+            &path,
+            "\"aGVsbG8=\"\n\n{\"pos\": [17, 3, 3], \"neg\": [42]}\n{\"data\": \"aGVsbG8=\", \"path\": \"a.txt\"}\n",
+        )
+        .unwrap();
+        let log = QueryLog::load(&path, &config).unwrap();
+        assert_eq!((log.queries.len(), log.encoded), (3, 2));
+        let trits = |v: &SparseVec| (v.pos.clone(), v.neg.clone());
+        let hello = |path| SparseVec::encode_data(b"hello", &config, path);
+        assert_eq!(trits(&log.queries[0]), trits(&hello(None)));
+        assert_eq!(trits(&log.queries[1]), (vec![3, 17], vec![42]));
+        assert_eq!(trits(&log.queries[2]), trits(&hello(Some("a.txt"))));
+        assert_eq!(log.sha256.len(), 64);
+
+        for (body, line, what) in [
+            (
+                "\"aGVsbG8=\"\n{\"pos\": [1]}\n",
+                2,
+                "expected a base64 string",
+            ),
+            ("\"not base64!\"\n", 1, "invalid base64"),
+            (
+                "\n\n{\"pos\": [1], \"neg\": [1]}\n",
+                3,
+                "index 1 is both positive and negative",
+            ),
+            (
+                &format!("{{\"pos\": [{DIM}], \"neg\": []}}\n"),
+                1,
+                &format!("index {DIM} out of range"),
+            ),
+        ] {
+            std::fs::write(&path, body).unwrap();
+            let err = QueryLog::load(&path, &config).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+            let msg = err.to_string();
+            assert!(
+                msg.contains(&format!("queries.jsonl:{line}: {what}")),
+                "{msg}"
+            );
+        }
+
+        std::fs::write(&path, "\n").unwrap();
+        assert!(QueryLog::load(&path, &config)
+            .unwrap_err()
+            .to_string()
+            .ends_with("no queries"));
+    }
+}
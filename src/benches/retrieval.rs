@@ -1,13 +1,31 @@
-use crate::harness::{measure_fn, BenchConfig, Profile};
-use crate::schema::Measurement;
+use crate::benches::chunking;
+use crate::benches::input_walk::{self, InputWalk, WalkOptions};
+use crate::benches::query_log::QueryLog;
+use crate::error::BenchError;
+use crate::harness::{cool_down, measure_fn, BenchConfig, Cost, Profile};
+use crate::interrupt::{self, InterruptGuard};
+use crate::measurements;
+use crate::plan;
+use crate::registry::Bench;
+use crate::schema::{tags, Measurement};
+use crate::sink::{self, MeasurementSink};
+use crate::vsa_config::VsaConfig;
+use embeddenator::retrieval::{RerankedResult, TernaryInvertedIndex};
 use embeddenator::EmbrFS;
-use embeddenator::retrieval::RerankedResult;
-use embeddenator::ReversibleVSAConfig;
+use embeddenator::{ReversibleVSAConfig, SparseVec};
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
 use rayon::prelude::*;
 use serde_json::json;
+use sha2::{Digest, Sha256};
 use std::cmp::Ordering;
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hint::black_box;
 use std::io;
+use std::path::Path;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
 
 #[derive(Clone, Debug)]
 pub struct RetrievalArgs {
@@ -15,6 +33,553 @@ pub struct RetrievalArgs {
     pub k: usize,
     pub candidate_factor: usize,
     pub queries: Option<usize>,
+    /// Sweep candidate_k over a geometric series instead of measuring one operating point.
+    pub frontier: bool,
+    /// Remove the query vectors from the codebook and index before building, so no query
+    /// has an exact self-match.
+    pub holdout: bool,
+    /// Also serve the queries from this many concurrent clients per level, emitting one
+    /// `retrieval.concurrency.c<N>` measurement each (empty = single-client only).
+    pub concurrency: Vec<usize>,
+    /// Compute exact top-k for only this seeded fraction of the queries (`None` = all);
+    /// recall is then estimated from them, with a confidence interval.
+    pub ground_truth_sample: Option<f64>,
+    /// Stop the ground-truth pass after this long, keeping the queries done so far.
+    pub ground_truth_timeout: Option<Duration>,
+    /// Which files under `input_dir` make up the corpus.
+    pub walk: WalkOptions,
+    /// Ingest files in pieces of at most this many bytes (see [`chunking`]).
+    pub chunk_size: Option<usize>,
+    /// Replay the queries of this log (see [`crate::benches::query_log`]) instead of
+    /// taking the first corpus chunks; `queries` then caps how many are replayed.
+    pub query_file: Option<std::path::PathBuf>,
+    /// The config the corpus and queries are encoded with (`--vsa-config`).
+    pub vsa_config: VsaConfig,
+}
+
+/// Accumulated recall counts over a set of queries.
+///
+/// `recall_at_k` counts every hit; `recall_at_k_excl_self` drops the query's own id from
+/// both the approximate and the exact sets, so the trivially-correct self-match does not
+/// flatter the result. In holdout mode the two coincide.
+#[derive(Clone, Copy, Debug, Default)]
+struct RecallCounts {
+    hits: usize,
+    expected: usize,
+    hits_excl_self: usize,
+    expected_excl_self: usize,
+    /// Queries counted, and the sum and sum of squares of their individual recalls.
+    queries: usize,
+    recall_sum: f64,
+    recall_sq: f64,
+}
+
+impl RecallCounts {
+    fn add(&mut self, qid: usize, approx: &[RerankedResult], exact: &HashSet<usize>) {
+        let approx_ids: HashSet<usize> = approx.iter().map(|r| r.id).collect();
+        let hits = approx_ids.intersection(exact).count();
+        let self_hit = approx_ids.contains(&qid) && exact.contains(&qid);
+        self.hits += hits;
+        self.expected += exact.len();
+        self.hits_excl_self += hits - usize::from(self_hit);
+        self.expected_excl_self += exact.len() - usize::from(exact.contains(&qid));
+        self.add_query_recall(Self::ratio(hits, exact.len()));
+    }
+
+    fn add_query_recall(&mut self, recall: f64) {
+        self.queries += 1;
+        self.recall_sum += recall;
+        self.recall_sq += recall * recall;
+    }
+
+    fn ratio(num: usize, den: usize) -> f64 {
+        if den == 0 {
+            0.0
+        } else {
+            (num as f64) / (den as f64)
+        }
+    }
+
+    fn recall(&self) -> f64 {
+        Self::ratio(self.hits, self.expected)
+    }
+
+    fn recall_excl_self(&self) -> f64 {
+        Self::ratio(self.hits_excl_self, self.expected_excl_self)
+    }
+
+    /// Recall@k over all `population` queries, estimated from the ones counted (a
+    /// simple random sample of them) as the mean per-query recall.
+    ///
+    /// `ci95` is the normal-approximation interval with the finite-population
+    /// correction: it collapses to the estimate once every query is counted, and is
+    /// `null` with fewer than two counted out of more.
+    fn estimate(&self, population: usize) -> serde_json::Value {
+        let n = self.queries as f64;
+        let mean = if self.queries == 0 {
+            0.0
+        } else {
+            self.recall_sum / n
+        };
+        let ci95 = if self.queries >= population.max(1) {
+            Some((mean, mean))
+        } else if self.queries >= 2 {
+            let var = ((self.recall_sq - n * mean * mean) / (n - 1.0)).max(0.0);
+            let fpc = 1.0 - n / population as f64;
+            let half = 1.96 * (var / n * fpc).sqrt();
+            Some(((mean - half).max(0.0), (mean + half).min(1.0)))
+        } else {
+            None
+        };
+        json!({
+            "recall_at_k": mean,
+            "ci95": ci95.map(|(lo, hi)| [lo, hi]),
+            "queries": self.queries,
+            "population": population,
+        })
+    }
+}
+
+/// Exact top-k ids by brute-force cosine over the whole codebook (parallel).
+fn exact_top_k(codebook: &[(usize, SparseVec)], qv: &SparseVec, k: usize) -> HashSet<usize> {
+    let mut exact: Vec<(usize, f64)> = codebook
+        .par_iter()
+        .map(|(cid, cv)| (*cid, qv.cosine(cv)))
+        .collect();
+    exact.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+    exact.truncate(k);
+    exact.into_iter().map(|(id, _)| id).collect()
+}
+
+/// Sampled queries whose exact top-k are computed between progress reports and stop
+/// checks.
+const GROUND_TRUTH_CHUNK: usize = 16;
+
+/// What the ground-truth pass reports its progress as, in sampled queries, every tenth
+/// of the way (see [`MeasurementSink::on_progress`]).
+const GROUND_TRUTH_PROGRESS: &str = "retrieval.ground_truth";
+
+/// Separates the ground-truth sample from other streams drawn from the run seed.
+const GROUND_TRUTH_SALT: u64 = 0x6774_7361_6d70;
+
+/// Exact top-k for a sample of the queries, as computed by [`ground_truth`].
+#[derive(Clone, Debug)]
+struct GroundTruth {
+    /// Per query (in `query_vecs` order); `None` if not sampled or not reached.
+    exact: Vec<Option<HashSet<usize>>>,
+    fraction: f64,
+    sampled: usize,
+    completed: usize,
+    /// Why the pass ended before covering the sample: `interrupted` or `timeout`.
+    stopped: Option<&'static str>,
+    seconds: f64,
+}
+
+impl GroundTruth {
+    fn extra(&self, extra: &mut serde_json::Value) {
+        extra["ground_truth"] = json!({
+            "sample_fraction": self.fraction,
+            "sampled_queries": self.sampled,
+            "completed_queries": self.completed,
+            "stopped": self.stopped,
+            "seconds": self.seconds,
+        });
+    }
+}
+
+/// `ceil(fraction * queries)` query indices (at least one), in a seeded random order.
+///
+/// Any prefix of the result is itself a simple random sample, so a ground-truth pass
+/// stopped partway still supports an unbiased recall estimate.
+fn sample_queries(queries: usize, fraction: f64, seed: u64) -> Vec<usize> {
+    let n = ((queries as f64) * fraction.clamp(0.0, 1.0)).ceil() as usize;
+    let mut order: Vec<usize> = (0..queries).collect();
+    order.shuffle(&mut ChaCha8Rng::seed_from_u64(seed ^ GROUND_TRUTH_SALT));
+    order.truncate(n.clamp(1, queries.max(1)));
+    order
+}
+
+/// Exact top-k for the `sample` queries, [`GROUND_TRUTH_CHUNK`] at a time.
+///
+/// Each chunk's queries run in parallel. Between chunks `progress(done, total)` is
+/// called and `stop` is polled; a stop reason ends the pass with the chunks finished so
+/// far.
+fn ground_truth(
+    codebook: &[(usize, SparseVec)],
+    query_vecs: &[(usize, SparseVec)],
+    k: usize,
+    sample: &[usize],
+    fraction: f64,
+    progress: &mut dyn FnMut(usize, usize),
+    stop: &dyn Fn() -> Option<&'static str>,
+) -> GroundTruth {
+    let start = Instant::now();
+    let mut exact = vec![None; query_vecs.len()];
+    let mut completed = 0;
+    let mut stopped = None;
+    for chunk in sample.chunks(GROUND_TRUTH_CHUNK) {
+        if let Some(reason) = stop() {
+            stopped = Some(reason);
+            break;
+        }
+        let ids: Vec<HashSet<usize>> = chunk
+            .par_iter()
+            .map(|&i| exact_top_k(codebook, &query_vecs[i].1, k))
+            .collect();
+        for (&i, ids) in chunk.iter().zip(ids) {
+            exact[i] = Some(ids);
+        }
+        completed += chunk.len();
+        progress(completed, sample.len());
+    }
+    GroundTruth {
+        exact,
+        fraction,
+        sampled: sample.len(),
+        completed,
+        stopped,
+        seconds: start.elapsed().as_secs_f64(),
+    }
+}
+
+/// `{min, p50, p90, max, mean}` of `values`.
+fn distribution(mut values: Vec<f64>) -> serde_json::Value {
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    json!({
+        "min": values.first().copied().unwrap_or(0.0),
+        "p50": quantile(&values, 0.50),
+        "p90": quantile(&values, 0.90),
+        "max": values.last().copied().unwrap_or(0.0),
+        "mean": values.iter().sum::<f64>() / values.len().max(1) as f64,
+    })
+}
+
+/// What the candidate stage did per query, for telling a recall change apart from a
+/// change in how many candidates reach the rerank.
+///
+/// `query_codebook_with_index` reports no counters, so these are taken here, outside the
+/// timed loop. `returned` re-runs the index's `query_top_k(candidate_k)`, the candidate
+/// set the rerank sees, and `short` counts the queries where it came back smaller than
+/// candidate_k. `touched_docs` (documents sharing a nonzero dimension with the query)
+/// and `postings_scanned` (the summed posting-list lengths of the query's dimensions)
+/// come from posting lists rebuilt from the codebook, on the assumption that the index
+/// walks every list of every query dimension; they are listed under `approximate`.
+fn candidate_stats(
+    index: &TernaryInvertedIndex,
+    codebook: &[(usize, SparseVec)],
+    query_vecs: &[(usize, SparseVec)],
+    candidate_k: usize,
+) -> serde_json::Value {
+    let mut postings: HashMap<usize, Vec<u32>> = HashMap::new();
+    for (doc, (_, v)) in codebook.iter().enumerate() {
+        for &d in v.pos.iter().chain(&v.neg) {
+            postings.entry(d).or_default().push(doc as u32);
+        }
+    }
+    let per_query: Vec<(f64, f64, f64)> = query_vecs
+        .par_iter()
+        .map(|(_, qv)| {
+            let returned = index.query_top_k(qv, candidate_k).len();
+            let mut touched = vec![false; codebook.len()];
+            let mut scanned = 0;
+            for list in qv.pos.iter().chain(&qv.neg).filter_map(|d| postings.get(d)) {
+                scanned += list.len();
+                for &doc in list {
+                    touched[doc as usize] = true;
+                }
+            }
+            let touched = touched.iter().filter(|&&t| t).count();
+            (returned as f64, touched as f64, scanned as f64)
+        })
+        .collect();
+    let short = per_query
+        .iter()
+        .filter(|(returned, _, _)| (*returned as usize) < candidate_k)
+        .count();
+    json!({
+        "candidate_k": candidate_k,
+        "queries": per_query.len(),
+        "returned": distribution(per_query.iter().map(|q| q.0).collect()),
+        "short": short,
+        "short_fraction": short as f64 / per_query.len().max(1) as f64,
+        "touched_docs": distribution(per_query.iter().map(|q| q.1).collect()),
+        "postings_scanned": distribution(per_query.iter().map(|q| q.2).collect()),
+        "approximate": ["touched_docs", "postings_scanned"],
+    })
+}
+
+/// Candidate factors 1, 2, 4, ... up to the first one whose candidate_k covers the corpus.
+fn frontier_factors(k: usize, chunks: usize) -> Vec<usize> {
+    let mut out = Vec::new();
+    let mut cf = 1usize;
+    loop {
+        out.push(cf);
+        if k.saturating_mul(cf) >= chunks {
+            break;
+        }
+        cf *= 2;
+    }
+    out
+}
+
+/// Recall/latency frontier: one `retrieval.frontier.cf<N>` measurement per candidate factor.
+///
+/// Ground truth is computed once by the caller and shared by every point.
+fn run_frontier(
+    cfg: &BenchConfig,
+    args: &RetrievalArgs,
+    chunks: usize,
+    query_vecs: &[(usize, SparseVec)],
+    gt: &GroundTruth,
+    k: usize,
+    query: impl Fn(&SparseVec, usize) -> Vec<RerankedResult>,
+) -> Vec<Measurement> {
+    let queries = query_vecs.len();
+    let warmup_queries = (cfg.warmup_iters().min(10) as usize).min(queries);
+
+    let mut out = Vec::new();
+    for cf in frontier_factors(k, chunks) {
+        let candidate_k = k.saturating_mul(cf).min(chunks);
+        cool_down();
+
+        for (_, qv) in query_vecs.iter().take(warmup_queries) {
+            black_box(query(qv, candidate_k));
+        }
+
+        let mut latencies_ms: Vec<f64> = Vec::with_capacity(queries);
+        let mut total_ns: u128 = 0;
+        let mut counts = RecallCounts::default();
+        for ((qid, qv), exact_ids) in query_vecs.iter().zip(&gt.exact) {
+            let start = Instant::now();
+            let approx = query(qv, candidate_k);
+            let elapsed = start.elapsed();
+            total_ns += elapsed.as_nanos();
+            latencies_ms.push(elapsed.as_secs_f64() * 1000.0);
+
+            if let Some(exact_ids) = exact_ids {
+                counts.add(*qid, &approx, exact_ids);
+            }
+        }
+
+        latencies_ms.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+        let mean_ms = latencies_ms.iter().sum::<f64>() / (queries.max(1) as f64);
+        let total_s = (total_ns as f64) / 1e9;
+        let qps = if total_s <= 0.0 {
+            0.0
+        } else {
+            (queries as f64) / total_s
+        };
+
+        out.push(Measurement {
+            name: measurements::retrieval::frontier(cf),
+            unit: "ns/query".to_string(),
+            iters: queries as u64,
+            warmup_iters: warmup_queries as u64,
+            total_ns,
+            ns_per_iter: (total_ns as f64) / (queries.max(1) as f64),
+            bytes_processed: None,
+            throughput_bytes_per_s: None,
+            extra: json!({
+                "input_dir": args.input_dir.to_string_lossy().to_string(),
+                "chunks": chunks,
+                "queries": queries,
+                "k": k,
+                "candidate_factor": cf,
+                "candidate_k": candidate_k,
+                "qps": qps,
+                "latency_ms": {
+                    "p50": quantile(&latencies_ms, 0.50),
+                    "p95": quantile(&latencies_ms, 0.95),
+                    "p99": quantile(&latencies_ms, 0.99),
+                    "mean": mean_ms,
+                },
+                "recall_at_k": counts.recall(),
+                "recall_at_k_excl_self": counts.recall_excl_self(),
+                "recall_estimate": counts.estimate(queries),
+                "holdout": args.holdout,
+            }),
+            tags: tags(&[("k", k)]),
+        });
+    }
+    out
+}
+
+/// Serve the queries from `workers` concurrent clients per level.
+///
+/// Each level splits the query stream into `workers` interleaved partitions (query `i`
+/// goes to worker `i % workers`), so every query runs exactly once per level. Workers
+/// share one index by reference, and send each query's latency and results back over a
+/// channel. Ground truth is computed once up front and shared by every level, as in
+/// [`run_frontier`].
+///
+/// `ns_per_iter` is wall time per query, the inverse of the level's QPS; per-query
+/// latencies are in `latency_ms`.
+#[allow(clippy::too_many_arguments)]
+fn run_concurrency(
+    cfg: &BenchConfig,
+    args: &RetrievalArgs,
+    chunks: usize,
+    query_vecs: &[(usize, SparseVec)],
+    gt: &GroundTruth,
+    k: usize,
+    candidate_k: usize,
+    query: impl Fn(&SparseVec) -> Vec<RerankedResult> + Sync,
+) -> Vec<Measurement> {
+    let queries = query_vecs.len();
+    let warmup_queries = (cfg.warmup_iters().min(10) as usize).min(queries);
+
+    let mut out = Vec::new();
+    for &workers in &args.concurrency {
+        cool_down();
+        for (_, qv) in query_vecs.iter().take(warmup_queries) {
+            black_box(query(qv));
+        }
+
+        let (tx, rx) = mpsc::channel();
+        let start = Instant::now();
+        std::thread::scope(|s| {
+            for w in 0..workers {
+                let tx = tx.clone();
+                let query = &query;
+                s.spawn(move || {
+                    for i in (w..queries).step_by(workers) {
+                        let t = Instant::now();
+                        let approx = query(&query_vecs[i].1);
+                        let elapsed = t.elapsed();
+                        tx.send((i, elapsed, approx))
+                            .expect("receiver outlives workers");
+                    }
+                });
+            }
+        });
+        let wall_ns = start.elapsed().as_nanos();
+        drop(tx);
+
+        let mut latencies_ms: Vec<f64> = Vec::with_capacity(queries);
+        let mut counts = RecallCounts::default();
+        for (i, elapsed, approx) in rx {
+            latencies_ms.push(elapsed.as_secs_f64() * 1000.0);
+            if let Some(exact_ids) = &gt.exact[i] {
+                counts.add(query_vecs[i].0, &approx, exact_ids);
+            }
+        }
+        let completed = latencies_ms.len();
+
+        latencies_ms.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+        let mean_ms = latencies_ms.iter().sum::<f64>() / (completed.max(1) as f64);
+        let wall_s = (wall_ns as f64) / 1e9;
+        let qps = if wall_s <= 0.0 {
+            0.0
+        } else {
+            (completed as f64) / wall_s
+        };
+
+        out.push(Measurement {
+            name: measurements::retrieval::concurrency(workers),
+            unit: "ns/query".to_string(),
+            iters: completed as u64,
+            warmup_iters: warmup_queries as u64,
+            total_ns: wall_ns,
+            ns_per_iter: (wall_ns as f64) / (completed.max(1) as f64),
+            bytes_processed: None,
+            throughput_bytes_per_s: None,
+            extra: json!({
+                "input_dir": args.input_dir.to_string_lossy().to_string(),
+                "chunks": chunks,
+                "queries": completed,
+                "workers": workers,
+                "k": k,
+                "candidate_k": candidate_k,
+                "qps": qps,
+                "latency_ms": {
+                    "p50": quantile(&latencies_ms, 0.50),
+                    "p95": quantile(&latencies_ms, 0.95),
+                    "p99": quantile(&latencies_ms, 0.99),
+                    "mean": mean_ms,
+                },
+                "recall_at_k": counts.recall(),
+                "recall_at_k_excl_self": counts.recall_excl_self(),
+                "recall_estimate": counts.estimate(completed),
+                "holdout": args.holdout,
+                // Workers borrow the one index; nothing is cloned per worker.
+                "shared_index": true,
+            }),
+            tags: tags(&[("k", k), ("workers", workers)]),
+        });
+    }
+    out
+}
+
+/// The k, candidate_k and query count a run actually used, and which of the requested
+/// values had to be clamped to fit the corpus.
+#[derive(Clone, Debug)]
+struct Effective {
+    k: usize,
+    candidate_k: usize,
+    queries: usize,
+    clamped: Vec<&'static str>,
+}
+
+impl Effective {
+    /// Top-level extra keys, so the operating point is visible without digging into stats.
+    fn extra(&self, extra: &mut serde_json::Value) {
+        extra["effective_k"] = json!(self.k);
+        extra["effective_candidate_k"] = json!(self.candidate_k);
+        extra["effective_queries"] = json!(self.queries);
+        extra["clamped"] = json!(self.clamped);
+    }
+}
+
+/// The files of a retrieval corpus in ingestion order, and a hash identifying them.
+#[derive(Clone, Debug)]
+struct Corpus {
+    /// The collected files, sorted by relative path.
+    input: InputWalk,
+    /// SHA-256 over every relative path and file content, in order.
+    sha256: String,
+}
+
+impl Corpus {
+    /// The files under `root`, sorted by relative path so chunk ids (and with them the
+    /// query set, which is the first N chunks) do not depend on filesystem walk order.
+    fn walk(root: &Path, opts: &WalkOptions) -> io::Result<Self> {
+        let input = input_walk::walk(root, opts)?;
+        let mut hasher = Sha256::new();
+        for f in &input.files {
+            let bytes = std::fs::read(&f.path)?;
+            hasher.update(f.rel.as_bytes());
+            hasher.update([0]);
+            hasher.update((bytes.len() as u64).to_le_bytes());
+            hasher.update(&bytes);
+        }
+        let sha256 = hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect();
+        Ok(Self { input, sha256 })
+    }
+
+    /// Ingest the files in order into a fresh filesystem, split into `chunk_size`
+    /// pieces if given.
+    fn ingest(
+        &self,
+        config: &ReversibleVSAConfig,
+        chunk_size: Option<usize>,
+    ) -> io::Result<EmbrFS> {
+        let split = chunking::split(std::slice::from_ref(&self.input), chunk_size)?;
+        let mut fsys = EmbrFS::new();
+        for f in &split.walks[0].files {
+            fsys.ingest_file(&f.path, f.rel.clone(), false, config)?;
+        }
+        Ok(fsys)
+    }
+
+    fn extra(&self, extra: &mut serde_json::Value) {
+        extra["corpus_files"] = json!(self.input.files.len());
+        extra["corpus_sha256"] = json!(self.sha256);
+        self.input.extra(extra);
+    }
 }
 
 fn quantile(sorted: &[f64], q: f64) -> f64 {
@@ -25,17 +590,42 @@ fn quantile(sorted: &[f64], q: f64) -> f64 {
     sorted[idx.min(sorted.len() - 1)]
 }
 
+/// [`run`] as the suite's `retrieval` section.
+impl Bench for RetrievalArgs {
+    fn name(&self) -> &str {
+        "retrieval"
+    }
+
+    fn run(&self, cfg: &BenchConfig, sink: &mut dyn MeasurementSink) -> io::Result<()> {
+        run_with_sink(cfg, self, sink)
+    }
+
+    fn unplanned(&self) -> Option<&'static str> {
+        Some(plan::INGESTS)
+    }
+}
+
 pub fn run(cfg: &BenchConfig, args: &RetrievalArgs) -> io::Result<Vec<Measurement>> {
+    sink::collect(|sink| run_with_sink(cfg, args, sink))
+}
+
+/// [`run`], sending each measurement to `sink` as it is taken.
+pub fn run_with_sink(
+    cfg: &BenchConfig,
+    args: &RetrievalArgs,
+    sink: &mut dyn MeasurementSink,
+) -> io::Result<()> {
     if !args.input_dir.is_dir() {
-        return Err(io::Error::other("--input-dir must be a directory"));
+        return Err(BenchError::invalid_args("--input-dir must be a directory").into());
+    }
+    if args.concurrency.contains(&0) {
+        return Err(BenchError::invalid_args("--concurrency levels must be at least 1").into());
     }
 
-    let config = ReversibleVSAConfig::default();
-    let mut fsys = EmbrFS::new();
-    fsys.ingest_directory(&args.input_dir, false, &config)?;
-
-    let engram = &fsys.engram;
-    let index = engram.build_codebook_index();
+    let config = args.vsa_config.config();
+    let corpus = Corpus::walk(&args.input_dir, &args.walk)?;
+    let mut engram = corpus.ingest(&config, args.chunk_size)?.engram;
+    let chunking = chunking::extra(args.chunk_size, &config);
 
     let mut codebook: Vec<(usize, embeddenator::SparseVec)> = engram
         .codebook
@@ -44,56 +634,178 @@ pub fn run(cfg: &BenchConfig, args: &RetrievalArgs) -> io::Result<Vec<Measuremen
         .collect();
     codebook.sort_by_key(|(k, _)| *k);
 
-    let chunks = codebook.len();
-    if chunks == 0 {
-        return Err(io::Error::other("no chunks in codebook"));
+    let total_chunks = codebook.len();
+    if total_chunks == 0 {
+        return Err(BenchError::too_small("no chunks in codebook").into());
+    }
+    if args.holdout && total_chunks < 2 {
+        return Err(BenchError::too_small(format!(
+            "--holdout needs at least 2 chunks (corpus has {total_chunks})"
+        ))
+        .into());
+    }
+    if args.holdout && args.query_file.is_some() {
+        return Err(BenchError::invalid_args(
+            "--holdout takes its queries from the corpus, not --query-file",
+        )
+        .into());
+    }
+    let query_log = match &args.query_file {
+        Some(path) => Some(QueryLog::load(path, &config)?),
+        None => None,
+    };
+
+    // In holdout mode at least half of the corpus stays searchable.
+    let max_queries = match &query_log {
+        Some(log) => log.queries.len(),
+        None if args.holdout => total_chunks / 2,
+        None => total_chunks,
+    };
+    let requested_queries = args.queries;
+    let queries = match (cfg.profile, args.queries, &query_log) {
+        (_, Some(q), _) => q,
+        (_, None, Some(log)) => log.queries.len(),
+        (Profile::Quick, None, None) => total_chunks.min(100),
+        (Profile::Full, None, None) => total_chunks.min(1_000),
+    }
+    .max(1)
+    .min(max_queries);
+
+    // Deterministic queries: the log's first N in file order, else the first N vectors.
+    // Logged queries have no corpus id, so none of them counts as a self-match.
+    let query_vecs: Vec<(usize, embeddenator::SparseVec)> = match &query_log {
+        Some(log) => log.queries[..queries]
+            .iter()
+            .map(|v| (usize::MAX, v.clone()))
+            .collect(),
+        None => codebook.iter().take(queries).cloned().collect(),
+    };
+
+    if args.holdout {
+        for (qid, _) in &query_vecs {
+            engram.codebook.remove(qid);
+        }
+        codebook.drain(..queries);
     }
 
+    let engram = &engram;
+    let index = engram.build_codebook_index();
+
+    let chunks = codebook.len();
     let k = args.k.max(1).min(chunks);
-    let candidate_k = (k.saturating_mul(args.candidate_factor)).max(50).min(chunks);
+    let candidate_k = (k.saturating_mul(args.candidate_factor))
+        .max(50)
+        .min(chunks);
 
-    let queries = match (cfg.profile, args.queries) {
-        (_, Some(q)) => q,
-        (Profile::Quick, None) => chunks.min(100),
-        (Profile::Full, None) => chunks.min(1_000),
+    let mut clamped = Vec::new();
+    if k != args.k {
+        clamped.push("k");
     }
-    .max(1)
-    .min(chunks);
+    if candidate_k < k.saturating_mul(args.candidate_factor).max(50) {
+        clamped.push("candidate_k");
+    }
+    if requested_queries.is_some_and(|q| q != queries) {
+        clamped.push("queries");
+    }
+    if !clamped.is_empty() {
+        eprintln!(
+            "warning: retrieval clamped {} to fit {chunks} chunk(s): k={k} (requested {}), candidate_k={candidate_k}, queries={queries}",
+            clamped.join(", "),
+            args.k
+        );
+    }
+    let effective = Effective {
+        k,
+        candidate_k,
+        queries,
+        clamped,
+    };
 
-    // Deterministic queries: take first N vectors.
-    let query_vecs: Vec<(usize, embeddenator::SparseVec)> = codebook.iter().take(queries).cloned().collect();
+    // Ground truth once, outside the timed loops, for every measurement below.
+    let fraction = args.ground_truth_sample.unwrap_or(1.0);
+    if !(fraction > 0.0 && fraction <= 1.0) {
+        return Err(BenchError::invalid_args(format!(
+            "--ground-truth-sample must be in (0, 1] (got {fraction})"
+        ))
+        .into());
+    }
+    let sample = sample_queries(queries, fraction, cfg.seed);
+    let deadline = args.ground_truth_timeout.map(|t| Instant::now() + t);
+    let mut reported = 0;
+    let mut progress = |done: usize, total: usize| {
+        // Every tenth of the way, and at the end.
+        let tenth = done * 10 / total;
+        if tenth > reported || done == total {
+            reported = tenth;
+            sink.on_progress(GROUND_TRUTH_PROGRESS, done, total);
+        }
+    };
+    let gt = {
+        let _interrupt = InterruptGuard::install();
+        let stop = || {
+            if interrupt::interrupted() {
+                Some("interrupted")
+            } else if deadline.is_some_and(|d| Instant::now() >= d) {
+                Some("timeout")
+            } else {
+                None
+            }
+        };
+        ground_truth(
+            &codebook,
+            &query_vecs,
+            k,
+            &sample,
+            fraction,
+            &mut progress,
+            &stop,
+        )
+    };
+    if let Some(reason) = gt.stopped {
+        eprintln!(
+            "warning: retrieval ground truth stopped ({reason}) after {}/{} queries; recall is estimated from those",
+            gt.completed, gt.sampled
+        );
+    }
 
-    let warmup = cfg.warmup_iters().min(10);
-    let iters = 1; // One measured pass over all queries.
+    if args.frontier {
+        sink.on_start(measurements::retrieval::FRONTIER_PREFIX.trim_end_matches('.'));
+        let out = run_frontier(cfg, args, chunks, &query_vecs, &gt, k, |qv, ck| {
+            engram.query_codebook_with_index(&index, qv, ck, k)
+        });
+        for mut m in out {
+            effective.extra(&mut m.extra);
+            corpus.extra(&mut m.extra);
+            if let Some(log) = &query_log {
+                log.extra(&mut m.extra);
+            }
+            m.extra["chunking"] = chunking.clone();
+            gt.extra(&mut m.extra);
+            sink.on_measurement(m)?;
+        }
+        return Ok(());
+    }
+
+    // One iteration is a pass over every query.
+    let (iters, warmup) = cfg.counts(Cost::Macro);
+    sink.on_start(measurements::retrieval::QUERY_PASS_WITH_INDEX);
 
     let mut last_stats = json!({});
 
     let m = measure_fn(iters, warmup, || {
         let mut latencies_ms: Vec<f64> = Vec::with_capacity(queries);
-        let mut total_recall_hits: usize = 0;
+        let mut counts = RecallCounts::default();
 
-        for (qid, qv) in &query_vecs {
+        for ((qid, qv), exact_ids) in query_vecs.iter().zip(&gt.exact) {
             let start = std::time::Instant::now();
             let approx: Vec<RerankedResult> =
                 engram.query_codebook_with_index(&index, qv, candidate_k, k);
             let elapsed = start.elapsed();
             latencies_ms.push(elapsed.as_secs_f64() * 1000.0);
 
-            // Brute force exact top-k (parallel).
-            let mut exact: Vec<(usize, f64)> = codebook
-                .par_iter()
-                .map(|(cid, cv)| (*cid, qv.cosine(cv)))
-                .collect();
-            exact.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
-            exact.truncate(k);
-
-            let exact_ids: HashSet<usize> = exact.into_iter().map(|(id, _)| id).collect();
-            let approx_ids: HashSet<usize> = approx.into_iter().map(|r| r.id).collect();
-
-            let hits = approx_ids.intersection(&exact_ids).count();
-            total_recall_hits += hits;
-
-            let _ = qid; // keep deterministic query id in scope
+            if let Some(exact_ids) = exact_ids {
+                counts.add(*qid, &approx, exact_ids);
+            }
         }
 
         latencies_ms.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
@@ -104,10 +816,9 @@ pub fn run(cfg: &BenchConfig, args: &RetrievalArgs) -> io::Result<Vec<Measuremen
         } else {
             (queries as f64) / total_time_s
         };
-        let recall = (total_recall_hits as f64) / ((queries * k) as f64);
-
         last_stats = json!({
             "chunks": chunks,
+            "holdout": args.holdout,
             "queries": queries,
             "k": k,
             "candidate_k": candidate_k,
@@ -118,14 +829,29 @@ pub fn run(cfg: &BenchConfig, args: &RetrievalArgs) -> io::Result<Vec<Measuremen
                 "p99": quantile(&latencies_ms, 0.99),
                 "mean": mean_ms,
             },
-            "recall_at_k": recall,
+            "recall_at_k": counts.recall(),
+            "recall_at_k_excl_self": counts.recall_excl_self(),
+            "recall_estimate": counts.estimate(queries),
         });
 
         Ok::<(), io::Error>(())
     });
 
-    Ok(vec![Measurement {
-        name: "retrieval.query_codebook_with_index".to_string(),
+    let mut extra = json!({
+        "input_dir": args.input_dir.to_string_lossy().to_string(),
+        "stats": last_stats,
+        "candidates": candidate_stats(&index, &codebook, &query_vecs, candidate_k),
+    });
+    effective.extra(&mut extra);
+    corpus.extra(&mut extra);
+    if let Some(log) = &query_log {
+        log.extra(&mut extra);
+    }
+    extra["chunking"] = chunking.clone();
+    gt.extra(&mut extra);
+
+    sink.on_measurement(Measurement {
+        name: measurements::retrieval::QUERY_PASS_WITH_INDEX.to_string(),
         unit: "ns/iter".to_string(),
         iters: m.iters,
         warmup_iters: m.warmup_iters,
@@ -133,9 +859,608 @@ pub fn run(cfg: &BenchConfig, args: &RetrievalArgs) -> io::Result<Vec<Measuremen
         ns_per_iter: m.ns_per_iter,
         bytes_processed: None,
         throughput_bytes_per_s: None,
-        extra: json!({
-            "input_dir": args.input_dir.to_string_lossy().to_string(),
-            "stats": last_stats,
-        }),
-    }])
+        extra,
+        tags: BTreeMap::new(),
+    })?;
+
+    if !args.concurrency.is_empty() {
+        sink.on_start("retrieval.concurrency");
+        let levels = run_concurrency(cfg, args, chunks, &query_vecs, &gt, k, candidate_k, |qv| {
+            engram.query_codebook_with_index(&index, qv, candidate_k, k)
+        });
+        for mut m in levels {
+            effective.extra(&mut m.extra);
+            corpus.extra(&mut m.extra);
+            if let Some(log) = &query_log {
+                log.extra(&mut m.extra);
+            }
+            m.extra["chunking"] = chunking.clone();
+            gt.extra(&mut m.extra);
+            sink.on_measurement(m)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use tempfile::TempDir;
+
+    /// Small deterministic corpus: a handful of files with distinct seeded content.
+    fn synthetic_corpus(files: usize, bytes_per_file: usize) -> TempDir {
+        use rand::{Rng, SeedableRng};
+        let dir = TempDir::new().unwrap();
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(1234);
+        for i in 0..files {
+            let body: Vec<u8> = (0..bytes_per_file).map(|_| rng.gen()).collect();
+            std::fs::write(dir.path().join(format!("doc{i:03}.bin")), body).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn test_frontier_factors() {
+        assert_eq!(frontier_factors(10, 10), vec![1]);
+        assert_eq!(frontier_factors(10, 75), vec![1, 2, 4, 8]);
+        assert_eq!(frontier_factors(3, 12), vec![1, 2, 4]);
+    }
+
+    #[test]
+    fn test_frontier_recall_monotone() {
+        let corpus = synthetic_corpus(8, 16 * 1024);
+        let cfg = BenchConfig {
+            profile: Profile::Quick,
+            seed: 0,
+        };
+        let args = RetrievalArgs {
+            input_dir: corpus.path().to_path_buf(),
+            k: 3,
+            candidate_factor: 10,
+            queries: Some(8),
+            frontier: true,
+            holdout: false,
+            concurrency: Vec::new(),
+            ground_truth_sample: None,
+            ground_truth_timeout: None,
+            walk: WalkOptions::default(),
+            chunk_size: None,
+            query_file: None,
+            vsa_config: VsaConfig::default(),
+        };
+
+        let ms = run(&cfg, &args).unwrap();
+        assert!(ms.len() > 1);
+        assert!(ms
+            .iter()
+            .all(|m| m.name.starts_with("retrieval.frontier.cf")));
+
+        let mut last_ck = 0;
+        let mut last_recall = 0.0;
+        for m in &ms {
+            let ck = m.extra["candidate_k"].as_u64().unwrap();
+            let recall = m.extra["recall_at_k"].as_f64().unwrap();
+            assert!(ck > last_ck);
+            assert!(
+                recall + 1e-12 >= last_recall,
+                "{} recall {recall} < {last_recall}",
+                m.name
+            );
+            last_ck = ck;
+            last_recall = recall;
+        }
+    }
+
+    #[test]
+    fn test_clamping_reported() {
+        let corpus = synthetic_corpus(5, 4 * 1024);
+        let cfg = BenchConfig {
+            profile: Profile::Quick,
+            seed: 0,
+        };
+        let mut args = RetrievalArgs {
+            input_dir: corpus.path().to_path_buf(),
+            k: 50,
+            candidate_factor: 10,
+            queries: Some(1_000),
+            frontier: false,
+            holdout: false,
+            concurrency: Vec::new(),
+            ground_truth_sample: None,
+            ground_truth_timeout: None,
+            walk: WalkOptions::default(),
+            chunk_size: None,
+            query_file: None,
+            vsa_config: VsaConfig::default(),
+        };
+
+        let m = &run(&cfg, &args).unwrap()[0];
+        let chunks = m.extra["stats"]["chunks"].clone();
+        assert_eq!(m.extra["effective_k"], chunks);
+        assert_eq!(m.extra["effective_candidate_k"], chunks);
+        assert_eq!(m.extra["effective_queries"], chunks);
+        assert_eq!(m.extra["clamped"], json!(["k", "candidate_k", "queries"]));
+
+        args.k = 1;
+        args.queries = Some(1);
+        let m = &run(&cfg, &args).unwrap()[0];
+        assert_eq!(m.extra["effective_k"], 1);
+        assert_eq!(m.extra["clamped"], json!(["candidate_k"]));
+    }
+
+    #[test]
+    fn test_corpus_independent_of_creation_order() {
+        let files = ["b/2.bin", "a.bin", "b/1.bin", "c/d/3.bin", "z.bin", "b.bin"];
+        let tree = |order: &[usize]| {
+            let dir = TempDir::new().unwrap();
+            for &i in order {
+                let path = dir.path().join(files[i]);
+                std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+                let body: Vec<u8> = (0..8 * 1024).map(|j| (j * (i + 3) % 251) as u8).collect();
+                std::fs::write(path, body).unwrap();
+            }
+            dir
+        };
+        let forward = tree(&[0, 1, 2, 3, 4, 5]);
+        let shuffled = tree(&[4, 2, 5, 0, 3, 1]);
+
+        let config = ReversibleVSAConfig::default();
+        let a = Corpus::walk(forward.path(), &WalkOptions::default()).unwrap();
+        let b = Corpus::walk(shuffled.path(), &WalkOptions::default()).unwrap();
+        let rels: Vec<&str> = a.input.files.iter().map(|f| f.rel.as_str()).collect();
+        assert_eq!(
+            rels,
+            ["a.bin", "b.bin", "b/1.bin", "b/2.bin", "c/d/3.bin", "z.bin"]
+        );
+        assert_eq!(a.sha256, b.sha256);
+
+        // Queries are the first N chunks by id, so identical codebooks mean identical
+        // query sets.
+        let codebook = |c: &Corpus| {
+            let mut cb: Vec<(usize, SparseVec)> = c
+                .ingest(&config, None)
+                .unwrap()
+                .engram
+                .codebook
+                .into_iter()
+                .collect();
+            cb.sort_by_key(|(id, _)| *id);
+            cb
+        };
+        let (ca, cb) = (codebook(&a), codebook(&b));
+        assert_eq!(ca.len(), cb.len());
+        for ((ia, va), (ib, vb)) in ca.iter().zip(&cb) {
+            assert_eq!((ia, &va.pos, &va.neg), (ib, &vb.pos, &vb.neg));
+        }
+
+        let cfg = BenchConfig {
+            profile: Profile::Quick,
+            seed: 0,
+        };
+        let args = |dir: &TempDir| RetrievalArgs {
+            input_dir: dir.path().to_path_buf(),
+            k: 3,
+            candidate_factor: 10,
+            queries: Some(4),
+            frontier: false,
+            holdout: false,
+            concurrency: Vec::new(),
+            ground_truth_sample: None,
+            ground_truth_timeout: None,
+            walk: WalkOptions::default(),
+            chunk_size: None,
+            query_file: None,
+            vsa_config: VsaConfig::default(),
+        };
+        let ma = &run(&cfg, &args(&forward)).unwrap()[0];
+        let mb = &run(&cfg, &args(&shuffled)).unwrap()[0];
+        assert_eq!(ma.extra["corpus_sha256"], json!(a.sha256));
+        assert_eq!(ma.extra["corpus_sha256"], mb.extra["corpus_sha256"]);
+        assert_eq!(ma.extra["corpus_files"], 6);
+
+        // Any content change shows up in the hash.
+        std::fs::write(shuffled.path().join("z.bin"), b"changed").unwrap();
+        assert_ne!(
+            Corpus::walk(shuffled.path(), &WalkOptions::default())
+                .unwrap()
+                .sha256,
+            a.sha256
+        );
+    }
+
+    #[test]
+    fn test_concurrency_runs_each_query_once() {
+        let corpus = synthetic_corpus(6, 8 * 1024);
+        let cfg = BenchConfig {
+            profile: Profile::Quick,
+            seed: 0,
+        };
+        let args = RetrievalArgs {
+            input_dir: corpus.path().to_path_buf(),
+            k: 3,
+            candidate_factor: 10,
+            queries: Some(5),
+            frontier: false,
+            holdout: false,
+            concurrency: vec![1, 2],
+            ground_truth_sample: None,
+            ground_truth_timeout: None,
+            walk: WalkOptions::default(),
+            chunk_size: None,
+            query_file: None,
+            vsa_config: VsaConfig::default(),
+        };
+
+        let ms = run(&cfg, &args).unwrap();
+        let names: Vec<&str> = ms.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "retrieval.query_pass_with_index",
+                "retrieval.concurrency.c1",
+                "retrieval.concurrency.c2",
+            ]
+        );
+        let queries = ms[0].extra["effective_queries"].as_u64().unwrap();
+        for (m, workers) in ms[1..].iter().zip([1, 2]) {
+            // queries x 1, not queries x workers.
+            assert_eq!(m.iters, queries, "{}", m.name);
+            assert_eq!(m.extra["queries"], queries);
+            assert_eq!(m.extra["workers"], workers);
+            assert_eq!(m.tags["workers"], workers.to_string());
+            assert!(m.extra["qps"].as_f64().unwrap() > 0.0);
+            assert_eq!(m.extra["recall_at_k"], ms[1].extra["recall_at_k"]);
+        }
+
+        // Zero clients would serve nothing under a `c0` name.
+        let zero = RetrievalArgs {
+            concurrency: vec![0],
+            ..args
+        };
+        let err = run(&cfg, &zero).unwrap_err();
+        assert!(matches!(
+            BenchError::of(&err),
+            Some(BenchError::InvalidArgs(_))
+        ));
+    }
+
+    #[test]
+    fn test_recall_excl_self_not_above_recall() {
+        let corpus = synthetic_corpus(8, 16 * 1024);
+        let cfg = BenchConfig {
+            profile: Profile::Quick,
+            seed: 0,
+        };
+        let mut args = RetrievalArgs {
+            input_dir: corpus.path().to_path_buf(),
+            k: 5,
+            candidate_factor: 10,
+            queries: Some(16),
+            frontier: false,
+            holdout: false,
+            concurrency: Vec::new(),
+            ground_truth_sample: None,
+            ground_truth_timeout: None,
+            walk: WalkOptions::default(),
+            chunk_size: None,
+            query_file: None,
+            vsa_config: VsaConfig::default(),
+        };
+
+        let stats = &run(&cfg, &args).unwrap()[0].extra["stats"];
+        let recall = stats["recall_at_k"].as_f64().unwrap();
+        let excl = stats["recall_at_k_excl_self"].as_f64().unwrap();
+        assert!(excl <= recall, "excl_self {excl} > recall {recall}");
+
+        // With held-out queries there is no self-match, so both figures agree.
+        args.holdout = true;
+        let stats = &run(&cfg, &args).unwrap()[0].extra["stats"];
+        assert_eq!(stats["holdout"], true);
+        assert_eq!(stats["recall_at_k"], stats["recall_at_k_excl_self"]);
+        let chunks = stats["chunks"].as_u64().unwrap();
+        let queries = stats["queries"].as_u64().unwrap();
+        assert!(queries <= chunks);
+    }
+
+    #[test]
+    fn test_candidate_stats_bounded_by_k_and_corpus() {
+        let corpus = synthetic_corpus(8, 16 * 1024);
+        let cfg = BenchConfig {
+            profile: Profile::Quick,
+            seed: 0,
+        };
+        let args = RetrievalArgs {
+            input_dir: corpus.path().to_path_buf(),
+            k: 3,
+            candidate_factor: 10,
+            queries: Some(12),
+            frontier: false,
+            holdout: false,
+            concurrency: Vec::new(),
+            ground_truth_sample: None,
+            ground_truth_timeout: None,
+            walk: WalkOptions::default(),
+            chunk_size: None,
+            query_file: None,
+            vsa_config: VsaConfig::default(),
+        };
+
+        let extra = &run(&cfg, &args).unwrap()[0].extra;
+        let chunks = extra["stats"]["chunks"].as_f64().unwrap();
+        let candidates = &extra["candidates"];
+        let candidate_k = candidates["candidate_k"].as_f64().unwrap();
+        assert_eq!(candidates["candidate_k"], extra["effective_candidate_k"]);
+        assert_eq!(candidates["queries"], 12);
+        for (field, cap) in [("returned", candidate_k), ("touched_docs", chunks)] {
+            let d = &candidates[field];
+            let (min, max) = (d["min"].as_f64().unwrap(), d["max"].as_f64().unwrap());
+            assert!(
+                3.0 <= min && min <= d["p50"].as_f64().unwrap() && max <= cap,
+                "{field}: {d}"
+            );
+        }
+        // Every document a query touches is reached through at least one posting.
+        assert!(
+            candidates["postings_scanned"]["min"].as_f64().unwrap()
+                >= candidates["touched_docs"]["min"].as_f64().unwrap()
+        );
+        let short = candidates["short"].as_u64().unwrap();
+        assert_eq!(
+            short == 0,
+            candidates["returned"]["min"].as_f64().unwrap() >= candidate_k
+        );
+        assert_eq!(
+            candidates["approximate"],
+            json!(["touched_docs", "postings_scanned"])
+        );
+    }
+
+    #[test]
+    fn test_query_file_replayed_in_order() {
+        let corpus = synthetic_corpus(6, 8 * 1024);
+        let logs = TempDir::new().unwrap();
+        let log = logs.path().join("queries.jsonl");
+        std::fs::write(
+            &log,
+            concat!(
+                "\"cXVlcnkgb25l\"\n",
+                "{\"pos\": [1, 2, 3, 500], \"neg\": [7, 9000]}\n",
+                "\n",
+                "{\"data\": \"cXVlcnkgdHdv\", \"path\": \"doc000.bin\"}\n",
+            ),
+        )
+        .unwrap();
+        let cfg = BenchConfig {
+            profile: Profile::Quick,
+            seed: 0,
+        };
+        let mut args = RetrievalArgs {
+            input_dir: corpus.path().to_path_buf(),
+            k: 3,
+            candidate_factor: 10,
+            queries: None,
+            frontier: false,
+            holdout: false,
+            concurrency: Vec::new(),
+            ground_truth_sample: None,
+            ground_truth_timeout: None,
+            walk: WalkOptions::default(),
+            chunk_size: None,
+            query_file: Some(log.clone()),
+            vsa_config: VsaConfig::default(),
+        };
+
+        let a = run(&cfg, &args).unwrap().remove(0);
+        assert_eq!(a.extra["stats"]["queries"], 3);
+        assert_eq!(a.extra["effective_queries"], 3);
+        assert_eq!(a.extra["query_file"]["queries"], 3);
+        assert_eq!(a.extra["query_file"]["explicit"], 1);
+        assert_eq!(
+            a.extra["query_file"]["path"],
+            log.to_string_lossy().as_ref()
+        );
+        assert_eq!(a.extra["ground_truth"]["completed_queries"], 3);
+        // Replayed queries have no corpus id to match themselves.
+        assert_eq!(
+            a.extra["stats"]["recall_at_k"],
+            a.extra["stats"]["recall_at_k_excl_self"]
+        );
+
+        let b = run(&cfg, &args).unwrap().remove(0);
+        for key in ["recall_at_k", "recall_estimate"] {
+            assert_eq!(a.extra["stats"][key], b.extra["stats"][key], "{key}");
+        }
+        assert_eq!(a.extra["query_file"], b.extra["query_file"]);
+
+        args.queries = Some(2);
+        let capped = run(&cfg, &args).unwrap().remove(0);
+        assert_eq!(capped.extra["stats"]["queries"], 2);
+        assert_eq!(
+            capped.extra["query_file"]["sha256"],
+            a.extra["query_file"]["sha256"]
+        );
+
+        std::fs::write(&log, "\"cXVlcnkgb25l\"\n[1, 2]\n").unwrap();
+        let err = run(&cfg, &args).unwrap_err();
+        assert!(err.to_string().contains("queries.jsonl:2: "), "{err}");
+        assert!(matches!(
+            BenchError::of(&err),
+            Some(BenchError::DatasetFormat { .. })
+        ));
+
+        // --holdout draws its queries from the corpus.
+        args.holdout = true;
+        let err = run(&cfg, &args).unwrap_err();
+        assert!(
+            matches!(BenchError::of(&err), Some(BenchError::InvalidArgs(_))),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn test_chunk_size_changes_corpus_chunks() {
+        let corpus = synthetic_corpus(4, 8 * 1024);
+        let cfg = BenchConfig {
+            profile: Profile::Quick,
+            seed: 0,
+        };
+        let mut args = RetrievalArgs {
+            input_dir: corpus.path().to_path_buf(),
+            k: 5,
+            candidate_factor: 10,
+            queries: Some(8),
+            frontier: false,
+            holdout: false,
+            concurrency: Vec::new(),
+            ground_truth_sample: None,
+            ground_truth_timeout: None,
+            walk: WalkOptions::default(),
+            chunk_size: None,
+            query_file: None,
+            vsa_config: VsaConfig::default(),
+        };
+        let default = run(&cfg, &args).unwrap().remove(0);
+        args.chunk_size = Some(1024);
+        let small = run(&cfg, &args).unwrap().remove(0);
+
+        assert_eq!(default.extra["stats"]["chunks"], 8);
+        assert_eq!(small.extra["stats"]["chunks"], 32);
+        assert_eq!(small.extra["chunking"]["requested_chunk_size"], 1024);
+        assert_eq!(
+            default.extra["chunking"]["chunk_size"],
+            chunking::LIBRARY_CHUNK_SIZE
+        );
+        // Same files either way.
+        assert_eq!(small.extra["corpus_sha256"], default.extra["corpus_sha256"]);
+    }
+
+    #[test]
+    fn test_sampled_recall_estimate_unbiased() {
+        // 200 queries with known per-query recall@10, so the full recall is known.
+        let population: Vec<f64> = (0..200).map(|i| ((i * 37) % 11) as f64 / 10.0).collect();
+        let full = population.iter().sum::<f64>() / 200.0;
+
+        let mut all = RecallCounts::default();
+        population.iter().for_each(|&r| all.add_query_recall(r));
+        let est = all.estimate(200);
+        assert!((est["recall_at_k"].as_f64().unwrap() - full).abs() < 1e-12);
+        assert_eq!(est["ci95"][0], est["ci95"][1]);
+
+        let seeds = 500;
+        let (mut sum, mut covered) = (0.0, 0);
+        for seed in 0..seeds {
+            let sample = sample_queries(200, 0.2, seed);
+            assert_eq!(sample.len(), 40);
+            let mut counts = RecallCounts::default();
+            sample
+                .iter()
+                .for_each(|&i| counts.add_query_recall(population[i]));
+            let est = counts.estimate(200);
+            let recall = est["recall_at_k"].as_f64().unwrap();
+            let (lo, hi) = (
+                est["ci95"][0].as_f64().unwrap(),
+                est["ci95"][1].as_f64().unwrap(),
+            );
+            assert!(lo <= recall && recall <= hi);
+            sum += recall;
+            covered += usize::from(lo <= full && full <= hi);
+        }
+        let mean = sum / seeds as f64;
+        assert!(
+            (mean - full).abs() < 0.01,
+            "mean estimate {mean} vs full {full}"
+        );
+        assert!(
+            covered * 100 >= seeds as usize * 90,
+            "ci95 covered {covered}/{seeds}"
+        );
+
+        // A single counted query out of many gives no interval.
+        let mut one = RecallCounts::default();
+        one.add_query_recall(1.0);
+        assert!(one.estimate(200)["ci95"].is_null());
+    }
+
+    #[test]
+    fn test_ground_truth_stops_between_chunks() {
+        let vectors: Vec<(usize, SparseVec)> = (0..40)
+            .map(|i| {
+                let v = SparseVec {
+                    pos: vec![i, i + 1, 100 + i % 7],
+                    neg: vec![200 + i % 5],
+                };
+                (i, v)
+            })
+            .collect();
+        let sample = sample_queries(40, 1.0, 3);
+        let mut progress_calls = Vec::new();
+        let mut progress = |done, total| progress_calls.push((done, total));
+
+        let full = ground_truth(&vectors, &vectors, 3, &sample, 1.0, &mut progress, &|| None);
+        assert_eq!((full.completed, full.stopped), (40, None));
+        assert_eq!(progress_calls, [(16, 40), (32, 40), (40, 40)]);
+
+        // Stopping after the first chunk keeps that chunk's queries, exactly.
+        let chunks_run = Cell::new(0);
+        let stop = || {
+            chunks_run.set(chunks_run.get() + 1);
+            (chunks_run.get() > 1).then_some("interrupted")
+        };
+        let partial = ground_truth(&vectors, &vectors, 3, &sample, 1.0, &mut |_, _| {}, &stop);
+        assert_eq!(
+            (partial.completed, partial.stopped),
+            (16, Some("interrupted"))
+        );
+        let done: Vec<usize> = (0..40).filter(|&i| partial.exact[i].is_some()).collect();
+        let mut first_chunk = sample[..16].to_vec();
+        first_chunk.sort_unstable();
+        assert_eq!(done, first_chunk);
+        for &i in &done {
+            assert_eq!(partial.exact[i], full.exact[i]);
+        }
+    }
+
+    #[test]
+    fn test_ground_truth_sample_and_timeout_reported() {
+        let corpus = synthetic_corpus(6, 8 * 1024);
+        let cfg = BenchConfig {
+            profile: Profile::Quick,
+            seed: 0,
+        };
+        let mut args = RetrievalArgs {
+            input_dir: corpus.path().to_path_buf(),
+            k: 3,
+            candidate_factor: 10,
+            queries: Some(9),
+            frontier: false,
+            holdout: false,
+            concurrency: Vec::new(),
+            ground_truth_sample: Some(0.5),
+            ground_truth_timeout: None,
+            walk: WalkOptions::default(),
+            chunk_size: None,
+            query_file: None,
+            vsa_config: VsaConfig::default(),
+        };
+
+        let m = &run(&cfg, &args).unwrap()[0];
+        let queries = m.extra["effective_queries"].as_u64().unwrap();
+        let sampled = (queries as f64 / 2.0).ceil() as u64;
+        assert_eq!(m.extra["ground_truth"]["sampled_queries"], sampled);
+        assert_eq!(m.extra["ground_truth"]["completed_queries"], sampled);
+        assert_eq!(m.extra["stats"]["recall_estimate"]["queries"], sampled);
+        assert_eq!(m.iters, cfg.counts(Cost::Macro).0);
+
+        // A zero budget stops before the first chunk; queries are still timed.
+        args.ground_truth_timeout = Some(Duration::ZERO);
+        let m = &run(&cfg, &args).unwrap()[0];
+        assert_eq!(m.extra["ground_truth"]["stopped"], "timeout");
+        assert_eq!(m.extra["ground_truth"]["completed_queries"], 0);
+        assert_eq!(m.extra["stats"]["recall_estimate"]["queries"], 0);
+        assert_eq!(m.extra["stats"]["queries"], queries);
+
+        args.ground_truth_sample = Some(0.0);
+        assert!(run(&cfg, &args).is_err());
+    }
 }
@@ -0,0 +1,283 @@
+//! Storage/wire cost of each substrate.
+//!
+//! `vsa.<substrate>.serialized_bytes` is the bincode size of a standard seeded vector
+//! (`_dataset` for a vector at the generator's default density). SparseVec is always
+//! bincode; the other substrates are too with the `substrate-serde` feature (on by
+//! default), and without it get an in-crate estimate of their payload, marked
+//! `"method": "estimate"` in extra. Where bincode is used, `vsa.<substrate>.serialize`
+//! and `.deserialize` time the round trip.
+//!
+//! Size measurements (`unit: "bytes"`) carry the count in `extra.bytes` and
+//! `bytes_processed`; their `ns_per_iter` is 0, so compare reports them unchanged rather
+//! than reading a size as a time.
+
+use crate::benches::inputs::blocks_touched;
+use crate::dataset::generate_indexed;
+use crate::harness::{measure_fn, BenchConfig, Measured};
+use crate::measurements;
+use crate::schema::{tags, Measurement};
+use crate::VsaVariant;
+use embeddenator::{
+    BitslicedTritVec, BlockSparseTritVec, PackedTritVec, ReversibleVSAConfig, SparseVec, DIM,
+};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::json;
+
+/// Estimated bytes per stored block-sparse block (u32 block id + one u64 mask per sign).
+const BLOCK_BYTES: usize = 4 + 2 * 8;
+
+/// bincode encoding of a packed, bitsliced or block-sparse vector, or `None` (size
+/// estimated, no round-trip timings) when built without `substrate-serde`.
+#[cfg(feature = "substrate-serde")]
+fn substrate_encode<T: Serialize>(v: &T) -> Option<Vec<u8>> {
+    bincode::serialize(v).ok()
+}
+
+#[cfg(not(feature = "substrate-serde"))]
+fn substrate_encode<T>(_v: &T) -> Option<Vec<u8>> {
+    None
+}
+
+/// Size of one substrate's representation of a vector.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SerializedSize {
+    pub bytes: usize,
+    /// `"bincode"` or `"estimate"`.
+    pub method: &'static str,
+}
+
+impl SerializedSize {
+    fn of(encoded: Option<&[u8]>, estimate: usize) -> Self {
+        match encoded {
+            Some(b) => Self {
+                bytes: b.len(),
+                method: "bincode",
+            },
+            None => Self {
+                bytes: estimate,
+                method: "estimate",
+            },
+        }
+    }
+}
+
+/// Two bits per trit, plus a length word.
+fn packed_estimate(dim: usize) -> usize {
+    8 + (dim * 2).div_ceil(64) * 8
+}
+
+/// One bit plane per sign, plus a length word.
+fn bitsliced_estimate(dim: usize) -> usize {
+    8 + 2 * dim.div_ceil(64) * 8
+}
+
+/// Only non-empty blocks are stored, plus a length word and a block count.
+fn blocksparse_estimate(v: &SparseVec) -> usize {
+    16 + blocks_touched(v) * BLOCK_BYTES
+}
+
+/// Index lists: a length word and one word per index for each sign.
+fn sparsevec_estimate(v: &SparseVec) -> usize {
+    16 + 8 * (v.pos.len() + v.neg.len())
+}
+
+/// `(substrate, size)` for every substrate selected by `variant` (sparsevec always).
+pub fn serialized_sizes(v: &SparseVec, variant: VsaVariant) -> Vec<(&'static str, SerializedSize)> {
+    let mut out = vec![(
+        "sparsevec",
+        SerializedSize::of(bincode::serialize(v).ok().as_deref(), sparsevec_estimate(v)),
+    )];
+    if matches!(variant, VsaVariant::All | VsaVariant::Packed) {
+        let p = PackedTritVec::from_sparsevec(v, DIM);
+        let encoded = substrate_encode(&p);
+        out.push((
+            "packed",
+            SerializedSize::of(encoded.as_deref(), packed_estimate(DIM)),
+        ));
+    }
+    if matches!(variant, VsaVariant::All | VsaVariant::Bitsliced) {
+        let b = BitslicedTritVec::from_sparse(v, DIM);
+        let encoded = substrate_encode(&b);
+        out.push((
+            "bitsliced",
+            SerializedSize::of(encoded.as_deref(), bitsliced_estimate(DIM)),
+        ));
+    }
+    if matches!(variant, VsaVariant::All | VsaVariant::BlockSparse) {
+        let b = BlockSparseTritVec::from_sparse(v, DIM);
+        let encoded = substrate_encode(&b);
+        out.push((
+            "blocksparse",
+            SerializedSize::of(encoded.as_deref(), blocksparse_estimate(v)),
+        ));
+    }
+    out
+}
+
+fn size_measurement(
+    substrate: &str,
+    suffix: &str,
+    size: SerializedSize,
+    nnz: usize,
+) -> Measurement {
+    Measurement {
+        name: measurements::vsa::serialized_bytes(substrate, suffix),
+        unit: "bytes".to_string(),
+        iters: 1,
+        warmup_iters: 0,
+        total_ns: 0,
+        ns_per_iter: 0.0,
+        bytes_processed: Some(size.bytes as u64),
+        throughput_bytes_per_s: None,
+        extra: json!({"dim": DIM, "nnz": nnz, "bytes": size.bytes, "method": size.method}),
+        tags: tags(&[("substrate", substrate)]),
+    }
+}
+
+fn timing_measurement(substrate: &str, op: &str, m: Measured, bytes: usize) -> Measurement {
+    let secs = m.ns_per_iter / 1e9;
+    Measurement {
+        name: measurements::vsa::serialization(substrate, op),
+        unit: "ns/iter".to_string(),
+        iters: m.iters,
+        warmup_iters: m.warmup_iters,
+        total_ns: m.total_ns,
+        ns_per_iter: m.ns_per_iter,
+        bytes_processed: Some(bytes as u64),
+        throughput_bytes_per_s: (secs > 0.0).then(|| bytes as f64 / secs),
+        extra: json!({"dim": DIM, "bytes": bytes, "method": "bincode"}),
+        tags: tags(&[("substrate", substrate)]),
+    }
+}
+
+/// Time bincode encode/decode of `v`.
+fn serde_timings<T: Serialize + DeserializeOwned>(
+    out: &mut Vec<Measurement>,
+    cfg: &BenchConfig,
+    substrate: &str,
+    v: &T,
+) {
+    let Ok(bytes) = bincode::serialize(v) else {
+        return;
+    };
+    let (iters, warmup) = (cfg.iters(), cfg.warmup_iters());
+    let m = measure_fn(iters, warmup, || bincode::serialize(v).ok());
+    out.push(timing_measurement(substrate, "serialize", m, bytes.len()));
+    let m = measure_fn(iters, warmup, || bincode::deserialize::<T>(&bytes).ok());
+    out.push(timing_measurement(substrate, "deserialize", m, bytes.len()));
+}
+
+/// Sizes and timings for the substrates of `variant`, and for SparseVec (the reference
+/// every substrate is compared against) when `sparsevec` is set.
+pub fn run(
+    cfg: &BenchConfig,
+    config: &ReversibleVSAConfig,
+    variant: VsaVariant,
+    sparsevec: bool,
+) -> Vec<Measurement> {
+    let standard = SparseVec::encode_data(b"alpha", config, Some("/bench/vsa"));
+    // Same density as a default `generate-dataset` vector.
+    let dataset = generate_indexed(cfg.seed, 0, DIM, DIM / 100);
+
+    let mut out = Vec::new();
+    for (v, suffix) in [(&standard, ""), (&dataset, "_dataset")] {
+        let nnz = v.pos.len() + v.neg.len();
+        for (substrate, size) in serialized_sizes(v, variant) {
+            if sparsevec || substrate != "sparsevec" {
+                out.push(size_measurement(substrate, suffix, size, nnz));
+            }
+        }
+    }
+
+    if sparsevec {
+        serde_timings(&mut out, cfg, "sparsevec", &standard);
+    }
+    #[cfg(feature = "substrate-serde")]
+    {
+        if matches!(variant, VsaVariant::All | VsaVariant::Packed) {
+            let p = PackedTritVec::from_sparsevec(&standard, DIM);
+            serde_timings(&mut out, cfg, "packed", &p);
+        }
+        if matches!(variant, VsaVariant::All | VsaVariant::Bitsliced) {
+            let b = BitslicedTritVec::from_sparse(&standard, DIM);
+            serde_timings(&mut out, cfg, "bitsliced", &b);
+        }
+        if matches!(variant, VsaVariant::All | VsaVariant::BlockSparse) {
+            let b = BlockSparseTritVec::from_sparse(&standard, DIM);
+            serde_timings(&mut out, cfg, "blocksparse", &b);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::harness::Profile;
+
+    #[test]
+    fn test_estimates_ordered_at_one_percent() {
+        // 1% density overall: DIM / 200 indices per sign.
+        let v = generate_indexed(1, 0, DIM, DIM / 200);
+        let packed = packed_estimate(DIM);
+        assert!(bitsliced_estimate(DIM).abs_diff(packed) <= 8);
+        assert!(sparsevec_estimate(&v) < blocksparse_estimate(&v));
+        assert!(
+            blocksparse_estimate(&v) < packed,
+            "{}",
+            blocksparse_estimate(&v)
+        );
+
+        // Fully dense: every block is stored and block-sparse loses to packed.
+        let dense = generate_indexed(1, 0, DIM, DIM / 2);
+        assert!(blocksparse_estimate(&dense) > packed);
+    }
+
+    #[test]
+    fn test_sizes_positive_and_timed_when_serializable() {
+        let cfg = BenchConfig {
+            profile: Profile::Quick,
+            seed: 3,
+        };
+        let v = generate_indexed(3, 0, DIM, DIM / 100);
+        let sizes = serialized_sizes(&v, VsaVariant::All);
+        let names: Vec<&str> = sizes.iter().map(|(s, _)| *s).collect();
+        assert_eq!(names, ["sparsevec", "packed", "bitsliced", "blocksparse"]);
+        assert!(sizes.iter().all(|(_, s)| s.bytes > 0));
+        // SparseVec is serde-serializable (engrams are bincode), so it is never estimated.
+        assert_eq!(sizes[0].1.method, "bincode");
+        let substrate_method = if cfg!(feature = "substrate-serde") {
+            "bincode"
+        } else {
+            "estimate"
+        };
+        assert!(sizes[1..].iter().all(|(_, s)| s.method == substrate_method));
+
+        let ms = run(
+            &cfg,
+            &ReversibleVSAConfig::default(),
+            VsaVariant::Packed,
+            true,
+        );
+        let has = |name: &str| ms.iter().any(|m| m.name == name);
+        assert!(has("vsa.packed.serialized_bytes") && has("vsa.packed.serialized_bytes_dataset"));
+        assert!(!has("vsa.bitsliced.serialized_bytes"));
+        assert!(has("vsa.sparsevec.serialize") && has("vsa.sparsevec.deserialize"));
+        assert_eq!(
+            has("vsa.packed.serialize"),
+            cfg!(feature = "substrate-serde")
+        );
+        let packed_only = run(
+            &cfg,
+            &ReversibleVSAConfig::default(),
+            VsaVariant::Packed,
+            false,
+        );
+        assert!(packed_only.iter().all(|m| m.tags["substrate"] == "packed"));
+        for m in ms.iter().filter(|m| m.unit == "bytes") {
+            assert_eq!(m.ns_per_iter, 0.0, "{}", m.name);
+            assert_eq!(m.bytes_processed, m.extra["bytes"].as_u64(), "{}", m.name);
+        }
+    }
+}
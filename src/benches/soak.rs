@@ -0,0 +1,444 @@
+//! Sustained-load runs (`soak`).
+//!
+//! The other benches time an op for as long as it takes to get a stable mean, which
+//! says nothing about what happens over minutes: allocator fragmentation, caches
+//! filling, a leak, thermal throttling. A soak runs one op back to back for `duration`
+//! and snapshots it every `snapshot_interval`: ops/s, latency percentiles and resident
+//! memory over that interval. The whole run is one `soak.<op>` measurement with the
+//! snapshots, timestamped from the start, in extra.
+//!
+//! Throughput falling, or RSS growing, by more than `drift_threshold` between the first
+//! snapshot and the last is flagged in `drift.flagged`. Ctrl-C ends the run early: the
+//! interval in progress becomes a last, shorter snapshot and `stopped` says why. A last
+//! snapshot under half an interval (a run stopped, or ending, just after the one before)
+//! is too short to compare, so drift is taken to the one before it.
+
+use crate::dataset::{convert_batch, generate_dataset, DatasetReader, GenerateConfig};
+use crate::error::BenchError;
+use crate::harness::BenchConfig;
+use crate::interrupt::{self, InterruptGuard};
+use crate::measurements;
+use crate::schema::Measurement;
+use clap::ValueEnum;
+use embeddenator::retrieval::TernaryInvertedIndex;
+use embeddenator::{PackedTritVec, SparseVec};
+use serde_json::{json, Value};
+use std::hint::black_box;
+use std::io;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// Ops a soak can run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum SoakOp {
+    PackedBind,
+    SparsevecBind,
+    SparsevecBundle,
+    SparsevecCosine,
+    /// `query_top_k` (k = 10) against an index of the whole vector pool.
+    IndexQuery,
+}
+
+impl SoakOp {
+    /// The op's part of the measurement name.
+    pub fn name(self) -> &'static str {
+        match self {
+            SoakOp::PackedBind => "packed.bind",
+            SoakOp::SparsevecBind => "sparsevec.bind",
+            SoakOp::SparsevecBundle => "sparsevec.bundle",
+            SoakOp::SparsevecCosine => "sparsevec.cosine",
+            SoakOp::IndexQuery => "index.query_top_k",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SoakArgs {
+    pub op: SoakOp,
+    pub duration: Duration,
+    pub snapshot_interval: Duration,
+    /// Relative change between the first snapshot and the last (a short trailing one
+    /// aside) that counts as drift.
+    pub drift_threshold: f64,
+    /// Take the vector pool from this dataset rather than generating it.
+    pub dataset: Option<PathBuf>,
+}
+
+impl SoakArgs {
+    pub fn validate(&self) -> Result<(), BenchError> {
+        if self.duration.is_zero() || self.snapshot_interval.is_zero() {
+            return Err(BenchError::invalid_args(
+                "soak: duration and snapshot interval must be positive",
+            ));
+        }
+        if !(self.drift_threshold.is_finite() && self.drift_threshold >= 0.0) {
+            return Err(BenchError::invalid_args(format!(
+                "soak: drift threshold must be a non-negative number, got {}",
+                self.drift_threshold
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Vectors in the pool the op cycles through.
+const POOL: usize = 1024;
+
+/// `query_top_k` k for [`SoakOp::IndexQuery`].
+const QUERY_K: usize = 10;
+
+/// Latencies kept per snapshot; past this the sample is thinned to every other one.
+const LATENCY_SAMPLE_CAP: usize = 100_000;
+
+/// Parse `500ms`, `10s`, `10m`, `1h` or a bare number of seconds.
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let (number, unit_secs) = [("ms", 1e-3), ("s", 1.0), ("m", 60.0), ("h", 3600.0)]
+        .iter()
+        .find_map(|(suffix, secs)| s.strip_suffix(suffix).map(|n| (n, *secs)))
+        .unwrap_or((s, 1.0));
+    number
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .and_then(|n| Duration::try_from_secs_f64(n * unit_secs).ok())
+        .ok_or_else(|| format!("expected a duration such as 500ms, 10s, 10m or 1h, got `{s}`"))
+}
+
+/// The pool converted for `op` up front, so only the op itself is timed.
+enum Prepared {
+    Sparsevec(Vec<SparseVec>),
+    Packed(Vec<PackedTritVec>),
+    Index {
+        index: TernaryInvertedIndex,
+        queries: Vec<SparseVec>,
+    },
+}
+
+impl Prepared {
+    fn new(op: SoakOp, pool: Vec<SparseVec>, dim: usize) -> Self {
+        match op {
+            SoakOp::PackedBind => Prepared::Packed(convert_batch(&pool, dim)),
+            SoakOp::SparsevecBind | SoakOp::SparsevecBundle | SoakOp::SparsevecCosine => {
+                Prepared::Sparsevec(pool)
+            }
+            SoakOp::IndexQuery => {
+                let mut index = TernaryInvertedIndex::new();
+                for (id, v) in pool.iter().enumerate() {
+                    index.add(id, v);
+                }
+                index.finalize();
+                Prepared::Index {
+                    index,
+                    queries: pool,
+                }
+            }
+        }
+    }
+
+    /// Run `op` on pair `i` (vectors `i` and `i + 1`, cycling), returning a value to
+    /// black-box.
+    fn run(&self, op: SoakOp, i: usize) -> f64 {
+        match (self, op) {
+            (Prepared::Packed(p), _) => p[i % p.len()].bind(&p[(i + 1) % p.len()]).len() as f64,
+            (Prepared::Sparsevec(p), SoakOp::SparsevecBundle) => {
+                p[i % p.len()].bundle(&p[(i + 1) % p.len()]).pos.len() as f64
+            }
+            (Prepared::Sparsevec(p), SoakOp::SparsevecCosine) => {
+                p[i % p.len()].cosine(&p[(i + 1) % p.len()])
+            }
+            (Prepared::Sparsevec(p), _) => {
+                p[i % p.len()].bind(&p[(i + 1) % p.len()]).pos.len() as f64
+            }
+            (Prepared::Index { index, queries }, _) => index
+                .query_top_k(&queries[i % queries.len()], QUERY_K)
+                .len() as f64,
+        }
+    }
+}
+
+/// The vector pool and its dimension: the first [`POOL`] records of `dataset`, or a
+/// seeded one.
+fn pool(cfg: &BenchConfig, dataset: Option<&PathBuf>) -> io::Result<(Vec<SparseVec>, usize)> {
+    let (pool, dim) = match dataset {
+        Some(path) => {
+            let mut reader = DatasetReader::open(path)?;
+            let dim = reader.meta().dimension as usize;
+            (reader.read_batch(POOL)?, dim)
+        }
+        None => {
+            let config = GenerateConfig {
+                count: POOL as u64,
+                seed: cfg.seed,
+                ..Default::default()
+            };
+            let dim = config.dimension;
+            (generate_dataset(&config)?, dim)
+        }
+    };
+    if pool.len() < 2 {
+        return Err(BenchError::too_small("soak needs at least 2 vectors").into());
+    }
+    Ok((pool, dim))
+}
+
+/// Resident set size of this process (Linux `/proc/self/status`; `None` elsewhere).
+fn rss_bytes() -> Option<u64> {
+    if !cfg!(target_os = "linux") {
+        return None;
+    }
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    parse_vm_rss(&status)
+}
+
+/// The `VmRSS:` line of a `/proc/<pid>/status`, in bytes.
+fn parse_vm_rss(status: &str) -> Option<u64> {
+    let kib = status
+        .lines()
+        .find_map(|l| l.strip_prefix("VmRSS:"))?
+        .trim()
+        .strip_suffix("kB")?
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    Some(kib * 1024)
+}
+
+/// Per-op latencies over one interval: every `stride`-th op, the stride doubling (and
+/// the sample halving) each time it fills, so memory stays bounded however fast the op.
+struct LatencySample {
+    ns: Vec<u64>,
+    stride: u64,
+    seen: u64,
+}
+
+impl LatencySample {
+    fn new() -> Self {
+        Self {
+            ns: Vec::new(),
+            stride: 1,
+            seen: 0,
+        }
+    }
+
+    fn record(&mut self, ns: u64) {
+        if self.seen.is_multiple_of(self.stride) {
+            if self.ns.len() == LATENCY_SAMPLE_CAP {
+                self.ns = self.ns.iter().step_by(2).copied().collect();
+                self.stride *= 2;
+            }
+            if self.seen.is_multiple_of(self.stride) {
+                self.ns.push(ns);
+            }
+        }
+        self.seen += 1;
+    }
+
+    /// Nearest-rank p50, p90 and p99, clearing the sample for the next interval.
+    fn take_percentiles(&mut self) -> [Option<u64>; 3] {
+        let mut ns = std::mem::take(&mut self.ns);
+        *self = Self::new();
+        ns.sort_unstable();
+        [0.50, 0.90, 0.99].map(|q| {
+            let rank = ((q * ns.len() as f64).ceil() as usize).max(1);
+            ns.get(rank - 1).copied()
+        })
+    }
+}
+
+/// One interval of the run.
+fn snapshot(t: Duration, ops: u64, interval: Duration, latencies: &mut LatencySample) -> Value {
+    let [p50, p90, p99] = latencies.take_percentiles();
+    json!({
+        "t_ms": t.as_millis() as u64,
+        "ops": ops,
+        "ops_per_s": ops as f64 / interval.as_secs_f64().max(1e-9),
+        "rss_bytes": rss_bytes(),
+        "p50_ns": p50,
+        "p90_ns": p90,
+        "p99_ns": p99,
+    })
+}
+
+/// Relative change of `field` from the first snapshot to the last, when both have it.
+fn relative_change(snapshots: &[Value], field: &str) -> Option<f64> {
+    let (first, last) = (snapshots.first()?, snapshots.last()?);
+    let (first, last) = (first[field].as_f64()?, last[field].as_f64()?);
+    (snapshots.len() > 1 && first > 0.0).then(|| last / first - 1.0)
+}
+
+/// Throughput falling or RSS growing by more than the threshold, up to the last snapshot
+/// of at least half of `interval`.
+fn drift(snapshots: &[Value], interval: Duration, threshold: f64) -> Value {
+    let t_ms = |s: &Value| s["t_ms"].as_u64().unwrap_or(0);
+    let compared = match snapshots {
+        [rest @ .., before, last]
+            if t_ms(last).saturating_sub(t_ms(before)) * 2 < interval.as_millis() as u64 =>
+        {
+            &snapshots[..rest.len() + 1]
+        }
+        _ => snapshots,
+    };
+    let ops_per_s = relative_change(compared, "ops_per_s");
+    let rss = relative_change(compared, "rss_bytes");
+    let mut flagged = Vec::new();
+    if ops_per_s.is_some_and(|c| -c > threshold) {
+        flagged.push("ops_per_s");
+    }
+    if rss.is_some_and(|c| c > threshold) {
+        flagged.push("rss");
+    }
+    json!({
+        "ops_per_s": ops_per_s,
+        "rss": rss,
+        "threshold": threshold,
+        "snapshots_compared": compared.len(),
+        "flagged": flagged,
+    })
+}
+
+pub fn run(cfg: &BenchConfig, args: &SoakArgs) -> io::Result<Measurement> {
+    args.validate()?;
+    let (pool, dim) = pool(cfg, args.dataset.as_ref())?;
+    let pool_len = pool.len();
+    let prepared = Prepared::new(args.op, pool, dim);
+
+    let _interrupt = InterruptGuard::install();
+    let mut snapshots = Vec::new();
+    let mut latencies = LatencySample::new();
+    let (mut ops, mut interval_ops) = (0u64, 0u64);
+    let mut stopped = None;
+    let start = Instant::now();
+    let mut interval_start = Duration::ZERO;
+    let mut elapsed = Duration::ZERO;
+    while elapsed < args.duration {
+        if interrupt::interrupted() {
+            stopped = Some("interrupted");
+            break;
+        }
+        let op_start = Instant::now();
+        black_box(prepared.run(args.op, black_box(ops as usize)));
+        let op_end = Instant::now();
+        latencies.record((op_end - op_start).as_nanos() as u64);
+        ops += 1;
+        interval_ops += 1;
+        elapsed = op_end - start;
+        if elapsed - interval_start >= args.snapshot_interval {
+            snapshots.push(snapshot(
+                elapsed,
+                interval_ops,
+                elapsed - interval_start,
+                &mut latencies,
+            ));
+            interval_start = elapsed;
+            interval_ops = 0;
+        }
+    }
+    if interval_ops > 0 {
+        snapshots.push(snapshot(
+            elapsed,
+            interval_ops,
+            elapsed - interval_start,
+            &mut latencies,
+        ));
+    }
+
+    let total_ns = elapsed.as_nanos();
+    Ok(Measurement {
+        name: measurements::soak::op(args.op.name()),
+        unit: "ns/op".to_string(),
+        iters: ops,
+        warmup_iters: 0,
+        total_ns,
+        ns_per_iter: total_ns as f64 / ops.max(1) as f64,
+        bytes_processed: None,
+        throughput_bytes_per_s: None,
+        extra: json!({
+            "op": args.op.name(),
+            "pool": pool_len,
+            "dim": dim,
+            "duration_s": args.duration.as_secs_f64(),
+            "snapshot_interval_ms": args.snapshot_interval.as_millis() as u64,
+            "drift": drift(&snapshots, args.snapshot_interval, args.drift_threshold),
+            "snapshots": snapshots,
+            "stopped": stopped,
+        }),
+        tags: Default::default(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        for (s, ms) in [
+            ("500ms", 500),
+            ("10s", 10_000),
+            ("10m", 600_000),
+            ("1h", 3_600_000),
+            ("2.5", 2_500),
+        ] {
+            assert_eq!(parse_duration(s).unwrap(), Duration::from_millis(ms), "{s}");
+        }
+        for s in ["", "ten", "-1s", "5d"] {
+            assert!(parse_duration(s).is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn test_latency_sample_and_drift() {
+        assert_eq!(
+            parse_vm_rss("Name:\tx\nVmRSS:\t  2048 kB\n"),
+            Some(2 * 1024 * 1024)
+        );
+        assert_eq!(parse_vm_rss("Name:\tx\n"), None);
+
+        let mut sample = LatencySample::new();
+        for ns in 1..=(LATENCY_SAMPLE_CAP as u64 * 3) {
+            sample.record(ns);
+        }
+        assert!(sample.ns.len() <= LATENCY_SAMPLE_CAP);
+        assert_eq!(sample.stride, 4);
+        let [p50, p90, p99] = sample.take_percentiles().map(Option::unwrap);
+        let n = LATENCY_SAMPLE_CAP as f64 * 3.0;
+        for (p, q) in [(p50, 0.5), (p90, 0.9), (p99, 0.99)] {
+            assert!((p as f64 / n - q).abs() < 0.01, "{p} vs {q}");
+        }
+        assert_eq!(sample.take_percentiles(), [None; 3]);
+
+        let snap = |ops_per_s: f64, rss: u64| json!({"ops_per_s": ops_per_s, "rss_bytes": rss});
+        let interval = Duration::ZERO;
+        let flagged = |snapshots: &[Value]| drift(snapshots, interval, 0.1)["flagged"].clone();
+        assert_eq!(flagged(&[snap(100.0, 1000), snap(95.0, 1050)]), json!([]));
+        assert_eq!(
+            flagged(&[snap(100.0, 1000), snap(85.0, 1000)]),
+            json!(["ops_per_s"])
+        );
+        assert_eq!(
+            flagged(&[snap(100.0, 1000), snap(150.0, 1200)]),
+            json!(["rss"])
+        );
+        assert_eq!(
+            drift(&[snap(100.0, 1000)], interval, 0.1)["ops_per_s"],
+            Value::Null
+        );
+
+        // A last snapshot of a few ms after Ctrl-C is left out; one of half an interval
+        // or more is compared.
+        let at = |t_ms: u64, ops_per_s: f64| json!({"t_ms": t_ms, "ops_per_s": ops_per_s});
+        let interval = Duration::from_secs(1);
+        let stopped = [at(1000, 100.0), at(2000, 98.0), at(2004, 20.0)];
+        let d = drift(&stopped, interval, 0.1);
+        assert_eq!(
+            (&d["flagged"], &d["snapshots_compared"]),
+            (&json!([]), &json!(2))
+        );
+        let half = [at(1000, 100.0), at(2000, 98.0), at(2500, 20.0)];
+        let d = drift(&half, interval, 0.1);
+        assert_eq!(
+            (&d["flagged"], &d["snapshots_compared"]),
+            (&json!(["ops_per_s"]), &json!(3))
+        );
+    }
+}
@@ -1,12 +1,41 @@
-use clap::{Parser, Subcommand, ValueEnum};
+use clap::parser::ValueSource;
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use embeddenator_contract_bench::assertions::{self, Assertion};
+use embeddenator_contract_bench::atomic_write;
 use embeddenator_contract_bench::benches;
+use embeddenator_contract_bench::benches::input_walk::WalkOptions;
+use embeddenator_contract_bench::benches::inputs::InputClass;
+use embeddenator_contract_bench::benches::vsa::VsaOp;
+use embeddenator_contract_bench::compare::{self, CompareOptions};
+use embeddenator_contract_bench::confidence;
+use embeddenator_contract_bench::criterion_import;
 use embeddenator_contract_bench::dataset::{self, GenerateConfig};
-use embeddenator_contract_bench::harness::{BenchConfig, Profile};
-use embeddenator_contract_bench::schema::{ContractBenchReport, RunMeta};
+use embeddenator_contract_bench::dedupe;
+use embeddenator_contract_bench::disk_space;
+use embeddenator_contract_bench::environment::{Environment, PERFORMANCE_GOVERNOR};
+use embeddenator_contract_bench::gzip;
+use embeddenator_contract_bench::harness::{self, BenchConfig, Profile};
+use embeddenator_contract_bench::measurements;
+use embeddenator_contract_bench::plan::{self, Plan};
+use embeddenator_contract_bench::ratios;
+use embeddenator_contract_bench::registry::Registry;
+use embeddenator_contract_bench::schema::{
+    self, unix_secs, ContractBenchReport, Measurement, RunMeta,
+};
+use embeddenator_contract_bench::sink;
+use embeddenator_contract_bench::status::RunStatus;
+use embeddenator_contract_bench::suite::{self, SuiteSpec};
+use embeddenator_contract_bench::summary::{self, SummaryOptions};
+use embeddenator_contract_bench::tidy;
+use embeddenator_contract_bench::trend::{self, TrendOptions};
+use embeddenator_contract_bench::vsa_config::VsaConfig;
 use embeddenator_contract_bench::VsaVariant;
+use std::collections::BTreeSet;
 use std::fs;
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
 
 #[derive(Clone, Copy, Debug, ValueEnum)]
 enum ProfileArg {
@@ -34,9 +63,163 @@ enum Command {
         /// Optional dataset file for scaled benchmarks.
         ///
         /// If provided, the VSA benches run over the dataset vectors (streamed from disk)
-        /// instead of the small fixed "alpha/beta/gamma" microbench inputs.
+        /// instead of the small fixed "alpha/beta/gamma" microbench inputs. Use `-` to read
+        /// the dataset from stdin; it is read once and the needed prefix buffered in memory.
+        ///
+        /// Repeat it to run several datasets one after another into one report; their
+        /// measurements are told apart by the `scale` tag (the vector count), so the
+        /// datasets must differ in size.
         #[arg(long, value_name = "FILE")]
-        dataset: Option<PathBuf>,
+        dataset: Vec<PathBuf>,
+
+        /// With several --dataset files: keep running the remaining datasets when one
+        /// fails. The report holds what did run and the process still exits non-zero.
+        #[arg(long, default_value_t = false, requires = "dataset")]
+        keep_going: bool,
+
+        /// Cap the number of pairs/triples per dataset op. With `--dataset -` this also
+        /// bounds how many vectors are buffered from stdin.
+        #[arg(long, value_name = "N", requires = "dataset")]
+        max_ops: Option<u64>,
+
+        /// Override a dataset op's cost weight, e.g.
+        /// `--op-weight vsa_dataset.packed.bundle=4`. The quick profile gives every op the
+        /// same weighted units, so it runs 10000/weight pairs/triples (defaults in
+        /// `DATASET_OP_WEIGHTS`, recorded per measurement as `op_weight`). Repeatable.
+        #[arg(long = "op-weight", value_name = "NAME=WEIGHT", value_parser = parse_op_weight, requires = "dataset")]
+        op_weights: Vec<(String, f64)>,
+
+        /// Total dataset ops for the whole run instead of per op: split across the
+        /// enabled ops in proportion to the pairs/triples each can use, and read in
+        /// seeded stripes spread through the file rather than from its front. Keeps a
+        /// full report over a large dataset within a predictable time.
+        #[arg(
+            long,
+            value_name = "N",
+            requires = "dataset",
+            conflicts_with = "zero_copy"
+        )]
+        ops_budget: Option<u64>,
+
+        /// Split each dataset loop's time into reading, substrate conversion and the op,
+        /// recorded per measurement as `stage_breakdown` (absolute ns and fractions of
+        /// total_ns). Adds one clock read per stage, whose measured cost is reported too.
+        #[arg(long, default_value_t = false, requires = "dataset")]
+        stage_breakdown: bool,
+
+        /// Record the distribution of the cosine values the dataset cosine loops (and
+        /// packed dot, normalised) compute, as `cosine_values` (histogram, mean, stddev)
+        /// per measurement, so `compare` can flag value drift between versions. Not
+        /// collected for the zero-copy SparseVec cosine.
+        #[arg(long, default_value_t = false, requires = "dataset")]
+        collect_values: bool,
+
+        /// Before the ops, convert K seeded records, the K with the most block-boundary
+        /// indices and a few synthetic boundary vectors to each enabled substrate and
+        /// back, and record the mismatches per substrate (`vsa.contract.roundtrip`; fails
+        /// the run under --strict-contract).
+        #[arg(long, value_name = "K", requires = "dataset")]
+        roundtrip_check: Option<u64>,
+
+        /// Pair each dataset vector with the one S records on instead of its neighbour:
+        /// pair k is records O + 2k and O + 2k + S (triple k: O + 3k, then S and 2S on),
+        /// wrapping at the end of the file. Recorded per measurement as `sampling`.
+        #[arg(long, value_name = "S", value_parser = clap::value_parser!(u64).range(1..), requires = "dataset", conflicts_with_all = ["zero_copy", "ops_budget"])]
+        pair_stride: Option<u64>,
+
+        /// First record of the dataset groups (see --pair-stride).
+        #[arg(long, value_name = "O", requires = "dataset", conflicts_with_all = ["zero_copy", "ops_budget"])]
+        pair_offset: Option<u64>,
+
+        /// Run the SparseVec-level dataset measurements over zero-copy mmapped views.
+        /// Cosine is then this crate's, reported as `vsa_dataset.sparsevec_ref.cosine`.
+        #[arg(long, default_value_t = false, requires = "dataset")]
+        zero_copy: bool,
+
+        /// Check the dataset body against its header before running.
+        #[arg(long, default_value_t = false, requires = "dataset")]
+        validate_dataset: bool,
+
+        /// Count empty/degenerate vectors before running (recorded in extra) and
+        /// substitute a deterministic fallback for empty ones.
+        #[arg(long, default_value_t = false, requires = "dataset")]
+        validate_vectors: bool,
+
+        /// With --validate-vectors: fail at the first empty vector instead.
+        #[arg(long, default_value_t = false, requires = "validate_vectors")]
+        strict: bool,
+
+        /// Checkpoint file (JSONL, gzipped if it ends in `.gz`). Dataset measurements
+        /// already in it are kept instead of rerun, and each new one is appended as it
+        /// completes, so a killed run can be restarted with the same flags and still
+        /// produce one complete report.
+        #[arg(long, value_name = "FILE", requires = "dataset")]
+        resume: Option<PathBuf>,
+
+        /// Stop after N new measurements (for testing --resume).
+        #[arg(long, value_name = "N", requires = "resume", hide = true)]
+        stop_after: Option<usize>,
+
+        /// Cycle the fixed-input microbenches through K seeded input sets instead of
+        /// repeating one pair, so branch predictors and caches cannot overfit.
+        #[arg(
+            long,
+            value_name = "K",
+            default_value_t = 1,
+            conflicts_with = "dataset"
+        )]
+        rotate_inputs: usize,
+
+        /// Index layout of the fixed inputs: encoded `random` payloads, or a constructed
+        /// best/worst case (`clustered`, `spread`, `dense`, `single-block`). Other than
+        /// `random`, measurement names get the class as a suffix.
+        #[arg(long, value_enum, default_value_t = InputClass::Random, conflicts_with_all = ["dataset", "check_bundle_semantics"])]
+        input_class: InputClass,
+
+        /// Also measure packed/bitsliced bundle, bind, cosine and dot on seeded vectors at
+        /// 0.1%, 1%, 5% and 20% density (`vsa.<substrate>.<op>.density_<label>`, tagged
+        /// `density`).
+        #[arg(long, default_value_t = false, conflicts_with_all = ["dataset", "check_bundle_semantics"])]
+        density_sweep: bool,
+
+        /// Before anything else, time one execution of each selected SparseVec op (bind,
+        /// bundle, cosine, in that order) on fresh inputs with no warmup:
+        /// `vsa.coldstart.<op>`, the first-request latency a service sees. Tagged
+        /// `variance=high`, so compare does not gate on them.
+        #[arg(long, default_value_t = false, conflicts_with_all = ["dataset", "check_bundle_semantics"])]
+        cold_start: bool,
+
+        /// Only run the measurements of these op groups, e.g. `--ops bundle,bind` (default:
+        /// every op). Combines with --variant; with either set, the SparseVec reference
+        /// measurements only run under `--variant all`.
+        #[arg(
+            long,
+            value_enum,
+            value_delimiter = ',',
+            value_name = "OP,...",
+            conflicts_with = "check_bundle_semantics"
+        )]
+        ops: Vec<VsaOp>,
+
+        /// Instead of timing, compare every bundling entry point against each other and
+        /// record the pairwise cosine matrix (`vsa.contract.bundle_semantics`).
+        #[arg(long, default_value_t = false, conflicts_with = "dataset")]
+        check_bundle_semantics: bool,
+
+        /// Minimum pairwise cosine for --check-bundle-semantics to pass.
+        #[arg(long, default_value_t = 0.5, requires = "check_bundle_semantics")]
+        bundle_threshold: f64,
+
+        /// Fail the run (after writing the report) unless a bundle of every size probed by
+        /// `vsa.contract.bundle_capacity` keeps at least this mean cosine to its components.
+        #[arg(long, value_name = "COSINE", conflicts_with_all = ["dataset", "check_bundle_semantics"])]
+        capacity_threshold: Option<f64>,
+
+        /// Fail the run (after writing the report) when a contract check recorded in a
+        /// measurement did not pass, e.g. `decode_data(encode_data(x)) != x` in
+        /// `vsa.sparsevec.roundtrip_fidelity.*`.
+        #[arg(long, default_value_t = false)]
+        strict_contract: bool,
     },
 
     /// Encode/extract contract metrics (ingest time, size breakdown; optional verify).
@@ -60,6 +243,50 @@ enum Command {
         /// Perform an extract + SHA256 verify pass.
         #[arg(long, default_value_t = false)]
         verify: bool,
+
+        /// Hash the verify pass's files in parallel, over the --threads pool.
+        #[arg(long, default_value_t = false, requires = "verify")]
+        parallel_hash: bool,
+
+        /// After ingest, wrap the engram with each codec (`none,zstd:3,zstd:9,lz4`) and
+        /// emit one `encode.wrap.<codec>` measurement per entry.
+        #[arg(long, value_name = "CODEC[:LEVEL],...", value_delimiter = ',', value_parser = parse_codec_spec)]
+        codec_sweep: Vec<benches::encode::CodecSpec>,
+
+        /// Instead of --input, ingest N generated files of seeded random bytes (the
+        /// ingest measurement records the corpus as `corpus`).
+        #[arg(long, value_name = "N", conflicts_with = "input")]
+        synthetic_files: Option<usize>,
+
+        /// Bytes per synthetic file.
+        #[arg(
+            long,
+            value_name = "BYTES",
+            default_value_t = 8192,
+            requires = "synthetic_files",
+            conflicts_with = "input"
+        )]
+        synthetic_file_bytes: usize,
+
+        /// Fraction (0.0..=1.0) of the synthetic files that copy an earlier one.
+        #[arg(
+            long,
+            value_name = "R",
+            default_value_t = 0.0,
+            requires = "synthetic_files",
+            conflicts_with = "input"
+        )]
+        redundancy: f64,
+
+        /// Chance of each byte of a synthetic copy being replaced (0: exact copies).
+        #[arg(
+            long,
+            value_name = "M",
+            default_value_t = 0.0,
+            requires = "synthetic_files",
+            conflicts_with = "input"
+        )]
+        mutation_rate: f64,
     },
 
     /// Retrieval seam metrics (approx QPS/latency + recall@k vs brute force).
@@ -75,6 +302,123 @@ enum Command {
 
         #[arg(long)]
         queries: Option<usize>,
+
+        /// Sweep candidate_k over 1x, 2x, 4x, ... of k and emit one
+        /// `retrieval.frontier.cf<N>` measurement per point.
+        #[arg(long, default_value_t = false)]
+        frontier: bool,
+
+        /// Remove the query vectors from the codebook/index so no query has a self-match.
+        #[arg(long, default_value_t = false)]
+        holdout: bool,
+
+        /// Also serve the queries from N concurrent clients sharing the index, for each
+        /// N in the list, and emit `retrieval.concurrency.c<N>` with QPS and latency
+        /// percentiles per level. Each N must be at least 1.
+        #[arg(
+            long,
+            value_name = "N,..",
+            value_delimiter = ',',
+            value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..),
+            conflicts_with = "frontier"
+        )]
+        concurrency: Vec<usize>,
+
+        /// Compute brute-force ground truth for only this seeded fraction of the queries
+        /// and estimate recall from them, with a 95% interval in `recall_estimate`.
+        #[arg(long, value_name = "F")]
+        ground_truth_sample: Option<f64>,
+
+        /// Stop the ground-truth pass after SECS (Ctrl-C also stops it); recall is then
+        /// estimated from the queries finished by then.
+        #[arg(long, value_name = "SECS", value_parser = parse_seconds)]
+        ground_truth_timeout: Option<Duration>,
+
+        /// Replay the queries recorded in FILE (JSONL, in order) instead of using the
+        /// first corpus chunks. Each line is a base64 payload to encode, `{"data",
+        /// "path"}` for a payload under a logical path, or an explicit `{"pos", "neg"}`
+        /// vector. --queries caps how many are replayed.
+        #[arg(long, value_name = "FILE", conflicts_with = "holdout")]
+        query_file: Option<PathBuf>,
+    },
+
+    /// Raw TernaryInvertedIndex build/finalize/query over synthetic corpora
+    /// (1k for quick; 1k/5k/10k for full).
+    Index,
+
+    /// Raw dataset parse throughput (`vsa_dataset.reader.scan`): decode every vector of
+    /// the file with no VSA op, reporting vectors/s and MB/s.
+    DatasetBench {
+        /// Path to the dataset file.
+        #[arg(value_name = "FILE")]
+        path: PathBuf,
+    },
+
+    /// Write one generated dataset in every on-disk format and compare the formats
+    /// (`dataset_formats.<format>.{write,scan,packed_bind}`, tagged `format`): write
+    /// throughput, file size, scan throughput and a packed bind loop.
+    DatasetBenchFormats {
+        /// Generation parameters as JSON (count, dimension, seed, sparsity, and optionally
+        /// index_distribution and generator). Default: the generate-dataset defaults.
+        #[arg(long, value_name = "FILE")]
+        config: Option<PathBuf>,
+
+        /// Only this format (repeatable, in order). Default: every format the config can
+        /// be written in.
+        #[arg(long = "format", value_enum)]
+        formats: Vec<dataset::DatasetFormat>,
+
+        /// Write the format files into DIR and keep them (default: a temporary directory,
+        /// removed afterwards).
+        #[arg(long, value_name = "DIR")]
+        keep: Option<PathBuf>,
+    },
+
+    /// Interleave two sides op-block by op-block in one process and report paired
+    /// deltas (`duel.*`), so drift between separate runs cannot masquerade as a change.
+    Duel {
+        /// Side A: a substrate (sparsevec, packed, bitsliced, blocksparse) or a dataset file.
+        #[arg(long, value_name = "SUBSTRATE|FILE", value_parser = parse_duel_side)]
+        a: benches::duel::DuelSide,
+
+        /// Side B, of the same kind as A. Measurement times are B's; deltas are B vs A.
+        #[arg(long, value_name = "SUBSTRATE|FILE", value_parser = parse_duel_side)]
+        b: benches::duel::DuelSide,
+
+        /// Substrate both sides run on when they are dataset files.
+        #[arg(long, value_enum, default_value_t = benches::duel::DuelSubstrate::Sparsevec)]
+        substrate: benches::duel::DuelSubstrate,
+
+        /// Number of alternating blocks (default 30 quick / 60 full).
+        #[arg(long, value_name = "N")]
+        blocks: Option<u64>,
+
+        /// Cap the number of pairs loaded from each dataset.
+        #[arg(long, value_name = "N")]
+        max_ops: Option<u64>,
+    },
+
+    /// Run one op continuously, snapshotting throughput, latency percentiles and RSS
+    /// (`soak.*`); flags drift between the first and last snapshot. Ctrl-C finishes early.
+    Soak {
+        #[arg(long, value_enum)]
+        op: benches::soak::SoakOp,
+
+        /// How long to run (e.g. 90s, 10m).
+        #[arg(long, value_name = "DURATION", default_value = "60s", value_parser = parse_duration)]
+        duration: Duration,
+
+        /// Time between snapshots.
+        #[arg(long, value_name = "DURATION", default_value = "10s", value_parser = parse_duration)]
+        snapshot_interval: Duration,
+
+        /// Relative throughput drop or RSS growth from first to last snapshot to flag.
+        #[arg(long, value_name = "FRACTION", default_value_t = 0.10)]
+        drift_threshold: f64,
+
+        /// Cycle through the first vectors of this dataset instead of a seeded pool.
+        #[arg(long, value_name = "FILE")]
+        dataset: Option<PathBuf>,
     },
 
     /// Run all contract benches.
@@ -85,6 +429,18 @@ enum Command {
         #[arg(long, value_name = "DIR")]
         retrieval_input_dir: Option<PathBuf>,
 
+        /// Retrieval k (clamped to the corpus chunk count).
+        #[arg(long, value_name = "K", default_value_t = 10)]
+        retrieval_k: usize,
+
+        /// Retrieval candidate_k as a multiple of k.
+        #[arg(long, value_name = "N", default_value_t = 10)]
+        retrieval_candidate_factor: usize,
+
+        /// Retrieval query count (default: up to 100 quick / 1000 full).
+        #[arg(long, value_name = "N")]
+        retrieval_queries: Option<usize>,
+
         #[arg(long, default_value = "none")]
         codec: String,
 
@@ -97,6 +453,28 @@ enum Command {
         /// Which VSA substrate variant(s) to benchmark.
         #[arg(long, value_enum, default_value_t = VsaVariant::All)]
         variant: VsaVariant,
+
+        /// Also run the raw inverted index bench (see the `index` subcommand).
+        #[arg(long, default_value_t = false)]
+        index: bool,
+
+        /// Dataset file: adds the `vsa_dataset.*` benches and the reader scan baseline.
+        #[arg(long, value_name = "FILE")]
+        dataset: Option<PathBuf>,
+
+        /// Keep running the remaining sections when one fails. The report holds what
+        /// did run and the process still exits non-zero.
+        #[arg(long, default_value_t = false)]
+        keep_going: bool,
+
+        /// Run only this section (`vsa`, `vsa_dataset`, `dataset_io`, `encode`,
+        /// `retrieval`, `index`). Can be provided multiple times.
+        #[arg(long, value_name = "NAME")]
+        section: Vec<String>,
+
+        /// Print the sections these flags would run, one per line, and exit.
+        #[arg(long, default_value_t = false)]
+        list_sections: bool,
     },
 
     /// Generate a deterministic dataset of SparseVec vectors for scaled benchmarks.
@@ -124,6 +502,38 @@ enum Command {
         /// Vector dimension. Default is 10000.
         #[arg(long, default_value_t = 10_000)]
         dimension: usize,
+
+        /// Split generation into this many contiguous shards (use with --shard-index).
+        #[arg(long, value_name = "N", requires = "shard_index")]
+        shards: Option<u32>,
+
+        /// Generate only this shard (0-based), written as `<name>.shard<I>of<N>.embr`.
+        /// The union of all shards equals a single-process run bit-for-bit.
+        #[arg(long, value_name = "I", requires = "shards")]
+        shard_index: Option<u32>,
+
+        /// How indices are drawn: `uniform`, or `zipf:<s>` to skew them toward low ids
+        /// (hot dimensions), e.g. `zipf:1.1`.
+        #[arg(long, value_name = "DIST", default_value = "uniform", value_parser = parse_index_distribution)]
+        index_distribution: dataset::IndexDistribution,
+
+        /// Uniform sampling routine: `v2` draws only the needed indices, `v1` shuffles the
+        /// whole dimension per vector (reproduces datasets generated before v2). Recorded
+        /// in the header; v2 files are named `..._gen2.embr`.
+        #[arg(long, value_enum, default_value_t = dataset::GeneratorVersion::V2)]
+        generator: dataset::GeneratorVersion,
+
+        /// Fail instead of warning when the written file's size differs from what the
+        /// header and record lengths imply.
+        #[arg(long)]
+        strict_size: bool,
+
+        /// Also write a report (to --out/--out-dir, or stdout) with the generation's
+        /// throughput: `dataset.generate.vectors_per_s`, `dataset.generate.bytes_per_s`
+        /// and `dataset.generate.wall_s`, tagged with count, dimension, sparsity and
+        /// generator.
+        #[arg(long)]
+        report: bool,
     },
 
     /// Show metadata for a generated dataset file.
@@ -131,6 +541,182 @@ enum Command {
         /// Path to the dataset file.
         #[arg(value_name = "FILE")]
         path: PathBuf,
+
+        /// Also print N records: their pos/neg lengths and first indices, and density
+        /// stats over them. The first N, or N random ones seeded by an explicit --seed
+        /// (found by skipping through the file; only the sampled records are decoded).
+        #[arg(long, value_name = "N")]
+        sample: Option<usize>,
+
+        /// Print the --sample records as JSON on stdout instead of as text.
+        #[arg(long, default_value_t = false, requires = "sample")]
+        json: bool,
+    },
+
+    /// Check that a dataset file's body matches its header (record count, no trailing bytes),
+    /// and flag empty or degenerate vectors.
+    DatasetVerify {
+        /// Path to the dataset file.
+        #[arg(value_name = "FILE")]
+        path: PathBuf,
+    },
+
+    /// Check that a dataset file matches what the current generator would produce.
+    ///
+    /// Regenerates a seeded sample of records from the header's seed and compares them
+    /// against the file; exits with an error on the first mismatch.
+    DatasetCheckDeterminism {
+        /// Path to the dataset file.
+        #[arg(value_name = "FILE")]
+        path: PathBuf,
+
+        /// Number of seeded records to regenerate; the first and last are checked on top.
+        #[arg(long, default_value_t = 1000)]
+        sample: usize,
+    },
+
+    /// Remove duplicate vectors from a dataset file.
+    ///
+    /// Exact duplicates (identical indices) are always removed; with --near-threshold,
+    /// so are vectors within that cosine of an earlier one (found via LSH buckets, so a
+    /// near pair can be missed). The first occurrence is kept and labels are preserved.
+    DatasetDedupe {
+        /// Dataset to deduplicate.
+        #[arg(long, value_name = "FILE")]
+        input: PathBuf,
+
+        /// Where to write the deduplicated dataset (may be the input).
+        #[arg(long, value_name = "FILE")]
+        output: PathBuf,
+
+        /// Also remove vectors with cosine >= this to an earlier kept vector.
+        #[arg(long, value_name = "COSINE")]
+        near_threshold: Option<f64>,
+    },
+
+    /// Append vectors from a JSONL file to a dataset file in place.
+    ///
+    /// Each line is `{"pos": [...], "neg": [...]}`, plus `"label"` for a labeled
+    /// dataset. The records are written and synced before the header count is updated,
+    /// so an interrupted append leaves the old dataset readable; the next append drops
+    /// its leftovers. The dataset's sidecar, if any, is removed.
+    DatasetAppend {
+        /// JSONL vectors to append (`-` for stdin).
+        #[arg(long, value_name = "FILE")]
+        input: PathBuf,
+
+        /// Dataset to grow.
+        #[arg(long, value_name = "FILE")]
+        into: PathBuf,
+    },
+
+    /// Import criterion estimates (target/criterion) as contract measurements.
+    ///
+    /// Each benchmark becomes a `criterion.<group>.<function>[.<value>]` measurement
+    /// carrying the mean point estimate and its confidence interval.
+    ImportCriterion {
+        /// Criterion output directory.
+        #[arg(long, value_name = "DIR", default_value = "target/criterion")]
+        criterion_dir: PathBuf,
+    },
+
+    /// Compare a current report against a baseline report.
+    ///
+    /// Writes the comparison as JSON (to --out or stdout) and a summary to stderr.
+    Compare {
+        /// Baseline report.
+        #[arg(long, value_name = "FILE")]
+        baseline: PathBuf,
+
+        /// Current report.
+        #[arg(long, value_name = "FILE")]
+        current: PathBuf,
+
+        /// Align measurements by name plus these tag keys (e.g. `substrate,scale`).
+        #[arg(long, value_name = "KEY", value_delimiter = ',')]
+        match_tags: Vec<String>,
+
+        /// Relative ns_per_iter change treated as a regression or improvement.
+        #[arg(long, default_value_t = 0.10)]
+        threshold: f64,
+
+        /// Which way derived ratio measurements (`vsa.ratio.*`) improve; without it their
+        /// change is reported with no verdict.
+        #[arg(long, value_enum, value_name = "DIR")]
+        ratio_direction: Option<compare::RatioDirection>,
+
+        /// Exit with an error when any regression is found.
+        #[arg(long, default_value_t = false)]
+        fail_on_regression: bool,
+
+        /// Give regression verdicts between measurements below normal confidence
+        /// (quick-profile runs, capped datasets, noisy samples) instead of marking them
+        /// inconclusive.
+        #[arg(long, default_value_t = false)]
+        allow_low_confidence: bool,
+
+        /// Give verdicts on high-variance informational measurements (tagged
+        /// `variance=high`, e.g. `vsa.coldstart.*`) instead of reporting them ungated.
+        #[arg(long, default_value_t = false)]
+        gate_informational: bool,
+    },
+
+    /// Flag measurements whose latest value left their recent range, across a directory
+    /// of reports.
+    ///
+    /// Reports are ordered by timestamp. Each measurement's latest ns_per_iter is scored
+    /// against the median of its earlier values, in median absolute deviations. Writes the
+    /// trend as JSON (to --out or stdout) and a table to stderr.
+    Trend {
+        /// Directory of reports (e.g. collected `--out-dir` output).
+        #[arg(long, value_name = "DIR")]
+        reports_dir: PathBuf,
+
+        /// Flag values more than this many MADs from the trailing median.
+        #[arg(long, value_name = "X", default_value_t = 3.0)]
+        mads: f64,
+
+        /// Earlier values the median is taken over (the most recent ones).
+        #[arg(long, value_name = "N", default_value_t = 10, value_parser = clap::value_parser!(u64).range(1..))]
+        window: u64,
+
+        /// Earlier values a measurement needs before it is scored.
+        #[arg(long, value_name = "N", default_value_t = 3, value_parser = clap::value_parser!(u64).range(1..))]
+        min_history: u64,
+
+        /// Align measurements by name plus these tag keys (e.g. `substrate,scale`).
+        #[arg(long, value_name = "KEY", value_delimiter = ',')]
+        match_tags: Vec<String>,
+
+        /// Exit with an error when any measurement trends slower.
+        #[arg(long, default_value_t = false)]
+        fail_on_regression: bool,
+    },
+
+    /// Write reports in a form other tools read.
+    Export {
+        #[command(subcommand)]
+        format: ExportFormat,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ExportFormat {
+    /// Long-form CSV, one row per (report, measurement, metric), to --out or stdout.
+    ///
+    /// Columns: run_id (the report file name), timestamp_utc, profile, seed, git_sha,
+    /// run_tags, measurement, unit, measurement_tags, metric, value. Metrics are the
+    /// timing fields, the latency percentiles (p50_ms, ...) and any --extra-keys.
+    Tidy {
+        /// Reports to export, in order (`.json` or `.json.gz`).
+        #[arg(value_name = "REPORT", required = true)]
+        reports: Vec<PathBuf>,
+
+        /// Also export these keys of each measurement's `extra` (dotted paths, e.g.
+        /// `recall_at_k,cosine_values.mean`). Values that are not numbers are skipped
+        /// with a warning.
+        #[arg(long, value_name = "KEY", value_delimiter = ',')]
+        extra_keys: Vec<String>,
     },
 }
 
@@ -144,66 +730,775 @@ struct Args {
     #[arg(long, default_value_t = 0, global = true)]
     seed: u64,
 
+    /// Whether --seed was given rather than defaulted.
+    #[arg(skip)]
+    seed_given: bool,
+
+    /// Label the run, e.g. `--tag branch=feature-x`. Can be provided multiple times.
+    #[arg(long = "tag", value_name = "KEY=VALUE", value_parser = parse_tag, global = true)]
+    tags: Vec<(String, String)>,
+
+    /// Free-text note recorded in the report (`run.notes`) and shown by the summary,
+    /// compare and trend, e.g. `--note "governor pinned to performance"`. Can be provided
+    /// multiple times.
+    #[arg(long = "note", value_name = "TEXT", global = true)]
+    notes: Vec<String>,
+
+    /// Absolute contract on the results, `[MEASUREMENT:]FIELD OP VALUE`, e.g.
+    /// `retrieval.query_pass_with_index:p99_ms<5` or `recall_at_k>0.95`. Checked
+    /// after every bench has run (also under --keep-going); any violation exits
+    /// non-zero after the report is written. Can be provided multiple times.
+    #[arg(long = "assert", value_name = "ASSERTION", value_parser = Assertion::parse, global = true)]
+    assertions: Vec<Assertion>,
+
     /// Where to write the JSON report. If omitted, prints to stdout.
     #[arg(long, global = true)]
     out: Option<PathBuf>,
 
+    /// Write the report into this directory under an auto-generated name
+    /// (`report_<subcommand>_<variant-or-args>_<profile>_<seed>_<timestamp>.json`).
+    #[arg(long, value_name = "DIR", global = true, conflicts_with = "out")]
+    out_dir: Option<PathBuf>,
+
+    /// Gzip the JSON output. Implied by an --out path ending in `.gz`; with --out-dir
+    /// the generated name ends in `.json.gz`. Every command that reads reports takes
+    /// either form.
+    #[arg(long, default_value_t = false, global = true)]
+    compress_out: bool,
+
+    /// Create the parent directories of --out if they are missing. Without it a
+    /// missing directory is an error at startup, before any benchmarking.
+    #[arg(long, default_value_t = false, global = true, requires = "out")]
+    create_out_dir: bool,
+
+    /// Refuse to run benches unless the cpufreq governor is `performance` (for CI
+    /// machines we control). An unknown governor (non-Linux, no cpufreq) also refuses.
+    #[arg(long, default_value_t = false, global = true)]
+    require_performance_governor: bool,
+
+    /// Pause this long before every measurement but the first, so a heavy measurement
+    /// doesn't leave the next one on a throttled CPU. Recorded in the report.
+    #[arg(long, value_name = "MS", default_value_t = 0, global = true)]
+    cooldown_ms: u64,
+
+    /// After each cooldown pause, also wait for the hottest thermal zone and the load
+    /// average to return near their values at run start (up to --cooldown-max-wait-ms).
+    #[arg(long, default_value_t = false, global = true)]
+    cooldown_until_idle: bool,
+
+    /// Longest a --cooldown-until-idle wait may last.
+    #[arg(long, value_name = "MS", default_value_t = 30_000, global = true)]
+    cooldown_max_wait_ms: u64,
+
+    /// Follow symlinks when collecting encode/retrieval input files (loops are skipped
+    /// with a warning). By default symlinks are left out.
+    #[arg(long, default_value_t = false, global = true)]
+    follow_symlinks: bool,
+
+    /// Leave out hidden encode/retrieval input files and directories (names starting
+    /// with `.`). By default they are collected like any other.
+    #[arg(long, default_value_t = false, global = true)]
+    exclude_hidden: bool,
+
+    /// Leave out encode/retrieval input files larger than this many bytes.
+    #[arg(long, value_name = "BYTES", global = true)]
+    max_file_size: Option<u64>,
+
+    /// Leave out encode/retrieval input paths matching this glob (relative to the input
+    /// root; `*`, `**`, `?`). A glob without `/` matches any path component. Can be
+    /// provided multiple times.
+    #[arg(long, value_name = "GLOB", global = true)]
+    ignore: Vec<String>,
+
+    /// Ingest encode/retrieval input files in pieces of at most this many bytes, giving
+    /// chunks of that size when below the library's own chunk size (4096). The values in
+    /// effect are recorded under `chunking` either way.
+    #[arg(long, value_name = "BYTES", global = true, value_parser = clap::value_parser!(u64).range(1..))]
+    chunk_size: Option<u64>,
+
+    /// Read dataset files through a buffer of this many KiB (default 64). Recorded as
+    /// `read_buffer_bytes` in the dataset measurements.
+    #[arg(long, value_name = "KIB", global = true, value_parser = clap::value_parser!(u64).range(1..))]
+    read_buffer_kib: Option<u64>,
+
+    /// Keep up to N raw timings per measurement, as its `samples` extra, for offline
+    /// statistics: loops are timed in at most N batches, and loops timed per iteration
+    /// (the dataset ops, one extra clock read each) keep a reservoir sample of N.
+    #[arg(long, value_name = "N", global = true, value_parser = clap::value_parser!(u64).range(1..))]
+    record_samples: Option<u64>,
+
+    /// Also emit each dataset/retrieval measurement's ops/s figure as a sibling
+    /// `<name>.ops_per_s` measurement (unit `ops/s`, higher is better in compare).
+    #[arg(long, default_value_t = false, global = true)]
+    emit_throughput: bool,
+
+    /// Don't print the summary table to stderr after a run, retrieval's ground-truth
+    /// progress during it, or a dry run's plan table.
+    #[arg(long, default_value_t = false, global = true)]
+    quiet: bool,
+
+    /// Flag the N slowest measurements in the summary table.
+    #[arg(long, value_name = "N", default_value_t = 5, global = true)]
+    summary_top: usize,
+
+    /// Report to show deltas against in the summary table. Must precede the subcommand
+    /// (`embeddenator-contract-bench --baseline old.json vsa`).
+    #[arg(long, value_name = "FILE")]
+    baseline: Option<PathBuf>,
+
+    /// Print what a bench subcommand would measure (iterations, estimated time) and exit
+    /// without writing a report. Each measurement is sampled for --calibration-iters;
+    /// benches that would have to run in full to be sampled are listed as unplanned.
+    #[arg(long, default_value_t = false, global = true)]
+    dry_run: bool,
+
+    /// Like --dry-run, and print the plan as JSON on stdout.
+    #[arg(long, default_value_t = false, global = true)]
+    dry_run_json: bool,
+
+    /// Timed iterations sampled per measurement in a dry run (0 lists without estimates).
+    #[arg(long, value_name = "N", default_value_t = 3, global = true, value_parser = clap::value_parser!(u64).range(0..=3))]
+    calibration_iters: u64,
+
+    /// Size of the rayon pool used for dataset generation, batch substrate conversion
+    /// and the brute-force retrieval baseline. Defaults to one thread per CPU.
+    #[arg(long, value_name = "N", global = true, value_parser = clap::value_parser!(u64).range(1..))]
+    threads: Option<u64>,
+
+    /// Run even when the disk space preflight finds less free space than the run is
+    /// estimated to need (generated datasets, engrams, extractions, split inputs); the
+    /// shortfall is warned about and recorded under `disk_space`.
+    #[arg(long, default_value_t = false, global = true)]
+    ignore_space_check: bool,
+
+    /// JSON file of `ReversibleVSAConfig` fields (block_size, max_path_depth, base_shift,
+    /// target_sparsity) to encode with instead of the library defaults, in vsa (without
+    /// --dataset), encode, retrieval and suite runs. Fields left out keep their defaults;
+    /// the effective config is recorded as `run.vsa_config` by the runs that use it.
+    #[arg(long, value_name = "FILE", global = true)]
+    vsa_config: Option<PathBuf>,
+
+    /// Write a small JSON exit status here (exit code, sections run/failed, report
+    /// path, run id, wall time) on every exit path, for wrapper scripts.
+    #[arg(long, value_name = "PATH", global = true)]
+    status_file: Option<PathBuf>,
+
     #[command(subcommand)]
     cmd: Command,
 }
 
-fn now_utc_rfc3339() -> String {
-    // Avoid adding chrono dependency; this is "good enough" for filenames + reports.
-    // Format: YYYY-MM-DDTHH:MM:SSZ
-    use std::time::{SystemTime, UNIX_EPOCH};
-    let secs = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_secs();
-    format!("unix:{secs}")
+fn variant_name(v: VsaVariant) -> String {
+    v.to_possible_value()
+        .map(|p| p.get_name().to_string())
+        .unwrap_or_else(|| format!("{v:?}"))
+}
+
+/// Subcommand name plus the variant/argument segments used in `--out-dir` file names.
+fn report_name_detail(cmd: &Command) -> io::Result<(&'static str, Vec<String>)> {
+    Ok(match cmd {
+        Command::Vsa {
+            variant,
+            dataset,
+            check_bundle_semantics,
+            rotate_inputs,
+            input_class,
+            density_sweep,
+            cold_start,
+            ..
+        } => {
+            let mut detail = vec![variant_name(*variant)];
+            if *rotate_inputs > 1 {
+                detail.push(format!("rot{rotate_inputs}"));
+            }
+            if *input_class != InputClass::Random {
+                detail.push(input_class.label().to_string());
+            }
+            if *density_sweep {
+                detail.push("density-sweep".to_string());
+            }
+            if *cold_start {
+                detail.push("cold-start".to_string());
+            }
+            let mut scales = Vec::new();
+            for path in dataset {
+                match dataset::DatasetSource::from_arg(path) {
+                    dataset::DatasetSource::File(path) => {
+                        scales.push(dataset::format_count(
+                            dataset::read_dataset_meta(path)?.count,
+                        ));
+                    }
+                    // Reading the header here would consume stdin.
+                    _ => scales.push("stdin".to_string()),
+                }
+            }
+            if !scales.is_empty() {
+                detail.push(scales.join("+"));
+            }
+            if *check_bundle_semantics {
+                detail.push("bundle-semantics".to_string());
+            }
+            ("vsa", detail)
+        }
+        Command::Encode {
+            codec,
+            verify,
+            codec_sweep,
+            synthetic_files,
+            ..
+        } => {
+            let mut detail = vec![codec.clone()];
+            if *verify {
+                detail.push("verify".to_string());
+            }
+            if synthetic_files.is_some() {
+                detail.push("synthetic".to_string());
+            }
+            if !codec_sweep.is_empty() {
+                detail.push("sweep".to_string());
+            }
+            ("encode", detail)
+        }
+        Command::Retrieval {
+            k,
+            frontier,
+            holdout,
+            concurrency,
+            ground_truth_sample,
+            query_file,
+            ..
+        } => {
+            let mut detail = vec![format!("k{k}")];
+            if *frontier {
+                detail.push("frontier".to_string());
+            }
+            if *holdout {
+                detail.push("holdout".to_string());
+            }
+            if !concurrency.is_empty() {
+                let levels: Vec<String> = concurrency.iter().map(|n| n.to_string()).collect();
+                detail.push(format!("c{}", levels.join("-")));
+            }
+            if let Some(f) = ground_truth_sample {
+                detail.push(format!("gt{f}"));
+            }
+            if let Some(stem) = query_file.as_ref().and_then(|p| p.file_stem()) {
+                detail.push(format!("replay-{}", stem.to_string_lossy()));
+            }
+            ("retrieval", detail)
+        }
+        Command::Index => ("index", Vec::new()),
+        Command::Duel { a, b, .. } => {
+            let side = |s: &benches::duel::DuelSide| match s {
+                benches::duel::DuelSide::Substrate(s) => s.name().to_string(),
+                benches::duel::DuelSide::Dataset(path) => path
+                    .file_stem()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+            };
+            ("duel", vec![side(a), side(b)])
+        }
+        Command::Soak { op, duration, .. } => (
+            "soak",
+            vec![
+                op.name().replace('.', "-"),
+                format!("{}s", duration.as_secs()),
+            ],
+        ),
+        Command::DatasetBench { path } => (
+            "dataset-bench",
+            vec![dataset::format_count(
+                dataset::read_dataset_meta(path)?.count,
+            )],
+        ),
+        Command::DatasetBenchFormats { .. } => ("dataset-bench-formats", Vec::new()),
+        Command::Suite { variant, .. } => ("suite", vec![variant_name(*variant)]),
+        Command::GenerateDataset { .. } => ("generate-dataset", Vec::new()),
+        Command::DatasetInfo { .. } => ("dataset-info", Vec::new()),
+        Command::DatasetVerify { .. } => ("dataset-verify", Vec::new()),
+        Command::DatasetCheckDeterminism { .. } => ("dataset-check-determinism", Vec::new()),
+        Command::DatasetDedupe { .. } => ("dataset-dedupe", Vec::new()),
+        Command::DatasetAppend { .. } => ("dataset-append", Vec::new()),
+        Command::ImportCriterion { .. } => ("import-criterion", Vec::new()),
+        Command::Compare { .. } => ("compare", Vec::new()),
+        Command::Trend { .. } => ("trend", Vec::new()),
+        Command::Export { .. } => ("export", Vec::new()),
+    })
+}
+
+/// Resolve `--out` / `--out-dir` to the file the JSON output goes to (None = stdout).
+fn resolve_out(args: &Args, cfg: &BenchConfig) -> io::Result<Option<PathBuf>> {
+    let Some(dir) = &args.out_dir else {
+        return Ok(args.out.clone());
+    };
+    let (subcommand, detail) = report_name_detail(&args.cmd)?;
+    let mut name = schema::report_file_name(
+        subcommand,
+        &detail,
+        cfg.profile.as_str(),
+        cfg.seed,
+        unix_secs(),
+    );
+    if args.compress_out {
+        name.push_str(".gz");
+    }
+    fs::create_dir_all(dir)?;
+    let path = dir.join(name);
+    eprintln!("Writing report to {}", path.display());
+    Ok(Some(path))
 }
 
-fn git_sha_short() -> Option<String> {
-    // Best-effort: read from environment set by CI/build scripts.
-    std::env::var("GIT_SHA")
+/// Whether the output written to `out` is gzipped.
+fn compress_out(args: &Args, out: &Path) -> bool {
+    args.compress_out || gzip::is_gz_path(out)
+}
+
+/// Fail at startup, not after an hour of benchmarking, when the output cannot be written.
+fn check_out_target(args: &Args) -> io::Result<()> {
+    let (flag, probe) = match (&args.out, &args.out_dir) {
+        (Some(out), _) => {
+            if args.create_out_dir {
+                if let Some(parent) = out.parent().filter(|p| !p.as_os_str().is_empty()) {
+                    fs::create_dir_all(parent)?;
+                }
+            }
+            ("--out", out.clone())
+        }
+        (None, Some(dir)) => {
+            fs::create_dir_all(dir)?;
+            ("--out-dir", dir.join("report.json"))
+        }
+        (None, None) => return Ok(()),
+    };
+    atomic_write::check_writable(&probe).map_err(|e| {
+        let hint = if e.kind() == io::ErrorKind::NotFound {
+            " (pass --create-out-dir to create it)"
+        } else {
+            ""
+        };
+        io::Error::new(e.kind(), format!("{flag}: {e}{hint}"))
+    })
+}
+
+/// Subcommands that time code on this host, and so record and check its state.
+fn is_bench(cmd: &Command) -> bool {
+    matches!(
+        cmd,
+        Command::Vsa { .. }
+            | Command::Encode { .. }
+            | Command::Retrieval { .. }
+            | Command::Index
+            | Command::Duel { .. }
+            | Command::Soak { .. }
+            | Command::DatasetBench { .. }
+            | Command::DatasetBenchFormats { .. }
+            | Command::Suite { .. }
+    )
+}
+
+/// Whether `cmd` encodes with `--vsa-config`, so that the report records it.
+fn applies_vsa_config(cmd: &Command) -> bool {
+    match cmd {
+        Command::Vsa { dataset, .. } => dataset.is_empty(),
+        Command::Encode { .. } | Command::Retrieval { .. } | Command::Suite { .. } => true,
+        _ => false,
+    }
+}
+
+fn check_governor(env: &Environment, require_performance: bool) -> io::Result<()> {
+    match env.governor() {
+        Some(PERFORMANCE_GOVERNOR) => Ok(()),
+        governor if require_performance => Err(io::Error::other(format!(
+            "--require-performance-governor: cpufreq governor is {}",
+            governor.unwrap_or("unknown")
+        ))),
+        Some(governor) => {
+            eprintln!(
+                "warning: cpufreq governor is `{governor}`, not `{PERFORMANCE_GOVERNOR}`; timings may swing with frequency scaling"
+            );
+            Ok(())
+        }
+        None => Ok(()),
+    }
+}
+
+/// `dataset-info --sample` as text, under the dataset's summary on stderr.
+fn print_dataset_sample(sample: &dataset::DatasetSample) {
+    let how = match sample.seed {
+        Some(seed) => format!("seeded, seed {seed}"),
+        None => "first records".to_string(),
+    };
+    eprintln!(
+        "  Sample: {} of {} requested ({how})",
+        sample.records.len(),
+        sample.requested
+    );
+    for r in &sample.records {
+        eprintln!(
+            "    #{}: pos {} {:?}, neg {} {:?}, density {:.4}",
+            r.index, r.pos_len, r.pos_head, r.neg_len, r.neg_head, r.density
+        );
+    }
+    if let Some(d) = &sample.density {
+        eprintln!(
+            "  Sample density: mean {:.4} (min {:.4}, max {:.4}), {:.1} nonzero trits per record",
+            d.mean, d.min, d.max, d.mean_nonzero
+        );
+    }
+}
+
+fn print_summary(args: &Args, report: &ContractBenchReport) -> io::Result<()> {
+    let cmp = match &args.baseline {
+        Some(path) => {
+            // Align on every tag the current run uses so labelled variants stay apart.
+            let mut match_tags: Vec<String> = report
+                .measurements
+                .iter()
+                .flat_map(|m| m.tags.keys().cloned())
+                .collect();
+            match_tags.sort();
+            match_tags.dedup();
+            let opts = CompareOptions {
+                match_tags,
+                ..Default::default()
+            };
+            Some(compare::compare_reports(
+                &schema::load_report(path)?,
+                report,
+                &opts,
+            ))
+        }
+        None => None,
+    };
+    let opts = SummaryOptions {
+        slowest: args.summary_top,
+        notes: report.run.notes.clone(),
+    };
+    eprint!(
+        "{}",
+        summary::render(&report.measurements, cmp.as_ref(), &opts)
+    );
+    Ok(())
+}
+
+/// One of several `vsa --dataset` runs. `scales` holds those already run: a dataset
+/// with the same vector count would produce measurements indistinguishable from theirs.
+fn run_dataset_once(
+    cfg: &BenchConfig,
+    variant: VsaVariant,
+    source: &dataset::DatasetSource,
+    opts: &benches::vsa::DatasetRunOptions,
+    scales: &mut BTreeSet<String>,
+) -> io::Result<Vec<Measurement>> {
+    let duplicate = |scale: &str| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "{} has {scale} vectors, like an earlier --dataset; their measurements would share the scale tag",
+                source.label()
+            ),
+        )
+    };
+    // Files are checked before running; stdin only has its count once it is read.
+    if let dataset::DatasetSource::File(path) = source {
+        let scale = dataset::format_count(dataset::read_dataset_meta(path)?.count);
+        if scales.contains(&scale) {
+            return Err(duplicate(&scale));
+        }
+    }
+    let ms = benches::vsa::run_dataset(cfg, variant, source, opts)?;
+    if let Some(scale) = ms.first().and_then(|m| m.tags.get("scale")) {
+        if !scales.insert(scale.clone()) {
+            return Err(duplicate(scale));
+        }
+    }
+    Ok(ms)
+}
+
+fn parse_tag(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((k, v)) if !k.is_empty() => Ok((k.to_string(), v.to_string())),
+        _ => Err(format!("expected KEY=VALUE, got `{s}`")),
+    }
+}
+
+fn parse_op_weight(s: &str) -> Result<(String, f64), String> {
+    s.split_once('=')
+        .and_then(|(name, w)| Some((name.to_string(), w.parse::<f64>().ok()?)))
+        .filter(|(name, w)| !name.is_empty() && w.is_finite() && *w > 0.0)
+        .ok_or_else(|| format!("expected NAME=WEIGHT with a positive weight, got `{s}`"))
+}
+
+fn parse_seconds(s: &str) -> Result<Duration, String> {
+    s.parse::<f64>()
         .ok()
-        .or_else(|| std::env::var("GITHUB_SHA").ok())
-        .map(|s| s.chars().take(12).collect())
+        .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+        .ok_or_else(|| format!("expected a non-negative number of seconds, got `{s}`"))
+}
+
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    benches::soak::parse_duration(s)
 }
 
 fn parse_codec(s: &str) -> io::Result<embeddenator::envelope::CompressionCodec> {
-    match s.to_ascii_lowercase().as_str() {
-        "none" => Ok(embeddenator::envelope::CompressionCodec::None),
-        "zstd" => Ok(embeddenator::envelope::CompressionCodec::Zstd),
-        "lz4" => Ok(embeddenator::envelope::CompressionCodec::Lz4),
-        _ => Err(io::Error::other(format!("unknown codec: {s} (none|zstd|lz4)"))),
-    }
+    benches::encode::parse_codec(s)
 }
 
-/// Format vector count as human-readable suffix (10k, 100k, 1m, etc.)
-fn format_count(count: u64) -> String {
-    match count {
-        n if n >= 1_000_000 && n % 1_000_000 == 0 => format!("{}m", n / 1_000_000),
-        n if n >= 1_000 && n % 1_000 == 0 => format!("{}k", n / 1_000),
-        n => n.to_string(),
+fn parse_index_distribution(s: &str) -> Result<dataset::IndexDistribution, String> {
+    dataset::IndexDistribution::parse(s).map_err(|e| e.to_string())
+}
+
+fn parse_duel_side(s: &str) -> Result<benches::duel::DuelSide, String> {
+    Ok(benches::duel::DuelSide::parse(s))
+}
+
+fn parse_codec_spec(s: &str) -> Result<benches::encode::CodecSpec, String> {
+    benches::encode::CodecSpec::parse(s).map_err(|e| e.to_string())
+}
+
+fn main() -> ExitCode {
+    let matches = Args::command().get_matches();
+    let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    args.seed_given = matches.value_source("seed") == Some(ValueSource::CommandLine);
+    let started = Instant::now();
+    let mut status = RunStatus::new(matches.subcommand_name().unwrap_or_default(), unix_secs());
+
+    let result = run(&args, &mut status);
+    if let Err(e) = &result {
+        eprintln!("Error: {e:?}");
+    }
+    if let Some(path) = &args.status_file {
+        status.finish(&result, started.elapsed());
+        if let Err(e) = status.write(path) {
+            eprintln!(
+                "warning: could not write status file {}: {e}",
+                path.display()
+            );
+        }
+    }
+    if result.is_ok() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
     }
 }
 
-fn main() -> io::Result<()> {
-    let args = Args::parse();
+fn run(args: &Args, status: &mut RunStatus) -> io::Result<()> {
     let cfg = BenchConfig {
         profile: args.profile.into(),
         seed: args.seed,
     };
 
+    if let Some(threads) = args.threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads as usize)
+            .build_global()
+            .map_err(io::Error::other)?;
+    }
+
+    // A dry run walks the benches with every measurement cut to a calibration sample.
+    let dry_run = args.dry_run || args.dry_run_json;
+    if !args.assertions.is_empty() && !is_bench(&args.cmd) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "--assert only applies to bench subcommands, not {}",
+                status.subcommand
+            ),
+        ));
+    }
+    if dry_run && !is_bench(&args.cmd) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "--dry-run only plans bench subcommands, not {}",
+                status.subcommand
+            ),
+        ));
+    }
+    let mut plan = dry_run.then(|| {
+        Plan::new(
+            &status.subcommand,
+            cfg.profile.as_str(),
+            args.calibration_iters,
+        )
+    });
+    let _calibration = dry_run.then(|| harness::calibration(args.calibration_iters));
+    if plan.is_none() {
+        check_out_target(args)?;
+    }
+    let cooldown = (is_bench(&args.cmd)
+        && plan.is_none()
+        && (args.cooldown_ms > 0 || args.cooldown_until_idle))
+        .then(|| {
+            harness::cooldown(harness::Cooldown {
+                pause_ms: args.cooldown_ms,
+                until_idle_max_wait_ms: args
+                    .cooldown_until_idle
+                    .then_some(args.cooldown_max_wait_ms),
+                ..Default::default()
+            })
+        });
+    let recording = args
+        .record_samples
+        .filter(|_| is_bench(&args.cmd) && plan.is_none())
+        .map(|n| harness::record_samples(n as usize));
+    let walk = WalkOptions {
+        follow_symlinks: args.follow_symlinks,
+        exclude_hidden: args.exclude_hidden,
+        max_file_size: args.max_file_size,
+        ignore: args.ignore.clone(),
+    };
+    let chunk_size = args.chunk_size.map(|c| c as usize);
+    let vsa_config = match &args.vsa_config {
+        Some(path) => VsaConfig::load(path)?,
+        None => VsaConfig::default(),
+    };
+    let read_buffer = args.read_buffer_kib.map(|k| k as usize * 1024);
+    let started = Instant::now();
+
+    let mut environment = (is_bench(&args.cmd) && plan.is_none()).then(Environment::start);
+    if let Some(env) = &environment {
+        check_governor(env, args.require_performance_governor)?;
+    }
+
     let mut measurements = Vec::new();
+    // Free disk space is checked before a bench that writes starts, and polled while it runs.
+    let mut disk_watch = None;
+    // A contract check that failed; reported after the report is written.
+    let mut contract_failure: Option<String> = None;
 
+    if !matches!(args.cmd, Command::Suite { .. }) {
+        status.begin(&status.subcommand.clone());
+    }
     match &args.cmd {
-        Command::Vsa { variant, dataset } => {
-            if let Some(path) = dataset {
-                measurements.extend(benches::vsa::run_dataset(&cfg, *variant, path)?);
+        Command::Vsa {
+            variant,
+            dataset,
+            zero_copy,
+            validate_dataset,
+            check_bundle_semantics,
+            bundle_threshold,
+            rotate_inputs,
+            input_class,
+            density_sweep,
+            cold_start,
+            capacity_threshold,
+            ops,
+            max_ops,
+            op_weights,
+            ops_budget,
+            stage_breakdown,
+            collect_values,
+            roundtrip_check,
+            pair_stride,
+            pair_offset,
+            validate_vectors,
+            strict,
+            resume,
+            stop_after,
+            strict_contract,
+            keep_going,
+        } => {
+            if *check_bundle_semantics {
+                let check = benches::bundle_semantics::check(&cfg, *bundle_threshold);
+                if !check.pass {
+                    contract_failure = Some(format!(
+                        "bundle semantics: min pairwise cosine {:.4} below threshold {:.4}",
+                        check.min_cosine, bundle_threshold
+                    ));
+                }
+                measurements.push(check.measurement);
+            } else if let (false, Some(plan)) = (dataset.is_empty(), &mut plan) {
+                plan.unplanned("vsa_dataset", plan::STREAMED);
+            } else if !dataset.is_empty() {
+                if dataset.len() > 1 && resume.is_some() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "--resume checkpoints one dataset; pass a single --dataset",
+                    ));
+                }
+                if dataset.iter().filter(|p| p.as_os_str() == "-").count() > 1 {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "stdin (`--dataset -`) can only be read once",
+                    ));
+                }
+                let opts = benches::vsa::DatasetRunOptions {
+                    zero_copy: *zero_copy,
+                    validate: *validate_dataset,
+                    max_ops: *max_ops,
+                    op_weights: op_weights.iter().cloned().collect(),
+                    validate_vectors: *validate_vectors,
+                    strict: *strict,
+                    resume: resume.clone(),
+                    stop_after: *stop_after,
+                    ops_budget: *ops_budget,
+                    stage_breakdown: *stage_breakdown,
+                    ops: (!ops.is_empty()).then(|| ops.clone()),
+                    read_buffer,
+                    collect_values: *collect_values,
+                    roundtrip_check: *roundtrip_check,
+                    pair_layout: dataset::GroupLayout {
+                        stride: pair_stride.unwrap_or(1),
+                        offset: pair_offset.unwrap_or(0),
+                    },
+                };
+                if dataset.len() == 1 {
+                    let source = dataset::DatasetSource::from_arg(&dataset[0]);
+                    measurements.extend(benches::vsa::run_dataset(&cfg, *variant, &source, &opts)?);
+                } else {
+                    // One status section per dataset, so --keep-going can skip past a bad one.
+                    let mut scales = BTreeSet::new();
+                    let mut failed = Vec::new();
+                    for path in dataset {
+                        let source = dataset::DatasetSource::from_arg(path);
+                        let section = format!("vsa_dataset:{}", source.label());
+                        let result = status.section(&section, || {
+                            run_dataset_once(&cfg, *variant, &source, &opts, &mut scales)
+                        });
+                        match result {
+                            Ok(ms) => measurements.extend(ms),
+                            Err(e) if *keep_going => {
+                                eprintln!("warning: {section} failed: {e}");
+                                failed.push(source.label());
+                            }
+                            Err(e) => return Err(e),
+                        }
+                    }
+                    if !failed.is_empty() {
+                        contract_failure =
+                            Some(format!("dataset(s) failed: {}", failed.join(", ")));
+                    }
+                }
             } else {
-                measurements.extend(benches::vsa::run(&cfg, *variant));
+                let opts = benches::vsa::RunOptions {
+                    rotate_inputs: *rotate_inputs,
+                    input_class: *input_class,
+                    capacity_threshold: *capacity_threshold,
+                    ops: (!ops.is_empty()).then(|| ops.clone()),
+                    density_sweep: *density_sweep,
+                    vsa_config: vsa_config.clone(),
+                    cold_start: *cold_start,
+                };
+                measurements.extend(benches::vsa::run(&cfg, *variant, &opts));
+                let capacity = measurements
+                    .iter()
+                    .find(|m| m.name == measurements::vsa::CONTRACT_BUNDLE_CAPACITY);
+                if let (Some(threshold), Some(m)) = (capacity_threshold, capacity) {
+                    if m.extra["pass"] == false {
+                        contract_failure = Some(format!(
+                            "bundle capacity: mean component cosine below {threshold} (capacity per strategy: {})",
+                            m.extra["capacity"]
+                        ));
+                    }
+                }
+            }
+            if *strict_contract {
+                let failed = assertions::failed_contracts(&measurements);
+                if !failed.is_empty() {
+                    contract_failure =
+                        Some(format!("contract check(s) failed: {}", failed.join(", ")));
+                }
             }
         }
         Command::Encode {
@@ -212,61 +1507,260 @@ fn main() -> io::Result<()> {
             codec,
             level,
             verify,
+            parallel_hash,
+            codec_sweep,
+            synthetic_files,
+            synthetic_file_bytes,
+            redundancy,
+            mutation_rate,
         } => {
             let codec = parse_codec(codec)?;
+            let synthetic = synthetic_files.map(|files| benches::corpus::CorpusConfig {
+                files,
+                file_bytes: *synthetic_file_bytes,
+                redundancy: *redundancy,
+                mutation_rate: *mutation_rate,
+            });
             let enc_args = benches::encode::EncodeArgs {
                 inputs: input.clone(),
                 prefix: prefix.clone(),
                 codec,
                 codec_level: *level,
                 verify: *verify,
+                parallel_hash: *parallel_hash,
+                codec_sweep: codec_sweep.clone(),
+                walk: walk.clone(),
+                chunk_size,
+                vsa_config: vsa_config.clone(),
+                synthetic: synthetic.clone(),
             };
-            measurements.extend(benches::encode::run(&cfg, &enc_args)?);
+            if let Some(plan) = &mut plan {
+                plan.unplanned("encode", plan::INGESTS);
+            } else {
+                let requirements = match &synthetic {
+                    Some(corpus) => {
+                        disk_space::synthetic_encode_requirements(corpus.total_bytes(), chunk_size)
+                    }
+                    None => disk_space::encode_requirements(input, &walk, chunk_size),
+                };
+                disk_watch = disk_space::watch(&requirements, args.ignore_space_check)?;
+                measurements.extend(benches::encode::run(&cfg, &enc_args)?);
+            }
         }
         Command::Retrieval {
             input_dir,
             k,
             candidate_factor,
             queries,
+            frontier,
+            holdout,
+            concurrency,
+            ground_truth_sample,
+            ground_truth_timeout,
+            query_file,
         } => {
             let r_args = benches::retrieval::RetrievalArgs {
                 input_dir: input_dir.clone(),
                 k: *k,
                 candidate_factor: *candidate_factor,
                 queries: *queries,
+                frontier: *frontier,
+                holdout: *holdout,
+                concurrency: concurrency.clone(),
+                ground_truth_sample: *ground_truth_sample,
+                ground_truth_timeout: *ground_truth_timeout,
+                walk: walk.clone(),
+                chunk_size,
+                query_file: query_file.clone(),
+                vsa_config: vsa_config.clone(),
             };
-            measurements.extend(benches::retrieval::run(&cfg, &r_args)?);
+            if let Some(plan) = &mut plan {
+                plan.unplanned("retrieval", plan::INGESTS);
+            } else {
+                disk_watch = disk_space::watch(
+                    &disk_space::retrieval_requirements(input_dir, &walk, chunk_size),
+                    args.ignore_space_check,
+                )?;
+                // Ground-truth progress on stderr unless quiet; skips are warned about
+                // either way.
+                let mut report = sink::Progress::warnings(io::stderr());
+                if !args.quiet {
+                    report = report.with_progress();
+                }
+                let mut taken = Vec::new();
+                benches::retrieval::run_with_sink(
+                    &cfg,
+                    &r_args,
+                    &mut sink::FanOut::new(vec![&mut taken, &mut report]),
+                )?;
+                measurements.extend(taken);
+            }
         }
         Command::Suite {
             input,
             retrieval_input_dir,
+            retrieval_k,
+            retrieval_candidate_factor,
+            retrieval_queries,
             codec,
             level,
             verify,
             variant,
+            index,
+            dataset,
+            keep_going,
+            section,
+            list_sections,
         } => {
-            measurements.extend(benches::vsa::run(&cfg, *variant));
-
-            if !input.is_empty() {
-                let codec = parse_codec(codec)?;
-                let enc_args = benches::encode::EncodeArgs {
-                    inputs: input.clone(),
-                    prefix: None,
-                    codec,
-                    codec_level: *level,
-                    verify: *verify,
-                };
-                measurements.extend(benches::encode::run(&cfg, &enc_args)?);
+            let mut spec = SuiteSpec {
+                profile: cfg.profile,
+                seed: cfg.seed,
+                variant: *variant,
+                inputs: input.clone(),
+                retrieval_input_dir: retrieval_input_dir.clone(),
+                retrieval_k: *retrieval_k,
+                retrieval_candidate_factor: *retrieval_candidate_factor,
+                retrieval_queries: *retrieval_queries,
+                codec: codec.clone(),
+                codec_level: *level,
+                verify: *verify,
+                index: *index,
+                dataset: dataset.clone(),
+                keep_going: *keep_going,
+                walk: walk.clone(),
+                chunk_size,
+                read_buffer,
+                record_samples: args.record_samples.map(|n| n as usize),
+                tags: args.tags.iter().cloned().collect(),
+                notes: args.notes.clone(),
+                sections: section.clone(),
+                ignore_space_check: args.ignore_space_check,
+                vsa_config: vsa_config.clone(),
+            };
+            let mut registry = Registry::suite(&spec)?;
+            registry.select(&std::mem::take(&mut spec.sections))?;
+            if *list_sections {
+                for name in registry.names() {
+                    println!("{name}");
+                }
+                return Ok(());
             }
+            if let Some(plan) = &mut plan {
+                for (name, reason) in registry.take_unplanned() {
+                    plan.unplanned(&name, reason);
+                }
+            }
+            disk_watch = disk_space::watch(
+                &spec.disk_requirements(&registry.names()),
+                args.ignore_space_check,
+            )?;
+            // Each bench group is a status section; without --keep-going the first
+            // failure ends the run.
+            let (ms, _) =
+                suite::run_sections(&spec, &registry, &mut |name, f| status.section(name, f))?;
+            measurements.extend(ms);
 
-            if let Some(dir) = retrieval_input_dir {
-                let r_args = benches::retrieval::RetrievalArgs {
-                    input_dir: dir.clone(),
-                    k: 10,
-                    candidate_factor: 10,
-                    queries: None,
+            if !status.sections_failed.is_empty() {
+                let failed: Vec<&str> = status
+                    .sections_failed
+                    .iter()
+                    .map(|f| f.section.as_str())
+                    .collect();
+                contract_failure = Some(format!("suite section(s) failed: {}", failed.join(", ")));
+            }
+        }
+        Command::Index => {
+            measurements.extend(benches::index::run(&cfg));
+        }
+        Command::Duel {
+            a,
+            b,
+            substrate,
+            blocks,
+            max_ops,
+        } => {
+            let duel_args = benches::duel::DuelArgs {
+                a: a.clone(),
+                b: b.clone(),
+                substrate: *substrate,
+                blocks: *blocks,
+                max_ops: *max_ops,
+            };
+            measurements.extend(benches::duel::run(&cfg, &duel_args)?);
+        }
+        Command::Soak {
+            op,
+            duration,
+            snapshot_interval,
+            drift_threshold,
+            dataset,
+        } => {
+            if let Some(plan) = &mut plan {
+                plan.unplanned("soak", "runs for its whole --duration; not sampled");
+            } else {
+                let soak_args = benches::soak::SoakArgs {
+                    op: *op,
+                    duration: *duration,
+                    snapshot_interval: *snapshot_interval,
+                    drift_threshold: *drift_threshold,
+                    dataset: dataset.clone(),
                 };
-                measurements.extend(benches::retrieval::run(&cfg, &r_args)?);
+                let m = benches::soak::run(&cfg, &soak_args)?;
+                if m.extra["stopped"] == "interrupted" {
+                    eprintln!("soak: interrupted; reporting the snapshots so far");
+                }
+                let flagged: Vec<&str> = m.extra["drift"]["flagged"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|f| f.as_str())
+                    .collect();
+                if !flagged.is_empty() {
+                    eprintln!(
+                        "WARNING: {}: {} drifted by more than {drift_threshold} between the first and last snapshot",
+                        m.name,
+                        flagged.join(" and ")
+                    );
+                }
+                measurements.push(m);
+            }
+        }
+        Command::DatasetBench { path } => match &mut plan {
+            Some(plan) => plan.unplanned("dataset_io", plan::STREAMED),
+            None => measurements.extend(benches::dataset_io::run(&cfg, path, read_buffer)?),
+        },
+        Command::DatasetBenchFormats {
+            config,
+            formats,
+            keep,
+        } => {
+            let ignore_space_check = args.ignore_space_check;
+            let config = match config {
+                Some(path) => serde_json::from_slice(&fs::read(path)?).map_err(|e| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("{}: {e}", path.display()),
+                    )
+                })?,
+                None => GenerateConfig::default(),
+            };
+            let args = benches::dataset_formats::FormatsArgs {
+                config,
+                formats: formats.clone(),
+                keep: keep.clone(),
+            };
+            match &mut plan {
+                Some(plan) => plan.unplanned(
+                    "dataset_formats",
+                    "writes and scans whole files; not sampled",
+                ),
+                None => {
+                    disk_watch = disk_space::watch(
+                        &benches::dataset_formats::disk_requirements(&args),
+                        ignore_space_check,
+                    )?;
+                    measurements.extend(benches::dataset_formats::run(&cfg, &args)?)
+                }
             }
         }
         Command::GenerateDataset {
@@ -275,6 +1769,12 @@ fn main() -> io::Result<()> {
             seed,
             sparsity,
             dimension,
+            shards,
+            shard_index,
+            index_distribution,
+            generator,
+            strict_size,
+            report,
         } => {
             let sparsity = sparsity.unwrap_or(dimension / 100);
             let gen_config = GenerateConfig {
@@ -282,78 +1782,582 @@ fn main() -> io::Result<()> {
                 dimension: *dimension,
                 seed: *seed,
                 sparsity,
+                index_distribution: *index_distribution,
+                generator: *generator,
             };
+            gen_config.validate().map_err(|e| {
+                io::Error::new(
+                    e.kind(),
+                    format!("invalid --count/--dimension/--sparsity: {e}"),
+                )
+            })?;
 
             // Create output directory
             fs::create_dir_all(output)?;
 
-            // Generate filename based on count
+            // Generate filename based on count (and the distribution, unless uniform, and
+            // the generator, unless v1)
+            let distribution = match index_distribution {
+                dataset::IndexDistribution::Uniform => String::new(),
+                d => format!("_{}", d.label()),
+            };
+            let generator_suffix = match generator {
+                dataset::GeneratorVersion::V1 => "",
+                dataset::GeneratorVersion::V2 => "_gen2",
+            };
             let filename = format!(
-                "sparsevec_{}_{}_seed{}.embr",
-                format_count(*count),
+                "sparsevec_{}_{}_seed{}{}{}.embr",
+                dataset::format_count(*count),
                 dimension,
-                seed
+                seed,
+                distribution,
+                generator_suffix
             );
-            let filepath = output.join(&filename);
+            let shard = match (shards, shard_index) {
+                (Some(n), Some(i)) => Some(dataset::ShardDescriptor::new(*i, *n, *count)?),
+                _ => None,
+            };
+            let filepath = match &shard {
+                Some(shard) => output.join(shard.file_name(&filename)),
+                None => output.join(&filename),
+            };
+
+            let written = shard.map_or(*count, |s| s.range().end - s.range().start);
+            disk_space::preflight(
+                &[disk_space::generate_requirement(
+                    &filepath,
+                    dataset::expected_file_size(written, sparsity),
+                )],
+                &disk_space::Statvfs,
+                args.ignore_space_check,
+            )?;
 
             eprintln!("Generating {} vectors (dim={}, sparsity={}, seed={})...",
                 count, dimension, sparsity, seed);
+            if let Some(shard) = &shard {
+                let range = shard.range();
+                eprintln!(
+                    "  Shard {} of {}: vectors {}..{}",
+                    shard.index, shard.count, range.start, range.end
+                );
+            }
 
             let start = std::time::Instant::now();
             // Stream directly to disk to avoid materializing Vec<SparseVec> (RAM spike at 1M+).
-            dataset::write_dataset_streaming(&filepath, &gen_config, 4096)?;
+            let size_check = if *strict_size {
+                dataset::SizeCheck::Strict
+            } else {
+                dataset::SizeCheck::Warn
+            };
+            dataset::write_generated(&filepath, &gen_config, shard, 4096, size_check)?;
             let elapsed = start.elapsed();
+            let ext = dataset::write_sidecar(&filepath, &gen_config)?;
 
             let file_size = fs::metadata(&filepath)?.len();
             eprintln!("Wrote {:.2} MB in {:.2}s ({:.1} MB/s, {:.0} vec/s)",
                 file_size as f64 / 1_048_576.0,
                 elapsed.as_secs_f64(),
                 (file_size as f64 / 1_048_576.0) / elapsed.as_secs_f64(),
-                (*count as f64) / elapsed.as_secs_f64());
+                (written as f64) / elapsed.as_secs_f64()
+            );
 
             eprintln!("\nDataset saved: {}", filepath.display());
-            eprintln!("  Vectors: {}", count);
+            eprintln!("  Vectors: {}", written);
             eprintln!("  Dimension: {}", dimension);
             eprintln!("  Sparsity: {} per sign (~{:.1}% density)", sparsity, (sparsity * 2) as f64 / *dimension as f64 * 100.0);
             eprintln!("  Seed: {}", seed);
+            eprintln!("  Index distribution: {}", index_distribution.label());
+            eprintln!("  Generator: {}", generator.label());
             eprintln!("  File size: {:.2} MB", file_size as f64 / 1_048_576.0);
+            eprintln!("  SHA-256: {}", ext.content_sha256);
+            eprintln!("  Sidecar: {}", dataset::sidecar_path(&filepath).display());
 
-            // Skip normal JSON report for generate-dataset
-            return Ok(());
+            if !*report {
+                // Skip normal JSON report for generate-dataset
+                return Ok(());
+            }
+            measurements.extend(benches::dataset_io::generation_measurements(
+                &gen_config,
+                written,
+                file_size,
+                elapsed,
+            ));
         }
-        Command::DatasetInfo { path } => {
+        Command::DatasetInfo { path, sample, json } => {
             let meta = dataset::read_dataset_meta(path)?;
             eprintln!("Dataset: {}", path.display());
             eprintln!("  Vectors: {}", meta.count);
             eprintln!("  Dimension: {}", meta.dimension);
             eprintln!("  Seed: {}", meta.seed);
+            eprintln!("  Format version: {}", meta.version);
+            eprintln!("  Generator: {}", meta.generator.label());
+            if meta.appended {
+                eprintln!("  Appended: yes (records added after generation)");
+            }
+            if meta.derived {
+                eprintln!("  Derived: yes (records taken from another dataset)");
+            }
+            eprintln!(
+                "  Labels: {}",
+                if meta.labeled {
+                    "yes (one u32 per vector)"
+                } else {
+                    "no"
+                }
+            );
+            if let Some(shard) = &meta.shard {
+                let range = shard.range();
+                eprintln!(
+                    "  Shard: {} of {} (vectors {}..{} of {})",
+                    shard.index, shard.count, range.start, range.end, shard.total
+                );
+            }
+            for ext in &meta.extensions {
+                eprintln!("  Header extension {}: {}", ext.tag(), ext.describe());
+            }
 
             let file_size = fs::metadata(path)?.len();
             eprintln!("  File size: {:.2} MB", file_size as f64 / 1_048_576.0);
 
+            match &meta.extended {
+                Some(ext) => {
+                    eprintln!("  Sparsity: {} per sign", ext.generate.sparsity);
+                    eprintln!(
+                        "  Index distribution: {}",
+                        ext.generate.index_distribution.label()
+                    );
+                    eprintln!(
+                        "  Generated by: v{} at {}",
+                        ext.crate_version, ext.created_utc
+                    );
+                    eprintln!("  SHA-256: {}", ext.content_sha256);
+                }
+                None => eprintln!("  (no {} sidecar)", dataset::sidecar_path(path).display()),
+            }
+
+            if let Some(n) = sample {
+                let seed = args.seed_given.then_some(args.seed);
+                let sample =
+                    dataset::sample_records(&dataset::DatasetSource::File(path.clone()), *n, seed)?;
+                if *json {
+                    let json = serde_json::to_string_pretty(&sample).map_err(io::Error::other)?;
+                    println!("{json}");
+                } else {
+                    print_dataset_sample(&sample);
+                }
+            }
+
+            // Skip normal JSON report
+            return Ok(());
+        }
+        Command::DatasetVerify { path } => {
+            let mut reader = dataset::DatasetReader::open_validated(path)?;
+            let meta = reader.meta().clone();
+            let scan = dataset::scan_vectors(&mut reader, meta.count)?;
+            eprintln!("Dataset OK: {}", path.display());
+            eprintln!("  Vectors: {}", meta.count);
+            eprintln!("  Dimension: {}", meta.dimension);
+            if let Some(first) = scan.first_empty {
+                eprintln!(
+                    "  warning: {} empty vector(s), first at record {}",
+                    scan.empty, first
+                );
+            }
+            if let Some(first) = scan.first_degenerate {
+                eprintln!(
+                    "  warning: {} degenerate vector(s) (index out of range or both signs), first at record {}",
+                    scan.degenerate, first
+                );
+            }
+            if scan.duplicates > 0 {
+                eprintln!(
+                    "  Duplicates: {} (exact; dataset-dedupe removes them)",
+                    scan.duplicates
+                );
+            }
+
+            // Skip normal JSON report
+            return Ok(());
+        }
+        Command::DatasetCheckDeterminism { path, sample } => {
+            let report = dataset::check_determinism(path, *sample)?;
+            if let Some(index) = report.first_mismatch {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "{}: record {} does not match regeneration (seed={}, dim={}, sparsity={})",
+                        path.display(),
+                        index,
+                        report.meta.seed,
+                        report.meta.dimension,
+                        report.sparsity
+                    ),
+                ));
+            }
+            eprintln!("Dataset deterministic: {}", path.display());
+            eprintln!("  Vectors: {}", report.meta.count);
+            eprintln!("  Sparsity: {}", report.sparsity);
+            eprintln!("  Records checked: {}", report.checked);
+
+            // Skip normal JSON report
+            return Ok(());
+        }
+        Command::DatasetDedupe {
+            input,
+            output,
+            near_threshold,
+        } => {
+            if near_threshold.is_some_and(|t| !(-1.0..=1.0).contains(&t)) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "--near-threshold must be a cosine in [-1, 1]",
+                ));
+            }
+            let report = dedupe::dedupe_file(input, output, *near_threshold)?;
+            eprintln!("Deduplicated {} -> {}", input.display(), output.display());
+            eprintln!("  Vectors: {} in, {} kept", report.input, report.kept);
+            eprintln!("  Exact duplicates removed: {}", report.exact_removed);
+            if report.removed_sidecar {
+                eprintln!(
+                    "warning: removed {}: it describes the dataset before deduplication",
+                    dataset::sidecar_path(output).display()
+                );
+            }
+            if let Some(threshold) = report.near_threshold {
+                eprintln!(
+                    "  Near duplicates removed (cosine >= {threshold}): {} ({} candidate comparisons)",
+                    report.near_removed, report.near_compared
+                );
+            }
+
             // Skip normal JSON report
             return Ok(());
         }
+        Command::DatasetAppend { input, into } => {
+            let mut writer = dataset::DatasetWriter::open_append(into)?;
+            if writer.recovered_bytes() > 0 {
+                eprintln!(
+                    "warning: dropped {} uncounted bytes left by an interrupted append",
+                    writer.recovered_bytes()
+                );
+            }
+            if writer.removed_sidecar() {
+                eprintln!(
+                    "warning: removed {}: it describes the dataset before this append",
+                    dataset::sidecar_path(into).display()
+                );
+            }
+            let appended = if input.as_os_str() == "-" {
+                dataset::append_jsonl(&mut writer, io::stdin().lock(), "<stdin>")?
+            } else {
+                dataset::append_jsonl(
+                    &mut writer,
+                    io::BufReader::new(fs::File::open(input)?),
+                    &input.display().to_string(),
+                )?
+            };
+            let before = writer.meta().count;
+            let count = writer.finish()?;
+            eprintln!("Appended {appended} vector(s) to {}", into.display());
+            eprintln!("  Vectors: {before} -> {count}");
+
+            // Skip normal JSON report
+            return Ok(());
+        }
+        Command::ImportCriterion { criterion_dir } => {
+            measurements.extend(criterion_import::import_dir(criterion_dir)?);
+        }
+        Command::Compare {
+            baseline,
+            current,
+            match_tags,
+            threshold,
+            ratio_direction,
+            fail_on_regression,
+            allow_low_confidence,
+            gate_informational,
+        } => {
+            let baseline = schema::load_report(baseline)?;
+            let current = schema::load_report(current)?;
+            let opts = CompareOptions {
+                threshold: *threshold,
+                match_tags: match_tags.clone(),
+                ratio_direction: *ratio_direction,
+                allow_low_confidence: *allow_low_confidence,
+                gate_informational: *gate_informational,
+            };
+            let cmp = compare::compare_reports(&baseline, &current, &opts);
+
+            for (side, run) in [
+                ("baseline", &cmp.baseline_run),
+                ("current", &cmp.current_run),
+            ] {
+                for note in &run.notes {
+                    eprintln!("note         {side}: {note}");
+                }
+            }
+            for d in &cmp.deltas {
+                let verdict = if d.undirected {
+                    "ratio".to_string()
+                } else {
+                    format!("{:?}", d.verdict).to_lowercase()
+                };
+                eprintln!(
+                    "{:<12} {:>+8.1}%  {:>14.1} -> {:<14.1} {}",
+                    verdict,
+                    d.delta_ratio * 100.0,
+                    d.baseline_ns_per_iter,
+                    d.current_ns_per_iter,
+                    compare::display_key(&d.name, &d.tags)
+                );
+            }
+            if let Some(ns) = &cmp.namespace_change {
+                let version =
+                    |v: Option<u32>| v.map_or("unversioned".to_string(), |v| format!("v{v}"));
+                eprintln!(
+                    "WARNING: measurement names changed between namespace {} (baseline) and {} (current); missing/new entries may be renames",
+                    version(ns.baseline),
+                    version(ns.current)
+                );
+            }
+            for k in &cmp.only_in_baseline {
+                eprintln!("missing      {k}");
+            }
+            for k in &cmp.only_in_current {
+                eprintln!("new          {k}");
+            }
+            for k in &cmp.dispatch_changes {
+                eprintln!("dispatch     {k}: embeddenator version or input density changed");
+            }
+            for d in &cmp.value_drift {
+                eprintln!(
+                    "values       {}: cosine mean {:.6} -> {:.6}, histogram distance {:.4}",
+                    d.name, d.baseline_mean, d.current_mean, d.histogram_distance
+                );
+            }
+            if let Some(v) = &cmp.vsa_config_mismatch {
+                eprintln!("!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!");
+                eprintln!(
+                    "WARNING: encoding config differs: baseline {}, current {}",
+                    serde_json::to_string(&v.baseline).map_err(io::Error::other)?,
+                    serde_json::to_string(&v.current).map_err(io::Error::other)?
+                );
+                eprintln!("WARNING: the runs encoded different vectors; deltas below are not like for like");
+                eprintln!("!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!");
+            }
+            if let Some(b) = &cmp.build_change {
+                eprintln!("!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!");
+                eprintln!("WARNING: build differs:");
+                eprintln!("WARNING:   baseline {}", b.baseline.describe());
+                eprintln!("WARNING:   current  {}", b.current.describe());
+                eprintln!("WARNING: deltas below may come from the library or uncommitted changes");
+                eprintln!("!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!");
+            }
+            if let Some(g) = &cmp.governor_mismatch {
+                eprintln!("!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!");
+                eprintln!(
+                    "WARNING: cpufreq governor differs: baseline {}, current {}",
+                    g.baseline.as_deref().unwrap_or("unknown"),
+                    g.current.as_deref().unwrap_or("unknown")
+                );
+                eprintln!("WARNING: timing deltas below may reflect frequency scaling");
+                eprintln!("!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!");
+            }
+            let low_confidence = cmp.low_confidence();
+            if low_confidence > 0 {
+                eprintln!("!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!");
+                eprintln!(
+                    "WARNING: {low_confidence} measurement(s) below normal confidence (quick profile, capped dataset or noisy samples)"
+                );
+                if *allow_low_confidence {
+                    eprintln!(
+                        "WARNING: their verdicts were computed anyway (--allow-low-confidence)"
+                    );
+                } else {
+                    eprintln!("WARNING: their verdicts are inconclusive; rerun with --profile full, or pass --allow-low-confidence");
+                }
+                eprintln!("!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!");
+            }
+            if let Some(f) = &cmp.frontier {
+                let latency = f
+                    .latency
+                    .map(|v| format!("{v:?}").to_lowercase())
+                    .unwrap_or_else(|| "mixed/unchanged".to_string());
+                eprintln!(
+                    "frontier     {} point(s): latency {latency}, recall delta mean {:+.4} min {:+.4}",
+                    f.points, f.recall_delta_mean, f.recall_delta_min
+                );
+            }
+
+            let json = serde_json::to_string_pretty(&cmp).map_err(io::Error::other)?;
+            if let Some(out) = resolve_out(args, &cfg)? {
+                status.write_report(&out, &json, compress_out(args, &out), &mut io::stdout())?;
+            } else {
+                println!("{json}");
+            }
+
+            let regressions = cmp.regressions();
+            if *fail_on_regression && regressions > 0 {
+                return Err(io::Error::other(format!(
+                    "{regressions} regression(s) beyond {:.1}%",
+                    threshold * 100.0
+                )));
+            }
+
+            // Skip normal JSON report
+            return Ok(());
+        }
+        Command::Trend {
+            reports_dir,
+            mads,
+            window,
+            min_history,
+            match_tags,
+            fail_on_regression,
+        } => {
+            let reports = schema::load_reports_dir(reports_dir)?;
+            if reports.is_empty() {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("no reports in {}", reports_dir.display()),
+                ));
+            }
+            let opts = TrendOptions {
+                mads: *mads,
+                window: *window as usize,
+                min_history: *min_history as usize,
+                match_tags: match_tags.clone(),
+                ..TrendOptions::default()
+            };
+            let trend = trend::analyze(&reports, &opts);
+            if !args.quiet {
+                eprint!("{}", trend.render());
+            }
+
+            let json = serde_json::to_string_pretty(&trend).map_err(io::Error::other)?;
+            if let Some(out) = resolve_out(args, &cfg)? {
+                status.write_report(&out, &json, compress_out(args, &out), &mut io::stdout())?;
+            } else {
+                println!("{json}");
+            }
+
+            let regressions = trend.regressions();
+            if *fail_on_regression && regressions > 0 {
+                return Err(io::Error::other(format!(
+                    "{regressions} measurement(s) trending beyond {mads} MADs"
+                )));
+            }
+
+            // Skip normal JSON report
+            return Ok(());
+        }
+        Command::Export {
+            format:
+                ExportFormat::Tidy {
+                    reports,
+                    extra_keys,
+                },
+        } => {
+            if args.out_dir.is_some() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "export writes one file: use --out, not --out-dir",
+                ));
+            }
+            let rows = match &args.out {
+                Some(out) => {
+                    let mut rows = 0;
+                    atomic_write::write_atomic_with(out, |w| {
+                        rows = tidy::export(reports, extra_keys, w)?;
+                        Ok(())
+                    })?;
+                    status.report_path = Some(out.clone());
+                    rows
+                }
+                None => tidy::export(reports, extra_keys, io::BufWriter::new(io::stdout().lock()))?,
+            };
+            if !args.quiet {
+                eprintln!("Exported {rows} row(s) from {} report(s)", reports.len());
+            }
+
+            // Skip normal JSON report
+            return Ok(());
+        }
+    }
+
+    status.end();
+
+    if let Some(mut plan) = plan {
+        plan.add(&measurements);
+        plan.calibration_wall_seconds = started.elapsed().as_secs_f64();
+        // The table goes to stderr like the summary table, and --quiet drops it the same
+        // way; the JSON plan on stdout is what --dry-run-json asked for.
+        if !args.quiet {
+            eprint!("{}", plan.render());
+        }
+        if args.dry_run_json {
+            let json = serde_json::to_string_pretty(&plan).map_err(io::Error::other)?;
+            println!("{json}");
+        }
+        return match contract_failure {
+            Some(msg) => Err(io::Error::other(msg)),
+            None => Ok(()),
+        };
+    }
+
+    if let Some(env) = &mut environment {
+        env.finish();
+    }
+
+    if let Some(recording) = &recording {
+        recording.attach(&mut measurements);
+    }
+    confidence::annotate(&mut measurements, cfg.profile);
+    measurements.extend(ratios::derive(&measurements));
+    if args.emit_throughput {
+        measurements = schema::with_ops_per_s(measurements);
+    }
+
+    let failed_assertions: Vec<String> = assertions::evaluate_all(&args.assertions, &measurements)
+        .into_iter()
+        .inspect(|outcome| eprintln!("{outcome}"))
+        .filter(|outcome| !outcome.pass)
+        .map(|outcome| outcome.assertion.to_string())
+        .collect();
+    if !failed_assertions.is_empty() {
+        let msg = format!("assertion(s) failed: {}", failed_assertions.join(", "));
+        contract_failure = Some(match contract_failure {
+            Some(earlier) => format!("{earlier}; {msg}"),
+            None => msg,
+        });
     }
 
     let report = ContractBenchReport {
         run: RunMeta {
-            schema_version: 1,
-            bench_version: env!("CARGO_PKG_VERSION").to_string(),
-            profile: cfg.profile.as_str().to_string(),
-            seed: cfg.seed,
-            timestamp_utc: now_utc_rfc3339(),
-            git_sha: git_sha_short(),
+            environment,
+            cooldown: cooldown.as_ref().map(|c| c.report()),
+            notes: args.notes.clone(),
+            disk_space: disk_watch.map(disk_space::Watch::finish),
+            vsa_config: applies_vsa_config(&args.cmd).then_some(vsa_config),
+            ..RunMeta::new(&cfg, args.tags.iter().cloned().collect())
         },
         measurements,
     };
 
     let json = serde_json::to_string_pretty(&report).map_err(io::Error::other)?;
-    if let Some(out) = args.out {
-        fs::write(out, json)?;
+    if let Some(out) = resolve_out(args, &cfg)? {
+        status.write_report(&out, &json, compress_out(args, &out), &mut io::stdout())?;
     } else {
         println!("{json}");
     }
 
+    if !args.quiet {
+        print_summary(args, &report)?;
+    }
+
+    if let Some(msg) = contract_failure {
+        return Err(io::Error::other(msg));
+    }
+
     Ok(())
 }
@@ -0,0 +1,186 @@
+//! `--ops-budget`: a fixed number of dataset ops spread over a whole run.
+//!
+//! Without a budget, every dataset op runs over as many pairs/triples as the profile
+//! allows, all taken from the front of the file, so a Full run over a 1M-vector dataset
+//! takes hours. A budget is split across the enabled ops in proportion to the groups
+//! each could run ([`split_budget`]), and each op reads its share as a handful of
+//! stripes spread through the file ([`stripes`]) rather than one prefix. Both are
+//! deterministic from the budget, the dataset size and the seed.
+//!
+//! Without a budget, a quick run caps each op by cost rather than count
+//! ([`profile_ops`]): every op gets [`QUICK_DATASET_UNITS`] weighted units and runs as
+//! many pairs/triples as its weight allows, so a packed bundle does not take three times
+//! as long as a SparseVec dot.
+
+use crate::confidence::QUICK_DATASET_UNITS;
+use crate::harness::Profile;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+/// Most stripes one op's share is read in.
+pub const MAX_STRIPES: u64 = 16;
+
+/// A run of consecutive groups (pairs or triples) read from one place in the file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Stripe {
+    /// Index of the first group.
+    pub first_group: u64,
+    pub groups: u64,
+}
+
+/// Split `budget` ops across ops that could each run `available[i]` groups, in
+/// proportion to `available`.
+///
+/// Shares are floored and the leftover units go to the largest remainders (earlier ops
+/// first on ties), so the result sums to `min(budget, sum(available))` and no share
+/// exceeds its op's availability.
+pub fn split_budget(budget: u64, available: &[u64]) -> Vec<u64> {
+    let total: u128 = available.iter().map(|&a| u128::from(a)).sum();
+    if total <= u128::from(budget) {
+        return available.to_vec();
+    }
+    let budget = u128::from(budget);
+    let mut shares: Vec<u64> = available
+        .iter()
+        .map(|&a| (budget * u128::from(a) / total) as u64)
+        .collect();
+    let assigned: u128 = shares.iter().map(|&s| u128::from(s)).sum();
+
+    let mut order: Vec<usize> = (0..available.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(budget * u128::from(available[i]) % total));
+    for &i in order.iter().take((budget - assigned) as usize) {
+        shares[i] += 1;
+    }
+    shares
+}
+
+/// Pairs/triples the profile lets an op of cost `weight` run out of `available`: all of
+/// them under Full, [`weighted_ops`] of [`QUICK_DATASET_UNITS`] under Quick.
+pub fn profile_ops(profile: Profile, weight: f64, available: u64) -> u64 {
+    match profile {
+        Profile::Quick => weighted_ops(QUICK_DATASET_UNITS, weight, available),
+        Profile::Full => available,
+    }
+}
+
+/// The groups `units` weighted units buy at `weight` units each: floored, at least one
+/// and never more than `available`. `weight` must be positive.
+pub fn weighted_ops(units: u64, weight: f64, available: u64) -> u64 {
+    let ops = (units as f64 / weight).floor() as u64;
+    ops.max(1).min(available)
+}
+
+/// Place `ops` groups out of `available` as up to [`MAX_STRIPES`] stripes spread over
+/// the whole range.
+///
+/// Stripes are in file order, never overlap, and differ in length by at most one. The
+/// slack (`available - ops`) is split evenly between them, after a seeded offset before
+/// the first, so different seeds sample different records but always from throughout
+/// the file.
+pub fn stripes(available: u64, ops: u64, seed: u64) -> Vec<Stripe> {
+    let ops = ops.min(available);
+    let n = ops.min(MAX_STRIPES);
+    if n == 0 {
+        return Vec::new();
+    }
+    let slack = available - ops;
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let lead = rng.gen_range(0..=slack / n);
+    let gap = (slack - lead) / n;
+
+    let mut out = Vec::with_capacity(n as usize);
+    let mut next = lead;
+    for i in 0..n {
+        let groups = ops / n + u64::from(i < ops % n);
+        out.push(Stripe {
+            first_group: next,
+            groups,
+        });
+        next += groups + gap;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_budget_sums_to_budget() {
+        let available = [500_000, 500_000, 333_333, 500_000];
+        for budget in [0, 1, 7, 10_000, 1_000_003] {
+            let shares = split_budget(budget, &available);
+            assert_eq!(shares.iter().sum::<u64>(), budget, "budget {budget}");
+            assert!(shares.iter().zip(&available).all(|(s, a)| s <= a));
+        }
+        // Pair ops get 1.5x the triples of a triple op over the same vectors.
+        assert_eq!(split_budget(4_000, &available), [1_091, 1_091, 727, 1_091]);
+
+        // A budget beyond what the dataset holds runs everything once.
+        assert_eq!(split_budget(u64::MAX, &[3, 0, 5]), [3, 0, 5]);
+        assert_eq!(split_budget(6, &[3, 0, 5]), [2, 0, 4]);
+    }
+
+    #[test]
+    fn test_weighted_ops() {
+        assert_eq!(weighted_ops(10_000, 1.0, 500_000), 10_000);
+        assert_eq!(weighted_ops(10_000, 2.5, 500_000), 4_000);
+        assert_eq!(weighted_ops(10_000, 3.0, 500_000), 3_333);
+        // Cheaper than the unit op: more groups, still bounded by the dataset.
+        assert_eq!(weighted_ops(10_000, 0.5, 500_000), 20_000);
+        assert_eq!(weighted_ops(10_000, 0.5, 12_000), 12_000);
+        // However heavy, an op runs at least once if it can.
+        assert_eq!(weighted_ops(10_000, 1e9, 500_000), 1);
+        assert_eq!(weighted_ops(10_000, 1e9, 0), 0);
+
+        // Equal weighted cost: ops times weight stays within one op's weight of the units.
+        for w in [1.0, 1.5, 2.0, 3.0, 7.25] {
+            let cost = weighted_ops(10_000, w, u64::MAX) as f64 * w;
+            assert!(
+                cost <= 10_000.0 && cost > 10_000.0 - w,
+                "weight {w}: {cost}"
+            );
+        }
+
+        assert_eq!(
+            profile_ops(Profile::Quick, 2.0, 500_000),
+            QUICK_DATASET_UNITS / 2
+        );
+        assert_eq!(profile_ops(Profile::Full, 2.0, 500_000), 500_000);
+    }
+
+    #[test]
+    fn test_stripes_cover_distinct_regions() {
+        let available = 500_000;
+        let s = stripes(available, 10_000, 7);
+        assert_eq!(s.len() as u64, MAX_STRIPES);
+        assert_eq!(s.iter().map(|s| s.groups).sum::<u64>(), 10_000);
+        for w in s.windows(2) {
+            assert!(w[0].first_group + w[0].groups < w[1].first_group, "{w:?}");
+        }
+        let last = s.last().unwrap();
+        assert!(last.first_group + last.groups <= available);
+
+        // Spread over the file: one stripe in each sixteenth.
+        for (i, stripe) in s.iter().enumerate() {
+            let region =
+                i as u64 * available / MAX_STRIPES..(i as u64 + 1) * available / MAX_STRIPES;
+            assert!(
+                region.contains(&stripe.first_group),
+                "stripe {i}: {stripe:?}"
+            );
+        }
+
+        assert_eq!(stripes(available, 10_000, 7), s);
+        assert_ne!(stripes(available, 10_000, 8), s);
+
+        // No slack: the stripes tile the whole range.
+        let full = stripes(40, 40, 3);
+        assert_eq!(full[0].first_group, 0);
+        assert!(full
+            .windows(2)
+            .all(|w| w[0].first_group + w[0].groups == w[1].first_group));
+        assert_eq!(stripes(5, 3, 0).len(), 3);
+        assert!(stripes(0, 10, 0).is_empty());
+    }
+}
@@ -0,0 +1,195 @@
+//! Measurement-granular checkpoints for long runs.
+//!
+//! A checkpoint is a JSONL file with one completed [`Measurement`] per line, appended
+//! and synced as each measurement finishes. Reopening it yields the measurements an
+//! earlier (possibly killed) run already completed, so a rerun can skip them and
+//! continue with the rest. Only newline-terminated lines count: a process killed
+//! mid-write leaves an unterminated tail, which is truncated away on open.
+//!
+//! A `.gz` checkpoint holds each line as its own gzip member (see [`crate::gzip`]), so
+//! it is synced per line just the same and a cut-off member is the tail that goes.
+
+use crate::gzip;
+use crate::schema::Measurement;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug)]
+pub struct Checkpoint {
+    path: PathBuf,
+    file: File,
+    gzip: bool,
+    completed: Vec<Measurement>,
+}
+
+impl Checkpoint {
+    /// Open (or create) the checkpoint at `path` and load its completed measurements.
+    ///
+    /// Malformed terminated lines are an `InvalidData` error, so an unrelated file is
+    /// never silently appended to.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let raw = match std::fs::read(&path) {
+            Ok(b) => b,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        let gzip = gzip::is_gz_path(&path);
+        // `kept`: how much of the file stays once the unterminated tail is cut.
+        let (bytes, complete, kept) = if gzip {
+            let (bytes, kept) = gzip::decompress_members(&raw, &path)?;
+            let complete = bytes.len();
+            (bytes, complete, kept)
+        } else {
+            let complete = raw.iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1);
+            (raw, complete, complete)
+        };
+
+        let mut completed = Vec::new();
+        for (i, line) in bytes[..complete].split(|&b| b == b'\n').enumerate() {
+            if line.iter().all(u8::is_ascii_whitespace) {
+                continue;
+            }
+            let m = serde_json::from_slice::<Measurement>(line).map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "{}: line {} is not a measurement: {e}",
+                        path.display(),
+                        i + 1
+                    ),
+                )
+            })?;
+            completed.push(m);
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        if (kept as u64) < file.metadata()?.len() {
+            file.set_len(kept as u64)?;
+        }
+        Ok(Self {
+            path,
+            file,
+            gzip,
+            completed,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Measurements completed by earlier runs and not yet taken.
+    pub fn completed(&self) -> &[Measurement] {
+        &self.completed
+    }
+
+    pub fn is_completed(&self, name: &str) -> bool {
+        self.completed.iter().any(|m| m.name == name)
+    }
+
+    /// Remove and return the completed measurement called `name`.
+    pub fn take(&mut self, name: &str) -> Option<Measurement> {
+        let i = self.completed.iter().position(|m| m.name == name)?;
+        Some(self.completed.remove(i))
+    }
+
+    /// Append `m` and sync it to disk before returning.
+    pub fn record(&mut self, m: &Measurement) -> io::Result<()> {
+        let mut line = serde_json::to_vec(m).map_err(io::Error::other)?;
+        line.push(b'\n');
+        if self.gzip {
+            line = gzip::compress(&line)?;
+        }
+        self.file.write_all(&line)?;
+        self.file.sync_data()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::tags;
+    use serde_json::json;
+
+    fn measurement(name: &str) -> Measurement {
+        Measurement {
+            name: name.to_string(),
+            unit: "ns/op".to_string(),
+            iters: 4,
+            warmup_iters: 0,
+            total_ns: 400,
+            ns_per_iter: 100.0,
+            bytes_processed: None,
+            throughput_bytes_per_s: None,
+            extra: json!({"ops": 4}),
+            tags: tags(&[("substrate", "packed")]),
+        }
+    }
+
+    #[test]
+    fn test_record_reopen_and_torn_tail() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("partial.jsonl");
+
+        let mut cp = Checkpoint::open(&path).unwrap();
+        assert!(cp.completed().is_empty());
+        cp.record(&measurement("a")).unwrap();
+        cp.record(&measurement("b")).unwrap();
+        drop(cp);
+
+        // A kill mid-write leaves half a line behind.
+        let mut f = OpenOptions::new().append(true).open(&path).unwrap();
+        f.write_all(br#"{"name":"c","unit"#).unwrap();
+        drop(f);
+
+        let mut cp = Checkpoint::open(&path).unwrap();
+        let names: Vec<&str> = cp.completed().iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, ["a", "b"]);
+        assert_eq!(cp.take("b").unwrap().total_ns, 400);
+        assert!(!cp.is_completed("b") && cp.is_completed("a"));
+
+        cp.record(&measurement("c")).unwrap();
+        drop(cp);
+        let cp = Checkpoint::open(&path).unwrap();
+        assert_eq!(cp.completed().len(), 3);
+
+        std::fs::write(&path, "not json\n").unwrap();
+        let err = Checkpoint::open(&path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_gzip_checkpoint_torn_tail() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("partial.jsonl.gz");
+
+        let mut cp = Checkpoint::open(&path).unwrap();
+        cp.record(&measurement("a")).unwrap();
+        cp.record(&measurement("b")).unwrap();
+        drop(cp);
+        let synced = std::fs::metadata(&path).unwrap().len();
+
+        // Half of the next member made it to disk.
+        let member = gzip::compress(b"{\"name\":\"c\"}\n").unwrap();
+        let mut f = OpenOptions::new().append(true).open(&path).unwrap();
+        f.write_all(&member[..member.len() / 2]).unwrap();
+        drop(f);
+
+        let mut cp = Checkpoint::open(&path).unwrap();
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), synced);
+        let names: Vec<&str> = cp.completed().iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, ["a", "b"]);
+        cp.record(&measurement("c")).unwrap();
+        drop(cp);
+        assert_eq!(Checkpoint::open(&path).unwrap().completed().len(), 3);
+        let text = String::from_utf8(gzip::read(&path).unwrap()).unwrap();
+        assert_eq!(text.lines().count(), 3);
+
+        std::fs::write(&path, b"\x1f\x8b\x08garbage, not deflate").unwrap();
+        let err = Checkpoint::open(&path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("corrupt gzip"), "{err}");
+    }
+}
@@ -0,0 +1,909 @@
+//! Baseline-vs-current comparison of contract reports.
+//!
+//! Measurements are aligned by name, optionally refined by a set of tag keys
+//! (`--match-tags substrate,scale`) so that the same name measured under different
+//! labels is not conflated.
+//!
+//! Retrieval frontier points (`retrieval.frontier.cf<N>`) are additionally summarized as a
+//! whole curve: if every aligned point moved in the same direction the shift is reported
+//! once, together with the change in `recall_at_k`.
+//!
+//! Runs taken under different cpufreq governors are not comparable; when both reports
+//! carry an environment block and the governors differ, `governor_mismatch` is set.
+//! Nor are runs that encoded with different `ReversibleVSAConfig`s (`--vsa-config`):
+//! when both reports record one and they differ, `vsa_config_mismatch` is set. A
+//! different embeddenator (version or git revision), or a working tree dirty on one side
+//! only, sets `build_change`: the library moves most numbers, so its deltas are not the
+//! code under test's alone.
+//!
+//! Rates (`ops/s` and other `.../s` units) are higher-is-better, so their verdict is
+//! taken on the inverted change: a throughput drop is the regression. Ratios (unit
+//! `ratio`, see [`crate::ratios`]) have no inherent direction: their change is reported
+//! without a verdict unless `ratio_direction` says which way is better.
+//!
+//! SparseVec measurements record what embeddenator's internal path choice depends on
+//! in `extra.dispatch`; aligned measurements whose dispatch blocks differ are listed in
+//! `dispatch_changes`, since their delta may come from a different code path.
+//!
+//! When the reports carry different measurement namespace versions (see
+//! [`crate::measurements`]) and some names are on one side only, `namespace_change` is
+//! set: those names may have been renamed rather than added or dropped.
+//!
+//! Dataset cosine measurements taken with `--collect-values` carry the distribution of
+//! the values they computed; aligned ones whose distributions differ (see
+//! [`crate::cosine_values::drift`]) are listed in `value_drift`, whatever their timings
+//! did.
+//!
+//! Aligned measurements where either side's [`Confidence`] is below normal (quick-profile
+//! runs, mostly) get the `inconclusive` verdict instead of a regression or improvement,
+//! unless `allow_low_confidence` is set; either way the delta records the lower
+//! confidence.
+//!
+//! Measurements tagged `variance=high` (the single-shot `vsa.coldstart.*` timings) are
+//! informational: their delta is reported with the `informational` verdict, never a
+//! regression, unless `gate_informational` is set.
+
+use crate::confidence::Confidence;
+use crate::cosine_values::{self, CosineValues, ValueDrift};
+use crate::schema::{match_key, ContractBenchReport, MatchKey, Measurement, RunMeta};
+use crate::vsa_config::VsaConfig;
+use clap::ValueEnum;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+#[derive(Clone, Debug)]
+pub struct CompareOptions {
+    /// Relative ns_per_iter change beyond which a delta counts as a regression/improvement.
+    pub threshold: f64,
+    /// Tag keys that must match (in addition to the name) for two measurements to align.
+    pub match_tags: Vec<String>,
+    /// Which way ratio measurements improve; `None` gives them no verdict.
+    pub ratio_direction: Option<RatioDirection>,
+    /// Give verdicts between measurements below normal confidence too.
+    pub allow_low_confidence: bool,
+    /// Give verdicts on informational (`variance=high`) measurements too.
+    pub gate_informational: bool,
+}
+
+impl Default for CompareOptions {
+    fn default() -> Self {
+        Self {
+            threshold: 0.10,
+            match_tags: Vec::new(),
+            ratio_direction: None,
+            allow_low_confidence: false,
+            gate_informational: false,
+        }
+    }
+}
+
+/// Which way a ratio measurement improves.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum RatioDirection {
+    /// Larger ratios are better (e.g. speedups).
+    Higher,
+    /// Smaller ratios are better.
+    Lower,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Verdict {
+    Regression,
+    Improvement,
+    Unchanged,
+    /// Withheld: one side's confidence is below normal.
+    Inconclusive,
+    /// Withheld: a high-variance measurement, recorded for information only.
+    Informational,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct MeasurementDelta {
+    pub name: String,
+    /// The subset of tags used for alignment.
+    pub tags: BTreeMap<String, String>,
+    pub baseline_ns_per_iter: f64,
+    pub current_ns_per_iter: f64,
+    /// `current / baseline - 1`; positive means slower (or larger), except for
+    /// higher-is-better rates where it means faster.
+    pub delta_ratio: f64,
+    /// Set for rates, whose verdict treats a negative `delta_ratio` as the regression.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub higher_is_better: bool,
+    /// Set for ratios compared without a `ratio_direction`: the verdict is always
+    /// `unchanged`, whatever `delta_ratio` is.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub undirected: bool,
+    /// The lower of the two sides' confidence, when below normal.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub low_confidence: Option<Confidence>,
+    pub verdict: Verdict,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ComparisonReport {
+    pub baseline_run: RunMeta,
+    pub current_run: RunMeta,
+    pub threshold: f64,
+    pub match_tags: Vec<String>,
+    pub deltas: Vec<MeasurementDelta>,
+    pub only_in_baseline: Vec<String>,
+    pub only_in_current: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frontier: Option<FrontierShift>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub governor_mismatch: Option<GovernorMismatch>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vsa_config_mismatch: Option<VsaConfigMismatch>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub build_change: Option<BuildChange>,
+    /// Aligned measurements whose `extra.dispatch` differs between the two runs.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub dispatch_changes: Vec<String>,
+    /// Aligned measurements whose `extra.cosine_values` distribution moved.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub value_drift: Vec<ValueDrift>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub namespace_change: Option<NamespaceChange>,
+}
+
+/// Baseline and current use different measurement name sets, and some names did not
+/// align; `None` is a report from before names were versioned.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct NamespaceChange {
+    pub baseline: Option<u32>,
+    pub current: Option<u32>,
+}
+
+/// Baseline and current were built differently (see [`build_change`]).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct BuildChange {
+    pub baseline: BuildInfo,
+    pub current: BuildInfo,
+}
+
+/// What a report records of the build that took it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct BuildInfo {
+    pub git_sha: Option<String>,
+    pub git_dirty: Option<bool>,
+    pub embeddenator_version: String,
+    pub embeddenator_rev: Option<String>,
+}
+
+impl BuildInfo {
+    pub fn of(run: &RunMeta) -> Self {
+        Self {
+            git_sha: run.git_sha.clone(),
+            git_dirty: run.git_dirty,
+            embeddenator_version: run.embeddenator_version.clone(),
+            embeddenator_rev: run.embeddenator_rev.clone(),
+        }
+    }
+
+    /// `embeddenator <version>[ @ <rev>], tree <sha>[ (dirty)]`, with `unknown` for what
+    /// was not recorded.
+    pub fn describe(&self) -> String {
+        let mut out = format!(
+            "embeddenator {}",
+            if self.embeddenator_version.is_empty() {
+                "unknown"
+            } else {
+                &self.embeddenator_version
+            }
+        );
+        if let Some(rev) = &self.embeddenator_rev {
+            out.push_str(&format!(" @ {}", &rev[..rev.len().min(12)]));
+        }
+        out.push_str(&format!(
+            ", tree {}",
+            self.git_sha.as_deref().unwrap_or("unknown")
+        ));
+        out.push_str(match self.git_dirty {
+            Some(true) => " (dirty)",
+            Some(false) => " (clean)",
+            None => "",
+        });
+        out
+    }
+}
+
+/// Baseline and current were measured under different cpufreq governors.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct GovernorMismatch {
+    pub baseline: Option<String>,
+    pub current: Option<String>,
+}
+
+/// Baseline and current encoded with different `ReversibleVSAConfig`s.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct VsaConfigMismatch {
+    pub baseline: VsaConfig,
+    pub current: VsaConfig,
+}
+
+pub use crate::measurements::retrieval::FRONTIER_PREFIX;
+
+/// Whole-curve summary of aligned retrieval frontier points.
+#[derive(Clone, Debug, Serialize)]
+pub struct FrontierShift {
+    pub points: usize,
+    /// Set when every point shares the same non-`unchanged` latency verdict.
+    pub latency: Option<Verdict>,
+    /// Mean of `current - baseline` recall_at_k across points.
+    pub recall_delta_mean: f64,
+    /// Worst (most negative) recall_at_k change across points.
+    pub recall_delta_min: f64,
+}
+
+impl ComparisonReport {
+    pub fn regressions(&self) -> usize {
+        self.deltas
+            .iter()
+            .filter(|d| d.verdict == Verdict::Regression)
+            .count()
+    }
+
+    /// Deltas with a side below normal confidence.
+    pub fn low_confidence(&self) -> usize {
+        self.deltas
+            .iter()
+            .filter(|d| d.low_confidence.is_some())
+            .count()
+    }
+}
+
+/// Render a match key as `name{k=v,...}` (or just `name` without tags).
+pub fn display_key(name: &str, tags: &BTreeMap<String, String>) -> String {
+    if tags.is_empty() {
+        return name.to_string();
+    }
+    let inner: Vec<String> = tags.iter().map(|(k, v)| format!("{k}={v}")).collect();
+    format!("{name}{{{}}}", inner.join(","))
+}
+
+fn index_measurements<'a>(
+    ms: &'a [Measurement],
+    match_tags: &[String],
+) -> BTreeMap<MatchKey, &'a Measurement> {
+    let mut out = BTreeMap::new();
+    for m in ms {
+        let key = match_key(m, match_tags);
+        if out.contains_key(&key) {
+            eprintln!(
+                "warning: duplicate measurement {}; keeping the first (add --match-tags to disambiguate)",
+                display_key(&key.0, &key.1)
+            );
+            continue;
+        }
+        out.insert(key, m);
+    }
+    out
+}
+
+fn verdict_for(delta_ratio: f64, threshold: f64) -> Verdict {
+    if delta_ratio > threshold {
+        Verdict::Regression
+    } else if delta_ratio < -threshold {
+        Verdict::Improvement
+    } else {
+        Verdict::Unchanged
+    }
+}
+
+/// Compare two reports measurement-by-measurement.
+pub fn compare_reports(
+    baseline: &ContractBenchReport,
+    current: &ContractBenchReport,
+    opts: &CompareOptions,
+) -> ComparisonReport {
+    let base = index_measurements(&baseline.measurements, &opts.match_tags);
+    let cur = index_measurements(&current.measurements, &opts.match_tags);
+
+    let mut deltas = Vec::new();
+    let mut only_in_baseline = Vec::new();
+    let mut frontier_points: Vec<(Verdict, f64)> = Vec::new();
+    let mut dispatch_changes = Vec::new();
+    let mut value_drift = Vec::new();
+    for (key, b) in &base {
+        let Some(c) = cur.get(key) else {
+            only_in_baseline.push(display_key(&key.0, &key.1));
+            continue;
+        };
+        let delta_ratio = if b.ns_per_iter > 0.0 {
+            c.ns_per_iter / b.ns_per_iter - 1.0
+        } else {
+            0.0
+        };
+        let undirected = c.is_ratio() && opts.ratio_direction.is_none();
+        let higher_is_better = c.higher_is_better()
+            || (c.is_ratio() && opts.ratio_direction == Some(RatioDirection::Higher));
+        let worse_ratio = if higher_is_better {
+            -delta_ratio
+        } else {
+            delta_ratio
+        };
+        let low_confidence = match (b.confidence(), c.confidence()) {
+            (Some(cb), Some(cc)) => Some(cb.min(cc)),
+            (one, other) => one.or(other),
+        }
+        .filter(|&c| c < Confidence::Normal);
+        let informational = b.is_informational() || c.is_informational();
+        let verdict = if undirected {
+            Verdict::Unchanged
+        } else if informational && !opts.gate_informational {
+            Verdict::Informational
+        } else if low_confidence.is_some() && !opts.allow_low_confidence {
+            Verdict::Inconclusive
+        } else {
+            verdict_for(worse_ratio, opts.threshold)
+        };
+        if key.0.starts_with(FRONTIER_PREFIX) {
+            let recall = |m: &Measurement| m.extra.get("recall_at_k").and_then(|v| v.as_f64());
+            if let (Some(rb), Some(rc)) = (recall(b), recall(c)) {
+                frontier_points.push((verdict, rc - rb));
+            }
+        }
+        if let (Some(db), Some(dc)) = (b.extra.get("dispatch"), c.extra.get("dispatch")) {
+            if db != dc {
+                dispatch_changes.push(display_key(&key.0, &key.1));
+            }
+        }
+        if let (Some(vb), Some(vc)) = (CosineValues::of(&b.extra), CosineValues::of(&c.extra)) {
+            value_drift.extend(cosine_values::drift(&display_key(&key.0, &key.1), &vb, &vc));
+        }
+        deltas.push(MeasurementDelta {
+            name: key.0.clone(),
+            tags: key.1.clone(),
+            baseline_ns_per_iter: b.ns_per_iter,
+            current_ns_per_iter: c.ns_per_iter,
+            delta_ratio,
+            higher_is_better,
+            undirected,
+            low_confidence,
+            verdict,
+        });
+    }
+
+    let only_in_current: Vec<String> = cur
+        .keys()
+        .filter(|k| !base.contains_key(*k))
+        .map(|k| display_key(&k.0, &k.1))
+        .collect();
+    let (bv, cv) = (
+        baseline.run.measurement_namespace_version,
+        current.run.measurement_namespace_version,
+    );
+    let namespace_change = (bv != cv
+        && !(only_in_baseline.is_empty() && only_in_current.is_empty()))
+    .then_some(NamespaceChange {
+        baseline: bv,
+        current: cv,
+    });
+
+    ComparisonReport {
+        baseline_run: baseline.run.clone(),
+        current_run: current.run.clone(),
+        threshold: opts.threshold,
+        match_tags: opts.match_tags.clone(),
+        deltas,
+        only_in_baseline,
+        only_in_current,
+        frontier: frontier_shift(&frontier_points),
+        governor_mismatch: governor_mismatch(&baseline.run, &current.run),
+        vsa_config_mismatch: vsa_config_mismatch(&baseline.run, &current.run),
+        build_change: build_change(&baseline.run, &current.run),
+        dispatch_changes,
+        value_drift,
+        namespace_change,
+    }
+}
+
+/// Reports without an environment block (older runs) are never flagged.
+fn governor_mismatch(baseline: &RunMeta, current: &RunMeta) -> Option<GovernorMismatch> {
+    let b = baseline.environment.as_ref()?.governor();
+    let c = current.environment.as_ref()?.governor();
+    (b != c).then(|| GovernorMismatch {
+        baseline: b.map(str::to_string),
+        current: c.map(str::to_string),
+    })
+}
+
+/// Reports from before the config was recorded are never flagged.
+fn vsa_config_mismatch(baseline: &RunMeta, current: &RunMeta) -> Option<VsaConfigMismatch> {
+    let b = baseline.vsa_config.as_ref()?;
+    let c = current.vsa_config.as_ref()?;
+    (b != c).then(|| VsaConfigMismatch {
+        baseline: b.clone(),
+        current: c.clone(),
+    })
+}
+
+/// Set when the embeddenator version or revision differs, or the tree was dirty on one
+/// side only. Each is compared only when both reports record it, so older reports are
+/// never flagged.
+fn build_change(baseline: &RunMeta, current: &RunMeta) -> Option<BuildChange> {
+    fn differs<T: PartialEq>(b: Option<T>, c: Option<T>) -> bool {
+        matches!((b, c), (Some(b), Some(c)) if b != c)
+    }
+    fn version(r: &RunMeta) -> Option<&str> {
+        Some(r.embeddenator_version.as_str()).filter(|v| !v.is_empty())
+    }
+    let changed = differs(version(baseline), version(current))
+        || differs(
+            baseline.embeddenator_rev.as_deref(),
+            current.embeddenator_rev.as_deref(),
+        )
+        || differs(baseline.git_dirty, current.git_dirty);
+    changed.then(|| BuildChange {
+        baseline: BuildInfo::of(baseline),
+        current: BuildInfo::of(current),
+    })
+}
+
+fn frontier_shift(points: &[(Verdict, f64)]) -> Option<FrontierShift> {
+    let (first, _) = points.first()?;
+    let directed = matches!(first, Verdict::Regression | Verdict::Improvement);
+    let latency = if directed && points.iter().all(|(v, _)| v == first) {
+        Some(*first)
+    } else {
+        None
+    };
+    let recall_deltas = points.iter().map(|(_, d)| *d);
+    Some(FrontierShift {
+        points: points.len(),
+        latency,
+        recall_delta_mean: recall_deltas.clone().sum::<f64>() / points.len() as f64,
+        recall_delta_min: recall_deltas.fold(f64::INFINITY, f64::min),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::tags;
+    use serde_json::json;
+
+    fn meta() -> RunMeta {
+        RunMeta {
+            schema_version: 1,
+            bench_version: "0.0.0".to_string(),
+            profile: "quick".to_string(),
+            seed: 0,
+            timestamp_utc: "unix:0".to_string(),
+            git_sha: None,
+            git_dirty: None,
+            embeddenator_version: String::new(),
+            embeddenator_rev: None,
+            tags: BTreeMap::new(),
+            environment: None,
+            measurement_namespace_version: None,
+            cooldown: None,
+            notes: Vec::new(),
+            invocation: Vec::new(),
+            disk_space: None,
+            vsa_config: None,
+        }
+    }
+
+    fn m(name: &str, ns: f64, t: &[(&str, &str)]) -> Measurement {
+        Measurement {
+            name: name.to_string(),
+            unit: "ns/op".to_string(),
+            iters: 1,
+            warmup_iters: 0,
+            total_ns: ns as u128,
+            ns_per_iter: ns,
+            bytes_processed: None,
+            throughput_bytes_per_s: None,
+            extra: json!({}),
+            tags: tags(t),
+        }
+    }
+
+    fn report(ms: Vec<Measurement>) -> ContractBenchReport {
+        ContractBenchReport {
+            run: meta(),
+            measurements: ms,
+        }
+    }
+
+    #[test]
+    fn test_compare_by_name() {
+        let base = report(vec![m("a", 100.0, &[]), m("b", 100.0, &[])]);
+        let cur = report(vec![m("a", 150.0, &[]), m("c", 1.0, &[])]);
+        let r = compare_reports(&base, &cur, &CompareOptions::default());
+
+        assert_eq!(r.deltas.len(), 1);
+        assert_eq!(r.deltas[0].verdict, Verdict::Regression);
+        assert!((r.deltas[0].delta_ratio - 0.5).abs() < 1e-12);
+        assert_eq!(r.only_in_baseline, vec!["b"]);
+        assert_eq!(r.only_in_current, vec!["c"]);
+    }
+
+    #[test]
+    fn test_compare_rate_direction() {
+        let rate = |name: &str, v: f64| Measurement {
+            unit: "ops/s".to_string(),
+            ..m(name, v, &[])
+        };
+        let base = report(vec![
+            rate("x.ops_per_s", 1000.0),
+            rate("y.ops_per_s", 1000.0),
+            rate("z.ops_per_s", 1000.0),
+            m("x", 1000.0, &[]),
+        ]);
+        let cur = report(vec![
+            rate("x.ops_per_s", 700.0),
+            rate("y.ops_per_s", 1500.0),
+            rate("z.ops_per_s", 1050.0),
+            m("x", 700.0, &[]),
+        ]);
+        let r = compare_reports(&base, &cur, &CompareOptions::default());
+        let delta = |name: &str| r.deltas.iter().find(|d| d.name == name).unwrap();
+
+        // Fewer ops/s is the regression; the raw ratio keeps its sign.
+        let x = delta("x.ops_per_s");
+        assert!(x.higher_is_better);
+        assert!((x.delta_ratio + 0.3).abs() < 1e-12);
+        assert_eq!(x.verdict, Verdict::Regression);
+        assert_eq!(delta("y.ops_per_s").verdict, Verdict::Improvement);
+        assert_eq!(delta("z.ops_per_s").verdict, Verdict::Unchanged);
+        // The same drop in a time is an improvement.
+        assert!(!delta("x").higher_is_better);
+        assert_eq!(delta("x").verdict, Verdict::Improvement);
+        assert_eq!(r.regressions(), 1);
+    }
+
+    #[test]
+    fn test_compare_match_tags() {
+        let name = "vsa_dataset.packed.bind";
+        let base = report(vec![
+            m(name, 100.0, &[("substrate", "packed"), ("scale", "10k")]),
+            m(name, 400.0, &[("substrate", "packed"), ("scale", "1m")]),
+        ]);
+        let cur = report(vec![
+            m(name, 420.0, &[("substrate", "packed"), ("scale", "1m")]),
+            m(name, 50.0, &[("substrate", "packed"), ("scale", "10k")]),
+        ]);
+
+        // Name-only alignment conflates the two scales (first entry wins on each side).
+        let by_name = compare_reports(&base, &cur, &CompareOptions::default());
+        assert_eq!(by_name.deltas.len(), 1);
+        assert_eq!(by_name.deltas[0].current_ns_per_iter, 420.0);
+
+        let opts = CompareOptions {
+            match_tags: vec!["scale".to_string()],
+            ..Default::default()
+        };
+        let by_tags = compare_reports(&base, &cur, &opts);
+        assert_eq!(by_tags.deltas.len(), 2);
+        let small = by_tags
+            .deltas
+            .iter()
+            .find(|d| d.tags["scale"] == "10k")
+            .unwrap();
+        assert_eq!(small.verdict, Verdict::Improvement);
+        let large = by_tags
+            .deltas
+            .iter()
+            .find(|d| d.tags["scale"] == "1m")
+            .unwrap();
+        assert_eq!(large.verdict, Verdict::Unchanged);
+        assert!(!large.tags.contains_key("substrate"));
+    }
+
+    #[test]
+    fn test_compare_frontier_shift() {
+        let point = |cf: u32, ns: f64, recall: f64| {
+            let mut p = m(&format!("retrieval.frontier.cf{cf}"), ns, &[("k", "10")]);
+            p.extra = json!({ "candidate_factor": cf, "recall_at_k": recall });
+            p
+        };
+        let base = report(vec![
+            point(1, 100.0, 0.5),
+            point(2, 200.0, 0.8),
+            m("other", 1.0, &[]),
+        ]);
+        let slower = report(vec![
+            point(1, 150.0, 0.5),
+            point(2, 300.0, 0.7),
+            m("other", 1.0, &[]),
+        ]);
+
+        let r = compare_reports(&base, &slower, &CompareOptions::default());
+        let shift = r.frontier.expect("frontier summary");
+        assert_eq!(shift.points, 2);
+        assert_eq!(shift.latency, Some(Verdict::Regression));
+        assert!((shift.recall_delta_min + 0.1).abs() < 1e-12);
+        assert!((shift.recall_delta_mean + 0.05).abs() < 1e-12);
+
+        // One point faster, one slower: no whole-curve verdict.
+        let mixed = report(vec![point(1, 50.0, 0.5), point(2, 300.0, 0.8)]);
+        let r = compare_reports(&base, &mixed, &CompareOptions::default());
+        assert_eq!(r.frontier.unwrap().latency, None);
+
+        let plain = compare_reports(
+            &report(vec![m("other", 1.0, &[])]),
+            &report(vec![m("other", 1.0, &[])]),
+            &CompareOptions::default(),
+        );
+        assert!(plain.frontier.is_none());
+    }
+
+    #[test]
+    fn test_compare_governor_mismatch() {
+        use crate::environment::{Environment, HostSnapshot};
+
+        let with_governor = |g: Option<&str>| {
+            let mut r = report(vec![m("a", 1.0, &[])]);
+            r.run.environment = Some(Environment {
+                os: "linux".to_string(),
+                start: HostSnapshot {
+                    governor: g.map(str::to_string),
+                    ..Default::default()
+                },
+                end: None,
+            });
+            r
+        };
+        let opts = CompareOptions::default();
+        let perf = with_governor(Some("performance"));
+
+        let r = compare_reports(&perf, &with_governor(Some("powersave")), &opts);
+        assert_eq!(
+            r.governor_mismatch,
+            Some(GovernorMismatch {
+                baseline: Some("performance".to_string()),
+                current: Some("powersave".to_string()),
+            })
+        );
+        let r = compare_reports(&perf, &with_governor(None), &opts);
+        assert!(r.governor_mismatch.is_some());
+        let r = compare_reports(&perf, &with_governor(Some("performance")), &opts);
+        assert!(r.governor_mismatch.is_none());
+        // No environment block on one side: nothing to compare against.
+        let r = compare_reports(&report(vec![m("a", 1.0, &[])]), &perf, &opts);
+        assert!(r.governor_mismatch.is_none());
+    }
+
+    #[test]
+    fn test_compare_vsa_config_mismatch() {
+        let with_config = |config: Option<VsaConfig>| {
+            let mut r = report(vec![m("a", 1.0, &[])]);
+            r.run.vsa_config = config;
+            r
+        };
+        let opts = CompareOptions::default();
+        let tuned = VsaConfig {
+            block_size: 64,
+            ..VsaConfig::default()
+        };
+        let defaults = with_config(Some(VsaConfig::default()));
+
+        let r = compare_reports(&defaults, &with_config(Some(tuned.clone())), &opts);
+        assert_eq!(
+            r.vsa_config_mismatch,
+            Some(VsaConfigMismatch {
+                baseline: VsaConfig::default(),
+                current: tuned,
+            })
+        );
+        let r = compare_reports(&defaults, &with_config(Some(VsaConfig::default())), &opts);
+        assert!(r.vsa_config_mismatch.is_none());
+        // A report from before the config was recorded: nothing to compare against.
+        let r = compare_reports(&with_config(None), &defaults, &opts);
+        assert!(r.vsa_config_mismatch.is_none());
+    }
+
+    #[test]
+    fn test_compare_build_change() {
+        let built = |version: &str, rev: Option<&str>, dirty: Option<bool>| {
+            let mut r = report(vec![m("a", 1.0, &[])]);
+            r.run.embeddenator_version = version.to_string();
+            r.run.embeddenator_rev = rev.map(str::to_string);
+            r.run.git_dirty = dirty;
+            r
+        };
+        let opts = CompareOptions::default();
+        let change = |b: &ContractBenchReport, c: &ContractBenchReport| {
+            compare_reports(b, c, &opts).build_change
+        };
+        let base = built("0.20.0", Some("0123456789abcdef"), Some(false));
+
+        assert_eq!(change(&base, &base.clone()), None);
+        let r = change(
+            &base,
+            &built("0.21.0", Some("0123456789abcdef"), Some(false)),
+        )
+        .unwrap();
+        assert_eq!(r.baseline.embeddenator_version, "0.20.0");
+        assert_eq!(r.current.embeddenator_version, "0.21.0");
+        assert_eq!(
+            r.baseline.describe(),
+            "embeddenator 0.20.0 @ 0123456789ab, tree unknown (clean)"
+        );
+        assert!(change(&base, &built("0.20.0", Some("fedcba"), Some(false))).is_some());
+        let dirty = change(
+            &base,
+            &built("0.20.0", Some("0123456789abcdef"), Some(true)),
+        )
+        .unwrap();
+        assert!(dirty.current.describe().ends_with("(dirty)"));
+        // What one side did not record is not a change.
+        assert_eq!(change(&base, &built("", None, None)), None);
+        assert_eq!(change(&built("", None, None), &base), None);
+    }
+
+    #[test]
+    fn test_compare_dispatch_changes() {
+        let with_dispatch = |version: &str| {
+            let mut a = m("vsa.sparsevec.bundle", 1.0, &[]);
+            a.extra = json!({"dispatch": {"embeddenator_version": version, "input_density": 0.02}});
+            report(vec![a, m("vsa.packed.bundle", 1.0, &[])])
+        };
+        let opts = CompareOptions::default();
+
+        let r = compare_reports(&with_dispatch("1.0.0"), &with_dispatch("1.1.0"), &opts);
+        assert_eq!(r.dispatch_changes, vec!["vsa.sparsevec.bundle".to_string()]);
+        let r = compare_reports(&with_dispatch("1.0.0"), &with_dispatch("1.0.0"), &opts);
+        assert!(r.dispatch_changes.is_empty());
+        // Older reports without a dispatch block are not flagged.
+        let old = report(vec![m("vsa.sparsevec.bundle", 1.0, &[])]);
+        let r = compare_reports(&old, &with_dispatch("1.1.0"), &opts);
+        assert!(r.dispatch_changes.is_empty());
+    }
+
+    #[test]
+    fn test_compare_value_drift() {
+        let with_values = |xs: &[f64]| {
+            let mut values = CosineValues::default();
+            xs.iter().for_each(|&x| values.add(x));
+            values.finish();
+            let mut a = m("vsa_dataset.bitsliced.cosine", 1.0, &[]);
+            a.extra = json!({ "cosine_values": values });
+            report(vec![a, m("vsa_dataset.packed.bind", 1.0, &[])])
+        };
+        let opts = CompareOptions::default();
+
+        // Same timings, shifted values.
+        let r = compare_reports(
+            &with_values(&[0.0, 0.1, 0.2]),
+            &with_values(&[0.0, 0.1, 0.5]),
+            &opts,
+        );
+        assert!(r.deltas.iter().all(|d| d.verdict == Verdict::Unchanged));
+        assert_eq!(r.value_drift.len(), 1);
+        assert_eq!(r.value_drift[0].name, "vsa_dataset.bitsliced.cosine");
+        let same = with_values(&[0.0, 0.1, 0.2]);
+        assert!(compare_reports(&same, &same, &opts).value_drift.is_empty());
+    }
+    #[test]
+    fn test_compare_ratios_undirected_by_default() {
+        let ratio = |value: f64| {
+            let mut r = m("vsa.ratio.packed_vs_sparsevec.bind", value, &[]);
+            r.unit = "ratio".to_string();
+            r
+        };
+        let (base, cur) = (report(vec![ratio(4.0)]), report(vec![ratio(2.0)]));
+
+        let r = compare_reports(&base, &cur, &CompareOptions::default());
+        let d = &r.deltas[0];
+        assert!((d.delta_ratio + 0.5).abs() < 1e-12);
+        assert!(d.undirected);
+        assert_eq!(d.verdict, Verdict::Unchanged);
+
+        let directed = |direction| CompareOptions {
+            ratio_direction: Some(direction),
+            ..Default::default()
+        };
+        let r = compare_reports(&base, &cur, &directed(RatioDirection::Higher));
+        assert_eq!(
+            (r.deltas[0].undirected, r.deltas[0].verdict),
+            (false, Verdict::Regression)
+        );
+        let r = compare_reports(&base, &cur, &directed(RatioDirection::Lower));
+        assert_eq!(r.deltas[0].verdict, Verdict::Improvement);
+    }
+
+    #[test]
+    fn test_compare_namespace_change() {
+        let versioned = |version: Option<u32>, names: &[&str]| {
+            let mut r = report(names.iter().map(|n| m(n, 1.0, &[])).collect());
+            r.run.measurement_namespace_version = version;
+            r
+        };
+        let opts = CompareOptions::default();
+
+        let r = compare_reports(
+            &versioned(Some(1), &["a", "old.name"]),
+            &versioned(Some(2), &["a", "new.name"]),
+            &opts,
+        );
+        assert_eq!(
+            r.namespace_change,
+            Some(NamespaceChange {
+                baseline: Some(1),
+                current: Some(2),
+            })
+        );
+        // Same names on both sides: the version bump renamed nothing here.
+        let r = compare_reports(
+            &versioned(Some(1), &["a"]),
+            &versioned(Some(2), &["a"]),
+            &opts,
+        );
+        assert!(r.namespace_change.is_none());
+        // Same version: unmatched names are plain additions and removals.
+        let r = compare_reports(
+            &versioned(Some(1), &["a"]),
+            &versioned(Some(1), &["b"]),
+            &opts,
+        );
+        assert!(r.namespace_change.is_none());
+        let r = compare_reports(&versioned(None, &["a"]), &versioned(Some(1), &["b"]), &opts);
+        assert_eq!(r.namespace_change.unwrap().baseline, None);
+    }
+
+    #[test]
+    fn test_compare_low_confidence_withheld() {
+        let at = |ns: f64, confidence: Option<&str>| {
+            let mut a = m("a", ns, &[]);
+            if let Some(c) = confidence {
+                a.extra = json!({ "confidence": c });
+            }
+            report(vec![a])
+        };
+        let verdict = |b, c, opts: &CompareOptions| {
+            let d = compare_reports(&b, &c, opts).deltas.remove(0);
+            (d.verdict, d.low_confidence)
+        };
+        let opts = CompareOptions::default();
+        let allow = CompareOptions {
+            allow_low_confidence: true,
+            ..Default::default()
+        };
+
+        let normal = Some("normal");
+        assert_eq!(
+            verdict(at(1.0, normal), at(2.0, normal), &opts),
+            (Verdict::Regression, None)
+        );
+        // The lower side decides.
+        assert_eq!(
+            verdict(at(1.0, Some("low")), at(2.0, Some("smoke")), &opts),
+            (Verdict::Inconclusive, Some(Confidence::Smoke))
+        );
+        assert_eq!(
+            verdict(at(1.0, normal), at(2.0, Some("low")), &allow),
+            (Verdict::Regression, Some(Confidence::Low))
+        );
+        // Reports from before confidence was recorded compare as before.
+        assert_eq!(
+            verdict(at(1.0, None), at(2.0, None), &opts),
+            (Verdict::Regression, None)
+        );
+        let r = compare_reports(&at(1.0, Some("low")), &at(2.0, normal), &opts);
+        assert_eq!((r.regressions(), r.low_confidence()), (0, 1));
+    }
+
+    #[test]
+    fn test_compare_informational_not_gated() {
+        let cold = |ns| m("vsa.coldstart.bind", ns, &[("variance", "high")]);
+        let base = report(vec![cold(1_000.0), m("a", 100.0, &[])]);
+        let cur = report(vec![cold(5_000.0), m("a", 100.0, &[])]);
+        let r = compare_reports(&base, &cur, &CompareOptions::default());
+        assert_eq!(r.deltas[1].name, "vsa.coldstart.bind");
+        assert_eq!(r.deltas[1].verdict, Verdict::Informational);
+        assert_eq!(r.regressions(), 0);
+
+        let gated = CompareOptions {
+            gate_informational: true,
+            allow_low_confidence: true,
+            ..Default::default()
+        };
+        let r = compare_reports(&base, &cur, &gated);
+        assert_eq!(r.deltas[1].verdict, Verdict::Regression);
+    }
+}
@@ -0,0 +1,258 @@
+//! How far a measurement can be trusted for comparisons.
+//!
+//! The quick profile (300 iterations, dataset ops capped at [`QUICK_DATASET_UNITS`]
+//! weighted units) is for smoke tests, but its numbers look like any others in a report.
+//! Every timing gets a [`Confidence`] in `extra.confidence` when the report is assembled,
+//! and `compare` gives no regression verdict between measurements below
+//! [`Confidence::Normal`] unless told to (`--allow-low-confidence`).
+//!
+//! The rules are all in [`derive`]: too few iterations or a dataset cut short by the
+//! profile's op cap is `smoke`; noisy samples (with `--record-samples`) or a short timed
+//! loop is `low`. What counts as short depends on the cost of an iteration: a
+//! nanosecond-scale op needs [`NORMAL_ITERS`], while a whole pass ([`MACRO_NS`] or more
+//! per iteration, the [`crate::harness::Cost::Macro`] and `OneShot` measurements) runs
+//! the handful of iterations the full profile plans for it. Derived measurements inherit
+//! it: `<name>.ops_per_s` from its source, ratios the lower of their operands.
+
+use crate::harness::{Measured, Profile, Samples};
+use crate::schema::Measurement;
+use serde::{Deserialize, Serialize};
+
+/// Weighted units per op a quick-profile dataset run is capped at: pairs/triples for an
+/// op of weight 1, fewer for heavier ones (see [`crate::budget::profile_ops`]).
+pub const QUICK_DATASET_UNITS: u64 = 10_000;
+
+/// Key in `extra` of dataset measurements whose op count the quick cap truncated, with
+/// the cap, the op's weight and the groups it could have run.
+pub const OP_CAP_KEY: &str = "profile_op_cap";
+
+/// Fewer timed iterations than this is `smoke`.
+pub const SMOKE_ITERS: u64 = 3;
+
+/// At least this many iterations, or [`NORMAL_NS`] of timed work, is `normal`.
+pub const NORMAL_ITERS: u64 = 1_000;
+
+/// Timed nanoseconds that make a measurement `normal` whatever its iteration count
+/// (whole-corpus passes run only a handful of iterations).
+pub const NORMAL_NS: u128 = 500_000_000;
+
+/// Nanoseconds per iteration from which a measurement is a whole pass (a file, a query
+/// set, an index build), which the profiles time only a few times. Micro ops run
+/// [`NORMAL_ITERS`] or more under the full profile whatever they cost.
+pub const MACRO_NS: f64 = 100_000.0;
+
+/// Coefficient of variation across recorded samples above which a measurement is `low`.
+pub const NOISY_CV: f64 = 0.25;
+
+/// Samples needed before their spread is taken into account.
+const MIN_SAMPLES: usize = 5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Confidence {
+    /// Good for "does it run"; not for performance claims.
+    Smoke,
+    /// Indicative only.
+    Low,
+    Normal,
+}
+
+impl Confidence {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Confidence::Smoke => "smoke",
+            Confidence::Low => "low",
+            Confidence::Normal => "normal",
+        }
+    }
+}
+
+/// The confidence of a timing from its loop, its recorded samples (if any), whether the
+/// profile's op cap cut its dataset short and the profile it ran under.
+///
+/// Under the full profile, whole-pass iterations are `normal` at [`SMOKE_ITERS`] or more:
+/// that is all the profile runs of them, so holding them to [`NORMAL_ITERS`] would leave
+/// every such measurement out of `compare`'s verdicts. The quick profile gets no such
+/// allowance.
+pub fn derive(
+    m: &Measured,
+    samples: Option<&Samples>,
+    op_capped: bool,
+    profile: Profile,
+) -> Confidence {
+    if op_capped || m.iters < SMOKE_ITERS {
+        return Confidence::Smoke;
+    }
+    if samples.and_then(sample_cv).is_some_and(|cv| cv > NOISY_CV) {
+        return Confidence::Low;
+    }
+    let whole_pass = profile == Profile::Full && m.ns_per_iter >= MACRO_NS;
+    if whole_pass || m.iters >= NORMAL_ITERS || m.total_ns >= NORMAL_NS {
+        Confidence::Normal
+    } else {
+        Confidence::Low
+    }
+}
+
+/// Coefficient of variation of the per-batch timings. The last batch of a loop may be
+/// short, so it is left out when every batch was kept.
+fn sample_cv(samples: &Samples) -> Option<f64> {
+    let mut ns = samples.ns.as_slice();
+    if samples.batches == ns.len() as u64 {
+        ns = &ns[..ns.len().saturating_sub(1)];
+    }
+    if ns.len() < MIN_SAMPLES {
+        return None;
+    }
+    let n = ns.len() as f64;
+    let mean = ns.iter().map(|&x| x as f64).sum::<f64>() / n;
+    if mean <= 0.0 {
+        return None;
+    }
+    let var = ns.iter().map(|&x| (x as f64 - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    Some(var.sqrt() / mean)
+}
+
+/// [`derive`] for a measurement in a report run under `profile`, or `None` if it is not
+/// a timing of its own (sizes, rates and ratios have no loop to judge).
+pub fn of(m: &Measurement, profile: Profile) -> Option<Confidence> {
+    if !m.is_timing() || m.iters == 0 || m.total_ns == 0 {
+        return None;
+    }
+    let measured = Measured {
+        iters: m.iters,
+        warmup_iters: m.warmup_iters,
+        total_ns: m.total_ns,
+        ns_per_iter: m.ns_per_iter,
+    };
+    let samples: Option<Samples> = m
+        .extra
+        .get("samples")
+        .and_then(|s| serde_json::from_value(s.clone()).ok());
+    let op_capped = m.extra.get(OP_CAP_KEY).is_some();
+    Some(derive(&measured, samples.as_ref(), op_capped, profile))
+}
+
+/// Set `extra.confidence` on every timing in `ms`, run under `profile`, that has none yet.
+pub fn annotate(ms: &mut [Measurement], profile: Profile) {
+    for m in ms {
+        if m.confidence().is_some() {
+            continue;
+        }
+        let Some(c) = of(m, profile) else {
+            continue;
+        };
+        if let Some(extra) = m.extra.as_object_mut() {
+            extra.insert("confidence".to_string(), serde_json::json!(c));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn measured(iters: u64, ns_per_iter: f64) -> Measured {
+        Measured {
+            iters,
+            warmup_iters: 0,
+            total_ns: (iters as f64 * ns_per_iter) as u128,
+            ns_per_iter,
+        }
+    }
+
+    fn samples(ns: &[u64]) -> Samples {
+        Samples {
+            batch_iters: 10,
+            batches: ns.len() as u64 + 1,
+            ns: ns.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_derive_rules() {
+        use Confidence::*;
+        use Profile::*;
+        // Iteration counts: quick (300), full (3000), too few to mean anything.
+        assert_eq!(derive(&measured(300, 1_000.0), None, false, Full), Low);
+        assert_eq!(derive(&measured(3_000, 1_000.0), None, false, Full), Normal);
+        assert_eq!(derive(&measured(2, 1e9), None, false, Full), Smoke);
+        // A few long iterations are enough.
+        assert_eq!(derive(&measured(10, 1e8), None, false, Full), Normal);
+        // Whole passes run as often as the full profile plans (Macro 5, OneShot 3) are
+        // normal however short the total; under quick they stay low.
+        assert_eq!(derive(&measured(5, 2e7), None, false, Full), Normal);
+        assert_eq!(derive(&measured(3, 2e6), None, false, Full), Normal);
+        assert_eq!(derive(&measured(3, 2e6), None, false, Quick), Low);
+        assert_eq!(derive(&measured(100, 5e4), None, false, Full), Low);
+        // The quick op cap outranks everything else.
+        assert_eq!(derive(&measured(10_000, 1e6), None, true, Full), Smoke);
+
+        // Spread: steady samples change nothing, noisy ones drop to low.
+        let steady = samples(&[100, 102, 98, 101, 99, 100]);
+        let noisy = samples(&[100, 300, 90, 250, 100, 400]);
+        assert_eq!(
+            derive(&measured(3_000, 1e3), Some(&steady), false, Full),
+            Normal
+        );
+        assert_eq!(
+            derive(&measured(3_000, 1e3), Some(&noisy), false, Full),
+            Low
+        );
+        // Too few samples to judge.
+        let few = samples(&[100, 400, 90]);
+        assert_eq!(
+            derive(&measured(3_000, 1e3), Some(&few), false, Full),
+            Normal
+        );
+        // A complete sample set leaves out its (possibly short) last batch.
+        let short_tail = Samples {
+            batches: 6,
+            ..samples(&[100, 101, 99, 100, 100, 3])
+        };
+        assert_eq!(
+            derive(&measured(3_000, 1e3), Some(&short_tail), false, Full),
+            Normal
+        );
+
+        assert!(Smoke < Low && Low < Normal);
+    }
+
+    #[test]
+    fn test_annotate_timings_only() {
+        let m = |name: &str, unit: &str, iters: u64, extra| Measurement {
+            name: name.to_string(),
+            unit: unit.to_string(),
+            iters,
+            warmup_iters: 0,
+            total_ns: iters as u128 * 1_000,
+            ns_per_iter: 1_000.0,
+            bytes_processed: None,
+            throughput_bytes_per_s: None,
+            extra,
+            tags: Default::default(),
+        };
+        let mut ms = vec![
+            m("a", "ns/iter", 3_000, serde_json::json!({})),
+            m("b", "ns/op", 10_000, serde_json::json!({OP_CAP_KEY: {}})),
+            m("c", "bytes", 1, serde_json::json!({})),
+            m(
+                "d",
+                "ns/iter",
+                300,
+                serde_json::json!({"confidence": "normal"}),
+            ),
+        ];
+        annotate(&mut ms, Profile::Full);
+        let got: Vec<_> = ms.iter().map(Measurement::confidence).collect();
+        assert_eq!(
+            got,
+            [
+                Some(Confidence::Normal),
+                Some(Confidence::Smoke),
+                None,
+                Some(Confidence::Normal)
+            ]
+        );
+    }
+}
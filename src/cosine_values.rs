@@ -0,0 +1,241 @@
+//! Distribution of the cosine values a dataset loop computed.
+//!
+//! A substrate bug often shows up as shifted similarity values before anything crashes
+//! or slows down. With `vsa --dataset ... --collect-values` the cosine loops (and packed
+//! dot, normalised to a cosine by the records' norms) accumulate every value they
+//! compute into a [`CosineValues`]: a fixed [`BINS`]-bin histogram over [-1, 1] plus
+//! mean and standard deviation, recorded as `extra.cosine_values`. Memory stays
+//! O([`BINS`]) whatever the dataset size.
+//!
+//! Values are added inside the timed loops, so adding one only bins it and moves the
+//! running sums; mean and standard deviation are derived by [`CosineValues::finish`]
+//! once the timing is taken.
+//!
+//! `compare` checks the distributions of aligned measurements with [`drift`] and lists
+//! those that moved in `value_drift`, even when their timings did not.
+
+use embeddenator::SparseVec;
+use serde::{Deserialize, Serialize};
+
+/// Histogram bins over [-1, 1].
+pub const BINS: usize = 40;
+
+/// Mean shift tolerated between runs over the same pairs (floating-point noise).
+pub const MEAN_TOLERANCE: f64 = 1e-6;
+
+/// Total variation distance between histograms tolerated over the same pairs: a few
+/// values may land on the other side of a bin edge.
+pub const HISTOGRAM_TOLERANCE: f64 = 0.01;
+
+/// Over different numbers of pairs, the mean may move by this many standard errors.
+pub const MEAN_SIGMAS: f64 = 4.0;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CosineValues {
+    pub count: u64,
+    pub mean: f64,
+    pub stddev: f64,
+    pub min: f64,
+    pub max: f64,
+    /// Counts per bin, bin `i` covering `[-1 + i * w, -1 + (i + 1) * w)` with
+    /// `w = 2 / BINS`; 1.0 goes in the last bin.
+    pub bins: Vec<u64>,
+    #[serde(skip)]
+    sum: f64,
+    #[serde(skip)]
+    sum_sq: f64,
+    /// `sqrt(n)` for a record with `n` non-zeros, for [`Self::add_dot`].
+    #[serde(skip)]
+    norms: Vec<f64>,
+}
+
+impl Default for CosineValues {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            mean: 0.0,
+            stddev: 0.0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            bins: vec![0; BINS],
+            sum: 0.0,
+            sum_sq: 0.0,
+            norms: Vec::new(),
+        }
+    }
+}
+
+impl CosineValues {
+    /// Count one value; `mean` and `stddev` are stale until [`Self::finish`].
+    pub fn add(&mut self, value: f64) {
+        let clamped = value.clamp(-1.0, 1.0);
+        let bin = ((clamped + 1.0) / 2.0 * BINS as f64) as usize;
+        self.bins[bin.min(BINS - 1)] += 1;
+        self.count += 1;
+        self.sum += value;
+        self.sum_sq += value * value;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+    }
+
+    /// [`Self::add`] the cosine [`from_dot`] gives two records with `nnz_a` and `nnz_b`
+    /// non-zeros, with their norms looked up instead of taken per pair.
+    pub fn add_dot(&mut self, dot: i64, nnz_a: usize, nnz_b: usize) {
+        let needed = nnz_a.max(nnz_b) + 1;
+        if self.norms.len() < needed {
+            let from = self.norms.len();
+            self.norms.extend((from..needed).map(|n| (n as f64).sqrt()));
+        }
+        let (na, nb) = (self.norms[nnz_a], self.norms[nnz_b]);
+        self.add(if na == 0.0 || nb == 0.0 {
+            0.0
+        } else {
+            dot as f64 / (na * nb)
+        });
+    }
+
+    /// Derive `mean` and `stddev` from the values added so far.
+    pub fn finish(&mut self) {
+        let n = self.count as f64;
+        self.mean = if self.count > 0 { self.sum / n } else { 0.0 };
+        self.stddev = if self.count > 1 {
+            ((self.sum_sq - self.sum * self.sum / n) / (n - 1.0))
+                .max(0.0)
+                .sqrt()
+        } else {
+            0.0
+        };
+    }
+
+    /// `extra.cosine_values` of a measurement, if it has one.
+    pub fn of(extra: &serde_json::Value) -> Option<Self> {
+        serde_json::from_value(extra.get("cosine_values")?.clone()).ok()
+    }
+
+    /// Total variation distance between the two normalised histograms, in [0, 1].
+    pub fn histogram_distance(&self, other: &Self) -> f64 {
+        if self.count == 0 || other.count == 0 || self.bins.len() != other.bins.len() {
+            return 1.0;
+        }
+        let (n, m) = (self.count as f64, other.count as f64);
+        self.bins
+            .iter()
+            .zip(&other.bins)
+            .map(|(&a, &b)| (a as f64 / n - b as f64 / m).abs())
+            .sum::<f64>()
+            / 2.0
+    }
+}
+
+/// The cosine of two ternary vectors from their dot product, as `SparseVec::cosine`
+/// computes it: the dot over the product of their norms (the square roots of their
+/// non-zero counts), 0 if either is empty. Ternary cosines often fall exactly on a bin
+/// edge, so the rounding has to match too.
+pub fn from_dot(dot: i64, a: &SparseVec, b: &SparseVec) -> f64 {
+    let norm = |v: &SparseVec| ((v.pos.len() + v.neg.len()) as f64).sqrt();
+    let (na, nb) = (norm(a), norm(b));
+    if na == 0.0 || nb == 0.0 {
+        0.0
+    } else {
+        dot as f64 / (na * nb)
+    }
+}
+
+/// How far a measurement's cosine distribution moved.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ValueDrift {
+    pub name: String,
+    pub baseline_mean: f64,
+    pub current_mean: f64,
+    /// See [`CosineValues::histogram_distance`].
+    pub histogram_distance: f64,
+}
+
+/// `Some` if `current` is not the distribution `baseline` was. Over the same number of
+/// pairs (the same records, for a deterministic dataset) both mean and histogram must
+/// match to within float noise; otherwise only a mean shift beyond [`MEAN_SIGMAS`]
+/// standard errors counts.
+pub fn drift(name: &str, baseline: &CosineValues, current: &CosineValues) -> Option<ValueDrift> {
+    let mean_delta = (current.mean - baseline.mean).abs();
+    let histogram_distance = baseline.histogram_distance(current);
+    let drifted = if baseline.count == current.count {
+        mean_delta > MEAN_TOLERANCE || histogram_distance > HISTOGRAM_TOLERANCE
+    } else {
+        let se = |v: &CosineValues| v.stddev * v.stddev / v.count.max(1) as f64;
+        mean_delta > MEAN_TOLERANCE + MEAN_SIGMAS * (se(baseline) + se(current)).sqrt()
+    };
+    drifted.then(|| ValueDrift {
+        name: name.to_string(),
+        baseline_mean: baseline.mean,
+        current_mean: current.mean,
+        histogram_distance,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(xs: &[f64]) -> CosineValues {
+        let mut v = CosineValues::default();
+        for &x in xs {
+            v.add(x);
+        }
+        v.finish();
+        v
+    }
+
+    #[test]
+    fn test_accumulate_and_round_trip() {
+        let v = values(&[-1.0, 0.0, 0.5, 1.0]);
+        assert_eq!(v.count, 4);
+        assert!((v.mean - 0.125).abs() < 1e-12);
+        assert!((v.stddev - 0.853912).abs() < 1e-6, "{}", v.stddev);
+        assert_eq!((v.min, v.max), (-1.0, 1.0));
+        assert_eq!(v.bins[0], 1);
+        assert_eq!(v.bins[BINS / 2], 1);
+        assert_eq!(v.bins[BINS - 1], 1);
+        assert_eq!(v.bins.iter().sum::<u64>(), 4);
+
+        let extra = serde_json::json!({ "cosine_values": v });
+        let back = CosineValues::of(&extra).unwrap();
+        assert_eq!((back.count, back.mean, &back.bins), (4, v.mean, &v.bins));
+        assert!(CosineValues::of(&serde_json::json!({})).is_none());
+    }
+
+    #[test]
+    fn test_add_dot_matches_from_dot() {
+        let v = |pos: Vec<usize>, neg: Vec<usize>| SparseVec { pos, neg };
+        let pairs = [
+            (v(vec![1, 4, 9], vec![2]), v(vec![1, 4], vec![9, 3, 7])),
+            (v(vec![5], vec![]), v(vec![5, 6], vec![8])),
+            (v(vec![], vec![]), v(vec![1], vec![2])),
+        ];
+        let (mut direct, mut looked_up) = (CosineValues::default(), CosineValues::default());
+        for (dot, (a, b)) in [(1, &pairs[0]), (1, &pairs[1]), (0, &pairs[2])] {
+            direct.add(from_dot(dot, a, b));
+            looked_up.add_dot(dot, a.pos.len() + a.neg.len(), b.pos.len() + b.neg.len());
+        }
+        direct.finish();
+        looked_up.finish();
+        assert_eq!(
+            serde_json::to_value(&direct).unwrap(),
+            serde_json::to_value(&looked_up).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_drift() {
+        let base = values(&[0.1, 0.2, 0.3, 0.2]);
+        assert_eq!(drift("c", &base, &base.clone()), None);
+        let shifted = values(&[0.1, 0.2, 0.3, 0.25]);
+        let d = drift("c", &base, &shifted).unwrap();
+        assert!((d.current_mean - d.baseline_mean - 0.0125).abs() < 1e-12);
+
+        // Different pair counts: only a mean shift well beyond sampling error counts.
+        let more = values(&[0.1, 0.2, 0.3, 0.2, 0.2, 0.1, 0.3, 0.2]);
+        assert_eq!(drift("c", &base, &more), None);
+        let moved = values(&[0.6, 0.7, 0.8, 0.7, 0.7, 0.6, 0.8, 0.7]);
+        assert!(drift("c", &base, &moved).is_some());
+    }
+}
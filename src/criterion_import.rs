@@ -0,0 +1,363 @@
+//! Import criterion benchmark outputs into contract measurements.
+//!
+//! Criterion writes one directory per benchmark under `target/criterion`, each with a
+//! `new/estimates.json` (and, since criterion 0.3, a `new/benchmark.json` carrying the
+//! group/function/value ids). This module walks that tree and maps every benchmark to a
+//! `criterion.<group>.<function>[.<value>]` measurement so trend tracking sees both worlds.
+//!
+//! Two layouts are understood:
+//!
+//! - **new** (criterion >= 0.3): lowercase estimate keys (`mean`, `median`, `std_dev`, ...)
+//!   and a `benchmark.json` with explicit ids.
+//! - **old** (criterion 0.2): capitalized estimate keys (`Mean`, `Median`, `StdDev`, ...) and
+//!   no `benchmark.json`; ids are derived from the directory path.
+//!
+//! Entries that cannot be parsed are skipped with a warning on stderr.
+
+use crate::schema::Measurement;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Criterion JSON layout an entry was read from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CriterionLayout {
+    Old,
+    New,
+}
+
+impl CriterionLayout {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CriterionLayout::Old => "old",
+            CriterionLayout::New => "new",
+        }
+    }
+}
+
+/// One statistic from `estimates.json`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Estimate {
+    pub point_estimate: f64,
+    pub lower_bound: f64,
+    pub upper_bound: f64,
+    pub confidence_level: f64,
+    pub standard_error: f64,
+}
+
+/// Identity of a criterion benchmark.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CriterionId {
+    pub group: String,
+    pub function: Option<String>,
+    pub value: Option<String>,
+}
+
+impl CriterionId {
+    /// Contract measurement name, e.g. `criterion.sparsevec_ops.bundle`.
+    pub fn measurement_name(&self) -> String {
+        let mut name = format!("criterion.{}", sanitize_segment(&self.group));
+        for part in [&self.function, &self.value].into_iter().flatten() {
+            name.push('.');
+            name.push_str(&sanitize_segment(part));
+        }
+        name
+    }
+}
+
+/// Lowercase a criterion id segment and replace characters that would break dotted names.
+fn sanitize_segment(s: &str) -> String {
+    s.trim()
+        .chars()
+        .map(|c| match c {
+            '.' | '/' | '\\' | ' ' | '\t' => '_',
+            c => c.to_ascii_lowercase(),
+        })
+        .collect()
+}
+
+fn parse_estimate(v: &Value) -> Option<Estimate> {
+    let ci = v.get("confidence_interval")?;
+    Some(Estimate {
+        point_estimate: v.get("point_estimate")?.as_f64()?,
+        lower_bound: ci.get("lower_bound")?.as_f64()?,
+        upper_bound: ci.get("upper_bound")?.as_f64()?,
+        confidence_level: ci.get("confidence_level")?.as_f64()?,
+        standard_error: v
+            .get("standard_error")
+            .and_then(Value::as_f64)
+            .unwrap_or(0.0),
+    })
+}
+
+fn estimate_json(e: &Estimate) -> Value {
+    json!({
+        "point_estimate": e.point_estimate,
+        "standard_error": e.standard_error,
+        "confidence_interval": {
+            "confidence_level": e.confidence_level,
+            "lower_bound": e.lower_bound,
+            "upper_bound": e.upper_bound,
+        },
+    })
+}
+
+/// Parse an `estimates.json` document, detecting the layout from its key casing.
+fn parse_estimates(v: &Value) -> Option<(CriterionLayout, Estimate, Value)> {
+    let (layout, mean_key, others): (_, _, &[(&str, &str)]) = if v.get("mean").is_some() {
+        (
+            CriterionLayout::New,
+            "mean",
+            &[
+                ("median", "median"),
+                ("std_dev", "std_dev"),
+                ("median_abs_dev", "median_abs_dev"),
+                ("slope", "slope"),
+            ],
+        )
+    } else if v.get("Mean").is_some() {
+        (
+            CriterionLayout::Old,
+            "Mean",
+            &[
+                ("Median", "median"),
+                ("StdDev", "std_dev"),
+                ("MedianAbsDev", "median_abs_dev"),
+                ("Slope", "slope"),
+            ],
+        )
+    } else {
+        return None;
+    };
+
+    let mean = parse_estimate(v.get(mean_key)?)?;
+    let mut stats = serde_json::Map::new();
+    for (src, dst) in others {
+        // `slope` is null for flat sampling in new criterion; skip rather than fail.
+        if let Some(e) = v.get(*src).and_then(parse_estimate) {
+            stats.insert(dst.to_string(), estimate_json(&e));
+        }
+    }
+    Some((layout, mean, Value::Object(stats)))
+}
+
+/// Parse `benchmark.json` (new layout only).
+fn parse_benchmark_id(v: &Value) -> Option<CriterionId> {
+    let group = v.get("group_id")?.as_str()?.to_string();
+    let function = v
+        .get("function_id")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    let value = v
+        .get("value_str")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    Some(CriterionId {
+        group,
+        function,
+        value,
+    })
+}
+
+/// Derive an id from `<root>/<group>/[<function>/][<value>/]new/estimates.json`.
+fn id_from_path(root: &Path, estimates: &Path) -> Option<CriterionId> {
+    let bench_dir = estimates.parent()?.parent()?;
+    let rel = bench_dir.strip_prefix(root).ok()?;
+    let mut parts = rel
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string());
+    let group = parts.next()?;
+    Some(CriterionId {
+        group,
+        function: parts.next(),
+        value: parts.next(),
+    })
+}
+
+/// Sum `iters` and `times` from `sample.json`, if present and understood.
+fn parse_sample_totals(v: &Value) -> Option<(u64, f64)> {
+    let (iters, times) = match v {
+        Value::Object(_) => (v.get("iters")?.as_array()?, v.get("times")?.as_array()?),
+        // criterion 0.2 stored `[iters, times]` as a bare pair of arrays.
+        Value::Array(a) if a.len() == 2 => (a[0].as_array()?, a[1].as_array()?),
+        _ => return None,
+    };
+    let iters = iters.iter().map(|x| x.as_f64().unwrap_or(0.0)).sum::<f64>();
+    let times = times.iter().map(|x| x.as_f64().unwrap_or(0.0)).sum::<f64>();
+    Some((iters as u64, times))
+}
+
+fn read_json(path: &Path) -> io::Result<Value> {
+    let bytes = fs::read(path)?;
+    serde_json::from_slice(&bytes).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {e}", path.display()),
+        )
+    })
+}
+
+/// Build a measurement from one `new/estimates.json` file.
+fn import_entry(root: &Path, estimates_path: &Path) -> io::Result<Measurement> {
+    let dir = estimates_path
+        .parent()
+        .ok_or_else(|| io::Error::other("estimates.json has no parent directory"))?;
+
+    let estimates = read_json(estimates_path)?;
+    let (layout, mean, stats) = parse_estimates(&estimates).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "{}: no mean estimate in a known layout",
+                estimates_path.display()
+            ),
+        )
+    })?;
+
+    let benchmark_path = dir.join("benchmark.json");
+    let (id, full_id) = if benchmark_path.is_file() {
+        let v = read_json(&benchmark_path)?;
+        let id = parse_benchmark_id(&v).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: missing group_id", benchmark_path.display()),
+            )
+        })?;
+        let full_id = v.get("full_id").and_then(Value::as_str).map(str::to_string);
+        (id, full_id)
+    } else {
+        let id = id_from_path(root, estimates_path).ok_or_else(|| {
+            io::Error::other(format!(
+                "{}: cannot derive benchmark id from path",
+                estimates_path.display()
+            ))
+        })?;
+        (id, None)
+    };
+
+    let sample_path = dir.join("sample.json");
+    let totals = if sample_path.is_file() {
+        read_json(&sample_path)
+            .ok()
+            .and_then(|v| parse_sample_totals(&v))
+    } else {
+        None
+    };
+    let (iters, total_ns) = match totals {
+        Some((iters, times)) => (iters, times.max(0.0).round() as u128),
+        None => (0, mean.point_estimate.max(0.0).round() as u128),
+    };
+
+    Ok(Measurement {
+        name: id.measurement_name(),
+        unit: "ns/iter".to_string(),
+        iters,
+        warmup_iters: 0,
+        total_ns,
+        ns_per_iter: mean.point_estimate,
+        bytes_processed: None,
+        throughput_bytes_per_s: None,
+        extra: json!({
+            "source": "criterion",
+            "layout": layout.as_str(),
+            "group_id": id.group,
+            "function_id": id.function,
+            "value_str": id.value,
+            "full_id": full_id,
+            "estimate": "mean",
+            "mean": estimate_json(&mean),
+            "stats": stats,
+        }),
+        tags: BTreeMap::new(),
+    })
+}
+
+fn find_estimates(root: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut out = Vec::new();
+    for entry in walkdir::WalkDir::new(root).follow_links(false) {
+        let entry = entry?;
+        if !entry.file_type().is_file() || entry.file_name() != "estimates.json" {
+            continue;
+        }
+        // Only the latest run; `base/` and `change/` hold history and deltas.
+        let in_new = entry
+            .path()
+            .parent()
+            .and_then(Path::file_name)
+            .is_some_and(|n| n == "new");
+        if in_new {
+            out.push(entry.path().to_path_buf());
+        }
+    }
+    out.sort();
+    Ok(out)
+}
+
+/// Import every benchmark under a criterion output directory (usually `target/criterion`).
+///
+/// Measurements are sorted by name. Unparsable entries are skipped with a warning.
+pub fn import_dir(criterion_dir: &Path) -> io::Result<Vec<Measurement>> {
+    if !criterion_dir.is_dir() {
+        return Err(io::Error::other(format!(
+            "--criterion-dir {} is not a directory",
+            criterion_dir.display()
+        )));
+    }
+
+    let mut out = Vec::new();
+    for path in find_estimates(criterion_dir)? {
+        match import_entry(criterion_dir, &path) {
+            Ok(m) => out.push(m),
+            Err(e) => eprintln!("warning: skipping criterion entry: {e}"),
+        }
+    }
+    out.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture_dir() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/criterion")
+    }
+
+    #[test]
+    fn test_fixture_mapping() {
+        let ms = import_dir(&fixture_dir()).unwrap();
+        let names: Vec<&str> = ms.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "criterion.legacy_ops.bind",
+                "criterion.sparsevec_ops.bundle",
+                "criterion.vsa_ops.bundle_chain.8",
+            ]
+        );
+
+        let bundle = &ms[1];
+        assert_eq!(bundle.extra["layout"], "new");
+        assert_eq!(bundle.ns_per_iter, 1234.5);
+        assert_eq!(bundle.iters, 600);
+        assert_eq!(bundle.total_ns, 740_700);
+        assert_eq!(
+            bundle.extra["mean"]["confidence_interval"]["lower_bound"],
+            1200.0
+        );
+        assert!(bundle.extra["stats"].get("slope").is_none());
+
+        let legacy = &ms[0];
+        assert_eq!(legacy.extra["layout"], "old");
+        assert_eq!(legacy.ns_per_iter, 88.25);
+        assert_eq!(legacy.extra["stats"]["median"]["point_estimate"], 87.0);
+    }
+
+    #[test]
+    fn test_sanitize_segment() {
+        assert_eq!(sanitize_segment("Bundle Chain/8"), "bundle_chain_8");
+        assert_eq!(sanitize_segment("dim=10.000"), "dim=10_000");
+    }
+}
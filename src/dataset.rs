@@ -8,47 +8,422 @@
 //! ```text
 //! Header:
 //!   magic: [u8; 8]  = b"EMBR_DST"
-//!   version: u32    = 1
+//!   version: u32    = 1, or 2 when any flag is set
 //!   count: u64      = number of vectors
 //!   dimension: u64  = vector dimension (typically 10000)
 //!   seed: u64       = random seed used for generation
-//!   reserved: [u8; 32] = zeros (future use)
+//!   reserved: [u8; 32] = header extensions in the first 28 bytes (see below); in
+//!                        version 2 the last 4 bytes are `flags: u32`
 //!
 //! Body (repeated `count` times):
+//!   label: u32      = only with the labels flag (bit 0)
 //!   pos_len: u32
 //!   pos_indices: [u32; pos_len]
 //!   neg_len: u32
 //!   neg_indices: [u32; neg_len]
 //! ```
+//!
+//! # Labels
+//!
+//! A labeled dataset associates a `u32` (cluster id, source file id) with each vector;
+//! [`DatasetReader::next_labeled_vector`] returns it. Every other reader skips it, so
+//! benches run over labeled and unlabeled files alike. Unlabeled files are still written
+//! as version 1, byte for byte as before.
+//!
+//! # Generator versions
+//!
+//! Flag bit 1 marks a file whose uniform vectors were drawn by [`GeneratorVersion::V2`]
+//! (sampling without replacement) instead of the original full shuffle. Files without it
+//! regenerate with the v1 code path, which is kept unchanged for them.
+//!
+//! # Header extensions
+//!
+//! Anything stored in the reserved bytes goes through one type-length-value list, so
+//! separate features cannot claim overlapping bytes. The first [`EXTENSION_BYTES`]
+//! reserved bytes hold entries back to back:
+//!
+//! ```text
+//!   tag: u8          = an [`ExtensionTag`]; 0 ends the list (the rest is zeros)
+//!   len: u8          = length of value
+//!   value: [u8; len]
+//! ```
+//!
+//! An entry must end within the region; one that would not is a corrupt header. A tag
+//! this reader does not know is kept as [`HeaderExtension::Unknown`] and otherwise
+//! ignored, so older readers open files written with newer extensions. A known tag
+//! with the wrong length is an error. New extensions take the next unused tag and must
+//! fit alongside the existing ones: all-zero reserved bytes (no extensions) stay valid
+//! for every version, which keeps unlabeled version 1 files unchanged.
+//!
+//! # Shards
+//!
+//! `write_dataset_shard` writes one contiguous slice of a dataset's global index range,
+//! so several processes or machines can generate a large dataset in parallel. A shard
+//! file's `count` is its own vector count and `seed` is the global seed; the
+//! [`ExtensionTag::Shard`] extension records where the slice sits:
+//!
+//! ```text
+//!   shard_index: u32
+//!   shard_count: u32
+//!   start: u64       = global index of the first vector
+//!   total: u64       = vector count of the whole dataset
+//! ```
+//!
+//! Shard files written before extensions existed start the reserved bytes with
+//! `b"SHRD"` followed by the same four fields; they are still read as such.
+//!
+//! Vectors depend only on `(seed, global index)`, so concatenating the bodies of all
+//! shards in order gives exactly the body of a single-process run.
+//!
+//! # Appending
+//!
+//! [`DatasetWriter::open_append`] grows a file in place rather than rewriting it. There
+//! is no footer or offset index to rebuild, and nothing but the header's `count` says
+//! where the records end, so an append is ordered around that field:
+//!
+//! 1. The new records are written after the counted ones and synced.
+//! 2. The first time, the header is flagged [`FLAG_APPENDED`] (raising a version 1 file
+//!    to [`FORMAT_VERSION_FLAGS`]) and synced.
+//! 3. `count` is overwritten in place, an 8-byte positioned write, and synced.
+//!
+//! Until step 3 the header still claims the old count, and every reader stops there. A
+//! crash before it leaves the old dataset followed by uncounted bytes, which
+//! `open_validated` (and so `dataset-verify`) reports as trailing bytes and the next
+//! `open_append` truncates. The count itself is a single aligned write within the
+//! first sector, the smallest in-place update the format allows.
+//!
+//! The writer holds an exclusive advisory lock on the file until it is dropped, so a
+//! second append fails at open instead of interleaving its records with the first's.
 
-use embeddenator::{SparseVec, DIM};
+use crate::atomic_write::{write_atomic, write_atomic_with};
+use crate::error::{self, BenchError};
+use clap::ValueEnum;
+use embeddenator::{BitslicedTritVec, BlockSparseTritVec, PackedTritVec, SparseVec, DIM};
+use memmap2::Mmap;
 use rand::seq::SliceRandom;
-use rand::SeedableRng;
+use rand::{Rng, SeedableRng};
 use rand_chacha::ChaCha8Rng;
 use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
 use std::fs::File;
-use std::io::{self, BufReader, BufWriter, Read, Write};
-use std::path::Path;
+use std::io::{self, BufReader, BufWriter, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tempfile::NamedTempFile;
 
 /// Magic bytes identifying the dataset format.
 const MAGIC: &[u8; 8] = b"EMBR_DST";
 
-/// Current format version.
-const FORMAT_VERSION: u32 = 1;
+/// Format version of files without flags (every generated v1 dataset).
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Format version whose header carries [`flags`](FLAG_LABELS).
+pub const FORMAT_VERSION_FLAGS: u32 = 2;
+
+/// Header flag: every record starts with a `u32` label.
+pub const FLAG_LABELS: u32 = 1;
+
+/// Header flag: vectors were generated by [`GeneratorVersion::V2`].
+pub const FLAG_GENERATOR_V2: u32 = 2;
+
+/// Header flag: records were appended after the file was written ([`DatasetWriter`]),
+/// so it is not one generator run's output.
+pub const FLAG_APPENDED: u32 = 4;
+
+/// Header flag: the records were taken from another dataset ([`write_derived_dataset`],
+/// as `dataset-dedupe` does), so the seed no longer regenerates them.
+pub const FLAG_DERIVED: u32 = 8;
+
+/// Flags this reader understands.
+const KNOWN_FLAGS: u32 = FLAG_LABELS | FLAG_GENERATOR_V2 | FLAG_APPENDED | FLAG_DERIVED;
+
+/// Offset of the version 2 flags within the reserved bytes (after the shard descriptor).
+const FLAGS_AT: usize = 28;
+
+/// Byte offset of the header's `version`, after the magic.
+const VERSION_AT: u64 = 8;
+
+/// Byte offset of the header's `count`, after magic and version.
+const COUNT_AT: u64 = 8 + 4;
+
+/// Byte offset of the version 2 flags: the reserved bytes follow magic, version, count,
+/// dimension and seed.
+const FLAGS_OFFSET: u64 = 8 + 4 + 8 + 8 + 8 + FLAGS_AT as u64;
 
 /// Header size in bytes.
 const HEADER_SIZE: usize = 8 + 4 + 8 + 8 + 8 + 32; // magic + version + count + dim + seed + reserved
 
+/// Marks a pre-extension shard descriptor in the header's reserved bytes.
+const SHARD_MAGIC: &[u8; 4] = b"SHRD";
+
+/// Reserved bytes available to header extensions (the rest holds the flags).
+pub const EXTENSION_BYTES: usize = FLAGS_AT;
+
+/// `BufReader` capacity of [`DatasetReader::open`] and stream readers.
+pub const DEFAULT_READ_BUFFER: usize = 64 * 1024;
+
+/// Most indices preallocated from a length prefix read out of a file. Longer lists grow
+/// as the data is actually read, so a corrupt prefix cannot trigger a huge allocation
+/// up front.
+const MAX_PREALLOC: usize = 1 << 16;
+
 /// Dataset metadata from the header.
 #[derive(Debug, Clone)]
 pub struct DatasetMeta {
     pub count: u64,
     pub dimension: u64,
     pub seed: u64,
+    /// [`FORMAT_VERSION`] or [`FORMAT_VERSION_FLAGS`].
+    pub version: u32,
+    /// Whether every record carries a label ([`FLAG_LABELS`]).
+    pub labeled: bool,
+    /// Which generator drew the vectors ([`FLAG_GENERATOR_V2`]).
+    pub generator: GeneratorVersion,
+    /// Whether records were appended after the file was written ([`FLAG_APPENDED`]).
+    pub appended: bool,
+    /// Whether the records came from another dataset ([`FLAG_DERIVED`]).
+    pub derived: bool,
+    /// Set when the file holds one shard of a larger dataset.
+    pub shard: Option<ShardDescriptor>,
+    /// Every header extension, in file order, including unknown ones.
+    pub extensions: Vec<HeaderExtension>,
+    /// Generation details from the `<name>.embr.meta.json` sidecar, when present.
+    pub extended: Option<ExtendedMeta>,
+}
+
+/// Which slice of a dataset a shard file holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShardDescriptor {
+    pub index: u32,
+    pub count: u32,
+    /// Global index of the shard's first vector.
+    pub start: u64,
+    /// Vector count of the whole (unsharded) dataset.
+    pub total: u64,
+}
+
+impl ShardDescriptor {
+    /// Shard `index` of `count` over a dataset of `total` vectors.
+    ///
+    /// Shards are contiguous and differ in size by at most one vector.
+    pub fn new(index: u32, count: u32, total: u64) -> io::Result<Self> {
+        if count == 0 || index >= count {
+            return Err(BenchError::invalid_args(format!(
+                "shard index {index} out of range for {count} shard(s)"
+            ))
+            .into());
+        }
+        let bound = |i: u32| (total as u128 * i as u128 / count as u128) as u64;
+        Ok(Self {
+            index,
+            count,
+            start: bound(index),
+            total,
+        })
+    }
+
+    /// Global index range covered by this shard.
+    pub fn range(&self) -> std::ops::Range<u64> {
+        let end = (self.total as u128 * (self.index as u128 + 1) / self.count as u128) as u64;
+        self.start..end
+    }
+
+    /// `name.shard3of8.embr` for `name.embr`.
+    pub fn file_name(&self, base: &str) -> String {
+        let stem = base.strip_suffix(".embr").unwrap_or(base);
+        format!("{stem}.shard{}of{}.embr", self.index, self.count)
+    }
+
+    /// Length of the [`ExtensionTag::Shard`] value.
+    const ENCODED_LEN: usize = 24;
+
+    fn encode(self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(Self::ENCODED_LEN);
+        out.extend_from_slice(&self.index.to_le_bytes());
+        out.extend_from_slice(&self.count.to_le_bytes());
+        out.extend_from_slice(&self.start.to_le_bytes());
+        out.extend_from_slice(&self.total.to_le_bytes());
+        out
+    }
+
+    /// Decode the four fields from exactly [`Self::ENCODED_LEN`] bytes.
+    fn decode(value: &[u8]) -> Self {
+        let u32_at = |at: usize| u32::from_le_bytes(value[at..at + 4].try_into().unwrap());
+        let u64_at = |at: usize| u64::from_le_bytes(value[at..at + 8].try_into().unwrap());
+        Self {
+            index: u32_at(0),
+            count: u32_at(4),
+            start: u64_at(8),
+            total: u64_at(16),
+        }
+    }
+}
+
+/// Tags of the header extensions this reader understands (see "Header extensions").
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ExtensionTag {
+    /// A [`ShardDescriptor`].
+    Shard = 1,
+}
+
+impl ExtensionTag {
+    pub fn from_u8(tag: u8) -> Option<Self> {
+        match tag {
+            1 => Some(ExtensionTag::Shard),
+            _ => None,
+        }
+    }
+
+    /// Value length every entry with this tag has.
+    fn value_len(self) -> usize {
+        match self {
+            ExtensionTag::Shard => ShardDescriptor::ENCODED_LEN,
+        }
+    }
+}
+
+/// One entry of the header extension list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HeaderExtension {
+    Shard(ShardDescriptor),
+    /// A tag this reader does not know; kept so it can be reported, otherwise ignored.
+    Unknown {
+        tag: u8,
+        value: Vec<u8>,
+    },
+}
+
+impl HeaderExtension {
+    pub fn tag(&self) -> u8 {
+        match self {
+            HeaderExtension::Shard(_) => ExtensionTag::Shard as u8,
+            HeaderExtension::Unknown { tag, .. } => *tag,
+        }
+    }
+
+    fn value(&self) -> Cow<'_, [u8]> {
+        match self {
+            HeaderExtension::Shard(s) => Cow::Owned(s.encode()),
+            HeaderExtension::Unknown { value, .. } => Cow::Borrowed(value),
+        }
+    }
+
+    /// One line for `dataset-info`.
+    pub fn describe(&self) -> String {
+        match self {
+            HeaderExtension::Shard(s) => format!(
+                "shard {} of {} (start {}, total {})",
+                s.index, s.count, s.start, s.total
+            ),
+            HeaderExtension::Unknown { tag, value } => {
+                format!("unknown tag {tag} ({} bytes, ignored)", value.len())
+            }
+        }
+    }
+}
+
+/// Encode `extensions` into the [`EXTENSION_BYTES`] extension region, zero-padded.
+/// Fails if they do not fit or one is tag 0 (the end marker).
+pub fn encode_extensions(extensions: &[HeaderExtension]) -> io::Result<[u8; EXTENSION_BYTES]> {
+    let mut out = [0u8; EXTENSION_BYTES];
+    let mut at = 0;
+    for ext in extensions {
+        let value = ext.value();
+        let end = at + 2 + value.len();
+        if ext.tag() == 0 || value.len() > u8::MAX as usize || end > EXTENSION_BYTES {
+            return Err(BenchError::invalid_args(format!(
+                "header extension {} ({} bytes) does not fit the {EXTENSION_BYTES}-byte extension region",
+                ext.tag(),
+                value.len()
+            ))
+            .into());
+        }
+        out[at] = ext.tag();
+        out[at + 1] = value.len() as u8;
+        out[at + 2..end].copy_from_slice(&value);
+        at = end;
+    }
+    Ok(out)
+}
+
+/// Decode the extension region: entries up to the first tag 0 or the end of `region`.
+/// Unknown tags are kept as [`HeaderExtension::Unknown`]; an entry running past the
+/// region or a known tag of the wrong length is an error.
+pub fn decode_extensions(region: &[u8]) -> io::Result<Vec<HeaderExtension>> {
+    let invalid = |msg: String| io::Error::from(BenchError::format(None, msg));
+    let mut out = Vec::new();
+    let mut at = 0;
+    while at < region.len() && region[at] != 0 {
+        let tag = region[at];
+        let Some(&len) = region.get(at + 1) else {
+            return Err(invalid(format!(
+                "header extension {tag} at byte {at} has no length"
+            )));
+        };
+        let end = at + 2 + len as usize;
+        let Some(value) = region.get(at + 2..end) else {
+            return Err(invalid(format!(
+                "header extension {tag} at byte {at} ({len} bytes) runs past the {}-byte extension region",
+                region.len()
+            )));
+        };
+        out.push(match ExtensionTag::from_u8(tag) {
+            Some(known) if known.value_len() != value.len() => {
+                return Err(invalid(format!(
+                    "header extension {known:?} has {len} bytes, expected {}",
+                    known.value_len()
+                )));
+            }
+            Some(ExtensionTag::Shard) => HeaderExtension::Shard(ShardDescriptor::decode(value)),
+            None => HeaderExtension::Unknown {
+                tag,
+                value: value.to_vec(),
+            },
+        });
+        at = end;
+    }
+    Ok(out)
+}
+
+/// The extensions in a header's reserved bytes, legacy shard descriptors included.
+fn read_extensions(reserved: &[u8; 32]) -> io::Result<Vec<HeaderExtension>> {
+    if &reserved[..4] == SHARD_MAGIC {
+        return Ok(vec![HeaderExtension::Shard(ShardDescriptor::decode(
+            &reserved[4..EXTENSION_BYTES],
+        ))]);
+    }
+    decode_extensions(&reserved[..EXTENSION_BYTES])
+}
+
+/// Reserved header bytes holding `extensions` (flags are added by the header writer).
+fn reserved_with(extensions: &[HeaderExtension]) -> io::Result<[u8; 32]> {
+    let mut reserved = [0u8; 32];
+    reserved[..EXTENSION_BYTES].copy_from_slice(&encode_extensions(extensions)?);
+    Ok(reserved)
+}
+
+/// Sidecar metadata written next to generated datasets (`<name>.embr.meta.json`).
+///
+/// The binary header only stores count/dimension/seed; the sidecar keeps everything
+/// else needed to interpret or regenerate an archived dataset.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExtendedMeta {
+    pub generate: GenerateConfig,
+    /// Version of this crate that wrote the dataset.
+    pub crate_version: String,
+    pub created_utc: String,
+    /// SHA-256 of the whole dataset file (header included).
+    pub content_sha256: String,
+    /// Shard descriptor, for files written by `write_dataset_shard`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shard: Option<ShardDescriptor>,
 }
 
 /// Configuration for dataset generation.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct GenerateConfig {
     /// Number of vectors to generate.
     pub count: u64,
@@ -58,6 +433,12 @@ pub struct GenerateConfig {
     pub seed: u64,
     /// Target sparsity: number of +1 and -1 indices each (~1% of dimension).
     pub sparsity: usize,
+    /// How indices are drawn (sidecars written before this existed are uniform).
+    #[serde(default)]
+    pub index_distribution: IndexDistribution,
+    /// How uniform indices are sampled (sidecars written before this existed are v1).
+    #[serde(default)]
+    pub generator: GeneratorVersion,
 }
 
 impl Default for GenerateConfig {
@@ -67,7 +448,127 @@ impl Default for GenerateConfig {
             dimension: DIM,
             seed: 42,
             sparsity: DIM / 100, // ~1% density for each sign
+            index_distribution: IndexDistribution::Uniform,
+            generator: GeneratorVersion::V1,
+        }
+    }
+}
+
+/// Sampling routine for uniform vectors, recorded in the header ([`FLAG_GENERATOR_V2`])
+/// so a file always regenerates with the routine that wrote it. Zipf vectors are the same
+/// under both.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum GeneratorVersion {
+    /// Shuffle all of `0..dimension` and take a prefix: O(dimension) time and memory per
+    /// vector.
+    #[default]
+    V1,
+    /// Draw `2 * sparsity` distinct indices with Floyd's algorithm: O(sparsity).
+    V2,
+}
+
+impl GeneratorVersion {
+    pub fn label(self) -> &'static str {
+        match self {
+            GeneratorVersion::V1 => "v1",
+            GeneratorVersion::V2 => "v2",
+        }
+    }
+
+    fn flags(self) -> u32 {
+        match self {
+            GeneratorVersion::V1 => 0,
+            GeneratorVersion::V2 => FLAG_GENERATOR_V2,
+        }
+    }
+}
+
+/// Distribution of non-zero indices within a generated vector.
+///
+/// Uniform gives every dimension the same hit probability. Encoded content is usually
+/// concentrated in hot dimensions instead, which lengthens some inverted-index posting
+/// lists and fills some blocks far more than others; `Zipf` models that by weighting
+/// index `i` by `1 / (i + 1)^s`, so low ids are hot.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum IndexDistribution {
+    #[default]
+    Uniform,
+    Zipf {
+        s: f64,
+    },
+}
+
+impl IndexDistribution {
+    /// Parse `uniform` or `zipf:<s>` (e.g. `zipf:1.1`).
+    pub fn parse(s: &str) -> io::Result<Self> {
+        let invalid = |msg: String| io::Error::from(BenchError::invalid_args(msg));
+        match s.split_once(':') {
+            None if s == "uniform" => Ok(Self::Uniform),
+            Some(("zipf", exponent)) => {
+                let s: f64 = exponent
+                    .parse()
+                    .map_err(|_| invalid(format!("invalid zipf exponent `{exponent}`")))?;
+                let dist = Self::Zipf { s };
+                dist.validate()?;
+                Ok(dist)
+            }
+            _ => Err(invalid(format!(
+                "unknown index distribution `{s}` (expected uniform or zipf:<s>)"
+            ))),
+        }
+    }
+
+    fn validate(&self) -> io::Result<()> {
+        match *self {
+            Self::Zipf { s } if !(s.is_finite() && s > 0.0) => Err(BenchError::invalid_args(
+                format!("zipf exponent must be positive and finite (got {s})"),
+            )
+            .into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Short form for file names and reports: `uniform`, `zipf1.1`.
+    pub fn label(&self) -> String {
+        match self {
+            Self::Uniform => "uniform".to_string(),
+            Self::Zipf { s } => format!("zipf{s}"),
+        }
+    }
+}
+
+impl GenerateConfig {
+    /// Reject combinations the generator cannot produce.
+    ///
+    /// Requires `count >= 1`, `dimension >= 2`, `sparsity >= 1` and
+    /// `2 * sparsity <= dimension` (positive and negative indices are disjoint).
+    pub fn validate(&self) -> io::Result<()> {
+        let invalid = |msg: String| Err(BenchError::invalid_args(msg).into());
+        if self.count == 0 {
+            return invalid("count must be at least 1".to_string());
+        }
+        if self.dimension < 2 {
+            return invalid(format!(
+                "dimension must be at least 2 (got {})",
+                self.dimension
+            ));
+        }
+        if self.sparsity == 0 {
+            return invalid(
+                "sparsity must be at least 1 (empty vectors have no defined cosine)".to_string(),
+            );
         }
+        if self.sparsity.saturating_mul(2) > self.dimension {
+            return invalid(format!(
+                "sparsity {} needs {} distinct indices (+1 and -1 each) but dimension is {}",
+                self.sparsity,
+                self.sparsity.saturating_mul(2),
+                self.dimension
+            ));
+        }
+        self.index_distribution.validate()
     }
 }
 
@@ -85,27 +586,279 @@ fn generate_sparse_vec(rng: &mut ChaCha8Rng, dimension: usize, sparsity: usize)
     SparseVec { pos, neg }
 }
 
+/// [`GeneratorVersion::V2`]: the same distribution as `generate_sparse_vec` (a uniformly
+/// random `2 * sparsity`-subset, split uniformly into signs) without touching the other
+/// `dimension - 2 * sparsity` indices.
+fn sample_sparse_vec(rng: &mut ChaCha8Rng, dimension: usize, sparsity: usize) -> SparseVec {
+    let k = sparsity * 2;
+    // Floyd: for j in dimension-k..dimension, take a random t <= j, or j if t is taken.
+    let mut set = std::collections::HashSet::with_capacity(k);
+    for j in dimension - k..dimension {
+        let t = rng.gen_range(0..=j);
+        if !set.insert(t) {
+            set.insert(j);
+        }
+    }
+    // Floyd's insertion order is not uniform; sort away the set's order, then shuffle.
+    let mut chosen: Vec<usize> = set.into_iter().collect();
+    chosen.sort_unstable();
+    chosen.shuffle(rng);
+    let mut pos = chosen[..sparsity].to_vec();
+    let mut neg = chosen[sparsity..].to_vec();
+    pos.sort_unstable();
+    neg.sort_unstable();
+
+    SparseVec { pos, neg }
+}
+
+/// Like `generate_sparse_vec`, but drawing the `2 * sparsity` indices without replacement
+/// with Zipf weights `1 / (i + 1)^s` (Efraimidis–Spirakis: each index gets the key
+/// `ln(u) / w` and the largest keys win).
+fn generate_zipf_vec(rng: &mut ChaCha8Rng, dimension: usize, sparsity: usize, s: f64) -> SparseVec {
+    let mut keyed: Vec<(f64, usize)> = (0..dimension)
+        .map(|i| {
+            let u = 1.0 - rng.gen::<f64>(); // (0, 1], so ln(u) is finite
+            (u.ln() * ((i + 1) as f64).powf(s), i)
+        })
+        .collect();
+    let k = sparsity * 2;
+    keyed.select_nth_unstable_by(k - 1, |a, b| b.0.total_cmp(&a.0));
+
+    let mut chosen: Vec<usize> = keyed[..k].iter().map(|&(_, i)| i).collect();
+    chosen.sort_unstable();
+    // Selection order says nothing about sign; split at random.
+    chosen.shuffle(rng);
+    let mut pos = chosen[..sparsity].to_vec();
+    let mut neg = chosen[sparsity..].to_vec();
+    pos.sort_unstable();
+    neg.sort_unstable();
+
+    SparseVec { pos, neg }
+}
+
 fn per_vector_seed(master_seed: u64, index: usize) -> u64 {
     master_seed
         .wrapping_add(index as u64)
         .wrapping_mul(0x517cc1b727220a95)
 }
 
+/// Generate vector `index` of a uniform dataset exactly as the generators do.
+///
+/// Every vector depends only on `(seed, index, dimension, sparsity)`, which is what lets
+/// `check_determinism` regenerate a sample without touching the rest of the file.
+pub(crate) fn generate_indexed(
+    seed: u64,
+    index: usize,
+    dimension: usize,
+    sparsity: usize,
+) -> SparseVec {
+    generate_indexed_with(
+        seed,
+        index,
+        dimension,
+        sparsity,
+        IndexDistribution::Uniform,
+        GeneratorVersion::V1,
+    )
+}
+
+/// [`generate_indexed`] for any index distribution.
+pub(crate) fn generate_indexed_with(
+    seed: u64,
+    index: usize,
+    dimension: usize,
+    sparsity: usize,
+    distribution: IndexDistribution,
+    generator: GeneratorVersion,
+) -> SparseVec {
+    let mut rng = ChaCha8Rng::seed_from_u64(per_vector_seed(seed, index));
+    match (distribution, generator) {
+        (IndexDistribution::Uniform, GeneratorVersion::V1) => {
+            generate_sparse_vec(&mut rng, dimension, sparsity)
+        }
+        (IndexDistribution::Uniform, GeneratorVersion::V2) => {
+            sample_sparse_vec(&mut rng, dimension, sparsity)
+        }
+        (IndexDistribution::Zipf { s }, _) => generate_zipf_vec(&mut rng, dimension, sparsity, s),
+    }
+}
+
 fn write_header<W: Write>(
     writer: &mut W,
     count: u64,
     dimension: usize,
     seed: u64,
+    labeled: bool,
+) -> io::Result<()> {
+    let flags = if labeled { FLAG_LABELS } else { 0 };
+    write_header_reserved(writer, count, dimension, seed, [0u8; 32], flags)
+}
+
+/// Write a header; any `flags` make it version 2, none keep it version 1.
+fn write_header_reserved<W: Write>(
+    writer: &mut W,
+    count: u64,
+    dimension: usize,
+    seed: u64,
+    reserved: [u8; 32],
+    flags: u32,
+) -> io::Result<()> {
+    let version = if flags == 0 {
+        FORMAT_VERSION
+    } else {
+        FORMAT_VERSION_FLAGS
+    };
+    write_header_version(writer, count, dimension, seed, reserved, flags, version)
+}
+
+/// Write a header of `version`, which must be able to carry `flags`.
+fn write_header_version<W: Write>(
+    writer: &mut W,
+    count: u64,
+    dimension: usize,
+    seed: u64,
+    mut reserved: [u8; 32],
+    flags: u32,
+    version: u32,
 ) -> io::Result<()> {
+    debug_assert!(flags == 0 || version == FORMAT_VERSION_FLAGS);
+    if version == FORMAT_VERSION_FLAGS {
+        reserved[FLAGS_AT..].copy_from_slice(&flags.to_le_bytes());
+    }
     writer.write_all(MAGIC)?;
-    writer.write_all(&FORMAT_VERSION.to_le_bytes())?;
+    writer.write_all(&version.to_le_bytes())?;
     writer.write_all(&count.to_le_bytes())?;
     writer.write_all(&(dimension as u64).to_le_bytes())?;
     writer.write_all(&seed.to_le_bytes())?;
-    writer.write_all(&[0u8; 32])?; // reserved
+    writer.write_all(&reserved)?;
+    Ok(())
+}
+
+/// Parse and validate a dataset header (including the reserved bytes).
+fn read_header<R: Read>(reader: &mut R) -> io::Result<DatasetMeta> {
+    read_header_fields(reader).map_err(|e| match e.kind() {
+        io::ErrorKind::UnexpectedEof if BenchError::of(&e).is_none() => BenchError::truncated(
+            0,
+            format!("dataset ends inside its {HEADER_SIZE}-byte header"),
+        )
+        .into(),
+        _ => e,
+    })
+}
+
+fn read_header_fields<R: Read>(reader: &mut R) -> io::Result<DatasetMeta> {
+    let mut magic = [0u8; 8];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(BenchError::format(
+            0,
+            format!("Invalid magic bytes: expected {:?}, got {:?}", MAGIC, magic),
+        )
+        .into());
+    }
+
+    let mut buf4 = [0u8; 4];
+    let mut buf8 = [0u8; 8];
+
+    reader.read_exact(&mut buf4)?;
+    let version = u32::from_le_bytes(buf4);
+    if version != FORMAT_VERSION && version != FORMAT_VERSION_FLAGS {
+        return Err(
+            BenchError::format(8, format!("Unsupported format version: {}", version)).into(),
+        );
+    }
+
+    reader.read_exact(&mut buf8)?;
+    let count = u64::from_le_bytes(buf8);
+
+    reader.read_exact(&mut buf8)?;
+    let dimension = u64::from_le_bytes(buf8);
+
+    reader.read_exact(&mut buf8)?;
+    let seed = u64::from_le_bytes(buf8);
+
+    let mut reserved = [0u8; 32];
+    reader.read_exact(&mut reserved)?;
+    let flags = if version == FORMAT_VERSION_FLAGS {
+        u32::from_le_bytes(reserved[FLAGS_AT..].try_into().unwrap())
+    } else {
+        0
+    };
+    if flags & !KNOWN_FLAGS != 0 {
+        return Err(
+            BenchError::format(None, format!("Unsupported header flags: {flags:#x}")).into(),
+        );
+    }
+    let extensions = read_extensions(&reserved)?;
+    let shard = extensions.iter().find_map(|e| match e {
+        HeaderExtension::Shard(s) => Some(*s),
+        _ => None,
+    });
+
+    Ok(DatasetMeta {
+        count,
+        dimension,
+        seed,
+        version,
+        labeled: flags & FLAG_LABELS != 0,
+        generator: if flags & FLAG_GENERATOR_V2 != 0 {
+            GeneratorVersion::V2
+        } else {
+            GeneratorVersion::V1
+        },
+        appended: flags & FLAG_APPENDED != 0,
+        derived: flags & FLAG_DERIVED != 0,
+        shard,
+        extensions,
+        extended: None,
+    })
+}
+
+/// Reject an index-list length prefix longer than the dimension: such a list cannot
+/// hold distinct in-range indices, so the prefix is corrupt.
+fn check_index_len(len: u32, dimension: u64) -> io::Result<()> {
+    if u64::from(len) > dimension {
+        return Err(BenchError::format(
+            None,
+            format!("index list length {len} exceeds dimension {dimension}"),
+        )
+        .into());
+    }
     Ok(())
 }
 
+/// Read one length-prefixed index list.
+fn read_indices<R: Read>(reader: &mut R, dimension: u64) -> io::Result<Vec<usize>> {
+    let mut buf4 = [0u8; 4];
+    reader.read_exact(&mut buf4)?;
+    let len = u32::from_le_bytes(buf4);
+    check_index_len(len, dimension)?;
+    let mut indices = Vec::with_capacity((len as usize).min(MAX_PREALLOC));
+    for _ in 0..len {
+        reader.read_exact(&mut buf4)?;
+        indices.push(u32::from_le_bytes(buf4) as usize);
+    }
+    Ok(indices)
+}
+
+/// Read a record's label, when the file has them.
+fn read_label<R: Read>(reader: &mut R, labeled: bool) -> io::Result<Option<u32>> {
+    if !labeled {
+        return Ok(None);
+    }
+    let mut buf4 = [0u8; 4];
+    reader.read_exact(&mut buf4)?;
+    Ok(Some(u32::from_le_bytes(buf4)))
+}
+
+/// Write one record: the label (only for labeled files), then the two index lists.
+fn write_record<W: Write>(writer: &mut W, label: Option<u32>, vec: &SparseVec) -> io::Result<()> {
+    if let Some(label) = label {
+        writer.write_all(&label.to_le_bytes())?;
+    }
+    write_vector(writer, vec)
+}
+
 fn write_vector<W: Write>(writer: &mut W, vec: &SparseVec) -> io::Result<()> {
     writer.write_all(&(vec.pos.len() as u32).to_le_bytes())?;
     for &idx in &vec.pos {
@@ -124,22 +877,24 @@ fn write_vector<W: Write>(writer: &mut W, vec: &SparseVec) -> io::Result<()> {
 ///
 /// Uses parallel generation with per-thread RNGs derived from the master seed
 /// for reproducibility and performance.
-pub fn generate_dataset(config: &GenerateConfig) -> Vec<SparseVec> {
+pub fn generate_dataset(config: &GenerateConfig) -> io::Result<Vec<SparseVec>> {
+    config.validate()?;
+
     let count = config.count as usize;
     let dimension = config.dimension;
     let sparsity = config.sparsity;
     let seed = config.seed;
+    let distribution = config.index_distribution;
+    let generator = config.generator;
 
     // For reproducibility, we generate sequential indices and use index-derived seeds
-    (0..count)
+    Ok((0..count)
         .into_par_iter()
         .map(|i| {
             // Derive per-vector seed from master seed + index for determinism
-            let vec_seed = per_vector_seed(seed, i);
-            let mut rng = ChaCha8Rng::seed_from_u64(vec_seed);
-            generate_sparse_vec(&mut rng, dimension, sparsity)
+            generate_indexed_with(seed, i, dimension, sparsity, distribution, generator)
         })
-        .collect()
+        .collect())
 }
 
 /// Write a dataset directly to disk without materializing all vectors in memory.
@@ -151,39 +906,152 @@ pub fn write_dataset_streaming<P: AsRef<Path>>(
     config: &GenerateConfig,
     batch_size: usize,
 ) -> io::Result<()> {
-    let file = File::create(path)?;
-    let mut writer = BufWriter::with_capacity(64 * 1024, file);
-
-    write_header(&mut writer, config.count, config.dimension, config.seed)?;
+    write_generated(path, config, None, batch_size, SizeCheck::Warn)
+}
 
-    let count = config.count as usize;
-    let dimension = config.dimension;
-    let sparsity = config.sparsity;
-    let seed = config.seed;
+/// Write only shard `shard` of the dataset described by `config`.
+///
+/// Vectors keep their global index (and so their per-vector seed); see the module docs
+/// for the header layout and how shards recombine.
+pub fn write_dataset_shard<P: AsRef<Path>>(
+    path: P,
+    config: &GenerateConfig,
+    shard: ShardDescriptor,
+    batch_size: usize,
+) -> io::Result<()> {
+    write_generated(path, config, Some(shard), batch_size, SizeCheck::Warn)
+}
 
-    let batch_size = batch_size.max(1);
-    let mut start = 0usize;
-    while start < count {
-        let end = (start + batch_size).min(count);
+/// What to do when a written file's size differs from the size its contents imply.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SizeCheck {
+    /// Print a warning and keep the file.
+    #[default]
+    Warn,
+    /// Fail, leaving any previous file in place (`--strict-size`).
+    Strict,
+}
 
-        // Range is an IndexedParallelIterator; collect preserves order.
-        let batch: Vec<SparseVec> = (start..end)
-            .into_par_iter()
-            .map(|i| {
-                let vec_seed = per_vector_seed(seed, i);
-                let mut rng = ChaCha8Rng::seed_from_u64(vec_seed);
-                generate_sparse_vec(&mut rng, dimension, sparsity)
+/// [`write_dataset_streaming`] or, with `shard`, [`write_dataset_shard`], with a choice
+/// of what a size mismatch does.
+pub fn write_generated<P: AsRef<Path>>(
+    path: P,
+    config: &GenerateConfig,
+    shard: Option<ShardDescriptor>,
+    batch_size: usize,
+    size_check: SizeCheck,
+) -> io::Result<()> {
+    config.validate()?;
+    if let Some(shard) = shard {
+        if shard.total != config.count {
+            return Err(BenchError::invalid_args(format!(
+                "shard covers {} vectors but the dataset has {}",
+                shard.total, config.count
+            ))
+            .into());
+        }
+    }
+    let range = shard.map_or(0..config.count, |s| s.range());
+    // Both index distributions give every vector exactly `sparsity` indices per sign.
+    let expected = expected_file_size(range.end - range.start, config.sparsity);
+    // An interrupted generation must not leave a truncated file that looks valid.
+    write_sized(path.as_ref(), Some(expected), size_check, |writer| {
+        write_range_to(writer, config, shard, batch_size)
+    })
+}
+
+/// Atomically write `path` with `write`, preallocating `expected` bytes (when known up
+/// front) and checking the result against it.
+///
+/// Preallocation is best-effort and only saves the filesystem from growing the file in
+/// small steps; whatever of it `write` leaves unwritten is truncated away before the
+/// check, so a short write is never padded with zeros.
+fn write_sized<F>(path: &Path, expected: Option<u64>, check: SizeCheck, write: F) -> io::Result<()>
+where
+    F: FnOnce(&mut BufWriter<NamedTempFile>) -> io::Result<()>,
+{
+    write_atomic_with(path, |writer| {
+        if let Some(expected) = expected {
+            let _ = writer.get_ref().as_file().set_len(expected);
+        }
+        write(writer)?;
+        writer.flush()?;
+        let mut file = writer.get_ref().as_file();
+        let written = file.stream_position()?;
+        file.set_len(written)?;
+        match expected {
+            Some(expected) => check_size(path, written, expected, check),
+            None => Ok(()),
+        }
+    })
+}
+
+fn check_size(path: &Path, written: u64, expected: u64, check: SizeCheck) -> io::Result<()> {
+    if written == expected {
+        return Ok(());
+    }
+    let msg = format!(
+        "{}: wrote {written} bytes but the header and record lengths imply {expected}",
+        path.display()
+    );
+    match check {
+        SizeCheck::Warn => {
+            eprintln!("warning: {msg}");
+            Ok(())
+        }
+        SizeCheck::Strict => Err(BenchError::format(None, msg).into()),
+    }
+}
+
+fn write_range_to<W: Write>(
+    writer: &mut W,
+    config: &GenerateConfig,
+    shard: Option<ShardDescriptor>,
+    batch_size: usize,
+) -> io::Result<()> {
+    let range = shard.map_or(0..config.count, |s| s.range());
+    let extensions: Vec<HeaderExtension> = shard.into_iter().map(HeaderExtension::Shard).collect();
+    let reserved = reserved_with(&extensions)?;
+    write_header_reserved(
+        writer,
+        range.end - range.start,
+        config.dimension,
+        config.seed,
+        reserved,
+        config.generator.flags(),
+    )?;
+
+    let count = range.end as usize;
+    let dimension = config.dimension;
+    let sparsity = config.sparsity;
+    let seed = config.seed;
+
+    let batch_size = batch_size.max(1);
+    let mut start = range.start as usize;
+    while start < count {
+        let end = (start + batch_size).min(count);
+
+        // Range is an IndexedParallelIterator; collect preserves order.
+        let batch: Vec<SparseVec> = (start..end)
+            .into_par_iter()
+            .map(|i| {
+                generate_indexed_with(
+                    seed,
+                    i,
+                    dimension,
+                    sparsity,
+                    config.index_distribution,
+                    config.generator,
+                )
             })
             .collect();
 
         for v in &batch {
-            write_vector(&mut writer, v)?;
+            write_vector(writer, v)?;
         }
 
         start = end;
     }
-
-    writer.flush()?;
     Ok(())
 }
 
@@ -193,401 +1061,3565 @@ pub fn write_dataset<P: AsRef<Path>>(
     vectors: &[SparseVec],
     config: &GenerateConfig,
 ) -> io::Result<()> {
-    let file = File::create(path)?;
-    let mut writer = BufWriter::with_capacity(64 * 1024, file);
+    write_records(path.as_ref(), vectors, None, config, 0)
+}
 
-    write_header(
-        &mut writer,
-        vectors.len() as u64,
-        config.dimension,
-        config.seed,
-    )?;
+/// [`write_dataset`] with a label per vector (`labels[i]` for `vectors[i]`), as a
+/// version 2 file with [`FLAG_LABELS`] set.
+pub fn write_labeled_dataset<P: AsRef<Path>>(
+    path: P,
+    vectors: &[SparseVec],
+    labels: &[u32],
+    config: &GenerateConfig,
+) -> io::Result<()> {
+    if labels.len() != vectors.len() {
+        return Err(BenchError::invalid_args(format!(
+            "{} labels for {} vectors",
+            labels.len(),
+            vectors.len()
+        ))
+        .into());
+    }
+    write_records(path.as_ref(), vectors, Some(labels), config, 0)
+}
+
+/// [`write_dataset`], or [`write_labeled_dataset`] with `labels`, for vectors taken from
+/// another dataset rather than generated from `config`: the header is flagged
+/// [`FLAG_DERIVED`], so [`check_determinism`] does not take them for generator output.
+pub fn write_derived_dataset<P: AsRef<Path>>(
+    path: P,
+    vectors: &[SparseVec],
+    labels: Option<&[u32]>,
+    config: &GenerateConfig,
+) -> io::Result<()> {
+    if labels.is_some_and(|l| l.len() != vectors.len()) {
+        return Err(BenchError::invalid_args(format!(
+            "{} labels for {} vectors",
+            labels.map_or(0, <[u32]>::len),
+            vectors.len()
+        ))
+        .into());
+    }
+    write_records(path.as_ref(), vectors, labels, config, FLAG_DERIVED)
+}
+
+/// On-disk layouts the writer can produce for the same vectors, compared by
+/// `dataset-bench-formats`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum DatasetFormat {
+    /// [`FORMAT_VERSION`]: no flags, so not for [`GeneratorVersion::V2`] vectors.
+    V1,
+    /// [`FORMAT_VERSION_FLAGS`]: the flags header, unlabeled records.
+    V2,
+    /// [`FORMAT_VERSION_FLAGS`] with [`FLAG_LABELS`]: a `u32` label before every record.
+    V2Labeled,
+}
+
+impl DatasetFormat {
+    pub const ALL: [DatasetFormat; 3] = [
+        DatasetFormat::V1,
+        DatasetFormat::V2,
+        DatasetFormat::V2Labeled,
+    ];
 
-    for vec in vectors {
-        write_vector(&mut writer, vec)?;
+    /// Measurement-name segment and `format` tag: `v1`, `v2`, `v2-labeled`.
+    pub fn label(self) -> &'static str {
+        match self {
+            DatasetFormat::V1 => "v1",
+            DatasetFormat::V2 => "v2",
+            DatasetFormat::V2Labeled => "v2-labeled",
+        }
     }
 
-    writer.flush()?;
-    Ok(())
+    pub fn version(self) -> u32 {
+        match self {
+            DatasetFormat::V1 => FORMAT_VERSION,
+            DatasetFormat::V2 | DatasetFormat::V2Labeled => FORMAT_VERSION_FLAGS,
+        }
+    }
+
+    /// Whether vectors generated under `config` can be written in this format.
+    pub fn supports(self, config: &GenerateConfig) -> bool {
+        self != DatasetFormat::V1 || config.generator.flags() == 0
+    }
+}
+
+/// [`write_dataset`] in a chosen [`DatasetFormat`]. [`DatasetFormat::V2Labeled`] labels
+/// each vector with its index.
+pub fn write_dataset_as<P: AsRef<Path>>(
+    path: P,
+    vectors: &[SparseVec],
+    config: &GenerateConfig,
+    format: DatasetFormat,
+) -> io::Result<()> {
+    if !format.supports(config) {
+        return Err(
+            BenchError::invalid_args("a version 1 header cannot record the v2 generator").into(),
+        );
+    }
+    let labels: Option<Vec<u32>> =
+        (format == DatasetFormat::V2Labeled).then(|| (0..vectors.len() as u32).collect());
+    write_records_version(
+        path.as_ref(),
+        vectors,
+        labels.as_deref(),
+        config,
+        0,
+        format.version(),
+    )
+}
+
+/// `flags` on top of the labels and generator ones.
+fn write_records(
+    path: &Path,
+    vectors: &[SparseVec],
+    labels: Option<&[u32]>,
+    config: &GenerateConfig,
+    flags: u32,
+) -> io::Result<()> {
+    let version = if labels.is_none() && config.generator.flags() | flags == 0 {
+        FORMAT_VERSION
+    } else {
+        FORMAT_VERSION_FLAGS
+    };
+    write_records_version(path, vectors, labels, config, flags, version)
+}
+
+fn write_records_version(
+    path: &Path,
+    vectors: &[SparseVec],
+    labels: Option<&[u32]>,
+    config: &GenerateConfig,
+    flags: u32,
+    version: u32,
+) -> io::Result<()> {
+    let label_bytes = if labels.is_some() { 4 } else { 0 };
+    let expected = expected_file_size_of(vectors.iter().map(|v| v.pos.len() + v.neg.len()))
+        .saturating_add(label_bytes * vectors.len() as u64);
+    write_sized(path, Some(expected), SizeCheck::Warn, |writer| {
+        let labeled = if labels.is_some() { FLAG_LABELS } else { 0 };
+        write_header_version(
+            writer,
+            vectors.len() as u64,
+            config.dimension,
+            config.seed,
+            [0u8; 32],
+            labeled | config.generator.flags() | flags,
+            version,
+        )?;
+        for (i, vec) in vectors.iter().enumerate() {
+            write_record(writer, labels.map(|l| l[i]), vec)?;
+        }
+        Ok(())
+    })
+}
+
+/// Appends records to an existing dataset file in place (see "Appending" in the module
+/// docs).
+///
+/// Records go to the end of the file as they are appended; the header's count only
+/// changes in [`Self::finish`]. Dropping the writer without finishing leaves the file as
+/// a crash would: the old dataset, with the appended bytes after it. Either way the
+/// file's lock is released with the writer.
+pub struct DatasetWriter {
+    path: PathBuf,
+    meta: DatasetMeta,
+    out: BufWriter<File>,
+    appended: u64,
+    /// Bytes past the counted records that [`Self::open_append`] truncated.
+    recovered_bytes: u64,
+    removed_sidecar: bool,
+}
+
+impl DatasetWriter {
+    /// Open `path` for appending: validate its header, find where its counted records
+    /// end (the format has no footer or offset index, so by walking their length
+    /// prefixes) and truncate anything after them, which only an interrupted append
+    /// leaves there.
+    ///
+    /// The file is locked exclusively first; a file another writer holds fails with
+    /// [`BenchError::Locked`]. Shard files are refused: their count is a slice of a
+    /// larger dataset's range. A sidecar is removed, since the appended file is no longer
+    /// what its generate config and hash describe.
+    pub fn open_append<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let mut file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path)?;
+        file.try_lock().map_err(|e| match e {
+            std::fs::TryLockError::WouldBlock => {
+                BenchError::Locked(format!("{}: another append has it locked", path.display()))
+                    .into()
+            }
+            std::fs::TryLockError::Error(e) => e,
+        })?;
+        let file_len = file.metadata()?.len();
+        // Read through the locked handle: on Windows the lock keeps other handles out.
+        let mut reader = DatasetReader::from_source_reader(SourceReader::File(BufReader::new(
+            file.try_clone()?,
+        )))?;
+        if let Some(shard) = reader.meta.shard {
+            return Err(BenchError::invalid_args(format!(
+                "{}: cannot append to shard {} of {}",
+                path.display(),
+                shard.index,
+                shard.count
+            ))
+            .into());
+        }
+        let end = reader.counted_len(file_len)?;
+        let meta = reader.meta.clone();
+        drop(reader);
+
+        let removed_sidecar = match std::fs::remove_file(sidecar_path(path)) {
+            Ok(()) => true,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => false,
+            Err(e) => return Err(e),
+        };
+        if end < file_len {
+            file.set_len(end)?;
+            file.sync_data()?;
+        }
+        file.seek(io::SeekFrom::Start(end))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            meta,
+            out: BufWriter::new(file),
+            appended: 0,
+            recovered_bytes: file_len - end,
+            removed_sidecar,
+        })
+    }
+
+    /// The header as opened; its count is the one before this writer's records.
+    pub fn meta(&self) -> &DatasetMeta {
+        &self.meta
+    }
+
+    /// Append one record. A labeled dataset needs a `label` for every record and an
+    /// unlabeled one takes none; indices must fit the dimension, with none both
+    /// positive and negative.
+    pub fn append(&mut self, vec: &SparseVec, label: Option<u32>) -> io::Result<()> {
+        if label.is_some() != self.meta.labeled {
+            return Err(BenchError::invalid_args(format!(
+                "{} is {}labeled; records appended to it must {}carry a label",
+                self.path.display(),
+                if self.meta.labeled { "" } else { "un" },
+                if self.meta.labeled { "" } else { "not " }
+            ))
+            .into());
+        }
+        if is_degenerate(vec, self.meta.dimension as usize) {
+            return Err(BenchError::invalid_args(format!(
+                "record {}: an index is out of range (dimension {}) or both positive and negative",
+                self.meta.count + self.appended,
+                self.meta.dimension
+            ))
+            .into());
+        }
+        write_record(&mut self.out, label, vec)?;
+        self.appended += 1;
+        Ok(())
+    }
+
+    /// Records appended so far.
+    pub fn appended(&self) -> u64 {
+        self.appended
+    }
+
+    /// Bytes an earlier, interrupted append had left after the counted records.
+    pub fn recovered_bytes(&self) -> u64 {
+        self.recovered_bytes
+    }
+
+    /// Whether opening removed the dataset's sidecar.
+    pub fn removed_sidecar(&self) -> bool {
+        self.removed_sidecar
+    }
+
+    /// Make the appended records durable, then publish them by patching the header
+    /// count. Returns the new count.
+    pub fn finish(mut self) -> io::Result<u64> {
+        self.sync_records()?;
+        self.mark_appended()?;
+        self.patch_count()
+    }
+
+    /// Step one of [`Self::finish`]: the records reach the disk, still uncounted.
+    fn sync_records(&mut self) -> io::Result<()> {
+        self.out.flush()?;
+        self.out.get_ref().sync_data()
+    }
+
+    /// Between the steps: flag the header [`FLAG_APPENDED`], once records were appended
+    /// and unless it already is, so the file no longer passes as generated.
+    fn mark_appended(&mut self) -> io::Result<()> {
+        if self.appended == 0 || self.meta.appended {
+            return Ok(());
+        }
+        let labeled = if self.meta.labeled { FLAG_LABELS } else { 0 };
+        let derived = if self.meta.derived { FLAG_DERIVED } else { 0 };
+        let flags = labeled | self.meta.generator.flags() | derived | FLAG_APPENDED;
+        let file = self.out.get_mut();
+        file.seek(io::SeekFrom::Start(VERSION_AT))?;
+        file.write_all(&FORMAT_VERSION_FLAGS.to_le_bytes())?;
+        file.seek(io::SeekFrom::Start(FLAGS_OFFSET))?;
+        file.write_all(&flags.to_le_bytes())?;
+        file.sync_data()?;
+        self.meta.version = FORMAT_VERSION_FLAGS;
+        self.meta.appended = true;
+        Ok(())
+    }
+
+    /// Step two: one 8-byte positioned write of the count, synced.
+    fn patch_count(&mut self) -> io::Result<u64> {
+        let count = self.meta.count + self.appended;
+        let file = self.out.get_mut();
+        file.seek(io::SeekFrom::Start(COUNT_AT))?;
+        file.write_all(&count.to_le_bytes())?;
+        file.sync_data()?;
+        self.meta.count = count;
+        self.appended = 0;
+        Ok(count)
+    }
+}
+
+/// One line of a `dataset-append` input: `{"pos": [...], "neg": [...]}`, with a
+/// `"label"` for a labeled dataset.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AppendLine {
+    pos: Vec<usize>,
+    neg: Vec<usize>,
+    #[serde(default)]
+    label: Option<u32>,
+}
+
+/// Append every JSONL record of `input` to `writer`, in order, returning how many.
+///
+/// Index lists are sorted and deduplicated. Blank lines are skipped; a line that does
+/// not parse, or a record [`DatasetWriter::append`] refuses, is a
+/// [`BenchError::DatasetFormat`] naming `source` and the line. Records before it stay
+/// appended but uncounted until the writer finishes.
+pub fn append_jsonl<R: io::BufRead>(
+    writer: &mut DatasetWriter,
+    input: R,
+    source: &str,
+) -> io::Result<u64> {
+    let mut appended = 0;
+    for (i, line) in input.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let bad = |what: String| {
+            io::Error::from(BenchError::format(
+                None,
+                format!("{source}:{}: {what}", i + 1),
+            ))
+        };
+        let AppendLine {
+            mut pos,
+            mut neg,
+            label,
+        } = serde_json::from_str(&line).map_err(|e| {
+            bad(format!(
+                "expected {{\"pos\": [...], \"neg\": [...]}} with an optional \"label\": {e}"
+            ))
+        })?;
+        pos.sort_unstable();
+        pos.dedup();
+        neg.sort_unstable();
+        neg.dedup();
+        writer
+            .append(&SparseVec { pos, neg }, label)
+            .map_err(|e| match BenchError::of(&e) {
+                Some(BenchError::InvalidArgs(reason)) => bad(reason.clone()),
+                _ => e,
+            })?;
+        appended += 1;
+    }
+    Ok(appended)
 }
 
 /// Read dataset metadata from a file header.
 pub fn read_dataset_meta<P: AsRef<Path>>(path: P) -> io::Result<DatasetMeta> {
-    let file = File::open(path)?;
+    let file = File::open(&path)?;
     let mut reader = BufReader::new(file);
 
-    let mut magic = [0u8; 8];
-    reader.read_exact(&mut magic)?;
-    if &magic != MAGIC {
-        return Err(io::Error::new(
-            io::ErrorKind::InvalidData,
-            format!("Invalid magic bytes: expected {:?}, got {:?}", MAGIC, magic),
-        ));
+    let mut meta = read_header(&mut reader)?;
+    meta.extended = load_sidecar(path.as_ref());
+    Ok(meta)
+}
+
+/// Load a dataset from a binary file.
+pub fn load_dataset<P: AsRef<Path>>(path: P) -> io::Result<(DatasetMeta, Vec<SparseVec>)> {
+    let file = File::open(&path)?;
+    let mut reader = BufReader::with_capacity(DEFAULT_READ_BUFFER, file);
+
+    let mut meta = read_header(&mut reader)?;
+    meta.extended = load_sidecar(path.as_ref());
+    let count = meta.count;
+
+    // Read vectors. Every record takes at least its two length prefixes, which bounds
+    // how many the file can hold whatever the header claims.
+    let max_records = std::fs::metadata(&path)?
+        .len()
+        .saturating_sub(HEADER_SIZE as u64)
+        / 8;
+    let mut vectors = Vec::with_capacity(count.min(max_records) as usize);
+    for _ in 0..count {
+        read_label(&mut reader, meta.labeled)?;
+        let pos = read_indices(&mut reader, meta.dimension)?;
+        let neg = read_indices(&mut reader, meta.dimension)?;
+        vectors.push(SparseVec { pos, neg });
     }
 
-    let mut buf4 = [0u8; 4];
-    let mut buf8 = [0u8; 8];
+    Ok((meta, vectors))
+}
 
-    reader.read_exact(&mut buf4)?;
-    let version = u32::from_le_bytes(buf4);
-    if version != FORMAT_VERSION {
-        return Err(io::Error::new(
-            io::ErrorKind::InvalidData,
-            format!("Unsupported format version: {}", version),
-        ));
+/// Where a dataset stream comes from.
+///
+/// File and memory sources are seekable, so `DatasetReader::reset` can rewind them for
+/// another pass. Stdin is read once; benches buffer the prefix they need from it (see
+/// [`buffer_prefix`]) and run over the in-memory copy.
+#[derive(Debug, Clone)]
+pub enum DatasetSource {
+    File(PathBuf),
+    Stdin,
+    Memory(Arc<[u8]>),
+}
+
+impl DatasetSource {
+    /// `-` means stdin; anything else is a file path.
+    pub fn from_arg(path: &Path) -> Self {
+        if path.as_os_str() == "-" {
+            DatasetSource::Stdin
+        } else {
+            DatasetSource::File(path.to_path_buf())
+        }
     }
 
-    reader.read_exact(&mut buf8)?;
-    let count = u64::from_le_bytes(buf8);
+    pub fn is_seekable(&self) -> bool {
+        !matches!(self, DatasetSource::Stdin)
+    }
 
-    reader.read_exact(&mut buf8)?;
-    let dimension = u64::from_le_bytes(buf8);
+    /// Human-readable label for reports (`<stdin>`, `<memory>` or the path).
+    pub fn label(&self) -> String {
+        match self {
+            DatasetSource::File(p) => p.display().to_string(),
+            DatasetSource::Stdin => "<stdin>".to_string(),
+            DatasetSource::Memory(_) => "<memory>".to_string(),
+        }
+    }
 
-    reader.read_exact(&mut buf8)?;
-    let seed = u64::from_le_bytes(buf8);
+    /// Open a streaming reader over this source.
+    pub fn open(&self) -> io::Result<DatasetReader> {
+        self.open_with_capacity(DEFAULT_READ_BUFFER)
+    }
 
-    Ok(DatasetMeta {
-        count,
-        dimension,
-        seed,
-    })
+    /// [`open`](Self::open) with a `capacity`-byte read buffer (unused for memory).
+    pub fn open_with_capacity(&self, capacity: usize) -> io::Result<DatasetReader> {
+        match self {
+            DatasetSource::File(p) => DatasetReader::open_with_capacity(p, capacity),
+            DatasetSource::Stdin => DatasetReader::from_stream_with_capacity(io::stdin(), capacity),
+            DatasetSource::Memory(bytes) => DatasetReader::from_source_reader(
+                SourceReader::Memory(io::Cursor::new(bytes.clone())),
+            ),
+        }
+    }
 }
 
-/// Load a dataset from a binary file.
-pub fn load_dataset<P: AsRef<Path>>(path: P) -> io::Result<(DatasetMeta, Vec<SparseVec>)> {
-    let file = File::open(path)?;
-    let mut reader = BufReader::with_capacity(64 * 1024, file);
+/// `posix_fadvise(2)` where it exists. Elsewhere (macOS has no such call, Windows
+/// takes its access hint when a file is opened) the hint is never given, and
+/// measurements record it as `"unsupported"` ([`sequential_hint_extra`]).
+#[cfg(target_os = "linux")]
+mod fadvise {
+    use std::fs::File;
+    use std::os::unix::io::AsRawFd;
 
-    // Read header
-    let mut magic = [0u8; 8];
-    reader.read_exact(&mut magic)?;
-    if &magic != MAGIC {
-        return Err(io::Error::new(
-            io::ErrorKind::InvalidData,
-            format!("Invalid magic bytes: expected {:?}, got {:?}", MAGIC, magic),
-        ));
+    pub const SUPPORTED: bool = true;
+
+    /// Whole file (`len` 0 means to the end).
+    pub fn sequential(file: &File) -> bool {
+        unsafe { libc::posix_fadvise(file.as_raw_fd(), 0, 0, libc::POSIX_FADV_SEQUENTIAL) == 0 }
     }
+}
 
-    let mut buf4 = [0u8; 4];
-    let mut buf8 = [0u8; 8];
+#[cfg(not(target_os = "linux"))]
+mod fadvise {
+    pub const SUPPORTED: bool = false;
 
-    reader.read_exact(&mut buf4)?;
-    let version = u32::from_le_bytes(buf4);
-    if version != FORMAT_VERSION {
-        return Err(io::Error::new(
-            io::ErrorKind::InvalidData,
-            format!("Unsupported format version: {}", version),
-        ));
+    pub fn sequential(_: &std::fs::File) -> bool {
+        false
     }
+}
 
-    reader.read_exact(&mut buf8)?;
-    let count = u64::from_le_bytes(buf8);
+/// Byte stream behind a `DatasetReader`.
+enum SourceReader {
+    File(BufReader<File>),
+    Memory(io::Cursor<Arc<[u8]>>),
+    /// Non-seekable (stdin, pipes).
+    Stream(BufReader<Box<dyn Read + Send>>),
+}
 
-    reader.read_exact(&mut buf8)?;
-    let dimension = u64::from_le_bytes(buf8);
+impl SourceReader {
+    /// Skip `n` bytes forward.
+    fn skip(&mut self, n: u64) -> io::Result<()> {
+        match self {
+            SourceReader::File(r) => r.seek_relative(n as i64),
+            SourceReader::Memory(c) => {
+                c.set_position(c.position() + n);
+                Ok(())
+            }
+            SourceReader::Stream(r) => {
+                let skipped = io::copy(&mut r.by_ref().take(n), &mut io::sink())?;
+                if skipped < n {
+                    return Err(io::ErrorKind::UnexpectedEof.into());
+                }
+                Ok(())
+            }
+        }
+    }
+}
 
-    reader.read_exact(&mut buf8)?;
-    let seed = u64::from_le_bytes(buf8);
+impl Read for SourceReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            SourceReader::File(r) => r.read(buf),
+            SourceReader::Memory(c) => c.read(buf),
+            SourceReader::Stream(r) => r.read(buf),
+        }
+    }
+}
+
+/// Memory-mapped dataset loader for large datasets.
+///
+/// This allows iterating over vectors without loading the entire dataset into memory.
+pub struct DatasetReader {
+    meta: DatasetMeta,
+    reader: SourceReader,
+    current_index: u64,
+    /// Byte offset of the next unread record.
+    offset: u64,
+    empty_policy: EmptyVectorPolicy,
+}
+
+/// What `DatasetReader` does with an empty record (`pos_len = neg_len = 0`).
+///
+/// Empty vectors are legal in the format, but their cosine is undefined (NaN or 0
+/// depending on the substrate), so a bench over them reports clean timings for
+/// meaningless work.
+#[derive(Debug, Clone, Default)]
+pub enum EmptyVectorPolicy {
+    /// Return the empty vector as-is.
+    #[default]
+    Allow,
+    /// Return this vector in its place.
+    Substitute(SparseVec),
+    /// Fail with the record index.
+    Reject,
+}
+
+/// Empty and degenerate vectors found by [`scan_vectors`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VectorScan {
+    pub scanned: u64,
+    /// Records with no non-zero index.
+    pub empty: u64,
+    /// Records with an index `>= dimension` or an index that is both +1 and -1.
+    pub degenerate: u64,
+    /// Records identical to an earlier record (see [`crate::dedupe`]).
+    pub duplicates: u64,
+    pub first_empty: Option<u64>,
+    pub first_degenerate: Option<u64>,
+}
+
+/// Whether `v` is unusable as a `dimension`-dimensional ternary vector (see [`VectorScan`]).
+pub fn is_degenerate(v: &SparseVec, dimension: usize) -> bool {
+    let out_of_range = v.pos.iter().chain(&v.neg).any(|&i| i >= dimension);
+    // Both lists are sorted by construction, but don't rely on it for handcrafted files.
+    let pos: std::collections::HashSet<usize> = v.pos.iter().copied().collect();
+    out_of_range || v.neg.iter().any(|i| pos.contains(i))
+}
+
+/// Decode up to `limit` records from the reader's current position and count empty,
+/// degenerate and duplicate vectors. The reader's empty-vector policy should be `Allow`.
+pub fn scan_vectors(reader: &mut DatasetReader, limit: u64) -> io::Result<VectorScan> {
+    let dimension = reader.meta.dimension as usize;
+    let mut scan = VectorScan::default();
+    let mut seen = std::collections::HashSet::new();
+    while scan.scanned < limit {
+        let index = reader.current_index;
+        let Some(v) = reader.next_vector()? else {
+            break;
+        };
+        scan.scanned += 1;
+        // By hash alone: a collision over 64 bits only miscounts a report line.
+        if !seen.insert(crate::dedupe::vector_hash(&v)) {
+            scan.duplicates += 1;
+        }
+        if v.pos.is_empty() && v.neg.is_empty() {
+            scan.empty += 1;
+            scan.first_empty.get_or_insert(index);
+        } else if is_degenerate(&v, dimension) {
+            scan.degenerate += 1;
+            scan.first_degenerate.get_or_insert(index);
+        }
+    }
+    Ok(scan)
+}
+
+/// Deterministic stand-in for empty records under `EmptyVectorPolicy::Substitute`.
+pub fn fallback_vector(dimension: usize) -> SparseVec {
+    generate_indexed(0, 0, dimension.max(2), (dimension / 100).max(1))
+}
+
+/// A [`DatasetReader::sequential_hint`] outcome as measurements record it: whether the
+/// hint was given, or `"unsupported"` on a platform without one, so that `false` only
+/// ever means a hint that could have been given was not.
+pub fn sequential_hint_extra(given: bool) -> serde_json::Value {
+    if fadvise::SUPPORTED {
+        serde_json::Value::Bool(given)
+    } else {
+        serde_json::Value::from("unsupported")
+    }
+}
+
+impl DatasetReader {
+    /// Open a dataset file for streaming reads.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::open_with_capacity(path, DEFAULT_READ_BUFFER)
+    }
+
+    /// [`open`](Self::open) with a `capacity`-byte read buffer instead of
+    /// [`DEFAULT_READ_BUFFER`]. What is read is the same for any capacity.
+    pub fn open_with_capacity<P: AsRef<Path>>(path: P, capacity: usize) -> io::Result<Self> {
+        let file = File::open(&path)?;
+        let mut reader = Self::from_source_reader(SourceReader::File(BufReader::with_capacity(
+            capacity.max(1),
+            file,
+        )))?;
+        reader.meta.extended = load_sidecar(path.as_ref());
+        Ok(reader)
+    }
+
+    /// Read a dataset from a non-seekable stream (stdin, a pipe). `reset` is unsupported.
+    pub fn from_stream<R: Read + Send + 'static>(stream: R) -> io::Result<Self> {
+        Self::from_stream_with_capacity(stream, DEFAULT_READ_BUFFER)
+    }
+
+    /// [`from_stream`](Self::from_stream) with a `capacity`-byte read buffer.
+    pub fn from_stream_with_capacity<R: Read + Send + 'static>(
+        stream: R,
+        capacity: usize,
+    ) -> io::Result<Self> {
+        let boxed: Box<dyn Read + Send> = Box::new(stream);
+        Self::from_source_reader(SourceReader::Stream(BufReader::with_capacity(
+            capacity.max(1),
+            boxed,
+        )))
+    }
+
+    /// The read buffer's capacity in bytes (`None` for in-memory sources, which have none).
+    pub fn buffer_capacity(&self) -> Option<usize> {
+        match &self.reader {
+            SourceReader::File(r) => Some(r.capacity()),
+            SourceReader::Stream(r) => Some(r.capacity()),
+            SourceReader::Memory(_) => None,
+        }
+    }
+
+    /// Advise the OS that the file will be read sequentially, so it can read ahead more
+    /// aggressively (`posix_fadvise(POSIX_FADV_SEQUENTIAL)`). Advisory only: returns
+    /// whether the hint was given, which is never off Linux or for non-file sources.
+    pub fn sequential_hint(&self) -> bool {
+        match &self.reader {
+            SourceReader::File(r) => fadvise::sequential(r.get_ref()),
+            _ => false,
+        }
+    }
+
+    fn from_source_reader(mut reader: SourceReader) -> io::Result<Self> {
+        let meta = read_header(&mut reader)?;
+        Ok(Self {
+            meta,
+            reader,
+            current_index: 0,
+            offset: HEADER_SIZE as u64,
+            empty_policy: EmptyVectorPolicy::Allow,
+        })
+    }
+
+    /// Set what decoding an empty record does (default: `Allow`).
+    pub fn set_empty_policy(&mut self, policy: EmptyVectorPolicy) {
+        self.empty_policy = policy;
+    }
+
+    /// Whether `reset` can rewind this reader.
+    pub fn is_seekable(&self) -> bool {
+        !matches!(self.reader, SourceReader::Stream(_))
+    }
+
+    /// Open a dataset and check that the body matches the header before returning.
+    ///
+    /// Scans every record's length prefixes (skipping the index payloads) and fails with
+    /// a descriptive error if the file holds fewer records than `count` claims or has
+    /// bytes left over after the last record. The returned reader is positioned at the
+    /// first record.
+    pub fn open_validated<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::open_validated_with_capacity(path, DEFAULT_READ_BUFFER)
+    }
+
+    /// [`open_validated`](Self::open_validated) with a `capacity`-byte read buffer.
+    pub fn open_validated_with_capacity<P: AsRef<Path>>(
+        path: P,
+        capacity: usize,
+    ) -> io::Result<Self> {
+        let file_len = std::fs::metadata(&path)?.len();
+        let mut reader = Self::open_with_capacity(&path, capacity)?;
+        let count = reader.meta.count;
+        let offset = reader.counted_len(file_len)?;
+        if offset != file_len {
+            return Err(BenchError::format(
+                offset,
+                format!(
+                    "{} trailing bytes after last record (record {} ends at byte offset {}, file is {} bytes)",
+                    file_len - offset,
+                    count,
+                    offset,
+                    file_len
+                ),
+            )
+            .into());
+        }
+
+        reader.reset()?;
+        Ok(reader)
+    }
+
+    /// Walk the `count` records the header claims by their length prefixes, from the
+    /// first, and return the byte offset where the last one ends. Fails if a file of
+    /// `file_len` bytes cannot hold them; bytes after them are left for the caller.
+    fn counted_len(&mut self, file_len: u64) -> io::Result<u64> {
+        let count = self.meta.count;
+        let mut offset = HEADER_SIZE as u64;
+        let mut buf4 = [0u8; 4];
+        let label_len = self.label_len();
+        for index in 0..count {
+            let record_offset = offset;
+            if offset + label_len > file_len {
+                return Err(short_file_error(count, index, record_offset));
+            }
+            self.reader.skip(label_len)?;
+            offset += label_len;
+            for _ in 0..2 {
+                if offset + 4 > file_len {
+                    return Err(short_file_error(count, index, record_offset));
+                }
+                self.reader.read_exact(&mut buf4)?;
+                let len = u32::from_le_bytes(buf4);
+                check_index_len(len, self.meta.dimension).map_err(|e| {
+                    error::in_record(
+                        e,
+                        record_offset,
+                        &format!("record {index} at byte offset {record_offset}"),
+                    )
+                })?;
+                let len = u64::from(len);
+                offset += 4;
+                if offset + len * 4 > file_len {
+                    return Err(short_file_error(count, index, record_offset));
+                }
+                self.reader.skip(len * 4)?;
+                offset += len * 4;
+            }
+        }
+        Ok(offset)
+    }
+
+    /// Get dataset metadata.
+    pub fn meta(&self) -> &DatasetMeta {
+        &self.meta
+    }
+
+    /// Bytes of label in front of each record (0 or 4).
+    fn label_len(&self) -> u64 {
+        if self.meta.labeled {
+            4
+        } else {
+            0
+        }
+    }
+
+    /// Read the next vector from the dataset.
+    ///
+    /// Errors name the failing record index and its byte offset.
+    pub fn next_vector(&mut self) -> io::Result<Option<SparseVec>> {
+        Ok(self.next_labeled_vector()?.map(|(vec, _)| vec))
+    }
+
+    /// [`Self::next_vector`] with the record's label (`None` throughout an unlabeled
+    /// dataset).
+    pub fn next_labeled_vector(&mut self) -> io::Result<Option<(SparseVec, Option<u32>)>> {
+        if self.current_index >= self.meta.count {
+            return Ok(None);
+        }
+
+        let record_offset = self.offset;
+        let (label, vec) = self.read_record().map_err(|e| {
+            error::in_record(
+                e,
+                record_offset,
+                &format!(
+                    "record {} of {} at byte offset {}",
+                    self.current_index, self.meta.count, record_offset
+                ),
+            )
+        })?;
+
+        let vec = if vec.pos.is_empty() && vec.neg.is_empty() {
+            match &self.empty_policy {
+                EmptyVectorPolicy::Allow => vec,
+                EmptyVectorPolicy::Substitute(fallback) => fallback.clone(),
+                EmptyVectorPolicy::Reject => {
+                    return Err(BenchError::format(
+                        record_offset,
+                        format!(
+                            "record {} of {} at byte offset {} is an empty vector",
+                            self.current_index, self.meta.count, record_offset
+                        ),
+                    )
+                    .into())
+                }
+            }
+        } else {
+            vec
+        };
+
+        self.current_index += 1;
+        Ok(Some((vec, label)))
+    }
+
+    fn read_record(&mut self) -> io::Result<(Option<u32>, SparseVec)> {
+        let label = read_label(&mut self.reader, self.meta.labeled)?;
+        let pos = read_indices(&mut self.reader, self.meta.dimension)?;
+        let neg = read_indices(&mut self.reader, self.meta.dimension)?;
+        self.offset += self.label_len() + (4 + pos.len() * 4 + 4 + neg.len() * 4) as u64;
+        Ok((label, SparseVec { pos, neg }))
+    }
+
+    /// Read multiple vectors at once for batch processing.
+    pub fn read_batch(&mut self, batch_size: usize) -> io::Result<Vec<SparseVec>> {
+        let remaining = (self.meta.count - self.current_index) as usize;
+        let to_read = batch_size.min(remaining);
+        let mut batch = Vec::with_capacity(to_read);
+
+        for _ in 0..to_read {
+            if let Some(vec) = self.next_vector()? {
+                batch.push(vec);
+            }
+        }
+
+        Ok(batch)
+    }
+
+    /// [`Self::read_batch`], converted to a substrate in parallel (see [`convert_batch`]).
+    pub fn read_batch_converted<T: FromSparse>(&mut self, batch_size: usize) -> io::Result<Vec<T>> {
+        let batch = self.read_batch(batch_size)?;
+        Ok(convert_batch(&batch, self.meta.dimension as usize))
+    }
+
+    /// Reset reader to the beginning of the dataset.
+    ///
+    /// Fails with `ErrorKind::Unsupported` on non-seekable sources.
+    pub fn reset(&mut self) -> io::Result<()> {
+        use std::io::Seek;
+        match &mut self.reader {
+            SourceReader::File(r) => {
+                r.seek(std::io::SeekFrom::Start(HEADER_SIZE as u64))?;
+            }
+            SourceReader::Memory(c) => c.set_position(HEADER_SIZE as u64),
+            SourceReader::Stream(_) => {
+                return Err(BenchError::unavailable(
+                    "cannot reset a non-seekable dataset source (stdin/pipe); buffer it first",
+                )
+                .into());
+            }
+        }
+        self.current_index = 0;
+        self.offset = HEADER_SIZE as u64;
+        Ok(())
+    }
+
+    /// Position the reader so the next vector read is record `index`.
+    ///
+    /// Records in between are skipped by their length prefixes without decoding. Going
+    /// backwards rewinds first, so that needs a seekable source.
+    pub fn seek_record(&mut self, index: u64) -> io::Result<()> {
+        if index < self.current_index {
+            self.reset()?;
+        }
+        let index = index.min(self.meta.count);
+        let mut buf4 = [0u8; 4];
+        let label_len = self.label_len();
+        while self.current_index < index {
+            let record_offset = self.offset;
+            self.reader.skip(label_len)?;
+            self.offset += label_len;
+            for _ in 0..2 {
+                self.reader.read_exact(&mut buf4)?;
+                let len = u32::from_le_bytes(buf4);
+                check_index_len(len, self.meta.dimension).map_err(|e| {
+                    error::in_record(
+                        e,
+                        record_offset,
+                        &format!(
+                            "record {} at byte offset {record_offset}",
+                            self.current_index
+                        ),
+                    )
+                })?;
+                self.reader.skip(u64::from(len) * 4)?;
+                self.offset += 4 + u64::from(len) * 4;
+            }
+            self.current_index += 1;
+        }
+        Ok(())
+    }
+}
+
+/// A substrate a `SparseVec` converts into.
+pub trait FromSparse: Sized + Send {
+    fn from_sparse_vec(v: &SparseVec, dim: usize) -> Self;
+}
+
+impl FromSparse for PackedTritVec {
+    fn from_sparse_vec(v: &SparseVec, dim: usize) -> Self {
+        PackedTritVec::from_sparsevec(v, dim)
+    }
+}
+
+impl FromSparse for BitslicedTritVec {
+    fn from_sparse_vec(v: &SparseVec, dim: usize) -> Self {
+        BitslicedTritVec::from_sparse(v, dim)
+    }
+}
+
+impl FromSparse for BlockSparseTritVec {
+    fn from_sparse_vec(v: &SparseVec, dim: usize) -> Self {
+        BlockSparseTritVec::from_sparse(v, dim)
+    }
+}
+
+/// Convert `batch` to substrate `T` on the rayon pool (sized by `--threads`), keeping
+/// order. Conversion is pure, so this only changes how fast the batch is ready.
+pub fn convert_batch<T: FromSparse>(batch: &[SparseVec], dim: usize) -> Vec<T> {
+    batch
+        .par_iter()
+        .map(|v| T::from_sparse_vec(v, dim))
+        .collect()
+}
+
+/// Single-threaded [`convert_batch`], the baseline it is measured against.
+pub fn convert_batch_serial<T: FromSparse>(batch: &[SparseVec], dim: usize) -> Vec<T> {
+    batch.iter().map(|v| T::from_sparse_vec(v, dim)).collect()
+}
+
+/// Read up to `max_vectors` records from `reader` into an in-memory dataset.
+///
+/// The buffered copy keeps the original dimension and seed, with `count` set to the
+/// number of records actually read. This is how single-pass sources (stdin) get the
+/// repeated passes the dataset benches need.
+pub fn buffer_prefix(reader: &mut DatasetReader, max_vectors: u64) -> io::Result<DatasetSource> {
+    let n = max_vectors.min(reader.meta.count.saturating_sub(reader.current_index));
+    let mut body = Vec::new();
+    for _ in 0..n {
+        let (v, label) = reader.next_labeled_vector()?.ok_or_else(|| {
+            BenchError::truncated(
+                reader.offset,
+                format!(
+                    "dataset ended at record {} of {}",
+                    reader.current_index, reader.meta.count
+                ),
+            )
+        })?;
+        write_record(&mut body, label, &v)?;
+    }
+
+    let mut bytes = Vec::with_capacity(HEADER_SIZE + body.len());
+    write_header(
+        &mut bytes,
+        n,
+        reader.meta.dimension as usize,
+        reader.meta.seed,
+        reader.meta.labeled,
+    )?;
+    bytes.extend_from_slice(&body);
+    Ok(DatasetSource::Memory(bytes.into()))
+}
+
+fn short_file_error(claimed: u64, found: u64, offset: u64) -> io::Error {
+    BenchError::truncated(
+        offset,
+        format!(
+            "header claims {claimed} vectors but file contains {found} (record {found} truncated at byte offset {offset})"
+        ),
+    )
+    .into()
+}
+
+impl Iterator for DatasetReader {
+    type Item = io::Result<SparseVec>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_vector() {
+            Ok(Some(vec)) => Some(Ok(vec)),
+            Ok(None) => None,
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+impl DatasetReader {
+    /// The remaining records as consecutive `(a, b)` pairs (see [`Pairs`]).
+    pub fn pairs(&mut self) -> Pairs<&mut Self> {
+        let first = self.current_index;
+        Pairs(Tuples::new(self, first))
+    }
+
+    /// The remaining records as consecutive `(a, b, c)` triples (see [`Triples`]).
+    pub fn triples(&mut self) -> Triples<&mut Self> {
+        let first = self.current_index;
+        Triples(Tuples::new(self, first))
+    }
+
+    /// Pairs from group `first_group` on as `cursors` take them, or this reader's own
+    /// consecutive pairs from there without (see [`GroupLayout`]).
+    ///
+    /// Either way the readers are positioned here, before the first pair is taken, so a
+    /// loop timed around the pairs only reads forward (see [`LayoutCursors`]).
+    pub fn layout_pairs<'a>(
+        &'a mut self,
+        cursors: Option<&'a mut LayoutCursors>,
+        first_group: u64,
+    ) -> io::Result<Pairs<LayoutRecords<'a>>> {
+        let (records, first) = self.layout_records(cursors, 2, first_group)?;
+        Ok(Pairs(Tuples::new(records, first)))
+    }
+
+    /// [`Self::layout_pairs`] in threes.
+    pub fn layout_triples<'a>(
+        &'a mut self,
+        cursors: Option<&'a mut LayoutCursors>,
+        first_group: u64,
+    ) -> io::Result<Triples<LayoutRecords<'a>>> {
+        let (records, first) = self.layout_records(cursors, 3, first_group)?;
+        Ok(Triples(Tuples::new(records, first)))
+    }
+
+    fn layout_records<'a>(
+        &'a mut self,
+        cursors: Option<&'a mut LayoutCursors>,
+        arity: u64,
+        first_group: u64,
+    ) -> io::Result<(LayoutRecords<'a>, u64)> {
+        match cursors {
+            None => {
+                let first = first_group * arity;
+                self.seek_record(first)?;
+                Ok((LayoutRecords::Reader(self), first))
+            }
+            Some(cursors) => {
+                cursors.position(arity, first_group)?;
+                let first = cursors.layout.record(arity, first_group, 0, cursors.count);
+                let records = LayoutRecords::Cursors {
+                    cursors,
+                    arity,
+                    next: first_group * arity,
+                };
+                Ok((records, first))
+            }
+        }
+    }
+}
+
+/// Which records dataset ops take their pairs and triples from (`vsa --pair-stride`,
+/// `--pair-offset`).
+///
+/// Group `k` of `arity` records is records `offset + arity * k + j * stride` for `j` in
+/// `0..arity`, wrapping at the record count. The default, stride 1 and offset 0, is
+/// consecutive groups from the front of the file; a larger stride pairs each vector with
+/// the one `stride` records on instead of its neighbour, and an offset moves the whole
+/// sample elsewhere in the file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GroupLayout {
+    pub stride: u64,
+    pub offset: u64,
+}
+
+impl Default for GroupLayout {
+    fn default() -> Self {
+        Self {
+            stride: 1,
+            offset: 0,
+        }
+    }
+}
+
+impl GroupLayout {
+    /// Whether this is the default layout, which reads the file straight through.
+    pub fn is_consecutive(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Index of member `j` of group `k` of `arity` records, in a dataset of `count`.
+    pub fn record(&self, arity: u64, k: u64, j: u64, count: u64) -> u64 {
+        let index = u128::from(self.offset)
+            + u128::from(arity) * u128::from(k)
+            + u128::from(j) * u128::from(self.stride);
+        (index % u128::from(count.max(1))) as u64
+    }
+}
+
+/// One reader per group member for a non-consecutive [`GroupLayout`].
+///
+/// Each member's records move forward through the file by the group size, so each
+/// cursor skips ahead (undecoded) between reads. Seeking to the first group and
+/// rewinding where a member wraps are done up front, when the layout's pairs or triples
+/// are taken: every member has a spare reader parked on the record it wraps to, so up to
+/// `count / arity` groups read forward only. Needs a seekable source.
+pub struct LayoutCursors {
+    layout: GroupLayout,
+    count: u64,
+    cursors: Vec<DatasetReader>,
+    /// Per member, the reader it continues on once it wraps to the front.
+    wrapped: Vec<DatasetReader>,
+}
+
+impl LayoutCursors {
+    /// Cursors for groups of up to three records of `source`.
+    pub fn open(source: &DatasetSource, capacity: usize, layout: GroupLayout) -> io::Result<Self> {
+        if !source.is_seekable() {
+            return Err(BenchError::unavailable(
+                "--pair-stride/--pair-offset need a seekable dataset source",
+            )
+            .into());
+        }
+        let open = || {
+            (0..3)
+                .map(|_| source.open_with_capacity(capacity))
+                .collect::<io::Result<Vec<_>>>()
+        };
+        let (cursors, wrapped) = (open()?, open()?);
+        Ok(Self {
+            layout,
+            count: cursors[0].meta.count,
+            cursors,
+            wrapped,
+        })
+    }
+
+    /// [`DatasetReader::set_empty_policy`] for every cursor.
+    pub fn set_empty_policy(&mut self, policy: EmptyVectorPolicy) {
+        for cursor in self.cursors.iter_mut().chain(&mut self.wrapped) {
+            cursor.set_empty_policy(policy.clone());
+        }
+    }
+
+    /// Park each member's cursor on its record of group `first_group`, and its spare on
+    /// the record it wraps to next.
+    fn position(&mut self, arity: u64, first_group: u64) -> io::Result<()> {
+        if self.count == 0 {
+            return Ok(());
+        }
+        for j in 0..arity {
+            let at = self.layout.record(arity, first_group, j, self.count);
+            self.cursors[j as usize].seek_record(at)?;
+            let to_wrap = (self.count - at).div_ceil(arity);
+            let wraps_to = self
+                .layout
+                .record(arity, first_group + to_wrap, j, self.count);
+            self.wrapped[j as usize].seek_record(wraps_to)?;
+        }
+        Ok(())
+    }
+}
+
+/// The records of [`DatasetReader::layout_pairs`] and [`DatasetReader::layout_triples`],
+/// in group order. Through cursors the sequence wraps and never ends.
+pub enum LayoutRecords<'a> {
+    Reader(&'a mut DatasetReader),
+    Cursors {
+        cursors: &'a mut LayoutCursors,
+        arity: u64,
+        /// Position of the next record in the sequence: `group * arity + member`.
+        next: u64,
+    },
+}
+
+impl Iterator for LayoutRecords<'_> {
+    type Item = io::Result<SparseVec>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            LayoutRecords::Reader(reader) => reader.next(),
+            LayoutRecords::Cursors {
+                cursors,
+                arity,
+                next,
+            } => {
+                let (k, j) = (*next / *arity, *next % *arity);
+                *next += 1;
+                if cursors.count == 0 {
+                    return None;
+                }
+                let index = cursors.layout.record(*arity, k, j, cursors.count);
+                let j = j as usize;
+                if index < cursors.cursors[j].current_index {
+                    std::mem::swap(&mut cursors.cursors[j], &mut cursors.wrapped[j]);
+                }
+                let cursor = &mut cursors.cursors[j];
+                Some(cursor.seek_record(index).and_then(|()| {
+                    cursor.next_vector()?.ok_or_else(|| {
+                        BenchError::truncated(None, format!("dataset ended before record {index}"))
+                            .into()
+                    })
+                }))
+            }
+        }
+    }
+}
+
+/// `records` as consecutive pairs: records 0 and 1, then 2 and 3, and so on.
+pub fn pairs<I>(records: I) -> Pairs<I> {
+    Pairs(Tuples::new(records, 0))
+}
+
+/// `records` as consecutive triples.
+pub fn triples<I>(records: I) -> Triples<I> {
+    Triples(Tuples::new(records, 0))
+}
+
+/// Record cursor shared by [`Pairs`] and [`Triples`].
+struct Tuples<I> {
+    records: I,
+    /// Index of the next record to take.
+    next_record: u64,
+}
+
+impl<I> Tuples<I> {
+    fn new(records: I, first: u64) -> Self {
+        Self {
+            records,
+            next_record: first,
+        }
+    }
+
+    fn take<T>(&mut self) -> io::Result<Option<T>>
+    where
+        I: Iterator<Item = io::Result<T>>,
+    {
+        let record = self.records.next().transpose()?;
+        self.next_record += record.is_some() as u64;
+        Ok(record)
+    }
+
+    fn ended(&self, tuple: &str) -> io::Error {
+        BenchError::truncated(
+            None,
+            format!(
+                "dataset ended at record {} partway through a {tuple}",
+                self.next_record
+            ),
+        )
+        .into()
+    }
+}
+
+/// Consecutive `(a, b)` records from an iterator of records.
+///
+/// Iteration ends cleanly when fewer than two records are left, so an odd trailing
+/// record is dropped rather than reported. A read error is passed through as it is
+/// (reader errors already name their record). Loops that need a fixed number of pairs
+/// take them with [`Pairs::next_pair`].
+pub struct Pairs<I>(Tuples<I>);
+
+impl<T, I: Iterator<Item = io::Result<T>>> Pairs<I> {
+    fn try_next(&mut self) -> io::Result<Option<(T, T)>> {
+        let Some(a) = self.0.take()? else {
+            return Ok(None);
+        };
+        let Some(b) = self.0.take()? else {
+            return Ok(None);
+        };
+        Ok(Some((a, b)))
+    }
+
+    /// The next pair, failing with the record index if the records run out first.
+    pub fn next_pair(&mut self) -> io::Result<(T, T)> {
+        self.try_next()?.ok_or_else(|| self.0.ended("pair"))
+    }
+
+    /// Index of the next record to be read (counted from the reader's position when
+    /// the adapter was made, or from 0 for [`pairs`]).
+    pub fn next_record(&self) -> u64 {
+        self.0.next_record
+    }
+}
+
+impl<T, I: Iterator<Item = io::Result<T>>> Iterator for Pairs<I> {
+    type Item = io::Result<(T, T)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.try_next().transpose()
+    }
+}
+
+/// [`Pairs`] in threes.
+pub struct Triples<I>(Tuples<I>);
+
+impl<T, I: Iterator<Item = io::Result<T>>> Triples<I> {
+    fn try_next(&mut self) -> io::Result<Option<(T, T, T)>> {
+        let Some(a) = self.0.take()? else {
+            return Ok(None);
+        };
+        let Some(b) = self.0.take()? else {
+            return Ok(None);
+        };
+        let Some(c) = self.0.take()? else {
+            return Ok(None);
+        };
+        Ok(Some((a, b, c)))
+    }
+
+    /// The next triple, failing with the record index if the records run out first.
+    pub fn next_triple(&mut self) -> io::Result<(T, T, T)> {
+        self.try_next()?.ok_or_else(|| self.0.ended("triple"))
+    }
+
+    /// See [`Pairs::next_record`].
+    pub fn next_record(&self) -> u64 {
+        self.0.next_record
+    }
+}
+
+impl<T, I: Iterator<Item = io::Result<T>>> Iterator for Triples<I> {
+    type Item = io::Result<(T, T, T)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.try_next().transpose()
+    }
+}
+
+/// Borrowed view of one dataset record.
+///
+/// Indices point straight into the mapped file when the host is little-endian and the
+/// record is 4-byte aligned (always true for a page-aligned mapping, since every field
+/// is a `u32`); otherwise they are decoded into an owned buffer.
+#[derive(Clone, Debug)]
+pub struct SparseVecRef<'a> {
+    pos: Cow<'a, [u32]>,
+    neg: Cow<'a, [u32]>,
+}
+
+impl<'a> SparseVecRef<'a> {
+    pub fn pos(&self) -> &[u32] {
+        &self.pos
+    }
+
+    pub fn neg(&self) -> &[u32] {
+        &self.neg
+    }
+
+    /// Whether both index arrays borrow the mapped bytes (no decode copy).
+    pub fn is_borrowed(&self) -> bool {
+        matches!(self.pos, Cow::Borrowed(_)) && matches!(self.neg, Cow::Borrowed(_))
+    }
+
+    /// Heap bytes the owned decode path would allocate for this record.
+    pub fn owned_alloc_bytes(&self) -> usize {
+        (self.pos.len() + self.neg.len()) * std::mem::size_of::<usize>()
+    }
+
+    /// Convert to an owned `SparseVec` (for substrate constructors and SparseVec ops).
+    pub fn to_sparsevec(&self) -> SparseVec {
+        SparseVec {
+            pos: self.pos.iter().map(|&i| i as usize).collect(),
+            neg: self.neg.iter().map(|&i| i as usize).collect(),
+        }
+    }
+
+    /// Ternary dot product. Requires sorted indices, as written by the generators.
+    pub fn dot(&self, other: &SparseVecRef<'_>) -> i64 {
+        let (same, opposite) = trit_overlap(&self.pos, &self.neg, &other.pos, &other.neg);
+        same as i64 - opposite as i64
+    }
+
+    /// Positions out of `dimension` where both records hold the same trit; see
+    /// [`trit_agreement`].
+    pub fn agreement(&self, other: &SparseVecRef<'_>, dimension: usize) -> u64 {
+        let (same, opposite) = trit_overlap(&self.pos, &self.neg, &other.pos, &other.neg);
+        agreement_count(
+            dimension,
+            self.pos.len() + self.neg.len(),
+            other.pos.len() + other.neg.len(),
+            same,
+            opposite,
+        )
+    }
+
+    /// Cosine similarity over the ternary values; 0.0 if either vector is empty.
+    pub fn cosine(&self, other: &SparseVecRef<'_>) -> f64 {
+        let na = (self.pos.len() + self.neg.len()) as f64;
+        let nb = (other.pos.len() + other.neg.len()) as f64;
+        if na == 0.0 || nb == 0.0 {
+            return 0.0;
+        }
+        self.dot(other) as f64 / (na.sqrt() * nb.sqrt())
+    }
+}
+
+/// Ternary dot product of two `SparseVec`s, computed here over the index sets (the
+/// reference for `vsa.sparsevec.dot`; embeddenator only exposes cosine). Requires sorted
+/// indices.
+pub fn sparse_dot(a: &SparseVec, b: &SparseVec) -> i64 {
+    let (same, opposite) = trit_overlap(&a.pos, &a.neg, &b.pos, &b.neg);
+    same as i64 - opposite as i64
+}
+
+/// Positions out of `dimension` where `a` and `b` hold the same trit, zeros included:
+/// `dimension` minus their Hamming distance. Requires sorted indices.
+pub fn trit_agreement(a: &SparseVec, b: &SparseVec, dimension: usize) -> u64 {
+    let (same, opposite) = trit_overlap(&a.pos, &a.neg, &b.pos, &b.neg);
+    agreement_count(
+        dimension,
+        a.pos.len() + a.neg.len(),
+        b.pos.len() + b.neg.len(),
+        same,
+        opposite,
+    )
+}
+
+/// Indices where two ternary vectors are both non-zero with the same / opposite sign.
+fn trit_overlap<T: Ord>(a_pos: &[T], a_neg: &[T], b_pos: &[T], b_neg: &[T]) -> (usize, usize) {
+    let same = sorted_intersection_len(a_pos, b_pos) + sorted_intersection_len(a_neg, b_neg);
+    let opposite = sorted_intersection_len(a_pos, b_neg) + sorted_intersection_len(a_neg, b_pos);
+    (same, opposite)
+}
+
+/// Agreeing positions from the support sizes and overlaps: zero in both (everything
+/// outside the union of supports) plus non-zero with the same sign.
+fn agreement_count(
+    dimension: usize,
+    nnz_a: usize,
+    nnz_b: usize,
+    same: usize,
+    opposite: usize,
+) -> u64 {
+    let union = nnz_a + nnz_b - same - opposite;
+    (dimension.saturating_sub(union) + same) as u64
+}
+
+fn sorted_intersection_len<T: Ord>(a: &[T], b: &[T]) -> usize {
+    let (mut i, mut j, mut n) = (0, 0, 0);
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(&b[j]) {
+            std::cmp::Ordering::Less => i += 1,
+            std::cmp::Ordering::Greater => j += 1,
+            std::cmp::Ordering::Equal => {
+                n += 1;
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    n
+}
+
+/// Reinterpret little-endian `u32`s in place, copying on misalignment or big-endian hosts.
+fn u32_slice(bytes: &[u8]) -> Cow<'_, [u32]> {
+    if cfg!(target_endian = "little") {
+        // SAFETY: every bit pattern is a valid u32, and align_to only returns a non-empty
+        // middle slice at a correctly aligned address.
+        let (head, body, tail) = unsafe { bytes.align_to::<u32>() };
+        if head.is_empty() && tail.is_empty() {
+            return Cow::Borrowed(body);
+        }
+    }
+    Cow::Owned(
+        bytes
+            .chunks_exact(4)
+            .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+            .collect(),
+    )
+}
+
+/// Memory-mapped dataset yielding zero-copy `SparseVecRef` views.
+pub struct MappedDataset {
+    meta: DatasetMeta,
+    mmap: Mmap,
+}
+
+impl MappedDataset {
+    /// Map a dataset file read-only and validate its header. On Windows the file cannot
+    /// be deleted or replaced while it is mapped.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = File::open(&path)?;
+        // SAFETY: the mapping is read-only; concurrent truncation of the file by another
+        // process is outside what the benches guard against.
+        let mmap = unsafe { Mmap::map(&file)? };
+        let mut meta = read_header(&mut &mmap[..])?;
+        meta.extended = load_sidecar(path.as_ref());
+        Ok(Self { meta, mmap })
+    }
+
+    /// Get dataset metadata.
+    pub fn meta(&self) -> &DatasetMeta {
+        &self.meta
+    }
+
+    /// Iterate over records in file order.
+    pub fn iter(&self) -> MappedRecords<'_> {
+        MappedRecords {
+            data: &self.mmap[..],
+            offset: HEADER_SIZE,
+            index: 0,
+            count: self.meta.count,
+            dimension: self.meta.dimension,
+            labeled: self.meta.labeled,
+        }
+    }
+}
+
+/// Iterator over the records of a `MappedDataset`.
+pub struct MappedRecords<'a> {
+    data: &'a [u8],
+    offset: usize,
+    index: u64,
+    count: u64,
+    dimension: u64,
+    /// Whether each record starts with a label (skipped).
+    labeled: bool,
+}
+
+impl<'a> MappedRecords<'a> {
+    fn take_u32(&mut self) -> io::Result<u32> {
+        let bytes = self.take_bytes(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn take_bytes(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        let end = self
+            .offset
+            .checked_add(len)
+            .filter(|&e| e <= self.data.len());
+        let Some(end) = end else {
+            return Err(BenchError::truncated(
+                self.offset as u64,
+                format!(
+                    "record {} truncated at byte offset {}",
+                    self.index, self.offset
+                ),
+            )
+            .into());
+        };
+        let bytes = &self.data[self.offset..end];
+        self.offset = end;
+        Ok(bytes)
+    }
+
+    fn take_indices(&mut self) -> io::Result<Cow<'a, [u32]>> {
+        let len = self.take_u32()?;
+        check_index_len(len, self.dimension).map_err(|e| {
+            let offset = self.offset as u64 - 4;
+            error::in_record(
+                e,
+                offset,
+                &format!("record {} at byte offset {offset}", self.index),
+            )
+        })?;
+        Ok(u32_slice(self.take_bytes(len as usize * 4)?))
+    }
+}
+
+impl<'a> Iterator for MappedRecords<'a> {
+    type Item = io::Result<SparseVecRef<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index >= self.count {
+            return None;
+        }
+        let label = if self.labeled {
+            self.take_bytes(4).map(drop)
+        } else {
+            Ok(())
+        };
+        let rec = label.and_then(|()| self.take_indices()).and_then(|pos| {
+            let neg = self.take_indices()?;
+            Ok(SparseVecRef { pos, neg })
+        });
+        if rec.is_err() {
+            // Stop after the first error instead of decoding garbage.
+            self.index = self.count;
+        } else {
+            self.index += 1;
+        }
+        Some(rec)
+    }
+}
+
+/// Path of the sidecar for a dataset file: `<name>.embr` -> `<name>.embr.meta.json`.
+pub fn sidecar_path<P: AsRef<Path>>(path: P) -> PathBuf {
+    let mut name = path.as_ref().as_os_str().to_owned();
+    name.push(".meta.json");
+    PathBuf::from(name)
+}
+
+/// Hash a dataset file and write its sidecar next to it.
+pub fn write_sidecar<P: AsRef<Path>>(path: P, config: &GenerateConfig) -> io::Result<ExtendedMeta> {
+    let shard = read_header(&mut BufReader::new(File::open(&path)?))?.shard;
+    let mut hasher = Sha256::new();
+    io::copy(&mut BufReader::new(File::open(&path)?), &mut hasher)?;
+    let digest = hasher.finalize();
+
+    let created_secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let ext = ExtendedMeta {
+        generate: config.clone(),
+        crate_version: env!("CARGO_PKG_VERSION").to_string(),
+        created_utc: format!("unix:{created_secs}"),
+        content_sha256: digest.iter().map(|b| format!("{b:02x}")).collect(),
+        shard,
+    };
+
+    let json = serde_json::to_vec_pretty(&ext).map_err(io::Error::other)?;
+    write_atomic(sidecar_path(&path), json)?;
+    Ok(ext)
+}
+
+/// Read a dataset's sidecar, if any.
+///
+/// A missing sidecar is `Ok(None)`; an unreadable or malformed one is an error.
+pub fn read_sidecar<P: AsRef<Path>>(path: P) -> io::Result<Option<ExtendedMeta>> {
+    let sidecar = sidecar_path(path);
+    let bytes = match std::fs::read(&sidecar) {
+        Ok(b) => b,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    serde_json::from_slice(&bytes)
+        .map(Some)
+        .map_err(|e| BenchError::format(None, format!("{}: {e}", sidecar.display())).into())
+}
+
+/// Sidecar lookup for the dataset openers: never fails, warns on a broken sidecar.
+fn load_sidecar(path: &Path) -> Option<ExtendedMeta> {
+    read_sidecar(path).unwrap_or_else(|e| {
+        eprintln!("warning: ignoring dataset sidecar: {e}");
+        None
+    })
+}
+
+/// Outcome of [`check_determinism`].
+#[derive(Debug, Clone)]
+pub struct DeterminismReport {
+    pub meta: DatasetMeta,
+    /// Sparsity inferred from the first record.
+    pub sparsity: usize,
+    /// Number of records regenerated and compared.
+    pub checked: usize,
+    /// Lowest sampled record index that differs from regeneration, if any.
+    pub first_mismatch: Option<u64>,
+}
+
+/// Check that a dataset file matches what the current generator produces.
+///
+/// Sparsity is not stored in the header, so it is taken from the first record; generated
+/// datasets have fixed-size records, which lets each sampled record be located directly.
+/// Regenerates only a sample: `sample` record indices (at least one, at most every
+/// record) chosen deterministically from the dataset seed, plus the first and last
+/// record. A file with appended records ([`FLAG_APPENDED`]) or derived from another
+/// ([`FLAG_DERIVED`]) is refused: records the seed does not regenerate would read as
+/// generator drift.
+pub fn check_determinism<P: AsRef<Path>>(path: P, sample: usize) -> io::Result<DeterminismReport> {
+    let mapped = MappedDataset::open(path)?;
+    let meta = mapped.meta.clone();
+    if meta.appended {
+        return Err(BenchError::format(
+            None,
+            "not a generated dataset: records were appended to it",
+        )
+        .into());
+    }
+    if meta.derived {
+        return Err(BenchError::format(
+            None,
+            "not a generated dataset: its records were taken from another (e.g. by dataset-dedupe)",
+        )
+        .into());
+    }
+    if meta.count == 0 {
+        return Ok(DeterminismReport {
+            meta,
+            sparsity: 0,
+            checked: 0,
+            first_mismatch: None,
+        });
+    }
+
+    let first = mapped
+        .iter()
+        .next()
+        .unwrap_or_else(|| Err(BenchError::too_small("dataset has no records").into()))?;
+    let sparsity = first.pos().len();
+    let expected = expected_file_size(meta.count, sparsity);
+    if mapped.mmap.len() as u64 != expected {
+        return Err(BenchError::format(
+            None,
+            format!(
+                "file is {} bytes but {} fixed-size records of sparsity {} need {} bytes (not a generated dataset?)",
+                mapped.mmap.len(),
+                meta.count,
+                sparsity,
+                expected
+            ),
+        )
+        .into());
+    }
+
+    // A hand-edited header can claim a dimension the first record's sparsity does not
+    // fit in, which the generator cannot reproduce.
+    GenerateConfig {
+        count: meta.count,
+        dimension: meta.dimension as usize,
+        sparsity,
+        ..Default::default()
+    }
+    .validate()
+    .map_err(|e| BenchError::format(None, format!("not a generated dataset: {e}")))?;
+
+    let count = meta.count as usize;
+    let mut indices: Vec<usize> = if sample >= count {
+        (0..count).collect()
+    } else {
+        let mut rng = ChaCha8Rng::seed_from_u64(meta.seed ^ 0x6465_7465_726d_696e);
+        rand::seq::index::sample(&mut rng, count, sample.clamp(1, count)).into_vec()
+    };
+    indices.extend([0, count - 1]);
+    indices.sort_unstable();
+    indices.dedup();
+
+    let record_size = expected_file_size(1, sparsity) as usize - HEADER_SIZE;
+    // Without a sidecar there is no record of a non-uniform distribution.
+    let distribution = meta
+        .extended
+        .as_ref()
+        .map_or(IndexDistribution::Uniform, |e| {
+            e.generate.index_distribution
+        });
+    let mut first_mismatch = None;
+    for &i in &indices {
+        let mut records = MappedRecords {
+            data: &mapped.mmap[..],
+            offset: HEADER_SIZE + i * record_size,
+            index: i as u64,
+            count: i as u64 + 1,
+            dimension: meta.dimension,
+            labeled: meta.labeled,
+        };
+        let rec = records.next().unwrap_or_else(|| {
+            Err(BenchError::truncated(None, format!("record {i} out of range")).into())
+        })?;
+        let global = meta.shard.map_or(0, |s| s.start as usize) + i;
+        let want = generate_indexed_with(
+            meta.seed,
+            global,
+            meta.dimension as usize,
+            sparsity,
+            distribution,
+            meta.generator,
+        );
+        let same = |got: &[u32], want: &[usize]| {
+            got.len() == want.len() && got.iter().zip(want).all(|(&g, &w)| g as usize == w)
+        };
+        if !same(rec.pos(), &want.pos) || !same(rec.neg(), &want.neg) {
+            first_mismatch = Some(i as u64);
+            break;
+        }
+    }
+
+    Ok(DeterminismReport {
+        meta,
+        sparsity,
+        checked: indices.len(),
+        first_mismatch,
+    })
+}
+
+/// Leading indices of each sign [`sample_records`] keeps per record.
+pub const SAMPLE_HEAD: usize = 8;
+
+/// One record read by [`sample_records`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RecordSample {
+    pub index: u64,
+    pub pos_len: usize,
+    pub neg_len: usize,
+    /// The first [`SAMPLE_HEAD`] positive indices.
+    pub pos_head: Vec<usize>,
+    pub neg_head: Vec<usize>,
+    /// Nonzero trits over the dimension.
+    pub density: f64,
+}
+
+/// A few records of a dataset, for `dataset-info --sample`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DatasetSample {
+    pub requested: usize,
+    /// `seeded` (random records, in index order) or `first`.
+    pub strategy: &'static str,
+    pub seed: Option<u64>,
+    pub records: Vec<RecordSample>,
+    /// Over the sampled records; `None` when there are none.
+    pub density: Option<DensityStats>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct DensityStats {
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub mean_nonzero: f64,
+}
+
+/// Read `n` records of `source` (all of them if it has fewer): `n` seeded random ones
+/// with `seed`, the first `n` without.
+///
+/// The format has no offset index, so seeded records are reached in one forward pass
+/// that skips the records in between by their length prefixes (see
+/// [`DatasetReader::seek_record`]); only the sampled records are decoded, and nothing is
+/// held but them. A non-seekable source works too, being read once from the front.
+pub fn sample_records(
+    source: &DatasetSource,
+    n: usize,
+    seed: Option<u64>,
+) -> io::Result<DatasetSample> {
+    let mut reader = source.open()?;
+    let count = reader.meta().count;
+    let dim = reader.meta().dimension as usize;
+    let take = (n as u64).min(count) as usize;
+    let indices: Vec<u64> = match seed {
+        Some(seed) => {
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            let mut indices: Vec<u64> = rand::seq::index::sample(&mut rng, count as usize, take)
+                .into_iter()
+                .map(|i| i as u64)
+                .collect();
+            indices.sort_unstable();
+            indices
+        }
+        None => (0..take as u64).collect(),
+    };
+
+    let mut records = Vec::with_capacity(indices.len());
+    for index in indices {
+        reader.seek_record(index)?;
+        let v = reader.next_vector()?.ok_or_else(|| {
+            io::Error::from(BenchError::truncated(
+                None,
+                format!("dataset ended before record {index}"),
+            ))
+        })?;
+        let head = |ix: &[usize]| ix.iter().take(SAMPLE_HEAD).copied().collect();
+        records.push(RecordSample {
+            index,
+            pos_len: v.pos.len(),
+            neg_len: v.neg.len(),
+            pos_head: head(&v.pos),
+            neg_head: head(&v.neg),
+            density: (v.pos.len() + v.neg.len()) as f64 / dim.max(1) as f64,
+        });
+    }
+
+    let density = (!records.is_empty()).then(|| {
+        let n = records.len() as f64;
+        DensityStats {
+            min: records
+                .iter()
+                .map(|r| r.density)
+                .fold(f64::INFINITY, f64::min),
+            max: records.iter().map(|r| r.density).fold(0.0, f64::max),
+            mean: records.iter().map(|r| r.density).sum::<f64>() / n,
+            mean_nonzero: records
+                .iter()
+                .map(|r| (r.pos_len + r.neg_len) as f64)
+                .sum::<f64>()
+                / n,
+        }
+    });
+    Ok(DatasetSample {
+        requested: n,
+        strategy: if seed.is_some() { "seeded" } else { "first" },
+        seed,
+        records,
+        density,
+    })
+}
+
+/// Format vector count as human-readable suffix (10k, 100k, 1m, etc.)
+pub fn format_count(count: u64) -> String {
+    match count {
+        n if n >= 1_000_000 && n % 1_000_000 == 0 => format!("{}m", n / 1_000_000),
+        n if n >= 1_000 && n % 1_000 == 0 => format!("{}k", n / 1_000),
+        n => n.to_string(),
+    }
+}
+
+/// Compute expected file size for a dataset of `count` vectors with `sparsity` indices
+/// per sign, saturating at `u64::MAX` for headers no file could satisfy.
+///
+/// Datasets whose vectors differ in size need [`expected_file_size_of`].
+pub fn expected_file_size(count: u64, sparsity: usize) -> u64 {
+    // Header: 68 bytes
+    // Per vector: 4 (pos_len) + sparsity*4 (pos) + 4 (neg_len) + sparsity*4 (neg)
+    let per_vector = (sparsity as u64).saturating_mul(8).saturating_add(8);
+    count
+        .saturating_mul(per_vector)
+        .saturating_add(HEADER_SIZE as u64)
+}
+
+/// Expected file size for a dataset whose vectors have the given numbers of non-zero
+/// indices (positive plus negative), one item per vector.
+pub fn expected_file_size_of<I: IntoIterator<Item = usize>>(nonzeros: I) -> u64 {
+    nonzeros.into_iter().fold(HEADER_SIZE as u64, |size, n| {
+        size.saturating_add((n as u64).saturating_mul(4).saturating_add(8))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_generate_deterministic() {
+        let config = GenerateConfig {
+            count: 100,
+            seed: 42,
+            ..Default::default()
+        };
+
+        let vecs1 = generate_dataset(&config).unwrap();
+        let vecs2 = generate_dataset(&config).unwrap();
+
+        assert_eq!(vecs1.len(), vecs2.len());
+        for (v1, v2) in vecs1.iter().zip(vecs2.iter()) {
+            assert_eq!(v1.pos, v2.pos);
+            assert_eq!(v1.neg, v2.neg);
+        }
+    }
+
+    #[test]
+    fn test_write_and_read() {
+        let config = GenerateConfig {
+            count: 50,
+            seed: 123,
+            ..Default::default()
+        };
+
+        let vectors = generate_dataset(&config).unwrap();
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.embr");
+
+        write_dataset(&path, &vectors, &config).unwrap();
+        let (meta, loaded) = load_dataset(&path).unwrap();
+
+        assert_eq!(meta.count, 50);
+        assert_eq!(meta.seed, 123);
+        assert_eq!(loaded.len(), vectors.len());
+
+        for (orig, loaded) in vectors.iter().zip(loaded.iter()) {
+            assert_eq!(orig.pos, loaded.pos);
+            assert_eq!(orig.neg, loaded.neg);
+        }
+    }
+
+    #[test]
+    fn test_streaming_reader() {
+        let config = GenerateConfig {
+            count: 25,
+            seed: 999,
+            ..Default::default()
+        };
+
+        let vectors = generate_dataset(&config).unwrap();
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("stream.embr");
+
+        write_dataset(&path, &vectors, &config).unwrap();
+
+        let mut reader = DatasetReader::open(&path).unwrap();
+        assert_eq!(reader.meta().count, 25);
+
+        let mut count = 0;
+        for result in &mut reader {
+            let vec = result.unwrap();
+            assert_eq!(vec.pos, vectors[count].pos);
+            assert_eq!(vec.neg, vectors[count].neg);
+            count += 1;
+        }
+        assert_eq!(count, 25);
+    }
+
+    #[test]
+    fn test_seek_record() {
+        let config = GenerateConfig {
+            count: 20,
+            seed: 41,
+            ..Default::default()
+        };
+        let vectors = generate_dataset(&config).unwrap();
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("seek.embr");
+        write_dataset(&path, &vectors, &config).unwrap();
+
+        let bytes = std::fs::read(&path).unwrap();
+        for source in [
+            DatasetSource::File(path),
+            DatasetSource::Memory(bytes.into()),
+        ] {
+            let mut reader = source.open().unwrap();
+            for index in [7, 12, 3, 0, 19] {
+                reader.seek_record(index).unwrap();
+                let v = reader.next_vector().unwrap().unwrap();
+                assert_eq!(v.pos, vectors[index as usize].pos, "record {index}");
+            }
+            reader.seek_record(25).unwrap();
+            assert!(reader.next_vector().unwrap().is_none());
+        }
+    }
+
+    #[test]
+    fn test_sample_records() {
+        let config = GenerateConfig {
+            count: 20,
+            seed: 12,
+            ..Default::default()
+        };
+        let vectors = generate_dataset(&config).unwrap();
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("sample.embr");
+        write_dataset(&path, &vectors, &config).unwrap();
+        let source = DatasetSource::File(path);
+
+        let first = sample_records(&source, 3, None).unwrap();
+        assert_eq!(first.strategy, "first");
+        let indices = |s: &DatasetSample| s.records.iter().map(|r| r.index).collect::<Vec<_>>();
+        assert_eq!(indices(&first), [0, 1, 2]);
+
+        let seeded = sample_records(&source, 5, Some(7)).unwrap();
+        assert_eq!(seeded, sample_records(&source, 5, Some(7)).unwrap());
+        let picked = indices(&seeded);
+        assert_eq!(picked.len(), 5);
+        assert!(picked.windows(2).all(|w| w[0] < w[1]), "{picked:?}");
+        assert_ne!(
+            picked,
+            indices(&sample_records(&source, 5, Some(8)).unwrap())
+        );
+        for r in &seeded.records {
+            let v = &vectors[r.index as usize];
+            assert_eq!((r.pos_len, r.neg_len), (v.pos.len(), v.neg.len()));
+            assert_eq!(r.pos_head, v.pos[..SAMPLE_HEAD.min(v.pos.len())]);
+        }
+        let density = seeded.density.unwrap();
+        assert!(density.min <= density.mean && density.mean <= density.max);
+
+        // More than there are: every record, once.
+        let all = sample_records(&source, 50, Some(7)).unwrap();
+        assert_eq!((all.requested, all.records.len()), (50, 20));
+        assert_eq!(indices(&all), (0..20).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_pairs_and_triples() {
+        let ok = |n: u32| (0..n).map(Ok::<u32, io::Error>);
+        // Exact multiples, then a trailing record that cannot complete a tuple.
+        let p: Vec<_> = pairs(ok(4)).map(Result::unwrap).collect();
+        assert_eq!(p, [(0, 1), (2, 3)]);
+        let p: Vec<_> = pairs(ok(5)).map(Result::unwrap).collect();
+        assert_eq!(p, [(0, 1), (2, 3)]);
+        let t: Vec<_> = triples(ok(6)).map(Result::unwrap).collect();
+        assert_eq!(t, [(0, 1, 2), (3, 4, 5)]);
+        let t: Vec<_> = triples(ok(8)).map(Result::unwrap).collect();
+        assert_eq!(t, [(0, 1, 2), (3, 4, 5)]);
+
+        let mut short = triples(ok(4));
+        assert_eq!(short.next_triple().unwrap(), (0, 1, 2));
+        let err = short.next_triple().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(
+            err.to_string(),
+            "dataset ended at record 4 partway through a triple"
+        );
+
+        // A read error mid-tuple comes through as it is.
+        let records = [Ok(0), Err(io::Error::other("bad record 1")), Ok(2), Ok(3)];
+        let mut p = pairs(records.into_iter());
+        assert_eq!(p.next().unwrap().unwrap_err().to_string(), "bad record 1");
+        assert_eq!(p.next().unwrap().unwrap(), (2, 3));
+
+        // From a reader, record indices count from where it stands.
+        let config = GenerateConfig {
+            count: 7,
+            seed: 3,
+            ..Default::default()
+        };
+        let vectors = generate_dataset(&config).unwrap();
+        let mut bytes = Vec::new();
+        write_header(&mut bytes, 7, config.dimension, config.seed, false).unwrap();
+        for v in &vectors {
+            write_record(&mut bytes, None, v).unwrap();
+        }
+        let mut reader = DatasetSource::Memory(bytes.clone().into()).open().unwrap();
+        reader.seek_record(2).unwrap();
+        let mut p = reader.pairs();
+        let (a, b) = p.next_pair().unwrap();
+        assert_eq!(
+            (a.pos, b.pos),
+            (vectors[2].pos.clone(), vectors[3].pos.clone())
+        );
+        p.next_pair().unwrap();
+        assert_eq!(p.next_record(), 6);
+        let err = p.next_pair().unwrap_err();
+        assert!(err.to_string().contains("at record 7"), "{err}");
+
+        // Truncated inside record 6: the reader's error names it.
+        bytes.truncate(bytes.len() - 2);
+        let mut reader = DatasetSource::Memory(bytes.into()).open().unwrap();
+        reader.seek_record(1).unwrap();
+        let mut t = reader.triples();
+        t.next_triple().unwrap();
+        let err = t.next_triple().unwrap_err();
+        assert!(err.to_string().contains("record 6 of 7"), "{err}");
+    }
+
+    #[test]
+    fn test_layout_pairs_stride_and_offset() {
+        let config = GenerateConfig {
+            count: 7,
+            seed: 9,
+            ..Default::default()
+        };
+        let vectors = generate_dataset(&config).unwrap();
+        let mut bytes = Vec::new();
+        write_header(&mut bytes, 7, config.dimension, config.seed, false).unwrap();
+        for v in &vectors {
+            write_record(&mut bytes, None, v).unwrap();
+        }
+        let source = DatasetSource::Memory(bytes.into());
+        let index = |v: SparseVec| vectors.iter().position(|w| w.pos == v.pos).unwrap();
+        let mut reader = source.open().unwrap();
+
+        // Stride 3 from record 2: (2 + 2k, 2 + 2k + 3), wrapping at 7.
+        let layout = GroupLayout {
+            stride: 3,
+            offset: 2,
+        };
+        let mut cursors = LayoutCursors::open(&source, 64, layout).unwrap();
+        let mut p = reader.layout_pairs(Some(&mut cursors), 0).unwrap();
+        let consumed: Vec<_> = (0..8)
+            .map(|_| {
+                let (a, b) = p.next_pair().unwrap();
+                (index(a), index(b))
+            })
+            .collect();
+        assert_eq!(
+            consumed,
+            [
+                (2, 5),
+                (4, 0),
+                (6, 2),
+                (1, 4),
+                (3, 6),
+                (5, 1),
+                (0, 3),
+                (2, 5)
+            ]
+        );
+        // Positioning happens when the pairs are taken: each member on its first record,
+        // its spare on the record it wraps to.
+        reader.layout_pairs(Some(&mut cursors), 3).unwrap();
+        let at = |readers: &[DatasetReader]| -> Vec<u64> {
+            readers[..2].iter().map(|r| r.current_index).collect()
+        };
+        assert_eq!(at(&cursors.cursors), [1, 4]);
+        assert_eq!(at(&cursors.wrapped), [0, 1]);
+
+        // Later groups, and triples: (2 + 3k, 2 + 3k + 3, 2 + 3k + 6).
+        let mut p = reader.layout_pairs(Some(&mut cursors), 3).unwrap();
+        let (a, b) = p.next_pair().unwrap();
+        assert_eq!((index(a), index(b)), (1, 4));
+        let mut t = reader.layout_triples(Some(&mut cursors), 0).unwrap();
+        let consumed: Vec<_> = (0..3)
+            .map(|_| {
+                let (a, b, c) = t.next_triple().unwrap();
+                (index(a), index(b), index(c))
+            })
+            .collect();
+        assert_eq!(consumed, [(2, 5, 1), (5, 1, 4), (1, 4, 0)]);
+
+        // Without cursors: consecutive groups from the reader, ending with the file.
+        assert!(GroupLayout::default().is_consecutive());
+        let mut p = reader.layout_pairs(None, 1).unwrap();
+        let (a, b) = p.next_pair().unwrap();
+        assert_eq!((index(a), index(b)), (2, 3));
+        p.next_pair().unwrap();
+        assert!(p
+            .next_pair()
+            .unwrap_err()
+            .to_string()
+            .contains("at record 7"));
+
+        let err = LayoutCursors::open(&DatasetSource::Stdin, 64, layout)
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+    }
+
+    #[test]
+    fn test_read_buffer_capacity_parity() {
+        let config = GenerateConfig {
+            count: 30,
+            seed: 5,
+            ..Default::default()
+        };
+        let vectors = generate_dataset(&config).unwrap();
+        let labels: Vec<u32> = (0..30).map(|i| i * 7).collect();
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("capacity.embr");
+        write_labeled_dataset(&path, &vectors, &labels, &config).unwrap();
+
+        // One byte forces a refill per read; 8 MiB holds the whole file.
+        for capacity in [1, 4096, DEFAULT_READ_BUFFER, 8 << 20] {
+            let mut reader = DatasetReader::open_validated_with_capacity(&path, capacity).unwrap();
+            assert_eq!(reader.buffer_capacity(), Some(capacity));
+            reader.sequential_hint();
+            for (i, v) in vectors.iter().enumerate() {
+                let (got, label) = reader.next_labeled_vector().unwrap().unwrap();
+                assert_eq!(
+                    (&got.pos, &got.neg, label),
+                    (&v.pos, &v.neg, Some(labels[i])),
+                    "capacity {capacity}, record {i}"
+                );
+            }
+            assert!(reader.next_vector().unwrap().is_none());
+
+            reader.reset().unwrap();
+            assert_eq!(reader.buffer_capacity(), Some(capacity));
+            assert_eq!(reader.next_vector().unwrap().unwrap().pos, vectors[0].pos);
+            reader.seek_record(17).unwrap();
+            assert_eq!(reader.next_vector().unwrap().unwrap().neg, vectors[17].neg);
+        }
+        assert_eq!(
+            DatasetReader::open_with_capacity(&path, 0)
+                .unwrap()
+                .buffer_capacity(),
+            Some(1)
+        );
+        let bytes = std::fs::read(&path).unwrap();
+        let memory = DatasetSource::Memory(bytes.into()).open().unwrap();
+        assert_eq!(memory.buffer_capacity(), None);
+        assert!(!memory.sequential_hint());
+        assert_eq!(
+            sequential_hint_extra(false),
+            if cfg!(target_os = "linux") {
+                serde_json::json!(false)
+            } else {
+                serde_json::json!("unsupported")
+            }
+        );
+    }
+
+    #[test]
+    fn test_batch_reading() {
+        let config = GenerateConfig {
+            count: 100,
+            seed: 456,
+            ..Default::default()
+        };
+
+        let vectors = generate_dataset(&config).unwrap();
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("batch.embr");
+
+        write_dataset(&path, &vectors, &config).unwrap();
+
+        let mut reader = DatasetReader::open(&path).unwrap();
+        let batch = reader.read_batch(30).unwrap();
+        assert_eq!(batch.len(), 30);
+
+        for (i, vec) in batch.iter().enumerate() {
+            assert_eq!(vec.pos, vectors[i].pos);
+        }
+    }
+
+    #[test]
+    fn test_streaming_writer_matches_in_memory() {
+        let config = GenerateConfig {
+            count: 250,
+            seed: 2026,
+            ..Default::default()
+        };
+
+        let vectors = generate_dataset(&config).unwrap();
+        let dir = tempdir().unwrap();
+        let path_mem = dir.path().join("mem.embr");
+        let path_stream = dir.path().join("stream.embr");
+
+        write_dataset(&path_mem, &vectors, &config).unwrap();
+        write_dataset_streaming(&path_stream, &config, 64).unwrap();
+
+        let (_m1, v1) = load_dataset(&path_mem).unwrap();
+        let (_m2, v2) = load_dataset(&path_stream).unwrap();
+
+        assert_eq!(v1.len(), v2.len());
+        for (a, b) in v1.iter().zip(v2.iter()) {
+            assert_eq!(a.pos, b.pos);
+            assert_eq!(a.neg, b.neg);
+        }
+    }
+
+    #[test]
+    fn test_shards_concatenate_to_monolithic() {
+        let config = GenerateConfig {
+            count: 101,
+            seed: 9,
+            ..Default::default()
+        };
+        let dir = tempdir().unwrap();
+        let mono = dir.path().join("mono.embr");
+        write_dataset_streaming(&mono, &config, 16).unwrap();
+
+        let mut body = Vec::new();
+        for index in 0..2 {
+            let shard = ShardDescriptor::new(index, 2, config.count).unwrap();
+            let path = dir.path().join(shard.file_name("mono.embr"));
+            write_dataset_shard(&path, &config, shard, 16).unwrap();
+            write_sidecar(&path, &config).unwrap();
+
+            let meta = read_dataset_meta(&path).unwrap();
+            assert_eq!(meta.count, shard.range().end - shard.range().start);
+            assert_eq!(meta.seed, config.seed);
+            assert_eq!(meta.shard, Some(shard));
+            assert_eq!(meta.extended.unwrap().shard, Some(shard));
+            assert_eq!(check_determinism(&path, 1000).unwrap().first_mismatch, None);
+
+            body.extend_from_slice(&std::fs::read(&path).unwrap()[HEADER_SIZE..]);
+        }
+        assert_eq!(ShardDescriptor::new(1, 2, 101).unwrap().range(), 50..101);
+        assert_eq!(body, std::fs::read(&mono).unwrap()[HEADER_SIZE..]);
+
+        assert!(ShardDescriptor::new(2, 2, 101).is_err());
+        let wrong_total = ShardDescriptor::new(0, 2, 50).unwrap();
+        assert!(write_dataset_shard(dir.path().join("x.embr"), &config, wrong_total, 16).is_err());
+    }
+
+    #[test]
+    fn test_extension_encoding() {
+        let shard = ShardDescriptor::new(2, 4, 1000).unwrap();
+        // A shard (2 + 24 bytes) and an empty unknown entry (2) fill the region.
+        let exts = vec![
+            HeaderExtension::Shard(shard),
+            HeaderExtension::Unknown {
+                tag: 200,
+                value: Vec::new(),
+            },
+        ];
+        let region = encode_extensions(&exts).unwrap();
+        assert_eq!(region[..2], [ExtensionTag::Shard as u8, 24]);
+        assert_eq!(region[26..], [200, 0]);
+        assert_eq!(decode_extensions(&region).unwrap(), exts);
+
+        let unknown = HeaderExtension::Unknown {
+            tag: 200,
+            value: vec![1, 2],
+        };
+        let one = encode_extensions(std::slice::from_ref(&unknown)).unwrap();
+        assert_eq!(one[..4], [200, 2, 1, 2]);
+        assert_eq!(decode_extensions(&one).unwrap(), [unknown]);
+        assert_eq!(
+            decode_extensions(&[0u8; EXTENSION_BYTES]).unwrap(),
+            Vec::new()
+        );
+        assert_eq!(encode_extensions(&[]).unwrap(), [0u8; EXTENSION_BYTES]);
+
+        // A value filling the region exactly fits; one byte more does not.
+        let fill = |len: usize| HeaderExtension::Unknown {
+            tag: 9,
+            value: vec![7; len],
+        };
+        let full = encode_extensions(&[fill(EXTENSION_BYTES - 2)]).unwrap();
+        assert_eq!(
+            decode_extensions(&full).unwrap(),
+            [fill(EXTENSION_BYTES - 2)]
+        );
+        assert!(encode_extensions(&[fill(EXTENSION_BYTES - 1)]).is_err());
+        assert!(encode_extensions(&[HeaderExtension::Shard(shard), fill(1)]).is_err());
+        assert!(encode_extensions(&[HeaderExtension::Unknown {
+            tag: 0,
+            value: Vec::new()
+        }])
+        .is_err());
+        assert!(encode_extensions(&[fill(300)]).is_err());
+    }
+
+    #[test]
+    fn test_extension_truncation_and_length_checks() {
+        let mut region = [0u8; EXTENSION_BYTES];
+        // A tag in the last byte has no room for its length.
+        region[EXTENSION_BYTES - 1] = 9;
+        region[..2].copy_from_slice(&[9, (EXTENSION_BYTES - 3) as u8]);
+        let err = decode_extensions(&region).unwrap_err();
+        assert!(err.to_string().contains("has no length"), "{err}");
+
+        // A length running one byte past the region.
+        let mut region = [0u8; EXTENSION_BYTES];
+        region[..2].copy_from_slice(&[9, (EXTENSION_BYTES - 1) as u8]);
+        let err = decode_extensions(&region).unwrap_err();
+        assert!(err.to_string().contains("runs past"), "{err}");
+
+        // A known tag must have its exact length, even when the bytes are there.
+        let mut region = [0u8; EXTENSION_BYTES];
+        region[..2].copy_from_slice(&[ExtensionTag::Shard as u8, 20]);
+        let err = decode_extensions(&region).unwrap_err();
+        assert!(err.to_string().contains("expected 24"), "{err}");
+
+        // Everything after the end marker is ignored.
+        let mut region = [0u8; EXTENSION_BYTES];
+        region[1..3].copy_from_slice(&[5, 200]);
+        assert_eq!(decode_extensions(&region).unwrap(), Vec::new());
+    }
+
+    #[test]
+    fn test_unknown_extension_and_legacy_shard_headers() {
+        let config = GenerateConfig {
+            count: 6,
+            ..Default::default()
+        };
+        let vectors = generate_dataset(&config).unwrap();
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("ext.embr");
+        write_labeled_dataset(&path, &vectors, &[0, 1, 2, 3, 4, 5], &config).unwrap();
+        let reserved_at = HEADER_SIZE - 32;
+
+        // A newer writer's extension: reported, otherwise ignored.
+        let mut bytes = std::fs::read(&path).unwrap();
+        let future = HeaderExtension::Unknown {
+            tag: 77,
+            value: vec![0xAB; 5],
+        };
+        bytes[reserved_at..reserved_at + EXTENSION_BYTES]
+            .copy_from_slice(&encode_extensions(std::slice::from_ref(&future)).unwrap());
+        std::fs::write(&path, &bytes).unwrap();
+        let mut reader = DatasetReader::open_validated(&path).unwrap();
+        assert_eq!(reader.meta().extensions, [future]);
+        assert!(reader.meta().labeled && reader.meta().shard.is_none());
+        let (v, label) = reader.next_labeled_vector().unwrap().unwrap();
+        assert_eq!((v.pos, label), (vectors[0].pos.clone(), Some(0)));
+        assert_eq!(
+            reader.meta().extensions[0].describe(),
+            "unknown tag 77 (5 bytes, ignored)"
+        );
+
+        // A shard header from before extensions: `SHRD` and the fields at offset 4.
+        let shard = ShardDescriptor::new(1, 3, 6).unwrap();
+        bytes[reserved_at..reserved_at + 4].copy_from_slice(SHARD_MAGIC);
+        bytes[reserved_at + 4..reserved_at + EXTENSION_BYTES].copy_from_slice(&shard.encode());
+        std::fs::write(&path, &bytes).unwrap();
+        let meta = read_dataset_meta(&path).unwrap();
+        assert_eq!(meta.shard, Some(shard));
+        assert_eq!(meta.extensions, [HeaderExtension::Shard(shard)]);
+
+        // New shard files use the extension list.
+        let shard_path = dir.path().join("s.embr");
+        write_dataset_shard(&shard_path, &config, shard, 4).unwrap();
+        let bytes = std::fs::read(&shard_path).unwrap();
+        assert_eq!(bytes[reserved_at], ExtensionTag::Shard as u8);
+        assert_eq!(read_dataset_meta(&shard_path).unwrap().shard, Some(shard));
+
+        // A corrupt extension region fails the open.
+        let mut bytes = bytes;
+        bytes[reserved_at + 1] = 40;
+        std::fs::write(&shard_path, &bytes).unwrap();
+        assert!(DatasetReader::open(&shard_path).is_err());
+    }
+
+    #[test]
+    fn test_labeled_roundtrip() {
+        let config = GenerateConfig {
+            count: 40,
+            seed: 3,
+            ..Default::default()
+        };
+        let vectors = generate_dataset(&config).unwrap();
+        let labels: Vec<u32> = (0..40).map(|i| i % 4 * 1000 + i).collect();
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("labeled.embr");
+        write_labeled_dataset(&path, &vectors, &labels, &config).unwrap();
+        assert!(write_labeled_dataset(&path, &vectors, &labels[1..], &config).is_err());
+
+        let meta = read_dataset_meta(&path).unwrap();
+        assert_eq!((meta.version, meta.labeled), (FORMAT_VERSION_FLAGS, true));
+        let size = expected_file_size(40, config.sparsity) + 40 * 4;
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), size);
+
+        let mut reader = DatasetReader::open_validated(&path).unwrap();
+        for (i, v) in vectors.iter().enumerate() {
+            let (got, label) = reader.next_labeled_vector().unwrap().unwrap();
+            assert_eq!(
+                (&got.pos, &got.neg, label),
+                (&v.pos, &v.neg, Some(labels[i]))
+            );
+        }
+        assert!(reader.next_labeled_vector().unwrap().is_none());
+
+        // The unlabeled readers skip the labels.
+        reader.seek_record(25).unwrap();
+        assert_eq!(reader.next_vector().unwrap().unwrap().pos, vectors[25].pos);
+        let (_, loaded) = load_dataset(&path).unwrap();
+        assert_eq!(loaded[39].neg, vectors[39].neg);
+        let mapped = MappedDataset::open(&path).unwrap();
+        let refs: Vec<_> = mapped.iter().collect::<io::Result<_>>().unwrap();
+        assert_eq!(refs[7].to_sparsevec().pos, vectors[7].pos);
+
+        // A buffered prefix keeps them.
+        reader.seek_record(10).unwrap();
+        let mut buffered = buffer_prefix(&mut reader, 5).unwrap().open().unwrap();
+        assert!(buffered.meta().labeled);
+        let (_, label) = buffered.next_labeled_vector().unwrap().unwrap();
+        assert_eq!(label, Some(labels[10]));
+    }
+
+    #[test]
+    fn test_write_dataset_as_each_format() {
+        let config = GenerateConfig {
+            count: 5,
+            seed: 2,
+            ..Default::default()
+        };
+        let vectors = generate_dataset(&config).unwrap();
+        let dir = tempdir().unwrap();
+        for format in DatasetFormat::ALL {
+            let path = dir.path().join(format!("{}.embr", format.label()));
+            write_dataset_as(&path, &vectors, &config, format).unwrap();
+            let (meta, back) = load_dataset(&path).unwrap();
+            assert_eq!(meta.version, format.version());
+            assert_eq!(meta.labeled, format == DatasetFormat::V2Labeled);
+            assert!(back
+                .iter()
+                .zip(&vectors)
+                .all(|(a, b)| a.pos == b.pos && a.neg == b.neg));
+        }
+
+        let v2 = GenerateConfig {
+            generator: GeneratorVersion::V2,
+            ..config
+        };
+        assert!(!DatasetFormat::V1.supports(&v2));
+        let path = dir.path().join("v2gen.embr");
+        assert!(write_dataset_as(&path, &vectors, &v2, DatasetFormat::V1).is_err());
+        write_dataset_as(&path, &vectors, &v2, DatasetFormat::V2).unwrap();
+        assert_eq!(
+            read_dataset_meta(&path).unwrap().generator,
+            GeneratorVersion::V2
+        );
+    }
+
+    #[test]
+    fn test_labeled_and_unlabeled_side_by_side() {
+        let config = GenerateConfig {
+            count: 6,
+            seed: 11,
+            ..Default::default()
+        };
+        let vectors = generate_dataset(&config).unwrap();
+        let dir = tempdir().unwrap();
+        let plain = dir.path().join("plain.embr");
+        let labeled = dir.path().join("labeled.embr");
+        write_dataset(&plain, &vectors, &config).unwrap();
+        write_labeled_dataset(&labeled, &vectors, &[9; 6], &config).unwrap();
+
+        // Unlabeled files are still version 1, and read back without labels.
+        let mut reader = DatasetReader::open(&plain).unwrap();
+        assert_eq!(
+            (reader.meta().version, reader.meta().labeled),
+            (FORMAT_VERSION, false)
+        );
+        let (v, label) = reader.next_labeled_vector().unwrap().unwrap();
+        assert_eq!((v.pos, label), (vectors[0].pos.clone(), None));
+
+        let plain_vs: Vec<SparseVec> = DatasetReader::open(&plain)
+            .unwrap()
+            .collect::<io::Result<_>>()
+            .unwrap();
+        let labeled_vs: Vec<SparseVec> = DatasetReader::open(&labeled)
+            .unwrap()
+            .collect::<io::Result<_>>()
+            .unwrap();
+        assert_eq!(plain_vs.len(), labeled_vs.len());
+        for (a, b) in plain_vs.iter().zip(&labeled_vs) {
+            assert_eq!((&a.pos, &a.neg), (&b.pos, &b.neg));
+        }
+
+        // Flags this reader doesn't know are refused.
+        let mut bytes = std::fs::read(&labeled).unwrap();
+        bytes[FLAGS_OFFSET as usize] |= 16;
+        std::fs::write(&labeled, &bytes).unwrap();
+        let err = read_dataset_meta(&labeled).unwrap_err();
+        assert!(err.to_string().contains("flags"), "{err}");
+    }
+
+    /// Five generated vectors with record 2 replaced by an empty one.
+    fn write_with_empty_record(path: &Path) {
+        let config = GenerateConfig {
+            count: 5,
+            ..Default::default()
+        };
+        let mut vectors = generate_dataset(&config).unwrap();
+        vectors[2] = SparseVec {
+            pos: Vec::new(),
+            neg: Vec::new(),
+        };
+        write_dataset(path, &vectors, &config).unwrap();
+    }
+
+    #[test]
+    fn test_empty_vector_scan_and_policies() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("empty.embr");
+        write_with_empty_record(&path);
+
+        let mut reader = DatasetReader::open(&path).unwrap();
+        let scan = scan_vectors(&mut reader, u64::MAX).unwrap();
+        assert_eq!(scan.scanned, 5);
+        assert_eq!(scan.empty, 1);
+        assert_eq!(scan.first_empty, Some(2));
+        assert_eq!(scan.degenerate, 0);
+
+        // Lenient: the empty record is replaced, everything else is untouched.
+        let fallback = fallback_vector(DIM);
+        reader.reset().unwrap();
+        reader.set_empty_policy(EmptyVectorPolicy::Substitute(fallback.clone()));
+        let vs: Vec<SparseVec> = reader.by_ref().map(|v| v.unwrap()).collect();
+        assert_eq!(vs.len(), 5);
+        assert_eq!(vs[2].pos, fallback.pos);
+        assert!(!vs[1].pos.is_empty());
+
+        // Strict: fails at the empty record, naming it.
+        reader.reset().unwrap();
+        reader.set_empty_policy(EmptyVectorPolicy::Reject);
+        let err = reader.by_ref().find_map(|v| v.err()).unwrap();
+        assert!(err.to_string().contains("record 2 of 5"), "{err}");
+
+        let bad = SparseVec {
+            pos: vec![1, 5],
+            neg: vec![5],
+        };
+        assert!(is_degenerate(&bad, DIM));
+        assert!(is_degenerate(
+            &SparseVec {
+                pos: vec![DIM],
+                neg: Vec::new()
+            },
+            DIM
+        ));
+        assert!(!is_degenerate(&fallback, DIM));
+    }
+
+    fn write_fixture(name: &str, count: u64) -> (tempfile::TempDir, std::path::PathBuf) {
+        let config = GenerateConfig {
+            count,
+            seed: 31,
+            ..Default::default()
+        };
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(name);
+        write_dataset_streaming(&path, &config, 16).unwrap();
+        (dir, path)
+    }
+
+    #[test]
+    fn test_generate_config_validation() {
+        let cfg = |count, dimension, sparsity| GenerateConfig {
+            count,
+            dimension,
+            seed: 0,
+            sparsity,
+            index_distribution: IndexDistribution::Uniform,
+            generator: GeneratorVersion::V1,
+        };
+        let rejected = [
+            (cfg(0, 100, 10), "count"),
+            (cfg(10, 1, 0), "dimension"),
+            (cfg(10, 100, 0), "sparsity must be at least 1"),
+            (cfg(10, 100, 51), "distinct indices"),
+        ];
+        for (c, needle) in rejected {
+            let err = c.validate().unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+            assert!(err.to_string().contains(needle), "{c:?}: {err}");
+            assert!(generate_dataset(&c).is_err());
+        }
 
-    // Skip reserved bytes
-    let mut reserved = [0u8; 32];
-    reader.read_exact(&mut reserved)?;
+        // Boundary: every index is used, half positive and half negative.
+        let full = cfg(3, 100, 50);
+        full.validate().unwrap();
+        let vecs = generate_dataset(&full).unwrap();
+        assert_eq!(vecs[0].pos.len() + vecs[0].neg.len(), 100);
 
-    let meta = DatasetMeta {
-        count,
-        dimension,
-        seed,
-    };
+        let dir = tempdir().unwrap();
+        let err =
+            write_dataset_streaming(dir.path().join("bad.embr"), &cfg(10, 100, 51), 4).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(!dir.path().join("bad.embr").exists());
+    }
 
-    // Read vectors
-    let mut vectors = Vec::with_capacity(count as usize);
-    for _ in 0..count {
-        // Read pos indices
-        reader.read_exact(&mut buf4)?;
-        let pos_len = u32::from_le_bytes(buf4) as usize;
-        let mut pos = Vec::with_capacity(pos_len);
-        for _ in 0..pos_len {
-            reader.read_exact(&mut buf4)?;
-            pos.push(u32::from_le_bytes(buf4) as usize);
+    #[test]
+    fn test_index_distribution_parse() {
+        assert_eq!(
+            IndexDistribution::parse("uniform").unwrap(),
+            IndexDistribution::Uniform
+        );
+        let zipf = IndexDistribution::parse("zipf:1.1").unwrap();
+        assert_eq!(zipf, IndexDistribution::Zipf { s: 1.1 });
+        assert_eq!(zipf.label(), "zipf1.1");
+        for bad in ["zipf", "zipf:", "zipf:-1", "zipf:nan", "pareto:2"] {
+            assert!(IndexDistribution::parse(bad).is_err(), "{bad}");
         }
+    }
 
-        // Read neg indices
-        reader.read_exact(&mut buf4)?;
-        let neg_len = u32::from_le_bytes(buf4) as usize;
-        let mut neg = Vec::with_capacity(neg_len);
-        for _ in 0..neg_len {
-            reader.read_exact(&mut buf4)?;
-            neg.push(u32::from_le_bytes(buf4) as usize);
+    #[test]
+    fn test_zipf_concentrates_on_low_indices() {
+        let config = GenerateConfig {
+            count: 200,
+            dimension: 1000,
+            sparsity: 10,
+            index_distribution: IndexDistribution::Zipf { s: 1.2 },
+            ..Default::default()
+        };
+        let vecs = generate_dataset(&config).unwrap();
+
+        let mut top_decile = 0usize;
+        let mut total = 0usize;
+        for v in &vecs {
+            assert_eq!((v.pos.len(), v.neg.len()), (10, 10));
+            assert!(!v.pos.iter().any(|i| v.neg.contains(i)));
+            top_decile += v.pos.iter().chain(&v.neg).filter(|&&i| i < 100).count();
+            total += v.pos.len() + v.neg.len();
         }
+        // Uniform would put 10% of hits in the lowest-id decile.
+        let share = top_decile as f64 / total as f64;
+        assert!(share > 0.4, "top-decile share {share}");
 
-        vectors.push(SparseVec { pos, neg });
+        // Still determined by (seed, index), and recorded in the sidecar.
+        assert_eq!(generate_dataset(&config).unwrap()[7].pos, vecs[7].pos);
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("zipf.embr");
+        write_dataset_streaming(&path, &config, 64).unwrap();
+        write_sidecar(&path, &config).unwrap();
+        let meta = read_dataset_meta(&path).unwrap();
+        assert_eq!(
+            meta.extended.unwrap().generate.index_distribution,
+            config.index_distribution
+        );
+        assert_eq!(check_determinism(&path, 16).unwrap().first_mismatch, None);
     }
 
-    Ok((meta, vectors))
-}
+    #[test]
+    fn test_stream_source_buffers_prefix() {
+        let (_dir, path) = write_fixture("stream.embr", 9);
+        let expected = load_dataset(&path).unwrap().1;
 
-/// Memory-mapped dataset loader for large datasets.
-///
-/// This allows iterating over vectors without loading the entire dataset into memory.
-pub struct DatasetReader {
-    meta: DatasetMeta,
-    reader: BufReader<File>,
-    current_index: u64,
-}
+        let mut stream = DatasetReader::from_stream(File::open(&path).unwrap()).unwrap();
+        assert!(!stream.is_seekable());
+        assert_eq!(stream.meta().count, 9);
+        let err = stream.reset().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
 
-impl DatasetReader {
-    /// Open a dataset file for streaming reads.
-    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
-        let file = File::open(&path)?;
-        let mut reader = BufReader::with_capacity(64 * 1024, file);
-
-        // Read and validate header
-        let mut magic = [0u8; 8];
-        reader.read_exact(&mut magic)?;
-        if &magic != MAGIC {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                "Invalid magic bytes",
-            ));
+        let mem = buffer_prefix(&mut stream, 5).unwrap();
+        assert!(mem.is_seekable());
+        let mut reader = mem.open().unwrap();
+        assert_eq!(reader.meta().count, 5);
+        for _pass in 0..2 {
+            let got: Vec<SparseVec> = reader.by_ref().map(Result::unwrap).collect();
+            assert_eq!(got.len(), 5);
+            for (a, b) in got.iter().zip(&expected) {
+                assert_eq!(a.pos, b.pos);
+                assert_eq!(a.neg, b.neg);
+            }
+            reader.reset().unwrap();
         }
+    }
 
-        let mut buf4 = [0u8; 4];
-        let mut buf8 = [0u8; 8];
+    #[test]
+    fn test_generator_v1_output_unchanged() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("v1.embr");
+        let config = GenerateConfig {
+            count: 50,
+            dimension: 1000,
+            seed: 42,
+            sparsity: 10,
+            ..Default::default()
+        };
+        assert_eq!(config.generator, GeneratorVersion::V1);
+        write_dataset_streaming(&path, &config, 16).unwrap();
+        // Pinned from the generator as it was before v2 existed.
+        let digest = write_sidecar(&path, &config).unwrap().content_sha256;
+        assert_eq!(
+            digest,
+            "76ec8d75d26747fa90b8c29ca633ac788c79830736de4245c5c6259c50fb2675"
+        );
+        let meta = read_dataset_meta(&path).unwrap();
+        assert_eq!(
+            (meta.version, meta.generator),
+            (FORMAT_VERSION, GeneratorVersion::V1)
+        );
+    }
 
-        reader.read_exact(&mut buf4)?;
-        let version = u32::from_le_bytes(buf4);
-        if version != FORMAT_VERSION {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                format!("Unsupported format version: {}", version),
-            ));
+    #[test]
+    fn test_generator_v2_deterministic_and_disjoint() {
+        let config = GenerateConfig {
+            count: 200,
+            dimension: 100_000,
+            seed: 9,
+            sparsity: 1000,
+            generator: GeneratorVersion::V2,
+            ..Default::default()
+        };
+        let a = generate_dataset(&config).unwrap();
+        let b = generate_dataset(&config).unwrap();
+        for (x, y) in a.iter().zip(&b) {
+            assert_eq!((&x.pos, &x.neg), (&y.pos, &y.neg));
+            assert_eq!((x.pos.len(), x.neg.len()), (1000, 1000));
+            assert!(x.pos.windows(2).all(|w| w[0] < w[1]) && x.neg.windows(2).all(|w| w[0] < w[1]));
+            assert!(!is_degenerate(x, config.dimension));
         }
+        let v1 = generate_indexed(9, 0, 100_000, 1000);
+        assert_ne!(v1.pos, a[0].pos);
 
-        reader.read_exact(&mut buf8)?;
-        let count = u64::from_le_bytes(buf8);
-
-        reader.read_exact(&mut buf8)?;
-        let dimension = u64::from_le_bytes(buf8);
+        // Every index and sign equally likely, as with v1.
+        let small = GenerateConfig {
+            count: 4000,
+            dimension: 20,
+            sparsity: 3,
+            ..config.clone()
+        };
+        let (mut pos, mut neg) = ([0u32; 20], [0u32; 20]);
+        for v in generate_dataset(&small).unwrap() {
+            v.pos.iter().for_each(|&i| pos[i] += 1);
+            v.neg.iter().for_each(|&i| neg[i] += 1);
+        }
+        // Expected 4000 * 3 / 20 = 600 per index and sign.
+        assert!(
+            pos.iter().chain(&neg).all(|&c| (500..700).contains(&c)),
+            "{pos:?} {neg:?}"
+        );
 
-        reader.read_exact(&mut buf8)?;
-        let seed = u64::from_le_bytes(buf8);
+        // The header records v2, so regeneration uses it.
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("v2.embr");
+        write_dataset_streaming(
+            &path,
+            &GenerateConfig {
+                count: 20,
+                ..config
+            },
+            8,
+        )
+        .unwrap();
+        assert_eq!(
+            read_dataset_meta(&path).unwrap().generator,
+            GeneratorVersion::V2
+        );
+        assert_eq!(check_determinism(&path, 5).unwrap().first_mismatch, None);
+    }
 
-        let mut reserved = [0u8; 32];
-        reader.read_exact(&mut reserved)?;
+    #[test]
+    fn test_sidecar_roundtrip() {
+        let (_dir, path) = write_fixture("side.embr", 6);
+        assert!(DatasetReader::open(&path)
+            .unwrap()
+            .meta()
+            .extended
+            .is_none());
 
-        let meta = DatasetMeta {
-            count,
-            dimension,
-            seed,
+        let config = GenerateConfig {
+            count: 6,
+            seed: 31,
+            ..Default::default()
         };
+        let written = write_sidecar(&path, &config).unwrap();
+        assert!(sidecar_path(&path)
+            .to_string_lossy()
+            .ends_with("side.embr.meta.json"));
+        assert_eq!(written.content_sha256.len(), 64);
 
-        Ok(Self {
-            meta,
-            reader,
-            current_index: 0,
-        })
+        let from_reader = DatasetReader::open(&path).unwrap().meta().extended.clone();
+        assert_eq!(from_reader.as_ref(), Some(&written));
+        assert_eq!(
+            read_dataset_meta(&path).unwrap().extended,
+            Some(written.clone())
+        );
+        assert_eq!(
+            MappedDataset::open(&path).unwrap().meta().extended,
+            Some(written.clone())
+        );
+        assert_eq!(
+            load_dataset(&path).unwrap().0.extended.unwrap().generate,
+            config
+        );
     }
 
-    /// Get dataset metadata.
-    pub fn meta(&self) -> &DatasetMeta {
-        &self.meta
+    #[test]
+    fn test_broken_sidecar_is_not_fatal() {
+        let (_dir, path) = write_fixture("broken.embr", 3);
+        std::fs::write(sidecar_path(&path), b"{not json").unwrap();
+        assert!(read_sidecar(&path).is_err());
+        let reader = DatasetReader::open(&path).unwrap();
+        assert!(reader.meta().extended.is_none());
     }
 
-    /// Read the next vector from the dataset.
-    pub fn next_vector(&mut self) -> io::Result<Option<SparseVec>> {
-        if self.current_index >= self.meta.count {
-            return Ok(None);
-        }
+    #[test]
+    fn test_determinism_accepts_fresh_file() {
+        let (_dir, path) = write_fixture("fresh.embr", 40);
+        let report = check_determinism(&path, 8).unwrap();
+        assert_eq!(report.sparsity, DIM / 100);
+        assert!(report.checked >= 8);
+        assert_eq!(report.first_mismatch, None);
 
-        let mut buf4 = [0u8; 4];
+        // A zero sample still checks the first and last record, even of a single one.
+        assert!((2..=3).contains(&check_determinism(&path, 0).unwrap().checked));
+        let (_dir, single) = write_fixture("single.embr", 1);
+        assert_eq!(check_determinism(&single, 0).unwrap().checked, 1);
+    }
 
-        // Read pos indices
-        self.reader.read_exact(&mut buf4)?;
-        let pos_len = u32::from_le_bytes(buf4) as usize;
-        let mut pos = Vec::with_capacity(pos_len);
-        for _ in 0..pos_len {
-            self.reader.read_exact(&mut buf4)?;
-            pos.push(u32::from_le_bytes(buf4) as usize);
-        }
+    #[test]
+    fn test_determinism_detects_corrupt_record() {
+        let (_dir, path) = write_fixture("corrupt.embr", 20);
+        let record_size = (expected_file_size(1, DIM / 100) - HEADER_SIZE as u64) as usize;
+        let mut bytes = std::fs::read(&path).unwrap();
+        // Bump the first positive index of record 7.
+        let at = HEADER_SIZE + 7 * record_size + 4;
+        bytes[at] ^= 1;
+        std::fs::write(&path, bytes).unwrap();
 
-        // Read neg indices
-        self.reader.read_exact(&mut buf4)?;
-        let neg_len = u32::from_le_bytes(buf4) as usize;
-        let mut neg = Vec::with_capacity(neg_len);
-        for _ in 0..neg_len {
-            self.reader.read_exact(&mut buf4)?;
-            neg.push(u32::from_le_bytes(buf4) as usize);
-        }
+        let report = check_determinism(&path, 1000).unwrap();
+        assert_eq!(report.checked, 20);
+        assert_eq!(report.first_mismatch, Some(7));
+    }
 
-        self.current_index += 1;
-        Ok(Some(SparseVec { pos, neg }))
+    #[test]
+    fn test_validated_accepts_clean_file() {
+        let (_dir, path) = write_fixture("clean.embr", 12);
+        let reader = DatasetReader::open_validated(&path).unwrap();
+        assert_eq!(reader.count(), 12);
     }
 
-    /// Read multiple vectors at once for batch processing.
-    pub fn read_batch(&mut self, batch_size: usize) -> io::Result<Vec<SparseVec>> {
-        let remaining = (self.meta.count - self.current_index) as usize;
-        let to_read = batch_size.min(remaining);
-        let mut batch = Vec::with_capacity(to_read);
+    #[test]
+    fn test_validated_detects_short_file() {
+        let (_dir, path) = write_fixture("short.embr", 10);
+        let len = std::fs::metadata(&path).unwrap().len();
+        let per_vector = expected_file_size(1, DIM / 100) - HEADER_SIZE as u64;
+        // Drop the last record entirely plus half of the one before.
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_len(len - per_vector - per_vector / 2)
+            .unwrap();
 
-        for _ in 0..to_read {
-            if let Some(vec) = self.next_vector()? {
-                batch.push(vec);
-            }
-        }
+        let err = DatasetReader::open_validated(&path).err().unwrap();
+        let msg = err.to_string();
+        assert!(
+            msg.contains("header claims 10 vectors but file contains 8"),
+            "{msg}"
+        );
+        let offset = HEADER_SIZE as u64 + 8 * per_vector;
+        assert!(msg.contains(&format!("byte offset {offset}")), "{msg}");
+        let truncated_at = |err: &io::Error| match BenchError::of(err) {
+            Some(BenchError::DatasetTruncated { offset, .. }) => *offset,
+            other => panic!("expected DatasetTruncated, got {other:?}"),
+        };
+        assert_eq!(truncated_at(&err), Some(offset));
 
-        Ok(batch)
+        // The unvalidated reader still fails, but now says where.
+        let mut reader = DatasetReader::open(&path).unwrap();
+        let err = reader.by_ref().find_map(|r| r.err()).unwrap();
+        assert!(err.to_string().starts_with("record 8 of 10"), "{err}");
+        assert_eq!(truncated_at(&err), Some(offset));
     }
 
-    /// Reset reader to the beginning of the dataset.
-    pub fn reset(&mut self) -> io::Result<()> {
-        use std::io::Seek;
-        self.reader.seek(std::io::SeekFrom::Start(HEADER_SIZE as u64))?;
-        self.current_index = 0;
-        Ok(())
-    }
-}
+    #[test]
+    fn test_validated_detects_trailing_garbage() {
+        let (_dir, path) = write_fixture("long.embr", 5);
+        let len = std::fs::metadata(&path).unwrap().len();
+        let mut f = File::options().append(true).open(&path).unwrap();
+        f.write_all(&[0xAB; 7]).unwrap();
+        drop(f);
 
-impl Iterator for DatasetReader {
-    type Item = io::Result<SparseVec>;
+        let err = DatasetReader::open_validated(&path).err().unwrap();
+        let msg = err.to_string();
+        assert!(msg.contains("7 trailing bytes after last record"), "{msg}");
+        assert!(msg.contains(&format!("byte offset {len}")), "{msg}");
+        assert!(matches!(
+            BenchError::of(&err),
+            Some(BenchError::DatasetFormat { offset: Some(o), .. }) if *o == len
+        ));
 
-    fn next(&mut self) -> Option<Self::Item> {
-        match self.next_vector() {
-            Ok(Some(vec)) => Some(Ok(vec)),
-            Ok(None) => None,
-            Err(e) => Some(Err(e)),
-        }
+        // Plain open keeps trusting the header.
+        assert_eq!(DatasetReader::open(&path).unwrap().count(), 5);
     }
-}
 
-/// Compute expected file size for a dataset.
-pub fn expected_file_size(count: u64, sparsity: usize) -> u64 {
-    // Header: 68 bytes
-    // Per vector: 4 (pos_len) + sparsity*4 (pos) + 4 (neg_len) + sparsity*4 (neg)
-    let per_vector = 4 + (sparsity * 4) + 4 + (sparsity * 4);
-    HEADER_SIZE as u64 + count * per_vector as u64
-}
+    #[test]
+    fn test_header_errors_by_variant() {
+        let mut header = Vec::new();
+        write_header(&mut header, 1, 4, 0, false).unwrap();
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use tempfile::tempdir;
+        let open = |bytes: Vec<u8>| DatasetSource::Memory(bytes.into()).open().err().unwrap();
+        let mut bad_magic = header.clone();
+        bad_magic[0] ^= 0xFF;
+        let err = open(bad_magic);
+        assert!(err.to_string().starts_with("Invalid magic bytes"), "{err}");
+        assert!(matches!(
+            BenchError::of(&err),
+            Some(BenchError::DatasetFormat {
+                offset: Some(0),
+                ..
+            })
+        ));
+
+        let mut bad_version = header.clone();
+        bad_version[8] = 99;
+        assert!(matches!(
+            BenchError::of(&open(bad_version)),
+            Some(BenchError::DatasetFormat {
+                offset: Some(8),
+                ..
+            })
+        ));
+
+        let err = open(header[..HEADER_SIZE - 1].to_vec());
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        assert!(matches!(
+            BenchError::of(&err),
+            Some(BenchError::DatasetTruncated {
+                offset: Some(0),
+                ..
+            })
+        ));
+    }
 
     #[test]
-    fn test_generate_deterministic() {
+    fn test_convert_batch_matches_serial_in_order() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("convert.embr");
         let config = GenerateConfig {
-            count: 100,
-            seed: 42,
+            count: 257,
+            dimension: 1_000,
+            sparsity: 10,
             ..Default::default()
         };
+        write_dataset_streaming(&path, &config, 64).unwrap();
+        let dim = config.dimension;
 
-        let vecs1 = generate_dataset(&config);
-        let vecs2 = generate_dataset(&config);
+        let mut reader = DatasetReader::open(&path).unwrap();
+        let batch = reader.read_batch(300).unwrap();
+        assert_eq!(batch.len(), 257);
 
-        assert_eq!(vecs1.len(), vecs2.len());
-        for (v1, v2) in vecs1.iter().zip(vecs2.iter()) {
-            assert_eq!(v1.pos, v2.pos);
-            assert_eq!(v1.neg, v2.neg);
+        let packed: Vec<PackedTritVec> = convert_batch(&batch, dim);
+        let serial: Vec<PackedTritVec> = convert_batch_serial(&batch, dim);
+        assert_eq!(packed.len(), batch.len());
+        for ((p, s), v) in packed.iter().zip(&serial).zip(&batch) {
+            assert_eq!(p.to_sparsevec().pos, v.pos);
+            assert_eq!(p.to_sparsevec().neg, s.to_sparsevec().neg);
+        }
+
+        let bitsliced: Vec<BitslicedTritVec> = convert_batch(&batch, dim);
+        let block: Vec<BlockSparseTritVec> = convert_batch(&batch, dim);
+        for ((b, k), v) in bitsliced.iter().zip(&block).zip(&batch) {
+            assert_eq!(b.to_sparse().pos, v.pos);
+            assert_eq!(k.to_sparse().neg, v.neg);
         }
+
+        // The reader variant converts with the file's own dimension.
+        reader.reset().unwrap();
+        let first: Vec<BlockSparseTritVec> = reader.read_batch_converted(10).unwrap();
+        let rest: Vec<BlockSparseTritVec> = reader.read_batch_converted(1_000).unwrap();
+        assert_eq!((first.len(), rest.len()), (10, 247));
+        assert_eq!(rest[0].to_sparse().pos, batch[10].pos);
     }
 
     #[test]
-    fn test_write_and_read() {
+    fn test_mapped_views_match_owned() {
         let config = GenerateConfig {
-            count: 50,
-            seed: 123,
+            count: 40,
+            seed: 77,
             ..Default::default()
         };
 
-        let vectors = generate_dataset(&config);
+        let vectors = generate_dataset(&config).unwrap();
         let dir = tempdir().unwrap();
-        let path = dir.path().join("test.embr");
-
+        let path = dir.path().join("mapped.embr");
         write_dataset(&path, &vectors, &config).unwrap();
-        let (meta, loaded) = load_dataset(&path).unwrap();
 
-        assert_eq!(meta.count, 50);
-        assert_eq!(meta.seed, 123);
-        assert_eq!(loaded.len(), vectors.len());
+        let mapped = MappedDataset::open(&path).unwrap();
+        assert_eq!(mapped.meta().count, 40);
 
-        for (orig, loaded) in vectors.iter().zip(loaded.iter()) {
-            assert_eq!(orig.pos, loaded.pos);
-            assert_eq!(orig.neg, loaded.neg);
+        let views: Vec<SparseVecRef<'_>> = mapped.iter().map(|r| r.unwrap()).collect();
+        assert_eq!(views.len(), vectors.len());
+        for (view, owned) in views.iter().zip(vectors.iter()) {
+            if cfg!(target_endian = "little") {
+                assert!(view.is_borrowed());
+            }
+            let back = view.to_sparsevec();
+            assert_eq!(back.pos, owned.pos);
+            assert_eq!(back.neg, owned.neg);
+        }
+
+        for pair in views.windows(2) {
+            let owned_a = pair[0].to_sparsevec();
+            let owned_b = pair[1].to_sparsevec();
+            let expected = owned_a.cosine(&owned_b);
+            assert!((pair[0].cosine(&pair[1]) - expected).abs() < 1e-12);
+            assert_eq!(pair[0].dot(&pair[1]), sparse_dot(&owned_a, &owned_b));
+            assert_eq!(
+                pair[0].agreement(&pair[1], config.dimension),
+                trit_agreement(&owned_a, &owned_b, config.dimension)
+            );
         }
+        assert!((views[0].cosine(&views[0]) - 1.0).abs() < 1e-12);
     }
 
     #[test]
-    fn test_streaming_reader() {
+    fn test_dot_and_agreement_reference() {
+        // a = [+ 0 + 0 0 - 0 0], b = [- 0 + + 0 - 0 0]
+        let a = SparseVec {
+            pos: vec![0, 2],
+            neg: vec![5],
+        };
+        let b = SparseVec {
+            pos: vec![2, 3],
+            neg: vec![0, 5],
+        };
+        assert_eq!(sparse_dot(&a, &b), 1);
+        assert_eq!(trit_agreement(&a, &b, 8), 6);
+        assert_eq!(trit_agreement(&a, &a, 8), 8);
+        assert_eq!(sparse_dot(&a, &a), 3);
+        let empty = SparseVec {
+            pos: Vec::new(),
+            neg: Vec::new(),
+        };
+        assert_eq!(trit_agreement(&a, &empty, 8), 5);
+    }
+
+    #[test]
+    fn test_u32_slice_misaligned_fallback() {
+        let words: Vec<u32> = vec![0, 7, 0xdead_beef, 42];
+        let bytes: Vec<u8> = words.iter().flat_map(|w| w.to_le_bytes()).collect();
+
+        // One byte in: never 4-aligned, so this must take the copying path.
+        let mut shifted = vec![0u8; 1];
+        shifted.extend_from_slice(&bytes[4..]);
+        let view = u32_slice(&shifted[1..]);
+        assert!(matches!(view, Cow::Owned(_)));
+        assert_eq!(&*view, &words[1..]);
+    }
+
+    #[test]
+    fn test_mapped_truncated_record() {
         let config = GenerateConfig {
-            count: 25,
-            seed: 999,
+            count: 3,
+            seed: 5,
             ..Default::default()
         };
-
-        let vectors = generate_dataset(&config);
         let dir = tempdir().unwrap();
-        let path = dir.path().join("stream.embr");
+        let path = dir.path().join("short.embr");
+        write_dataset_streaming(&path, &config, 8).unwrap();
+        let len = std::fs::metadata(&path).unwrap().len();
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_len(len - 10)
+            .unwrap();
 
-        write_dataset(&path, &vectors, &config).unwrap();
+        let mapped = MappedDataset::open(&path).unwrap();
+        let results: Vec<_> = mapped.iter().collect();
+        assert_eq!(results.len(), 3);
+        assert!(results[2].is_err());
+    }
 
-        let mut reader = DatasetReader::open(&path).unwrap();
-        assert_eq!(reader.meta().count, 25);
+    /// Sorted, disjoint pos/neg index sets within `dim`: random subsets plus the cases
+    /// that stress the length prefixes (empty, every index used, one contiguous run).
+    fn arb_sparsevec(dim: usize) -> impl Strategy<Value = SparseVec> {
+        fn split(indices: impl IntoIterator<Item = usize>, signs: &[bool]) -> SparseVec {
+            let (pos, neg) = indices.into_iter().partition(|&i| signs[i]);
+            SparseVec { pos, neg }
+        }
+        let signs = || proptest::collection::vec(any::<bool>(), dim);
+        prop_oneof![
+            Just(SparseVec {
+                pos: Vec::new(),
+                neg: Vec::new()
+            }),
+            signs().prop_map(move |s| split(0..dim, &s)),
+            (0..dim, 1..=dim, signs())
+                .prop_map(move |(start, len, s)| split(start..(start + len).min(dim), &s)),
+            (
+                proptest::sample::subsequence((0..dim).collect::<Vec<_>>(), 0..=dim),
+                signs()
+            )
+                .prop_map(|(indices, s)| split(indices, &s)),
+        ]
+    }
 
-        let mut count = 0;
-        for result in &mut reader {
-            let vec = result.unwrap();
-            assert_eq!(vec.pos, vectors[count].pos);
-            assert_eq!(vec.neg, vectors[count].neg);
-            count += 1;
+    fn arb_dataset() -> impl Strategy<Value = (usize, Vec<SparseVec>)> {
+        (1usize..=96).prop_flat_map(|dim| {
+            (
+                Just(dim),
+                proptest::collection::vec(arb_sparsevec(dim), 0..12),
+            )
+        })
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn prop_roundtrip_every_read_path((dim, vectors) in arb_dataset()) {
+            let config = GenerateConfig {
+                count: vectors.len() as u64,
+                dimension: dim,
+                ..Default::default()
+            };
+            let dir = tempdir().unwrap();
+            let path = dir.path().join("prop.embr");
+            write_dataset(&path, &vectors, &config).unwrap();
+            let bytes = std::fs::read(&path).unwrap();
+            let lens: u64 = vectors.iter().map(|v| (v.pos.len() + v.neg.len()) as u64).sum();
+            prop_assert_eq!(bytes.len() as u64, HEADER_SIZE as u64 + 8 * vectors.len() as u64 + 4 * lens);
+
+            let same = |got: &[SparseVec]| {
+                got.len() == vectors.len()
+                    && got.iter().zip(&vectors).all(|(g, v)| g.pos == v.pos && g.neg == v.neg)
+            };
+            let read_all = |mut r: DatasetReader| r.by_ref().collect::<io::Result<Vec<_>>>().unwrap();
+
+            prop_assert!(same(&load_dataset(&path).unwrap().1));
+            prop_assert!(same(&read_all(DatasetReader::open_validated(&path).unwrap())));
+            prop_assert!(same(&read_all(DatasetReader::from_stream(io::Cursor::new(bytes.clone())).unwrap())));
+            let memory = DatasetSource::Memory(bytes.into());
+            prop_assert!(same(&read_all(memory.open().unwrap())));
+
+            let mapped = MappedDataset::open(&path).unwrap();
+            let views: Vec<SparseVec> = mapped.iter().map(|r| r.unwrap().to_sparsevec()).collect();
+            prop_assert!(same(&views));
+        }
+
+        #[test]
+        fn prop_arbitrary_body_never_panics(
+            count in prop_oneof![0u64..8, Just(u64::MAX)],
+            dim in prop_oneof![0u64..64, Just(u64::MAX)],
+            body in proptest::collection::vec(any::<u8>(), 0..256),
+        ) {
+            let mut bytes = Vec::new();
+            write_header(&mut bytes, count, 0, 7, false).unwrap();
+            bytes[20..28].copy_from_slice(&dim.to_le_bytes());
+            bytes.extend_from_slice(&body);
+
+            let mut reader = DatasetSource::Memory(bytes.into()).open().unwrap();
+            let mut decoded = 0u64;
+            for v in reader.by_ref() {
+                match v {
+                    Ok(v) => {
+                        prop_assert!((v.pos.len() as u64) <= dim && (v.neg.len() as u64) <= dim);
+                        decoded += 1;
+                    }
+                    Err(_) => break,
+                }
+            }
+            // Every record takes at least its two length prefixes.
+            prop_assert!(decoded <= body.len() as u64 / 8);
         }
-        assert_eq!(count, 25);
     }
 
     #[test]
-    fn test_batch_reading() {
+    fn test_length_prefix_over_dimension_rejected() {
+        let mut bytes = Vec::new();
+        write_header(&mut bytes, 1, 4, 0, false).unwrap();
+        bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+
+        let mut reader = DatasetSource::Memory(bytes.into()).open().unwrap();
+        let err = reader.next_vector().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("exceeds dimension 4"), "{err}");
+        assert!(matches!(
+            BenchError::of(&err),
+            Some(BenchError::DatasetFormat {
+                offset: Some(o),
+                ..
+            }) if *o == HEADER_SIZE as u64
+        ));
+
+        assert_eq!(expected_file_size(u64::MAX, 100), u64::MAX);
+        assert_eq!(expected_file_size(2, 1), HEADER_SIZE as u64 + 2 * 16);
+    }
+
+    #[test]
+    fn test_written_size_cross_check() {
+        let dir = tempfile::tempdir().unwrap();
+
+        // Exact match: the preallocation is fully overwritten, strict mode passes.
+        let path = dir.path().join("exact.embr");
         let config = GenerateConfig {
-            count: 100,
-            seed: 456,
+            count: 37,
+            dimension: 500,
+            sparsity: 5,
+            seed: 3,
             ..Default::default()
         };
+        write_generated(&path, &config, None, 8, SizeCheck::Strict).unwrap();
+        assert_eq!(
+            std::fs::metadata(&path).unwrap().len(),
+            expected_file_size(37, 5)
+        );
+        assert!(MappedDataset::open(&path)
+            .unwrap()
+            .iter()
+            .all(|r| r.is_ok()));
+
+        // Undersized: strict keeps the previous file; warn keeps the short one, with
+        // the unwritten preallocation truncated rather than zero-padded.
+        let short = |w: &mut BufWriter<NamedTempFile>| w.write_all(&[7u8; 60]);
+        let err = write_sized(&path, Some(100), SizeCheck::Strict, short).unwrap_err();
+        assert!(
+            matches!(BenchError::of(&err), Some(BenchError::DatasetFormat { .. })),
+            "{err:?}"
+        );
+        assert!(err.to_string().contains("wrote 60 bytes"), "{err}");
+        assert_eq!(
+            std::fs::metadata(&path).unwrap().len(),
+            expected_file_size(37, 5)
+        );
+        write_sized(&path, Some(100), SizeCheck::Warn, short).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), [7u8; 60]);
+
+        // Variable size: records of different lengths, summed per vector.
+        let vectors = vec![
+            SparseVec {
+                pos: vec![1, 2, 3],
+                neg: vec![4],
+            },
+            SparseVec {
+                pos: vec![],
+                neg: vec![],
+            },
+            SparseVec {
+                pos: vec![9],
+                neg: vec![5, 6, 7, 8],
+            },
+        ];
+        let path = dir.path().join("varied.embr");
+        write_dataset(&path, &vectors, &GenerateConfig::default()).unwrap();
+        let expected = expected_file_size_of([4, 0, 5]);
+        assert_eq!(expected, HEADER_SIZE as u64 + 3 * 8 + 9 * 4);
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), expected);
+        assert_eq!(expected_file_size_of(vec![10; 6]), expected_file_size(6, 5));
+    }
+
+    fn read_all(path: &Path) -> Vec<(SparseVec, Option<u32>)> {
+        let mut reader = DatasetReader::open(path).unwrap();
+        std::iter::from_fn(|| reader.next_labeled_vector().unwrap()).collect()
+    }
+
+    fn same(a: &[(SparseVec, Option<u32>)], b: &[(SparseVec, Option<u32>)]) -> bool {
+        a.len() == b.len()
+            && a.iter()
+                .zip(b)
+                .all(|((x, xl), (y, yl))| x.pos == y.pos && x.neg == y.neg && xl == yl)
+    }
 
-        let vectors = generate_dataset(&config);
+    #[test]
+    fn test_append_round_trip_and_refusals() {
         let dir = tempdir().unwrap();
-        let path = dir.path().join("batch.embr");
+        let config = GenerateConfig {
+            count: 10,
+            ..Default::default()
+        };
+        let path = dir.path().join("grow.embr");
+        write_dataset_streaming(&path, &config, 4).unwrap();
+        write_sidecar(&path, &config).unwrap();
+        let extra = generate_dataset(&GenerateConfig {
+            count: 5,
+            seed: 9,
+            ..config.clone()
+        })
+        .unwrap();
 
-        write_dataset(&path, &vectors, &config).unwrap();
+        let mut writer = DatasetWriter::open_append(&path).unwrap();
+        assert!(writer.removed_sidecar());
+        assert_eq!((writer.meta().count, writer.recovered_bytes()), (10, 0));
+        for v in &extra {
+            writer.append(v, None).unwrap();
+        }
+        // Refused records are not written.
+        let err = writer.append(&extra[0], Some(1)).unwrap_err();
+        assert!(
+            matches!(BenchError::of(&err), Some(BenchError::InvalidArgs(_))),
+            "{err}"
+        );
+        let degenerate = SparseVec {
+            pos: vec![3],
+            neg: vec![3],
+        };
+        assert!(writer.append(&degenerate, None).is_err());
+        assert_eq!(writer.appended(), 5);
+        assert_eq!(writer.finish().unwrap(), 15);
 
-        let mut reader = DatasetReader::open(&path).unwrap();
-        let batch = reader.read_batch(30).unwrap();
-        assert_eq!(batch.len(), 30);
+        assert!(!sidecar_path(&path).exists());
+        let reader = DatasetReader::open_validated(&path).unwrap();
+        assert_eq!(reader.meta().count, 15);
+        // The file is marked appended, so it no longer passes for generator output.
+        let meta = reader.meta();
+        assert_eq!((meta.version, meta.appended), (FORMAT_VERSION_FLAGS, true));
+        let err = check_determinism(&path, 4).unwrap_err();
+        assert!(err.to_string().contains("records were appended"), "{err}");
+        let expected: Vec<_> = generate_dataset(&config)
+            .unwrap()
+            .into_iter()
+            .chain(extra)
+            .map(|v| (v, None))
+            .collect();
+        assert!(same(&read_all(&path), &expected));
 
-        for (i, vec) in batch.iter().enumerate() {
-            assert_eq!(vec.pos, vectors[i].pos);
+        // JSONL: sorted and deduplicated, labels where the file has them, errors by line.
+        let labeled = dir.path().join("labeled.embr");
+        write_labeled_dataset(
+            &labeled,
+            &expected[..2]
+                .iter()
+                .map(|r| r.0.clone())
+                .collect::<Vec<_>>(),
+            &[4, 5],
+            &config,
+        )
+        .unwrap();
+        let mut writer = DatasetWriter::open_append(&labeled).unwrap();
+        // One writer at a time.
+        let err = DatasetWriter::open_append(&labeled).err().unwrap();
+        assert!(
+            matches!(BenchError::of(&err), Some(BenchError::Locked(_))),
+            "{err:?}"
+        );
+        assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+        let input = "{\"pos\": [9, 2, 2], \"neg\": [7], \"label\": 6}\n\n{\"pos\": [], \"neg\": [1], \"label\": 7}\n";
+        assert_eq!(
+            append_jsonl(&mut writer, input.as_bytes(), "new.jsonl").unwrap(),
+            2
+        );
+        for (line, body) in [
+            (1, "{\"pos\": [1], \"neg\": []}"),
+            (2, "\n{\"pos\": [1], \"neg\": [99999], \"label\": 0}"),
+            (1, "[1, 2]"),
+        ] {
+            let err = append_jsonl(&mut writer, body.as_bytes(), "bad.jsonl").unwrap_err();
+            assert!(
+                matches!(BenchError::of(&err), Some(BenchError::DatasetFormat { .. })),
+                "{err}"
+            );
+            assert!(
+                err.to_string().contains(&format!("bad.jsonl:{line}: ")),
+                "{err}"
+            );
         }
+        assert_eq!(writer.finish().unwrap(), 4);
+        let meta = read_dataset_meta(&labeled).unwrap();
+        assert!(meta.labeled && meta.appended);
+        let records = read_all(&labeled);
+        assert_eq!(
+            records.iter().map(|r| r.1).collect::<Vec<_>>(),
+            [Some(4), Some(5), Some(6), Some(7)]
+        );
+        assert_eq!(
+            (&records[2].0.pos, &records[2].0.neg),
+            (&vec![2, 9], &vec![7])
+        );
+
+        let shard = dir.path().join("shard.embr");
+        write_dataset_shard(&shard, &config, ShardDescriptor::new(0, 2, 10).unwrap(), 4).unwrap();
+        let err = DatasetWriter::open_append(&shard).err().unwrap();
+        assert!(err.to_string().contains("shard 0 of 2"), "{err}");
     }
 
     #[test]
-    fn test_streaming_writer_matches_in_memory() {
+    fn test_append_interrupted_before_count_patch() {
+        let dir = tempdir().unwrap();
         let config = GenerateConfig {
-            count: 250,
-            seed: 2026,
+            count: 10,
             ..Default::default()
         };
+        let path = dir.path().join("grow.embr");
+        write_dataset_streaming(&path, &config, 4).unwrap();
+        let before = std::fs::read(&path).unwrap();
+        let old: Vec<_> = generate_dataset(&config)
+            .unwrap()
+            .into_iter()
+            .map(|v| (v, None))
+            .collect();
+        let extra = generate_dataset(&GenerateConfig {
+            count: 3,
+            seed: 9,
+            ..config.clone()
+        })
+        .unwrap();
 
-        let vectors = generate_dataset(&config);
-        let dir = tempdir().unwrap();
-        let path_mem = dir.path().join("mem.embr");
-        let path_stream = dir.path().join("stream.embr");
+        // Killed after the records are synced but before the count is patched.
+        let mut writer = DatasetWriter::open_append(&path).unwrap();
+        for v in &extra {
+            writer.append(v, None).unwrap();
+        }
+        writer.sync_records().unwrap();
+        drop(writer);
+        let after = std::fs::read(&path).unwrap();
+        assert!(after.len() > before.len() && after.starts_with(&before));
 
-        write_dataset(&path_mem, &vectors, &config).unwrap();
-        write_dataset_streaming(&path_stream, &config, 64).unwrap();
+        // Readers see the old dataset; validation reports the leftovers.
+        assert_eq!(read_dataset_meta(&path).unwrap().count, 10);
+        assert!(same(&read_all(&path), &old));
+        assert_eq!(MappedDataset::open(&path).unwrap().iter().count(), 10);
+        let err = DatasetReader::open_validated(&path).err().unwrap();
+        assert!(err.to_string().contains("trailing bytes"), "{err}");
 
-        let (_m1, v1) = load_dataset(&path_mem).unwrap();
-        let (_m2, v2) = load_dataset(&path_stream).unwrap();
+        // Killed partway through a record: the same, with a torn tail.
+        let torn = before.len() + 6;
+        std::fs::write(&path, &after[..torn]).unwrap();
+        assert!(same(&read_all(&path), &old));
 
-        assert_eq!(v1.len(), v2.len());
-        for (a, b) in v1.iter().zip(v2.iter()) {
-            assert_eq!(a.pos, b.pos);
-            assert_eq!(a.neg, b.neg);
-        }
+        // The next append drops the tail and counts only its own records.
+        let mut writer = DatasetWriter::open_append(&path).unwrap();
+        assert_eq!(writer.recovered_bytes(), 6);
+        writer.append(&extra[2], None).unwrap();
+        assert_eq!(writer.finish().unwrap(), 11);
+        DatasetReader::open_validated(&path).unwrap();
+        let mut expected = old;
+        expected.push((extra[2].clone(), None));
+        assert!(same(&read_all(&path), &expected));
     }
 }
@@ -0,0 +1,329 @@
+//! Exact and near-duplicate removal for dataset files (`dataset-dedupe`).
+//!
+//! Vectors are kept in file order and a vector is dropped when an earlier kept one
+//! duplicates it, so the first occurrence always survives. Exact duplicates (identical
+//! index arrays) are found by hashing the arrays and confirming candidates by
+//! comparison.
+//!
+//! Near-duplicates (cosine at or above a threshold) would need every pair compared.
+//! Instead each vector gets a MinHash signature over its signed indices, split into
+//! [`BANDS`] bands of [`ROWS`] seeded hashes. Only vectors sharing a whole band are
+//! compared. For two vectors with Jaccard similarity `j` over signed indices, that
+//! happens with probability `1 - (1 - j^ROWS)^BANDS`. A cosine of 0.9 between equal-size
+//! vectors is a Jaccard of about 0.82, which collides with probability above 0.999.
+//! Unrelated sparse vectors almost never collide. Near-duplicate detection is therefore
+//! probabilistic. A missed pair survives; nothing is removed without an exact cosine
+//! check.
+
+use crate::dataset::{
+    sidecar_path, sparse_dot, write_derived_dataset, DatasetReader, GenerateConfig,
+};
+use crate::error::BenchError;
+use embeddenator::SparseVec;
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::path::Path;
+
+/// MinHash bands; vectors sharing any one band are compared.
+pub const BANDS: usize = 8;
+/// Hashes per band.
+pub const ROWS: usize = 2;
+
+/// What [`dedupe`] removed.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct DedupeReport {
+    pub input: u64,
+    pub kept: u64,
+    pub exact_removed: u64,
+    pub near_removed: u64,
+    /// The cosine threshold, if near-duplicates were looked for.
+    pub near_threshold: Option<f64>,
+    /// Cosines computed for LSH candidates (a measure of how well the buckets pruned).
+    pub near_compared: u64,
+    /// Whether [`dedupe_file`] removed a sidecar left at the output path.
+    pub removed_sidecar: bool,
+}
+
+/// Hash of the index arrays; equal vectors hash equal.
+pub fn vector_hash(v: &SparseVec) -> u64 {
+    let mut h = DefaultHasher::new();
+    v.pos.hash(&mut h);
+    v.neg.hash(&mut h);
+    h.finish()
+}
+
+/// Ternary cosine over the index sets; 0.0 if either vector is empty.
+fn cosine(a: &SparseVec, b: &SparseVec) -> f64 {
+    let na = (a.pos.len() + a.neg.len()) as f64;
+    let nb = (b.pos.len() + b.neg.len()) as f64;
+    if na == 0.0 || nb == 0.0 {
+        return 0.0;
+    }
+    sparse_dot(a, b) as f64 / (na.sqrt() * nb.sqrt())
+}
+
+/// splitmix64 finalizer: a cheap seeded hash of one index.
+fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+/// One key per band: the band's [`ROWS`] MinHash values combined.
+fn band_keys(v: &SparseVec, seed: u64) -> [u64; BANDS] {
+    // A signed index is one element; +i and -i differ.
+    let codes: Vec<u64> = v
+        .pos
+        .iter()
+        .map(|&i| (i as u64) << 1)
+        .chain(v.neg.iter().map(|&i| ((i as u64) << 1) | 1))
+        .collect();
+    let mut keys = [0u64; BANDS];
+    for (band, key) in keys.iter_mut().enumerate() {
+        for row in 0..ROWS {
+            let salt = mix(seed ^ ((band * ROWS + row) as u64 + 1));
+            let min = codes
+                .iter()
+                .map(|&c| mix(c ^ salt))
+                .min()
+                .unwrap_or(u64::MAX);
+            *key = mix(*key ^ min);
+        }
+    }
+    keys
+}
+
+/// Indices of the vectors to keep, in order, and what was removed. `near_threshold`
+/// enables near-duplicate removal; `seed` picks the MinHash functions.
+pub fn dedupe(
+    vectors: &[SparseVec],
+    near_threshold: Option<f64>,
+    seed: u64,
+) -> (Vec<usize>, DedupeReport) {
+    let mut report = DedupeReport {
+        input: vectors.len() as u64,
+        near_threshold,
+        ..Default::default()
+    };
+    let mut kept = Vec::new();
+    let mut exact: HashMap<u64, Vec<usize>> = HashMap::new();
+    let mut buckets: HashMap<(usize, u64), Vec<usize>> = HashMap::new();
+
+    for (i, v) in vectors.iter().enumerate() {
+        let hash = vector_hash(v);
+        let same = |&j: &usize| vectors[j].pos == v.pos && vectors[j].neg == v.neg;
+        if exact.get(&hash).is_some_and(|js| js.iter().any(same)) {
+            report.exact_removed += 1;
+            continue;
+        }
+
+        let keys = near_threshold.map(|_| band_keys(v, seed));
+        if let (Some(threshold), Some(keys)) = (near_threshold, &keys) {
+            let mut candidates: Vec<usize> = keys
+                .iter()
+                .enumerate()
+                .filter_map(|(band, key)| buckets.get(&(band, *key)))
+                .flatten()
+                .copied()
+                .collect();
+            candidates.sort_unstable();
+            candidates.dedup();
+            report.near_compared += candidates.len() as u64;
+            if candidates
+                .iter()
+                .any(|&j| cosine(v, &vectors[j]) >= threshold)
+            {
+                report.near_removed += 1;
+                continue;
+            }
+        }
+
+        exact.entry(hash).or_default().push(i);
+        if let Some(keys) = keys {
+            for (band, key) in keys.into_iter().enumerate() {
+                buckets.entry((band, key)).or_default().push(i);
+            }
+        }
+        kept.push(i);
+    }
+    report.kept = kept.len() as u64;
+    (kept, report)
+}
+
+/// Deduplicate the dataset at `input` into `output`, keeping labels and the header's
+/// dimension, seed and generator. `output` may be `input`. It is written flagged
+/// [`FLAG_DERIVED`](crate::dataset::FLAG_DERIVED): the seed no longer regenerates it.
+///
+/// Shard files are refused: a deduplicated shard no longer covers its slice of the
+/// global range. A sidecar at `output` is removed, since its count and hash describe
+/// the file before deduplication.
+pub fn dedupe_file(
+    input: &Path,
+    output: &Path,
+    near_threshold: Option<f64>,
+) -> io::Result<DedupeReport> {
+    let mut reader = DatasetReader::open_validated(input)?;
+    let meta = reader.meta().clone();
+    if let Some(shard) = meta.shard {
+        return Err(BenchError::invalid_args(format!(
+            "{}: cannot deduplicate shard {} of {}",
+            input.display(),
+            shard.index,
+            shard.count
+        ))
+        .into());
+    }
+    let mut vectors = Vec::with_capacity(meta.count as usize);
+    let mut labels = Vec::new();
+    while let Some((v, label)) = reader.next_labeled_vector()? {
+        vectors.push(v);
+        labels.extend(label);
+    }
+
+    let (kept, mut report) = dedupe(&vectors, near_threshold, meta.seed);
+    let config = GenerateConfig {
+        count: kept.len() as u64,
+        dimension: meta.dimension as usize,
+        seed: meta.seed,
+        generator: meta.generator,
+        ..Default::default()
+    };
+    let kept_vectors: Vec<SparseVec> = kept.iter().map(|&i| vectors[i].clone()).collect();
+    let kept_labels: Option<Vec<u32>> = meta
+        .labeled
+        .then(|| kept.iter().map(|&i| labels[i]).collect());
+    write_derived_dataset(output, &kept_vectors, kept_labels.as_deref(), &config)?;
+    report.removed_sidecar = match std::fs::remove_file(sidecar_path(output)) {
+        Ok(()) => true,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => false,
+        Err(e) => return Err(e),
+    };
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dataset::{
+        check_determinism, generate_dataset, load_dataset, read_sidecar, write_dataset_shard,
+        write_labeled_dataset, write_sidecar, ShardDescriptor,
+    };
+    use embeddenator::DIM;
+
+    /// `v` with its first `n` positive indices moved by one (still sorted, no overlap
+    /// with the negatives in practice).
+    fn perturbed(v: &SparseVec, n: usize) -> SparseVec {
+        let mut out = v.clone();
+        for i in out.pos.iter_mut().take(n) {
+            *i += 1;
+        }
+        out.pos.sort_unstable();
+        out.pos.dedup();
+        out
+    }
+
+    fn base() -> Vec<SparseVec> {
+        generate_dataset(&GenerateConfig {
+            count: 6,
+            dimension: DIM,
+            seed: 3,
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_exact_and_near_duplicates_removed() {
+        let b = base();
+        // 0..6 distinct, then: exact copies of 1 and 4, a copy of a copy, and a near
+        // duplicate of 2 (cosine about 0.97).
+        let near = perturbed(&b[2], 5);
+        assert!(cosine(&near, &b[2]) > 0.9 && cosine(&near, &b[2]) < 1.0);
+        let mut vs = b.clone();
+        vs.extend([b[1].clone(), b[4].clone(), b[1].clone(), near]);
+
+        let (kept, report) = dedupe(&vs, None, 0);
+        assert_eq!(kept, [0, 1, 2, 3, 4, 5, 9]);
+        assert_eq!((report.exact_removed, report.near_removed), (3, 0));
+
+        let (kept, report) = dedupe(&vs, Some(0.9), 0);
+        assert_eq!(kept, [0, 1, 2, 3, 4, 5]);
+        assert_eq!((report.exact_removed, report.near_removed), (3, 1));
+        assert_eq!(report.kept, 6);
+        // The buckets pruned: far fewer cosines than the 45 pairs.
+        assert!(report.near_compared < 10, "{report:?}");
+
+        // The first occurrence survives, even when it is the perturbed one.
+        let mut swapped = vec![perturbed(&b[2], 5)];
+        swapped.extend(b.iter().cloned());
+        let (kept, _) = dedupe(&swapped, Some(0.9), 0);
+        assert_eq!(kept, [0, 1, 2, 4, 5, 6]);
+    }
+
+    #[test]
+    fn test_dedupe_file_keeps_labels() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("in.embr");
+        let output = dir.path().join("out.embr");
+        let b = base();
+        let mut vs = b.clone();
+        vs.push(b[0].clone());
+        let labels: Vec<u32> = (0..vs.len() as u32).collect();
+        let config = GenerateConfig {
+            count: vs.len() as u64,
+            seed: 3,
+            ..Default::default()
+        };
+        write_labeled_dataset(&input, &vs, &labels, &config).unwrap();
+
+        let report = dedupe_file(&input, &output, Some(0.95)).unwrap();
+        assert_eq!((report.input, report.kept, report.exact_removed), (7, 6, 1));
+
+        let mut reader = DatasetReader::open(&output).unwrap();
+        assert!(reader.meta().labeled && reader.meta().derived);
+        assert_eq!(reader.meta().seed, 3);
+        let mut out_labels = Vec::new();
+        while let Some((_, label)) = reader.next_labeled_vector().unwrap() {
+            out_labels.push(label.unwrap());
+        }
+        assert_eq!(out_labels, [0, 1, 2, 3, 4, 5]);
+        let (_, loaded) = load_dataset(&output).unwrap();
+        assert_eq!(loaded[5].pos, b[5].pos);
+    }
+
+    #[test]
+    fn test_dedupe_in_place_drops_sidecar_and_refuses_shards() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("d.embr");
+        let b = base();
+        let mut vs = b.clone();
+        vs.push(b[0].clone());
+        let config = GenerateConfig {
+            count: vs.len() as u64,
+            seed: 3,
+            ..Default::default()
+        };
+        crate::dataset::write_dataset(&path, &vs, &config).unwrap();
+        write_sidecar(&path, &config).unwrap();
+
+        let report = dedupe_file(&path, &path, None).unwrap();
+        assert_eq!(report.kept, 6);
+        assert!(report.removed_sidecar);
+        assert!(read_sidecar(&path).unwrap().is_none());
+        let meta = DatasetReader::open_validated(&path).unwrap().meta().clone();
+        assert_eq!((meta.count, meta.derived), (6, true));
+        // The seed and generator are the input's, but the records are not all its
+        // output any more.
+        let err = check_determinism(&path, 4).unwrap_err();
+        assert!(err.to_string().contains("not a generated dataset"), "{err}");
+
+        let shard = dir.path().join("s.embr");
+        let descriptor = ShardDescriptor::new(0, 2, config.count).unwrap();
+        write_dataset_shard(&shard, &config, descriptor, 16).unwrap();
+        let err = dedupe_file(&shard, &dir.path().join("out.embr"), None).unwrap_err();
+        assert!(err.to_string().contains("shard 0 of 2"), "{err}");
+    }
+}
@@ -0,0 +1,611 @@
+//! Disk space preflight and scratch directories.
+//!
+//! Ingesting a corpus, saving its engram and extracting it again (twice over, while an
+//! extract loop replaces its previous output) can take several times the corpus size in
+//! temporary space, and a run that hits ENOSPC halfway fails with an opaque error. Before
+//! a run that writes, the binary turns what it is about to do into [`Requirement`]s,
+//! checks them against the free space of each filesystem involved ([`preflight`]) and
+//! refuses to start when one is short, unless told to go ahead (`--ignore-space-check`).
+//!
+//! Requirements of one section exist together and are freed when it ends; a filesystem
+//! needs the largest section's total plus everything that outlives its section
+//! (generated datasets). The estimate, the free space seen and, from a [`PeakSampler`]
+//! polling free space during the run, the largest drop in it are recorded as
+//! `RunMeta.disk_space`.
+//!
+//! Benches create their temporary directories with [`scratch_dir`]: they are removed
+//! when dropped, early errors included, and hold an exclusive lock on a sibling
+//! `<dir>.lock` file while alive, so that [`sweep_stale_scratch`] can remove those left
+//! behind by a killed run. The lock goes away with the process, whatever pid namespace
+//! it ran in.
+
+use crate::benches::input_walk::{self, WalkOptions};
+use crate::error::BenchError;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use tempfile::TempDir;
+
+/// Name prefix of [`scratch_dir`] directories.
+pub const SCRATCH_PREFIX: &str = "embeddenator-bench-";
+
+/// Suffix of the lock file next to a [`scratch_dir`] directory.
+const LOCK_SUFFIX: &str = ".lock";
+
+/// How often a [`PeakSampler`] polls free space.
+pub const SAMPLE_INTERVAL: Duration = Duration::from_millis(200);
+
+/// A temporary directory for a bench, removed when dropped.
+pub struct ScratchDir {
+    dir: Option<TempDir>,
+    /// Held locked until the directory is gone.
+    lock: File,
+    lock_path: PathBuf,
+}
+
+impl ScratchDir {
+    pub fn path(&self) -> &Path {
+        self.dir
+            .as_ref()
+            .expect("scratch dir is only taken on drop")
+            .path()
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        drop(self.dir.take());
+        let _ = std::fs::remove_file(&self.lock_path);
+        let _ = self.lock.unlock();
+    }
+}
+
+/// A temporary directory for a bench, removed when dropped.
+pub fn scratch_dir() -> io::Result<ScratchDir> {
+    scratch_dir_in(&std::env::temp_dir())
+}
+
+fn scratch_dir_in(parent: &Path) -> io::Result<ScratchDir> {
+    let dir = tempfile::Builder::new()
+        .prefix(SCRATCH_PREFIX)
+        .tempdir_in(parent)?;
+    let mut lock_path = dir.path().as_os_str().to_owned();
+    lock_path.push(LOCK_SUFFIX);
+    let lock_path = PathBuf::from(lock_path);
+    let lock = File::create(&lock_path)?;
+    lock.lock()?;
+    Ok(ScratchDir {
+        dir: Some(dir),
+        lock,
+        lock_path,
+    })
+}
+
+/// Remove [`scratch_dir`] directories under `parent` whose lock no process holds,
+/// returning how many were removed. Directories without a lock file (one being created,
+/// or left by an older version) are left alone.
+pub fn sweep_stale_scratch(parent: &Path) -> usize {
+    let Ok(entries) = std::fs::read_dir(parent) else {
+        return 0;
+    };
+    let mut removed = 0;
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let Some(dir_name) = name
+            .to_str()
+            .filter(|n| n.starts_with(SCRATCH_PREFIX))
+            .and_then(|n| n.strip_suffix(LOCK_SUFFIX))
+        else {
+            continue;
+        };
+        let Ok(lock) = File::options().write(true).open(entry.path()) else {
+            continue;
+        };
+        // Held by a live run (or not lockable here at all).
+        if lock.try_lock().is_err() {
+            continue;
+        }
+        match std::fs::remove_dir_all(parent.join(dir_name)) {
+            Ok(()) => removed += 1,
+            // A run killed between removing its directory and its lock file.
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(_) => continue,
+        }
+        let _ = std::fs::remove_file(entry.path());
+    }
+    removed
+}
+
+/// Space something in the run will take.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Requirement {
+    /// What it is, for the report and the refusal message (`engram`, `extraction`).
+    pub what: String,
+    /// Where it is written; need not exist yet.
+    pub dir: PathBuf,
+    pub bytes: u64,
+    /// Section it belongs to and is freed with; `None` if it outlives the run.
+    pub section: Option<String>,
+}
+
+/// Free space of the filesystem holding a directory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FsSpace {
+    /// Identifies the filesystem (the device id on unix).
+    pub fs_id: u64,
+    pub available_bytes: u64,
+}
+
+/// Where free space comes from; [`Statvfs`] outside tests.
+pub trait SpaceProbe: Send + Sync {
+    /// Free space for `dir` (an existing directory), or `None` if it cannot be told.
+    fn space(&self, dir: &Path) -> Option<FsSpace>;
+}
+
+/// `statvfs(3)`: space available to unprivileged users.
+pub struct Statvfs;
+
+impl SpaceProbe for Statvfs {
+    fn space(&self, dir: &Path) -> Option<FsSpace> {
+        sys::space(dir)
+    }
+}
+
+#[cfg(unix)]
+mod sys {
+    use super::FsSpace;
+    use std::ffi::CString;
+    use std::mem::MaybeUninit;
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::fs::MetadataExt;
+    use std::path::Path;
+
+    pub fn space(dir: &Path) -> Option<FsSpace> {
+        let fs_id = std::fs::metadata(dir).ok()?.dev();
+        let path = CString::new(dir.as_os_str().as_bytes()).ok()?;
+        let mut buf = MaybeUninit::<libc::statvfs>::zeroed();
+        if unsafe { libc::statvfs(path.as_ptr(), buf.as_mut_ptr()) } != 0 {
+            return None;
+        }
+        let s = unsafe { buf.assume_init() };
+        // The field types differ between platforms (`fsblkcnt_t`, `c_ulong`).
+        #[allow(clippy::unnecessary_cast)]
+        let (bavail, frsize) = (s.f_bavail as u64, s.f_frsize as u64);
+        Some(FsSpace {
+            fs_id,
+            available_bytes: bavail.saturating_mul(frsize),
+        })
+    }
+}
+
+/// Off unix free space cannot be told: the preflight checks nothing and
+/// `RunMeta.disk_space` is left unset.
+#[cfg(not(unix))]
+mod sys {
+    use super::FsSpace;
+    use std::path::Path;
+
+    pub fn space(_: &Path) -> Option<FsSpace> {
+        None
+    }
+}
+
+/// The preflight and what the run took, as recorded in `RunMeta.disk_space`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DiskSpace {
+    /// Set when a filesystem was short and the run went ahead anyway.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub ignored: bool,
+    pub filesystems: Vec<FilesystemSpace>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct FilesystemSpace {
+    /// First directory of the run on this filesystem (or its nearest existing parent).
+    pub path: PathBuf,
+    /// Estimated need: the largest section plus what outlives its section.
+    pub required_bytes: u64,
+    /// Free at the preflight.
+    pub available_bytes: u64,
+    /// The estimate's parts, `<section>: <what>` (or just `<what>`) to bytes.
+    pub items: BTreeMap<String, u64>,
+    /// Largest drop in free space seen during the run (best-effort: polled every
+    /// [`SAMPLE_INTERVAL`], and other processes' writes count too).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub peak_used_bytes: Option<u64>,
+    #[serde(skip)]
+    fs_id: u64,
+}
+
+impl DiskSpace {
+    /// Filesystems whose free space is below their estimate.
+    pub fn short(&self) -> impl Iterator<Item = &FilesystemSpace> {
+        self.filesystems
+            .iter()
+            .filter(|fs| fs.required_bytes > fs.available_bytes)
+    }
+}
+
+/// Check `requirements` against the free space `probe` reports, per filesystem.
+///
+/// A short filesystem is a [`BenchError::InsufficientSpace`] error naming it, unless
+/// `ignore` is set, in which case it is warned about and the result is marked
+/// `ignored`. Requirements whose filesystem cannot be probed are left out.
+pub fn preflight(
+    requirements: &[Requirement],
+    probe: &dyn SpaceProbe,
+    ignore: bool,
+) -> io::Result<DiskSpace> {
+    let mut filesystems: Vec<FilesystemSpace> = Vec::new();
+    // Per filesystem, bytes per section (`None`: kept to the end).
+    let mut sections: Vec<BTreeMap<Option<&str>, u64>> = Vec::new();
+    for req in requirements {
+        let Some(dir) = req.dir.ancestors().find(|p| p.is_dir()) else {
+            continue;
+        };
+        let Some(space) = probe.space(dir) else {
+            continue;
+        };
+        let i = match filesystems.iter().position(|fs| fs.fs_id == space.fs_id) {
+            Some(i) => i,
+            None => {
+                filesystems.push(FilesystemSpace {
+                    path: dir.to_path_buf(),
+                    available_bytes: space.available_bytes,
+                    fs_id: space.fs_id,
+                    ..Default::default()
+                });
+                sections.push(BTreeMap::new());
+                filesystems.len() - 1
+            }
+        };
+        let key = match &req.section {
+            Some(section) => format!("{section}: {}", req.what),
+            None => req.what.clone(),
+        };
+        *filesystems[i].items.entry(key).or_default() += req.bytes;
+        *sections[i].entry(req.section.as_deref()).or_default() += req.bytes;
+    }
+    for (fs, sections) in filesystems.iter_mut().zip(&sections) {
+        let kept = sections.get(&None).copied().unwrap_or(0);
+        let largest = sections
+            .iter()
+            .filter(|(s, _)| s.is_some())
+            .map(|(_, &b)| b)
+            .max()
+            .unwrap_or(0);
+        fs.required_bytes = kept + largest;
+    }
+
+    let mut space = DiskSpace {
+        ignored: false,
+        filesystems,
+    };
+    let short: Vec<String> = space
+        .short()
+        .map(|fs| {
+            format!(
+                "{} has {} free but the run needs about {} ({})",
+                fs.path.display(),
+                format_bytes(fs.available_bytes),
+                format_bytes(fs.required_bytes),
+                fs.items
+                    .iter()
+                    .map(|(what, b)| format!("{what} {}", format_bytes(*b)))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        })
+        .collect();
+    if !short.is_empty() {
+        let msg = format!("not enough disk space: {}", short.join("; "));
+        if !ignore {
+            return Err(BenchError::InsufficientSpace(format!(
+                "{msg} (--ignore-space-check to run anyway)"
+            ))
+            .into());
+        }
+        eprintln!("warning: {msg}; running anyway (--ignore-space-check)");
+        space.ignored = true;
+    }
+    Ok(space)
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const MIB: f64 = 1024.0 * 1024.0;
+    if bytes as f64 >= 1024.0 * MIB {
+        format!("{:.1} GiB", bytes as f64 / (1024.0 * MIB))
+    } else {
+        format!("{:.1} MiB", bytes as f64 / MIB)
+    }
+}
+
+/// Bytes under `inputs` as the benches walk them; an input that cannot be walked counts
+/// as empty (the bench reports it).
+fn raw_bytes(inputs: &[PathBuf], walk: &WalkOptions) -> u64 {
+    inputs
+        .iter()
+        .filter_map(|input| input_walk::walk(input, walk).ok())
+        .map(|w| w.total_bytes)
+        .sum()
+}
+
+fn scratch(section: &str, what: &str, bytes: u64) -> Requirement {
+    Requirement {
+        what: what.to_string(),
+        dir: std::env::temp_dir(),
+        bytes,
+        section: Some(section.to_string()),
+    }
+}
+
+/// The `encode` bench over `inputs`: the saved engram (taken as the raw size), an
+/// extraction plus the one before it, which lives until the next replaces it, and with
+/// `chunk_size` the split copy of the inputs. All in the temp directory.
+pub fn encode_requirements(
+    inputs: &[PathBuf],
+    walk: &WalkOptions,
+    chunk_size: Option<usize>,
+) -> Vec<Requirement> {
+    encode_raw_requirements(raw_bytes(inputs, walk), chunk_size)
+}
+
+/// The `encode` bench over a generated corpus of `corpus_bytes`: the corpus itself, then
+/// as [`encode_requirements`].
+pub fn synthetic_encode_requirements(
+    corpus_bytes: u64,
+    chunk_size: Option<usize>,
+) -> Vec<Requirement> {
+    let mut out = vec![scratch("encode", "synthetic corpus", corpus_bytes)];
+    out.extend(encode_raw_requirements(corpus_bytes, chunk_size));
+    out
+}
+
+fn encode_raw_requirements(raw: u64, chunk_size: Option<usize>) -> Vec<Requirement> {
+    let mut out = vec![
+        scratch("encode", "engram", raw),
+        scratch("encode", "extraction", 2 * raw),
+    ];
+    if chunk_size.is_some() {
+        out.push(scratch("encode", "chunk split", raw));
+    }
+    out
+}
+
+/// The `retrieval` bench over `dir`: it ingests in memory, so only the split copy of the
+/// corpus with `chunk_size`.
+pub fn retrieval_requirements(
+    dir: &Path,
+    walk: &WalkOptions,
+    chunk_size: Option<usize>,
+) -> Vec<Requirement> {
+    match chunk_size {
+        Some(_) => vec![scratch(
+            "retrieval",
+            "chunk split",
+            raw_bytes(&[dir.to_path_buf()], walk),
+        )],
+        None => Vec::new(),
+    }
+}
+
+/// A generated dataset of `bytes` written to `path` (in full, next to it, before it is
+/// renamed into place).
+pub fn generate_requirement(path: &Path, bytes: u64) -> Requirement {
+    Requirement {
+        what: format!(
+            "dataset {}",
+            path.file_name().unwrap_or_default().to_string_lossy()
+        ),
+        dir: path.parent().unwrap_or(Path::new(".")).to_path_buf(),
+        bytes,
+        section: None,
+    }
+}
+
+/// A [`preflight`] of the real filesystems whose [`PeakSampler`] is running.
+pub struct Watch {
+    space: DiskSpace,
+    sampler: PeakSampler,
+}
+
+/// What the binary and the suite do before a run that writes: remove scratch
+/// directories of killed runs, [`preflight`] `requirements` with [`Statvfs`] and start
+/// sampling. `None` if nothing the run writes could be probed.
+pub fn watch(requirements: &[Requirement], ignore: bool) -> io::Result<Option<Watch>> {
+    sweep_stale_scratch(&std::env::temp_dir());
+    let space = preflight(requirements, &Statvfs, ignore)?;
+    if space.filesystems.is_empty() {
+        return Ok(None);
+    }
+    let sampler = PeakSampler::start(&space, Arc::new(Statvfs), SAMPLE_INTERVAL);
+    Ok(Some(Watch { space, sampler }))
+}
+
+impl Watch {
+    /// Stop sampling; the preflight with peak use, for `RunMeta.disk_space`.
+    pub fn finish(mut self) -> DiskSpace {
+        self.sampler.finish(&mut self.space);
+        self.space
+    }
+}
+
+/// Polls free space on the preflight's filesystems in a background thread until
+/// [`Self::finish`].
+pub struct PeakSampler {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<Vec<u64>>>,
+}
+
+impl PeakSampler {
+    pub fn start(space: &DiskSpace, probe: Arc<dyn SpaceProbe>, interval: Duration) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let paths: Vec<PathBuf> = space.filesystems.iter().map(|fs| fs.path.clone()).collect();
+        let mut lowest: Vec<u64> = space
+            .filesystems
+            .iter()
+            .map(|fs| fs.available_bytes)
+            .collect();
+        let handle = (!paths.is_empty()).then(|| {
+            let stop = stop.clone();
+            thread::spawn(move || loop {
+                for (path, low) in paths.iter().zip(&mut lowest) {
+                    if let Some(s) = probe.space(path) {
+                        *low = (*low).min(s.available_bytes);
+                    }
+                }
+                if stop.load(Ordering::Relaxed) {
+                    return lowest;
+                }
+                thread::park_timeout(interval);
+            })
+        });
+        Self { stop, handle }
+    }
+
+    /// Stop polling (after one last poll) and record each filesystem's peak use.
+    pub fn finish(mut self, space: &mut DiskSpace) {
+        self.stop.store(true, Ordering::Relaxed);
+        let Some(handle) = self.handle.take() else {
+            return;
+        };
+        handle.thread().unpark();
+        let Ok(lowest) = handle.join() else {
+            return;
+        };
+        for (fs, low) in space.filesystems.iter_mut().zip(lowest) {
+            fs.peak_used_bytes = Some(fs.available_bytes.saturating_sub(low));
+        }
+    }
+}
+
+/// A sampler dropped without [`PeakSampler::finish`] (the run failed) stops too.
+impl Drop for PeakSampler {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = &self.handle {
+            handle.thread().unpark();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Every directory on one filesystem with `free` bytes, as many times as asked.
+    struct Fixed(Mutex<Vec<u64>>);
+
+    impl SpaceProbe for Fixed {
+        fn space(&self, _: &Path) -> Option<FsSpace> {
+            let mut free = self.0.lock().unwrap();
+            let available_bytes = if free.len() > 1 {
+                free.remove(0)
+            } else {
+                free[0]
+            };
+            Some(FsSpace {
+                fs_id: 1,
+                available_bytes,
+            })
+        }
+    }
+
+    fn fixed(free: &[u64]) -> Fixed {
+        Fixed(Mutex::new(free.to_vec()))
+    }
+
+    fn requirements() -> Vec<Requirement> {
+        let dir = std::env::temp_dir();
+        let mut reqs = vec![
+            scratch("encode", "engram", 100),
+            scratch("encode", "extraction", 200),
+            scratch("retrieval", "chunk split", 250),
+        ];
+        reqs.push(generate_requirement(&dir.join("new/d.embr"), 50));
+        reqs
+    }
+
+    #[test]
+    fn test_preflight_refuses_when_short() {
+        // Largest section (encode, 300) plus the dataset that stays (50).
+        let space = preflight(&requirements(), &fixed(&[1_000]), false).unwrap();
+        assert_eq!(space.filesystems.len(), 1);
+        let fs = &space.filesystems[0];
+        assert_eq!((fs.required_bytes, fs.available_bytes), (350, 1_000));
+        assert_eq!(fs.items["encode: extraction"], 200);
+        assert_eq!(fs.items["dataset d.embr"], 50);
+        assert!(!space.ignored);
+
+        let err = preflight(&requirements(), &fixed(&[349]), false).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::StorageFull);
+        assert!(matches!(
+            BenchError::of(&err),
+            Some(BenchError::InsufficientSpace(_))
+        ));
+        assert!(err.to_string().contains("--ignore-space-check"), "{err}");
+
+        let space = preflight(&requirements(), &fixed(&[349]), true).unwrap();
+        assert!(space.ignored);
+        assert_eq!(space.short().count(), 1);
+
+        // Nothing to write, nothing to check.
+        assert_eq!(
+            preflight(&[], &fixed(&[0]), false).unwrap(),
+            DiskSpace::default()
+        );
+    }
+
+    #[test]
+    fn test_peak_sampler_records_lowest_free() {
+        let mut space = preflight(&requirements(), &fixed(&[1_000]), false).unwrap();
+        let probe = Arc::new(fixed(&[900, 400, 700]));
+        let sampler = PeakSampler::start(&space, probe, Duration::from_millis(1));
+        thread::sleep(Duration::from_millis(20));
+        sampler.finish(&mut space);
+        assert_eq!(space.filesystems[0].peak_used_bytes, Some(600));
+    }
+
+    #[test]
+    fn test_scratch_dirs_removed_and_swept() {
+        let parent = tempfile::tempdir().unwrap();
+        let mine = scratch_dir_in(parent.path()).unwrap();
+        let name = mine.path().file_name().unwrap().to_str().unwrap();
+        assert!(name.starts_with(SCRATCH_PREFIX));
+        let path = mine.path().to_path_buf();
+        let lock = parent.path().join(format!("{name}{LOCK_SUFFIX}"));
+        assert!(lock.exists());
+
+        // A live directory is kept while its lock is held; one whose lock nobody holds
+        // goes with its lock file, and so does a lock file whose directory is gone.
+        let stale = parent.path().join(format!("{SCRATCH_PREFIX}stale"));
+        let unlocked = parent.path().join(format!("{SCRATCH_PREFIX}new"));
+        let other = parent.path().join("unrelated");
+        for dir in [&stale, &unlocked, &other] {
+            std::fs::create_dir(dir).unwrap();
+        }
+        let stale_lock = parent
+            .path()
+            .join(format!("{SCRATCH_PREFIX}stale{LOCK_SUFFIX}"));
+        let orphan_lock = parent
+            .path()
+            .join(format!("{SCRATCH_PREFIX}gone{LOCK_SUFFIX}"));
+        for lock in [&stale_lock, &orphan_lock] {
+            File::create(lock).unwrap();
+        }
+        assert_eq!(sweep_stale_scratch(parent.path()), 1);
+        assert!(!stale.exists() && !stale_lock.exists() && !orphan_lock.exists());
+        assert!(path.exists() && lock.exists());
+        assert!(unlocked.exists() && other.exists());
+
+        drop(mine);
+        assert!(!path.exists() && !lock.exists());
+    }
+}
@@ -0,0 +1,278 @@
+//! Best-effort host state probe: CPU frequency scaling and thermal zones.
+//!
+//! Timings on the same machine can swing by 20% between a cold and a warm (or
+//! power-saving) CPU, so the report records the cpufreq governor, frequencies and
+//! thermal readings at run start and end. Only Linux sysfs is probed; elsewhere, or
+//! when a file is missing or unreadable, the corresponding field is `None`.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// Governor name that pins the CPU at its maximum frequency.
+pub const PERFORMANCE_GOVERNOR: &str = "performance";
+
+/// One reading of the host's frequency scaling and thermal state.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct HostSnapshot {
+    /// Distinct `scaling_governor` values across CPUs, comma-joined (e.g. `performance`
+    /// or `performance,powersave` on a mixed system).
+    pub governor: Option<String>,
+    /// Lowest and highest `scaling_cur_freq` across CPUs.
+    pub cur_freq_khz_min: Option<u64>,
+    pub cur_freq_khz_max: Option<u64>,
+    /// Highest `cpuinfo_max_freq` across CPUs.
+    pub max_freq_khz: Option<u64>,
+    /// Readable thermal zones; `None` when thermal sysfs is absent.
+    pub thermal: Option<Vec<ThermalZone>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ThermalZone {
+    /// Zone `type` (e.g. `x86_pkg_temp`), or the directory name when that is unreadable.
+    pub zone: String,
+    pub temp_c: f64,
+}
+
+/// Host state for a whole run, as recorded in `RunMeta.environment`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Environment {
+    pub os: String,
+    pub start: HostSnapshot,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end: Option<HostSnapshot>,
+}
+
+impl Environment {
+    /// Probe the host now; call [`Environment::finish`] once the benches are done.
+    pub fn start() -> Self {
+        Self {
+            os: std::env::consts::OS.to_string(),
+            start: probe(),
+            end: None,
+        }
+    }
+
+    pub fn finish(&mut self) {
+        self.end = Some(probe());
+    }
+
+    /// Governor at run start, if known.
+    pub fn governor(&self) -> Option<&str> {
+        self.start.governor.as_deref()
+    }
+}
+
+/// Probe the running host (Linux sysfs; all `None` elsewhere).
+pub fn probe() -> HostSnapshot {
+    if cfg!(target_os = "linux") {
+        probe_sysfs(Path::new("/sys"))
+    } else {
+        HostSnapshot::default()
+    }
+}
+
+/// Probe a sysfs tree rooted at `root` (normally `/sys`).
+pub fn probe_sysfs(root: &Path) -> HostSnapshot {
+    let read = |p: &Path| fs::read_to_string(p).ok();
+
+    let mut governors = Vec::new();
+    let mut cur = Vec::new();
+    let mut max = Vec::new();
+    for dir in sorted_entries(&root.join("devices/system/cpu"), "cpu") {
+        // Skip cpufreq/cpuidle/etc.; only cpuN directories carry per-CPU state.
+        let is_cpu = dir
+            .file_name()
+            .and_then(|n| n.to_str())
+            .and_then(|n| n.strip_prefix("cpu"))
+            .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()));
+        if !is_cpu {
+            continue;
+        }
+        let freq = dir.join("cpufreq");
+        governors.extend(read(&freq.join("scaling_governor")).and_then(|s| parse_governor(&s)));
+        cur.extend(read(&freq.join("scaling_cur_freq")).and_then(|s| parse_khz(&s)));
+        max.extend(read(&freq.join("cpuinfo_max_freq")).and_then(|s| parse_khz(&s)));
+    }
+
+    let thermal_root = root.join("class/thermal");
+    let thermal = thermal_root.is_dir().then(|| {
+        sorted_entries(&thermal_root, "thermal_zone")
+            .into_iter()
+            .filter_map(|dir| {
+                let temp_c = parse_millicelsius(&read(&dir.join("temp"))?)?;
+                let zone = read(&dir.join("type"))
+                    .map(|s| s.trim().to_string())
+                    .filter(|s| !s.is_empty())
+                    .unwrap_or_else(|| dir.file_name().unwrap().to_string_lossy().to_string());
+                Some(ThermalZone { zone, temp_c })
+            })
+            .collect()
+    });
+
+    HostSnapshot {
+        governor: summarize_governors(&governors),
+        cur_freq_khz_min: cur.iter().copied().min(),
+        cur_freq_khz_max: cur.iter().copied().max(),
+        max_freq_khz: max.iter().copied().max(),
+        thermal,
+    }
+}
+
+/// Margins within which [`IdleReading::near`] counts the host as back to baseline.
+pub const IDLE_TEMP_MARGIN_C: f64 = 2.0;
+pub const IDLE_LOAD_MARGIN: f64 = 0.5;
+
+/// What `--cooldown-until-idle` waits on: the hottest thermal zone and the 1-minute
+/// load average.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct IdleReading {
+    pub temp_c: Option<f64>,
+    pub loadavg_1m: Option<f64>,
+}
+
+impl IdleReading {
+    /// Read the running host (Linux sysfs and `/proc/loadavg`; all `None` elsewhere).
+    pub fn now() -> Self {
+        if !cfg!(target_os = "linux") {
+            return Self::default();
+        }
+        let temp_c = probe_sysfs(Path::new("/sys"))
+            .thermal
+            .and_then(|zones| zones.iter().map(|z| z.temp_c).reduce(f64::max));
+        let loadavg_1m = fs::read_to_string("/proc/loadavg")
+            .ok()
+            .and_then(|s| parse_loadavg(&s));
+        Self { temp_c, loadavg_1m }
+    }
+
+    /// Whether every reading known on both sides is within its margin above
+    /// `baseline` (trivially true when nothing is known).
+    pub fn near(&self, baseline: &IdleReading) -> bool {
+        let within = |now: Option<f64>, base: Option<f64>, margin: f64| match (now, base) {
+            (Some(now), Some(base)) => now <= base + margin,
+            _ => true,
+        };
+        within(self.temp_c, baseline.temp_c, IDLE_TEMP_MARGIN_C)
+            && within(self.loadavg_1m, baseline.loadavg_1m, IDLE_LOAD_MARGIN)
+    }
+}
+
+fn sorted_entries(dir: &Path, prefix: &str) -> Vec<std::path::PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut out: Vec<_> = entries
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_name().to_string_lossy().starts_with(prefix))
+        .map(|e| e.path())
+        .collect();
+    out.sort();
+    out
+}
+
+/// Parse a `scaling_governor` file.
+pub fn parse_governor(s: &str) -> Option<String> {
+    let s = s.trim();
+    (!s.is_empty()).then(|| s.to_string())
+}
+
+/// Parse a cpufreq frequency file (kHz).
+pub fn parse_khz(s: &str) -> Option<u64> {
+    s.trim().parse().ok()
+}
+
+/// Parse a thermal zone `temp` file (millidegrees Celsius) into degrees Celsius.
+pub fn parse_millicelsius(s: &str) -> Option<f64> {
+    s.trim().parse::<i64>().ok().map(|m| m as f64 / 1000.0)
+}
+
+/// Parse the 1-minute figure from `/proc/loadavg`.
+pub fn parse_loadavg(s: &str) -> Option<f64> {
+    s.split_whitespace().next()?.parse().ok()
+}
+
+/// Collapse per-CPU governors into one sorted, de-duplicated, comma-joined value.
+pub fn summarize_governors(governors: &[String]) -> Option<String> {
+    let mut distinct: Vec<&str> = governors.iter().map(String::as_str).collect();
+    distinct.sort_unstable();
+    distinct.dedup();
+    (!distinct.is_empty()).then(|| distinct.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sysfs_values() {
+        assert_eq!(
+            parse_governor("performance\n").as_deref(),
+            Some("performance")
+        );
+        assert_eq!(parse_governor("  \n"), None);
+        assert_eq!(parse_khz("3400000\n"), Some(3_400_000));
+        assert_eq!(parse_khz("<unknown>\n"), None);
+        assert_eq!(parse_millicelsius("54000\n"), Some(54.0));
+        assert_eq!(parse_millicelsius("-2500\n"), Some(-2.5));
+        assert_eq!(parse_millicelsius(""), None);
+        assert_eq!(parse_loadavg("0.52 0.58 0.59 1/467 12345\n"), Some(0.52));
+        assert_eq!(parse_loadavg(""), None);
+
+        let govs = ["powersave", "performance", "powersave"].map(String::from);
+        assert_eq!(
+            summarize_governors(&govs).as_deref(),
+            Some("performance,powersave")
+        );
+        assert_eq!(summarize_governors(&[]), None);
+    }
+
+    #[test]
+    fn test_probe_sysfs_fixture() {
+        let root = tempfile::tempdir().unwrap();
+        let write = |rel: &str, contents: &str| {
+            let p = root.path().join(rel);
+            fs::create_dir_all(p.parent().unwrap()).unwrap();
+            fs::write(p, contents).unwrap();
+        };
+        for (cpu, cur) in [("cpu0", "1200000\n"), ("cpu1", "3100000\n")] {
+            write(
+                &format!("devices/system/cpu/{cpu}/cpufreq/scaling_governor"),
+                "performance\n",
+            );
+            write(
+                &format!("devices/system/cpu/{cpu}/cpufreq/scaling_cur_freq"),
+                cur,
+            );
+            write(
+                &format!("devices/system/cpu/{cpu}/cpufreq/cpuinfo_max_freq"),
+                "3600000\n",
+            );
+        }
+        // Not a cpuN directory; must be ignored.
+        write(
+            "devices/system/cpu/cpufreq/policy0/scaling_governor",
+            "powersave\n",
+        );
+        write("class/thermal/thermal_zone0/type", "x86_pkg_temp\n");
+        write("class/thermal/thermal_zone0/temp", "61000\n");
+        write("class/thermal/thermal_zone1/temp", "garbage\n");
+
+        let snap = probe_sysfs(root.path());
+        assert_eq!(snap.governor.as_deref(), Some(PERFORMANCE_GOVERNOR));
+        assert_eq!(snap.cur_freq_khz_min, Some(1_200_000));
+        assert_eq!(snap.cur_freq_khz_max, Some(3_100_000));
+        assert_eq!(snap.max_freq_khz, Some(3_600_000));
+        assert_eq!(
+            snap.thermal,
+            Some(vec![ThermalZone {
+                zone: "x86_pkg_temp".to_string(),
+                temp_c: 61.0
+            }])
+        );
+
+        // An empty tree probes to all-None.
+        let empty = tempfile::tempdir().unwrap();
+        assert_eq!(probe_sysfs(empty.path()), HostSnapshot::default());
+    }
+}
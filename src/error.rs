@@ -0,0 +1,210 @@
+//! Errors the crate raises itself.
+//!
+//! Runners keep returning `io::Result` (the binary and the stable entry points are built
+//! on it), but an error that starts here is a [`BenchError`] carried inside the
+//! `io::Error`, so a caller can tell a bad dataset header from an unsupported option or
+//! a corpus with nothing in it without matching on message text:
+//!
+//! ```ignore
+//! match BenchError::of(&err) {
+//!     Some(BenchError::DatasetFormat { offset, .. }) => ...,
+//!     Some(BenchError::BenchUnavailable(_)) => ...,
+//!     _ => ...,
+//! }
+//! ```
+//!
+//! The `io::Error` keeps the message and an [`io::ErrorKind`] matching the variant.
+
+use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+pub enum BenchError {
+    /// A dataset that does not parse: bad magic, version or flags, an index list longer
+    /// than the dimension, trailing bytes, a corrupt extension or sidecar. `offset` is the
+    /// byte offset of the record or field at fault, where known.
+    DatasetFormat {
+        offset: Option<u64>,
+        reason: String,
+    },
+    /// A dataset that ends before its header (or the tuple being read) says it should.
+    DatasetTruncated {
+        offset: Option<u64>,
+        reason: String,
+    },
+    /// A dataset or corpus with too little in it for the bench: no records, no chunks,
+    /// fewer vectors than a pair needs.
+    DatasetTooSmall(String),
+    /// Arguments that are out of range, unknown or cannot be combined.
+    InvalidArgs(String),
+    /// A bench or option that cannot run on this input, e.g. one that seeks given stdin.
+    BenchUnavailable(String),
+    /// Less free disk space than the run is estimated to need.
+    InsufficientSpace(String),
+    /// A file another writer holds locked, e.g. a dataset being appended to.
+    Locked(String),
+    Io(io::Error),
+}
+
+impl BenchError {
+    /// The [`BenchError`] inside `err`, if it came from this crate.
+    pub fn of(err: &io::Error) -> Option<&BenchError> {
+        err.get_ref()?.downcast_ref()
+    }
+
+    pub fn kind(&self) -> io::ErrorKind {
+        match self {
+            BenchError::DatasetFormat { .. } => io::ErrorKind::InvalidData,
+            BenchError::DatasetTruncated { .. } => io::ErrorKind::UnexpectedEof,
+            BenchError::DatasetTooSmall(_) | BenchError::InvalidArgs(_) => {
+                io::ErrorKind::InvalidInput
+            }
+            BenchError::BenchUnavailable(_) => io::ErrorKind::Unsupported,
+            BenchError::InsufficientSpace(_) => io::ErrorKind::StorageFull,
+            BenchError::Locked(_) => io::ErrorKind::WouldBlock,
+            BenchError::Io(e) => e.kind(),
+        }
+    }
+
+    pub fn format(offset: impl Into<Option<u64>>, reason: impl Into<String>) -> Self {
+        BenchError::DatasetFormat {
+            offset: offset.into(),
+            reason: reason.into(),
+        }
+    }
+
+    pub fn truncated(offset: impl Into<Option<u64>>, reason: impl Into<String>) -> Self {
+        BenchError::DatasetTruncated {
+            offset: offset.into(),
+            reason: reason.into(),
+        }
+    }
+
+    pub fn too_small(reason: impl Into<String>) -> Self {
+        BenchError::DatasetTooSmall(reason.into())
+    }
+
+    pub fn invalid_args(reason: impl Into<String>) -> Self {
+        BenchError::InvalidArgs(reason.into())
+    }
+
+    pub fn unavailable(reason: impl Into<String>) -> Self {
+        BenchError::BenchUnavailable(reason.into())
+    }
+}
+
+/// `err` with `context` in front of its message, for a failure inside the record at
+/// byte `offset`. A dataset error keeps its variant (taking `offset`); an end of file
+/// becomes [`BenchError::DatasetTruncated`] and invalid data
+/// [`BenchError::DatasetFormat`]; anything else stays a plain `io::Error`.
+pub(crate) fn in_record(err: io::Error, offset: u64, context: &str) -> io::Error {
+    let reason = format!("{context}: {err}");
+    match (BenchError::of(&err), err.kind()) {
+        (Some(BenchError::DatasetFormat { .. }), _) | (None, io::ErrorKind::InvalidData) => {
+            BenchError::format(offset, reason).into()
+        }
+        (Some(BenchError::DatasetTruncated { .. }), _) | (None, io::ErrorKind::UnexpectedEof) => {
+            BenchError::truncated(offset, reason).into()
+        }
+        (_, kind) => io::Error::new(kind, reason),
+    }
+}
+
+impl fmt::Display for BenchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BenchError::DatasetFormat { reason, .. }
+            | BenchError::DatasetTruncated { reason, .. }
+            | BenchError::DatasetTooSmall(reason)
+            | BenchError::InvalidArgs(reason)
+            | BenchError::BenchUnavailable(reason)
+            | BenchError::InsufficientSpace(reason)
+            | BenchError::Locked(reason) => f.write_str(reason),
+            BenchError::Io(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for BenchError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BenchError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<BenchError> for io::Error {
+    fn from(err: BenchError) -> Self {
+        match err {
+            BenchError::Io(e) => e,
+            err => io::Error::new(err.kind(), err),
+        }
+    }
+}
+
+/// Unwraps an `io::Error` that carries a [`BenchError`]; any other becomes
+/// [`BenchError::Io`].
+impl From<io::Error> for BenchError {
+    fn from(err: io::Error) -> Self {
+        if BenchError::of(&err).is_none() {
+            return BenchError::Io(err);
+        }
+        match err.into_inner().map(|inner| inner.downcast::<BenchError>()) {
+            Some(Ok(inner)) => *inner,
+            _ => unreachable!("checked above"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_through_io() {
+        let err: io::Error =
+            BenchError::format(68, "index list length 9 exceeds dimension 4").into();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), "index list length 9 exceeds dimension 4");
+        assert!(matches!(
+            BenchError::of(&err),
+            Some(BenchError::DatasetFormat {
+                offset: Some(68),
+                ..
+            })
+        ));
+        assert!(matches!(
+            BenchError::from(err),
+            BenchError::DatasetFormat { .. }
+        ));
+
+        let plain = io::Error::new(io::ErrorKind::NotFound, "gone");
+        assert!(BenchError::of(&plain).is_none());
+        let wrapped = BenchError::from(plain);
+        assert_eq!(wrapped.kind(), io::ErrorKind::NotFound);
+        assert_eq!(io::Error::from(wrapped).to_string(), "gone");
+
+        // Record context keeps (or finds) the dataset variant.
+        let inner: io::Error = BenchError::format(None, "bad").into();
+        let err = in_record(inner, 100, "record 3 of 5 at byte offset 100");
+        assert_eq!(err.to_string(), "record 3 of 5 at byte offset 100: bad");
+        assert!(matches!(
+            BenchError::of(&err),
+            Some(BenchError::DatasetFormat {
+                offset: Some(100),
+                ..
+            })
+        ));
+        let eof = io::Error::from(io::ErrorKind::UnexpectedEof);
+        let err = in_record(eof, 7, "record 0");
+        assert!(matches!(
+            BenchError::of(&err),
+            Some(BenchError::DatasetTruncated { .. })
+        ));
+        let denied = io::Error::from(io::ErrorKind::PermissionDenied);
+        let err = in_record(denied, 7, "record 0");
+        assert!(BenchError::of(&err).is_none());
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+    }
+}
@@ -0,0 +1,121 @@
+//! Gzip for reports and checkpoints.
+//!
+//! Reports are compressed when written to a `.gz` path (or with `--compress-out`), and
+//! every reader sniffs the gzip magic rather than trusting the extension, so compare,
+//! trend and `--baseline` take either form. A stream is one or more gzip members: a
+//! checkpoint appends one member per line, so a crash loses at most the line being
+//! written and the file stays readable up to it.
+
+use flate2::bufread::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+/// The first two bytes of every gzip member.
+pub const MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Whether `path` names a gzip file (`.gz`, e.g. `report.json.gz`).
+pub fn is_gz_path(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("gz"))
+}
+
+pub fn is_gzip(bytes: &[u8]) -> bool {
+    bytes.starts_with(&MAGIC)
+}
+
+/// `bytes` as one gzip member.
+pub fn compress(bytes: &[u8]) -> io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(bytes)?;
+    encoder.finish()
+}
+
+/// The decompressed contents of a gzip stream that was written in full.
+///
+/// `path` is only used in the error, which is `InvalidData` for a corrupt or truncated
+/// stream.
+pub fn decompress(bytes: &[u8], path: &Path) -> io::Result<Vec<u8>> {
+    let (out, complete) = decompress_members(bytes, path)?;
+    if complete < bytes.len() {
+        return Err(corrupt(path, "truncated gzip stream"));
+    }
+    Ok(out)
+}
+
+/// Decompress the complete members at the start of `bytes`, returning their contents
+/// and how many bytes of `bytes` they span. A member cut short by the end of `bytes`
+/// ends the stream; a corrupt one is an `InvalidData` error.
+pub fn decompress_members(bytes: &[u8], path: &Path) -> io::Result<(Vec<u8>, usize)> {
+    let mut out = Vec::new();
+    let mut rest = bytes;
+    while !rest.is_empty() {
+        if !is_gzip(rest) && rest.len() >= MAGIC.len() {
+            return Err(corrupt(path, "not a gzip stream"));
+        }
+        let mut member = Vec::new();
+        let mut decoder = GzDecoder::new(rest);
+        match decoder.read_to_end(&mut member) {
+            Ok(_) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(corrupt(path, &e.to_string())),
+        }
+        out.extend(member);
+        rest = decoder.into_inner();
+    }
+    Ok((out, bytes.len() - rest.len()))
+}
+
+/// Read `path`, decompressing it if it is gzip.
+pub fn read(path: &Path) -> io::Result<Vec<u8>> {
+    let bytes = std::fs::read(path)?;
+    if is_gzip(&bytes) {
+        decompress(&bytes, path)
+    } else {
+        Ok(bytes)
+    }
+}
+
+fn corrupt(path: &Path, what: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{}: corrupt gzip: {what}", path.display()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_members_and_truncation() {
+        let path = Path::new("x.jsonl.gz");
+        let mut stream = compress(b"one\n").unwrap();
+        let first = stream.len();
+        stream.extend(compress(b"two\n").unwrap());
+        assert_eq!(decompress(&stream, path).unwrap(), b"one\ntwo\n");
+
+        // Every cut inside the second member keeps the first.
+        for cut in first..stream.len() {
+            let (out, complete) = decompress_members(&stream[..cut], path).unwrap();
+            assert_eq!(
+                (out.as_slice(), complete),
+                (&b"one\n"[..], first),
+                "cut at {cut}"
+            );
+            if cut > first {
+                assert!(decompress(&stream[..cut], path).is_err());
+            }
+        }
+
+        let mut flipped = stream.clone();
+        flipped[first + 12] ^= 0xff;
+        let err = decompress(&flipped, path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(
+            err.to_string().starts_with("x.jsonl.gz: corrupt gzip"),
+            "{err}"
+        );
+    }
+}
@@ -1,10 +1,17 @@
+use std::cell::{Cell, RefCell};
 use std::hint::black_box;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
+use rand::Rng;
 use rand_chacha::ChaCha8Rng;
 use rand_chacha::rand_core::SeedableRng;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, Debug)]
+use crate::environment::IdleReading;
+use crate::schema::Measurement;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Profile {
     Quick,
     Full,
@@ -43,6 +50,31 @@ impl BenchConfig {
             Profile::Full => 3_000,
         }
     }
+
+    /// `(iters, warmup_iters)` for a measurement of the given cost under this profile.
+    pub fn counts(&self, cost: Cost) -> (u64, u64) {
+        match (cost, self.profile) {
+            (Cost::Micro, _) => (self.iters(), self.warmup_iters()),
+            (Cost::Macro, Profile::Quick) => (2, 1),
+            (Cost::Macro, Profile::Full) => (5, 1),
+            (Cost::OneShot, Profile::Quick) => (1, 0),
+            (Cost::OneShot, Profile::Full) => (3, 0),
+        }
+    }
+}
+
+/// How long one iteration of a measurement takes. Benches declare it and take their
+/// iteration and warmup counts from [`BenchConfig::counts`] rather than tuning their own.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cost {
+    /// Nanoseconds to microseconds: the profile's full `iters` and `warmup_iters`.
+    Micro,
+    /// Milliseconds to seconds (a pass over a file or a query set): a few iterations
+    /// after one warmup, which also primes caches.
+    Macro,
+    /// Seconds to minutes (a full-corpus ingest): a warmup would only multiply the
+    /// runtime, so there is none.
+    OneShot,
 }
 
 #[derive(Clone, Debug)]
@@ -53,19 +85,394 @@ pub struct Measured {
     pub ns_per_iter: f64,
 }
 
+thread_local! {
+    /// Timed iterations per measurement while planning; see [`calibrate`].
+    static CALIBRATION: Cell<Option<u64>> = const { Cell::new(None) };
+}
+
+/// While alive, every measurement on this thread is cut to a few timed iterations;
+/// see [`calibration`]. Dropping it restores the previous setting.
+#[must_use]
+pub struct Calibration(Option<u64>);
+
+impl Drop for Calibration {
+    fn drop(&mut self) {
+        CALIBRATION.set(self.0);
+    }
+}
+
+/// Cut every measurement to `sample_iters` timed iterations and at most one warmup,
+/// for `--dry-run` planning, until the returned guard is dropped.
+///
+/// Benches run unchanged, so they produce the same measurements with the same planned
+/// `iters`/`warmup_iters`; only `ns_per_iter` comes from the sample (and means nothing
+/// if the sample is empty) and `total_ns` covers just the sampled iterations.
+pub fn calibration(sample_iters: u64) -> Calibration {
+    Calibration(CALIBRATION.replace(Some(sample_iters)))
+}
+
+/// Cooldown settings (`--cooldown-ms`, `--cooldown-until-idle`) and what they cost, as
+/// recorded in `RunMeta.cooldown`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Cooldown {
+    /// Fixed pause before every measurement but the first.
+    pub pause_ms: u64,
+    /// After the pause, wait up to this long for the host to get back near its state
+    /// when the cooldown was installed (see [`IdleReading::near`]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub until_idle_max_wait_ms: Option<u64>,
+    /// Cooldowns taken and their total length.
+    #[serde(default)]
+    pub pauses: u64,
+    #[serde(default)]
+    pub waited_ms: u64,
+    /// Idle waits cut off at `until_idle_max_wait_ms`.
+    #[serde(default)]
+    pub idle_timeouts: u64,
+}
+
+/// How often an idle wait re-reads the host.
+const IDLE_POLL: Duration = Duration::from_millis(200);
+
+struct CooldownState {
+    cooldown: Cooldown,
+    baseline: IdleReading,
+    /// Whether a measurement has started since install (the first one is not paused).
+    started: bool,
+}
+
+thread_local! {
+    static COOLDOWN: RefCell<Option<CooldownState>> = const { RefCell::new(None) };
+}
+
+/// While alive, measurements on this thread are spaced out; see [`cooldown`]. Dropping
+/// it restores the previous setting.
+#[must_use]
+pub struct CooldownGuard(Option<CooldownState>);
+
+impl CooldownGuard {
+    /// The settings with the pauses taken so far.
+    pub fn report(&self) -> Cooldown {
+        COOLDOWN.with_borrow(|c| c.as_ref().map(|c| c.cooldown.clone()).unwrap_or_default())
+    }
+}
+
+impl Drop for CooldownGuard {
+    fn drop(&mut self) {
+        COOLDOWN.set(self.0.take());
+    }
+}
+
+/// Pause before every measurement on this thread but the first, until the returned
+/// guard is dropped, so heavy measurements don't run on a CPU the previous one left hot.
+///
+/// The `measure_*` functions pause on their own; benches that time their own loops call
+/// [`cool_down`] before each measurement. Nothing pauses while calibrating.
+pub fn cooldown(settings: Cooldown) -> CooldownGuard {
+    let state = CooldownState {
+        baseline: settings
+            .until_idle_max_wait_ms
+            .map(|_| IdleReading::now())
+            .unwrap_or_default(),
+        cooldown: Cooldown {
+            pauses: 0,
+            waited_ms: 0,
+            idle_timeouts: 0,
+            ..settings
+        },
+        started: false,
+    };
+    CooldownGuard(COOLDOWN.replace(Some(state)))
+}
+
+/// Mark the start of a measurement: with a [`cooldown`] installed, wait out its pause
+/// (and idle wait) unless this is the first measurement.
+pub fn cool_down() {
+    if CALIBRATION.get().is_some() {
+        return;
+    }
+    COOLDOWN.with_borrow_mut(|state| {
+        let Some(state) = state else {
+            return;
+        };
+        if !std::mem::replace(&mut state.started, true) {
+            return;
+        }
+        let c = &mut state.cooldown;
+        let start = Instant::now();
+        std::thread::sleep(Duration::from_millis(c.pause_ms));
+        if let Some(max_ms) = c.until_idle_max_wait_ms {
+            let deadline = Instant::now() + Duration::from_millis(max_ms);
+            while !IdleReading::now().near(&state.baseline) {
+                let now = Instant::now();
+                if now >= deadline {
+                    c.idle_timeouts += 1;
+                    break;
+                }
+                std::thread::sleep(IDLE_POLL.min(deadline - now));
+            }
+        }
+        c.pauses += 1;
+        c.waited_ms += start.elapsed().as_millis() as u64;
+    });
+}
+
+/// Iterations actually run for a planned `(iters, warmup_iters)`.
+fn sampled(iters: u64, warmup_iters: u64) -> (u64, u64) {
+    match CALIBRATION.get() {
+        Some(n) => (iters.min(n), warmup_iters.min(n).min(1)),
+        None => (iters, warmup_iters),
+    }
+}
+
+/// Raw timings kept for one measurement under [`record_samples`], as its `samples` extra.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Samples {
+    /// Iterations each sample covers (the last batch of a loop may cover fewer).
+    pub batch_iters: u64,
+    /// Batches timed; more than `ns.len()` when the reservoir had to drop some.
+    pub batches: u64,
+    /// Nanoseconds per batch: every batch, or a uniform sample of them, in the order kept.
+    pub ns: Vec<u64>,
+}
+
+/// Fixed seed for the reservoir's choices, so reruns keep the same batches.
+const RESERVOIR_SEED: u64 = 0x7361_6d70_6c65;
+
+/// Keeps at most `cap` of the timings pushed, each with equal probability (algorithm R),
+/// so a long loop's samples stay bounded in the report.
+#[derive(Clone, Debug)]
+pub struct Reservoir {
+    cap: usize,
+    seen: u64,
+    ns: Vec<u64>,
+    rng: ChaCha8Rng,
+}
+
+impl Reservoir {
+    pub fn new(cap: usize) -> Self {
+        Self {
+            cap,
+            seen: 0,
+            ns: Vec::with_capacity(cap.min(4096)),
+            rng: ChaCha8Rng::seed_from_u64(RESERVOIR_SEED),
+        }
+    }
+
+    pub fn push(&mut self, ns: u64) {
+        self.seen += 1;
+        if self.ns.len() < self.cap {
+            self.ns.push(ns);
+        } else {
+            let slot = self.rng.gen_range(0..self.seen);
+            if slot < self.cap as u64 {
+                self.ns[slot as usize] = ns;
+            }
+        }
+    }
+
+    /// The samples pushed so far, each covering `batch_iters` iterations, and an empty
+    /// reservoir for the next loop. `None` if nothing was pushed.
+    pub fn take(&mut self, batch_iters: u64) -> Option<Samples> {
+        let taken = std::mem::replace(self, Self::new(self.cap));
+        (taken.seen > 0).then_some(Samples {
+            batch_iters,
+            batches: taken.seen,
+            ns: taken.ns,
+        })
+    }
+}
+
+/// Samples recorded on this thread, with the `total_ns` of the timing they belong to.
+struct SampleLog {
+    cap: usize,
+    recorded: Vec<(u128, Samples)>,
+}
+
+thread_local! {
+    static SAMPLES: RefCell<Option<SampleLog>> = const { RefCell::new(None) };
+}
+
+/// While alive, the `measure_*` functions on this thread record raw timings; see
+/// [`record_samples`]. Dropping it restores the previous setting.
+#[must_use]
+pub struct RecordSamples(Option<SampleLog>);
+
+impl RecordSamples {
+    /// Attach the samples recorded so far to `measurements`, as their `samples` extra.
+    ///
+    /// Benches build their measurements from [`Measured`] themselves, so samples follow
+    /// their timing by `total_ns`, which benches copy over unchanged, and by order:
+    /// measurements are reported in the order they were timed, so each one only looks at
+    /// timings recorded after the one the previous measurement took. Each timing goes to
+    /// one measurement name, the first reported for it, so a measurement derived from
+    /// another's timing (`index.add` shares `index.build`'s `total_ns`) gets none.
+    /// `batch_iters` counts the timed loop's iterations, which for per-record units is
+    /// not `iters`. Measurements no recorded timing matches, or with `samples` of their
+    /// own (the dataset loops), are left alone.
+    pub fn attach(&self, measurements: &mut [Measurement]) {
+        let recorded = SAMPLES.with_borrow_mut(|log| {
+            log.as_mut()
+                .map(|log| std::mem::take(&mut log.recorded))
+                .unwrap_or_default()
+        });
+        let mut recorded: Vec<Option<(u128, Samples)>> = recorded.into_iter().map(Some).collect();
+        let mut next = 0;
+        for m in measurements {
+            let Some(extra) = m.extra.as_object_mut() else {
+                continue;
+            };
+            if m.total_ns == 0 || extra.contains_key("samples") {
+                continue;
+            }
+            let found = recorded[next..].iter().position(|r| {
+                r.as_ref()
+                    .is_some_and(|(total_ns, _)| *total_ns == m.total_ns)
+            });
+            if let Some(i) = found {
+                let (_, samples) = recorded[next + i]
+                    .take()
+                    .expect("matched a recorded timing");
+                extra.insert("samples".to_string(), serde_json::json!(samples));
+                next += i + 1;
+            }
+        }
+    }
+}
+
+impl Drop for RecordSamples {
+    fn drop(&mut self) {
+        SAMPLES.set(self.0.take());
+    }
+}
+
+/// Record up to `cap` raw timings per measurement until the returned guard is dropped,
+/// for statistics beyond the mean (`--record-samples`).
+///
+/// The `measure_*` functions time their iterations in at most `cap` equal batches and
+/// record each batch; loops timed per iteration ([`measure_fn_with_setup`], and the
+/// dataset loops through [`sample_reservoir`]) keep a reservoir sample of `cap`.
+pub fn record_samples(cap: usize) -> RecordSamples {
+    let log = SampleLog {
+        cap: cap.max(1),
+        recorded: Vec::new(),
+    };
+    RecordSamples(SAMPLES.replace(Some(log)))
+}
+
+/// A reservoir for a loop that times its own iterations, while [`record_samples`] is on.
+pub fn sample_reservoir() -> Option<Reservoir> {
+    SAMPLES.with_borrow(|log| log.as_ref().map(|log| Reservoir::new(log.cap)))
+}
+
+fn record(total_ns: u128, samples: Option<Samples>) {
+    if let Some(samples) = samples {
+        SAMPLES.with_borrow_mut(|log| {
+            if let Some(log) = log {
+                log.recorded.push((total_ns, samples));
+            }
+        });
+    }
+}
+
+/// Run `body` for iterations `0..iters` and return how long that took; under
+/// [`record_samples`], in timed batches that are recorded.
+#[inline]
+fn timed_loop(iters: u64, mut body: impl FnMut(u64)) -> u128 {
+    let Some(mut reservoir) = sample_reservoir() else {
+        let start = Instant::now();
+        for i in 0..iters {
+            body(i);
+        }
+        return start.elapsed().as_nanos();
+    };
+    let batch = iters.div_ceil(reservoir.cap as u64).max(1);
+    let start = Instant::now();
+    let mut i = 0;
+    while i < iters {
+        let end = (i + batch).min(iters);
+        let batch_start = Instant::now();
+        for j in i..end {
+            body(j);
+        }
+        reservoir.push(batch_start.elapsed().as_nanos() as u64);
+        i = end;
+    }
+    let total_ns = start.elapsed().as_nanos();
+    record(total_ns, reservoir.take(batch));
+    total_ns
+}
+
 pub fn measure_fn<T>(iters: u64, warmup_iters: u64, mut f: impl FnMut() -> T) -> Measured {
-    for _ in 0..warmup_iters {
+    cool_down();
+    let (run_iters, run_warmup) = sampled(iters, warmup_iters);
+    for _ in 0..run_warmup {
         black_box(f());
     }
 
+    let total_ns = timed_loop(run_iters, |_| {
+        black_box(f());
+    });
+    let denom = run_iters.max(1) as f64;
+    let ns_per_iter = (total_ns as f64) / denom;
+
+    Measured {
+        iters,
+        warmup_iters,
+        total_ns,
+        ns_per_iter,
+    }
+}
+
+/// A single timed execution of `f`, without warmup ([`Cost::OneShot`] under `quick`).
+pub fn measure_once<T>(f: impl FnOnce() -> T) -> Measured {
+    cool_down();
+    let (run_iters, _) = sampled(1, 0);
     let start = Instant::now();
-    for _ in 0..iters {
+    if run_iters > 0 {
         black_box(f());
     }
-    let elapsed = start.elapsed();
+    let total_ns = start.elapsed().as_nanos();
+    if run_iters > 0 {
+        let mut reservoir = sample_reservoir();
+        if let Some(r) = &mut reservoir {
+            r.push(total_ns as u64);
+        }
+        record(total_ns, reservoir.and_then(|mut r| r.take(1)));
+    }
+
+    Measured {
+        iters: 1,
+        warmup_iters: 0,
+        total_ns,
+        ns_per_iter: total_ns as f64,
+    }
+}
+
+/// [`measure_fn`] with no warmup: `iters` timed executions from cold.
+pub fn measure_n_no_warmup<T>(iters: u64, f: impl FnMut() -> T) -> Measured {
+    measure_fn(iters, 0, f)
+}
 
-    let total_ns = elapsed.as_nanos();
-    let denom = iters.max(1) as f64;
+/// Like [`measure_fn`], but passes the iteration index to `f`.
+///
+/// Warmup and measured iterations each count from 0, so benches that cycle through K
+/// inputs with `i % K` exercise every input evenly in both phases.
+pub fn measure_fn_indexed<T>(
+    iters: u64,
+    warmup_iters: u64,
+    mut f: impl FnMut(u64) -> T,
+) -> Measured {
+    cool_down();
+    let (run_iters, run_warmup) = sampled(iters, warmup_iters);
+    for i in 0..run_warmup {
+        black_box(f(i));
+    }
+
+    let total_ns = timed_loop(run_iters, |i| {
+        black_box(f(i));
+    });
+    let denom = run_iters.max(1) as f64;
     let ns_per_iter = (total_ns as f64) / denom;
 
     Measured {
@@ -75,3 +482,500 @@ pub fn measure_fn<T>(iters: u64, warmup_iters: u64, mut f: impl FnMut() -> T) ->
         ns_per_iter,
     }
 }
+
+/// Like [`measure_fn`], but runs `setup` before every iteration outside the timing window.
+///
+/// Mirrors criterion's `iter_with_setup`: each iteration is timed individually, and the
+/// value returned by `f` is dropped after the clock stops, so neither construction of
+/// `S` nor destruction of `T` is counted.
+pub fn measure_fn_with_setup<S, T>(
+    iters: u64,
+    warmup_iters: u64,
+    mut setup: impl FnMut() -> S,
+    mut f: impl FnMut(S) -> T,
+) -> Measured {
+    cool_down();
+    let (run_iters, run_warmup) = sampled(iters, warmup_iters);
+    for _ in 0..run_warmup {
+        let input = setup();
+        black_box(f(input));
+    }
+
+    let mut reservoir = sample_reservoir();
+    let mut total_ns: u128 = 0;
+    for _ in 0..run_iters {
+        let input = setup();
+        let start = Instant::now();
+        let output = black_box(f(input));
+        let ns = start.elapsed().as_nanos();
+        total_ns += ns;
+        drop(output);
+        if let Some(r) = &mut reservoir {
+            r.push(ns as u64);
+        }
+    }
+    record(total_ns, reservoir.and_then(|mut r| r.take(1)));
+
+    let denom = run_iters.max(1) as f64;
+    let ns_per_iter = (total_ns as f64) / denom;
+
+    Measured {
+        iters,
+        warmup_iters,
+        total_ns,
+        ns_per_iter,
+    }
+}
+
+/// Two functions timed in alternating blocks; see [`measure_paired`].
+#[derive(Clone, Debug)]
+pub struct PairedMeasured {
+    pub a: Measured,
+    pub b: Measured,
+    /// ns/iter of each block, in block order.
+    pub blocks_a: Vec<f64>,
+    pub blocks_b: Vec<f64>,
+}
+
+/// Paired comparison of B against A over the blocks of a [`PairedMeasured`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct PairedStats {
+    pub blocks: usize,
+    /// Blocks where B was faster / slower than A in the same block.
+    pub b_faster: u64,
+    pub b_slower: u64,
+    pub ties: u64,
+    /// Median and mean over blocks of `(b - a) / a`; negative means B is faster.
+    pub median_delta_ratio: f64,
+    pub mean_delta_ratio: f64,
+    /// Two-sided sign test on faster/slower counts (ties dropped).
+    pub sign_test_p: f64,
+}
+
+impl PairedMeasured {
+    pub fn stats(&self) -> PairedStats {
+        let mut deltas: Vec<f64> = self
+            .blocks_a
+            .iter()
+            .zip(&self.blocks_b)
+            .filter(|(a, _)| **a > 0.0)
+            .map(|(a, b)| (b - a) / a)
+            .collect();
+        let b_faster = deltas.iter().filter(|d| **d < 0.0).count() as u64;
+        let b_slower = deltas.iter().filter(|d| **d > 0.0).count() as u64;
+        deltas.sort_by(f64::total_cmp);
+        let n = deltas.len();
+        let median_delta_ratio = match n {
+            0 => 0.0,
+            _ if n % 2 == 1 => deltas[n / 2],
+            _ => (deltas[n / 2 - 1] + deltas[n / 2]) / 2.0,
+        };
+        PairedStats {
+            blocks: self.blocks_a.len(),
+            b_faster,
+            b_slower,
+            ties: n as u64 - b_faster - b_slower,
+            median_delta_ratio,
+            mean_delta_ratio: deltas.iter().sum::<f64>() / n.max(1) as f64,
+            sign_test_p: sign_test_p(b_faster, b_slower),
+        }
+    }
+}
+
+/// Two-sided exact sign test: the probability of a split at least as uneven as
+/// `x` vs `y` if either outcome were equally likely.
+pub fn sign_test_p(x: u64, y: u64) -> f64 {
+    let n = x + y;
+    let k = x.min(y);
+    // P(X <= k) for X ~ Binomial(n, 1/2), term by term in log space so large n does
+    // not underflow 0.5^n before the binomial coefficient is applied.
+    let ln_half_n = n as f64 * 0.5f64.ln();
+    let mut ln_choose = 0.0;
+    let mut tail = 0.0;
+    for i in 0..=k {
+        if i > 0 {
+            ln_choose += ((n - i + 1) as f64).ln() - (i as f64).ln();
+        }
+        tail += (ln_choose + ln_half_n).exp();
+    }
+    (2.0 * tail).min(1.0)
+}
+
+/// Time `a` and `b` against each other in `blocks` alternating blocks of `block_iters`.
+///
+/// Both sides run within the same time window, block by block, so drift (thermal,
+/// background load) hits them alike and cancels in the per-block comparison. The order
+/// within a block alternates (AB, BA, ...) so neither side always runs on a warm cache
+/// left by the other. Both get the same iteration indices, counting from 0 across
+/// blocks as in [`measure_fn_indexed`].
+pub fn measure_paired<TA, TB>(
+    blocks: u64,
+    block_iters: u64,
+    warmup_iters: u64,
+    mut a: impl FnMut(u64) -> TA,
+    mut b: impl FnMut(u64) -> TB,
+) -> PairedMeasured {
+    cool_down();
+    // Calibration samples one block, cut like any other measurement.
+    let (run_block_iters, run_warmup) = sampled(block_iters, warmup_iters);
+    let run_blocks = match CALIBRATION.get() {
+        Some(_) => blocks.min(1),
+        None => blocks,
+    };
+    for i in 0..run_warmup {
+        black_box(a(i));
+        black_box(b(i));
+    }
+
+    let mut blocks_a = Vec::with_capacity(run_blocks as usize);
+    let mut blocks_b = Vec::with_capacity(run_blocks as usize);
+    let (mut total_a, mut total_b) = (0u128, 0u128);
+    let denom = run_block_iters.max(1) as f64;
+    for block in 0..run_blocks {
+        let first = block * run_block_iters;
+        let time = |f: &mut dyn FnMut(u64)| {
+            let start = Instant::now();
+            for i in first..first + run_block_iters {
+                f(i);
+            }
+            start.elapsed().as_nanos()
+        };
+        let mut run_a = |i| {
+            black_box(a(i));
+        };
+        let mut run_b = |i| {
+            black_box(b(i));
+        };
+        let (ns_a, ns_b) = if block % 2 == 0 {
+            let ns_a = time(&mut run_a);
+            (ns_a, time(&mut run_b))
+        } else {
+            let ns_b = time(&mut run_b);
+            (time(&mut run_a), ns_b)
+        };
+        total_a += ns_a;
+        total_b += ns_b;
+        blocks_a.push(ns_a as f64 / denom);
+        blocks_b.push(ns_b as f64 / denom);
+    }
+
+    let run_iters = run_blocks * run_block_iters;
+    let side = |total_ns: u128| Measured {
+        iters: blocks * block_iters,
+        warmup_iters,
+        total_ns,
+        ns_per_iter: total_ns as f64 / run_iters.max(1) as f64,
+    };
+    PairedMeasured {
+        a: side(total_a),
+        b: side(total_b),
+        blocks_a,
+        blocks_b,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// Sleeps on drop so teardown cost is observable.
+    struct SlowDrop;
+
+    impl Drop for SlowDrop {
+        fn drop(&mut self) {
+            std::thread::sleep(Duration::from_millis(2));
+        }
+    }
+
+    #[test]
+    fn test_indexed_rotation_is_even() {
+        let k = 4;
+        let mut hits = vec![0u64; k];
+        let m = measure_fn_indexed(300, 32, |i| hits[(i % k as u64) as usize] += 1);
+        assert_eq!(m.iters, 300);
+        // 32 warmup + 300 measured iterations, each phase starting at index 0.
+        assert_eq!(hits, vec![83, 83, 83, 83]);
+    }
+
+    #[test]
+    fn test_paired_blocks_and_sign_test() {
+        let mut seen = (Vec::new(), Vec::new());
+        let m = measure_paired(
+            4,
+            3,
+            2,
+            |i| seen.0.push(i),
+            |i| {
+                seen.1.push(i);
+                std::thread::sleep(Duration::from_micros(200));
+            },
+        );
+        // Warmup indices, then every block index once for each side.
+        assert_eq!(seen.0, [0, 1, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11]);
+        assert_eq!(seen.0, seen.1);
+        assert_eq!((m.a.iters, m.blocks_a.len()), (12, 4));
+
+        let s = m.stats();
+        assert_eq!((s.b_faster, s.b_slower, s.ties), (0, 4, 0));
+        assert!(s.median_delta_ratio > 1.0, "{s:?}");
+        assert!((s.sign_test_p - 0.125).abs() < 1e-12);
+
+        assert_eq!(sign_test_p(0, 0), 1.0);
+        assert_eq!(sign_test_p(5, 5), 1.0);
+        assert!((sign_test_p(9, 1) - 22.0 / 1024.0).abs() < 1e-12);
+        assert!(sign_test_p(2000, 0) == 0.0 && sign_test_p(0, 2000).is_finite());
+    }
+
+    #[test]
+    fn test_cost_class_counts() {
+        let quick = BenchConfig {
+            profile: Profile::Quick,
+            seed: 0,
+        };
+        let full = BenchConfig {
+            profile: Profile::Full,
+            ..quick.clone()
+        };
+        let table: Vec<_> = [Cost::Micro, Cost::Macro, Cost::OneShot]
+            .into_iter()
+            .map(|c| (quick.counts(c), full.counts(c)))
+            .collect();
+        assert_eq!(
+            table,
+            [
+                ((300, 32), (3_000, 200)),
+                ((2, 1), (5, 1)),
+                ((1, 0), (3, 0)),
+            ]
+        );
+
+        let mut calls = 0;
+        let m = measure_once(|| calls += 1);
+        assert_eq!((m.iters, m.warmup_iters, calls), (1, 0, 1));
+        assert_eq!(m.ns_per_iter, m.total_ns as f64);
+        let m = measure_n_no_warmup(3, || calls += 1);
+        assert_eq!((m.iters, m.warmup_iters, calls), (3, 0, 4));
+        {
+            let _c = calibration(0);
+            let m = measure_once(|| panic!("ran"));
+            assert_eq!(m.iters, 1);
+        }
+    }
+
+    #[test]
+    fn test_calibration_samples_but_reports_plan() {
+        let mut calls = 0;
+        {
+            let _c = calibration(3);
+            let m = measure_fn(300, 32, || calls += 1);
+            assert_eq!((m.iters, m.warmup_iters, calls), (300, 32, 4));
+
+            let sleep = |_| std::thread::sleep(Duration::from_millis(1));
+            let m = measure_fn_with_setup(10, 0, || (), sleep);
+            assert_eq!(m.iters, 10);
+            assert!(m.ns_per_iter >= 1_000_000.0 && m.total_ns < 10_000_000);
+
+            let p = measure_paired(30, 10, 4, |i| i, |i| i);
+            assert_eq!((p.a.iters, p.blocks_a.len()), (300, 1));
+
+            // Zero samples still walk the bench; nested guards restore in order.
+            let _zero = calibration(0);
+            let m = measure_fn_indexed(50, 5, |_| panic!("ran"));
+            assert_eq!((m.iters, m.warmup_iters), (50, 5));
+        }
+        let m = measure_fn(5, 0, || calls += 1);
+        assert_eq!((m.iters, calls), (5, 9));
+    }
+
+    #[test]
+    fn test_cooldown_pauses_between_measurements() {
+        let pause = Duration::from_millis(40);
+        let guard = cooldown(Cooldown {
+            pause_ms: 40,
+            ..Cooldown::default()
+        });
+        let start = Instant::now();
+        measure_fn(1, 0, || ());
+        let first = start.elapsed();
+        measure_fn_indexed(1, 0, |_| ());
+        assert!(start.elapsed() >= pause, "{:?}", start.elapsed());
+        assert!(first < pause, "first measurement paused: {first:?}");
+        let report = guard.report();
+        assert_eq!((report.pause_ms, report.pauses), (40, 1));
+        assert!(report.waited_ms >= 40);
+
+        // Calibration samples skip the pause; dropping the guard turns it off.
+        {
+            let _c = calibration(1);
+            measure_fn(1, 0, || ());
+        }
+        assert_eq!(guard.report().pauses, 1);
+        drop(guard);
+        let start = Instant::now();
+        measure_fn(1, 0, || ());
+        measure_fn(1, 0, || ());
+        assert!(start.elapsed() < pause);
+    }
+
+    #[test]
+    fn test_setup_excluded_from_timing() {
+        let mut setups = 0;
+        let m = measure_fn_with_setup(
+            10,
+            2,
+            || {
+                setups += 1;
+                std::thread::sleep(Duration::from_millis(2));
+                vec![1u64; 64]
+            },
+            |v| v.iter().sum::<u64>(),
+        );
+        assert_eq!(setups, 12);
+        assert_eq!(m.iters, 10);
+        // 2ms of setup per iteration would put this well above 1ms.
+        assert!(
+            m.ns_per_iter < 1_000_000.0,
+            "setup leaked into timing: {}",
+            m.ns_per_iter
+        );
+    }
+
+    #[test]
+    fn test_output_drop_excluded_from_timing() {
+        let m = measure_fn_with_setup(5, 0, || (), |_| SlowDrop);
+        assert!(
+            m.ns_per_iter < 1_000_000.0,
+            "drop leaked into timing: {}",
+            m.ns_per_iter
+        );
+
+        // Sanity check: the same work under measure_fn does pay for the drop.
+        let m = measure_fn(5, 0, || {
+            drop(SlowDrop);
+        });
+        assert!(m.ns_per_iter >= 2_000_000.0);
+    }
+
+    #[test]
+    fn test_reservoir_cap() {
+        let mut r = Reservoir::new(8);
+        for ns in 0..1000 {
+            r.push(ns);
+        }
+        let s = r.take(1).unwrap();
+        assert_eq!((s.batches, s.ns.len()), (1000, 8));
+        // A uniform sample, not just the first eight.
+        assert!(s.ns.iter().any(|&ns| ns >= 8), "{:?}", s.ns);
+        assert!(r.take(1).is_none());
+
+        let mut r = Reservoir::new(8);
+        (0..5).for_each(|ns| r.push(ns));
+        assert_eq!(r.take(1).unwrap().ns, [0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_recorded_samples_sum_to_total() {
+        let recording = record_samples(16);
+        let m = measure_fn(100, 0, || std::thread::sleep(Duration::from_micros(50)));
+        let w = measure_fn_with_setup(
+            40,
+            0,
+            || (),
+            |_| std::thread::sleep(Duration::from_micros(50)),
+        );
+        let mut ms: Vec<Measurement> = [("a", &m), ("b", &w)]
+            .into_iter()
+            .map(|(name, m)| Measurement {
+                name: name.to_string(),
+                unit: "ns/iter".to_string(),
+                iters: m.iters,
+                warmup_iters: 0,
+                total_ns: m.total_ns,
+                ns_per_iter: m.ns_per_iter,
+                bytes_processed: None,
+                throughput_bytes_per_s: None,
+                extra: serde_json::json!({}),
+                tags: Default::default(),
+            })
+            .collect();
+        recording.attach(&mut ms);
+        drop(recording);
+
+        let samples: Samples = serde_json::from_value(ms[0].extra["samples"].clone()).unwrap();
+        // 100 iterations in batches of ceil(100 / 16) = 7: 15 batches, the last of 2.
+        assert_eq!(
+            (samples.batch_iters, samples.batches, samples.ns.len()),
+            (7, 15, 15)
+        );
+        let sum: u64 = samples.ns.iter().sum();
+        assert!(
+            sum as u128 <= m.total_ns && m.total_ns - sum as u128 <= m.total_ns / 10,
+            "{sum} vs {}",
+            m.total_ns
+        );
+
+        // Per-iteration timings beyond the cap are reservoir-sampled.
+        let samples: Samples = serde_json::from_value(ms[1].extra["samples"].clone()).unwrap();
+        assert_eq!(
+            (samples.batch_iters, samples.batches, samples.ns.len()),
+            (1, 40, 16)
+        );
+        let mean = samples.ns.iter().sum::<u64>() as f64 / 16.0;
+        assert!(
+            (mean - w.ns_per_iter).abs() <= w.ns_per_iter / 2.0,
+            "{mean} vs {}",
+            w.ns_per_iter
+        );
+
+        // Timings are claimed in report order: a measurement derived from the previous
+        // one's timing gets nothing, and equal totals of separate timings stay apart.
+        let recording = record_samples(4);
+        let timing = |total_ns: u128, batches: u64| {
+            let samples = Samples {
+                batch_iters: 1,
+                batches,
+                ns: vec![1],
+            };
+            (total_ns, samples)
+        };
+        SAMPLES.with_borrow_mut(|log| {
+            log.as_mut().unwrap().recorded =
+                vec![timing(7, 1), timing(9, 2), timing(5, 3), timing(5, 4)];
+        });
+        let named = |name: &str, total_ns: u128| Measurement {
+            name: name.to_string(),
+            total_ns,
+            extra: serde_json::json!({}),
+            ..ms[0].clone()
+        };
+        let mut shared = vec![
+            named("index.build", 7),
+            named("index.add", 7),
+            named("index.finalize", 9),
+            named("a", 5),
+            named("b", 5),
+        ];
+        recording.attach(&mut shared);
+        drop(recording);
+        let batches: Vec<Option<u64>> = shared
+            .iter()
+            .map(|m| {
+                m.extra
+                    .get("samples")
+                    .map(|s| s["batches"].as_u64().unwrap())
+            })
+            .collect();
+        assert_eq!(batches, [Some(1), None, Some(2), Some(3), Some(4)]);
+
+        // Nothing is recorded without the guard.
+        let m = measure_fn(10, 0, || ());
+        let mut ms = vec![Measurement {
+            total_ns: m.total_ns.max(1),
+            ..ms[0].clone()
+        }];
+        ms[0].extra = serde_json::json!({});
+        record_samples(4).attach(&mut ms);
+        assert!(ms[0].extra.get("samples").is_none());
+    }
+}
@@ -0,0 +1,61 @@
+//! Ctrl-C as a request to stop a long phase early with partial results.
+//!
+//! While an [`InterruptGuard`] is alive, the first SIGINT only sets a flag, which the
+//! phase polls between units of work ([`interrupted`]). The handler then restores the
+//! default disposition, so a second Ctrl-C kills the process as usual; dropping the
+//! guard restores it too. Off unix, Ctrl-C is left alone and [`interrupted`] is always
+//! false.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+#[cfg(unix)]
+mod sys {
+    use libc::{c_int, sighandler_t, signal, SIGINT, SIG_DFL};
+    use std::sync::atomic::Ordering;
+
+    extern "C" fn on_sigint(_: c_int) {
+        super::INTERRUPTED.store(true, Ordering::SeqCst);
+        // `signal` is async-signal-safe.
+        unsafe { signal(SIGINT, SIG_DFL) };
+    }
+
+    pub fn install() {
+        let handler: extern "C" fn(c_int) = on_sigint;
+        unsafe { signal(SIGINT, handler as sighandler_t) };
+    }
+
+    pub fn restore() {
+        unsafe { signal(SIGINT, SIG_DFL) };
+    }
+}
+
+#[cfg(not(unix))]
+mod sys {
+    pub fn install() {}
+
+    pub fn restore() {}
+}
+
+/// Turns the first Ctrl-C into [`interrupted`] until dropped.
+pub struct InterruptGuard(());
+
+impl InterruptGuard {
+    pub fn install() -> Self {
+        INTERRUPTED.store(false, Ordering::SeqCst);
+        sys::install();
+        Self(())
+    }
+}
+
+impl Drop for InterruptGuard {
+    fn drop(&mut self) {
+        sys::restore();
+    }
+}
+
+/// Whether Ctrl-C was pressed since the current guard was installed.
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
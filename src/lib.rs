@@ -1,12 +1,74 @@
+//! Contract benchmarks for embeddenator, as a library.
+//!
+//! The `embeddenator_contract_bench` binary is a thin clap layer over this crate; other
+//! tools can call the runners directly and get [`Measurement`]s back. The stable entry
+//! points are:
+//!
+//! - [`run_suite`] with a [`SuiteSpec`]: the `suite` subcommand, returning its report.
+//! - [`registry::Registry`] and [`registry::Bench`], with [`suite::run_suite_with`]: the
+//!   suite's sections, to add benches of your own to.
+//! - [`benches::vsa::run`] and [`benches::vsa::run_dataset`]: `vsa` and `vsa --dataset`.
+//! - [`benches::retrieval::run`] and [`benches::encode::run`].
+//! - The `_with_sink` forms of those runners, which send each measurement to a
+//!   [`sink::MeasurementSink`] as it is taken, and the sinks in [`sink`].
+//! - [`BenchConfig`] and [`Profile`], plus [`harness::calibration`] to cut every timed
+//!   loop short (as `--dry-run` does).
+//! - The report types [`ContractBenchReport`], [`RunMeta`] and [`Measurement`], whose
+//!   JSON form is versioned by `schema_version`. Measurement names are versioned by
+//!   [`measurements::NAMESPACE_VERSION`].
+//! - [`error::BenchError`]: what kind of failure an `io::Error` from these runners is,
+//!   via [`error::BenchError::of`].
+//!
+//! Everything else is public for the binary and may change between releases.
+
 use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
 
+pub mod assertions;
+pub mod atomic_write;
 pub mod benches;
+pub mod budget;
+pub mod checkpoint;
+pub mod compare;
+pub mod confidence;
+pub mod cosine_values;
+pub mod criterion_import;
 pub mod dataset;
+pub mod dedupe;
+pub mod disk_space;
+pub mod environment;
+pub mod error;
+pub mod gzip;
 pub mod harness;
+pub mod interrupt;
+pub mod measurements;
+pub mod plan;
+pub mod ratios;
+pub mod registry;
 pub mod schema;
+pub mod sink;
+pub mod status;
+pub mod suite;
+pub mod summary;
+pub mod table;
+pub mod tidy;
+pub mod trend;
+pub mod vsa_config;
+
+pub use harness::{BenchConfig, Profile};
+pub use schema::{ContractBenchReport, Measurement, RunMeta};
+pub use suite::{run_suite, run_suite_with, SuiteSpec};
+
+/// embeddenator version this crate was built against (from Cargo.lock; `unknown` if absent).
+pub const EMBEDDENATOR_VERSION: &str = env!("EMBEDDENATOR_VERSION");
+
+/// embeddenator commit this crate was built against, when it is a git dependency (from
+/// Cargo.lock; `None` for a path or registry dependency).
+pub const EMBEDDENATOR_REV: Option<&str> = option_env!("EMBEDDENATOR_REV");
 
 /// VSA substrate variant to benchmark.
-#[derive(Clone, Copy, Debug, Default, ValueEnum, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, Default, ValueEnum, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum VsaVariant {
     /// Run all VSA substrate benchmarks (packed, bitsliced, hybrid, block-sparse).
     #[default]
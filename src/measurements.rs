@@ -0,0 +1,239 @@
+//! Measurement names: the identifiers reports are keyed on by `compare`, `trend` and
+//! every dashboard built on them.
+//!
+//! Benches take their names from here instead of spelling them inline. Fixed names are
+//! constants; families with a parameter (chain lengths, frontier points, codecs) are
+//! functions. The full set each subcommand emits is snapshotted in
+//! `tests/fixtures/measurement_names.txt`, which records [`NAMESPACE_VERSION`]: renaming,
+//! adding or removing a name fails the `schema_contract` test until the snapshot is
+//! regenerated, and regenerating it needs the version bumped. Reports carry the version
+//! in `RunMeta::measurement_namespace_version`, so compare can tell that names missing
+//! on one side may have been renamed rather than dropped.
+
+/// Version of the measurement name set; bump on any rename, addition or removal.
+pub const NAMESPACE_VERSION: u32 = 17;
+
+/// Suffix of the derived `<name>.ops_per_s` throughput measurements.
+pub const OPS_PER_S_SUFFIX: &str = ".ops_per_s";
+
+/// Fixed-input microbenches (`vsa`).
+pub mod vsa {
+    pub const SPARSEVEC_BUNDLE: &str = "vsa.sparsevec.bundle";
+    pub const SPARSEVEC_BIND: &str = "vsa.sparsevec.bind";
+    pub const SPARSEVEC_COSINE: &str = "vsa.sparsevec.cosine";
+    pub const SPARSEVEC_COSINE_DISJOINT: &str = "vsa.sparsevec.cosine_disjoint";
+    pub const SPARSEVEC_DOT: &str = "vsa.sparsevec.dot";
+    pub const SPARSEVEC_HAMMING_AGREEMENT: &str = "vsa.sparsevec.hamming_agreement";
+    pub const PACKED_BUNDLE: &str = "vsa.packed.bundle";
+    pub const PACKED_BIND: &str = "vsa.packed.bind";
+    pub const PACKED_DOT: &str = "vsa.packed.dot";
+    pub const PACKED_DOT_DISJOINT: &str = "vsa.packed.dot_disjoint";
+    pub const BITSLICED_BUNDLE: &str = "vsa.bitsliced.bundle";
+    pub const BITSLICED_BIND: &str = "vsa.bitsliced.bind";
+    pub const BITSLICED_COSINE: &str = "vsa.bitsliced.cosine";
+    pub const BITSLICED_COSINE_DISJOINT: &str = "vsa.bitsliced.cosine_disjoint";
+    pub const HYBRID_CARRY_SAVE_BUNDLE_3: &str = "vsa.hybrid.carry_save_bundle_3";
+    pub const BLOCKSPARSE_BIND: &str = "vsa.blocksparse.bind";
+    pub const BLOCKSPARSE_BUNDLE: &str = "vsa.blocksparse.bundle";
+    pub const BLOCKSPARSE_DOT: &str = "vsa.blocksparse.dot";
+    pub const BLOCKSPARSE_COSINE: &str = "vsa.blocksparse.cosine";
+    pub const BLOCKSPARSE_COSINE_DISJOINT: &str = "vsa.blocksparse.cosine_disjoint";
+    pub const BLOCKSPARSE_BUNDLE_MANY_3: &str = "vsa.blocksparse.bundle_many_3";
+    pub const CONTRACT_BUNDLE_SEMANTICS: &str = "vsa.contract.bundle_semantics";
+    pub const CONTRACT_BUNDLE_CAPACITY: &str = "vsa.contract.bundle_capacity";
+    pub const CONTRACT_ROUNDTRIP: &str = "vsa.contract.roundtrip";
+
+    /// `vsa.<substrate>.<op>_chain_<n>`: a fold over `n` inputs.
+    pub fn chain(substrate: &str, op: &str, n: usize) -> String {
+        format!("vsa.{substrate}.{op}_chain_{n}")
+    }
+
+    /// `vsa.hybrid.refinalize_after_<n>`.
+    pub fn refinalize_after(n: usize) -> String {
+        format!("vsa.hybrid.refinalize_after_{n}")
+    }
+
+    /// `vsa.sparsevec.roundtrip_fidelity.<op>_<size>`.
+    pub fn roundtrip_fidelity(op: &str, size: &str) -> String {
+        format!("vsa.sparsevec.roundtrip_fidelity.{op}_{size}")
+    }
+
+    /// `vsa.<substrate>.serialized_bytes<suffix>`.
+    pub fn serialized_bytes(substrate: &str, suffix: &str) -> String {
+        format!("vsa.{substrate}.serialized_bytes{suffix}")
+    }
+
+    /// `vsa.<substrate>.<op>` for the serialize/deserialize timings.
+    pub fn serialization(substrate: &str, op: &str) -> String {
+        format!("vsa.{substrate}.{op}")
+    }
+
+    /// `vsa.ratio.<subject>_vs_<baseline>.<op>`: a derived speedup (see
+    /// [`crate::ratios`]).
+    pub fn ratio(subject: &str, baseline: &str, op: &str) -> String {
+        format!("vsa.ratio.{subject}_vs_{baseline}.{op}")
+    }
+
+    /// `vsa.<substrate>.dot_batch_1x<n>`: one query against a block of `n`.
+    pub fn dot_batch(substrate: &str, n: usize) -> String {
+        format!("vsa.{substrate}.dot_batch_1x{n}")
+    }
+
+    /// `vsa.<substrate>.bundle_batch_<n>`: a block of `n` bundled into one.
+    pub fn bundle_batch(substrate: &str, n: usize) -> String {
+        format!("vsa.{substrate}.bundle_batch_{n}")
+    }
+
+    /// `vsa.blocksparse.bundle_many_<n>_<regime>`: `n` inputs sharing blocks or not.
+    pub fn bundle_many_regime(n: usize, regime: &str) -> String {
+        format!("vsa.blocksparse.bundle_many_{n}_{regime}")
+    }
+
+    /// `vsa.hybrid.carry_save_bundle_<n>_<regime>`: the same inputs through the
+    /// carry-save accumulator.
+    pub fn carry_save_regime(n: usize, regime: &str) -> String {
+        format!("vsa.hybrid.carry_save_bundle_{n}_{regime}")
+    }
+
+    /// `vsa.<substrate>.<op>.density_<label>`: an op at one `--density-sweep` density.
+    pub fn density(substrate: &str, op: &str, label: &str) -> String {
+        format!("vsa.{substrate}.{op}.density_{label}")
+    }
+
+    /// `vsa.coldstart.<op>`: the first execution of an op in the process
+    /// (`--cold-start`).
+    pub fn coldstart(op: &str) -> String {
+        format!("vsa.coldstart.{op}")
+    }
+
+    /// `<name>.<class>`: a microbench run on a non-default `--input-class`.
+    pub fn with_input_class(name: &str, class: &str) -> String {
+        format!("{name}.{class}")
+    }
+}
+
+/// Dataset-driven benches (`vsa --dataset`, `dataset-bench`).
+pub mod vsa_dataset {
+    pub const SPARSEVEC_BUNDLE: &str = "vsa_dataset.sparsevec.bundle";
+    pub const SPARSEVEC_BIND: &str = "vsa_dataset.sparsevec.bind";
+    pub const SPARSEVEC_COSINE: &str = "vsa_dataset.sparsevec.cosine";
+    pub const SPARSEVEC_DOT: &str = "vsa_dataset.sparsevec.dot";
+    pub const SPARSEVEC_HAMMING_AGREEMENT: &str = "vsa_dataset.sparsevec.hamming_agreement";
+    /// [`SPARSEVEC_COSINE`] under `--zero-copy`: this crate's cosine over borrowed
+    /// `SparseVecRef` views rather than embeddenator's, so a series of its own.
+    pub const SPARSEVEC_REF_COSINE: &str = "vsa_dataset.sparsevec_ref.cosine";
+    pub const PACKED_BUNDLE: &str = "vsa_dataset.packed.bundle";
+    pub const PACKED_BIND: &str = "vsa_dataset.packed.bind";
+    pub const PACKED_DOT: &str = "vsa_dataset.packed.dot";
+    pub const BITSLICED_BUNDLE: &str = "vsa_dataset.bitsliced.bundle";
+    pub const BITSLICED_BIND: &str = "vsa_dataset.bitsliced.bind";
+    pub const BITSLICED_COSINE: &str = "vsa_dataset.bitsliced.cosine";
+    pub const HYBRID_CARRY_SAVE_BUNDLE_3: &str = "vsa_dataset.hybrid.carry_save_bundle_3";
+    pub const BLOCKSPARSE_BIND: &str = "vsa_dataset.blocksparse.bind";
+    pub const BLOCKSPARSE_BUNDLE: &str = "vsa_dataset.blocksparse.bundle";
+    pub const BLOCKSPARSE_COSINE: &str = "vsa_dataset.blocksparse.cosine";
+    pub const BLOCKSPARSE_BUNDLE_MANY_3: &str = "vsa_dataset.blocksparse.bundle_many_3";
+    pub const READER_SCAN: &str = "vsa_dataset.reader.scan";
+
+    /// Prefix of the SparseVec ops, [`sparsevec`] included.
+    pub const SPARSEVEC_PREFIX: &str = "vsa_dataset.sparsevec.";
+
+    /// `vsa_dataset.sparsevec.<op>`.
+    pub fn sparsevec(op: &str) -> String {
+        format!("{SPARSEVEC_PREFIX}{op}")
+    }
+
+    /// `vsa_dataset.<substrate>.convert_batch<suffix>`.
+    pub fn convert_batch(substrate: &str, suffix: &str) -> String {
+        format!("vsa_dataset.{substrate}.convert_batch{suffix}")
+    }
+
+    /// `vsa_dataset.reader.scan_buffer_<kib>k`: the reader scan at one buffer size.
+    pub fn reader_scan_buffer(kib: usize) -> String {
+        format!("vsa_dataset.reader.scan_buffer_{kib}k")
+    }
+}
+
+/// `generate-dataset --report`.
+pub mod dataset {
+    pub const GENERATE_VECTORS_PER_S: &str = "dataset.generate.vectors_per_s";
+    pub const GENERATE_BYTES_PER_S: &str = "dataset.generate.bytes_per_s";
+    pub const GENERATE_WALL_S: &str = "dataset.generate.wall_s";
+}
+
+/// `dataset-bench-formats`: one set per on-disk format.
+pub mod dataset_formats {
+    /// `dataset_formats.<format>.<stage>`, stage `write`, `scan` or `packed_bind`.
+    pub fn stage(format: &str, stage: &str) -> String {
+        format!("dataset_formats.{format}.{stage}")
+    }
+}
+
+/// `encode`.
+pub mod encode {
+    pub const INGEST: &str = "encode.ingest";
+    pub const VERIFY_ROUNDTRIP: &str = "encode.verify_roundtrip";
+    pub const EXTRACT_FULL: &str = "encode.extract_full";
+    pub const EXTRACT_SUBSET: &str = "encode.extract_subset";
+    pub const LOAD_ENGRAM: &str = "encode.load_engram";
+    pub const SAVE_MANIFEST: &str = "encode.save_manifest";
+    pub const LOAD_MANIFEST: &str = "encode.load_manifest";
+
+    /// `encode.wrap.<codec label>`.
+    pub fn wrap(label: &str) -> String {
+        format!("encode.wrap.{label}")
+    }
+
+    /// `encode.save_engram.<codec label>`.
+    pub fn save_engram(label: &str) -> String {
+        format!("encode.save_engram.{label}")
+    }
+}
+
+/// `retrieval`.
+pub mod retrieval {
+    /// One timed pass of indexed queries; ground truth is computed outside the timing.
+    /// Before namespace 16 this was `retrieval.query_codebook_with_index`, whose time
+    /// also included the brute-force ground truth.
+    pub const QUERY_PASS_WITH_INDEX: &str = "retrieval.query_pass_with_index";
+
+    /// Prefix shared by the per-point measurements of `retrieval --frontier`.
+    pub const FRONTIER_PREFIX: &str = "retrieval.frontier.";
+
+    /// `retrieval.frontier.cf<N>`.
+    pub fn frontier(candidate_factor: usize) -> String {
+        format!("{FRONTIER_PREFIX}cf{candidate_factor}")
+    }
+
+    /// `retrieval.concurrency.c<N>`.
+    pub fn concurrency(workers: usize) -> String {
+        format!("retrieval.concurrency.c{workers}")
+    }
+}
+
+/// `index`.
+pub mod index {
+    pub const BUILD: &str = "index.build";
+    /// Amortized over the `index.build` pass.
+    pub const ADD: &str = "index.add";
+    pub const FINALIZE: &str = "index.finalize";
+    /// Build from empty plus finalize.
+    pub const REBUILD: &str = "index.rebuild";
+    pub const QUERY_TOP_K: &str = "index.query_top_k";
+}
+
+/// `duel`.
+pub mod duel {
+    /// `duel.<op>`.
+    pub fn op(op: &str) -> String {
+        format!("duel.{op}")
+    }
+}
+
+/// `soak`.
+pub mod soak {
+    /// `soak.<op>`: one sustained run, its snapshots in extra.
+    pub fn op(op: &str) -> String {
+        format!("soak.{op}")
+    }
+}
@@ -0,0 +1,186 @@
+//! `--dry-run` plans: what a run would measure, and roughly how long it would take.
+//!
+//! Benches are walked for real under [`crate::harness::calibration`], which cuts every
+//! harness measurement to a few timed iterations. The measurements that come out carry
+//! the planned iteration counts and a sampled per-iteration cost, which together give
+//! each one's estimated time. The dataset streaming benches time their own loops over
+//! every requested pair, and encode and retrieval ingest their whole corpus (retrieval
+//! then brute-forces its ground truth) before anything is timed, so walking them would
+//! run them in full; they are listed as unplanned instead.
+
+use crate::schema::Measurement;
+use crate::summary::format_ns;
+use crate::table::{Column, Table};
+use serde::{Deserialize, Serialize};
+
+/// Why the dataset streaming benches are unplanned.
+pub const STREAMED: &str = "times its own loop over every dataset pair; not sampled";
+
+/// Why encode and retrieval are unplanned.
+pub const INGESTS: &str = "ingests its whole corpus outside the harness; not sampled";
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PlanEntry {
+    pub name: String,
+    pub unit: String,
+    pub iters: u64,
+    pub warmup_iters: u64,
+    /// Per-iteration cost from the calibration sample (`None` without calibration or
+    /// for non-time units).
+    pub sample_ns_per_iter: Option<f64>,
+    /// `sample_ns_per_iter * (iters + warmup_iters)`.
+    pub estimated_ns: Option<f64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnplannedSection {
+    pub section: String,
+    pub reason: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Plan {
+    pub subcommand: String,
+    pub profile: String,
+    /// Timed iterations sampled per measurement (0 = names and counts only).
+    pub calibration_iters: u64,
+    pub entries: Vec<PlanEntry>,
+    pub unplanned: Vec<UnplannedSection>,
+    /// Sum of the entries' estimates.
+    pub estimated_ns: f64,
+    /// How long walking the benches took, setup included. Setup outside the timed loops
+    /// (input generation, index builds) is not in the estimates but is paid again by the
+    /// real run.
+    pub calibration_wall_seconds: f64,
+}
+
+impl Plan {
+    pub fn new(subcommand: &str, profile: &str, calibration_iters: u64) -> Self {
+        Self {
+            subcommand: subcommand.to_string(),
+            profile: profile.to_string(),
+            calibration_iters,
+            entries: Vec::new(),
+            unplanned: Vec::new(),
+            estimated_ns: 0.0,
+            calibration_wall_seconds: 0.0,
+        }
+    }
+
+    /// Add the measurements a calibrated bench produced.
+    pub fn add(&mut self, measurements: &[Measurement]) {
+        for m in measurements {
+            let timed = self.calibration_iters > 0 && m.is_timing();
+            let sample = timed.then_some(m.ns_per_iter);
+            let estimated_ns = sample.map(|ns| ns * (m.iters + m.warmup_iters) as f64);
+            self.estimated_ns += estimated_ns.unwrap_or(0.0);
+            self.entries.push(PlanEntry {
+                name: m.name.clone(),
+                unit: m.unit.clone(),
+                iters: m.iters,
+                warmup_iters: m.warmup_iters,
+                sample_ns_per_iter: sample,
+                estimated_ns,
+            });
+        }
+    }
+
+    /// Record a section that is not walked.
+    pub fn unplanned(&mut self, section: &str, reason: &str) {
+        self.unplanned.push(UnplannedSection {
+            section: section.to_string(),
+            reason: reason.to_string(),
+        });
+    }
+
+    /// The plan table, grouped by name prefix like the run summary.
+    pub fn render(&self) -> String {
+        let mut table = Table::new(vec![
+            Column::left("measurement"),
+            Column::right("iters"),
+            Column::right("warmup"),
+            Column::right("sample/iter"),
+            Column::right("estimate"),
+        ]);
+        let mut section = None;
+        for e in &self.entries {
+            let prefix = e.name.split('.').next().unwrap_or_default();
+            if section != Some(prefix) {
+                table.section(format!("[{prefix}]"));
+                section = Some(prefix);
+            }
+            let ns = |v: Option<f64>| v.map(format_ns).unwrap_or_else(|| "-".to_string());
+            table.row([
+                e.name.clone(),
+                e.iters.to_string(),
+                e.warmup_iters.to_string(),
+                ns(e.sample_ns_per_iter),
+                ns(e.estimated_ns),
+            ]);
+        }
+
+        let mut out = format!(
+            "Dry run: {} ({} profile), {} measurement(s)\n",
+            self.subcommand,
+            self.profile,
+            self.entries.len()
+        );
+        if !table.is_empty() {
+            out.push_str(&table.render());
+        }
+        for u in &self.unplanned {
+            out.push_str(&format!("unplanned    {}: {}\n", u.section, u.reason));
+        }
+        if self.calibration_iters > 0 {
+            out.push_str(&format!(
+                "Estimated timed work: {} (walking took {:.2}s; untimed setup comes on top)\n",
+                format_ns(self.estimated_ns),
+                self.calibration_wall_seconds
+            ));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benches::vsa::{run, RunOptions};
+    use crate::harness::{calibration, BenchConfig, Profile};
+    use crate::VsaVariant;
+
+    #[test]
+    fn test_quick_vsa_plan() {
+        let cfg = BenchConfig {
+            profile: Profile::Quick,
+            seed: 0,
+        };
+        let ms = {
+            let _c = calibration(1);
+            run(&cfg, VsaVariant::Packed, &RunOptions::default())
+        };
+        let mut plan = Plan::new("vsa", "quick", 1);
+        plan.add(&ms);
+        plan.unplanned("vsa_dataset", "streams every pair");
+
+        let entry = |name: &str| plan.entries.iter().find(|e| e.name == name).unwrap();
+        for name in ["vsa.sparsevec.bind", "vsa.packed.bundle", "vsa.packed.dot"] {
+            let e = entry(name);
+            assert_eq!((e.iters, e.warmup_iters), (cfg.iters(), cfg.warmup_iters()));
+            assert!(e.estimated_ns.unwrap() > 0.0, "{name}");
+        }
+        assert!(plan
+            .entries
+            .iter()
+            .all(|e| !e.name.starts_with("vsa.bitsliced.")));
+        assert_eq!(entry("vsa.packed.serialized_bytes").estimated_ns, None);
+        assert!(plan.estimated_ns > 0.0);
+
+        let text = plan.render();
+        assert!(
+            text.contains("[vsa]") && text.contains("vsa.packed.dot"),
+            "{text}"
+        );
+        assert!(text.contains("unplanned    vsa_dataset"), "{text}");
+    }
+}
@@ -0,0 +1,202 @@
+//! Derived speedup measurements between substrates (`vsa.ratio.<a>_vs_<b>.<op>`).
+//!
+//! Each entry of [`RATIOS`] names two measurements of the same op; when a run produced
+//! both, [`derive`] adds one measurement (unit `ratio`) whose value is
+//! `baseline.ns_per_iter / subject.ns_per_iter`, i.e. how many times faster the subject
+//! is. Operands are matched by name, the first of each name winning. Adding a ratio is
+//! adding a row.
+
+use crate::measurements::{self, vsa};
+use crate::schema::{Measurement, UNIT_RATIO};
+use serde_json::json;
+use std::collections::BTreeMap;
+
+/// One derived ratio: `subject` against `baseline`, both measuring `op`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RatioDef {
+    pub subject_label: &'static str,
+    pub subject: &'static str,
+    pub baseline_label: &'static str,
+    pub baseline: &'static str,
+    pub op: &'static str,
+}
+
+impl RatioDef {
+    const fn new(
+        op: &'static str,
+        (subject_label, subject): (&'static str, &'static str),
+        (baseline_label, baseline): (&'static str, &'static str),
+    ) -> Self {
+        Self {
+            subject_label,
+            subject,
+            baseline_label,
+            baseline,
+            op,
+        }
+    }
+
+    pub fn name(&self) -> String {
+        measurements::vsa::ratio(self.subject_label, self.baseline_label, self.op)
+    }
+}
+
+/// The derived ratios, each against the SparseVec reference implementation.
+pub const RATIOS: &[RatioDef] = &[
+    RatioDef::new(
+        "bind",
+        ("packed", vsa::PACKED_BIND),
+        ("sparsevec", vsa::SPARSEVEC_BIND),
+    ),
+    RatioDef::new(
+        "bundle",
+        ("packed", vsa::PACKED_BUNDLE),
+        ("sparsevec", vsa::SPARSEVEC_BUNDLE),
+    ),
+    RatioDef::new(
+        "dot",
+        ("packed", vsa::PACKED_DOT),
+        ("sparsevec", vsa::SPARSEVEC_DOT),
+    ),
+    RatioDef::new(
+        "bind",
+        ("bitsliced", vsa::BITSLICED_BIND),
+        ("sparsevec", vsa::SPARSEVEC_BIND),
+    ),
+    RatioDef::new(
+        "bundle",
+        ("bitsliced", vsa::BITSLICED_BUNDLE),
+        ("sparsevec", vsa::SPARSEVEC_BUNDLE),
+    ),
+    RatioDef::new(
+        "cosine",
+        ("bitsliced", vsa::BITSLICED_COSINE),
+        ("sparsevec", vsa::SPARSEVEC_COSINE),
+    ),
+    RatioDef::new(
+        "bind",
+        ("blocksparse", vsa::BLOCKSPARSE_BIND),
+        ("sparsevec", vsa::SPARSEVEC_BIND),
+    ),
+    RatioDef::new(
+        "bundle",
+        ("blocksparse", vsa::BLOCKSPARSE_BUNDLE),
+        ("sparsevec", vsa::SPARSEVEC_BUNDLE),
+    ),
+    RatioDef::new(
+        "cosine",
+        ("blocksparse", vsa::BLOCKSPARSE_COSINE),
+        ("sparsevec", vsa::SPARSEVEC_COSINE),
+    ),
+    RatioDef::new(
+        "dot",
+        ("blocksparse", vsa::BLOCKSPARSE_DOT),
+        ("sparsevec", vsa::SPARSEVEC_DOT),
+    ),
+];
+
+/// The [`RATIOS`] whose operands are both timings in `ms`, in table order.
+pub fn derive(ms: &[Measurement]) -> Vec<Measurement> {
+    let mut by_name: BTreeMap<&str, &Measurement> = BTreeMap::new();
+    for m in ms.iter().filter(|m| m.is_timing()) {
+        by_name.entry(m.name.as_str()).or_insert(m);
+    }
+
+    let mut out = Vec::new();
+    for def in RATIOS {
+        let (Some(subject), Some(baseline)) = (by_name.get(def.subject), by_name.get(def.baseline))
+        else {
+            continue;
+        };
+        if subject.ns_per_iter <= 0.0 {
+            continue;
+        }
+        let mut extra = json!({
+            "subject": def.subject,
+            "baseline": def.baseline,
+            "subject_ns_per_iter": subject.ns_per_iter,
+            "baseline_ns_per_iter": baseline.ns_per_iter,
+        });
+        if let (Some(s), Some(b)) = (subject.confidence(), baseline.confidence()) {
+            extra["confidence"] = json!(s.min(b));
+        }
+        out.push(Measurement {
+            name: def.name(),
+            unit: UNIT_RATIO.to_string(),
+            iters: subject.iters.min(baseline.iters),
+            warmup_iters: 0,
+            total_ns: 0,
+            ns_per_iter: baseline.ns_per_iter / subject.ns_per_iter,
+            bytes_processed: None,
+            throughput_bytes_per_s: None,
+            extra,
+            tags: BTreeMap::new(),
+        });
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn m(name: &str, unit: &str, ns: f64) -> Measurement {
+        Measurement {
+            name: name.to_string(),
+            unit: unit.to_string(),
+            iters: 10,
+            warmup_iters: 1,
+            total_ns: (ns * 10.0) as u128,
+            ns_per_iter: ns,
+            bytes_processed: None,
+            throughput_bytes_per_s: None,
+            extra: json!({}),
+            tags: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_ratios_need_both_operands() {
+        let ms = vec![
+            m("vsa.sparsevec.bind", "ns/iter", 400.0),
+            m("vsa.sparsevec.bundle", "ns/iter", 900.0),
+            m("vsa.packed.bind", "ns/iter", 100.0),
+            // First of a name wins.
+            m("vsa.packed.bind", "ns/iter", 1.0),
+            m("vsa.packed.bundle", "ns/iter", 300.0),
+            // No vsa.sparsevec.dot: no packed dot ratio.
+            m("vsa.packed.dot", "ns/iter", 50.0),
+            // Not a timing.
+            m("vsa.bitsliced.bind", "bytes", 2000.0),
+        ];
+        let ratios = derive(&ms);
+        let names: Vec<&str> = ratios.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "vsa.ratio.packed_vs_sparsevec.bind",
+                "vsa.ratio.packed_vs_sparsevec.bundle"
+            ]
+        );
+
+        let bind = &ratios[0];
+        assert_eq!((bind.unit.as_str(), bind.ns_per_iter), ("ratio", 4.0));
+        assert_eq!(bind.extra["subject"], "vsa.packed.bind");
+        assert_eq!(bind.extra["baseline"], "vsa.sparsevec.bind");
+        assert_eq!(ratios[1].ns_per_iter, 3.0);
+        assert!(!bind.is_timing() && !bind.higher_is_better());
+
+        assert!(derive(&ms[..1]).is_empty());
+    }
+
+    #[test]
+    fn test_ratio_table_names_distinct() {
+        let mut names: Vec<String> = RATIOS.iter().map(RatioDef::name).collect();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), RATIOS.len());
+        for def in RATIOS {
+            assert!(def.subject.ends_with(def.op) && def.baseline.ends_with(def.op));
+        }
+    }
+}
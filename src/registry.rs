@@ -0,0 +1,269 @@
+//! Bench registration: what a suite run is made of.
+//!
+//! A [`Bench`] is one suite section: a name and a runner that sends its measurements to a
+//! [`MeasurementSink`]. A [`Registry`] holds them in run order. [`Registry::suite`] fills
+//! one with the built-in sections a [`SuiteSpec`] asks for; downstream crates register
+//! their own benches after those and run the lot with [`crate::suite::run_suite_with`],
+//! so adding measurements needs no patch to this crate.
+//!
+//! Section selection (`SuiteSpec::sections`), listing, `--dry-run` and `keep_going` work
+//! on the registry, so a registered bench is treated like a built-in one.
+//!
+//! Stable: [`Bench`], [`MeasurementSink`] (see [`crate::sink`]) and the [`Registry`]
+//! methods.
+
+use crate::benches::dataset_io::ScanBench;
+use crate::benches::index::IndexBench;
+use crate::benches::vsa::{DatasetBench, DatasetRunOptions, RunOptions, VsaBench};
+use crate::benches::{encode, retrieval};
+use crate::dataset::DatasetSource;
+use crate::harness::BenchConfig;
+use crate::suite::SuiteSpec;
+use std::io;
+
+pub use crate::sink::MeasurementSink;
+
+/// One suite section.
+pub trait Bench {
+    /// Section name: what `SuiteSpec::sections` selects, the run status records and
+    /// `failed_sections` lists. Unique within a registry.
+    fn name(&self) -> &str;
+
+    /// Run the bench under `cfg`. Measurements already pushed when it fails are dropped
+    /// along with the section.
+    fn run(&self, cfg: &BenchConfig, sink: &mut dyn MeasurementSink) -> io::Result<()>;
+
+    /// Why a `--dry-run` cannot sample this bench (it times its own loops rather than
+    /// going through the harness), or `None` if it can.
+    fn unplanned(&self) -> Option<&'static str> {
+        None
+    }
+}
+
+/// Benches in run order.
+#[derive(Default)]
+pub struct Registry {
+    benches: Vec<Box<dyn Bench>>,
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The built-in sections `spec` asks for, in the order the `suite` subcommand runs
+    /// them: `vsa`, then `vsa_dataset` and `dataset_io` with a dataset, `encode` with
+    /// inputs, `retrieval` with a corpus and `index` if asked for. Fails on an unknown
+    /// codec name, before anything runs.
+    pub fn suite(spec: &SuiteSpec) -> io::Result<Self> {
+        let mut registry = Self::new();
+        registry.register(VsaBench {
+            variant: spec.variant,
+            opts: RunOptions {
+                vsa_config: spec.vsa_config.clone(),
+                ..Default::default()
+            },
+        });
+        if let Some(path) = &spec.dataset {
+            registry.register(DatasetBench {
+                variant: spec.variant,
+                source: DatasetSource::File(path.clone()),
+                opts: DatasetRunOptions {
+                    read_buffer: spec.read_buffer,
+                    ..Default::default()
+                },
+            });
+            registry.register(ScanBench {
+                path: path.clone(),
+                read_buffer: spec.read_buffer,
+            });
+        }
+        if !spec.inputs.is_empty() {
+            registry.register(encode::EncodeArgs {
+                inputs: spec.inputs.clone(),
+                prefix: None,
+                codec: encode::parse_codec(&spec.codec)?,
+                codec_level: spec.codec_level,
+                verify: spec.verify,
+                parallel_hash: false,
+                codec_sweep: Vec::new(),
+                walk: spec.walk.clone(),
+                chunk_size: spec.chunk_size,
+                vsa_config: spec.vsa_config.clone(),
+                synthetic: None,
+            });
+        }
+        if let Some(dir) = &spec.retrieval_input_dir {
+            registry.register(retrieval::RetrievalArgs {
+                input_dir: dir.clone(),
+                k: spec.retrieval_k,
+                candidate_factor: spec.retrieval_candidate_factor,
+                queries: spec.retrieval_queries,
+                frontier: false,
+                holdout: false,
+                concurrency: Vec::new(),
+                ground_truth_sample: None,
+                ground_truth_timeout: None,
+                walk: spec.walk.clone(),
+                chunk_size: spec.chunk_size,
+                query_file: None,
+                vsa_config: spec.vsa_config.clone(),
+            });
+        }
+        if spec.index {
+            registry.register(IndexBench);
+        }
+        Ok(registry)
+    }
+
+    /// Add `bench` after the ones already registered. A bench with the name of one
+    /// already registered replaces it in place.
+    pub fn register(&mut self, bench: impl Bench + 'static) -> &mut Self {
+        match self.benches.iter().position(|b| b.name() == bench.name()) {
+            Some(i) => self.benches[i] = Box::new(bench),
+            None => self.benches.push(Box::new(bench)),
+        }
+        self
+    }
+
+    /// Section names, in run order.
+    pub fn names(&self) -> Vec<&str> {
+        self.benches.iter().map(|b| b.name()).collect()
+    }
+
+    /// The benches to run: those named in `sections` (all of them if it is empty), in
+    /// run order. A name that is not registered is an error.
+    pub fn selected(&self, sections: &[String]) -> io::Result<Vec<&dyn Bench>> {
+        self.check_sections(sections)?;
+        Ok(self
+            .benches
+            .iter()
+            .map(|b| b.as_ref())
+            .filter(|b| is_selected(sections, b.name()))
+            .collect())
+    }
+
+    /// Keep only the benches [`Self::selected`] would run.
+    pub fn select(&mut self, sections: &[String]) -> io::Result<()> {
+        self.check_sections(sections)?;
+        self.benches.retain(|b| is_selected(sections, b.name()));
+        Ok(())
+    }
+
+    fn check_sections(&self, sections: &[String]) -> io::Result<()> {
+        match sections
+            .iter()
+            .find(|s| !self.names().contains(&s.as_str()))
+        {
+            Some(unknown) => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "no suite section `{unknown}` (registered: {})",
+                    self.names().join(", ")
+                ),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Drop the benches a dry run cannot sample, returning each one's name and reason.
+    pub fn take_unplanned(&mut self) -> Vec<(String, &'static str)> {
+        let mut unplanned = Vec::new();
+        self.benches.retain(|b| match b.unplanned() {
+            Some(reason) => {
+                unplanned.push((b.name().to_string(), reason));
+                false
+            }
+            None => true,
+        });
+        unplanned
+    }
+}
+
+fn is_selected(sections: &[String], name: &str) -> bool {
+    sections.is_empty() || sections.iter().any(|s| s == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::VsaVariant;
+    use std::path::PathBuf;
+
+    struct Named(&'static str);
+
+    impl Bench for Named {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        fn run(&self, _cfg: &BenchConfig, _sink: &mut dyn MeasurementSink) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_suite_sections_and_selection() {
+        let spec = SuiteSpec {
+            variant: VsaVariant::Packed,
+            dataset: Some(PathBuf::from("data.embr")),
+            inputs: vec![PathBuf::from("input.bin")],
+            index: true,
+            ..Default::default()
+        };
+        let mut registry = Registry::suite(&spec).unwrap();
+        registry.register(Named("custom"));
+        assert_eq!(
+            registry.names(),
+            [
+                "vsa",
+                "vsa_dataset",
+                "dataset_io",
+                "encode",
+                "index",
+                "custom"
+            ]
+        );
+        assert_eq!(
+            Registry::suite(&SuiteSpec::default()).unwrap().names(),
+            ["vsa"]
+        );
+        let bad_codec = SuiteSpec {
+            codec: "brotli".to_string(),
+            ..spec.clone()
+        };
+        assert!(Registry::suite(&bad_codec).is_err());
+
+        let names = |sections: &[&str]| {
+            let sections: Vec<String> = sections.iter().map(|s| s.to_string()).collect();
+            registry
+                .selected(&sections)
+                .map(|bs| bs.iter().map(|b| b.name().to_string()).collect::<Vec<_>>())
+        };
+        // Run order, not the order asked for.
+        assert_eq!(names(&["custom", "vsa"]).unwrap(), ["vsa", "custom"]);
+        assert_eq!(names(&[]).unwrap().len(), 6);
+        let err = names(&["retrieval"]).unwrap_err();
+        assert!(
+            err.to_string().contains("no suite section `retrieval`"),
+            "{err}"
+        );
+
+        let mut narrowed = Registry::suite(&spec).unwrap();
+        narrowed.select(&["encode".to_string()]).unwrap();
+        assert_eq!(narrowed.names(), ["encode"]);
+
+        // Re-registering a name replaces that bench where it stands.
+        registry.register(Named("vsa"));
+        assert_eq!(registry.names()[0], "vsa");
+        assert_eq!(registry.names().len(), 6);
+
+        let unplanned: Vec<String> = registry
+            .take_unplanned()
+            .into_iter()
+            .map(|(n, _)| n)
+            .collect();
+        assert_eq!(unplanned, ["vsa_dataset", "dataset_io", "encode"]);
+        assert_eq!(registry.names(), ["vsa", "index", "custom"]);
+    }
+}
@@ -1,4 +1,18 @@
+use crate::confidence::Confidence;
+use crate::disk_space::DiskSpace;
+use crate::environment::Environment;
+use crate::harness::{BenchConfig, Cooldown};
+use crate::measurements::OPS_PER_S_SUFFIX;
+use crate::vsa_config::VsaConfig;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Version of the report format; bump when a field's meaning changes or a new field
+/// matters to readers. Version 2 added `notes` and `invocation`.
+pub const SCHEMA_VERSION: u32 = 2;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RunMeta {
@@ -8,6 +22,130 @@ pub struct RunMeta {
     pub seed: u64,
     pub timestamp_utc: String,
     pub git_sha: Option<String>,
+
+    /// Whether the working tree had uncommitted changes: `git status --porcelain` in the
+    /// source tree the bench was built from, taken at build time when it was a
+    /// repository, else `GIT_DIRTY` from the environment. `None` when neither could
+    /// tell, and for reports from before it was recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_dirty: Option<bool>,
+
+    /// [`crate::EMBEDDENATOR_VERSION`] of the build that took the run (empty for reports
+    /// from before it was recorded).
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub embeddenator_version: String,
+
+    /// [`crate::EMBEDDENATOR_REV`]: the embeddenator commit, for a git dependency.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embeddenator_rev: Option<String>,
+
+    /// Free-form run labels from `--tag key=value` (e.g. the embeddenator branch).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tags: BTreeMap<String, String>,
+
+    /// CPU frequency scaling / thermal state at run start and end (best-effort).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment: Option<Environment>,
+
+    /// [`crate::measurements::NAMESPACE_VERSION`] of the names in this report (`None` for
+    /// reports from before names were versioned).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub measurement_namespace_version: Option<u32>,
+
+    /// Pauses between measurements (`--cooldown-ms`, `--cooldown-until-idle`), if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cooldown: Option<Cooldown>,
+
+    /// Free-text annotations from `--note` (e.g. "governor pinned to performance").
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<String>,
+
+    /// Command line of the process that wrote the report, program name first (empty for
+    /// reports from before schema version 2).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub invocation: Vec<String>,
+
+    /// Disk space the run was estimated to need, what was free and what it took (runs
+    /// that write to disk; see [`crate::disk_space`]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disk_space: Option<DiskSpace>,
+
+    /// The `ReversibleVSAConfig` the benches encoded with (see [`crate::vsa_config`]);
+    /// `None` for reports from before it was recorded, which all used the defaults.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vsa_config: Option<VsaConfig>,
+}
+
+impl RunMeta {
+    /// Metadata for a run of `cfg` starting now, without environment, cooldown or notes.
+    /// The invocation is this process's command line.
+    pub fn new(cfg: &BenchConfig, tags: BTreeMap<String, String>) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            bench_version: env!("CARGO_PKG_VERSION").to_string(),
+            profile: cfg.profile.as_str().to_string(),
+            seed: cfg.seed,
+            timestamp_utc: now_utc_rfc3339(),
+            git_sha: git_sha_short(&env_var),
+            git_dirty: git_dirty(),
+            embeddenator_version: crate::EMBEDDENATOR_VERSION.to_string(),
+            embeddenator_rev: crate::EMBEDDENATOR_REV.map(str::to_string),
+            tags,
+            environment: None,
+            measurement_namespace_version: Some(crate::measurements::NAMESPACE_VERSION),
+            cooldown: None,
+            notes: Vec::new(),
+            invocation: std::env::args_os()
+                .map(|a| a.to_string_lossy().into_owned())
+                .collect(),
+            disk_space: None,
+            vsa_config: None,
+        }
+    }
+}
+
+pub fn unix_secs() -> u64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+fn now_utc_rfc3339() -> String {
+    // Avoid adding chrono dependency; this is "good enough" for filenames + reports.
+    // Format: YYYY-MM-DDTHH:MM:SSZ
+    format!("unix:{}", unix_secs())
+}
+
+fn env_var(name: &str) -> Option<String> {
+    std::env::var(name).ok()
+}
+
+fn git_sha_short(var: &dyn Fn(&str) -> Option<String>) -> Option<String> {
+    // Best-effort: read from environment set by CI/build scripts.
+    var("GIT_SHA")
+        .or_else(|| var("GITHUB_SHA"))
+        .map(|s| s.chars().take(12).collect())
+}
+
+/// Best-effort: what git said about the source tree when the bench was built (see
+/// build.rs), not the current directory (which may be another checkout), falling back to
+/// the environment when the build was not from a repository (a CI artifact, a source
+/// tarball) or git failed.
+fn git_dirty() -> Option<bool> {
+    option_env!("BUILD_GIT_DIRTY")
+        .map(|dirty| dirty == "1")
+        .or_else(|| git_dirty_from_env(&env_var))
+}
+
+/// `GIT_DIRTY` as CI sets it: `1`/`true` or `0`/`false` (anything else: unknown).
+fn git_dirty_from_env(var: &dyn Fn(&str) -> Option<String>) -> Option<bool> {
+    match var("GIT_DIRTY")?.trim().to_ascii_lowercase().as_str() {
+        "1" | "true" => Some(true),
+        "0" | "false" => Some(false),
+        _ => None,
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -19,12 +157,109 @@ pub struct Measurement {
     pub warmup_iters: u64,
 
     pub total_ns: u128,
+    /// Per-iteration time; for rates (`"ops/s"`) and ratios the value in `unit` instead.
+    /// Sizes (`"bytes"`) keep theirs in `bytes_processed` and leave this at 0.
     pub ns_per_iter: f64,
 
     pub bytes_processed: Option<u64>,
     pub throughput_bytes_per_s: Option<f64>,
 
     pub extra: serde_json::Value,
+
+    /// Bench-attached labels (substrate, dataset scale, ...) used to align measurements.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tags: BTreeMap<String, String>,
+}
+
+/// Unit of the `<name>.ops_per_s` throughput measurements.
+pub const UNIT_OPS_PER_S: &str = "ops/s";
+
+/// Unit of the derived speedups ([`crate::ratios`]); the value is a plain ratio.
+pub const UNIT_RATIO: &str = "ratio";
+
+/// Tag marking a measurement's expected spread; `high` ones (single-shot timings such as
+/// `vsa.coldstart.*`) are informational and not gated by `compare`.
+pub const VARIANCE_TAG: &str = "variance";
+
+impl Measurement {
+    /// Whether `ns_per_iter` is a per-iteration time (`ns/...` units) rather than a size
+    /// or a rate.
+    pub fn is_timing(&self) -> bool {
+        self.unit.starts_with("ns/")
+    }
+
+    /// Whether a larger value is better: true for rates (`.../s`), false for times and
+    /// sizes (and for ratios, which have no direction; see [`Self::is_ratio`]).
+    pub fn higher_is_better(&self) -> bool {
+        self.unit.ends_with("/s")
+    }
+
+    /// Whether the value is a dimensionless ratio of two other measurements.
+    pub fn is_ratio(&self) -> bool {
+        self.unit == UNIT_RATIO
+    }
+
+    /// Whether the measurement is tagged `variance=high`: a value worth recording whose
+    /// run-to-run spread makes regression verdicts on it meaningless.
+    pub fn is_informational(&self) -> bool {
+        self.tags.get(VARIANCE_TAG).is_some_and(|v| v == "high")
+    }
+
+    /// `extra.confidence` (see [`crate::confidence`]); `None` for non-timings and for
+    /// reports from before it was recorded.
+    pub fn confidence(&self) -> Option<Confidence> {
+        self.extra
+            .get("confidence")
+            .and_then(|c| serde_json::from_value(c.clone()).ok())
+    }
+
+    /// The ops/s figure a dataset or retrieval bench recorded in extra (`ops_per_s`, or
+    /// `qps` at the top level or under `stats`).
+    fn extra_ops_per_s(&self) -> Option<f64> {
+        let stats = self.extra.get("stats");
+        [
+            self.extra.get("ops_per_s"),
+            self.extra.get("qps"),
+            stats.and_then(|s| s.get("qps")),
+        ]
+        .into_iter()
+        .flatten()
+        .find_map(|v| v.as_f64())
+    }
+
+    /// `<name>.ops_per_s`: the extra ops/s figure as a measurement of its own (unit
+    /// `ops/s`, value in `ns_per_iter`), so throughput can be tracked and compared without
+    /// parsing extra. `None` for measurements that record no such figure.
+    pub fn ops_per_s_measurement(&self) -> Option<Measurement> {
+        let ops_per_s = self.extra_ops_per_s()?;
+        let mut extra = serde_json::json!({"derived_from": self.name});
+        if let Some(c) = self.confidence() {
+            extra["confidence"] = serde_json::json!(c);
+        }
+        Some(Measurement {
+            name: format!("{}{OPS_PER_S_SUFFIX}", self.name),
+            unit: UNIT_OPS_PER_S.to_string(),
+            iters: self.iters,
+            warmup_iters: self.warmup_iters,
+            total_ns: self.total_ns,
+            ns_per_iter: ops_per_s,
+            bytes_processed: None,
+            throughput_bytes_per_s: None,
+            extra,
+            tags: self.tags.clone(),
+        })
+    }
+}
+
+/// `measurements` with each one's [`Measurement::ops_per_s_measurement`] right after it.
+pub fn with_ops_per_s(measurements: Vec<Measurement>) -> Vec<Measurement> {
+    let mut out = Vec::with_capacity(measurements.len());
+    for m in measurements {
+        let sibling = m.ops_per_s_measurement();
+        out.push(m);
+        out.extend(sibling);
+    }
+    out
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,3 +267,430 @@ pub struct ContractBenchReport {
     pub run: RunMeta,
     pub measurements: Vec<Measurement>,
 }
+
+/// Build a tag map from `(key, value)` pairs.
+pub fn tags<K: ToString, V: ToString>(pairs: &[(K, V)]) -> BTreeMap<String, String> {
+    pairs
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+/// Load a report previously written by the bench binary, gzipped or not.
+pub fn load_report<P: AsRef<Path>>(path: P) -> io::Result<ContractBenchReport> {
+    let path = path.as_ref();
+    let bytes = crate::gzip::read(path)?;
+    serde_json::from_slice(&bytes).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {e}", path.display()),
+        )
+    })
+}
+
+/// Load every `*.json` and `*.json.gz` report in `dir`, oldest first.
+///
+/// Reports are ordered by `timestamp_utc` (numerically for the `unix:<secs>` stamps the
+/// binary writes), then by file name. JSON files that are not reports, such as compare
+/// or trend output kept in the same directory, are skipped with a warning.
+pub fn load_reports_dir<P: AsRef<Path>>(dir: P) -> io::Result<Vec<(PathBuf, ContractBenchReport)>> {
+    let mut reports = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if !(name.ends_with(".json") || name.ends_with(".json.gz")) || !path.is_file() {
+            continue;
+        }
+        match load_report(&path) {
+            Ok(report) => reports.push((path, report)),
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                eprintln!("warning: skipping {e}");
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    reports.sort_by(|(pa, a), (pb, b)| {
+        let stamp = |r: &ContractBenchReport| {
+            let secs = r
+                .run
+                .timestamp_utc
+                .strip_prefix("unix:")
+                .and_then(|s| s.parse::<u64>().ok());
+            (secs, r.run.timestamp_utc.clone())
+        };
+        stamp(a).cmp(&stamp(b)).then_with(|| pa.cmp(pb))
+    });
+    Ok(reports)
+}
+
+/// A measurement name plus the tags used to align it across reports.
+pub type MatchKey = (String, BTreeMap<String, String>);
+
+/// `m`'s name and those of its tags listed in `match_tags`.
+pub fn match_key(m: &Measurement, match_tags: &[String]) -> MatchKey {
+    let tags = match_tags
+        .iter()
+        .filter_map(|k| m.tags.get(k).map(|v| (k.clone(), v.clone())))
+        .collect();
+    (m.name.clone(), tags)
+}
+
+/// One measurement's `ns_per_iter` across a sequence of reports.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeasurementSeries {
+    pub name: String,
+    /// The subset of tags used for alignment.
+    pub tags: BTreeMap<String, String>,
+    /// Unit in the latest report that has the measurement.
+    pub unit: String,
+    /// One value per report, `None` where the report lacks the measurement.
+    pub values: Vec<Option<f64>>,
+}
+
+/// Group the measurements of `reports` (in order) into per-measurement series, aligned by
+/// name plus `match_tags`. A name repeated within one report keeps its first value.
+pub fn group_series<'a, I>(reports: I, match_tags: &[String]) -> Vec<MeasurementSeries>
+where
+    I: IntoIterator<Item = &'a ContractBenchReport>,
+{
+    let mut series: BTreeMap<MatchKey, MeasurementSeries> = BTreeMap::new();
+    let mut runs = 0;
+    for report in reports {
+        for m in &report.measurements {
+            let (name, tags) = match_key(m, match_tags);
+            let s = series
+                .entry((name.clone(), tags.clone()))
+                .or_insert_with(|| MeasurementSeries {
+                    name,
+                    tags,
+                    unit: String::new(),
+                    values: Vec::new(),
+                });
+            if s.values.len() > runs {
+                continue;
+            }
+            s.values.resize(runs, None);
+            s.values.push(Some(m.ns_per_iter));
+            s.unit = m.unit.clone();
+        }
+        runs += 1;
+    }
+    series
+        .into_values()
+        .map(|mut s| {
+            s.values.resize(runs, None);
+            s
+        })
+        .collect()
+}
+
+/// Compose an auto-generated report file name (used by `--out-dir`).
+///
+/// Produces `report_<subcommand>[_<detail>...]_<profile>_<seed>_<timestamp>.json`, where
+/// `detail` carries the variant or key arguments (and the dataset scale when one is used).
+/// Segments are lowercased and anything outside `[a-z0-9.-]` becomes `-`. The timestamp
+/// is unix seconds rather than a clock time, so names never hold the `:` (or any other
+/// character) Windows refuses, and the `report_` stem keeps them off its reserved device
+/// names (`CON`, `NUL`, ...).
+pub fn report_file_name(
+    subcommand: &str,
+    detail: &[String],
+    profile: &str,
+    seed: u64,
+    timestamp_secs: u64,
+) -> String {
+    fn segment(s: &str) -> String {
+        s.chars()
+            .map(|c| match c.to_ascii_lowercase() {
+                c @ ('a'..='z' | '0'..='9' | '.' | '-') => c,
+                _ => '-',
+            })
+            .collect()
+    }
+
+    let mut parts = vec!["report".to_string(), segment(subcommand)];
+    parts.extend(detail.iter().filter(|d| !d.is_empty()).map(|d| segment(d)));
+    parts.push(segment(profile));
+    parts.push(seed.to_string());
+    parts.push(timestamp_secs.to_string());
+    format!("{}.json", parts.join("_"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn run_meta() -> RunMeta {
+        RunMeta {
+            schema_version: 1,
+            bench_version: "0.0.0".to_string(),
+            profile: "quick".to_string(),
+            seed: 0,
+            timestamp_utc: "unix:0".to_string(),
+            git_sha: None,
+            git_dirty: None,
+            embeddenator_version: String::new(),
+            embeddenator_rev: None,
+            tags: tags(&[("branch", "feature/x")]),
+            environment: None,
+            measurement_namespace_version: None,
+            cooldown: None,
+            notes: Vec::new(),
+            invocation: Vec::new(),
+            disk_space: None,
+            vsa_config: None,
+        }
+    }
+
+    #[test]
+    fn test_git_env_fallback() {
+        let env = |pairs: &'static [(&str, &str)]| {
+            move |name: &str| {
+                pairs
+                    .iter()
+                    .find(|(k, _)| *k == name)
+                    .map(|(_, v)| v.to_string())
+            }
+        };
+        const SHA: &str = "0123456789abcdef0123";
+        assert_eq!(
+            git_sha_short(&env(&[("GITHUB_SHA", SHA)])).as_deref(),
+            Some("0123456789ab")
+        );
+        assert_eq!(
+            git_sha_short(&env(&[("GIT_SHA", "fedcba"), ("GITHUB_SHA", SHA)])).as_deref(),
+            Some("fedcba")
+        );
+        assert_eq!(git_sha_short(&env(&[])), None);
+
+        for (value, dirty) in [
+            ("1", Some(true)),
+            ("TRUE", Some(true)),
+            ("0", Some(false)),
+            (" false\n", Some(false)),
+            ("maybe", None),
+        ] {
+            let var = move |name: &str| (name == "GIT_DIRTY").then(|| value.to_string());
+            assert_eq!(git_dirty_from_env(&var), dirty, "{value:?}");
+        }
+        assert_eq!(git_dirty_from_env(&env(&[])), None);
+
+        // Reports from before the build was recorded still load.
+        let mut old = serde_json::to_value(run_meta()).unwrap();
+        for key in ["git_dirty", "embeddenator_version", "embeddenator_rev"] {
+            old.as_object_mut().unwrap().remove(key);
+        }
+        let back: RunMeta = serde_json::from_value(old).unwrap();
+        assert_eq!(
+            (
+                back.git_dirty,
+                back.embeddenator_version.as_str(),
+                back.embeddenator_rev
+            ),
+            (None, "", None)
+        );
+        let cfg = BenchConfig {
+            profile: crate::harness::Profile::Quick,
+            seed: 0,
+        };
+        let now = RunMeta::new(&cfg, BTreeMap::new());
+        assert_eq!(now.embeddenator_version, crate::EMBEDDENATOR_VERSION);
+    }
+
+    #[test]
+    fn test_tags_roundtrip() {
+        let report = ContractBenchReport {
+            run: run_meta(),
+            measurements: vec![Measurement {
+                name: "vsa.packed.bind".to_string(),
+                unit: "ns/iter".to_string(),
+                iters: 1,
+                warmup_iters: 0,
+                total_ns: 10,
+                ns_per_iter: 10.0,
+                bytes_processed: None,
+                throughput_bytes_per_s: None,
+                extra: json!({}),
+                tags: tags(&[("substrate", "packed")]),
+            }],
+        };
+
+        let s = serde_json::to_string(&report).unwrap();
+        let back: ContractBenchReport = serde_json::from_str(&s).unwrap();
+        assert_eq!(back.run.tags["branch"], "feature/x");
+        assert_eq!(back.measurements[0].tags["substrate"], "packed");
+    }
+
+    #[test]
+    fn test_ops_per_s_siblings() {
+        let m = |name: &str, extra: serde_json::Value| Measurement {
+            name: name.to_string(),
+            unit: "ns/op".to_string(),
+            iters: 4,
+            warmup_iters: 0,
+            total_ns: 400,
+            ns_per_iter: 100.0,
+            bytes_processed: None,
+            throughput_bytes_per_s: None,
+            extra,
+            tags: tags(&[("scale", "10k")]),
+        };
+        let out = with_ops_per_s(vec![
+            m("vsa_dataset.packed.bind", json!({"ops_per_s": 1e7})),
+            m("vsa.packed.bind", json!({})),
+            m(
+                "retrieval.query_pass_with_index",
+                json!({"stats": {"qps": 250.0}}),
+            ),
+        ]);
+        let names: Vec<&str> = out.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "vsa_dataset.packed.bind",
+                "vsa_dataset.packed.bind.ops_per_s",
+                "vsa.packed.bind",
+                "retrieval.query_pass_with_index",
+                "retrieval.query_pass_with_index.ops_per_s",
+            ]
+        );
+        let rate = &out[1];
+        assert_eq!(
+            (rate.unit.as_str(), rate.ns_per_iter),
+            (UNIT_OPS_PER_S, 1e7)
+        );
+        assert_eq!(rate.tags["scale"], "10k");
+        assert!(rate.higher_is_better() && !rate.is_timing());
+        assert!(out[0].is_timing() && !out[0].higher_is_better());
+        assert_eq!(out[4].ns_per_iter, 250.0);
+    }
+
+    #[test]
+    fn test_report_file_name() {
+        assert_eq!(
+            report_file_name(
+                "vsa",
+                &["packed".to_string(), "10k".to_string()],
+                "quick",
+                0,
+                1700000000
+            ),
+            "report_vsa_packed_10k_quick_0_1700000000.json"
+        );
+        assert_eq!(
+            report_file_name("import-criterion", &[], "full", 42, 1),
+            "report_import-criterion_full_42_1.json"
+        );
+        // Path separators and underscores never leak into segments.
+        assert_eq!(
+            report_file_name(
+                "encode",
+                &["Zstd/3_x".to_string(), String::new()],
+                "quick",
+                1,
+                2
+            ),
+            "report_encode_zstd-3-x_quick_1_2.json"
+        );
+        // Nothing Windows refuses in a file name, from any segment.
+        let name = report_file_name(
+            "vsa",
+            &["C:\\data\\12:30".to_string(), "a<b>|?*\"".to_string()],
+            "con",
+            0,
+            3,
+        );
+        assert!(
+            !name.contains(['<', '>', ':', '"', '/', '\\', '|', '?', '*']),
+            "{name}"
+        );
+        assert_eq!(name, "report_vsa_c--data-12-30_a-b-----_con_0_3.json");
+    }
+
+    #[test]
+    fn test_group_series_tolerates_gaps() {
+        let m = |name: &str, substrate: &str, v: f64| Measurement {
+            name: name.to_string(),
+            unit: "ns/iter".to_string(),
+            iters: 1,
+            warmup_iters: 0,
+            total_ns: v as u128,
+            ns_per_iter: v,
+            bytes_processed: None,
+            throughput_bytes_per_s: None,
+            extra: json!({}),
+            tags: tags(&[("substrate", substrate)]),
+        };
+        let report = |measurements| ContractBenchReport {
+            run: run_meta(),
+            measurements,
+        };
+        let reports = [
+            report(vec![m("bind", "packed", 1.0), m("bind", "bitsliced", 2.0)]),
+            report(vec![m("bundle", "packed", 5.0)]),
+            report(vec![m("bind", "packed", 3.0)]),
+        ];
+
+        // By name only, the second `bind` of the first report is a duplicate.
+        let by_name = group_series(&reports, &[]);
+        let names: Vec<&str> = by_name.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["bind", "bundle"]);
+        assert_eq!(by_name[0].values, [Some(1.0), None, Some(3.0)]);
+        assert_eq!(by_name[1].values, [None, Some(5.0), None]);
+
+        let by_substrate = group_series(&reports, &["substrate".to_string()]);
+        assert_eq!(by_substrate.len(), 3);
+        assert_eq!(by_substrate[0].tags["substrate"], "bitsliced");
+        assert_eq!(by_substrate[0].values, [Some(2.0), None, None]);
+    }
+
+    #[test]
+    fn test_tags_optional_in_json() {
+        let mut meta = run_meta();
+        meta.tags.clear();
+        let v = serde_json::to_value(&meta).unwrap();
+        assert!(v.get("tags").is_none());
+
+        // Reports written before tags existed still load.
+        let old = json!({
+            "name": "vsa.packed.bind", "unit": "ns/iter", "iters": 1, "warmup_iters": 0,
+            "total_ns": 10, "ns_per_iter": 10.0, "bytes_processed": null,
+            "throughput_bytes_per_s": null, "extra": {}
+        });
+        let m: Measurement = serde_json::from_value(old).unwrap();
+        assert!(m.tags.is_empty());
+    }
+
+    #[test]
+    fn test_notes_and_invocation_roundtrip() {
+        let mut meta = run_meta();
+        let v = serde_json::to_value(&meta).unwrap();
+        assert!(v.get("notes").is_none() && v.get("invocation").is_none());
+
+        meta.schema_version = SCHEMA_VERSION;
+        meta.notes = vec!["governor pinned".to_string(), "kernel 6.8".to_string()];
+        meta.invocation = ["bench", "vsa", "--note", "governor pinned"]
+            .map(String::from)
+            .to_vec();
+        let back: RunMeta = serde_json::from_str(&serde_json::to_string(&meta).unwrap()).unwrap();
+        assert_eq!(back.notes, meta.notes);
+        assert_eq!(back.invocation, meta.invocation);
+
+        // Version 1 reports have neither and still load.
+        let mut old = serde_json::to_value(run_meta()).unwrap();
+        old.as_object_mut().unwrap().remove("tags");
+        let back: RunMeta = serde_json::from_value(old).unwrap();
+        assert!(back.notes.is_empty() && back.invocation.is_empty());
+
+        let fresh = RunMeta::new(
+            &BenchConfig {
+                profile: crate::harness::Profile::Quick,
+                seed: 0,
+            },
+            BTreeMap::new(),
+        );
+        assert_eq!(fresh.schema_version, SCHEMA_VERSION);
+        assert!(!fresh.invocation.is_empty());
+    }
+}
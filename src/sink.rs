@@ -0,0 +1,449 @@
+//! Where runners send their measurements as they take them.
+//!
+//! A runner calls [`MeasurementSink::on_start`] before it takes a measurement (or a group
+//! of them timed together), [`MeasurementSink::on_measurement`] with each one as it
+//! completes, and [`MeasurementSink::on_skip`] for one it will not take. Long untimed
+//! work, such as retrieval's ground truth, reports how far it got through
+//! [`MeasurementSink::on_progress`]. An error from
+//! `on_measurement` stops the runner and is returned from it, so a sink that cannot keep
+//! what it is given fails the run instead of losing measurements.
+//!
+//! The sinks here compose through [`FanOut`]:
+//!
+//! - `Vec<Measurement>` collects in memory; [`collect`] runs a runner into one and is how
+//!   the `Vec`-returning runners are built.
+//! - [`JsonLines`] writes each measurement as a line of JSON as it arrives.
+//! - [`Progress`] prints what is running, taken and skipped, and how far along it is.
+//! - [`ChannelSink`] hands events to another thread over a bounded channel. When the
+//!   receiver falls behind the runner blocks between measurements rather than queueing
+//!   without bound, and a receiver that hung up fails the run.
+//!
+//! Stable: [`MeasurementSink`] and the sinks in this module.
+
+use crate::schema::Measurement;
+use std::io::{self, Write};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+
+/// Receives a runner's measurements in the order it takes them.
+pub trait MeasurementSink {
+    /// `name`, a measurement or a group of measurements timed together, is about to run.
+    fn on_start(&mut self, _name: &str) {}
+
+    /// `m` has completed.
+    fn on_measurement(&mut self, m: Measurement) -> io::Result<()>;
+
+    /// `name` will not run, because of `reason`.
+    fn on_skip(&mut self, _name: &str, _reason: &str) {}
+
+    /// `name`, work outside any measurement, has done `done` of its `total` steps.
+    fn on_progress(&mut self, _name: &str, _done: usize, _total: usize) {}
+
+    /// [`Self::on_measurement`], under the name sinks had before it could fail.
+    #[deprecated(note = "use `on_measurement`")]
+    fn push(&mut self, m: Measurement) -> io::Result<()> {
+        self.on_measurement(m)
+    }
+
+    /// [`Self::on_measurement`] for each of `ms` in order, up to the first error.
+    fn push_all(&mut self, ms: Vec<Measurement>) -> io::Result<()> {
+        for m in ms {
+            self.on_measurement(m)?;
+        }
+        Ok(())
+    }
+}
+
+impl MeasurementSink for Vec<Measurement> {
+    fn on_measurement(&mut self, m: Measurement) -> io::Result<()> {
+        self.push(m);
+        Ok(())
+    }
+}
+
+/// Run `run` into a `Vec`, with skips warned about on stderr.
+pub fn collect(
+    run: impl FnOnce(&mut dyn MeasurementSink) -> io::Result<()>,
+) -> io::Result<Vec<Measurement>> {
+    let mut out = Vec::new();
+    let mut warnings = Progress::warnings(io::stderr());
+    run(&mut FanOut::new(vec![&mut out, &mut warnings]))?;
+    Ok(out)
+}
+
+/// Each event to every sink in turn, in the order given.
+///
+/// A sink's error is returned at once: the sinks after it do not see that measurement.
+pub struct FanOut<'a> {
+    sinks: Vec<&'a mut dyn MeasurementSink>,
+}
+
+impl<'a> FanOut<'a> {
+    pub fn new(sinks: Vec<&'a mut dyn MeasurementSink>) -> Self {
+        Self { sinks }
+    }
+}
+
+impl MeasurementSink for FanOut<'_> {
+    fn on_start(&mut self, name: &str) {
+        for sink in &mut self.sinks {
+            sink.on_start(name);
+        }
+    }
+
+    fn on_measurement(&mut self, m: Measurement) -> io::Result<()> {
+        let Some((last, rest)) = self.sinks.split_last_mut() else {
+            return Ok(());
+        };
+        for sink in rest {
+            sink.on_measurement(m.clone())?;
+        }
+        last.on_measurement(m)
+    }
+
+    fn on_skip(&mut self, name: &str, reason: &str) {
+        for sink in &mut self.sinks {
+            sink.on_skip(name, reason);
+        }
+    }
+
+    fn on_progress(&mut self, name: &str, done: usize, total: usize) {
+        for sink in &mut self.sinks {
+            sink.on_progress(name, done, total);
+        }
+    }
+}
+
+/// One JSON object per measurement, newline-terminated and flushed as it arrives, as in
+/// a checkpoint (see [`crate::checkpoint`]).
+pub struct JsonLines<W: Write> {
+    out: W,
+}
+
+impl<W: Write> JsonLines<W> {
+    pub fn new(out: W) -> Self {
+        Self { out }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+impl<W: Write> MeasurementSink for JsonLines<W> {
+    fn on_measurement(&mut self, m: Measurement) -> io::Result<()> {
+        serde_json::to_writer(&mut self.out, &m)?;
+        self.out.write_all(b"\n")?;
+        self.out.flush()
+    }
+}
+
+/// Human-readable progress lines. Write errors are ignored: progress is not worth
+/// failing a run over.
+pub struct Progress<W: Write> {
+    out: W,
+    /// Only skips, as warnings.
+    warnings_only: bool,
+    /// Print [`MeasurementSink::on_progress`] reports.
+    progress: bool,
+}
+
+impl<W: Write> Progress<W> {
+    /// A line per start, measurement, skip and progress report.
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            warnings_only: false,
+            progress: true,
+        }
+    }
+
+    /// Only the skips, as `warning:` lines.
+    pub fn warnings(out: W) -> Self {
+        Self {
+            out,
+            warnings_only: true,
+            progress: false,
+        }
+    }
+
+    /// Print progress reports too, as `name: done/total` lines.
+    pub fn with_progress(mut self) -> Self {
+        self.progress = true;
+        self
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+impl<W: Write> MeasurementSink for Progress<W> {
+    fn on_start(&mut self, name: &str) {
+        if !self.warnings_only {
+            let _ = writeln!(self.out, "running {name}");
+        }
+    }
+
+    fn on_measurement(&mut self, m: Measurement) -> io::Result<()> {
+        if !self.warnings_only {
+            let _ = writeln!(self.out, "{}: {:.1} {}", m.name, m.ns_per_iter, m.unit);
+        }
+        Ok(())
+    }
+
+    fn on_skip(&mut self, name: &str, reason: &str) {
+        let _ = writeln!(self.out, "warning: skipping {name}: {reason}");
+    }
+
+    fn on_progress(&mut self, name: &str, done: usize, total: usize) {
+        if self.progress {
+            let _ = writeln!(self.out, "{name}: {done}/{total}");
+        }
+    }
+}
+
+/// What a [`ChannelSink`] sends.
+#[derive(Clone, Debug)]
+pub enum SinkEvent {
+    Start(String),
+    Measurement(Measurement),
+    Skip { name: String, reason: String },
+}
+
+/// The sending half of [`channel`]. Progress reports are not sent.
+pub struct ChannelSink {
+    tx: SyncSender<SinkEvent>,
+}
+
+/// A sink whose events arrive at the returned receiver, with at most `bound` of them
+/// waiting (0: each send waits for its receive).
+pub fn channel(bound: usize) -> (ChannelSink, Receiver<SinkEvent>) {
+    let (tx, rx) = mpsc::sync_channel(bound);
+    (ChannelSink { tx }, rx)
+}
+
+impl MeasurementSink for ChannelSink {
+    /// Starts and skips are dropped if the receiver has hung up; the next measurement
+    /// reports it.
+    fn on_start(&mut self, name: &str) {
+        let _ = self.tx.send(SinkEvent::Start(name.to_string()));
+    }
+
+    fn on_measurement(&mut self, m: Measurement) -> io::Result<()> {
+        self.tx.send(SinkEvent::Measurement(m)).map_err(|e| {
+            let SinkEvent::Measurement(m) = e.0 else {
+                unreachable!("sent a measurement")
+            };
+            io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                format!("measurement receiver hung up before {}", m.name),
+            )
+        })
+    }
+
+    fn on_skip(&mut self, name: &str, reason: &str) {
+        let _ = self.tx.send(SinkEvent::Skip {
+            name: name.to_string(),
+            reason: reason.to_string(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn measurement(name: &str) -> Measurement {
+        Measurement {
+            name: name.to_string(),
+            unit: "ns/iter".to_string(),
+            iters: 10,
+            warmup_iters: 0,
+            total_ns: 425,
+            ns_per_iter: 42.5,
+            bytes_processed: None,
+            throughput_bytes_per_s: None,
+            extra: serde_json::json!({}),
+            tags: Default::default(),
+        }
+    }
+
+    /// Records events, failing on measurement `fail_on`.
+    #[derive(Default)]
+    struct Recorder {
+        events: Vec<String>,
+        fail_on: Option<&'static str>,
+    }
+
+    impl MeasurementSink for Recorder {
+        fn on_start(&mut self, name: &str) {
+            self.events.push(format!("start {name}"));
+        }
+
+        fn on_measurement(&mut self, m: Measurement) -> io::Result<()> {
+            if self.fail_on == Some(m.name.as_str()) {
+                return Err(io::Error::other(format!("cannot keep {}", m.name)));
+            }
+            self.events.push(format!("measurement {}", m.name));
+            Ok(())
+        }
+
+        fn on_skip(&mut self, name: &str, reason: &str) {
+            self.events.push(format!("skip {name} ({reason})"));
+        }
+
+        fn on_progress(&mut self, name: &str, done: usize, total: usize) {
+            self.events.push(format!("progress {name} {done}/{total}"));
+        }
+    }
+
+    /// A runner: a, a skipped b, then c after some untimed preparation.
+    fn run(sink: &mut dyn MeasurementSink) -> io::Result<()> {
+        sink.on_start("a");
+        sink.on_measurement(measurement("a"))?;
+        sink.on_skip("b", "not today");
+        sink.on_progress("prepare", 1, 2);
+        sink.on_start("c");
+        sink.on_measurement(measurement("c"))
+    }
+
+    #[test]
+    fn test_fan_out_order() {
+        let (mut first, mut second) = (Recorder::default(), Recorder::default());
+        let mut lines = JsonLines::new(Vec::new());
+        let mut progress = Progress::new(Vec::new());
+        let mut warnings = Progress::warnings(Vec::new());
+        let mut warnings_and_progress = Progress::warnings(Vec::new()).with_progress();
+        let mut collected = Vec::new();
+        run(&mut FanOut::new(vec![
+            &mut first,
+            &mut lines,
+            &mut progress,
+            &mut warnings,
+            &mut warnings_and_progress,
+            &mut collected,
+            &mut second,
+        ]))
+        .unwrap();
+
+        let expected = [
+            "start a",
+            "measurement a",
+            "skip b (not today)",
+            "progress prepare 1/2",
+            "start c",
+            "measurement c",
+        ];
+        assert_eq!(first.events, expected);
+        assert_eq!(second.events, expected);
+        assert_eq!(
+            collected
+                .iter()
+                .map(|m| m.name.as_str())
+                .collect::<Vec<_>>(),
+            ["a", "c"]
+        );
+        let lines = String::from_utf8(lines.into_inner()).unwrap();
+        let parsed: Vec<serde_json::Value> = lines
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        let expected: Vec<serde_json::Value> = collected
+            .iter()
+            .map(|m| serde_json::to_value(m).unwrap())
+            .collect();
+        assert_eq!(parsed, expected);
+        assert_eq!(
+            String::from_utf8(progress.into_inner()).unwrap(),
+            "running a\na: 42.5 ns/iter\nwarning: skipping b: not today\nprepare: 1/2\nrunning c\nc: 42.5 ns/iter\n"
+        );
+        assert_eq!(
+            String::from_utf8(warnings.into_inner()).unwrap(),
+            "warning: skipping b: not today\n"
+        );
+        assert_eq!(
+            String::from_utf8(warnings_and_progress.into_inner()).unwrap(),
+            "warning: skipping b: not today\nprepare: 1/2\n"
+        );
+    }
+
+    #[test]
+    fn test_failing_sink_stops_the_run() {
+        let mut before = Recorder::default();
+        let mut failing = Recorder {
+            fail_on: Some("a"),
+            ..Default::default()
+        };
+        let mut after = Recorder::default();
+        let err = run(&mut FanOut::new(vec![
+            &mut before,
+            &mut failing,
+            &mut after,
+        ]))
+        .unwrap_err();
+        assert_eq!(err.to_string(), "cannot keep a");
+        // The sinks before the failing one got the measurement; the run went no further.
+        assert_eq!(before.events, ["start a", "measurement a"]);
+        assert_eq!(failing.events, ["start a"]);
+        assert_eq!(after.events, ["start a"]);
+
+        let err = collect(|sink| {
+            let mut failing = Recorder {
+                fail_on: Some("c"),
+                ..Default::default()
+            };
+            run(&mut FanOut::new(vec![sink, &mut failing]))
+        })
+        .unwrap_err();
+        assert_eq!(err.to_string(), "cannot keep c");
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_push_forwards_to_on_measurement() {
+        let mut recorder = Recorder {
+            fail_on: Some("b"),
+            ..Default::default()
+        };
+        let sink: &mut dyn MeasurementSink = &mut recorder;
+        sink.push(measurement("a")).unwrap();
+        assert!(sink.push(measurement("b")).is_err());
+        assert_eq!(recorder.events, ["measurement a"]);
+    }
+
+    #[test]
+    fn test_channel_backpressure_and_hang_up() {
+        let (mut tx, rx) = channel(0);
+        let runner = std::thread::spawn(move || {
+            run(&mut tx)?;
+            // The receiver is gone by now.
+            tx.on_measurement(measurement("d"))
+        });
+        // With no room and nothing received, the runner waits on its first event.
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        assert!(!runner.is_finished());
+        let events: Vec<String> = rx
+            .iter()
+            .take(5)
+            .map(|event| match event {
+                SinkEvent::Start(name) => format!("start {name}"),
+                SinkEvent::Measurement(m) => format!("measurement {}", m.name),
+                SinkEvent::Skip { name, reason } => format!("skip {name} ({reason})"),
+            })
+            .collect();
+        assert_eq!(
+            events,
+            [
+                "start a",
+                "measurement a",
+                "skip b (not today)",
+                "start c",
+                "measurement c"
+            ]
+        );
+        drop(rx);
+        let err = runner.join().unwrap().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+        assert!(err.to_string().contains("before d"), "{err}");
+    }
+}
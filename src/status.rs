@@ -0,0 +1,221 @@
+//! Machine-readable run outcome for wrapper scripts (`--status-file`).
+//!
+//! Written on every exit path once arguments have parsed, so a wrapper can tell a
+//! clean run from a failed or partial one without parsing the report. A run is made of
+//! sections: one per subcommand, or one per bench group for `suite`. A failing section
+//! is recorded with its error; with `suite --keep-going` later sections still run.
+
+use crate::atomic_write::write_atomic;
+use crate::gzip;
+use serde::{Deserialize, Serialize};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunStatus {
+    pub exit_code: i32,
+    /// `<subcommand>-<unix secs>-<pid>`; unique per invocation on one host.
+    pub run_id: String,
+    pub subcommand: String,
+    /// Sections started, in order (failed ones included).
+    pub sections_run: Vec<String>,
+    pub sections_failed: Vec<SectionFailure>,
+    /// Where the JSON output was written; `None` for stdout or when nothing was written.
+    pub report_path: Option<PathBuf>,
+    /// Where the JSON output should have gone when writing it there failed; it was
+    /// printed to stdout instead.
+    #[serde(default)]
+    pub report_fallback: Option<PathBuf>,
+    pub wall_seconds: f64,
+    /// The error the process exited with, if any.
+    pub error: Option<String>,
+    #[serde(skip)]
+    current: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SectionFailure {
+    pub section: String,
+    pub error: String,
+}
+
+impl RunStatus {
+    pub fn new(subcommand: &str, unix_secs: u64) -> Self {
+        Self {
+            run_id: format!("{subcommand}-{unix_secs}-{}", std::process::id()),
+            subcommand: subcommand.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Start `section`; it counts as failed if an error is recorded before [`Self::end`].
+    pub fn begin(&mut self, section: &str) {
+        self.sections_run.push(section.to_string());
+        self.current = Some(section.to_string());
+    }
+
+    pub fn end(&mut self) {
+        self.current = None;
+    }
+
+    /// Run `f` as `section`, recording its failure.
+    pub fn section<T>(
+        &mut self,
+        section: &str,
+        f: impl FnOnce() -> io::Result<T>,
+    ) -> io::Result<T> {
+        self.begin(section);
+        let result = f();
+        match &result {
+            Ok(_) => self.end(),
+            Err(e) => self.fail(e),
+        }
+        result
+    }
+
+    /// Attribute `err` to the open section, if any.
+    pub fn fail(&mut self, err: &io::Error) {
+        if let Some(section) = self.current.take() {
+            self.sections_failed.push(SectionFailure {
+                section,
+                error: err.to_string(),
+            });
+        }
+    }
+
+    /// Record the final outcome; an open section fails along with the run.
+    pub fn finish(&mut self, result: &io::Result<()>, wall: Duration) {
+        if let Err(e) = result {
+            self.fail(e);
+            self.error = Some(e.to_string());
+        }
+        self.end();
+        self.exit_code = i32::from(result.is_err());
+        self.wall_seconds = wall.as_secs_f64();
+    }
+
+    /// Write the JSON output to `path`, gzipped if `compress`. If that fails, the
+    /// output goes to `fallback` (stdout, uncompressed) instead so the run's results
+    /// are not lost, and the failure is recorded and returned.
+    pub fn write_report(
+        &mut self,
+        path: &Path,
+        json: &str,
+        compress: bool,
+        fallback: &mut dyn Write,
+    ) -> io::Result<()> {
+        let written = if compress {
+            gzip::compress(json.as_bytes()).and_then(|gz| write_atomic(path, gz))
+        } else {
+            write_atomic(path, json)
+        };
+        let Err(e) = written else {
+            self.report_path = Some(path.to_path_buf());
+            return Ok(());
+        };
+        eprintln!("!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!");
+        eprintln!("ERROR: could not write {}: {e}", path.display());
+        eprintln!("ERROR: writing the output to stdout instead");
+        eprintln!("!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!");
+        writeln!(fallback, "{json}")?;
+        fallback.flush()?;
+        self.report_fallback = Some(path.to_path_buf());
+        Err(io::Error::new(
+            e.kind(),
+            format!(
+                "could not write {} (output printed to stdout instead): {e}",
+                path.display()
+            ),
+        ))
+    }
+
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let json = serde_json::to_vec_pretty(self).map_err(io::Error::other)?;
+        write_atomic(path, json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sections_and_finish() {
+        let mut s = RunStatus::new("suite", 1_700_000_000);
+        assert!(s.run_id.starts_with("suite-1700000000-"));
+
+        s.section("vsa", || Ok(())).unwrap();
+        let err = s
+            .section("encode", || -> io::Result<()> {
+                Err(io::Error::other("no input"))
+            })
+            .unwrap_err();
+        assert_eq!(err.to_string(), "no input");
+        s.section("index", || Ok(())).unwrap();
+        s.finish(&Ok(()), Duration::from_millis(1500));
+
+        assert_eq!(s.sections_run, ["vsa", "encode", "index"]);
+        assert_eq!(
+            s.sections_failed,
+            [SectionFailure {
+                section: "encode".to_string(),
+                error: "no input".to_string()
+            }]
+        );
+        assert_eq!(s.exit_code, 0);
+        assert_eq!(s.wall_seconds, 1.5);
+    }
+
+    #[test]
+    fn test_error_fails_open_section_only() {
+        let mut s = RunStatus::new("vsa", 0);
+        s.begin("vsa");
+        s.finish(&Err(io::Error::other("boom")), Duration::ZERO);
+        assert_eq!(s.exit_code, 1);
+        assert_eq!(s.error.as_deref(), Some("boom"));
+        assert_eq!(s.sections_failed.len(), 1);
+
+        // A failure after the section closed (e.g. writing the report) is not a section's.
+        let mut s = RunStatus::new("vsa", 0);
+        s.section("vsa", || Ok(())).unwrap();
+        s.finish(&Err(io::Error::other("disk full")), Duration::ZERO);
+        assert_eq!(s.exit_code, 1);
+        assert!(s.sections_failed.is_empty());
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("status.json");
+        s.write(&path).unwrap();
+        let back: RunStatus = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        assert_eq!(back, s);
+    }
+
+    #[test]
+    fn test_report_falls_back_to_stdout() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut s = RunStatus::new("vsa", 0);
+        let mut stdout = Vec::new();
+        let path = dir.path().join("report.json");
+        s.write_report(&path, "{}", false, &mut stdout).unwrap();
+        assert_eq!(s.report_path.as_deref(), Some(path.as_path()));
+        assert!(stdout.is_empty());
+
+        // The directory vanished during the run.
+        let gone = dir.path().join("gone/report.json");
+        let err = s
+            .write_report(&gone, "{\"measurements\": []}", true, &mut stdout)
+            .unwrap_err();
+        assert!(err.to_string().contains("printed to stdout"), "{err}");
+        assert_eq!(
+            String::from_utf8(stdout).unwrap(),
+            "{\"measurements\": []}\n"
+        );
+        assert_eq!(s.report_fallback.as_deref(), Some(gone.as_path()));
+
+        let gz = dir.path().join("report.json.gz");
+        s.write_report(&gz, "{}", true, &mut io::sink()).unwrap();
+        let bytes = std::fs::read(&gz).unwrap();
+        assert!(gzip::is_gzip(&bytes));
+        assert_eq!(gzip::read(&gz).unwrap(), b"{}");
+    }
+}
@@ -0,0 +1,210 @@
+//! The `suite` subcommand as a library call.
+//!
+//! [`SuiteSpec`] holds what the `suite` flags hold, and [`run_suite`] runs the same
+//! sections in the same order and returns the report the binary would write, without a
+//! process or JSON in between. The binary's `suite` subcommand goes through
+//! [`run_sections`] too, so the two cannot drift.
+//!
+//! The sections come from a [`Registry`]; [`run_suite_with`] runs one extended with
+//! benches from outside this crate.
+
+use crate::benches::input_walk::WalkOptions;
+use crate::confidence;
+use crate::disk_space::{self, Requirement};
+use crate::environment::Environment;
+use crate::harness::{self, BenchConfig, Profile};
+use crate::ratios;
+use crate::registry::Registry;
+use crate::schema::{ContractBenchReport, Measurement, RunMeta};
+use crate::sink;
+use crate::vsa_config::VsaConfig;
+use crate::VsaVariant;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io;
+use std::path::PathBuf;
+
+/// What a suite run covers; the fields mirror the `suite` flags and default to theirs.
+///
+/// Stable: fields may be added (with defaults), but not renamed or removed.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SuiteSpec {
+    pub profile: Profile,
+    pub seed: u64,
+    pub variant: VsaVariant,
+    /// Encode inputs; no `encode` section without any.
+    pub inputs: Vec<PathBuf>,
+    /// Corpus for the `retrieval` section, if any.
+    pub retrieval_input_dir: Option<PathBuf>,
+    pub retrieval_k: usize,
+    pub retrieval_candidate_factor: usize,
+    pub retrieval_queries: Option<usize>,
+    /// Encode codec name (`none|zstd|lz4`).
+    pub codec: String,
+    pub codec_level: Option<i32>,
+    pub verify: bool,
+    /// Run the raw inverted index bench.
+    pub index: bool,
+    /// Adds the `vsa_dataset` and `dataset_io` sections.
+    pub dataset: Option<PathBuf>,
+    /// Keep running the remaining sections when one fails.
+    pub keep_going: bool,
+    /// Which files under the encode inputs and retrieval corpus are used.
+    pub walk: WalkOptions,
+    /// Encode/retrieval ingest piece size (`--chunk-size`).
+    pub chunk_size: Option<usize>,
+    /// Dataset read buffer in bytes (`--read-buffer-kib`).
+    pub read_buffer: Option<usize>,
+    /// Raw timings kept per measurement (`--record-samples`).
+    pub record_samples: Option<usize>,
+    /// Report labels (`RunMeta::tags`).
+    pub tags: BTreeMap<String, String>,
+    /// Report annotations (`RunMeta::notes`).
+    pub notes: Vec<String>,
+    /// Sections to run, by name (empty = every registered section).
+    pub sections: Vec<String>,
+    /// Run even when the disk space preflight finds too little free
+    /// (`--ignore-space-check`).
+    pub ignore_space_check: bool,
+    /// Encoding config for the vsa, encode and retrieval sections (`--vsa-config`).
+    pub vsa_config: VsaConfig,
+}
+
+impl Default for SuiteSpec {
+    fn default() -> Self {
+        Self {
+            profile: Profile::Quick,
+            seed: 42,
+            variant: VsaVariant::All,
+            inputs: Vec::new(),
+            retrieval_input_dir: None,
+            retrieval_k: 10,
+            retrieval_candidate_factor: 10,
+            retrieval_queries: None,
+            codec: "none".to_string(),
+            codec_level: None,
+            verify: false,
+            index: false,
+            dataset: None,
+            keep_going: false,
+            walk: WalkOptions::default(),
+            chunk_size: None,
+            read_buffer: None,
+            record_samples: None,
+            tags: BTreeMap::new(),
+            notes: Vec::new(),
+            sections: Vec::new(),
+            ignore_space_check: false,
+            vsa_config: VsaConfig::default(),
+        }
+    }
+}
+
+impl SuiteSpec {
+    pub fn config(&self) -> BenchConfig {
+        BenchConfig {
+            profile: self.profile,
+            seed: self.seed,
+        }
+    }
+
+    /// Disk space the built-in `sections` among those named will take, for
+    /// [`disk_space::preflight`].
+    pub fn disk_requirements(&self, sections: &[&str]) -> Vec<Requirement> {
+        let mut out = Vec::new();
+        if sections.contains(&"encode") {
+            out.extend(disk_space::encode_requirements(
+                &self.inputs,
+                &self.walk,
+                self.chunk_size,
+            ));
+        }
+        if let (true, Some(dir)) = (sections.contains(&"retrieval"), &self.retrieval_input_dir) {
+            out.extend(disk_space::retrieval_requirements(
+                dir,
+                &self.walk,
+                self.chunk_size,
+            ));
+        }
+        out
+    }
+}
+
+/// Runs one section: `(name, bench)` to the bench's measurements. [`run_suite`] just
+/// calls the bench; the binary also records it in the run status.
+pub type SectionRunner<'a> = dyn FnMut(&str, &mut dyn FnMut() -> io::Result<Vec<Measurement>>) -> io::Result<Vec<Measurement>>
+    + 'a;
+
+/// Run the sections of `registry` that `spec` selects through `runner`, in order, with
+/// skips warned about on stderr (see [`sink::collect`]). Without `keep_going` the first
+/// failure is returned; with it, a failed section is warned about and skipped, and its
+/// name is in the returned list.
+pub fn run_sections(
+    spec: &SuiteSpec,
+    registry: &Registry,
+    runner: &mut SectionRunner<'_>,
+) -> io::Result<(Vec<Measurement>, Vec<String>)> {
+    let cfg = spec.config();
+    let mut measurements = Vec::new();
+    let mut failed = Vec::new();
+    for bench in registry.selected(&spec.sections)? {
+        let name = bench.name();
+        let result = runner(name, &mut || sink::collect(|sink| bench.run(&cfg, sink)));
+        match result {
+            Ok(ms) => measurements.extend(ms),
+            Err(e) if spec.keep_going => {
+                eprintln!("warning: suite section {name} failed: {e}");
+                failed.push(name.to_string());
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    Ok((measurements, failed))
+}
+
+/// Run the suite and return its report: the measurements, derived ratios, and the host
+/// environment at start and end.
+///
+/// Sections skipped under `keep_going` are listed, comma-separated, in the
+/// `failed_sections` run tag. Fails with [`crate::error::BenchError::InsufficientSpace`]
+/// before anything runs if the selected sections need more disk than is free, unless
+/// `ignore_space_check` is set.
+///
+/// Stable.
+pub fn run_suite(spec: &SuiteSpec) -> io::Result<ContractBenchReport> {
+    run_suite_with(spec, &Registry::suite(spec)?)
+}
+
+/// [`run_suite`] over `registry`: [`Registry::suite`] for `spec`, usually, with benches
+/// of the caller's own registered after the built-in ones.
+///
+/// Stable.
+pub fn run_suite_with(spec: &SuiteSpec, registry: &Registry) -> io::Result<ContractBenchReport> {
+    let names: Vec<&str> = registry
+        .selected(&spec.sections)?
+        .iter()
+        .map(|b| b.name())
+        .collect();
+    let watch = disk_space::watch(&spec.disk_requirements(&names), spec.ignore_space_check)?;
+    let mut environment = Environment::start();
+    let recording = spec.record_samples.map(harness::record_samples);
+    let (mut measurements, failed) = run_sections(spec, registry, &mut |_, f| f())?;
+    environment.finish();
+    if let Some(recording) = &recording {
+        recording.attach(&mut measurements);
+    }
+    confidence::annotate(&mut measurements, spec.profile);
+    measurements.extend(ratios::derive(&measurements));
+
+    let mut run = RunMeta::new(&spec.config(), spec.tags.clone());
+    run.environment = Some(environment);
+    run.notes = spec.notes.clone();
+    run.disk_space = watch.map(disk_space::Watch::finish);
+    run.vsa_config = Some(spec.vsa_config.clone());
+    if !failed.is_empty() {
+        run.tags
+            .insert("failed_sections".to_string(), failed.join(","));
+    }
+    Ok(ContractBenchReport { run, measurements })
+}
@@ -0,0 +1,377 @@
+//! Human-readable summary of a finished run.
+//!
+//! Printed to stderr by the binary after the report is written: one row per
+//! measurement, grouped into sections by name prefix (the part before the first `.`),
+//! with the slowest entries and any timed-out/skipped ones flagged. A prefix measured at
+//! several dataset scales (`vsa --dataset a --dataset b`) gets one section per `scale`
+//! tag, in run order. When a baseline
+//! comparison is available each row also shows its delta. The run's `--note`s follow the
+//! table.
+
+use crate::compare::{display_key, ComparisonReport};
+use crate::schema::{Measurement, UNIT_OPS_PER_S};
+use crate::table::{Column, Table};
+use std::collections::BTreeMap;
+
+/// Widest the measurement column gets before names are cut.
+const NAME_WIDTH: usize = 64;
+
+#[derive(Clone, Debug)]
+pub struct SummaryOptions {
+    /// How many of the slowest measurements (by ns_per_iter) to flag.
+    pub slowest: usize,
+    /// Report notes (`RunMeta::notes`), one line each under the table.
+    pub notes: Vec<String>,
+}
+
+impl Default for SummaryOptions {
+    fn default() -> Self {
+        Self {
+            slowest: 5,
+            notes: Vec::new(),
+        }
+    }
+}
+
+/// Format a duration in nanoseconds with an adaptive unit (`ns`, `µs`, `ms`, `s`).
+pub fn format_ns(ns: f64) -> String {
+    if ns < 1e3 {
+        format!("{ns:.1} ns")
+    } else if ns < 1e6 {
+        format!("{:.2} µs", ns / 1e3)
+    } else if ns < 1e9 {
+        format!("{:.2} ms", ns / 1e6)
+    } else {
+        format!("{:.2} s", ns / 1e9)
+    }
+}
+
+fn format_rate(per_s: f64) -> String {
+    if per_s >= 1e9 {
+        format!("{:.2}G", per_s / 1e9)
+    } else if per_s >= 1e6 {
+        format!("{:.2}M", per_s / 1e6)
+    } else if per_s >= 1e3 {
+        format!("{:.2}k", per_s / 1e3)
+    } else {
+        format!("{per_s:.1}")
+    }
+}
+
+fn flag_extra(m: &Measurement, key: &str) -> bool {
+    m.extra.get(key).and_then(|v| v.as_bool()) == Some(true)
+}
+
+/// Render the summary table for `measurements`, optionally with deltas from `cmp`.
+pub fn render(
+    measurements: &[Measurement],
+    cmp: Option<&ComparisonReport>,
+    opts: &SummaryOptions,
+) -> String {
+    let prefix = |m: &Measurement| m.name.split('.').next().unwrap_or_default().to_string();
+    let mut scales: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+    for m in measurements {
+        if let Some(scale) = m.tags.get("scale") {
+            let seen = scales.entry(prefix(m)).or_default();
+            if !seen.contains(&scale.as_str()) {
+                seen.push(scale);
+            }
+        }
+    }
+    // (prefix, position of the scale in run order) for prefixes split by scale.
+    let sections: Vec<(String, Option<usize>)> = measurements
+        .iter()
+        .map(|m| {
+            let p = prefix(m);
+            let scale = scales
+                .get(&p)
+                .filter(|seen| seen.len() > 1)
+                .zip(m.tags.get("scale"))
+                .and_then(|(seen, s)| seen.iter().position(|x| x == s));
+            (p, scale)
+        })
+        .collect();
+
+    let mut order: Vec<usize> = (0..measurements.len()).collect();
+    order.sort_by(|&a, &b| {
+        let (ma, mb) = (&measurements[a], &measurements[b]);
+        (&sections[a], &ma.name, &ma.tags).cmp(&(&sections[b], &mb.name, &mb.tags))
+    });
+
+    // Sizes (`bytes`) and rates (`ops/s`) are not timings and never rank as slowest.
+    let mut by_time: Vec<usize> = order
+        .iter()
+        .copied()
+        .filter(|&i| measurements[i].is_timing())
+        .collect();
+    by_time.sort_by(|&a, &b| {
+        measurements[b]
+            .ns_per_iter
+            .total_cmp(&measurements[a].ns_per_iter)
+    });
+    let slowest_rank: BTreeMap<usize, usize> = by_time
+        .into_iter()
+        .take(opts.slowest)
+        .enumerate()
+        .map(|(rank, i)| (i, rank + 1))
+        .collect();
+
+    // Deltas are keyed the same way compare aligned them.
+    let deltas: BTreeMap<String, f64> = cmp
+        .map(|c| {
+            c.deltas
+                .iter()
+                .map(|d| (display_key(&d.name, &d.tags), d.delta_ratio))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut columns = vec![
+        Column::left("measurement").max_width(NAME_WIDTH),
+        Column::right("time/iter"),
+        Column::right("ops/s"),
+    ];
+    if cmp.is_some() {
+        columns.push(Column::right("delta"));
+    }
+    columns.push(Column::left("note"));
+    let mut table = Table::new(columns);
+
+    let mut section = None;
+    for i in order {
+        let m = &measurements[i];
+        if section != Some(&sections[i]) {
+            let (prefix, scale) = &sections[i];
+            table.section(match scale {
+                Some(s) => format!("[{prefix} scale={}]", scales[prefix][*s]),
+                None => format!("[{prefix}]"),
+            });
+            section = Some(&sections[i]);
+        }
+
+        let mut notes = Vec::new();
+        if let Some(rank) = slowest_rank.get(&i) {
+            notes.push(format!("slowest #{rank}"));
+        }
+        if flag_extra(m, "timed_out") {
+            notes.push("TIMED OUT".to_string());
+        }
+        if flag_extra(m, "skipped") {
+            notes.push("SKIPPED".to_string());
+        }
+
+        let (value, ops) = if m.unit == "bytes" {
+            let bytes = m.bytes_processed.unwrap_or_default();
+            (format!("{bytes} B"), "-".to_string())
+        } else if m.unit == UNIT_OPS_PER_S {
+            ("-".to_string(), format_rate(m.ns_per_iter))
+        } else if m.is_ratio() {
+            (format!("{:.2}x", m.ns_per_iter), "-".to_string())
+        } else if m.ns_per_iter > 0.0 {
+            (format_ns(m.ns_per_iter), format_rate(1e9 / m.ns_per_iter))
+        } else {
+            (format_ns(m.ns_per_iter), "-".to_string())
+        };
+        let mut cells = vec![display_key(&m.name, &m.tags), value, ops];
+        if let Some(c) = cmp {
+            let aligned: BTreeMap<String, String> = m
+                .tags
+                .iter()
+                .filter(|(k, _)| c.match_tags.contains(k))
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect();
+            cells.push(
+                deltas
+                    .get(&display_key(&m.name, &aligned))
+                    .map(|d| format!("{:+.1}%", d * 100.0))
+                    .unwrap_or_else(|| "new".to_string()),
+            );
+        }
+        cells.push(notes.join(", "));
+        table.row(cells);
+    }
+    let mut out = table.render();
+    for note in &opts.notes {
+        out.push_str(&format!("note: {note}\n"));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compare::{compare_reports, CompareOptions};
+    use crate::schema::{tags, ContractBenchReport, RunMeta};
+    use serde_json::json;
+
+    fn m(name: &str, ns: f64, extra: serde_json::Value) -> Measurement {
+        Measurement {
+            name: name.to_string(),
+            unit: "ns/iter".to_string(),
+            iters: 1,
+            warmup_iters: 0,
+            total_ns: ns as u128,
+            ns_per_iter: ns,
+            bytes_processed: None,
+            throughput_bytes_per_s: None,
+            extra,
+            tags: tags::<&str, &str>(&[]),
+        }
+    }
+
+    fn report(ms: Vec<Measurement>) -> ContractBenchReport {
+        ContractBenchReport {
+            run: RunMeta {
+                schema_version: 1,
+                bench_version: "0.0.0".to_string(),
+                profile: "quick".to_string(),
+                seed: 0,
+                timestamp_utc: "unix:0".to_string(),
+                git_sha: None,
+                git_dirty: None,
+                embeddenator_version: String::new(),
+                embeddenator_rev: None,
+                tags: BTreeMap::new(),
+                environment: None,
+                measurement_namespace_version: None,
+                cooldown: None,
+                notes: Vec::new(),
+                invocation: Vec::new(),
+                disk_space: None,
+                vsa_config: None,
+            },
+            measurements: ms,
+        }
+    }
+
+    #[test]
+    fn test_format_ns() {
+        assert_eq!(format_ns(12.34), "12.3 ns");
+        assert_eq!(format_ns(1_500.0), "1.50 µs");
+        assert_eq!(format_ns(2_000_000.0), "2.00 ms");
+        assert_eq!(format_ns(3e9), "3.00 s");
+    }
+
+    #[test]
+    fn test_sections_and_slowest() {
+        let ms = vec![
+            m("vsa.bind", 10.0, json!({})),
+            m("encode.ingest", 5_000.0, json!({})),
+            m("vsa.bundle", 900.0, json!({"timed_out": true})),
+            m("vsa.cosine", 1.0, json!({"skipped": true})),
+        ];
+        let out = render(
+            &ms,
+            None,
+            &SummaryOptions {
+                slowest: 2,
+                ..Default::default()
+            },
+        );
+        let lines: Vec<&str> = out.lines().collect();
+
+        assert_eq!(lines[1], "[encode]");
+        assert!(lines[2].starts_with("encode.ingest") && lines[2].ends_with("slowest #1"));
+        assert_eq!(lines[3], "[vsa]");
+        assert!(lines[4].starts_with("vsa.bind") && !lines[4].contains("slowest"));
+        assert!(lines[5].ends_with("slowest #2, TIMED OUT"));
+        assert!(lines[6].ends_with("SKIPPED"));
+        assert!(!lines[0].contains("delta"));
+    }
+
+    #[test]
+    fn test_sections_per_scale() {
+        let at = |name: &str, scale: &str| {
+            let mut m = m(name, 10.0, json!({}));
+            m.tags = tags(&[("scale", scale)]);
+            m
+        };
+        let ms = vec![
+            at("vsa_dataset.packed.bind", "1m"),
+            at("vsa_dataset.packed.bind", "10k"),
+            at("vsa_dataset.packed.bundle", "10k"),
+            at("dataset_io.scan", "10k"),
+        ];
+        let out = render(&ms, None, &SummaryOptions::default());
+        let sections: Vec<&str> = out.lines().filter(|l| l.starts_with('[')).collect();
+        // One scale is no reason to split; run order, not string order, between scales.
+        assert_eq!(
+            sections,
+            [
+                "[dataset_io]",
+                "[vsa_dataset scale=1m]",
+                "[vsa_dataset scale=10k]"
+            ]
+        );
+        assert!(
+            out.lines()
+                .nth(4)
+                .unwrap()
+                .starts_with("vsa_dataset.packed.bind{scale=1m}"),
+            "{out}"
+        );
+    }
+
+    #[test]
+    fn test_size_and_rate_rows() {
+        let mut size = m("vsa.packed.serialized_bytes", 0.0, json!({}));
+        size.unit = "bytes".to_string();
+        size.bytes_processed = Some(2_520);
+        let mut rate = m("vsa_dataset.packed.bind.ops_per_s", 1.5e6, json!({}));
+        rate.unit = UNIT_OPS_PER_S.to_string();
+        let ms = vec![size, rate, m("vsa.packed.bind", 40.0, json!({}))];
+        let out = render(
+            &ms,
+            None,
+            &SummaryOptions {
+                slowest: 1,
+                ..Default::default()
+            },
+        );
+        let row = out
+            .lines()
+            .find(|l| l.contains("serialized_bytes"))
+            .unwrap();
+
+        assert!(
+            row.contains("2520 B") && row.trim_end().ends_with('-'),
+            "{out}"
+        );
+        assert!(!row.contains("slowest"), "{out}");
+        assert!(out.contains("slowest #1"));
+
+        let row = out.lines().find(|l| l.contains(".ops_per_s")).unwrap();
+        assert!(row.contains("1.50M") && !row.contains("slowest"), "{out}");
+    }
+
+    #[test]
+    fn test_deltas_from_baseline() {
+        let base = report(vec![m("vsa.bind", 100.0, json!({}))]);
+        let cur = report(vec![
+            m("vsa.bind", 150.0, json!({})),
+            m("vsa.new_op", 1.0, json!({})),
+        ]);
+        let cmp = compare_reports(&base, &cur, &CompareOptions::default());
+        let out = render(&cur.measurements, Some(&cmp), &SummaryOptions::default());
+        let lines: Vec<&str> = out.lines().collect();
+
+        assert!(lines[0].contains("delta"));
+        assert!(lines[2].contains("+50.0%"), "{out}");
+        assert!(lines[3].split_whitespace().any(|w| w == "new"), "{out}");
+    }
+
+    #[test]
+    fn test_notes_follow_table() {
+        let opts = SummaryOptions {
+            notes: vec!["governor pinned".to_string(), "kernel 6.8".to_string()],
+            ..Default::default()
+        };
+        let out = render(&[m("vsa.bind", 10.0, json!({}))], None, &opts);
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(
+            lines[lines.len() - 2..],
+            ["note: governor pinned", "note: kernel 6.8"]
+        );
+        assert!(!render(&[], None, &SummaryOptions::default()).contains("note:"));
+    }
+}
@@ -0,0 +1,172 @@
+//! Plain-text table formatter for terminal summaries.
+//!
+//! Columns are sized to their widest cell (counted in chars, not bytes). A column may
+//! be given a maximum width, in which case longer cells are cut and end in `…`.
+
+/// Horizontal alignment of a column.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Right,
+}
+
+#[derive(Clone, Debug)]
+pub struct Column {
+    pub header: String,
+    pub align: Align,
+    /// Cells longer than this are truncated (`None` = unbounded).
+    pub max_width: Option<usize>,
+}
+
+impl Column {
+    pub fn left(header: &str) -> Self {
+        Self {
+            header: header.to_string(),
+            align: Align::Left,
+            max_width: None,
+        }
+    }
+
+    pub fn right(header: &str) -> Self {
+        Self {
+            align: Align::Right,
+            ..Self::left(header)
+        }
+    }
+
+    pub fn max_width(mut self, width: usize) -> Self {
+        self.max_width = Some(width);
+        self
+    }
+}
+
+enum Row {
+    Cells(Vec<String>),
+    /// A full-width line (e.g. a section header), printed as-is.
+    Section(String),
+}
+
+#[derive(Default)]
+pub struct Table {
+    columns: Vec<Column>,
+    rows: Vec<Row>,
+}
+
+impl Table {
+    pub fn new(columns: Vec<Column>) -> Self {
+        Self {
+            columns,
+            rows: Vec::new(),
+        }
+    }
+
+    /// Append a row; missing trailing cells are left blank and extra cells are ignored.
+    pub fn row<S: Into<String>>(&mut self, cells: impl IntoIterator<Item = S>) {
+        let mut cells: Vec<String> = cells.into_iter().map(Into::into).collect();
+        cells.resize(self.columns.len(), String::new());
+        self.rows.push(Row::Cells(cells));
+    }
+
+    pub fn section(&mut self, title: impl Into<String>) {
+        self.rows.push(Row::Section(title.into()));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    fn widths(&self) -> Vec<usize> {
+        let mut widths: Vec<usize> = self.columns.iter().map(|c| char_len(&c.header)).collect();
+        for row in &self.rows {
+            let Row::Cells(cells) = row else { continue };
+            for (w, cell) in widths.iter_mut().zip(cells) {
+                *w = (*w).max(char_len(cell));
+            }
+        }
+        for (w, c) in widths.iter_mut().zip(&self.columns) {
+            if let Some(max) = c.max_width {
+                *w = (*w).min(max.max(1));
+            }
+        }
+        widths
+    }
+
+    /// Render with two spaces between columns and no trailing whitespace.
+    pub fn render(&self) -> String {
+        let widths = self.widths();
+        let line = |cells: &[String]| {
+            let parts: Vec<String> = cells
+                .iter()
+                .zip(&self.columns)
+                .zip(&widths)
+                .map(|((cell, col), &w)| {
+                    let cell = truncate(cell, w);
+                    match col.align {
+                        Align::Left => format!("{cell:<w$}"),
+                        Align::Right => format!("{cell:>w$}"),
+                    }
+                })
+                .collect();
+            parts.join("  ").trim_end().to_string()
+        };
+
+        let headers: Vec<String> = self.columns.iter().map(|c| c.header.clone()).collect();
+        let mut out = vec![line(&headers)];
+        for row in &self.rows {
+            match row {
+                Row::Cells(cells) => out.push(line(cells)),
+                Row::Section(title) => out.push(title.clone()),
+            }
+        }
+        let mut s = out.join("\n");
+        s.push('\n');
+        s
+    }
+}
+
+fn char_len(s: &str) -> usize {
+    s.chars().count()
+}
+
+fn truncate(s: &str, width: usize) -> String {
+    if char_len(s) <= width {
+        return s.to_string();
+    }
+    let mut out: String = s.chars().take(width.saturating_sub(1)).collect();
+    out.push('…');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_alignment_and_sections() {
+        let mut t = Table::new(vec![Column::left("name"), Column::right("ns")]);
+        t.section("[vsa]");
+        t.row(["vsa.bind", "12.5"]);
+        t.row(["vsa.bundle_many", "3"]);
+        t.row(["µs.unicode"]);
+
+        assert_eq!(
+            t.render(),
+            "name               ns\n\
+             [vsa]\n\
+             vsa.bind         12.5\n\
+             vsa.bundle_many     3\n\
+             µs.unicode\n"
+        );
+    }
+
+    #[test]
+    fn test_max_width_truncates() {
+        let mut t = Table::new(vec![Column::left("name").max_width(6), Column::left("x")]);
+        t.row(["abcdefghij", "1"]);
+        t.row(["abc", "2"]);
+        let out = t.render();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines, ["name    x", "abcde…  1", "abc     2"]);
+        assert!(Table::default().is_empty());
+    }
+}
@@ -0,0 +1,270 @@
+//! Reports as long-form CSV (`export tidy`), for pandas, R and the like.
+//!
+//! Every number in a measurement becomes a row of its own, next to the columns that say
+//! which run and which measurement it came from, so any of them can be pivoted against
+//! any other across runs:
+//!
+//! ```text
+//! run_id,timestamp_utc,profile,seed,git_sha,run_tags,measurement,unit,measurement_tags,metric,value
+//! report_vsa_all_quick_0_1700000000,unix:1700000000,quick,0,abc1234,,vsa.packed.bind,ns/iter,,ns_per_iter,41.5
+//! ```
+//!
+//! The columns are [`COLUMNS`] and do not change without a version bump. `run_id` is the
+//! report's file name without `.json`/`.json.gz`; tags are `key=value` pairs joined by
+//! `;`. The metrics are `ns_per_iter`, `iters`, `total_ns`, `bytes_processed` and
+//! `throughput_bytes_per_s` where set, `<p>_ms` for each entry of `extra.latency_ms`
+//! (the retrieval percentiles, named as in assertions), then each of the requested extra
+//! keys (dotted paths into `extra`) that a measurement has, named by the key.
+//!
+//! Reports are read and written out one at a time, so hundreds of them take no more
+//! memory than the largest.
+
+use crate::schema::{self, ContractBenchReport, Measurement};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, Write};
+use std::path::Path;
+
+/// The CSV header, in order.
+pub const COLUMNS: [&str; 11] = [
+    "run_id",
+    "timestamp_utc",
+    "profile",
+    "seed",
+    "git_sha",
+    "run_tags",
+    "measurement",
+    "unit",
+    "measurement_tags",
+    "metric",
+    "value",
+];
+
+/// Writes the rows of one report after another under a single header.
+pub struct TidyWriter<W: Write> {
+    out: W,
+    extra_keys: Vec<String>,
+    /// Extra keys already warned about as not numeric.
+    warned: BTreeSet<String>,
+    rows: u64,
+}
+
+impl<W: Write> TidyWriter<W> {
+    /// Write the header to `out`; `extra_keys` are the dotted `extra` paths to export.
+    pub fn new(mut out: W, extra_keys: Vec<String>) -> io::Result<Self> {
+        writeln!(out, "{}", COLUMNS.join(","))?;
+        Ok(Self {
+            out,
+            extra_keys,
+            warned: BTreeSet::new(),
+            rows: 0,
+        })
+    }
+
+    /// Rows written so far.
+    pub fn rows(&self) -> u64 {
+        self.rows
+    }
+
+    /// Write every metric of every measurement in `report`.
+    pub fn write_report(&mut self, run_id: &str, report: &ContractBenchReport) -> io::Result<()> {
+        let run = &report.run;
+        let run_cols = [
+            field(run_id),
+            field(&run.timestamp_utc),
+            field(&run.profile),
+            run.seed.to_string(),
+            field(run.git_sha.as_deref().unwrap_or("")),
+            field(&join_tags(&run.tags)),
+        ]
+        .join(",");
+        for m in &report.measurements {
+            let m_cols = [field(&m.name), field(&m.unit), field(&join_tags(&m.tags))].join(",");
+            for (metric, value) in self.metrics(m) {
+                writeln!(self.out, "{run_cols},{m_cols},{},{value}", field(&metric))?;
+                self.rows += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Flush and hand back the writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.out.flush()?;
+        Ok(self.out)
+    }
+
+    fn metrics(&mut self, m: &Measurement) -> Vec<(String, String)> {
+        let mut out = vec![
+            ("ns_per_iter".to_string(), m.ns_per_iter.to_string()),
+            ("iters".to_string(), m.iters.to_string()),
+            ("total_ns".to_string(), m.total_ns.to_string()),
+        ];
+        if let Some(b) = m.bytes_processed {
+            out.push(("bytes_processed".to_string(), b.to_string()));
+        }
+        if let Some(t) = m.throughput_bytes_per_s {
+            out.push(("throughput_bytes_per_s".to_string(), t.to_string()));
+        }
+        if let Some(latency) = m.extra.get("latency_ms").and_then(Value::as_object) {
+            for (p, v) in latency {
+                if let Some(v) = number(v) {
+                    out.push((format!("{p}_ms"), v));
+                }
+            }
+        }
+        for key in &self.extra_keys {
+            let Some(v) = key.split('.').try_fold(&m.extra, |v, k| v.get(k)) else {
+                continue;
+            };
+            match number(v) {
+                Some(v) => out.push((key.clone(), v)),
+                None if v.is_null() => {}
+                None => {
+                    if self.warned.insert(key.clone()) {
+                        eprintln!(
+                            "warning: skipping extra key {key}: not a number (first in {})",
+                            m.name
+                        );
+                    }
+                }
+            }
+        }
+        out
+    }
+}
+
+/// Export the reports at `paths`, in order, as tidy CSV to `out`, returning the number of
+/// rows written.
+pub fn export<P: AsRef<Path>>(
+    paths: &[P],
+    extra_keys: &[String],
+    out: impl Write,
+) -> io::Result<u64> {
+    let mut writer = TidyWriter::new(out, extra_keys.to_vec())?;
+    for path in paths {
+        let path = path.as_ref();
+        let report = schema::load_report(path)?;
+        writer.write_report(&run_id(path), &report)?;
+    }
+    let rows = writer.rows();
+    writer.finish()?;
+    Ok(rows)
+}
+
+/// A report's file name without its `.json`/`.json.gz` extension.
+pub fn run_id(path: &Path) -> String {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let name = name.strip_suffix(".gz").unwrap_or(&name);
+    name.strip_suffix(".json").unwrap_or(name).to_string()
+}
+
+/// JSON integers as written, floats in Rust's shortest round-tripping form.
+fn number(v: &Value) -> Option<String> {
+    match v {
+        Value::Number(n) if n.is_f64() => n.as_f64().map(|f| f.to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+fn join_tags(tags: &BTreeMap<String, String>) -> String {
+    tags.iter()
+        .map(|(k, v)| format!("{k}={v}"))
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+/// `s` as a CSV field, quoted when it has to be (RFC 4180).
+fn field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::RunMeta;
+
+    fn report() -> ContractBenchReport {
+        let mut run: RunMeta = serde_json::from_value(serde_json::json!({
+            "schema_version": 2,
+            "bench_version": "0.1.0",
+            "profile": "quick",
+            "seed": 7,
+            "timestamp_utc": "unix:1700000000",
+            "git_sha": "abc1234",
+        }))
+        .unwrap();
+        run.tags.insert("branch".to_string(), "main".to_string());
+        run.tags.insert("note".to_string(), "a, \"b\"".to_string());
+        let m = |name: &str, extra: Value| Measurement {
+            name: name.to_string(),
+            unit: "ns/iter".to_string(),
+            iters: 300,
+            warmup_iters: 10,
+            total_ns: 12_450,
+            ns_per_iter: 41.5,
+            bytes_processed: None,
+            throughput_bytes_per_s: None,
+            extra,
+            tags: Default::default(),
+        };
+        let mut query = m(
+            "retrieval.query",
+            serde_json::json!({
+                "latency_ms": {"p50": 0.25, "p99": 1},
+                "recall_at_k": 0.9,
+                "codec": "zstd",
+            }),
+        );
+        query.bytes_processed = Some(4096);
+        query.tags.insert("scale".to_string(), "10k".to_string());
+        ContractBenchReport {
+            run,
+            measurements: vec![m("vsa.packed.bind", serde_json::json!({})), query],
+        }
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let mut w = TidyWriter::new(
+            Vec::new(),
+            vec!["recall_at_k".to_string(), "codec".to_string()],
+        )
+        .unwrap();
+        w.write_report("run_a", &report()).unwrap();
+        assert_eq!(w.rows(), 10);
+        let csv = String::from_utf8(w.finish().unwrap()).unwrap();
+        let run = "run_a,unix:1700000000,quick,7,abc1234,\"branch=main;note=a, \"\"b\"\"\"";
+        let expected = [
+            COLUMNS.join(","),
+            format!("{run},vsa.packed.bind,ns/iter,,ns_per_iter,41.5"),
+            format!("{run},vsa.packed.bind,ns/iter,,iters,300"),
+            format!("{run},vsa.packed.bind,ns/iter,,total_ns,12450"),
+            format!("{run},retrieval.query,ns/iter,scale=10k,ns_per_iter,41.5"),
+            format!("{run},retrieval.query,ns/iter,scale=10k,iters,300"),
+            format!("{run},retrieval.query,ns/iter,scale=10k,total_ns,12450"),
+            format!("{run},retrieval.query,ns/iter,scale=10k,bytes_processed,4096"),
+            format!("{run},retrieval.query,ns/iter,scale=10k,p50_ms,0.25"),
+            format!("{run},retrieval.query,ns/iter,scale=10k,p99_ms,1"),
+            // `codec` is a string: warned about and left out.
+            format!("{run},retrieval.query,ns/iter,scale=10k,recall_at_k,0.9"),
+        ];
+        assert_eq!(csv.lines().collect::<Vec<_>>(), expected);
+        assert_eq!(
+            COLUMNS.join(","),
+            "run_id,timestamp_utc,profile,seed,git_sha,run_tags,measurement,unit,measurement_tags,metric,value"
+        );
+    }
+
+    #[test]
+    fn test_run_id() {
+        assert_eq!(run_id(Path::new("out/report_vsa_1.json")), "report_vsa_1");
+        assert_eq!(run_id(Path::new("report_vsa_1.json.gz")), "report_vsa_1");
+        assert_eq!(run_id(Path::new("plain")), "plain");
+    }
+}
@@ -0,0 +1,336 @@
+//! Trend detection over a directory of reports (`trend --reports-dir`).
+//!
+//! Each measurement's `ns_per_iter` across the reports, oldest first, forms a series
+//! ([`group_series`]). Its latest value is scored against the median of up to `window`
+//! earlier values, in median absolute deviations (MADs); a score beyond `mads` flags it
+//! as a regression or improvement, with rates (`.../s`) treated as higher-is-better like
+//! in compare. The MAD is floored at `min_mad_ratio` of the median, so a perfectly steady
+//! history does not turn the smallest wobble into an infinite score.
+//!
+//! Runs that lack a measurement are left out of its history. A measurement missing from
+//! the latest report, or with fewer than `min_history` earlier values, is listed but not
+//! scored.
+
+use crate::compare::display_key;
+use crate::schema::{group_series, ContractBenchReport, MeasurementSeries};
+use crate::summary::format_ns;
+use crate::table::{Column, Table};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+#[derive(Clone, Debug)]
+pub struct TrendOptions {
+    /// Distance from the trailing median, in MADs, beyond which the latest value is
+    /// flagged.
+    pub mads: f64,
+    /// Earlier values (the most recent ones) the median and MAD are taken over.
+    pub window: usize,
+    /// Earlier values needed before a measurement is scored.
+    pub min_history: usize,
+    /// Floor of the MAD as a fraction of the median.
+    pub min_mad_ratio: f64,
+    /// Tag keys that must match (in addition to the name) for measurements to align.
+    pub match_tags: Vec<String>,
+}
+
+impl Default for TrendOptions {
+    fn default() -> Self {
+        Self {
+            mads: 3.0,
+            window: 10,
+            min_history: 3,
+            min_mad_ratio: 0.01,
+            match_tags: Vec::new(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrendStatus {
+    Regression,
+    Improvement,
+    Steady,
+    /// Fewer than `min_history` earlier values.
+    ShortHistory,
+    /// Not in the latest report.
+    Missing,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct TrendEntry {
+    pub name: String,
+    /// The subset of tags used for alignment.
+    pub tags: BTreeMap<String, String>,
+    pub unit: String,
+    /// Reports that have the measurement.
+    pub runs: usize,
+    pub latest: Option<f64>,
+    /// Median and (floored) MAD of the trailing window.
+    pub median: Option<f64>,
+    pub mad: Option<f64>,
+    /// `(latest - median) / mad`; positive means larger.
+    pub score: Option<f64>,
+    pub status: TrendStatus,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct TrendRun {
+    pub path: PathBuf,
+    pub timestamp_utc: String,
+    pub git_sha: Option<String>,
+    /// The report's `--note`s.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct TrendReport {
+    /// The reports, oldest first; the last one is scored.
+    pub runs: Vec<TrendRun>,
+    pub mads: f64,
+    pub window: usize,
+    pub match_tags: Vec<String>,
+    pub entries: Vec<TrendEntry>,
+    /// Display keys of the regressed and improved measurements.
+    pub flagged: Vec<String>,
+}
+
+impl TrendReport {
+    pub fn regressions(&self) -> usize {
+        self.entries
+            .iter()
+            .filter(|e| e.status == TrendStatus::Regression)
+            .count()
+    }
+
+    /// The trend table: one row per measurement, flagged ones marked.
+    pub fn render(&self) -> String {
+        let mut table = Table::new(vec![
+            Column::left("measurement").max_width(64),
+            Column::right("runs"),
+            Column::right("median"),
+            Column::right("latest"),
+            Column::right("MADs"),
+            Column::left("status"),
+        ]);
+        for e in &self.entries {
+            let value = |v: Option<f64>| match v {
+                Some(v) if e.unit.starts_with("ns/") => format_ns(v),
+                Some(v) => format!("{v:.1} {}", e.unit),
+                None => "-".to_string(),
+            };
+            let status = match e.status {
+                TrendStatus::Regression => "REGRESSION",
+                TrendStatus::Improvement => "IMPROVEMENT",
+                TrendStatus::Steady => "steady",
+                TrendStatus::ShortHistory => "short history",
+                TrendStatus::Missing => "missing",
+            };
+            table.row([
+                display_key(&e.name, &e.tags),
+                e.runs.to_string(),
+                value(e.median),
+                value(e.latest),
+                e.score
+                    .map_or_else(|| "-".to_string(), |s| format!("{s:+.1}")),
+                status.to_string(),
+            ]);
+        }
+
+        let mut out = format!(
+            "Trend over {} report(s), flagging beyond {} MADs of the last {} value(s)\n",
+            self.runs.len(),
+            self.mads,
+            self.window
+        );
+        for run in &self.runs {
+            for note in &run.notes {
+                out.push_str(&format!("note ({}): {note}\n", run.path.display()));
+            }
+        }
+        if !table.is_empty() {
+            out.push_str(&table.render());
+        }
+        out.push_str(&format!(
+            "{} flagged ({} regression(s))\n",
+            self.flagged.len(),
+            self.regressions()
+        ));
+        out
+    }
+}
+
+/// Median of `values`, which must be non-empty (reordered in place).
+fn median(values: &mut [f64]) -> f64 {
+    values.sort_by(f64::total_cmp);
+    let mid = values.len() / 2;
+    if values.len() % 2 == 1 {
+        values[mid]
+    } else {
+        (values[mid - 1] + values[mid]) / 2.0
+    }
+}
+
+fn score(series: &MeasurementSeries, opts: &TrendOptions) -> TrendEntry {
+    let runs = series.values.iter().flatten().count();
+    let (latest, earlier) = match series.values.split_last() {
+        Some((&latest, earlier)) => (latest, earlier),
+        None => (None, &[][..]),
+    };
+    let mut history: Vec<f64> = earlier.iter().flatten().copied().collect();
+    let trailing = history.len().saturating_sub(opts.window);
+    let history = &mut history[trailing..];
+
+    let mut entry = TrendEntry {
+        name: series.name.clone(),
+        tags: series.tags.clone(),
+        unit: series.unit.clone(),
+        runs,
+        latest,
+        median: None,
+        mad: None,
+        score: None,
+        status: TrendStatus::Missing,
+    };
+    let Some(latest) = latest else {
+        return entry;
+    };
+    if history.is_empty() || history.len() < opts.min_history {
+        entry.status = TrendStatus::ShortHistory;
+        return entry;
+    }
+
+    let med = median(history);
+    let mut deviations: Vec<f64> = history.iter().map(|v| (v - med).abs()).collect();
+    let mad = median(&mut deviations).max(med.abs() * opts.min_mad_ratio);
+    let score = if mad > 0.0 { (latest - med) / mad } else { 0.0 };
+    // Larger is worse for times and sizes, better for rates.
+    let worse = if series.unit.ends_with("/s") {
+        -score
+    } else {
+        score
+    };
+    entry.median = Some(med);
+    entry.mad = Some(mad);
+    entry.score = Some(score);
+    entry.status = if worse > opts.mads {
+        TrendStatus::Regression
+    } else if worse < -opts.mads {
+        TrendStatus::Improvement
+    } else {
+        TrendStatus::Steady
+    };
+    entry
+}
+
+/// Score the latest of `reports` (oldest first, as from
+/// [`crate::schema::load_reports_dir`]) against the ones before it.
+pub fn analyze(reports: &[(PathBuf, ContractBenchReport)], opts: &TrendOptions) -> TrendReport {
+    let series = group_series(reports.iter().map(|(_, r)| r), &opts.match_tags);
+    let entries: Vec<TrendEntry> = series.iter().map(|s| score(s, opts)).collect();
+    let flagged = entries
+        .iter()
+        .filter(|e| matches!(e.status, TrendStatus::Regression | TrendStatus::Improvement))
+        .map(|e| display_key(&e.name, &e.tags))
+        .collect();
+    TrendReport {
+        runs: reports
+            .iter()
+            .map(|(path, r)| TrendRun {
+                path: path.clone(),
+                timestamp_utc: r.run.timestamp_utc.clone(),
+                git_sha: r.run.git_sha.clone(),
+                notes: r.run.notes.clone(),
+            })
+            .collect(),
+        mads: opts.mads,
+        window: opts.window,
+        match_tags: opts.match_tags.clone(),
+        entries,
+        flagged,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::load_reports_dir;
+    use std::path::Path;
+
+    #[test]
+    fn test_step_change_fixtures() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/trend");
+        let reports = load_reports_dir(&dir).unwrap();
+        // `notes.json` is not a report; `unix:999` sorts before `unix:1000`.
+        let stamps: Vec<&str> = reports
+            .iter()
+            .map(|(_, r)| r.run.timestamp_utc.as_str())
+            .collect();
+        assert_eq!(
+            stamps,
+            [
+                "unix:999",
+                "unix:1000",
+                "unix:1100",
+                "unix:1200",
+                "unix:1300",
+                "unix:1400"
+            ]
+        );
+
+        let trend = analyze(&reports, &TrendOptions::default());
+        assert_eq!(trend.flagged, ["vsa.packed.bind"]);
+        assert_eq!(trend.regressions(), 1);
+
+        let entry = |name: &str| trend.entries.iter().find(|e| e.name == name).unwrap();
+        let bind = entry("vsa.packed.bind");
+        assert_eq!((bind.runs, bind.latest), (6, Some(150.0)));
+        assert!(bind.score.unwrap() > 3.0);
+        // Steady despite jitter, and despite two runs without it.
+        let bundle = entry("vsa.packed.bundle");
+        assert_eq!((bundle.runs, bundle.status), (4, TrendStatus::Steady));
+        assert_eq!(entry("vsa.packed.dot").status, TrendStatus::Steady);
+        assert_eq!(entry("retired.op").status, TrendStatus::Missing);
+        assert_eq!(entry("brand.new").status, TrendStatus::ShortHistory);
+
+        let text = trend.render();
+        assert!(text.contains("REGRESSION"), "{text}");
+        assert!(text.contains("1 flagged (1 regression(s))"), "{text}");
+        assert!(
+            text.contains("report_vsa_packed_quick_0_1400.json): kernel 6.8, governor unpinned"),
+            "{text}"
+        );
+        assert_eq!(trend.runs[5].notes.len(), 1);
+    }
+
+    #[test]
+    fn test_rates_and_mad_floor() {
+        let series = |unit: &str, values: &[f64]| MeasurementSeries {
+            name: "x".to_string(),
+            tags: BTreeMap::new(),
+            unit: unit.to_string(),
+            values: values.iter().copied().map(Some).collect(),
+        };
+        let opts = TrendOptions::default();
+
+        // A flat history floors the MAD at 1% of the median: 0.5% off is steady.
+        let e = score(&series("ns/iter", &[100.0, 100.0, 100.0, 100.5]), &opts);
+        assert_eq!((e.mad, e.status), (Some(1.0), TrendStatus::Steady));
+        let e = score(&series("ns/iter", &[100.0, 100.0, 100.0, 96.0]), &opts);
+        assert_eq!(e.status, TrendStatus::Improvement);
+        // A throughput drop is the regression.
+        let e = score(&series("ops/s", &[100.0, 100.0, 100.0, 96.0]), &opts);
+        assert_eq!(e.status, TrendStatus::Regression);
+
+        // Only the trailing window counts.
+        let old_then_new = [500.0, 500.0, 500.0, 100.0, 100.0, 100.0, 100.0];
+        let windowed = TrendOptions {
+            window: 3,
+            ..TrendOptions::default()
+        };
+        let e = score(&series("ns/iter", &old_then_new), &windowed);
+        assert_eq!((e.median, e.status), (Some(100.0), TrendStatus::Steady));
+    }
+}
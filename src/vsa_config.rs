@@ -0,0 +1,149 @@
+//! `--vsa-config`: the `ReversibleVSAConfig` the benches encode with.
+//!
+//! Deployments tune the encoding parameters, so a report taken under the library
+//! defaults says little about them. A JSON file with any of the config's public fields
+//! ([`FIELDS`]) replaces those defaults for every bench that encodes data: the vsa
+//! inputs, encode and retrieval. Unknown fields and values that are not non-negative
+//! integers are errors naming the field.
+//!
+//! The effective config is recorded as `RunMeta::vsa_config`, and `compare` flags two
+//! reports taken under different ones (`vsa_config_mismatch`).
+
+use crate::error::BenchError;
+use embeddenator::ReversibleVSAConfig;
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::Path;
+
+/// The public fields of `ReversibleVSAConfig`, as they are spelled in the file.
+pub const FIELDS: [&str; 4] = [
+    "block_size",
+    "max_path_depth",
+    "base_shift",
+    "target_sparsity",
+];
+
+/// A `ReversibleVSAConfig`, comparable and serializable.
+///
+/// Unknown fields are ignored when it is read back from a report, so reports from a
+/// build that records more fields still load; [`VsaConfig::from_json`] rejects them in
+/// `--vsa-config` files.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VsaConfig {
+    pub block_size: usize,
+    pub max_path_depth: usize,
+    pub base_shift: usize,
+    pub target_sparsity: usize,
+}
+
+impl Default for VsaConfig {
+    /// The library's defaults.
+    fn default() -> Self {
+        Self::of(&ReversibleVSAConfig::default())
+    }
+}
+
+impl VsaConfig {
+    pub fn of(config: &ReversibleVSAConfig) -> Self {
+        Self {
+            block_size: config.block_size,
+            max_path_depth: config.max_path_depth,
+            base_shift: config.base_shift,
+            target_sparsity: config.target_sparsity,
+        }
+    }
+
+    /// The config to encode with; fields the library has beyond [`FIELDS`] keep their
+    /// defaults.
+    #[allow(clippy::needless_update)]
+    pub fn config(&self) -> ReversibleVSAConfig {
+        ReversibleVSAConfig {
+            block_size: self.block_size,
+            max_path_depth: self.max_path_depth,
+            base_shift: self.base_shift,
+            target_sparsity: self.target_sparsity,
+            ..ReversibleVSAConfig::default()
+        }
+    }
+
+    /// The defaults with the fields of `value`, a JSON object, replaced.
+    pub fn from_json(value: &serde_json::Value) -> Result<Self, BenchError> {
+        let object = value
+            .as_object()
+            .ok_or_else(|| BenchError::invalid_args("vsa config: expected a JSON object"))?;
+        let mut out = Self::default();
+        for (key, v) in object {
+            let slot = match key.as_str() {
+                "block_size" => &mut out.block_size,
+                "max_path_depth" => &mut out.max_path_depth,
+                "base_shift" => &mut out.base_shift,
+                "target_sparsity" => &mut out.target_sparsity,
+                _ => {
+                    return Err(BenchError::invalid_args(format!(
+                        "vsa config: unknown field `{key}` (expected one of {})",
+                        FIELDS.join(", ")
+                    )))
+                }
+            };
+            *slot = v
+                .as_u64()
+                .and_then(|n| usize::try_from(n).ok())
+                .ok_or_else(|| {
+                    BenchError::invalid_args(format!(
+                        "vsa config: field `{key}`: expected a non-negative integer, got {v}"
+                    ))
+                })?;
+        }
+        if out.block_size == 0 {
+            return Err(BenchError::invalid_args(
+                "vsa config: field `block_size`: must be at least 1",
+            ));
+        }
+        Ok(out)
+    }
+
+    /// Read a `--vsa-config` file.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        let value: serde_json::Value = serde_json::from_str(&text)
+            .map_err(|e| BenchError::invalid_args(format!("{}: not JSON: {e}", path.display())))?;
+        Self::from_json(&value)
+            .map_err(|e| BenchError::invalid_args(format!("{}: {e}", path.display())).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_from_json_overrides_defaults() {
+        let defaults = VsaConfig::default();
+        assert_eq!(VsaConfig::from_json(&json!({})).unwrap(), defaults);
+        let tuned =
+            VsaConfig::from_json(&json!({"block_size": 64, "target_sparsity": 50})).unwrap();
+        assert_eq!(
+            tuned,
+            VsaConfig {
+                block_size: 64,
+                target_sparsity: 50,
+                ..defaults.clone()
+            }
+        );
+        assert_eq!(VsaConfig::of(&tuned.config()), tuned);
+
+        let err = |v: serde_json::Value| VsaConfig::from_json(&v).unwrap_err().to_string();
+        assert!(err(json!({"blocksize": 64})).contains("unknown field `blocksize`"));
+        assert!(err(json!({"base_shift": -1})).contains("field `base_shift`"));
+        assert!(err(json!({"max_path_depth": "10"})).contains("field `max_path_depth`"));
+        assert!(err(json!({"block_size": 0})).contains("field `block_size`"));
+        assert!(err(json!([1])).contains("JSON object"));
+
+        // As recorded in a report, fields this build does not know are skipped.
+        let recorded: VsaConfig =
+            serde_json::from_value(json!({"block_size": 64, "later_field": 1})).unwrap();
+        assert_eq!(recorded.block_size, 64);
+    }
+}
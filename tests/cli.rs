@@ -0,0 +1,1163 @@
+//! End-to-end checks against the built binary.
+
+use std::path::Path;
+use std::process::Command;
+
+fn bench_bin() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_embeddenator-contract-bench"))
+}
+
+#[test]
+fn test_out_dir_auto_names_report() {
+    let dir = tempfile::tempdir().unwrap();
+    let out_dir = dir.path().join("reports");
+
+    let status = bench_bin()
+        .args([
+            "vsa",
+            "--variant",
+            "packed",
+            "--profile",
+            "quick",
+            "--seed",
+            "7",
+        ])
+        .arg("--out-dir")
+        .arg(&out_dir)
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let names: Vec<String> = std::fs::read_dir(&out_dir)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+        .collect();
+    assert_eq!(names.len(), 1, "{names:?}");
+    let name = &names[0];
+    assert!(name.starts_with("report_vsa_packed_quick_7_"), "{name}");
+    let ts = name
+        .trim_start_matches("report_vsa_packed_quick_7_")
+        .trim_end_matches(".json");
+    assert!(ts.parse::<u64>().is_ok(), "{name}");
+
+    let report =
+        embeddenator_contract_bench::schema::load_report(Path::new(&out_dir).join(name)).unwrap();
+    assert!(!report.measurements.is_empty());
+}
+
+#[test]
+fn test_out_and_out_dir_conflict() {
+    let dir = tempfile::tempdir().unwrap();
+    let status = bench_bin()
+        .args(["vsa", "--out", "x.json", "--out-dir"])
+        .arg(dir.path())
+        .status()
+        .unwrap();
+    assert!(!status.success());
+}
+
+#[test]
+fn test_zero_concurrency_rejected() {
+    let out = bench_bin()
+        .args(["retrieval", "--concurrency", "2,0"])
+        .output()
+        .unwrap();
+    assert!(!out.status.success());
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("--concurrency"), "{stderr}");
+}
+
+#[test]
+fn test_unwritable_out_fails_before_running() {
+    let dir = tempfile::tempdir().unwrap();
+    let status_path = dir.path().join("status.json");
+    let out = dir.path().join("missing/sub/report.json");
+    let vsa = || {
+        let mut cmd = bench_bin();
+        cmd.args(["vsa", "--variant", "packed", "--ops", "dot", "--quiet"]);
+        cmd
+    };
+
+    let output = vsa()
+        .arg("--out")
+        .arg(&out)
+        .arg("--status-file")
+        .arg(&status_path)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("--create-out-dir"), "{stderr}");
+    assert!(load_status(&status_path).sections_run.is_empty());
+
+    let created = vsa()
+        .arg("--out")
+        .arg(&out)
+        .arg("--create-out-dir")
+        .status()
+        .unwrap();
+    assert!(created.success());
+    assert!(out.is_file());
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let locked = dir.path().join("locked");
+        std::fs::create_dir(&locked).unwrap();
+        std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o555)).unwrap();
+        // Permissions don't bind a privileged user; nothing to check then.
+        if std::fs::File::create(locked.join("probe")).is_err() {
+            let output = vsa()
+                .arg("--out")
+                .arg(locked.join("report.json"))
+                .output()
+                .unwrap();
+            assert!(!output.status.success());
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            assert!(stderr.contains("cannot write to"), "{stderr}");
+        }
+        std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+}
+
+#[test]
+fn test_assertions_checked_after_report() {
+    let dir = tempfile::tempdir().unwrap();
+    let out = dir.path().join("report.json");
+    let vsa = |assertion: &str| {
+        bench_bin()
+            .args([
+                "vsa",
+                "--variant",
+                "packed",
+                "--ops",
+                "dot",
+                "--quiet",
+                "--assert",
+            ])
+            .arg(assertion)
+            .arg("--out")
+            .arg(&out)
+            .output()
+            .unwrap()
+    };
+
+    let pass = vsa("vsa.packed.dot:ns_per_iter>0");
+    let stderr = String::from_utf8_lossy(&pass.stderr);
+    assert!(pass.status.success(), "{stderr}");
+    assert!(
+        stderr.contains("PASS vsa.packed.dot:ns_per_iter>0"),
+        "{stderr}"
+    );
+
+    std::fs::remove_file(&out).unwrap();
+    let fail = vsa("vsa.packed.dot:ns_per_iter<0");
+    let stderr = String::from_utf8_lossy(&fail.stderr);
+    assert!(!fail.status.success());
+    assert!(
+        stderr.contains("FAIL vsa.packed.dot:ns_per_iter<0"),
+        "{stderr}"
+    );
+    assert!(out.is_file(), "report written despite the violation");
+
+    let bad = vsa("ns_per_iter=0");
+    assert!(!bad.status.success());
+    assert!(String::from_utf8_lossy(&bad.stderr).contains("expected a comparison"));
+}
+
+#[test]
+fn test_vsa_dataset_from_stdin() {
+    use embeddenator_contract_bench::dataset::{write_dataset_streaming, GenerateConfig};
+    use std::process::Stdio;
+
+    let dir = tempfile::tempdir().unwrap();
+    let data = dir.path().join("small.embr");
+    let config = GenerateConfig {
+        count: 64,
+        ..Default::default()
+    };
+    write_dataset_streaming(&data, &config, 16).unwrap();
+    let out = dir.path().join("stdin.json");
+
+    let status = bench_bin()
+        .args(["vsa", "--variant", "packed", "--profile", "quick"])
+        .args(["--dataset", "-", "--max-ops", "20"])
+        .arg("--out")
+        .arg(&out)
+        .stdin(Stdio::from(std::fs::File::open(&data).unwrap()))
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let report = embeddenator_contract_bench::schema::load_report(&out).unwrap();
+    assert!(!report.measurements.is_empty());
+    for m in &report.measurements {
+        assert_eq!(m.extra["source"], "stdin", "{}", m.name);
+        assert_eq!(m.extra["dataset"], "<stdin>", "{}", m.name);
+    }
+}
+
+#[test]
+fn test_vsa_dataset_resume() {
+    use embeddenator_contract_bench::dataset::{write_dataset_streaming, GenerateConfig};
+
+    let dir = tempfile::tempdir().unwrap();
+    let data = dir.path().join("small.embr");
+    let config = GenerateConfig {
+        count: 64,
+        ..Default::default()
+    };
+    write_dataset_streaming(&data, &config, 16).unwrap();
+    let partial = dir.path().join("partial.jsonl");
+    let run = |extra: &[&str]| {
+        bench_bin()
+            .args(["vsa", "--variant", "packed", "--max-ops", "20", "--quiet"])
+            .arg("--dataset")
+            .arg(&data)
+            .arg("--resume")
+            .arg(&partial)
+            .args(extra)
+            .arg("--out")
+            .arg(dir.path().join("report.json"))
+            .status()
+            .unwrap()
+    };
+
+    assert!(!run(&["--stop-after", "2"]).success());
+    let lines: Vec<serde_json::Value> = std::fs::read_to_string(&partial)
+        .unwrap()
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 2);
+
+    assert!(run(&[]).success());
+    let report =
+        embeddenator_contract_bench::schema::load_report(dir.path().join("report.json")).unwrap();
+    let names: Vec<&str> = report
+        .measurements
+        .iter()
+        .map(|m| m.name.as_str())
+        .collect();
+    assert_eq!(
+        names,
+        [
+            "vsa_dataset.sparsevec.bundle",
+            "vsa_dataset.sparsevec.bind",
+            "vsa_dataset.sparsevec.cosine",
+            "vsa_dataset.sparsevec.dot",
+            "vsa_dataset.sparsevec.hamming_agreement",
+            "vsa_dataset.packed.bundle",
+            "vsa_dataset.packed.bind",
+            "vsa_dataset.packed.dot",
+        ]
+    );
+    // The first two are the checkpointed results, not reruns.
+    for (m, line) in report.measurements.iter().zip(&lines) {
+        assert_eq!(m.total_ns as u64, line["total_ns"].as_u64().unwrap());
+    }
+}
+
+#[test]
+fn test_vsa_multiple_datasets() {
+    use embeddenator_contract_bench::dataset::{write_dataset_streaming, GenerateConfig};
+
+    let dir = tempfile::tempdir().unwrap();
+    let data = |count: u64| {
+        let path = dir.path().join(format!("d{count}.embr"));
+        let config = GenerateConfig {
+            count,
+            ..Default::default()
+        };
+        write_dataset_streaming(&path, &config, 16).unwrap();
+        path
+    };
+    let (small, large) = (data(21), data(61));
+    let out = dir.path().join("report.json");
+    let run = |datasets: &[&Path], extra: &[&str]| {
+        let mut cmd = bench_bin();
+        cmd.args(["vsa", "--variant", "packed", "--quiet"]);
+        for d in datasets {
+            cmd.arg("--dataset").arg(d);
+        }
+        cmd.args(extra).arg("--out").arg(&out).status().unwrap()
+    };
+
+    assert!(run(&[&small, &large], &[]).success());
+    let report = embeddenator_contract_bench::schema::load_report(&out).unwrap();
+    let ops = |scale: &str| {
+        let ms: Vec<_> = report
+            .measurements
+            .iter()
+            .filter(|m| m.name == "vsa_dataset.packed.bind" && m.tags["scale"] == scale)
+            .collect();
+        assert_eq!(ms.len(), 1, "scale {scale}");
+        ms[0].extra["ops"].as_u64().unwrap()
+    };
+    assert_eq!((ops("21"), ops("61")), (10, 30));
+
+    // Same size twice: the second would collide; --keep-going reports the first anyway.
+    let missing = dir.path().join("missing.embr");
+    assert!(!run(&[&small, &small], &[]).success());
+    assert!(!run(&[&missing, &large], &["--keep-going"]).success());
+    let report = embeddenator_contract_bench::schema::load_report(&out).unwrap();
+    assert!(report.measurements.iter().all(|m| m.tags["scale"] == "61"));
+}
+
+fn load_status(path: &Path) -> embeddenator_contract_bench::status::RunStatus {
+    serde_json::from_slice(&std::fs::read(path).unwrap()).unwrap()
+}
+
+#[test]
+fn test_status_file_success_and_failure() {
+    let dir = tempfile::tempdir().unwrap();
+    let status_path = dir.path().join("status.json");
+
+    // generate-dataset returns before the report stage; the status is still written.
+    let ok = bench_bin()
+        .args(["generate-dataset", "--count", "10", "--output"])
+        .arg(dir.path())
+        .arg("--status-file")
+        .arg(&status_path)
+        .status()
+        .unwrap();
+    assert!(ok.success());
+    let s = load_status(&status_path);
+    assert_eq!(s.exit_code, 0);
+    assert_eq!(s.sections_run, ["generate-dataset"]);
+    assert!(s.sections_failed.is_empty());
+    assert!(s.run_id.starts_with("generate-dataset-"));
+
+    let failed = bench_bin()
+        .args(["dataset-info", "--status-file"])
+        .arg(&status_path)
+        .arg(dir.path().join("missing.embr"))
+        .status()
+        .unwrap();
+    assert!(!failed.success());
+    let s = load_status(&status_path);
+    assert_eq!(s.exit_code, 1);
+    assert_eq!(s.sections_failed.len(), 1);
+    assert_eq!(s.sections_failed[0].section, "dataset-info");
+    assert!(s.error.is_some());
+    assert_eq!(s.report_path, None);
+}
+
+#[test]
+fn test_dataset_info_reports_labels() {
+    use embeddenator_contract_bench::dataset::{
+        generate_dataset, write_dataset, write_dataset_shard, write_labeled_dataset,
+        GenerateConfig, ShardDescriptor,
+    };
+    let dir = tempfile::tempdir().unwrap();
+    let config = GenerateConfig {
+        count: 4,
+        ..Default::default()
+    };
+    let vectors = generate_dataset(&config).unwrap();
+    let plain = dir.path().join("plain.embr");
+    let labeled = dir.path().join("labeled.embr");
+    write_dataset(&plain, &vectors, &config).unwrap();
+    write_labeled_dataset(&labeled, &vectors, &[0, 0, 1, 1], &config).unwrap();
+
+    let info = |path: &Path| {
+        let out = bench_bin().arg("dataset-info").arg(path).output().unwrap();
+        assert!(out.status.success());
+        String::from_utf8(out.stderr).unwrap()
+    };
+    let text = info(&plain);
+    assert!(
+        text.contains("Format version: 1") && text.contains("Labels: no"),
+        "{text}"
+    );
+    let text = info(&labeled);
+    assert!(
+        text.contains("Format version: 2") && text.contains("Labels: yes"),
+        "{text}"
+    );
+    assert!(!text.contains("Header extension"), "{text}");
+
+    let shard = dir.path().join("shard.embr");
+    write_dataset_shard(&shard, &config, ShardDescriptor::new(1, 2, 4).unwrap(), 4).unwrap();
+    let text = info(&shard);
+    assert!(
+        text.contains("Header extension 1: shard 1 of 2 (start 2, total 4)"),
+        "{text}"
+    );
+}
+
+#[test]
+fn test_dataset_append() {
+    use embeddenator_contract_bench::dataset::{
+        generate_dataset, read_dataset_meta, write_dataset, GenerateConfig,
+    };
+    let dir = tempfile::tempdir().unwrap();
+    let config = GenerateConfig {
+        count: 4,
+        ..Default::default()
+    };
+    let path = dir.path().join("corpus.embr");
+    write_dataset(&path, &generate_dataset(&config).unwrap(), &config).unwrap();
+    let input = dir.path().join("new.jsonl");
+    std::fs::write(
+        &input,
+        "{\"pos\": [1, 5], \"neg\": [3]}\n{\"pos\": [2], \"neg\": []}\n",
+    )
+    .unwrap();
+
+    let out = bench_bin()
+        .args(["dataset-append", "--input"])
+        .arg(&input)
+        .arg("--into")
+        .arg(&path)
+        .output()
+        .unwrap();
+    let text = String::from_utf8(out.stderr).unwrap();
+    assert!(out.status.success(), "{text}");
+    assert!(text.contains("Vectors: 4 -> 6"), "{text}");
+    assert_eq!(read_dataset_meta(&path).unwrap().count, 6);
+    let out = bench_bin()
+        .arg("dataset-verify")
+        .arg(&path)
+        .output()
+        .unwrap();
+    assert!(out.status.success());
+
+    // A bad line fails the command and leaves the count alone.
+    std::fs::write(&input, "{\"pos\": [1]}\n").unwrap();
+    let out = bench_bin()
+        .args(["dataset-append", "--input"])
+        .arg(&input)
+        .arg("--into")
+        .arg(&path)
+        .output()
+        .unwrap();
+    assert!(!out.status.success());
+    assert!(String::from_utf8(out.stderr)
+        .unwrap()
+        .contains("new.jsonl:1: "));
+    assert_eq!(read_dataset_meta(&path).unwrap().count, 6);
+}
+
+#[test]
+fn test_dataset_info_sample() {
+    use embeddenator_contract_bench::dataset::{generate_dataset, write_dataset, GenerateConfig};
+    let dir = tempfile::tempdir().unwrap();
+    let config = GenerateConfig {
+        count: 4,
+        ..Default::default()
+    };
+    let path = dir.path().join("sample.embr");
+    write_dataset(&path, &generate_dataset(&config).unwrap(), &config).unwrap();
+
+    // More samples than vectors: every record, each once.
+    let out = bench_bin()
+        .arg("dataset-info")
+        .arg(&path)
+        .args(["--sample", "10", "--seed", "3", "--json"])
+        .output()
+        .unwrap();
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    let sample: serde_json::Value = serde_json::from_slice(&out.stdout).unwrap();
+    assert_eq!(sample["requested"], 10);
+    assert_eq!(sample["strategy"], "seeded");
+    assert_eq!(sample["seed"], 3);
+    let records = sample["records"].as_array().unwrap();
+    let indices: Vec<u64> = records
+        .iter()
+        .map(|r| r["index"].as_u64().unwrap())
+        .collect();
+    assert_eq!(indices, [0, 1, 2, 3]);
+    for r in records {
+        let pos_len = r["pos_len"].as_u64().unwrap();
+        assert!(pos_len > 0);
+        let head = r["pos_head"].as_array().unwrap();
+        assert_eq!(head.len() as u64, pos_len.min(8));
+        assert!(r["neg_len"].is_u64() && r["neg_head"].is_array());
+        assert!(r["density"].as_f64().unwrap() > 0.0);
+    }
+    for key in ["min", "max", "mean", "mean_nonzero"] {
+        assert!(sample["density"][key].is_f64(), "{key}: {sample}");
+    }
+
+    let out = bench_bin()
+        .arg("dataset-info")
+        .arg(&path)
+        .args(["--sample", "2"])
+        .output()
+        .unwrap();
+    assert!(out.status.success());
+    let text = String::from_utf8(out.stderr).unwrap();
+    assert!(
+        text.contains("Sample: 2 of 2 requested (first records)"),
+        "{text}"
+    );
+    assert!(text.contains("#1: pos "), "{text}");
+    assert!(out.stdout.is_empty());
+}
+
+#[test]
+fn test_suite_keep_going_partial() {
+    let dir = tempfile::tempdir().unwrap();
+    let status_path = dir.path().join("status.json");
+    let out = dir.path().join("report.json");
+
+    let status = bench_bin()
+        .args(["suite", "--variant", "packed", "--index", "--keep-going"])
+        .arg("--dataset")
+        .arg(dir.path().join("missing.embr"))
+        .arg("--out")
+        .arg(&out)
+        .arg("--status-file")
+        .arg(&status_path)
+        .status()
+        .unwrap();
+    assert!(!status.success());
+
+    let s = load_status(&status_path);
+    assert_eq!(s.exit_code, 1);
+    assert_eq!(
+        s.sections_run,
+        ["vsa", "vsa_dataset", "dataset_io", "index"]
+    );
+    let failed: Vec<&str> = s
+        .sections_failed
+        .iter()
+        .map(|f| f.section.as_str())
+        .collect();
+    assert_eq!(failed, ["vsa_dataset", "dataset_io"]);
+    assert_eq!(s.report_path.as_deref(), Some(out.as_path()));
+
+    // The sections that did run are in the report.
+    let report = embeddenator_contract_bench::schema::load_report(&out).unwrap();
+    assert!(report
+        .measurements
+        .iter()
+        .any(|m| m.name.starts_with("vsa.")));
+    assert!(report
+        .measurements
+        .iter()
+        .any(|m| m.name.starts_with("index.")));
+}
+
+#[test]
+fn test_suite_dataset_warns_dimension_skips() {
+    use embeddenator_contract_bench::dataset::{write_dataset_streaming, GenerateConfig};
+    let dir = tempfile::tempdir().unwrap();
+    let data = dir.path().join("wide.embr");
+    let config = GenerateConfig {
+        count: 16,
+        dimension: 100_000,
+        ..Default::default()
+    };
+    write_dataset_streaming(&data, &config, 16).unwrap();
+
+    let output = bench_bin()
+        .args(["suite", "--variant", "packed", "--section", "vsa_dataset"])
+        .arg("--dataset")
+        .arg(&data)
+        .arg("--out")
+        .arg(dir.path().join("report.json"))
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("warning: skipping vsa_dataset.sparsevec.bundle: "),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn test_suite_retrieval_k_clamped_to_corpus() {
+    let dir = tempfile::tempdir().unwrap();
+    let corpus = dir.path().join("corpus");
+    std::fs::create_dir(&corpus).unwrap();
+    for i in 0..5u8 {
+        let body: Vec<u8> = (0..2048u32)
+            .map(|j| (j as u8) ^ i.wrapping_mul(37))
+            .collect();
+        std::fs::write(corpus.join(format!("doc{i}.bin")), body).unwrap();
+    }
+    let out = dir.path().join("report.json");
+
+    let output = bench_bin()
+        .args([
+            "suite",
+            "--variant",
+            "packed",
+            "--retrieval-k",
+            "50",
+            "--quiet",
+        ])
+        .arg("--retrieval-input-dir")
+        .arg(&corpus)
+        .arg("--out")
+        .arg(&out)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("retrieval clamped k"));
+
+    let report = embeddenator_contract_bench::schema::load_report(&out).unwrap();
+    let m = report
+        .measurements
+        .iter()
+        .find(|m| m.name == "retrieval.query_pass_with_index")
+        .unwrap();
+    assert_eq!(m.extra["effective_k"], m.extra["stats"]["chunks"]);
+    assert!(m.extra["clamped"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|c| c == "k"));
+}
+
+#[test]
+fn test_duel_out_dir_name_and_report() {
+    let dir = tempfile::tempdir().unwrap();
+    let status = bench_bin()
+        .args([
+            "duel",
+            "--a",
+            "packed",
+            "--b",
+            "bitsliced",
+            "--blocks",
+            "4",
+            "--quiet",
+        ])
+        .arg("--out-dir")
+        .arg(dir.path())
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let entry = std::fs::read_dir(dir.path())
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap();
+    let name = entry.file_name().to_string_lossy().to_string();
+    assert!(
+        name.starts_with("report_duel_packed_bitsliced_quick_0_"),
+        "{name}"
+    );
+    let report = embeddenator_contract_bench::schema::load_report(entry.path()).unwrap();
+    let names: Vec<&str> = report
+        .measurements
+        .iter()
+        .map(|m| m.name.as_str())
+        .collect();
+    assert_eq!(names, ["duel.bind", "duel.bundle", "duel.similarity"]);
+    assert_eq!(report.measurements[0].extra["blocks"], 4);
+
+    // A substrate against a dataset file is not a like-for-like duel.
+    let status = bench_bin()
+        .args(["duel", "--a", "packed", "--b", "missing.embr", "--quiet"])
+        .output()
+        .unwrap();
+    assert!(!status.status.success());
+    assert!(String::from_utf8_lossy(&status.stderr).contains("both be substrates"));
+}
+
+#[test]
+fn test_dry_run_plans_without_report() {
+    let dir = tempfile::tempdir().unwrap();
+    let out = dir.path().join("report.json");
+    let output = bench_bin()
+        .args(["vsa", "--variant", "packed", "--dry-run-json", "--quiet"])
+        .arg("--out")
+        .arg(&out)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(!out.exists());
+
+    let plan: embeddenator_contract_bench::plan::Plan =
+        serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        (plan.subcommand.as_str(), plan.calibration_iters),
+        ("vsa", 3)
+    );
+    let bind = plan
+        .entries
+        .iter()
+        .find(|e| e.name == "vsa.packed.bind")
+        .unwrap();
+    assert_eq!(bind.iters, 300);
+    assert!(bind.estimated_ns.unwrap() > 0.0);
+
+    // Retrieval would ingest its corpus and brute-force the ground truth before timing
+    // anything, so it is listed instead of walked.
+    let corpus = dir.path().join("corpus");
+    std::fs::create_dir(&corpus).unwrap();
+    std::fs::write(corpus.join("a.txt"), "some text to chunk").unwrap();
+    let output = bench_bin()
+        .args(["retrieval", "--dry-run-json", "--quiet", "--input-dir"])
+        .arg(&corpus)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    assert!(output.stderr.is_empty(), "{output:?}");
+    let plan: embeddenator_contract_bench::plan::Plan =
+        serde_json::from_slice(&output.stdout).unwrap();
+    assert!(plan.entries.is_empty());
+    assert_eq!(
+        plan.unplanned
+            .iter()
+            .map(|u| u.section.as_str())
+            .collect::<Vec<_>>(),
+        ["retrieval"]
+    );
+
+    // Non-bench subcommands have nothing to plan.
+    let status = bench_bin()
+        .args(["dataset-info", "x.embr", "--dry-run"])
+        .status()
+        .unwrap();
+    assert!(!status.success());
+}
+
+#[test]
+fn test_trend_flags_step_change() {
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/trend");
+    let out = bench_bin()
+        .args(["trend", "--reports-dir"])
+        .arg(&fixtures)
+        .output()
+        .unwrap();
+    assert!(out.status.success());
+    let trend: serde_json::Value = serde_json::from_slice(&out.stdout).unwrap();
+    assert_eq!(trend["flagged"], serde_json::json!(["vsa.packed.bind"]));
+    assert_eq!(trend["runs"].as_array().unwrap().len(), 6);
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("REGRESSION"), "{stderr}");
+
+    let status = bench_bin()
+        .args(["trend", "--fail-on-regression", "--reports-dir"])
+        .arg(&fixtures)
+        .output()
+        .unwrap()
+        .status;
+    assert!(!status.success());
+}
+
+#[test]
+fn test_notes_and_invocation_recorded() {
+    let dir = tempfile::tempdir().unwrap();
+    let out = dir.path().join("report.json");
+    let output = bench_bin()
+        .args(["vsa", "--variant", "packed", "--note", "governor pinned"])
+        .args(["--note", "kernel 6.8", "--out"])
+        .arg(&out)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let report = embeddenator_contract_bench::schema::load_report(&out).unwrap();
+    assert_eq!(report.run.notes, ["governor pinned", "kernel 6.8"]);
+    assert_eq!(
+        report.run.schema_version,
+        embeddenator_contract_bench::schema::SCHEMA_VERSION
+    );
+    assert_eq!(report.run.invocation[1..4], ["vsa", "--variant", "packed"]);
+    assert!(report.run.invocation.contains(&"kernel 6.8".to_string()));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("note: governor pinned"), "{stderr}");
+
+    let cmp = bench_bin()
+        .args(["compare", "--baseline"])
+        .arg(&out)
+        .arg("--current")
+        .arg(&out)
+        .output()
+        .unwrap();
+    assert!(cmp.status.success());
+    let stderr = String::from_utf8_lossy(&cmp.stderr);
+    assert!(stderr.contains("current: kernel 6.8"), "{stderr}");
+}
+
+#[test]
+fn test_record_samples() {
+    let dir = tempfile::tempdir().unwrap();
+    let out = dir.path().join("report.json");
+    let status = bench_bin()
+        .args(["vsa", "--variant", "packed", "--record-samples", "8"])
+        .args(["--quiet", "--out"])
+        .arg(&out)
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let report = embeddenator_contract_bench::schema::load_report(&out).unwrap();
+    let sampled: Vec<_> = report
+        .measurements
+        .iter()
+        .filter_map(|m| m.extra.get("samples").map(|s| (m, s)))
+        .collect();
+    assert!(!sampled.is_empty());
+    for (m, samples) in sampled {
+        let ns = samples["ns"].as_array().unwrap();
+        assert!(!ns.is_empty() && ns.len() <= 8, "{}: {samples}", m.name);
+        assert!(samples["batch_iters"].as_u64().unwrap() >= 1);
+    }
+}
+
+#[test]
+fn test_suite_sections() {
+    let list = bench_bin()
+        .args(["suite", "--index", "--section", "index", "--list-sections"])
+        .output()
+        .unwrap();
+    assert!(list.status.success());
+    assert_eq!(String::from_utf8_lossy(&list.stdout), "index\n");
+
+    let all = bench_bin()
+        .args(["suite", "--index", "--list-sections"])
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8_lossy(&all.stdout), "vsa\nindex\n");
+
+    let unknown = bench_bin()
+        .args(["suite", "--section", "retrieval", "--list-sections"])
+        .output()
+        .unwrap();
+    assert!(!unknown.status.success());
+    let stderr = String::from_utf8_lossy(&unknown.stderr);
+    assert!(stderr.contains("no suite section `retrieval`"), "{stderr}");
+}
+
+#[test]
+fn test_compressed_reports() {
+    use embeddenator_contract_bench::gzip;
+
+    let dir = tempfile::tempdir().unwrap();
+    let reports = dir.path().join("reports");
+    let run = |out: &[&std::ffi::OsStr]| {
+        bench_bin()
+            .args(["vsa", "--variant", "packed", "--ops", "bind", "--quiet"])
+            .args(out)
+            .status()
+            .unwrap()
+    };
+    let gz = dir.path().join("a.json.gz");
+    let plain = dir.path().join("b.json");
+    assert!(run(&["--out".as_ref(), gz.as_os_str()]).success());
+    assert!(run(&["--out".as_ref(), plain.as_os_str()]).success());
+    assert!(run(&[
+        "--compress-out".as_ref(),
+        "--out-dir".as_ref(),
+        reports.as_os_str()
+    ])
+    .success());
+
+    assert!(gzip::is_gzip(&std::fs::read(&gz).unwrap()));
+    assert!(!gzip::is_gzip(&std::fs::read(&plain).unwrap()));
+    let a = embeddenator_contract_bench::schema::load_report(&gz).unwrap();
+    assert_eq!(a.measurements[0].name, "vsa.packed.bind");
+    let names: Vec<String> = std::fs::read_dir(&reports)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+        .collect();
+    assert!(names[0].ends_with(".json.gz"), "{names:?}");
+
+    let cmp = bench_bin()
+        .args(["compare", "--baseline"])
+        .arg(&gz)
+        .arg("--current")
+        .arg(&plain)
+        .output()
+        .unwrap();
+    assert!(
+        cmp.status.success(),
+        "{}",
+        String::from_utf8_lossy(&cmp.stderr)
+    );
+    let cmp: serde_json::Value = serde_json::from_slice(&cmp.stdout).unwrap();
+    assert_eq!(cmp["deltas"][0]["name"], "vsa.packed.bind");
+
+    // Compressed and plain reports side by side in one trend directory.
+    std::fs::copy(&plain, reports.join("b.json")).unwrap();
+    let trend = bench_bin()
+        .args(["trend", "--reports-dir"])
+        .arg(&reports)
+        .output()
+        .unwrap();
+    assert!(trend.status.success());
+    let trend: serde_json::Value = serde_json::from_slice(&trend.stdout).unwrap();
+    assert_eq!(trend["runs"].as_array().unwrap().len(), 2);
+
+    let mut corrupt = std::fs::read(&gz).unwrap();
+    corrupt.truncate(corrupt.len() - 10);
+    std::fs::write(&gz, corrupt).unwrap();
+    let cmp = bench_bin()
+        .args(["compare", "--baseline"])
+        .arg(&gz)
+        .arg("--current")
+        .arg(&plain)
+        .output()
+        .unwrap();
+    assert!(!cmp.status.success());
+    let stderr = String::from_utf8_lossy(&cmp.stderr);
+    assert!(stderr.contains("a.json.gz: corrupt gzip"), "{stderr}");
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn test_generate_refused_without_disk_space() {
+    let dir = tempfile::tempdir().unwrap();
+    let out = dir.path().join("datasets");
+    // About 8 PB of dataset.
+    let refused = bench_bin()
+        .args(["generate-dataset", "--count", "10000000000000", "--output"])
+        .arg(&out)
+        .output()
+        .unwrap();
+    assert!(!refused.status.success());
+    let stderr = String::from_utf8_lossy(&refused.stderr);
+    assert!(stderr.contains("not enough disk space"), "{stderr}");
+    assert!(stderr.contains("--ignore-space-check"), "{stderr}");
+    assert_eq!(std::fs::read_dir(&out).unwrap().count(), 0);
+}
+
+#[test]
+fn test_export_tidy() {
+    let dir = tempfile::tempdir().unwrap();
+    let report = dir.path().join("report_a.json");
+    let status = bench_bin()
+        .args([
+            "vsa",
+            "--variant",
+            "packed",
+            "--quiet",
+            "--tag",
+            "branch=main",
+        ])
+        .arg("--out")
+        .arg(&report)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let gz = dir.path().join("report_b.json.gz");
+    std::fs::write(
+        &gz,
+        embeddenator_contract_bench::gzip::compress(&std::fs::read(&report).unwrap()).unwrap(),
+    )
+    .unwrap();
+
+    let csv = dir.path().join("data.csv");
+    let export = bench_bin()
+        .args(["export", "tidy", "--extra-keys", "confidence,dispatch"])
+        .arg(&report)
+        .arg(&gz)
+        .arg("--out")
+        .arg(&csv)
+        .output()
+        .unwrap();
+    assert!(
+        export.status.success(),
+        "{}",
+        String::from_utf8_lossy(&export.stderr)
+    );
+    let csv = std::fs::read_to_string(&csv).unwrap();
+    let mut lines = csv.lines();
+    assert_eq!(
+        lines.next(),
+        Some(
+            embeddenator_contract_bench::tidy::COLUMNS
+                .join(",")
+                .as_str()
+        )
+    );
+    let bind: Vec<&str> = lines
+        .filter(|l| l.contains(",vsa.packed.bind,") && l.contains(",ns_per_iter,"))
+        .collect();
+    assert_eq!(bind.len(), 2, "{csv}");
+    assert!(bind[0].starts_with("report_a,") && bind[0].contains(",branch=main,"));
+    assert!(bind[1].starts_with("report_b,"));
+    // `confidence` is a word, not a number.
+    let stderr = String::from_utf8_lossy(&export.stderr);
+    assert!(stderr.contains("skipping extra key confidence"), "{stderr}");
+}
+
+#[test]
+fn test_vsa_config_recorded_and_compared() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("vsa.json");
+    std::fs::write(&config, r#"{"block_size": 64, "target_sparsity": 50}"#).unwrap();
+    let run = |out: &Path, extra: &[&std::ffi::OsStr]| {
+        bench_bin()
+            .args(["vsa", "--variant", "packed", "--ops", "bind", "--quiet"])
+            .args(extra)
+            .arg("--out")
+            .arg(out)
+            .output()
+            .unwrap()
+    };
+    let default = dir.path().join("default.json");
+    let tuned = dir.path().join("tuned.json");
+    assert!(run(&default, &[]).status.success());
+    assert!(run(&tuned, &["--vsa-config".as_ref(), config.as_os_str()])
+        .status
+        .success());
+
+    let load = |p: &Path| embeddenator_contract_bench::schema::load_report(p).unwrap();
+    let (a, b) = (load(&default).run, load(&tuned).run);
+    let (a, b) = (a.vsa_config.unwrap(), b.vsa_config.unwrap());
+    assert_eq!((b.block_size, b.target_sparsity), (64, 50));
+    assert_eq!(b.max_path_depth, a.max_path_depth);
+    assert_ne!(a, b);
+
+    let cmp = bench_bin()
+        .args(["compare", "--baseline"])
+        .arg(&default)
+        .arg("--current")
+        .arg(&tuned)
+        .output()
+        .unwrap();
+    assert!(cmp.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&cmp.stdout).unwrap();
+    assert_eq!(json["vsa_config_mismatch"]["current"]["block_size"], 64);
+    let stderr = String::from_utf8_lossy(&cmp.stderr);
+    assert!(stderr.contains("encoding config differs"), "{stderr}");
+
+    // A duel's contenders always encode with the defaults, so it records no config.
+    let duel = dir.path().join("duel.json");
+    let status = bench_bin()
+        .args(["duel", "--a", "packed", "--b", "bitsliced", "--blocks", "2"])
+        .args(["--quiet", "--vsa-config"])
+        .arg(&config)
+        .arg("--out")
+        .arg(&duel)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    assert!(load(&duel).run.vsa_config.is_none());
+
+    // Unknown fields are named.
+    std::fs::write(&config, r#"{"blocksize": 64}"#).unwrap();
+    let bad = run(
+        &dir.path().join("bad.json"),
+        &["--vsa-config".as_ref(), config.as_os_str()],
+    );
+    assert!(!bad.status.success());
+    let stderr = String::from_utf8_lossy(&bad.stderr);
+    assert!(stderr.contains("unknown field `blocksize`"), "{stderr}");
+}
+
+#[test]
+fn test_cold_start_first_and_not_gated() {
+    let dir = tempfile::tempdir().unwrap();
+    let run = |out: &Path| {
+        let o = bench_bin()
+            .args(["vsa", "--variant", "packed", "--ops", "cosine,bind"])
+            .args(["--cold-start", "--quiet", "--out"])
+            .arg(out)
+            .output()
+            .unwrap();
+        assert!(o.status.success(), "{}", String::from_utf8_lossy(&o.stderr));
+    };
+    let (base, cur) = (dir.path().join("base.json"), dir.path().join("cur.json"));
+    run(&base);
+    run(&cur);
+
+    let mut report = embeddenator_contract_bench::schema::load_report(&base).unwrap();
+    let names: Vec<&str> = report
+        .measurements
+        .iter()
+        .map(|m| m.name.as_str())
+        .collect();
+    // Fixed order, whatever order --ops gave.
+    assert_eq!(names[..2], ["vsa.coldstart.bind", "vsa.coldstart.cosine"]);
+    assert!(names[2..].iter().all(|n| !n.starts_with("vsa.coldstart.")));
+    for (i, m) in report.measurements[..2].iter().enumerate() {
+        assert_eq!((m.iters, m.warmup_iters), (1, 0));
+        assert_eq!(m.tags["variance"], "high");
+        assert_eq!(m.extra["position"], i);
+        assert_eq!(m.extra["order"], serde_json::json!(["bind", "cosine"]));
+    }
+
+    // A baseline far faster than any real first call: still no regression.
+    for m in &mut report.measurements[..2] {
+        m.ns_per_iter = 1.0;
+    }
+    std::fs::write(&base, serde_json::to_string(&report).unwrap()).unwrap();
+    let cmp = bench_bin()
+        .args(["compare", "--fail-on-regression", "--baseline"])
+        .arg(&base)
+        .arg("--current")
+        .arg(&cur)
+        .output()
+        .unwrap();
+    assert!(
+        cmp.status.success(),
+        "{}",
+        String::from_utf8_lossy(&cmp.stderr)
+    );
+    let json: serde_json::Value = serde_json::from_slice(&cmp.stdout).unwrap();
+    let verdict = |name: &str| {
+        json["deltas"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|d| d["name"] == name)
+            .unwrap()["verdict"]
+            .clone()
+    };
+    assert_eq!(verdict("vsa.coldstart.bind"), "informational");
+    assert_eq!(verdict("vsa.coldstart.cosine"), "informational");
+}
+
+#[test]
+fn test_generate_dataset_report() {
+    let dir = tempfile::tempdir().unwrap();
+    let report = dir.path().join("generate.json");
+    let gen = bench_bin()
+        .args([
+            "generate-dataset",
+            "--count",
+            "1000",
+            "--report",
+            "--output",
+        ])
+        .arg(dir.path().join("datasets"))
+        .arg("--out")
+        .arg(&report)
+        .output()
+        .unwrap();
+    assert!(
+        gen.status.success(),
+        "{}",
+        String::from_utf8_lossy(&gen.stderr)
+    );
+    // The human summary is still there.
+    let stderr = String::from_utf8_lossy(&gen.stderr);
+    assert!(stderr.contains("vec/s"), "{stderr}");
+
+    let report = embeddenator_contract_bench::schema::load_report(&report).unwrap();
+    let names: Vec<&str> = report
+        .measurements
+        .iter()
+        .map(|m| m.name.as_str())
+        .collect();
+    assert_eq!(
+        names,
+        [
+            "dataset.generate.vectors_per_s",
+            "dataset.generate.bytes_per_s",
+            "dataset.generate.wall_s"
+        ]
+    );
+    for m in &report.measurements {
+        assert!(m.ns_per_iter > 0.0, "{}: {}", m.name, m.ns_per_iter);
+        assert_eq!(m.tags["count"], "1000");
+        assert_eq!(m.tags["dimension"], "10000");
+        assert_eq!(m.tags["sparsity"], "100");
+        assert_eq!(m.tags["generator"], "v2");
+    }
+}
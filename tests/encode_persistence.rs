@@ -0,0 +1,78 @@
+//! Saving and loading the ingested engram and manifest, timed per codec.
+
+use embeddenator::CompressionCodec;
+use embeddenator_contract_bench::benches::encode::{self, CodecSpec, EncodeArgs};
+use embeddenator_contract_bench::benches::input_walk::WalkOptions;
+use embeddenator_contract_bench::harness::Profile;
+use embeddenator_contract_bench::BenchConfig;
+
+#[test]
+fn persistence_round_trip_is_measured() {
+    let corpus = tempfile::tempdir().unwrap();
+    for i in 0..6u32 {
+        let body: Vec<u8> = (0..4096u32).map(|j| (j * (i + 3) % 251) as u8).collect();
+        std::fs::write(corpus.path().join(format!("doc{i}.bin")), body).unwrap();
+    }
+    let args = EncodeArgs {
+        inputs: vec![corpus.path().to_path_buf()],
+        prefix: None,
+        codec: CompressionCodec::None,
+        codec_level: None,
+        verify: false,
+        parallel_hash: false,
+        // The run's codec again is not timed twice.
+        codec_sweep: vec![
+            CodecSpec::parse("none").unwrap(),
+            CodecSpec::parse("zstd:3").unwrap(),
+        ],
+        walk: WalkOptions::default(),
+        chunk_size: None,
+        vsa_config: Default::default(),
+        synthetic: None,
+    };
+    let cfg = BenchConfig {
+        profile: Profile::Quick,
+        seed: 0,
+    };
+    let ms = encode::run(&cfg, &args).unwrap();
+    let get = |name: &str| {
+        ms.iter()
+            .find(|m| m.name == name)
+            .unwrap_or_else(|| panic!("no {name}"))
+    };
+
+    let saves: Vec<&str> = ms
+        .iter()
+        .map(|m| m.name.as_str())
+        .filter(|n| n.starts_with("encode.save_engram."))
+        .collect();
+    assert_eq!(
+        saves,
+        ["encode.save_engram.none", "encode.save_engram.zstd-3"]
+    );
+    assert_eq!(get("encode.save_engram.zstd-3").tags["codec"], "zstd-3");
+
+    for name in [
+        "encode.save_engram.none",
+        "encode.load_engram",
+        "encode.save_manifest",
+        "encode.load_manifest",
+    ] {
+        let m = get(name);
+        let bytes = m.extra["file_bytes"].as_u64().unwrap();
+        assert!(bytes > 0, "{name}");
+        assert_eq!(m.bytes_processed, Some(bytes), "{name}");
+        assert!(m.extra["mb_per_s"].as_f64().unwrap() > 0.0, "{name}");
+        assert!(m.iters > 0 && m.ns_per_iter > 0.0, "{name}");
+    }
+    // The engram loaded is the one saved with the run's codec.
+    assert_eq!(
+        get("encode.load_engram").extra["file_bytes"],
+        get("encode.save_engram.none").extra["file_bytes"]
+    );
+    assert_eq!(
+        get("encode.load_manifest").extra["file_bytes"],
+        get("encode.save_manifest").extra["file_bytes"]
+    );
+    assert_eq!(get("encode.save_manifest").extra["manifest_files"], 6);
+}
@@ -0,0 +1,217 @@
+//! Mutation fuzzer for the dataset codec.
+//!
+//! Mutates small valid datasets (bit flips, interesting length/count values, truncation,
+//! splices) and feeds each result to every decoder: `DatasetReader` over memory, a
+//! stream and a file (plus `open_validated`), `load_dataset` and `MappedDataset`. A
+//! decoder may return any error, but must not panic, and no single allocation may be out
+//! of proportion to the input.
+//!
+//! ```text
+//! FUZZ_SECONDS=3600 cargo test --release --features fuzz --test fuzz_dataset
+//! ```
+//!
+//! `FUZZ_SECONDS` defaults to 30 and `FUZZ_SEED` to 0. A failing input is written to the
+//! temp directory and its path printed.
+#![cfg(feature = "fuzz")]
+
+use embeddenator::SparseVec;
+use embeddenator_contract_bench::dataset::{
+    load_dataset, write_dataset, write_dataset_streaming, DatasetReader, DatasetSource,
+    GenerateConfig, MappedDataset,
+};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// Records the largest single allocation since the last reset.
+struct Tracking;
+
+static LARGEST: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Tracking {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        LARGEST.fetch_max(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        LARGEST.fetch_max(new_size, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOC: Tracking = Tracking;
+
+/// Decoded indices are `usize` (2x the on-disk `u32`) and vectors double as they grow,
+/// on top of the readers' fixed 64 KiB buffers and preallocation cap.
+fn allocation_bound(input_len: usize) -> usize {
+    input_len * 8 + (1 << 20)
+}
+
+fn env_u64(name: &str, default: u64) -> u64 {
+    std::env::var(name)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+}
+
+/// Small valid datasets to mutate: empty, generated, and hand-built edge records.
+fn seed_corpus(dir: &std::path::Path) -> Vec<Vec<u8>> {
+    let write = |name: &str, vectors: &[SparseVec], dimension: usize| {
+        let path = dir.join(name);
+        let config = GenerateConfig {
+            count: vectors.len() as u64,
+            dimension,
+            ..Default::default()
+        };
+        write_dataset(&path, vectors, &config).unwrap();
+        std::fs::read(&path).unwrap()
+    };
+    let generated = |count: u64, dimension: usize, sparsity: usize| {
+        let path = dir.join("generated.embr");
+        let config = GenerateConfig {
+            count,
+            dimension,
+            sparsity,
+            seed: count,
+            ..Default::default()
+        };
+        write_dataset_streaming(&path, &config, 4).unwrap();
+        std::fs::read(&path).unwrap()
+    };
+    let v = |pos: &[usize], neg: &[usize]| SparseVec {
+        pos: pos.to_vec(),
+        neg: neg.to_vec(),
+    };
+
+    vec![
+        write("empty.embr", &[], 16),
+        write("empty_record.embr", &[v(&[], &[]), v(&[1], &[2])], 16),
+        write("dense.embr", &[v(&[0, 1, 2, 3], &[4, 5, 6, 7])], 8),
+        generated(6, 64, 3),
+        generated(3, 1000, 10),
+    ]
+}
+
+fn mutate(rng: &mut ChaCha8Rng, input: &mut Vec<u8>) {
+    const BYTES: [u8; 6] = [0, 1, 0x7f, 0x80, 0xfe, 0xff];
+    const U32S: [u32; 7] = [0, 1, 2, 0x7fff_ffff, 0x8000_0000, 0xffff_fffe, u32::MAX];
+    const U64S: [u64; 6] = [0, 1, 2, 1 << 32, 1 << 62, u64::MAX];
+
+    for _ in 0..rng.gen_range(1..=4) {
+        let len = input.len();
+        match rng.gen_range(0..7) {
+            0 if len > 0 => {
+                let i = rng.gen_range(0..len);
+                input[i] ^= 1 << rng.gen_range(0..8);
+            }
+            1 if len > 0 => {
+                let i = rng.gen_range(0..len);
+                input[i] = BYTES[rng.gen_range(0..BYTES.len())];
+            }
+            // Length prefixes sit at arbitrary 4-byte offsets after the header.
+            2 if len >= 4 => {
+                let i = rng.gen_range(0..=len - 4);
+                let v = U32S[rng.gen_range(0..U32S.len())];
+                input[i..i + 4].copy_from_slice(&v.to_le_bytes());
+            }
+            // Header count (12) or dimension (20).
+            3 if len >= 28 => {
+                let i = if rng.gen() { 12 } else { 20 };
+                let v = U64S[rng.gen_range(0..U64S.len())];
+                input[i..i + 8].copy_from_slice(&v.to_le_bytes());
+            }
+            4 if len > 0 => input.truncate(rng.gen_range(0..len)),
+            5 => {
+                let n = rng.gen_range(1..=16);
+                input.extend((0..n).map(|_| rng.gen::<u8>()));
+            }
+            6 if len > 0 => {
+                let start = rng.gen_range(0..len);
+                let end = rng.gen_range(start..=len.min(start + 64));
+                let at = rng.gen_range(0..=len);
+                let chunk = input[start..end].to_vec();
+                input.splice(at..at, chunk);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn drain(reader: std::io::Result<DatasetReader>) {
+    if let Ok(mut r) = reader {
+        for v in r.by_ref() {
+            if v.is_err() {
+                break;
+            }
+        }
+    }
+}
+
+/// Run every decoder over `input` (also written to `path`).
+fn decode_all(input: &[u8], path: &std::path::Path) {
+    drain(DatasetSource::Memory(input.into()).open());
+    drain(DatasetReader::from_stream(std::io::Cursor::new(
+        input.to_vec(),
+    )));
+
+    std::fs::write(path, input).unwrap();
+    drain(DatasetReader::open(path));
+    drain(DatasetReader::open_validated(path));
+    let _ = load_dataset(path);
+    if let Ok(mapped) = MappedDataset::open(path) {
+        for rec in mapped.iter() {
+            match rec {
+                Ok(v) => {
+                    std::hint::black_box(v.to_sparsevec());
+                }
+                Err(_) => break,
+            }
+        }
+    }
+}
+
+#[test]
+fn fuzz_dataset_decoders() {
+    let seconds = env_u64("FUZZ_SECONDS", 30);
+    let seed = env_u64("FUZZ_SEED", 0);
+    let dir = tempfile::tempdir().unwrap();
+    let corpus = seed_corpus(dir.path());
+    let path = dir.path().join("input.embr");
+
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let deadline = Instant::now() + Duration::from_secs(seconds);
+    let mut runs = 0u64;
+    while Instant::now() < deadline {
+        let mut input = corpus[rng.gen_range(0..corpus.len())].clone();
+        mutate(&mut rng, &mut input);
+
+        LARGEST.store(0, Ordering::Relaxed);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| decode_all(&input, &path)));
+        let largest = LARGEST.load(Ordering::Relaxed);
+        if result.is_err() || largest > allocation_bound(input.len()) {
+            let crash = std::env::temp_dir().join(format!("fuzz-dataset-{seed}-{runs}.embr"));
+            std::fs::write(&crash, &input).unwrap();
+            panic!(
+                "input {} ({} bytes): {}",
+                crash.display(),
+                input.len(),
+                if result.is_err() {
+                    "decoder panicked".to_string()
+                } else {
+                    format!("allocated {largest} bytes at once")
+                }
+            );
+        }
+        runs += 1;
+    }
+    eprintln!("fuzz_dataset: {runs} inputs in {seconds}s (seed {seed})");
+}
@@ -0,0 +1,35 @@
+//! Hashing a large file keeps memory flat (its own process, so the peak RSS is ours).
+
+#[cfg(target_os = "linux")]
+#[test]
+fn hash_large_file_in_constant_memory() {
+    use embeddenator_contract_bench::benches::encode::sha256_file;
+
+    // Peak resident set size, from /proc.
+    fn vm_hwm_kib() -> u64 {
+        let status = std::fs::read_to_string("/proc/self/status").unwrap();
+        let line = status.lines().find(|l| l.starts_with("VmHWM:")).unwrap();
+        line.split_whitespace().nth(1).unwrap().parse().unwrap()
+    }
+
+    const SIZE: u64 = 64 * 1024 * 1024;
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("zeros.bin");
+    // Sparse: takes no disk space.
+    std::fs::File::create(&path).unwrap().set_len(SIZE).unwrap();
+
+    let before = vm_hwm_kib();
+    let (digest, len) = sha256_file(&path).unwrap();
+    let grew_kib = vm_hwm_kib() - before;
+
+    assert_eq!(len, SIZE);
+    let hex: String = digest.iter().map(|b| format!("{b:02x}")).collect();
+    assert_eq!(
+        hex,
+        "3b6a07d0d404fab4e23b6d34bc6696a6a312dd92821332385e5af7c01c421351"
+    );
+    assert!(
+        grew_kib < 8 * 1024,
+        "peak RSS grew by {grew_kib} KiB hashing a {SIZE}-byte file"
+    );
+}
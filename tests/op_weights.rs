@@ -0,0 +1,98 @@
+//! Weighting the quick profile's op cap by cost evens out the time each dataset op takes.
+
+use embeddenator_contract_bench::benches::vsa::{
+    run_dataset, DatasetRunOptions, DATASET_OP_WEIGHTS,
+};
+use embeddenator_contract_bench::confidence::QUICK_DATASET_UNITS;
+use embeddenator_contract_bench::dataset::{
+    write_dataset_streaming, DatasetSource, GenerateConfig,
+};
+use embeddenator_contract_bench::harness::Profile;
+use embeddenator_contract_bench::schema::Measurement;
+use embeddenator_contract_bench::{BenchConfig, VsaVariant};
+use std::collections::BTreeMap;
+
+/// Coefficient of variation of the measurements' total times.
+fn spread(ms: &[Measurement]) -> f64 {
+    let n = ms.len() as f64;
+    let mean = ms.iter().map(|m| m.total_ns as f64).sum::<f64>() / n;
+    let var = ms
+        .iter()
+        .map(|m| (m.total_ns as f64 - mean).powi(2))
+        .sum::<f64>()
+        / n;
+    var.sqrt() / mean
+}
+
+#[test]
+fn quick_wall_times_converge_with_weights() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("weights.embr");
+    let config = GenerateConfig {
+        count: 2_001,
+        ..Default::default()
+    };
+    write_dataset_streaming(&path, &config, 4).unwrap();
+    let source = DatasetSource::File(path);
+    let cfg = BenchConfig {
+        profile: Profile::Quick,
+        seed: 0,
+    };
+    let run = |weights: &BTreeMap<String, f64>| {
+        let opts = DatasetRunOptions {
+            op_weights: weights.clone(),
+            ..Default::default()
+        };
+        run_dataset(&cfg, VsaVariant::All, &source, &opts).unwrap()
+    };
+    // A dataset smaller than the quick cap runs every op to its end, so scale the
+    // weights up by 20 to cap within it: 500 groups at the cheapest.
+    const SCALE: f64 = 20.0;
+
+    let equal: BTreeMap<String, f64> = DATASET_OP_WEIGHTS
+        .iter()
+        .map(|(name, _)| (name.to_string(), SCALE))
+        .collect();
+    let attempt = || {
+        // Equal weights: the same op count for every op, as before ops had costs.
+        let uniform = run(&equal);
+        assert_eq!(uniform.len(), DATASET_OP_WEIGHTS.len());
+        assert!(uniform.iter().all(|m| m.extra["ops"] == 500), "{uniform:?}");
+
+        // Weights from the measured cost per group: every op gets about the same time.
+        let fastest = uniform
+            .iter()
+            .map(|m| m.ns_per_iter)
+            .fold(f64::INFINITY, f64::min);
+        let weights: BTreeMap<String, f64> = uniform
+            .iter()
+            .map(|m| (m.name.clone(), SCALE * m.ns_per_iter / fastest))
+            .collect();
+        let weighted = run(&weights);
+        assert_eq!(weighted.len(), uniform.len());
+        for m in &weighted {
+            let weight = weights[&m.name];
+            assert_eq!(m.extra["op_weight"], weight, "{}", m.name);
+            assert_eq!(
+                m.extra["ops"],
+                (QUICK_DATASET_UNITS as f64 / weight).floor() as u64,
+                "{}",
+                m.name
+            );
+        }
+        (spread(&uniform), spread(&weighted))
+    };
+
+    // Timing, so only the direction is certain. A quarter off the spread leaves room for
+    // noise, and a busy moment can still spoil one attempt; weights that do nothing
+    // fail all three.
+    let mut spreads = Vec::new();
+    for _ in 0..3 {
+        let (before, after) = attempt();
+        spreads.push(format!("{before:.2} -> {after:.2}"));
+        if after < before * 0.75 {
+            return;
+        }
+    }
+    panic!("spread with equal weights -> weighted: {spreads:?}");
+}
@@ -0,0 +1,271 @@
+//! Measurement names are an API: this snapshots the full set each subcommand emits.
+//!
+//! The benches run under `harness::calibration`, which keeps every measurement but cuts
+//! its timed loop to one iteration. Any difference from `tests/fixtures/measurement_names.txt`
+//! fails. To accept one, bump `measurements::NAMESPACE_VERSION` and regenerate:
+//!
+//! ```text
+//! UPDATE_MEASUREMENT_NAMES=1 cargo test --test schema_contract
+//! ```
+//!
+//! Regenerating refuses to record a changed name set under an unchanged version.
+
+use clap::ValueEnum;
+use embeddenator::DIM;
+use embeddenator_contract_bench::benches::duel::{DuelArgs, DuelSide, DuelSubstrate};
+use embeddenator_contract_bench::benches::encode::EncodeArgs;
+use embeddenator_contract_bench::benches::retrieval::RetrievalArgs;
+use embeddenator_contract_bench::benches::soak::{SoakArgs, SoakOp};
+use embeddenator_contract_bench::benches::{self, vsa};
+use embeddenator_contract_bench::dataset::{
+    write_dataset_streaming, DatasetSource, GenerateConfig,
+};
+use embeddenator_contract_bench::harness::{calibration, BenchConfig, Profile};
+use embeddenator_contract_bench::measurements::NAMESPACE_VERSION;
+use embeddenator_contract_bench::ratios;
+use embeddenator_contract_bench::schema::Measurement;
+use embeddenator_contract_bench::VsaVariant;
+use std::collections::BTreeSet;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+fn snapshot_path() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/measurement_names.txt")
+}
+
+/// Distinct names, sorted (some benches emit one name per tag set).
+fn names(ms: Vec<Measurement>) -> Vec<String> {
+    let set: BTreeSet<String> = ms.into_iter().map(|m| m.name).collect();
+    set.into_iter().collect()
+}
+
+/// `(section, names)` for every subcommand and variant the snapshot covers.
+fn current_names() -> Vec<(String, Vec<String>)> {
+    let cfg = BenchConfig {
+        profile: Profile::Quick,
+        seed: 0,
+    };
+    let _c = calibration(1);
+    let dir = tempfile::tempdir().unwrap();
+    let mut out = Vec::new();
+
+    for (label, variant) in [
+        ("all", VsaVariant::All),
+        ("packed", VsaVariant::Packed),
+        ("bitsliced", VsaVariant::Bitsliced),
+        ("hybrid", VsaVariant::Hybrid),
+        ("block-sparse", VsaVariant::BlockSparse),
+    ] {
+        let ms = vsa::run(&cfg, variant, &Default::default());
+        let derived = ratios::derive(&ms);
+        out.push((format!("vsa --variant {label}"), names(ms)));
+        if variant == VsaVariant::All {
+            out.push((
+                "vsa --variant all: derived ratios".to_string(),
+                names(derived),
+            ));
+        }
+    }
+
+    let sweep = vsa::RunOptions {
+        density_sweep: true,
+        ..Default::default()
+    };
+    let ms = vsa::run(&cfg, VsaVariant::All, &sweep);
+    let swept = ms.into_iter().filter(|m| m.tags.contains_key("density"));
+    out.push((
+        "vsa --density-sweep: swept".to_string(),
+        names(swept.collect()),
+    ));
+
+    let cold = vsa::RunOptions {
+        cold_start: true,
+        ..Default::default()
+    };
+    let ms = vsa::run(&cfg, VsaVariant::All, &cold);
+    let first = ms.into_iter().take_while(|m| m.is_informational());
+    out.push(("vsa --cold-start: cold".to_string(), names(first.collect())));
+
+    let dataset = dir.path().join("names.embr");
+    let config = GenerateConfig {
+        count: 32,
+        dimension: DIM,
+        sparsity: DIM / 100,
+        ..Default::default()
+    };
+    write_dataset_streaming(&dataset, &config, 16).unwrap();
+    let source = DatasetSource::File(dataset.clone());
+    let ms = vsa::run_dataset(&cfg, VsaVariant::All, &source, &Default::default()).unwrap();
+    out.push(("vsa --dataset".to_string(), names(ms)));
+    let roundtrip = vsa::DatasetRunOptions {
+        roundtrip_check: Some(4),
+        ..Default::default()
+    };
+    let ms = vsa::run_dataset(&cfg, VsaVariant::All, &source, &roundtrip).unwrap();
+    let contract = ms
+        .into_iter()
+        .filter(|m| m.name.starts_with("vsa.contract."));
+    out.push((
+        "vsa --dataset --roundtrip-check: contract".to_string(),
+        names(contract.collect()),
+    ));
+    let ms = benches::dataset_io::run(&cfg, &dataset, None).unwrap();
+    out.push(("dataset-bench".to_string(), names(ms)));
+    let ms = benches::dataset_io::generation_measurements(
+        &config,
+        config.count,
+        std::fs::metadata(&dataset).unwrap().len(),
+        std::time::Duration::from_secs(1),
+    );
+    out.push(("generate-dataset --report".to_string(), names(ms)));
+    let formats = benches::dataset_formats::FormatsArgs {
+        config: GenerateConfig {
+            count: 16,
+            ..Default::default()
+        },
+        formats: Vec::new(),
+        keep: None,
+    };
+    let ms = benches::dataset_formats::run(&cfg, &formats).unwrap();
+    out.push(("dataset-bench-formats".to_string(), names(ms)));
+
+    let input = dir.path().join("input.bin");
+    std::fs::write(&input, vec![7u8; 8 * 1024]).unwrap();
+    let encode = EncodeArgs {
+        inputs: vec![input],
+        prefix: None,
+        codec: benches::encode::parse_codec("none").unwrap(),
+        codec_level: None,
+        verify: true,
+        parallel_hash: false,
+        codec_sweep: vec![benches::encode::CodecSpec::parse("none").unwrap()],
+        walk: Default::default(),
+        chunk_size: None,
+        vsa_config: Default::default(),
+        synthetic: None,
+    };
+    let ms = benches::encode::run(&cfg, &encode).unwrap();
+    out.push(("encode --verify --codec-sweep none".to_string(), names(ms)));
+
+    let corpus = dir.path().join("corpus");
+    std::fs::create_dir(&corpus).unwrap();
+    for i in 0..4u8 {
+        let body: Vec<u8> = (0..4096u32)
+            .map(|j| (j * 31 + u32::from(i) * 7) as u8)
+            .collect();
+        std::fs::write(corpus.join(format!("doc{i}.bin")), body).unwrap();
+    }
+    let retrieval = RetrievalArgs {
+        input_dir: corpus,
+        k: 3,
+        candidate_factor: 10,
+        queries: Some(4),
+        frontier: false,
+        holdout: false,
+        concurrency: vec![2],
+        ground_truth_sample: None,
+        ground_truth_timeout: None,
+        walk: Default::default(),
+        chunk_size: None,
+        query_file: None,
+        vsa_config: Default::default(),
+    };
+    let ms = benches::retrieval::run(&cfg, &retrieval).unwrap();
+    out.push(("retrieval --concurrency 2".to_string(), names(ms)));
+    let frontier = RetrievalArgs {
+        frontier: true,
+        concurrency: Vec::new(),
+        ..retrieval
+    };
+    let ms = benches::retrieval::run(&cfg, &frontier).unwrap();
+    out.push(("retrieval --frontier".to_string(), names(ms)));
+
+    out.push(("index".to_string(), names(benches::index::run(&cfg))));
+
+    let duel = DuelArgs {
+        a: DuelSide::Substrate(DuelSubstrate::Packed),
+        b: DuelSide::Substrate(DuelSubstrate::Sparsevec),
+        substrate: DuelSubstrate::Packed,
+        blocks: Some(2),
+        max_ops: None,
+    };
+    let ms = benches::duel::run(&cfg, &duel).unwrap();
+    out.push(("duel packed sparsevec".to_string(), names(ms)));
+
+    let mut soaks = Vec::new();
+    for op in SoakOp::value_variants() {
+        let soak = SoakArgs {
+            op: *op,
+            duration: Duration::from_millis(20),
+            snapshot_interval: Duration::from_millis(10),
+            drift_threshold: 0.1,
+            dataset: None,
+        };
+        soaks.push(benches::soak::run(&cfg, &soak).unwrap());
+    }
+    out.push(("soak".to_string(), names(soaks)));
+    out
+}
+
+/// The section blocks, each starting with a blank line.
+fn sections_text(sections: &[(String, Vec<String>)]) -> String {
+    let mut text = String::new();
+    for (section, names) in sections {
+        write!(text, "\n[{section}]\n").unwrap();
+        for name in names {
+            writeln!(text, "{name}").unwrap();
+        }
+    }
+    text
+}
+
+fn render(version: u32, sections: &[(String, Vec<String>)]) -> String {
+    format!(
+        "# Measurement names per subcommand; see src/measurements.rs and tests/schema_contract.rs.\nnamespace_version = {version}\n{}",
+        sections_text(sections)
+    )
+}
+
+/// The snapshot minus its header lines (everything from the first section on).
+fn snapshot_body(snapshot: &str) -> &str {
+    snapshot.find("\n[").map_or("", |i| &snapshot[i..])
+}
+
+fn recorded_version(snapshot: &str) -> Option<u32> {
+    snapshot
+        .lines()
+        .find_map(|l| l.strip_prefix("namespace_version = "))
+        .and_then(|v| v.trim().parse().ok())
+}
+
+#[test]
+fn schema_contract_measurement_names() {
+    let path = snapshot_path();
+    let snapshot = std::fs::read_to_string(&path).unwrap_or_default();
+    let recorded = recorded_version(&snapshot);
+    let sections = current_names();
+    let unchanged = sections_text(&sections) == snapshot_body(&snapshot);
+
+    if std::env::var_os("UPDATE_MEASUREMENT_NAMES").is_some() {
+        assert!(
+            unchanged || recorded.is_none_or(|v| NAMESPACE_VERSION > v),
+            "measurement names changed: bump measurements::NAMESPACE_VERSION (recorded {recorded:?}) before regenerating"
+        );
+        std::fs::write(&path, render(NAMESPACE_VERSION, &sections)).unwrap();
+        return;
+    }
+
+    assert!(
+        unchanged,
+        "measurement names differ from {}: bump measurements::NAMESPACE_VERSION and rerun with UPDATE_MEASUREMENT_NAMES=1\n--- current ---\n{}",
+        path.display(),
+        render(NAMESPACE_VERSION, &sections)
+    );
+    assert_eq!(
+        recorded,
+        Some(NAMESPACE_VERSION),
+        "measurements::NAMESPACE_VERSION changed without regenerating {}",
+        path.display()
+    );
+}
@@ -0,0 +1,45 @@
+//! A shortened soak: the snapshot cadence and the shape of what it records.
+
+use embeddenator_contract_bench::benches::soak::{self, SoakArgs, SoakOp};
+use embeddenator_contract_bench::harness::{BenchConfig, Profile};
+use std::time::Duration;
+
+#[test]
+fn soak_snapshots_at_each_interval() {
+    let cfg = BenchConfig {
+        profile: Profile::Quick,
+        seed: 0,
+    };
+    let args = SoakArgs {
+        op: SoakOp::SparsevecBind,
+        duration: Duration::from_secs(2),
+        snapshot_interval: Duration::from_millis(500),
+        drift_threshold: 0.10,
+        dataset: None,
+    };
+    let m = soak::run(&cfg, &args).unwrap();
+    assert_eq!(m.name, "soak.sparsevec.bind");
+    assert!(m.iters > 0);
+    assert!(m.total_ns >= 2_000_000_000);
+
+    let snapshots = m.extra["snapshots"].as_array().unwrap();
+    // One per interval, plus a short one if the last interval was cut off by the end.
+    assert!((4..=5).contains(&snapshots.len()), "{snapshots:?}");
+    let t_ms: Vec<u64> = snapshots
+        .iter()
+        .map(|s| s["t_ms"].as_u64().unwrap())
+        .collect();
+    assert!(t_ms.windows(2).all(|w| w[0] < w[1]), "{t_ms:?}");
+    assert!(t_ms[0] >= 500 && *t_ms.last().unwrap() >= 2000, "{t_ms:?}");
+    for s in snapshots {
+        assert!(s["ops"].as_u64().unwrap() > 0);
+        assert!(s["ops_per_s"].as_f64().unwrap() > 0.0);
+        assert!(s["p50_ns"].as_u64().unwrap() <= s["p99_ns"].as_u64().unwrap());
+        assert_eq!(s["rss_bytes"].is_u64(), cfg!(target_os = "linux"));
+    }
+    let ops: u64 = snapshots.iter().map(|s| s["ops"].as_u64().unwrap()).sum();
+    assert_eq!(ops, m.iters);
+    assert_eq!(m.extra["drift"]["threshold"], 0.10);
+    assert!(m.extra["drift"]["ops_per_s"].is_f64());
+    assert!(m.extra["stopped"].is_null());
+}
@@ -0,0 +1,136 @@
+//! `run_suite` driven as a library, without the binary.
+
+use embeddenator_contract_bench::harness::calibration;
+use embeddenator_contract_bench::measurements::NAMESPACE_VERSION;
+use embeddenator_contract_bench::registry::{Bench, MeasurementSink, Registry};
+use embeddenator_contract_bench::schema::Measurement;
+use embeddenator_contract_bench::{run_suite, run_suite_with, BenchConfig, SuiteSpec, VsaVariant};
+use std::io;
+
+#[test]
+fn test_run_suite_returns_report() {
+    let _c = calibration(1);
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("input.bin");
+    std::fs::write(&input, vec![3u8; 4096]).unwrap();
+
+    // The spec is plain data: this is what a config file would hold.
+    let spec: SuiteSpec = serde_json::from_value(serde_json::json!({
+        "seed": 7,
+        "variant": "packed",
+        "inputs": [input],
+        "index": true,
+        "tags": {"caller": "test"},
+    }))
+    .unwrap();
+    assert_eq!(spec.variant, VsaVariant::Packed);
+    assert_eq!(spec.retrieval_k, 10);
+
+    let report = run_suite(&spec).unwrap();
+    assert_eq!((report.run.profile.as_str(), report.run.seed), ("quick", 7));
+    assert_eq!(
+        report.run.measurement_namespace_version,
+        Some(NAMESPACE_VERSION)
+    );
+    assert_eq!(report.run.tags["caller"], "test");
+    assert!(report.run.environment.is_some());
+
+    let has = |prefix: &str| {
+        report
+            .measurements
+            .iter()
+            .any(|m| m.name.starts_with(prefix))
+    };
+    assert!(has("vsa.packed."));
+    assert!(has("encode.ingest"));
+    assert!(has("index."));
+    assert!(!has("vsa.bitsliced.") && !has("retrieval."));
+}
+
+#[test]
+fn test_run_suite_keep_going_names_failed_sections() {
+    let _c = calibration(1);
+    let spec = SuiteSpec {
+        variant: VsaVariant::Packed,
+        retrieval_input_dir: Some("/nonexistent/corpus".into()),
+        ..Default::default()
+    };
+    assert!(run_suite(&spec).is_err());
+
+    let report = run_suite(&SuiteSpec {
+        keep_going: true,
+        ..spec
+    })
+    .unwrap();
+    assert_eq!(report.run.tags["failed_sections"], "retrieval");
+    assert!(!report.measurements.is_empty());
+}
+
+/// A downstream bench: one fixed measurement, or a failure.
+struct Downstream {
+    fail: bool,
+}
+
+impl Bench for Downstream {
+    fn name(&self) -> &str {
+        "downstream"
+    }
+
+    fn run(&self, cfg: &BenchConfig, sink: &mut dyn MeasurementSink) -> io::Result<()> {
+        if self.fail {
+            return Err(io::Error::other("downstream broke"));
+        }
+        sink.on_measurement(Measurement {
+            name: "downstream.op".to_string(),
+            unit: "ns/iter".to_string(),
+            iters: 1,
+            warmup_iters: 0,
+            total_ns: 10,
+            ns_per_iter: 10.0,
+            bytes_processed: None,
+            throughput_bytes_per_s: None,
+            extra: serde_json::json!({"seed": cfg.seed}),
+            tags: Default::default(),
+        })
+    }
+}
+
+#[test]
+fn test_registered_bench_in_report() {
+    let _c = calibration(1);
+    let mut spec = SuiteSpec {
+        seed: 3,
+        variant: VsaVariant::Packed,
+        ..Default::default()
+    };
+    let mut registry = Registry::suite(&spec).unwrap();
+    registry.register(Downstream { fail: false });
+
+    let report = run_suite_with(&spec, &registry).unwrap();
+    assert!(report
+        .measurements
+        .iter()
+        .any(|m| m.name.starts_with("vsa.packed.")));
+    let downstream = report
+        .measurements
+        .iter()
+        .find(|m| m.name == "downstream.op")
+        .unwrap();
+    assert_eq!(downstream.extra["seed"], 3);
+
+    // Selection and keep-going treat it like a built-in section.
+    spec.sections = vec!["downstream".to_string()];
+    let report = run_suite_with(&spec, &registry).unwrap();
+    assert_eq!(report.measurements.len(), 1);
+
+    registry.register(Downstream { fail: true });
+    assert!(run_suite_with(&spec, &registry).is_err());
+    spec.sections.clear();
+    spec.keep_going = true;
+    let report = run_suite_with(&spec, &registry).unwrap();
+    assert_eq!(report.run.tags["failed_sections"], "downstream");
+    assert!(report
+        .measurements
+        .iter()
+        .all(|m| m.name != "downstream.op"));
+}
@@ -0,0 +1,96 @@
+//! Windows path handling: datasets and reports under paths past `MAX_PATH` and with
+//! spaces, and logical paths that keep `/` whatever the native separator. The
+//! platform-specific fallbacks (no `posix_fadvise`, `statvfs` or SIGINT) are documented
+//! where they are defined; this checks what a Windows user sees of them.
+#![cfg(windows)]
+
+use embeddenator_contract_bench::atomic_write::write_atomic;
+use embeddenator_contract_bench::benches::input_walk::{self, WalkOptions};
+use embeddenator_contract_bench::dataset::{
+    generate_dataset, sequential_hint_extra, write_dataset_streaming, DatasetReader,
+    GenerateConfig, MappedDataset,
+};
+use embeddenator_contract_bench::harness::{BenchConfig, Profile};
+use embeddenator_contract_bench::schema::{
+    load_report, report_file_name, ContractBenchReport, RunMeta,
+};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// A directory under `root` whose path is well past the 260-character `MAX_PATH`.
+fn long_dir(root: &Path) -> PathBuf {
+    let mut dir = root.join("with space");
+    while dir.as_os_str().len() < 300 {
+        dir.push("a-fairly-long-directory-name");
+    }
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn dataset_round_trip_under_long_path() {
+    let root = tempfile::tempdir().unwrap();
+    let path = long_dir(root.path()).join("long path.embr");
+    let config = GenerateConfig {
+        count: 40,
+        ..Default::default()
+    };
+    write_dataset_streaming(&path, &config, 8).unwrap();
+    let vectors = generate_dataset(&config).unwrap();
+
+    let mut reader = DatasetReader::open_validated(&path).unwrap();
+    assert_eq!(
+        sequential_hint_extra(reader.sequential_hint()),
+        "unsupported"
+    );
+    for v in &vectors {
+        let got = reader.next_vector().unwrap().unwrap();
+        assert_eq!((&got.pos, &got.neg), (&v.pos, &v.neg));
+    }
+    assert!(reader.next_vector().unwrap().is_none());
+    drop(reader);
+
+    // The mapping holds the file open until dropped; the directory is removed after.
+    let mapped = MappedDataset::open(&path).unwrap();
+    let back: Vec<_> = mapped.iter().map(|r| r.unwrap().to_sparsevec()).collect();
+    assert_eq!(back.len(), vectors.len());
+    assert!(back
+        .iter()
+        .zip(&vectors)
+        .all(|(a, b)| a.pos == b.pos && a.neg == b.neg));
+}
+
+#[test]
+fn report_written_and_replaced_under_long_path() {
+    let root = tempfile::tempdir().unwrap();
+    let dir = long_dir(root.path());
+    let name = report_file_name("encode", &["C:\\in".to_string()], "quick", 0, 1_700_000_000);
+    let path = dir.join(&name);
+    let cfg = BenchConfig {
+        profile: Profile::Quick,
+        seed: 0,
+    };
+    let mut report = ContractBenchReport {
+        run: RunMeta::new(&cfg, BTreeMap::new()),
+        measurements: Vec::new(),
+    };
+    write_atomic(&path, serde_json::to_vec(&report).unwrap()).unwrap();
+    // A second write renames over the first, which Windows only allows when asked to.
+    report.run.notes.push("again".to_string());
+    write_atomic(&path, serde_json::to_vec(&report).unwrap()).unwrap();
+    assert_eq!(load_report(&path).unwrap().run.notes, ["again"]);
+}
+
+#[test]
+fn logical_paths_use_forward_slashes() {
+    let root = tempfile::tempdir().unwrap();
+    let nested = root.path().join("sub dir").join("deeper");
+    std::fs::create_dir_all(&nested).unwrap();
+    std::fs::write(nested.join("f.bin"), b"x").unwrap();
+    let walked = input_walk::walk(root.path(), &WalkOptions::default()).unwrap();
+    assert_eq!(walked.files[0].rel, "sub dir/deeper/f.bin");
+    assert_eq!(
+        input_walk::logical_path("docs\\2024", &walked.files[0].rel),
+        "docs/2024/sub dir/deeper/f.bin"
+    );
+}